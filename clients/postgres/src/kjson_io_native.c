@@ -10,6 +10,7 @@
 #include "utils/builtins.h"
 #include "utils/memutils.h"
 #include "lib/stringinfo.h"
+#include "libpq/pqformat.h"
 
 /* Use only our internal definitions, not the C library */
 #define PG_KJSON_INTERNAL_ONLY
@@ -26,6 +27,10 @@ typedef struct
 #define PGKJSON_DATA(x)       ((x)->data)
 #define PGKJSON_DATA_SIZE(x)  (VARSIZE(x) - VARHDRSZ)
 
+/* Binary wire format versions (first byte of send/recv payloads) */
+#define KJSON_WIRE_TEXT       1   /* kJSON text */
+#define KJSON_WIRE_INTERNAL   2   /* internal binary representation */
+
 PG_MODULE_MAGIC;
 
 /*
@@ -142,30 +147,84 @@ kjson_out(PG_FUNCTION_ARGS)
 
 /*
  * kjson_recv - Binary input function
+ *
+ * The binary wire format mirrors jsonb: a version byte followed by the
+ * payload. Version 1 carries kJSON text; version 2 carries the internal
+ * binary representation, which is validated and stored without going
+ * through text.
  */
 PG_FUNCTION_INFO_V1(kjson_recv);
 Datum
 kjson_recv(PG_FUNCTION_ARGS)
 {
-    /* For now, just error out - not implemented */
-    ereport(ERROR,
-            (errcode(ERRCODE_FEATURE_NOT_SUPPORTED),
-             errmsg("kjson_recv not implemented")));
+    StringInfo    buf = (StringInfo) PG_GETARG_POINTER(0);
+    int           version = pq_getmsgint(buf, 1);
+    char         *str;
+    int           nbytes;
+    const char   *data;
+    kjson_value  *value;
+    PGKJson      *result;
+    StringInfo    binary;
+    MemoryContext oldcontext;
+    MemoryContext decodecontext;
+    
+    if (version == KJSON_WIRE_TEXT)
+    {
+        str = pq_getmsgtext(buf, buf->len - buf->cursor, &nbytes);
+        return DirectFunctionCall1(kjson_in, CStringGetDatum(str));
+    }
     
-    PG_RETURN_NULL();
+    if (version != KJSON_WIRE_INTERNAL)
+        ereport(ERROR,
+                (errcode(ERRCODE_INVALID_BINARY_REPRESENTATION),
+                 errmsg("unsupported kjson binary format version %d", version)));
+    
+    nbytes = buf->len - buf->cursor;
+    data = pq_getmsgbytes(buf, nbytes);
+    
+    decodecontext = AllocSetContextCreate(CurrentMemoryContext,
+                                          "kJSON recv context",
+                                          ALLOCSET_DEFAULT_SIZES);
+    oldcontext = MemoryContextSwitchTo(decodecontext);
+    
+    /* Decode and re-encode so only well-formed data is stored */
+    value = pg_kjson_decode_binary((const uint8_t *) data, nbytes, decodecontext);
+    if (value == NULL)
+    {
+        MemoryContextSwitchTo(oldcontext);
+        MemoryContextDelete(decodecontext);
+        ereport(ERROR,
+                (errcode(ERRCODE_INVALID_BINARY_REPRESENTATION),
+                 errmsg("corrupt kjson binary data")));
+    }
+    binary = pg_kjson_encode_binary(value);
+    
+    MemoryContextSwitchTo(oldcontext);
+    
+    result = (PGKJson *) palloc(VARHDRSZ + binary->len);
+    SET_VARSIZE(result, VARHDRSZ + binary->len);
+    memcpy(PGKJSON_DATA(result), binary->data, binary->len);
+    
+    MemoryContextDelete(decodecontext);
+    
+    PG_RETURN_POINTER(result);
 }
 
 /*
  * kjson_send - Binary output function
+ *
+ * Sends a version 2 payload (the stored representation), see kjson_recv
  */
 PG_FUNCTION_INFO_V1(kjson_send);
 Datum
 kjson_send(PG_FUNCTION_ARGS)
 {
-    /* For now, just error out - not implemented */
-    ereport(ERROR,
-            (errcode(ERRCODE_FEATURE_NOT_SUPPORTED),
-             errmsg("kjson_send not implemented")));
+    PGKJson        *pgkj = (PGKJson *) PG_DETOAST_DATUM(PG_GETARG_DATUM(0));
+    StringInfoData  buf;
+    
+    pq_begintypsend(&buf);
+    pq_sendint8(&buf, KJSON_WIRE_INTERNAL);
+    pq_sendbytes(&buf, PGKJSON_DATA(pgkj), PGKJSON_DATA_SIZE(pgkj));
     
-    PG_RETURN_NULL();
+    PG_RETURN_BYTEA_P(pq_endtypsend(&buf));
 }
\ No newline at end of file
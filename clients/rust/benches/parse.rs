@@ -0,0 +1,45 @@
+//! Parse throughput across input sizes.
+//!
+//! Throughput is reported in bytes, so linear scaling shows as a flat rate
+//! from the smallest document to the largest.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// An array of records of at least `size` bytes, mixing plain JSON with
+/// extended literals, escapes, comments and non-ASCII text
+fn document(size: usize) -> String {
+    let mut doc = String::from("[\n");
+    let mut i = 0u64;
+    while doc.len() < size {
+        doc.push_str(&format!(
+            "  {{ id: {i}n, uuid: 550e8400-e29b-41d4-a716-{i:012}, price: {i}.99m, \
+             created: 2025-01-10T12:00:00.{ms:03}Z, ttl: PT{i}S, ratio: {i}.25e-3, \
+             name: 'item \\'{i}\\' — größe 日本', tags: [\"a\", \"b\\n\"], \
+             active: true, note: null }}, // record {i}\n",
+            ms = i % 1000,
+        ));
+        i += 1;
+    }
+    doc.push(']');
+    doc
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for size in [64 << 10, 1 << 20, 8 << 20] {
+        let doc = document(size);
+        group.throughput(Throughput::Bytes(doc.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &doc, |b, doc| {
+            b.iter(|| kjson::parse(black_box(doc)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = parse
+}
+criterion_main!(benches);
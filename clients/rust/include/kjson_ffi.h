@@ -0,0 +1,92 @@
+/*
+ * C interface to the Rust kJSON parser (the `ffi` feature)
+ *
+ * Ownership:
+ * - A kjson_value * from kjson_parse is owned by the caller; free it with
+ *   kjson_value_free.
+ * - const pointers from the accessors (array items, object values, keys,
+ *   string and binary contents) borrow from the value they were read from
+ *   and stay valid until the owning value is freed.
+ * - char * strings from the library are NUL-terminated and owned by the
+ *   caller; free them with kjson_string_free.
+ *
+ * Strings passed in are UTF-8 with an explicit length. Every function
+ * accepts NULL, returning NULL, 0 or false.
+ */
+
+#ifndef KJSON_FFI_H
+#define KJSON_FFI_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct kjson_value kjson_value;
+
+typedef enum {
+    KJSON_TYPE_NULL = 0,
+    KJSON_TYPE_BOOL = 1,
+    KJSON_TYPE_NUMBER = 2,
+    KJSON_TYPE_INTEGER = 3,
+    KJSON_TYPE_STRING = 4,
+    KJSON_TYPE_ARRAY = 5,
+    KJSON_TYPE_OBJECT = 6,
+    KJSON_TYPE_BIGINT = 7,
+    KJSON_TYPE_DECIMAL128 = 8,
+    KJSON_TYPE_UUID = 9,
+    KJSON_TYPE_DATE = 10,
+    KJSON_TYPE_INSTANT = 11,
+    KJSON_TYPE_DURATION = 12,
+    KJSON_TYPE_BINARY = 13,
+    KJSON_TYPE_UINTEGER = 14
+} kjson_type;
+
+/* Parse len bytes of kJSON text. On failure returns NULL and, if error is
+ * not NULL, stores a message to free with kjson_string_free. */
+kjson_value *kjson_parse(const char *input, size_t len, char **error);
+
+/* Serialize as kJSON text, compact or pretty-printed */
+char *kjson_stringify(const kjson_value *value, bool pretty);
+
+void kjson_value_free(kjson_value *value);
+void kjson_string_free(char *s);
+
+kjson_type kjson_value_type(const kjson_value *value);
+
+/* Scalar accessors return whether the value has the requested type */
+bool kjson_value_get_bool(const kjson_value *value, bool *out);
+bool kjson_value_get_i64(const kjson_value *value, int64_t *out);
+bool kjson_value_get_u64(const kjson_value *value, uint64_t *out);
+bool kjson_value_get_f64(const kjson_value *value, double *out);
+bool kjson_value_get_uuid(const kjson_value *value, uint8_t out[16]);
+/* Instant or Date, in nanoseconds since the Unix epoch */
+bool kjson_value_get_timestamp(const kjson_value *value, int64_t *out);
+/* Duration in nanoseconds */
+bool kjson_value_get_duration(const kjson_value *value, int64_t *out);
+
+/* Borrowed contents, not NUL-terminated */
+const char *kjson_value_get_string(const kjson_value *value, size_t *len);
+const uint8_t *kjson_value_get_binary(const kjson_value *value, size_t *len);
+
+/* Owned decimal strings, such as "-123" and "19.990" */
+char *kjson_value_get_bigint(const kjson_value *value);
+char *kjson_value_get_decimal(const kjson_value *value);
+
+/* Items in an array or entries in an object */
+size_t kjson_value_len(const kjson_value *value);
+const kjson_value *kjson_value_array_get(const kjson_value *value, size_t index);
+const kjson_value *kjson_value_object_get(const kjson_value *value, const char *key,
+                                          size_t key_len);
+/* Entry at index, for iteration; the key is borrowed and not NUL-terminated */
+const kjson_value *kjson_value_object_entry(const kjson_value *value, size_t index,
+                                            const char **key, size_t *key_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* KJSON_FFI_H */
@@ -0,0 +1,621 @@
+//! Derive macros for `kjson::KjsonSerialize` and `kjson::KjsonDeserialize`
+//!
+//! Use them through the `derive` feature of the `kjson` crate, which
+//! re-exports both; the expansion refers to `::kjson`. The attributes are
+//! documented in `kjson::derive`.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Fields, GenericArgument,
+    Generics, Ident, LitStr, Path, PathArguments, Type,
+};
+
+/// Implement `kjson::KjsonSerialize`
+#[proc_macro_derive(KjsonSerialize, attributes(kjson))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, serialize)
+}
+
+/// Implement `kjson::KjsonDeserialize`
+#[proc_macro_derive(KjsonDeserialize, attributes(kjson))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, deserialize)
+}
+
+fn expand(
+    input: &DeriveInput,
+    generate: fn(&DeriveInput, &Container) -> syn::Result<TokenStream2>,
+) -> TokenStream {
+    Container::parse(&input.attrs)
+        .and_then(|container| generate(input, &container))
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// `#[kjson(...)]` on the struct or enum
+#[derive(Default)]
+struct Container {
+    rename_all: Option<RenameRule>,
+    deny_unknown_fields: bool,
+}
+
+impl Container {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut container = Container::default();
+        for attr in kjson_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    let rule: LitStr = meta.value()?.parse()?;
+                    container.rename_all = Some(RenameRule::parse(&rule)?);
+                } else if meta.path.is_ident("deny_unknown_fields") {
+                    container.deny_unknown_fields = true;
+                } else {
+                    return Err(meta.error("unknown kjson container attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(container)
+    }
+}
+
+/// `#[kjson(...)]` on a field or variant
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    skip: bool,
+    default: Option<Option<Path>>,
+    convert: Option<Conversion>,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut field = FieldAttrs::default();
+        for attr in kjson_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    field.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    field.skip = true;
+                } else if meta.path.is_ident("default") {
+                    field.default = Some(if meta.input.peek(syn::Token![=]) {
+                        Some(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        None
+                    });
+                } else if meta.path.is_ident("as") {
+                    let kind: LitStr = meta.value()?.parse()?;
+                    field.convert = Some(Conversion::parse(&kind)?);
+                } else {
+                    return Err(meta.error("unknown kjson field attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(field)
+    }
+
+    /// Attributes that only make sense for named fields
+    fn reject_named_only(&self, span: Span, what: &str) -> syn::Result<()> {
+        if self.rename.is_some() || self.skip || self.default.is_some() {
+            return Err(syn::Error::new(
+                span,
+                format!("rename, skip and default are not supported on {}", what),
+            ));
+        }
+        Ok(())
+    }
+
+    fn default_expr(&self) -> TokenStream2 {
+        match &self.default {
+            Some(Some(path)) => quote!(#path()),
+            _ => quote!(::core::default::Default::default()),
+        }
+    }
+}
+
+fn kjson_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("kjson"))
+}
+
+/// The extended literal a field is written as, from `#[kjson(as = "...")]`
+#[derive(Clone, Copy)]
+enum Conversion {
+    BigInt,
+    Decimal,
+    Instant,
+    Duration,
+}
+
+impl Conversion {
+    fn parse(kind: &LitStr) -> syn::Result<Self> {
+        Ok(match kind.value().as_str() {
+            "bigint" => Conversion::BigInt,
+            "decimal" => Conversion::Decimal,
+            "instant" => Conversion::Instant,
+            "duration" => Conversion::Duration,
+            _ => {
+                return Err(syn::Error::new(
+                    kind.span(),
+                    "expected \"bigint\", \"decimal\", \"instant\" or \"duration\"",
+                ))
+            }
+        })
+    }
+
+    /// The `Value` variant, trait, its methods and the `__private` reader
+    fn parts(self) -> (Ident, Ident, Ident, Ident, Ident) {
+        let (variant, name, to, from, read) = match self {
+            Conversion::BigInt => ("BigInt", "AsBigInt", "to_bigint", "from_bigint", "bigint_of"),
+            Conversion::Decimal => {
+                ("Decimal128", "AsDecimal", "to_decimal", "from_decimal", "decimal_of")
+            }
+            Conversion::Instant => {
+                ("Instant", "AsInstant", "to_instant", "from_instant", "instant_of")
+            }
+            Conversion::Duration => {
+                ("Duration", "AsDuration", "to_duration", "from_duration", "duration_of")
+            }
+        };
+        let ident = |s: &str| Ident::new(s, Span::call_site());
+        (ident(variant), ident(name), ident(to), ident(from), ident(read))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> syn::Result<Self> {
+        Ok(match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            _ => return Err(syn::Error::new(rule.span(), "unknown rename_all rule")),
+        })
+    }
+
+    /// Rename a snake_case field
+    fn field(self, name: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => name.to_string(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => name.to_ascii_uppercase(),
+            RenameRule::Pascal | RenameRule::Camel => {
+                let mut out = String::new();
+                let mut upper = matches!(self, RenameRule::Pascal);
+                for c in name.chars() {
+                    if c == '_' {
+                        upper = true;
+                    } else if upper {
+                        out.push(c.to_ascii_uppercase());
+                        upper = false;
+                    } else {
+                        out.push(c);
+                    }
+                }
+                out
+            }
+            RenameRule::Kebab => name.replace('_', "-"),
+        }
+    }
+
+    /// Rename a PascalCase variant
+    fn variant(self, name: &str) -> String {
+        match self {
+            RenameRule::Lower => name.to_ascii_lowercase(),
+            RenameRule::Upper => name.to_ascii_uppercase(),
+            RenameRule::Pascal => name.to_string(),
+            RenameRule::Camel => name[..1].to_ascii_lowercase() + &name[1..],
+            RenameRule::Snake | RenameRule::ScreamingSnake | RenameRule::Kebab => {
+                let mut snake = String::new();
+                for (i, c) in name.char_indices() {
+                    if c.is_uppercase() && i > 0 {
+                        snake.push('_');
+                    }
+                    snake.push(c.to_ascii_lowercase());
+                }
+                RenameRule::field(self, &snake)
+            }
+        }
+    }
+}
+
+/// A named field that is read and written
+struct NamedField {
+    ident: Ident,
+    key: String,
+    attrs: FieldAttrs,
+}
+
+fn named_fields(fields: &syn::FieldsNamed, container: &Container) -> syn::Result<Vec<NamedField>> {
+    fields
+        .named
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().expect("named field");
+            let attrs = FieldAttrs::parse(&field.attrs)?;
+            let name = ident.to_string();
+            let name = name.trim_start_matches("r#");
+            let key = match (&attrs.rename, container.rename_all) {
+                (Some(rename), _) => rename.clone(),
+                (None, Some(rule)) => rule.field(name),
+                (None, None) => name.to_string(),
+            };
+            Ok(NamedField { ident, key, attrs })
+        })
+        .collect()
+}
+
+/// Whether the field is an `Option`, whose `as` conversion applies inside it
+fn check_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else { return false };
+    let Some(last) = path.path.segments.last() else { return false };
+    last.ident == "Option"
+        && matches!(
+            &last.arguments,
+            PathArguments::AngleBracketed(args)
+                if matches!(args.args.first(), Some(GenericArgument::Type(_)))
+        )
+}
+
+/// Code converting the place `value` (a reference) to a `Value`
+fn to_value(value: TokenStream2, ty: &Type, convert: Option<Conversion>) -> TokenStream2 {
+    let Some(convert) = convert else {
+        return quote!(::kjson::derive::KjsonSerialize::to_kjson(#value)?);
+    };
+    let (variant, name, to, _, _) = convert.parts();
+    let write = |v: TokenStream2| {
+        quote!(::kjson::Value::#variant(::kjson::derive::#name::#to(#v)?))
+    };
+    if check_option(ty) {
+        let inner = write(quote!(inner));
+        quote! {
+            match #value {
+                ::core::option::Option::Some(inner) => #inner,
+                ::core::option::Option::None => ::kjson::Value::Null,
+            }
+        }
+    } else {
+        write(value)
+    }
+}
+
+/// A closure converting a `&Value` to the type `ty`
+fn from_value(ty: &Type, convert: Option<Conversion>) -> TokenStream2 {
+    let Some(convert) = convert else {
+        return quote!(<#ty as ::kjson::derive::KjsonDeserialize>::from_kjson);
+    };
+    let (_, name, _, from, read) = convert.parts();
+    let read = quote!(::kjson::derive::#name::#from(&::kjson::derive::__private::#read(value)?));
+    if check_option(ty) {
+        quote! {
+            |value: &::kjson::Value| match value {
+                ::kjson::Value::Null => ::core::result::Result::Ok(::core::option::Option::None),
+                _ => #read.map(::core::option::Option::Some),
+            }
+        }
+    } else {
+        quote!(|value: &::kjson::Value| -> ::kjson::Result<#ty> { #read })
+    }
+}
+
+fn add_bounds(generics: &Generics, bound: Path) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn serialize(input: &DeriveInput, container: &Container) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let fields = named_fields(fields, container)?;
+                let fields = data_fields(&data.fields, &fields);
+                let entries = object_entries(&fields, |field| {
+                    let member = &field.0.ident;
+                    quote!(&self.#member)
+                });
+                quote!(::core::result::Result::Ok(#entries))
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field = &fields.unnamed[0];
+                let attrs = FieldAttrs::parse(&field.attrs)?;
+                attrs.reject_named_only(ident.span(), "newtype structs")?;
+                let value = to_value(quote!(&self.0), &field.ty, attrs.convert);
+                quote!(::core::result::Result::Ok(#value))
+            }
+            Fields::Unnamed(fields) => {
+                let mut items = Vec::new();
+                for (i, field) in fields.unnamed.iter().enumerate() {
+                    let attrs = FieldAttrs::parse(&field.attrs)?;
+                    attrs.reject_named_only(ident.span(), "tuple structs")?;
+                    let index = syn::Index::from(i);
+                    items.push(to_value(quote!(&self.#index), &field.ty, attrs.convert));
+                }
+                quote!(::core::result::Result::Ok(::kjson::Value::Array(::std::vec![#(#items),*])))
+            }
+            Fields::Unit => quote!(::core::result::Result::Ok(::kjson::Value::Null)),
+        },
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let name = &variant.ident;
+                let tag = variant_tag(variant, container)?;
+                let tagged = |content: TokenStream2| {
+                    quote! {
+                        ::kjson::derive::__private::to_object(::std::vec![(#tag, #content)])
+                    }
+                };
+                arms.push(match &variant.fields {
+                    Fields::Unit => quote!(#ident::#name => ::kjson::Value::string(#tag)),
+                    Fields::Unnamed(fields) => {
+                        let mut bindings = Vec::new();
+                        let mut items = Vec::new();
+                        for (i, field) in fields.unnamed.iter().enumerate() {
+                            let attrs = FieldAttrs::parse(&field.attrs)?;
+                            attrs.reject_named_only(name.span(), "tuple variants")?;
+                            let binding = format_ident!("__field{}", i);
+                            items.push(to_value(quote!(#binding), &field.ty, attrs.convert));
+                            bindings.push(binding);
+                        }
+                        let content = if items.len() == 1 {
+                            items.pop().expect("one item")
+                        } else {
+                            quote!(::kjson::Value::Array(::std::vec![#(#items),*]))
+                        };
+                        let value = tagged(content);
+                        quote!(#ident::#name(#(#bindings),*) => #value)
+                    }
+                    Fields::Named(fields) => {
+                        let named = named_fields(fields, &Container::default())?;
+                        let fields = data_fields(&variant.fields, &named);
+                        let members: Vec<_> = named.iter().map(|f| &f.ident).collect();
+                        let entries = object_entries(&fields, |field| {
+                            let member = &field.0.ident;
+                            quote!(#member)
+                        });
+                        let value = tagged(entries);
+                        quote!(#ident::#name { #(#members),* } => #value)
+                    }
+                });
+            }
+            quote!(::core::result::Result::Ok(match self { #(#arms,)* }))
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                ident.span(),
+                "KjsonSerialize cannot be derived for unions",
+            ))
+        }
+    };
+    let generics = add_bounds(&input.generics, parse_quote!(::kjson::derive::KjsonSerialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::kjson::derive::KjsonSerialize for #ident #ty_generics #where_clause {
+            fn to_kjson(&self) -> ::kjson::Result<::kjson::Value> {
+                #body
+            }
+        }
+    })
+}
+
+/// Named fields paired with their types
+fn data_fields<'a>(fields: &'a Fields, named: &'a [NamedField]) -> Vec<(&'a NamedField, &'a Type)> {
+    named.iter().zip(fields.iter().map(|f| &f.ty)).collect()
+}
+
+/// An object of the fields that are not skipped, reading each through `place`
+fn object_entries(
+    fields: &[(&NamedField, &Type)],
+    place: impl Fn(&(&NamedField, &Type)) -> TokenStream2,
+) -> TokenStream2 {
+    let entries = fields.iter().filter(|(field, _)| !field.attrs.skip).map(|field| {
+        let key = &field.0.key;
+        let value = to_value(place(field), field.1, field.0.attrs.convert);
+        quote!((#key, #value))
+    });
+    quote!(::kjson::derive::__private::to_object(::std::vec![#(#entries),*]))
+}
+
+fn variant_tag(variant: &syn::Variant, container: &Container) -> syn::Result<String> {
+    let attrs = FieldAttrs::parse(&variant.attrs)?;
+    if attrs.skip || attrs.default.is_some() || attrs.convert.is_some() {
+        return Err(syn::Error::new(
+            variant.ident.span(),
+            "only rename is supported on variants",
+        ));
+    }
+    let name = variant.ident.to_string();
+    Ok(match (attrs.rename, container.rename_all) {
+        (Some(rename), _) => rename,
+        (None, Some(rule)) => rule.variant(&name),
+        (None, None) => name,
+    })
+}
+
+/// Code building `path` with named fields from the object `map`
+fn read_named(
+    path: TokenStream2,
+    fields: &[(&NamedField, &Type)],
+    deny_unknown_fields: bool,
+) -> TokenStream2 {
+    let inits = fields.iter().map(|(field, ty)| {
+        let member = &field.ident;
+        let key = &field.key;
+        if field.attrs.skip {
+            let default = field.attrs.default_expr();
+            return quote!(#member: #default);
+        }
+        let convert = from_value(ty, field.attrs.convert);
+        if field.attrs.default.is_some() {
+            let default = field.attrs.default_expr();
+            quote!(#member: ::kjson::derive::__private::field_or(map, #key, #convert, || #default)?)
+        } else {
+            quote!(#member: ::kjson::derive::__private::field(map, #key, #convert)?)
+        }
+    });
+    let deny = deny_unknown_fields.then(|| {
+        let keys = fields.iter().filter(|(f, _)| !f.attrs.skip).map(|(f, _)| &f.key);
+        quote!(::kjson::derive::__private::deny_unknown(map, &[#(#keys),*])?;)
+    });
+    quote! {
+        #deny
+        ::core::result::Result::Ok(#path { #(#inits),* })
+    }
+}
+
+/// Code building `path` with unnamed fields from the array `items`
+fn read_unnamed(path: TokenStream2, fields: &syn::FieldsUnnamed, what: &str) -> syn::Result<TokenStream2> {
+    let mut elements = Vec::new();
+    for (i, field) in fields.unnamed.iter().enumerate() {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        attrs.reject_named_only(Span::call_site(), what)?;
+        let convert = from_value(&field.ty, attrs.convert);
+        elements.push(quote!(::kjson::derive::__private::element(items, #i, #convert)?));
+    }
+    Ok(quote!(::core::result::Result::Ok(#path(#(#elements),*))))
+}
+
+fn deserialize(input: &DeriveInput, container: &Container) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => {
+                let expected = format!("struct {}", ident);
+                let named = named_fields(fields, container)?;
+                let read = read_named(
+                    quote!(#ident),
+                    &data_fields(&data.fields, &named),
+                    container.deny_unknown_fields,
+                );
+                quote! {
+                    let map = ::kjson::derive::__private::object(value, #expected)?;
+                    #read
+                }
+            }
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let field = &fields.unnamed[0];
+                let attrs = FieldAttrs::parse(&field.attrs)?;
+                attrs.reject_named_only(ident.span(), "newtype structs")?;
+                let convert = from_value(&field.ty, attrs.convert);
+                quote!((#convert)(value).map(#ident))
+            }
+            Fields::Unnamed(fields) => {
+                let expected = format!("tuple struct {}", ident);
+                let len = fields.unnamed.len();
+                let read = read_unnamed(quote!(#ident), fields, "tuple structs")?;
+                quote! {
+                    let items = ::kjson::derive::__private::array(value, #expected, #len)?;
+                    #read
+                }
+            }
+            Fields::Unit => {
+                let expected = format!("unit struct {}", ident);
+                quote! {
+                    match value {
+                        ::kjson::Value::Null => ::core::result::Result::Ok(#ident),
+                        _ => ::core::result::Result::Err(
+                            ::kjson::derive::__private::expected(#expected, value),
+                        ),
+                    }
+                }
+            }
+        },
+        Data::Enum(data) => {
+            let expected = format!("enum {}", ident);
+            let mut tags = Vec::new();
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let name = &variant.ident;
+                let tag = variant_tag(variant, container)?;
+                let read = match &variant.fields {
+                    Fields::Unit => quote!(::core::result::Result::Ok(#ident::#name)),
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        let field = &fields.unnamed[0];
+                        let attrs = FieldAttrs::parse(&field.attrs)?;
+                        attrs.reject_named_only(name.span(), "tuple variants")?;
+                        let convert = from_value(&field.ty, attrs.convert);
+                        quote! {
+                            let value = ::kjson::derive::__private::content(#tag, content)?;
+                            (#convert)(value).map(#ident::#name)
+                        }
+                    }
+                    Fields::Unnamed(fields) => {
+                        let variant_expected = format!("tuple variant {}::{}", ident, name);
+                        let len = fields.unnamed.len();
+                        let read = read_unnamed(quote!(#ident::#name), fields, "tuple variants")?;
+                        quote! {
+                            let value = ::kjson::derive::__private::content(#tag, content)?;
+                            let items =
+                                ::kjson::derive::__private::array(value, #variant_expected, #len)?;
+                            #read
+                        }
+                    }
+                    Fields::Named(fields) => {
+                        let variant_expected = format!("struct variant {}::{}", ident, name);
+                        let named = named_fields(fields, &Container::default())?;
+                        let read = read_named(
+                            quote!(#ident::#name),
+                            &data_fields(&variant.fields, &named),
+                            container.deny_unknown_fields,
+                        );
+                        quote! {
+                            let value = ::kjson::derive::__private::content(#tag, content)?;
+                            let map = ::kjson::derive::__private::object(value, #variant_expected)?;
+                            #read
+                        }
+                    }
+                };
+                arms.push(quote! {
+                    #tag => (|| -> ::kjson::Result<Self> { #read })()
+                        .map_err(|e| ::kjson::derive::__private::at(e, #tag))
+                });
+                tags.push(tag);
+            }
+            quote! {
+                let (variant, content) = ::kjson::derive::__private::variant(value, #expected)?;
+                match variant {
+                    #(#arms,)*
+                    other => ::core::result::Result::Err(
+                        ::kjson::derive::__private::unknown_variant(other, &[#(#tags),*]),
+                    ),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                ident.span(),
+                "KjsonDeserialize cannot be derived for unions",
+            ))
+        }
+    };
+    let generics = add_bounds(&input.generics, parse_quote!(::kjson::derive::KjsonDeserialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::kjson::derive::KjsonDeserialize for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn from_kjson(value: &::kjson::Value) -> ::kjson::Result<Self> {
+                #body
+            }
+        }
+    })
+}
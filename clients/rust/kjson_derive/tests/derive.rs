@@ -0,0 +1,118 @@
+use kjson::{parse, to_canonical_string, KjsonDeserialize, KjsonSerialize, Value};
+use std::time::SystemTime;
+
+#[derive(Debug, PartialEq, KjsonSerialize, KjsonDeserialize)]
+#[kjson(rename_all = "camelCase")]
+struct Account {
+    #[kjson(as = "bigint")]
+    account_id: u64,
+    #[kjson(as = "decimal")]
+    balance: String,
+    #[kjson(as = "instant")]
+    opened_at: chrono::DateTime<chrono::Utc>,
+    #[kjson(as = "duration")]
+    grace: Option<std::time::Duration>,
+    #[kjson(rename = "label")]
+    name: Option<String>,
+    #[kjson(default)]
+    tags: Vec<String>,
+    #[kjson(skip)]
+    cached: bool,
+}
+
+#[derive(Debug, PartialEq, KjsonSerialize, KjsonDeserialize)]
+#[kjson(rename_all = "snake_case", deny_unknown_fields)]
+enum Event {
+    Opened,
+    Deposited(#[kjson(as = "decimal")] f64),
+    Moved(String, String),
+    Closed {
+        #[kjson(as = "instant")]
+        at: SystemTime,
+    },
+}
+
+#[derive(Debug, PartialEq, KjsonSerialize, KjsonDeserialize)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, KjsonSerialize, KjsonDeserialize)]
+struct AccountId(#[kjson(as = "bigint")] u128);
+
+#[test]
+fn test_struct_round_trip() {
+    let value = parse(
+        "{ accountId: 12345678901234567890n, balance: 10.50m, openedAt: 2025-01-10T12:00:00Z, \
+         grace: PT30S, cached: true }",
+    )
+    .unwrap();
+    let account = Account::from_kjson(&value).unwrap();
+    assert_eq!(account.account_id, 12345678901234567890);
+    assert_eq!(account.balance, "10.50");
+    assert_eq!(account.grace, Some(std::time::Duration::from_secs(30)));
+    assert_eq!((account.name.as_deref(), account.tags.len(), account.cached), (None, 0, false));
+
+    assert_eq!(
+        to_canonical_string(&account.to_kjson().unwrap()).unwrap(),
+        "{\"accountId\":12345678901234567890n,\"balance\":10.50m,\"grace\":PT30S,\
+         \"label\":null,\"openedAt\":2025-01-10T12:00:00Z,\"tags\":[]}"
+    );
+    assert!(matches!(account.to_kjson().unwrap().pointer("/openedAt"), Some(Value::Instant(_))));
+    assert_eq!(Account::from_kjson(&account.to_kjson().unwrap()).unwrap(), account);
+}
+
+#[test]
+fn test_enums_and_generics() {
+    let events = vec![
+        Event::Opened,
+        Event::Deposited(2.5),
+        Event::Moved("a".into(), "b".into()),
+        Event::Closed { at: SystemTime::UNIX_EPOCH },
+    ];
+    let value = events.to_kjson().unwrap();
+    assert_eq!(
+        to_canonical_string(&value).unwrap(),
+        "[\"opened\",{\"deposited\":2.5m},{\"moved\":[\"a\",\"b\"]},\
+         {\"closed\":{\"at\":1970-01-01T00:00:00Z}}]"
+    );
+    assert_eq!(Vec::<Event>::from_kjson(&value).unwrap(), events);
+
+    let page = Page { items: vec![AccountId(u128::MAX)], next: Some(2) };
+    let value = page.to_kjson().unwrap();
+    let expected = parse("{ items: [340282366920938463463374607431768211455n], next: 2 }");
+    assert_eq!(value, expected.unwrap());
+    assert_eq!(Page::<AccountId>::from_kjson(&value).unwrap(), page);
+}
+
+#[test]
+fn test_errors() {
+    let error = |text: &str| Account::from_kjson(&parse(text).unwrap()).unwrap_err().to_string();
+    let base = "accountId: 1n, balance: 1m, openedAt: 2025-01-10T12:00:00Z";
+    assert_eq!(
+        error(&format!("{{ {} }}", base.replace("1n", "'1'"))),
+        "expected a bigint at /accountId, found string"
+    );
+    assert_eq!(error("{ accountId: 1n }"), "missing field `balance`");
+    assert_eq!(
+        error(&format!("{{ {}, tags: [1] }}", base)),
+        "expected a string at /tags/0, found number"
+    );
+    assert_eq!(error("[]"), "expected struct Account, found array");
+
+    let error = |text: &str| Event::from_kjson(&parse(text).unwrap()).unwrap_err().to_string();
+    assert_eq!(
+        error("'reopened'"),
+        "unknown variant `reopened`, expected one of `opened`, `deposited`, `moved`, `closed`"
+    );
+    assert_eq!(
+        error("{ closed: { at: 2025-01-10T12:00:00Z, by: 'me' } }"),
+        "unknown field `by`, expected one of `at` at /closed"
+    );
+    assert_eq!(
+        error("{ moved: ['a'] }"),
+        "tuple variant Event::Moved has 1 elements, expected 2 at /moved"
+    );
+    assert_eq!(error("{ deposited: 'x' }"), "expected a decimal128 at /deposited, found string");
+}
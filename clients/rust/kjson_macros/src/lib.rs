@@ -0,0 +1,134 @@
+//! Compile-time checked kJSON literals
+//!
+//! [`kjson_lit!`] parses a kJSON document while the crate is being built
+//! and expands to code that constructs the [`kjson::Value`] directly, so a
+//! typo in an embedded fixture or default fails the build instead of a test
+//! run, and nothing is parsed at runtime:
+//!
+//! ```
+//! use kjson_macros::kjson_lit;
+//!
+//! let defaults = kjson_lit!("{ port: 8080, retries: 3n, timeout: PT30S, tags: ['a'] }");
+//! assert_eq!(defaults, kjson::parse("{ port: 8080, retries: 3n, timeout: PT30S, tags: ['a'] }")?);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! A syntax error is reported at the literal with its line and column:
+//!
+//! ```compile_fail
+//! let broken = kjson_macros::kjson_lit!("{ port: 80,, }");
+//! ```
+//!
+//! The expansion refers to `::kjson`, so the `kjson` crate must be a
+//! dependency of the crate using the macro.
+
+use kjson::{Error, SourceMap, Value};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::str::FromStr;
+use syn::{parse_macro_input, LitStr};
+
+/// Parse a kJSON string literal at compile time into a `kjson::Value`
+#[proc_macro]
+pub fn kjson_lit(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let source = literal.value();
+    match kjson::parse(&source) {
+        Ok(value) => construct(&value).into(),
+        Err(e) => {
+            let message = match e {
+                Error::ParseError { position, message } => {
+                    let location = SourceMap::new(&source).location(position);
+                    format!(
+                        "invalid kJSON at line {}, column {}: {}",
+                        location.line, location.column, message
+                    )
+                }
+                other => format!("invalid kJSON: {}", other),
+            };
+            syn::Error::new(literal.span(), message)
+                .to_compile_error()
+                .into()
+        }
+    }
+}
+
+/// Code that builds `value`
+fn construct(value: &Value) -> TokenStream2 {
+    match value {
+        Value::Null => quote!(::kjson::Value::Null),
+        Value::Bool(b) => quote!(::kjson::Value::Bool(#b)),
+        Value::Number(n) => {
+            // Through the bits, so the value is exactly the parsed one
+            let bits = n.to_bits();
+            quote!(::kjson::Value::Number(f64::from_bits(#bits)))
+        }
+        Value::Integer(n) => quote!(::kjson::Value::Integer(#n)),
+        Value::UInteger(n) => quote!(::kjson::Value::UInteger(#n)),
+        Value::String(s) => {
+            let s = s.as_str();
+            quote!(::kjson::Value::String(::kjson::Str::from(#s)))
+        }
+        Value::Array(items) => {
+            let items = items.iter().map(construct);
+            quote!(::kjson::Value::Array(::std::vec![#(#items),*]))
+        }
+        Value::Object(map) => {
+            // Sorted, so the expansion is the same on every build
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            let len = entries.len();
+            let inserts = entries.into_iter().map(|(key, value)| {
+                let key = key.as_str();
+                let value = construct(value);
+                quote!(map.insert(::kjson::Str::from(#key), #value);)
+            });
+            quote!({
+                let mut map = ::kjson::Map::with_capacity(#len);
+                #(#inserts)*
+                ::kjson::Value::Object(map)
+            })
+        }
+        Value::Uuid(uuid) => {
+            let bits = uuid.as_u128();
+            quote!(::kjson::Value::Uuid(::kjson::Uuid::from_u128(#bits)))
+        }
+        Value::Instant(instant) => {
+            let nanos = instant.nanoseconds;
+            quote!(::kjson::Value::Instant(::kjson::Instant::from_nanos(#nanos)))
+        }
+        Value::Duration(duration) => {
+            let nanos = duration.nanoseconds;
+            quote!(::kjson::Value::Duration(::kjson::Duration::from_nanos(#nanos)))
+        }
+        Value::Binary(bytes) => quote!(::kjson::Value::Binary(::std::vec![#(#bytes),*])),
+        Value::BigInt(n) => from_text(n, n.to_string(), quote!(BigInt), value),
+        Value::Decimal128(d) => from_text(d, d.to_string(), quote!(Decimal128), value),
+        Value::Date(d) => {
+            // `Display` drops fractional seconds of UTC dates
+            let text = match d.tz_offset {
+                Some(_) => d.to_string(),
+                None => d.utc.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string(),
+            };
+            from_text(d, text, quote!(Date), value)
+        }
+    }
+}
+
+/// Code that builds a value of type `ty` from `text`, checked here to parse
+/// back to the same value; failing that, the value is parsed as kJSON at
+/// runtime
+fn from_text<T>(inner: &T, text: String, ty: TokenStream2, value: &Value) -> TokenStream2
+where
+    T: FromStr + PartialEq,
+{
+    if T::from_str(&text).ok().as_ref() == Some(inner) {
+        return quote!(::kjson::Value::#ty(
+            <::kjson::#ty as ::std::str::FromStr>::from_str(#text)
+                .expect("checked by kjson_lit!")
+        ));
+    }
+    let text = kjson::to_string(value).expect("parsed values serialize");
+    quote!(::kjson::parse(#text).expect("checked by kjson_lit!"))
+}
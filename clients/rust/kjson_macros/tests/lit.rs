@@ -0,0 +1,44 @@
+use kjson::Value;
+use kjson_macros::kjson_lit;
+
+#[test]
+fn test_matches_runtime_parse() {
+    let value = kjson_lit!(
+        r#"{
+            // comments and trailing commas are fine
+            name: "shop",
+            price: 19.99m,
+            stock: 12345678901234567890n,
+            ratio: 0.1,
+            id: 550e8400-e29b-41d4-a716-446655440000,
+            opened: 2025-01-01T09:30:00.123456789Z,
+            listed: 2025-01-01T09:30:00+02:00,
+            ttl: PT1H30M,
+            key: b64'AQID',
+            tags: ['a', `b`, null, true, [], {}],
+        }"#
+    );
+    let expected = kjson::parse(
+        r#"{
+            name: "shop",
+            price: 19.99m,
+            stock: 12345678901234567890n,
+            ratio: 0.1,
+            id: 550e8400-e29b-41d4-a716-446655440000,
+            opened: 2025-01-01T09:30:00.123456789Z,
+            listed: 2025-01-01T09:30:00+02:00,
+            ttl: PT1H30M,
+            key: b64'AQID',
+            tags: ['a', 'b', null, true, [], {}],
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(value, expected);
+}
+
+#[test]
+fn test_scalars() {
+    assert_eq!(kjson_lit!("null"), Value::Null);
+    assert_eq!(kjson_lit!("-0.5"), Value::Number(-0.5));
+    assert_eq!(kjson_lit!("'it\\'s'"), Value::String("it's".into()));
+}
@@ -0,0 +1,476 @@
+//! Apache Arrow `RecordBatch` conversion
+//!
+//! An array of objects becomes one row per object and one column per key.
+//! Column types are inferred from the non-null values:
+//!
+//! | kJSON | Arrow |
+//! |-------|-------|
+//! | Boolean | `Boolean` |
+//! | Number | `Float64` |
+//! | Integer | `Int64`, or `Float64` mixed with other numbers |
+//! | String | `Utf8` |
+//! | Decimal128 | `Decimal128(38, s)`, or `Decimal256(76, s)` when wider |
+//! | BigInt | `Decimal256(76, 0)` |
+//! | UUID | `FixedSizeBinary(16)` with the `arrow.uuid` extension |
+//! | Instant, Date | `Timestamp(Nanosecond, "UTC")` |
+//! | Duration | `Duration(Nanosecond)` |
+//! | Binary | `Binary` |
+//!
+//! Columns that mix types, hold nested arrays or objects, or overflow the
+//! Arrow decimal types are stored as `Utf8` kJSON text and tagged with
+//! `kjson:type = "kjson"` field metadata so they are parsed back on the
+//! way in. Missing keys become nulls.
+
+use crate::columns::{column_kind, integer, split_rows, unscaled_decimals, Kind};
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Date32Type, Date64Type, Decimal128Type, Decimal256Type, DurationMicrosecondType,
+    DurationMillisecondType, DurationNanosecondType, DurationSecondType, Float32Type, Float64Type,
+    Int16Type, Int32Type, Int64Type, Int8Type, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    Array, ArrayRef, BinaryArray, BooleanArray, Decimal128Array, Decimal256Array, DurationNanosecondArray,
+    FixedSizeBinaryArray, Float64Array, Int64Array, NullArray, RecordBatch, RecordBatchOptions,
+    StringArray, TimestampNanosecondArray, UInt64Array,
+};
+use arrow_buffer::i256;
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Field metadata key marking columns stored as kJSON text
+pub const KJSON_TYPE_KEY: &str = "kjson:type";
+
+const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+const UUID_EXTENSION: &str = "arrow.uuid";
+
+/// Convert an array of objects to an Arrow `RecordBatch`
+pub fn to_record_batch(value: &Value) -> Result<RecordBatch> {
+    let (row_count, cells) = split_rows(value, "RecordBatch")?;
+    let mut fields = Vec::with_capacity(cells.len());
+    let mut columns = Vec::with_capacity(cells.len());
+    for (name, cells) in cells {
+        let (field, column) = build_column(name, &cells)?;
+        fields.push(field);
+        columns.push(column);
+    }
+
+    let options = RecordBatchOptions::new().with_row_count(Some(row_count));
+    RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)
+        .map_err(|e| Error::Conversion(e.to_string()))
+}
+
+fn build_column(name: &str, cells: &[&Value]) -> Result<(Field, ArrayRef)> {
+    let kind = match column_kind(cells) {
+        Some(kind) => kind,
+        None => {
+            return Ok((
+                Field::new(name, DataType::Null, true),
+                Arc::new(NullArray::new(cells.len())),
+            ))
+        }
+    };
+
+    let column: ArrayRef = match kind {
+        Kind::Bool => Arc::new(BooleanArray::from(
+            cells.iter().map(|v| v.as_bool()).collect::<Vec<_>>(),
+        )),
+        Kind::Number => Arc::new(Float64Array::from(
+            cells.iter().map(|v| v.as_f64()).collect::<Vec<_>>(),
+        )),
+        Kind::Integer => Arc::new(Int64Array::from(
+            cells.iter().map(|v| v.as_i64()).collect::<Vec<_>>(),
+        )),
+        Kind::UInteger => Arc::new(UInt64Array::from(
+            cells.iter().map(|v| v.as_u64()).collect::<Vec<_>>(),
+        )),
+        Kind::String => Arc::new(StringArray::from(
+            cells.iter().map(|v| v.as_str()).collect::<Vec<_>>(),
+        )),
+        Kind::Decimal => match decimal_column(cells) {
+            Some(column) => column,
+            None => return text_column(name, cells),
+        },
+        Kind::BigInt => {
+            let parsed: Option<Vec<Option<i256>>> = cells
+                .iter()
+                .map(|v| match v.as_bigint() {
+                    Some(b) => wide_integer(&b.to_string()).map(Some),
+                    None => Some(None),
+                })
+                .collect();
+            match parsed {
+                Some(values) => Arc::new(
+                    Decimal256Array::from(values)
+                        .with_precision_and_scale(76, 0)
+                        .map_err(|e| Error::Conversion(e.to_string()))?,
+                ),
+                None => return text_column(name, cells),
+            }
+        }
+        Kind::Uuid => {
+            let column = FixedSizeBinaryArray::try_from_sparse_iter_with_size(
+                cells.iter().map(|v| v.as_uuid().map(|u| u.as_bytes())),
+                16,
+            )
+            .map_err(|e| Error::Conversion(e.to_string()))?;
+            let metadata =
+                HashMap::from([(EXTENSION_NAME_KEY.to_string(), UUID_EXTENSION.to_string())]);
+            return Ok((
+                Field::new(name, DataType::FixedSizeBinary(16), true).with_metadata(metadata),
+                Arc::new(column),
+            ));
+        }
+        Kind::Timestamp => Arc::new(
+            TimestampNanosecondArray::from(
+                cells
+                    .iter()
+                    .map(|v| match v {
+                        Value::Instant(i) => Some(i.epoch_nanos()),
+                        Value::Date(d) => d.utc.timestamp_nanos_opt(),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .with_timezone("UTC"),
+        ),
+        Kind::Duration => Arc::new(DurationNanosecondArray::from(
+            cells
+                .iter()
+                .map(|v| v.as_duration().map(|d| d.total_nanos()))
+                .collect::<Vec<_>>(),
+        )),
+        Kind::Binary => Arc::new(BinaryArray::from(
+            cells.iter().map(|v| v.as_bytes()).collect::<Vec<_>>(),
+        )),
+        Kind::Nested => return text_column(name, cells),
+    };
+
+    Ok((Field::new(name, column.data_type().clone(), true), column))
+}
+
+/// Build a decimal column with a shared scale, or `None` if it overflows `Decimal256`
+fn decimal_column(cells: &[&Value]) -> Option<ArrayRef> {
+    let (scale, unscaled, digits) = unscaled_decimals(cells);
+
+    if digits <= 38 && scale <= 38 {
+        let values: Vec<Option<i128>> = unscaled
+            .iter()
+            .map(|s| s.as_ref().map(|s| s.parse().unwrap()))
+            .collect();
+        let column = Decimal128Array::from(values)
+            .with_precision_and_scale(38, scale as i8)
+            .ok()?;
+        Some(Arc::new(column))
+    } else if digits <= 76 && scale <= 76 {
+        let values: Vec<Option<i256>> = unscaled
+            .iter()
+            .map(|s| s.as_ref().map(|s| i256::from_string(s).unwrap()))
+            .collect();
+        let column = Decimal256Array::from(values)
+            .with_precision_and_scale(76, scale as i8)
+            .ok()?;
+        Some(Arc::new(column))
+    } else {
+        None
+    }
+}
+
+/// Parse an integer that fits within `Decimal256(76, 0)`
+fn wide_integer(digits: &str) -> Option<i256> {
+    if digits.trim_start_matches('-').len() > 76 {
+        return None;
+    }
+    i256::from_string(digits)
+}
+
+fn text_column(name: &str, cells: &[&Value]) -> Result<(Field, ArrayRef)> {
+    let texts = cells
+        .iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            v => to_string(v).map(Some),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let metadata = HashMap::from([(KJSON_TYPE_KEY.to_string(), "kjson".to_string())]);
+    Ok((
+        Field::new(name, DataType::Utf8, true).with_metadata(metadata),
+        Arc::new(StringArray::from(texts)),
+    ))
+}
+
+/// Convert an Arrow `RecordBatch` to an array of objects
+pub fn from_record_batch(batch: &RecordBatch) -> Result<Value> {
+    let mut rows = vec![Map::with_capacity(batch.num_columns()); batch.num_rows()];
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        let values = read_column(field, column)?;
+        for (row, value) in rows.iter_mut().zip(values) {
+            row.insert(Str::from(field.name().as_str()), value);
+        }
+    }
+    Ok(Value::Array(rows.into_iter().map(Value::Object).collect()))
+}
+
+fn read_column(field: &Field, column: &ArrayRef) -> Result<Vec<Value>> {
+    let len = column.len();
+    let cell = |i: usize, read: &dyn Fn(usize) -> Result<Value>| {
+        if column.is_null(i) {
+            Ok(Value::Null)
+        } else {
+            read(i)
+        }
+    };
+    let read_all = |read: &dyn Fn(usize) -> Result<Value>| {
+        (0..len).map(|i| cell(i, read)).collect::<Result<Vec<_>>>()
+    };
+
+    macro_rules! numbers {
+        ($ty:ty) => {{
+            let array = column.as_primitive::<$ty>();
+            read_all(&|i| Ok(integer(array.value(i) as i128)))
+        }};
+    }
+
+    match column.data_type() {
+        DataType::Null => Ok(vec![Value::Null; len]),
+        DataType::Boolean => {
+            let array = column.as_boolean();
+            read_all(&|i| Ok(Value::Bool(array.value(i))))
+        }
+        DataType::Int8 => numbers!(Int8Type),
+        DataType::Int16 => numbers!(Int16Type),
+        DataType::Int32 => numbers!(Int32Type),
+        DataType::Int64 => numbers!(Int64Type),
+        DataType::UInt8 => numbers!(UInt8Type),
+        DataType::UInt16 => numbers!(UInt16Type),
+        DataType::UInt32 => numbers!(UInt32Type),
+        DataType::UInt64 => numbers!(UInt64Type),
+        DataType::Float32 => {
+            let array = column.as_primitive::<Float32Type>();
+            read_all(&|i| Ok(Value::Number(array.value(i) as f64)))
+        }
+        DataType::Float64 => {
+            let array = column.as_primitive::<Float64Type>();
+            read_all(&|i| Ok(Value::Number(array.value(i))))
+        }
+        DataType::Utf8 => {
+            let array = column.as_string::<i32>();
+            if field.metadata().get(KJSON_TYPE_KEY).map(String::as_str) == Some("kjson") {
+                read_all(&|i| parse(array.value(i)))
+            } else {
+                read_all(&|i| Ok(Value::string(array.value(i).to_string())))
+            }
+        }
+        DataType::LargeUtf8 => {
+            let array = column.as_string::<i64>();
+            read_all(&|i| Ok(Value::string(array.value(i).to_string())))
+        }
+        DataType::Decimal128(_, scale) => {
+            let array = column.as_primitive::<Decimal128Type>();
+            let scale = *scale as i32;
+            read_all(&|i| {
+                let unscaled = BigInt::from_i128(array.value(i));
+                Ok(Value::Decimal128(Decimal128::from_parts(&unscaled, -scale)))
+            })
+        }
+        DataType::Decimal256(_, scale) => {
+            let array = column.as_primitive::<Decimal256Type>();
+            let scale = *scale as i32;
+            read_all(&|i| {
+                let unscaled = BigInt::from_str(&array.value(i).to_string())?;
+                if scale == 0 {
+                    Ok(Value::BigInt(unscaled))
+                } else {
+                    Ok(Value::Decimal128(Decimal128::from_parts(&unscaled, -scale)))
+                }
+            })
+        }
+        DataType::Binary => {
+            let array = column.as_binary::<i32>();
+            read_all(&|i| Ok(Value::Binary(array.value(i).to_vec())))
+        }
+        DataType::LargeBinary => {
+            let array = column.as_binary::<i64>();
+            read_all(&|i| Ok(Value::Binary(array.value(i).to_vec())))
+        }
+        DataType::FixedSizeBinary(16) => {
+            let array = column.as_fixed_size_binary();
+            read_all(&|i| {
+                uuid::Uuid::from_slice(array.value(i))
+                    .map(Value::Uuid)
+                    .map_err(|e| Error::Conversion(e.to_string()))
+            })
+        }
+        DataType::Timestamp(unit, _) => {
+            let nanos: Box<dyn Fn(usize) -> i64> = match unit {
+                TimeUnit::Second => {
+                    let a = column.as_primitive::<TimestampSecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000_000_000))
+                }
+                TimeUnit::Millisecond => {
+                    let a = column.as_primitive::<TimestampMillisecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000_000))
+                }
+                TimeUnit::Microsecond => {
+                    let a = column.as_primitive::<TimestampMicrosecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000))
+                }
+                TimeUnit::Nanosecond => {
+                    let a = column.as_primitive::<TimestampNanosecondType>();
+                    Box::new(move |i| a.value(i))
+                }
+            };
+            read_all(&|i| Ok(Value::Instant(Instant::from_nanos(nanos(i)))))
+        }
+        DataType::Date32 => {
+            let array = column.as_primitive::<Date32Type>();
+            read_all(&|i| {
+                Ok(Value::Instant(Instant::from_seconds(
+                    array.value(i) as i64 * 86_400,
+                )))
+            })
+        }
+        DataType::Date64 => {
+            let array = column.as_primitive::<Date64Type>();
+            read_all(&|i| Ok(Value::Instant(Instant::from_millis(array.value(i)))))
+        }
+        DataType::Duration(unit) => {
+            let nanos: Box<dyn Fn(usize) -> i64> = match unit {
+                TimeUnit::Second => {
+                    let a = column.as_primitive::<DurationSecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000_000_000))
+                }
+                TimeUnit::Millisecond => {
+                    let a = column.as_primitive::<DurationMillisecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000_000))
+                }
+                TimeUnit::Microsecond => {
+                    let a = column.as_primitive::<DurationMicrosecondType>();
+                    Box::new(move |i| a.value(i).saturating_mul(1_000))
+                }
+                TimeUnit::Nanosecond => {
+                    let a = column.as_primitive::<DurationNanosecondType>();
+                    Box::new(move |i| a.value(i))
+                }
+            };
+            read_all(&|i| Ok(Value::Duration(Duration::from_nanos(nanos(i)))))
+        }
+        other => Err(Error::Conversion(format!(
+            "Arrow column {} has unsupported type {}",
+            field.name(),
+            other
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROWS: &str = r#"[
+        {
+            id: 550e8400-e29b-41d4-a716-446655440000,
+            name: "widget",
+            price: 19.99m,
+            stock: 12,
+            total: 123456789012345678901234567890n,
+            created: 2025-01-10T12:00:00.123456789Z,
+            ttl: PT1H30M,
+            active: true,
+            tags: ["a", "b"],
+            thumb: b64'iVBORw==',
+        },
+        {
+            id: 6ba7b810-9dad-11d1-80b4-00c04fd430c8,
+            name: "gadget",
+            price: 5.5m,
+            stock: 3.5,
+            total: -1n,
+            created: 2025-01-11T00:00:00Z,
+            ttl: PT0S,
+            active: false,
+            tags: null,
+            thumb: null,
+        },
+    ]"#;
+
+    #[test]
+    fn test_column_types() {
+        let batch = to_record_batch(&parse(ROWS).unwrap()).unwrap();
+        let schema = batch.schema();
+        let types: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type().clone()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                ("active", DataType::Boolean),
+                (
+                    "created",
+                    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+                ),
+                ("id", DataType::FixedSizeBinary(16)),
+                ("name", DataType::Utf8),
+                ("price", DataType::Decimal128(38, 2)),
+                ("stock", DataType::Float64),
+                ("tags", DataType::Utf8),
+                ("thumb", DataType::Binary),
+                ("total", DataType::Decimal256(76, 0)),
+                ("ttl", DataType::Duration(TimeUnit::Nanosecond)),
+            ]
+        );
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(
+            schema.field_with_name("tags").unwrap().metadata()[KJSON_TYPE_KEY],
+            "kjson"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let value = parse(ROWS).unwrap();
+        let batch = to_record_batch(&value).unwrap();
+        let decoded = from_record_batch(&batch).unwrap();
+
+        // Timestamps come back as instants, and 5.5m widens to the column
+        // scale, which is numerically equal
+        let mut expected = value.clone();
+        if let Value::Array(rows) = &mut expected {
+            for row in rows.iter_mut() {
+                if let Value::Object(row) = row {
+                    let utc = row["created"].as_date().unwrap().utc;
+                    let nanos = utc.timestamp_nanos_opt().unwrap();
+                    row.insert("created", Value::Instant(Instant::from_nanos(nanos)));
+                }
+            }
+            if let Value::Object(row) = &mut rows[1] {
+                row.insert("price", Value::Decimal128(Decimal128::from_str("5.50").unwrap()));
+            }
+        }
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_missing_keys_and_errors() {
+        let value = parse("[{ a: 1 }, { b: true }, {}]").unwrap();
+        let batch = to_record_batch(&value).unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.column(0).null_count(), 2);
+
+        let empty = to_record_batch(&parse("[{}, {}]").unwrap()).unwrap();
+        assert_eq!((empty.num_rows(), empty.num_columns()), (2, 0));
+
+        assert!(matches!(
+            to_record_batch(&parse("[1, 2]").unwrap()),
+            Err(Error::Conversion(_))
+        ));
+    }
+}
@@ -0,0 +1,400 @@
+//! Tokio async reading and writing
+//!
+//! Readers parse chunks from an `AsyncRead` as they arrive, holding only
+//! the value built so far and the token being read, so a service can parse
+//! a request body without collecting it first or moving the work to
+//! `spawn_blocking`. Parsing yields to the runtime every 64 KiB, so a large
+//! body that has already arrived doesn't stall the other tasks on its
+//! worker.
+//!
+//! ```no_run
+//! # use tokio::io::{AsyncRead, AsyncWrite};
+//! # async fn run(body: impl AsyncRead + Unpin, response: impl AsyncWrite + Unpin) -> kjson::Result<()> {
+//! #[derive(serde::Deserialize, serde::Serialize)]
+//! struct Order {
+//!     id: String,
+//! }
+//!
+//! let order: Order = kjson::async_io::from_async_reader(body).await?;
+//! kjson::async_io::to_async_writer(response, &order).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`KjsonLinesStream`] reads line-delimited kJSON (one value per line) as a
+//! `futures` `Stream`, for long-lived feeds.
+
+use crate::error::{Error, Result};
+use crate::incremental::Incremental;
+use crate::parser::parse;
+use crate::ser::to_writer;
+use crate::serializer::to_string;
+use crate::value::{from_value, Value};
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines,
+};
+
+/// Input parsed between yields to the runtime, so a body that is already
+/// buffered doesn't hold the worker thread for the whole parse
+const YIELD_BYTES: usize = 64 * 1024;
+
+/// Read a single kJSON document and deserialize it.
+///
+/// Only whitespace and comments may follow the value.
+pub async fn from_async_reader<R, T>(reader: R) -> Result<T>
+where
+    R: AsyncRead + Unpin,
+    T: for<'de> Deserialize<'de>,
+{
+    let mut reader = BufReader::new(reader);
+    let mut parser = Incremental::new();
+    let mut budget = Budget::new();
+    loop {
+        let chunk = reader.fill_buf().await?;
+        if chunk.is_empty() {
+            break;
+        }
+        if let Some(end) = parser.feed(chunk)? {
+            // Check what follows the value
+            parser.feed(&chunk[end..])?;
+        }
+        let n = chunk.len();
+        reader.consume(n);
+        budget.spend(n).await;
+    }
+    from_value(parser.finish()?.ok_or(Error::UnexpectedEof)?)
+}
+
+/// Read the next value, leaving any bytes after it in the reader.
+///
+/// Returns `None` once only whitespace and comments remain, so a stream of
+/// concatenated values can be read in a loop.
+pub async fn read_value<R>(reader: &mut R) -> Result<Option<Value>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut parser = Incremental::new();
+    let mut budget = Budget::new();
+    loop {
+        let chunk = reader.fill_buf().await?;
+        if chunk.is_empty() {
+            return parser.finish();
+        }
+        match parser.feed(chunk)? {
+            Some(end) => {
+                reader.consume(end);
+                return parser.finish();
+            }
+            None => {
+                let n = chunk.len();
+                reader.consume(n);
+                budget.spend(n).await;
+            }
+        }
+    }
+}
+
+/// Counts parsed bytes down to the next yield
+struct Budget {
+    left: usize,
+}
+
+impl Budget {
+    fn new() -> Self {
+        Budget { left: YIELD_BYTES }
+    }
+
+    /// Record `n` parsed bytes, yielding once the budget runs out
+    async fn spend(&mut self, n: usize) {
+        self.left = self.left.saturating_sub(n);
+        if self.left == 0 {
+            self.left = YIELD_BYTES;
+            YieldNow { yielded: false }.await;
+        }
+    }
+}
+
+/// Returns to the runtime once, waking itself to be polled again
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Serialize a value as kJSON and write it, flushing the writer
+///
+/// The output matches [`to_writer`](crate::to_writer), extended types
+/// included.
+pub async fn to_async_writer<W, T>(mut writer: W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize + ?Sized,
+{
+    let mut buf = Vec::new();
+    to_writer(&mut buf, value)?;
+    writer.write_all(&buf).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Write a value as kJSON, flushing the writer
+pub async fn write_value<W>(mut writer: W, value: &Value) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(to_string(value)?.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// A stream of values from line-delimited kJSON.
+///
+/// Blank lines are skipped. A line that fails to parse yields an error
+/// naming the line, and the stream carries on with the next line.
+#[derive(Debug)]
+pub struct KjsonLinesStream<R> {
+    lines: Lines<R>,
+    line: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> KjsonLinesStream<R> {
+    /// Stream values from a buffered reader
+    pub fn new(reader: R) -> Self {
+        KjsonLinesStream {
+            lines: reader.lines(),
+            line: 0,
+        }
+    }
+
+    /// Deserialize each value into `T`
+    pub fn deserialize<T>(self) -> TypedLinesStream<R, T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        TypedLinesStream {
+            inner: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consume the stream, returning the underlying reader
+    pub fn into_inner(self) -> R {
+        self.lines.into_inner()
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for KjsonLinesStream<R> {
+    type Item = Result<Value>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let line = match ready!(Pin::new(&mut self.lines).poll_next_line(cx)) {
+                Ok(Some(line)) => line,
+                Ok(None) => return Poll::Ready(None),
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            };
+            self.line += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let number = self.line;
+            return Poll::Ready(Some(parse(&line).map_err(|e| match e {
+                Error::ParseError { position, message } => Error::ParseError {
+                    position,
+                    message: format!("line {}: {}", number, message),
+                },
+                other => other,
+            })));
+        }
+    }
+}
+
+/// A [`KjsonLinesStream`] that deserializes each value into `T`
+#[derive(Debug)]
+pub struct TypedLinesStream<R, T> {
+    inner: KjsonLinesStream<R>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Stream for TypedLinesStream<R, T>
+where
+    R: AsyncBufRead + Unpin,
+    T: for<'de> Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|item| item.map(|value| value.and_then(from_value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        port: f64,
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip() {
+        let config = Config {
+            name: "server".to_string(),
+            port: 8080.0,
+        };
+        let mut buf = Vec::new();
+        to_async_writer(&mut buf, &config).await.unwrap();
+
+        let parsed: Config = from_async_reader(&buf[..]).await.unwrap();
+        assert_eq!(parsed, config);
+    }
+
+    #[tokio::test]
+    async fn test_writer_matches_sync() {
+        #[derive(Serialize)]
+        struct Event {
+            id: crate::Uuid,
+            at: crate::Date,
+            price: crate::Decimal128,
+        }
+
+        let event = Event {
+            id: "550e8400-e29b-41d4-a716-446655440000".parse().unwrap(),
+            at: crate::Date::from_iso8601("2025-01-10T12:00:00+02:00").unwrap(),
+            price: "19.990".parse().unwrap(),
+        };
+        let mut sync = Vec::new();
+        crate::to_writer(&mut sync, &event).unwrap();
+        let mut buf = Vec::new();
+        to_async_writer(&mut buf, &event).await.unwrap();
+        assert_eq!(buf, sync);
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            "{id: 550e8400-e29b-41d4-a716-446655440000, at: 2025-01-10T12:00:00+02:00, \
+             price: 19.990m}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reads_in_chunks() {
+        let (mut client, server) = tokio::io::duplex(4);
+        let writer = tokio::spawn(async move {
+            client
+                .write_all(b"{ name: 'a \\' }', port: 1 } // done\n")
+                .await
+                .unwrap();
+        });
+        let parsed: Config = from_async_reader(server).await.unwrap();
+        assert_eq!(parsed.name, "a ' }");
+        writer.await.unwrap();
+
+        let trailing: Result<Config> = from_async_reader(&b"{ name: 'a', port: 1 } 2"[..]).await;
+        assert!(matches!(
+            trailing,
+            Err(Error::ParseError { position: 23, .. })
+        ));
+        let truncated: Result<Config> = from_async_reader(&b"{ name: 'a'"[..]).await;
+        assert!(matches!(truncated, Err(Error::UnexpectedEof)));
+    }
+
+    #[tokio::test]
+    async fn test_yields_on_large_input() {
+        let items = vec!["item"; YIELD_BYTES / 4];
+        let mut input = Vec::new();
+        to_async_writer(&mut input, &items).await.unwrap();
+
+        // Already in memory, so only the parser's own yields can return
+        // Pending
+        let mut read = std::pin::pin!(from_async_reader::<_, Vec<String>>(&input[..]));
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+        let mut pending = 0;
+        let parsed = loop {
+            match read.as_mut().poll(&mut cx) {
+                Poll::Ready(result) => break result.unwrap(),
+                Poll::Pending => pending += 1,
+            }
+        };
+        assert_eq!(parsed, items);
+        assert!(pending >= input.len() / YIELD_BYTES);
+    }
+
+    #[tokio::test]
+    async fn test_read_value_sequence() {
+        let mut reader = BufReader::new(&b"[1] {a: 2}\n3.5m 'x'"[..]);
+        let mut values = Vec::new();
+        while let Some(value) = read_value(&mut reader).await.unwrap() {
+            values.push(crate::serializer::to_string(&value).unwrap());
+        }
+        assert_eq!(values, ["[1]", "{a: 2}", "3.5m", "'x'"]);
+
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "");
+    }
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_lines_stream() {
+        let input = "{ name: 'a', port: 1 }\r\n\n[1, 2n]\n{ broken: }\n{ name: 'b', port: 2 }";
+        let mut stream = KjsonLinesStream::new(input.as_bytes());
+
+        assert!(next(&mut stream)
+            .await
+            .unwrap()
+            .unwrap()
+            .as_object()
+            .is_some());
+        assert_eq!(
+            next(&mut stream)
+                .await
+                .unwrap()
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        match next(&mut stream).await.unwrap() {
+            Err(Error::ParseError { message, .. }) => assert!(message.starts_with("line 4: ")),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(next(&mut stream).await.unwrap().is_ok());
+        assert!(next(&mut stream).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_typed_lines_stream() {
+        let input = "{ name: 'a', port: 1 }\n{ name: 'b', port: 2 }\n";
+        let mut stream = KjsonLinesStream::new(input.as_bytes()).deserialize::<Config>();
+        let mut names = Vec::new();
+        while let Some(config) = next(&mut stream).await {
+            names.push(config.unwrap().name);
+        }
+        assert_eq!(names, ["a", "b"]);
+    }
+}
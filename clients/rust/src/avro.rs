@@ -0,0 +1,640 @@
+//! Avro conversion
+//!
+//! Avro values are typed by a schema, so conversion in both directions
+//! follows one. Extended types map to Avro's logical types:
+//!
+//! | kJSON | Avro | Decodes as |
+//! |-------|------|------------|
+//! | Number | int, long when integral, float, double | Integer for int and long, Number |
+//! | Integer | int, long, float, double, or decimal with scale 0 | Integer, Number, Decimal128 |
+//! | UInteger | long when it fits, float, double, or decimal with scale 0 | Integer, Number, Decimal128 |
+//! | BigInt | int, long, or decimal with scale 0 | Integer, Decimal128 |
+//! | Decimal128 | decimal (bytes or fixed), rescaled to the schema's scale | Decimal128 |
+//! | UUID | uuid | UUID |
+//! | Instant, Date | timestamp-nanos (offset dropped), or the micros/millis variants, truncated | Instant |
+//! | Instant, Date | date | Date at midnight UTC |
+//! | Duration | duration (millisecond precision, non-negative) | Duration |
+//! | Binary | bytes, fixed | Binary |
+//! | Object | record, map | Object |
+//!
+//! Strings also convert to enums, and UUIDs, instants, dates and durations
+//! to Avro strings in their ISO forms. Nullable fields are unions with
+//! `null`; a value takes the first branch it converts to. Record fields
+//! missing from an object take the field's default, or null, and object
+//! keys the record does not declare are dropped.
+//!
+//! [`infer_schema`] derives a schema from a sample document:
+//!
+//! ```
+//! use kjson::avro::{from_avro_datum, infer_schema, to_avro_datum};
+//!
+//! let order = kjson::parse("{ id: 550e8400-e29b-41d4-a716-446655440000, total: 19.99m }")?;
+//! let schema = infer_schema(&order, "Order")?;
+//! let bytes = to_avro_datum(&order, &schema)?;
+//! assert_eq!(from_avro_datum(&bytes, &schema)?, order);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::pointer;
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{into_str, Map, Value};
+use apache_avro::schema::{DecimalSchema, Name, RecordSchema, ResolvedSchema, UnionSchema};
+use apache_avro::types::Value as Avro;
+use apache_avro::{Days, Millis, Months, Schema};
+use chrono::DateTime;
+use num_bigint::{BigInt as NumBigInt, Sign};
+use num_traits::Zero;
+use serde_json::json;
+use std::collections::HashMap;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: i64 = 1 << 53;
+
+/// Digits of a Decimal128 coefficient
+const DECIMAL128_PRECISION: usize = 34;
+
+const NANOS_PER_MICRO: i64 = 1_000;
+const NANOS_PER_MILLI: i64 = 1_000_000;
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+type Names<'s> = HashMap<Name, &'s Schema>;
+
+/// Convert a value to an Avro value of `schema`
+pub fn to_avro(value: &Value, schema: &Schema) -> Result<Avro> {
+    to_avro_at(value, schema, &names(schema)?, "")
+}
+
+/// Convert an Avro value of `schema` to a value
+pub fn from_avro(value: Avro, schema: &Schema) -> Result<Value> {
+    from_avro_at(value, schema, &names(schema)?, "")
+}
+
+/// Encode a value as a bare Avro datum of `schema`, without a container
+/// file header
+pub fn to_avro_datum(value: &Value, schema: &Schema) -> Result<Vec<u8>> {
+    apache_avro::to_avro_datum(schema, to_avro(value, schema)?).map_err(avro_error)
+}
+
+/// Decode a bare Avro datum written with `schema`
+pub fn from_avro_datum(mut bytes: &[u8], schema: &Schema) -> Result<Value> {
+    let avro = apache_avro::from_avro_datum(schema, &mut bytes, None).map_err(avro_error)?;
+    from_avro(avro, schema)
+}
+
+/// An Avro schema for documents shaped like `value`.
+///
+/// Objects become records named after `name` and the path of keys to them,
+/// with fields in key order; numbers become doubles and arrays take the
+/// schema of their first item. Decimals get the scale of the sample.
+pub fn infer_schema(value: &Value, name: &str) -> Result<Schema> {
+    Schema::parse(&infer(value, name)?).map_err(avro_error)
+}
+
+fn infer(value: &Value, name: &str) -> Result<serde_json::Value> {
+    Ok(match value {
+        Value::Null => json!("null"),
+        Value::Bool(_) => json!("boolean"),
+        Value::Number(_) => json!("double"),
+        Value::Integer(_) => json!("long"),
+        Value::String(_) => json!("string"),
+        Value::Binary(_) => json!("bytes"),
+        Value::Array(items) => {
+            let items = match items.first() {
+                Some(item) => infer(item, &format!("{}_item", name))?,
+                None => json!("null"),
+            };
+            json!({ "type": "array", "items": items })
+        }
+        Value::Object(obj) => {
+            let mut keys: Vec<_> = obj.keys().collect();
+            keys.sort();
+            let fields = keys
+                .into_iter()
+                .map(|key| {
+                    if !is_avro_name(key) {
+                        return Err(Error::Conversion(format!(
+                            "{:?} is not a valid Avro field name",
+                            key
+                        )));
+                    }
+                    let schema = infer(&obj[key], &format!("{}_{}", name, key))?;
+                    Ok(json!({ "name": key.as_str(), "type": schema }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            json!({ "type": "record", "name": name, "fields": fields })
+        }
+        Value::UInteger(n) if i64::try_from(*n).is_ok() => json!("long"),
+        // Avro has no unsigned long
+        Value::UInteger(n) => {
+            let digits = n.to_string().len();
+            json!({ "type": "bytes", "logicalType": "decimal", "precision": digits, "scale": 0 })
+        }
+        Value::BigInt(b) if b.to_i64().is_some() => json!("long"),
+        Value::BigInt(b) => {
+            let digits = b.to_string().trim_start_matches('-').len();
+            json!({ "type": "bytes", "logicalType": "decimal", "precision": digits, "scale": 0 })
+        }
+        Value::Decimal128(d) => {
+            let scale = d.exponent().unsigned_abs() as usize;
+            let precision = DECIMAL128_PRECISION.max(scale);
+            json!({ "type": "bytes", "logicalType": "decimal", "precision": precision, "scale": scale })
+        }
+        Value::Uuid(_) => json!({ "type": "string", "logicalType": "uuid" }),
+        Value::Instant(_) | Value::Date(_) => {
+            json!({ "type": "long", "logicalType": "timestamp-nanos" })
+        }
+        Value::Duration(_) => {
+            json!({ "type": "fixed", "name": name, "size": 12, "logicalType": "duration" })
+        }
+    })
+}
+
+fn is_avro_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn names(schema: &Schema) -> Result<Names<'_>> {
+    Ok(ResolvedSchema::try_from(schema)
+        .map_err(avro_error)?
+        .get_names()
+        .clone())
+}
+
+fn resolve<'s>(schema: &'s Schema, names: &Names<'s>, path: &str) -> Result<&'s Schema> {
+    match schema {
+        Schema::Ref { name } => names
+            .get(name)
+            .copied()
+            .ok_or_else(|| at(path, format!("unknown Avro type {}", name))),
+        schema => Ok(schema),
+    }
+}
+
+fn to_avro_at<'s>(
+    value: &Value,
+    schema: &'s Schema,
+    names: &Names<'s>,
+    path: &str,
+) -> Result<Avro> {
+    let schema = resolve(schema, names, path)?;
+    let mismatch = || {
+        at(
+            path,
+            format!("{} does not fit Avro {:?}", value.type_name(), schema),
+        )
+    };
+    Ok(match (schema, value) {
+        (Schema::Union(union), _) => return to_union(value, union, names, path),
+        (Schema::Null, Value::Null) => Avro::Null,
+        (Schema::Boolean, Value::Bool(b)) => Avro::Boolean(*b),
+        (Schema::Int, _) => Avro::Int(
+            integer(value)
+                .and_then(|i| i32::try_from(i).ok())
+                .ok_or_else(mismatch)?,
+        ),
+        (Schema::Long, _) => Avro::Long(integer(value).ok_or_else(mismatch)?),
+        (Schema::Float, Value::Number(_) | Value::Integer(_) | Value::UInteger(_)) => {
+            Avro::Float(value.as_f64().ok_or_else(mismatch)? as f32)
+        }
+        (Schema::Double, Value::Number(_) | Value::Integer(_) | Value::UInteger(_)) => {
+            Avro::Double(value.as_f64().ok_or_else(mismatch)?)
+        }
+        (Schema::Bytes, Value::Binary(bytes)) => Avro::Bytes(bytes.clone()),
+        (Schema::Fixed(fixed), Value::Binary(bytes)) if bytes.len() == fixed.size => {
+            Avro::Fixed(fixed.size, bytes.clone())
+        }
+        (Schema::String, _) => Avro::String(string(value).ok_or_else(mismatch)?),
+        (Schema::Enum(e), Value::String(s)) => {
+            let index =
+                e.symbols.iter().position(|sym| sym == s).ok_or_else(|| {
+                    at(path, format!("{:?} is not a symbol of enum {}", s, e.name))
+                })?;
+            Avro::Enum(index as u32, s.to_string())
+        }
+        (Schema::Array(array), Value::Array(items)) => Avro::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    to_avro_at(item, &array.items, names, &pointer::push_index(path, i))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        (Schema::Map(map), Value::Object(obj)) => Avro::Map(
+            obj.iter()
+                .map(|(k, v)| {
+                    Ok((
+                        k.to_string(),
+                        to_avro_at(v, &map.types, names, &pointer::push(path, k))?,
+                    ))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        (Schema::Record(record), Value::Object(obj)) => to_record(obj, record, names, path)?,
+        (Schema::Decimal(decimal), _) => Avro::Decimal(to_decimal(value, decimal, path)?),
+        (Schema::Uuid, Value::Uuid(u)) => Avro::Uuid(*u),
+        (Schema::Date, _) => {
+            let days = epoch_nanos(value)
+                .ok_or_else(mismatch)?
+                .div_euclid(NANOS_PER_DAY);
+            Avro::Date(i32::try_from(days).map_err(|_| mismatch())?)
+        }
+        (Schema::TimestampMillis, _) => Avro::TimestampMillis(
+            epoch_nanos(value)
+                .ok_or_else(mismatch)?
+                .div_euclid(NANOS_PER_MILLI),
+        ),
+        (Schema::TimestampMicros, _) => Avro::TimestampMicros(
+            epoch_nanos(value)
+                .ok_or_else(mismatch)?
+                .div_euclid(NANOS_PER_MICRO),
+        ),
+        (Schema::TimestampNanos, _) => {
+            Avro::TimestampNanos(epoch_nanos(value).ok_or_else(mismatch)?)
+        }
+        (Schema::LocalTimestampMillis, _) => Avro::LocalTimestampMillis(
+            epoch_nanos(value)
+                .ok_or_else(mismatch)?
+                .div_euclid(NANOS_PER_MILLI),
+        ),
+        (Schema::LocalTimestampMicros, _) => Avro::LocalTimestampMicros(
+            epoch_nanos(value)
+                .ok_or_else(mismatch)?
+                .div_euclid(NANOS_PER_MICRO),
+        ),
+        (Schema::LocalTimestampNanos, _) => {
+            Avro::LocalTimestampNanos(epoch_nanos(value).ok_or_else(mismatch)?)
+        }
+        (Schema::Duration, Value::Duration(d)) => {
+            Avro::Duration(to_duration(d).ok_or_else(|| {
+                at(
+                    path,
+                    format!("{} does not fit an Avro duration", d.to_iso8601()),
+                )
+            })?)
+        }
+        _ => return Err(mismatch()),
+    })
+}
+
+fn to_union<'s>(
+    value: &Value,
+    union: &'s UnionSchema,
+    names: &Names<'s>,
+    path: &str,
+) -> Result<Avro> {
+    let mut error = None;
+    for (i, variant) in union.variants().iter().enumerate() {
+        match to_avro_at(value, variant, names, path) {
+            Ok(avro) => return Ok(Avro::Union(i as u32, Box::new(avro))),
+            // The null branch's error says nothing about why the others failed
+            Err(e) if !matches!(variant, Schema::Null) || error.is_none() => error = Some(e),
+            Err(_) => {}
+        }
+    }
+    Err(error.unwrap_or_else(|| at(path, "empty Avro union")))
+}
+
+fn to_record<'s>(
+    obj: &Map,
+    record: &'s RecordSchema,
+    names: &Names<'s>,
+    path: &str,
+) -> Result<Avro> {
+    let mut fields = Vec::with_capacity(record.fields.len());
+    for field in &record.fields {
+        let field_path = pointer::push(path, &field.name);
+        let avro = match (obj.get(field.name.as_str()), &field.default) {
+            (Some(value), _) => to_avro_at(value, &field.schema, names, &field_path)?,
+            (None, Some(default)) => Avro::from(default.clone())
+                .resolve(resolve(&field.schema, names, &field_path)?)
+                .map_err(|e| at(&field_path, e))?,
+            (None, None) => to_avro_at(&Value::Null, &field.schema, names, &field_path)
+                .map_err(|_| at(&field_path, "missing field"))?,
+        };
+        fields.push((field.name.clone(), avro));
+    }
+    Ok(Avro::Record(fields))
+}
+
+/// Integers, integral numbers and BigInts that fit an i64
+fn integer(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(n) => Some(*n),
+        Value::UInteger(n) => i64::try_from(*n).ok(),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 => {
+            Some(*n as i64)
+        }
+        Value::BigInt(b) => b.to_i64(),
+        _ => None,
+    }
+}
+
+fn string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.to_string()),
+        Value::Uuid(u) => Some(u.to_string()),
+        Value::Instant(i) => Some(i.to_iso8601()),
+        Value::Date(d) => Some(d.to_iso8601()),
+        Value::Duration(d) => Some(d.to_iso8601()),
+        _ => None,
+    }
+}
+
+fn epoch_nanos(value: &Value) -> Option<i64> {
+    match value {
+        Value::Instant(i) => Some(i.epoch_nanos()),
+        Value::Date(d) => d.utc.timestamp_nanos_opt(),
+        _ => None,
+    }
+}
+
+fn to_decimal(value: &Value, schema: &DecimalSchema, path: &str) -> Result<apache_avro::Decimal> {
+    let (coefficient, exponent) = match value {
+        Value::Decimal128(d) => (num(&d.coefficient()), d.exponent()),
+        Value::BigInt(b) => (num(b), 0),
+        Value::UInteger(n) => (NumBigInt::from(*n), 0),
+        Value::Number(_) | Value::Integer(_) => (
+            NumBigInt::from(
+                integer(value)
+                    .ok_or_else(|| at(path, "only integral numbers convert to Avro decimals"))?,
+            ),
+            0,
+        ),
+        other => {
+            return Err(at(
+                path,
+                format!("{} does not fit an Avro decimal", other.type_name()),
+            ))
+        }
+    };
+    // Rescale to `coefficient * 10^-scale`
+    let shift = exponent + schema.scale as i32;
+    let unscaled = if shift >= 0 {
+        coefficient * NumBigInt::from(10).pow(shift.unsigned_abs())
+    } else {
+        let divisor = NumBigInt::from(10).pow(shift.unsigned_abs());
+        if !(&coefficient % &divisor).is_zero() {
+            return Err(at(
+                path,
+                format!("more than {} decimal places", schema.scale),
+            ));
+        }
+        coefficient / divisor
+    };
+    if unscaled.magnitude().to_string().len() > schema.precision {
+        return Err(at(path, format!("more than {} digits", schema.precision)));
+    }
+    Ok(apache_avro::Decimal::from(unscaled.to_signed_bytes_be()))
+}
+
+fn to_duration(d: &Duration) -> Option<apache_avro::Duration> {
+    let millis = u64::try_from(d.total_nanos() / NANOS_PER_MILLI).ok()?;
+    let days = u32::try_from(millis / MILLIS_PER_DAY as u64).ok()?;
+    let rest = (millis % MILLIS_PER_DAY as u64) as u32;
+    Some(apache_avro::Duration::new(
+        Months::new(0),
+        Days::new(days),
+        Millis::new(rest),
+    ))
+}
+
+fn num(b: &BigInt) -> NumBigInt {
+    let (negative, bytes) = b.to_bytes_be();
+    NumBigInt::from_bytes_be(if negative { Sign::Minus } else { Sign::Plus }, &bytes)
+}
+
+fn big(n: &NumBigInt) -> BigInt {
+    let (sign, bytes) = n.to_bytes_be();
+    BigInt::from_bytes_be(sign == Sign::Minus, &bytes)
+}
+
+fn from_avro_at<'s>(
+    value: Avro,
+    schema: &'s Schema,
+    names: &Names<'s>,
+    path: &str,
+) -> Result<Value> {
+    let schema = resolve(schema, names, path)?;
+    let mismatch = || {
+        at(
+            path,
+            format!("Avro value does not match schema {:?}", schema),
+        )
+    };
+    Ok(match value {
+        Avro::Null => Value::Null,
+        Avro::Boolean(b) => Value::Bool(b),
+        Avro::Int(i) => Value::Integer(i.into()),
+        Avro::Long(i) => Value::Integer(i),
+        Avro::Float(f) => Value::Number(f as f64),
+        Avro::Double(f) => Value::Number(f),
+        Avro::Bytes(bytes) | Avro::Fixed(_, bytes) => Value::Binary(bytes),
+        Avro::String(s) | Avro::Enum(_, s) => Value::string(s),
+        Avro::Union(i, inner) => {
+            let Schema::Union(union) = schema else {
+                return Err(mismatch());
+            };
+            let variant = union.variants().get(i as usize).ok_or_else(mismatch)?;
+            from_avro_at(*inner, variant, names, path)?
+        }
+        Avro::Array(items) => {
+            let Schema::Array(array) = schema else {
+                return Err(mismatch());
+            };
+            Value::Array(
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        from_avro_at(item, &array.items, names, &pointer::push_index(path, i))
+                    })
+                    .collect::<Result<_>>()?,
+            )
+        }
+        Avro::Map(map) => {
+            let Schema::Map(map_schema) = schema else {
+                return Err(mismatch());
+            };
+            let mut result = Map::with_capacity(map.len());
+            for (k, v) in map {
+                let item = from_avro_at(v, &map_schema.types, names, &pointer::push(path, &k))?;
+                result.insert(into_str(k), item);
+            }
+            Value::Object(result)
+        }
+        Avro::Record(fields) => {
+            let Schema::Record(record) = schema else {
+                return Err(mismatch());
+            };
+            let mut result = Map::with_capacity(fields.len());
+            for (k, v) in fields {
+                let field = record
+                    .lookup
+                    .get(&k)
+                    .map(|&i| &record.fields[i])
+                    .ok_or_else(mismatch)?;
+                let item = from_avro_at(v, &field.schema, names, &pointer::push(path, &k))?;
+                result.insert(into_str(k), item);
+            }
+            Value::Object(result)
+        }
+        Avro::Decimal(d) => {
+            let Schema::Decimal(decimal) = schema else {
+                return Err(mismatch());
+            };
+            let scale = i32::try_from(decimal.scale).map_err(|_| mismatch())?;
+            Value::Decimal128(Decimal128::from_parts(&big(&NumBigInt::from(d)), -scale))
+        }
+        Avro::BigDecimal(d) => {
+            let (coefficient, scale) = d.as_bigint_and_exponent();
+            let exponent = i32::try_from(-scale).map_err(|_| mismatch())?;
+            Value::Decimal128(Decimal128::from_parts(&big(&coefficient), exponent))
+        }
+        Avro::Uuid(u) => Value::Uuid(u),
+        Avro::Date(days) => {
+            let midnight =
+                DateTime::from_timestamp(days as i64 * 86_400, 0).ok_or_else(mismatch)?;
+            Value::Date(Date::from_utc(midnight))
+        }
+        Avro::TimeMillis(ms) => Value::Duration(Duration::from_millis(ms as i64)),
+        Avro::TimeMicros(us) => Value::Duration(Duration::from_nanos(
+            us.checked_mul(NANOS_PER_MICRO).ok_or_else(mismatch)?,
+        )),
+        Avro::TimestampMillis(ms) | Avro::LocalTimestampMillis(ms) => Value::Instant(
+            Instant::from_nanos(ms.checked_mul(NANOS_PER_MILLI).ok_or_else(mismatch)?),
+        ),
+        Avro::TimestampMicros(us) | Avro::LocalTimestampMicros(us) => Value::Instant(
+            Instant::from_nanos(us.checked_mul(NANOS_PER_MICRO).ok_or_else(mismatch)?),
+        ),
+        Avro::TimestampNanos(ns) | Avro::LocalTimestampNanos(ns) => {
+            Value::Instant(Instant::from_nanos(ns))
+        }
+        Avro::Duration(d) => {
+            if u32::from(d.months()) != 0 {
+                return Err(at(path, "Avro durations in months have no fixed length"));
+            }
+            let days = u32::from(d.days()) as i64;
+            let millis = u32::from(d.millis()) as i64;
+            Value::Duration(Duration::from_millis(days * MILLIS_PER_DAY + millis))
+        }
+    })
+}
+
+fn at(path: &str, message: impl std::fmt::Display) -> Error {
+    if path.is_empty() {
+        Error::Conversion(message.to_string())
+    } else {
+        Error::Conversion(format!("{}: {}", path, message))
+    }
+}
+
+fn avro_error(e: apache_avro::Error) -> Error {
+    Error::Conversion(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_roundtrip_inferred_schema() {
+        let mut value = parse(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                price: 19.99m,
+                count: 3,
+                wide: 9007199254740993,
+                ttl: PT1H30M,
+                key: b64'AQIDBA==',
+                lines: [{ sku: 'a', qty: 1.5 }],
+                note: null,
+            }"#,
+        )
+        .unwrap();
+        let Value::Object(map) = &mut value else {
+            unreachable!()
+        };
+        map.insert("at", Value::Instant(Instant::from_nanos(1_736_510_400_123_456_789)));
+        let schema = infer_schema(&value, "Order").unwrap();
+        let bytes = to_avro_datum(&value, &schema).unwrap();
+        assert_eq!(from_avro_datum(&bytes, &schema).unwrap(), value);
+    }
+
+    #[test]
+    fn test_logical_types() {
+        let schema = Schema::parse_str(
+            r#"{
+                "type": "record",
+                "name": "Payment",
+                "fields": [
+                    { "name": "amount", "type": { "type": "fixed", "name": "Amount", "size": 8, "logicalType": "decimal", "precision": 12, "scale": 4 } },
+                    { "name": "id", "type": { "type": "string", "logicalType": "uuid" } },
+                    { "name": "at", "type": { "type": "long", "logicalType": "timestamp-millis" } },
+                    { "name": "memo", "type": ["null", "string"] },
+                    { "name": "state", "type": { "type": "enum", "name": "State", "symbols": ["OPEN", "PAID"] }, "default": "OPEN" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let value = parse(
+            "{ amount: 19.9m, id: 550e8400-e29b-41d4-a716-446655440000, at: 2025-01-10T12:00:00.123456Z, extra: 1 }",
+        )
+        .unwrap();
+        let avro = to_avro(&value, &schema).unwrap();
+        let Avro::Record(fields) = &avro else {
+            panic!("expected a record, got {:?}", avro);
+        };
+        assert_eq!(
+            fields[2],
+            ("at".to_string(), Avro::TimestampMillis(1_736_510_400_123))
+        );
+        assert_eq!(
+            fields[3],
+            ("memo".to_string(), Avro::Union(0, Box::new(Avro::Null)))
+        );
+
+        let bytes = to_avro_datum(&value, &schema).unwrap();
+        let decoded = from_avro_datum(&bytes, &schema).unwrap();
+        // Decimals come back at the schema's scale, timestamps truncated to it
+        let decoded = decoded.as_object().unwrap();
+        assert_eq!(
+            decoded["amount"],
+            Value::Decimal128(Decimal128::from_str("19.9000").unwrap())
+        );
+        assert_eq!(
+            decoded["at"],
+            Value::Instant(Instant::from_millis(1_736_510_400_123))
+        );
+        assert_eq!(decoded["memo"], Value::Null);
+        assert_eq!(decoded["state"], Value::string("OPEN"));
+        assert!(!decoded.contains_key("extra"));
+    }
+
+    #[test]
+    fn test_errors_carry_path() {
+        let schema = Schema::parse_str(
+            r#"{ "type": "record", "name": "R", "fields": [
+                { "name": "items", "type": { "type": "array", "items": { "type": "bytes", "logicalType": "decimal", "precision": 4, "scale": 2 } } }
+            ] }"#,
+        )
+        .unwrap();
+        let error = |input: &str| match to_avro(&parse(input).unwrap(), &schema) {
+            Err(Error::Conversion(message)) => message,
+            other => panic!("expected a conversion error, got {:?}", other),
+        };
+        assert_eq!(
+            error("{ items: [1.5m, 1.555m] }"),
+            "/items/1: more than 2 decimal places"
+        );
+        assert_eq!(error("{ items: [100m] }"), "/items/0: more than 4 digits");
+        assert_eq!(error("{}"), "/items: missing field");
+    }
+}
@@ -0,0 +1,651 @@
+//! `kjson` command-line tool
+//!
+//! ```sh
+//! cargo install kjson --features cli
+//! kjson fmt --write config/*.kjson
+//! kjson validate --schema order.schema.kjson orders/*.kjson
+//! kjson get '$.orders[*].id' orders.kjson
+//! kjson convert --to yaml config.kjson
+//! kjson convert --to kjsonb --output config.kjsonb config.kjson
+//! kjson diff old.kjson new.kjson
+//! kjson get --stream '$.user.id' events.ndjson
+//! ```
+//!
+//! With `--stream`, `fmt`, `get` and `convert` read one value of an NDJSON
+//! stream or one element of a top-level array at a time, so files larger
+//! than memory can be piped through.
+//!
+//! Exit codes: 0 on success, 1 if a document is invalid (or, with
+//! `fmt --check`, not formatted; with `get`, nothing matched; with `diff`,
+//! the documents differ), 2 if a file cannot be read or written.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use kjson::diff::{render_diff, render_diff_plain, Change, DiffStyle};
+use kjson::lexer::TokenKind;
+use kjson::report::Report;
+use kjson::schema::Schema;
+use kjson::stream::StreamReader;
+use kjson::{Error, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Name used for standard input in messages
+const STDIN: &str = "<stdin>";
+
+#[derive(Parser)]
+#[command(
+    name = "kjson",
+    version,
+    about = "Format, validate, query and compare kJSON documents"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Reformat documents, printing them to stdout by default
+    Fmt(FmtArgs),
+    /// Check documents for syntax errors and schema violations
+    Validate(ValidateArgs),
+    /// Print the values at a JSON Pointer (`/a/0`) or JSONPath (`$.a[*]`)
+    Get(GetArgs),
+    /// Convert a document to another format
+    Convert(ConvertArgs),
+    /// Show the differences between two documents, exiting with 1 if any
+    Diff(DiffArgs),
+}
+
+#[derive(Args)]
+struct FmtArgs {
+    /// Files to format; standard input if none or `-`
+    files: Vec<PathBuf>,
+    /// Write everything on one line
+    #[arg(long)]
+    compact: bool,
+    /// Rewrite the files in place
+    #[arg(short, long, conflicts_with = "check")]
+    write: bool,
+    /// Only report files that are not formatted, exiting with 1 if any
+    #[arg(long)]
+    check: bool,
+    /// Read values one at a time, writing each on its own line
+    #[arg(long, conflicts_with_all = ["write", "check"])]
+    stream: bool,
+    /// Highlight the output with ANSI colors
+    #[arg(long, conflicts_with_all = ["write", "check", "stream"])]
+    color: bool,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Files to validate; standard input if none or `-`
+    files: Vec<PathBuf>,
+    /// kJSON schema the documents must match
+    #[arg(short, long)]
+    schema: Option<PathBuf>,
+    /// Print nothing for valid documents
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Args)]
+struct GetArgs {
+    /// JSON Pointer, or JSONPath starting with `$`
+    path: String,
+    /// File to read; standard input if omitted or `-`
+    file: Option<PathBuf>,
+    /// Pretty-print each value
+    #[arg(short, long)]
+    pretty: bool,
+    /// Query each value of an NDJSON stream or top-level array in turn
+    #[arg(long, conflicts_with = "pretty")]
+    stream: bool,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    /// File to read; standard input if omitted or `-`
+    file: Option<PathBuf>,
+    /// Output format
+    #[arg(short, long, value_enum)]
+    to: Format,
+    /// Pretty-print JSON output
+    #[arg(short, long)]
+    pretty: bool,
+    /// Convert each value of an NDJSON stream or top-level array in turn
+    #[arg(long, conflicts_with = "pretty")]
+    stream: bool,
+    /// File to write instead of stdout
+    #[arg(short, long, conflicts_with = "stream")]
+    output: Option<PathBuf>,
+}
+
+/// Formats `convert` can write
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// JSON, with extended types as strings
+    Json,
+    /// YAML, with extended types as strings
+    Yaml,
+    /// kJSONB binary
+    Kjsonb,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Original document
+    old: PathBuf,
+    /// Changed document
+    new: PathBuf,
+    /// Print a JSON Patch (RFC 6902) instead of one line per change
+    #[arg(long)]
+    patch: bool,
+    /// Show old and new values as a unified or side-by-side diff
+    #[arg(long, value_enum, conflicts_with = "patch")]
+    style: Option<Style>,
+    /// Color the --style output
+    #[arg(long, requires = "style")]
+    color: bool,
+}
+
+/// Layouts for `diff --style`
+#[derive(Clone, Copy, ValueEnum)]
+enum Style {
+    /// `@@ pointer @@` headers with `-` and `+` lines
+    Unified,
+    /// Pointer, old and new value in columns
+    SideBySide,
+}
+
+/// How a command ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Ok = 0,
+    Invalid = 1,
+    Failed = 2,
+}
+
+impl Status {
+    fn exit_code(self) -> ExitCode {
+        ExitCode::from(self as u8)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let status = match cli.command {
+        Command::Fmt(args) => fmt(&args),
+        Command::Validate(args) => validate(&args),
+        Command::Get(args) => get(&args),
+        Command::Convert(args) => convert(&args),
+        Command::Diff(args) => diff(&args),
+    };
+    status.exit_code()
+}
+
+fn fmt(args: &FmtArgs) -> Status {
+    if args.stream {
+        return inputs(&args.files)
+            .into_iter()
+            .map(|input| stream(input, |value| Ok(vec![kjson::to_string(&value)?])))
+            .max()
+            .unwrap_or(Status::Ok);
+    }
+    let mut status = Status::Ok;
+    for input in inputs(&args.files) {
+        if args.write && input.is_none() {
+            eprintln!("kjson: --write needs file arguments");
+            return Status::Failed;
+        }
+        let (name, text, value) = match load(input) {
+            Ok(document) => document,
+            Err(failed) => {
+                status = status.max(failed);
+                continue;
+            }
+        };
+        let formatted = match format(&value, args.compact) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("kjson: {}: {}", name, e);
+                status = status.max(Status::Failed);
+                continue;
+            }
+        };
+
+        if args.check {
+            if formatted != text {
+                println!("{}", name);
+                status = status.max(Status::Invalid);
+            }
+        } else if let (true, Some(path)) = (args.write, input) {
+            if formatted != text {
+                if let Err(e) = std::fs::write(path, &formatted) {
+                    eprintln!("kjson: {}: {}", name, e);
+                    status = status.max(Status::Failed);
+                }
+            }
+        } else if args.color {
+            if write_stdout(&colorize(&formatted)) == Status::Failed {
+                return Status::Failed;
+            }
+        } else if write_stdout(&formatted) == Status::Failed {
+            return Status::Failed;
+        }
+    }
+    status
+}
+
+fn validate(args: &ValidateArgs) -> Status {
+    let schema = match &args.schema {
+        Some(path) => match load_schema(path) {
+            Ok(schema) => Some(schema),
+            Err(e) => {
+                eprintln!("kjson: {}: {}", path.display(), e);
+                return Status::Failed;
+            }
+        },
+        None => None,
+    };
+
+    let mut status = Status::Ok;
+    for input in inputs(&args.files) {
+        let (name, _, value) = match load(input) {
+            Ok(document) => document,
+            Err(failed) => {
+                status = status.max(failed);
+                continue;
+            }
+        };
+        let violations = schema
+            .as_ref()
+            .map(|schema| schema.validate(&value))
+            .unwrap_or_default();
+        if violations.is_empty() {
+            if !args.quiet {
+                println!("{}: ok", name);
+            }
+        } else {
+            for violation in &violations {
+                println!("{}: {}", name, violation);
+            }
+            status = status.max(Status::Invalid);
+        }
+    }
+    status
+}
+
+fn get(args: &GetArgs) -> Status {
+    // Check the path up front rather than once per streamed value
+    if let Err(e) = select(&Value::Null, &args.path) {
+        eprintln!("kjson: invalid path {}: {}", args.path, e);
+        return Status::Failed;
+    }
+    if args.stream {
+        let mut matched = false;
+        let status = stream(single_input(&args.file), |value| {
+            let texts = select(&value, &args.path)?
+                .into_iter()
+                .map(kjson::to_string)
+                .collect::<kjson::Result<Vec<_>>>()?;
+            matched |= !texts.is_empty();
+            Ok(texts)
+        });
+        return match status {
+            Status::Ok if !matched => Status::Invalid,
+            status => status,
+        };
+    }
+    let (_, _, value) = match load(single_input(&args.file)) {
+        Ok(document) => document,
+        Err(status) => return status,
+    };
+    let matches = select(&value, &args.path).unwrap_or_default();
+    if matches.is_empty() {
+        return Status::Invalid;
+    }
+    let mut out = String::new();
+    for value in matches {
+        match format(value, !args.pretty) {
+            Ok(text) => out.push_str(&text),
+            Err(e) => {
+                eprintln!("kjson: {}", e);
+                return Status::Failed;
+            }
+        }
+    }
+    write_stdout(&out)
+}
+
+fn convert(args: &ConvertArgs) -> Status {
+    if args.stream {
+        if let Format::Kjsonb = args.to {
+            eprintln!("kjson: --stream cannot write kjsonb, which has no value separator");
+            return Status::Failed;
+        }
+        return stream(single_input(&args.file), |value| {
+            let json = kjson::from_value::<serde_json::Value>(value)?;
+            let text = match args.to {
+                Format::Json => serde_json::to_string(&json),
+                Format::Yaml => {
+                    // One YAML document per value
+                    let yaml = serde_yaml::to_string(&json)
+                        .map_err(|e| Error::Conversion(e.to_string()))?;
+                    return Ok(vec![format!("---\n{}", yaml.trim_end())]);
+                }
+                Format::Kjsonb => unreachable!("rejected above"),
+            };
+            Ok(vec![text.map_err(|e| Error::Conversion(e.to_string()))?])
+        });
+    }
+    let (name, _, value) = match load(single_input(&args.file)) {
+        Ok(document) => document,
+        Err(status) => return status,
+    };
+    let converted = match args.to {
+        Format::Kjsonb => Ok(kjson::binary::to_kjsonb(&value)),
+        Format::Json => kjson::from_value::<serde_json::Value>(value).and_then(|json| {
+            let text = if args.pretty {
+                serde_json::to_string_pretty(&json)
+            } else {
+                serde_json::to_string(&json)
+            };
+            text.map(|text| (text + "\n").into_bytes())
+                .map_err(|e| Error::Conversion(e.to_string()))
+        }),
+        Format::Yaml => kjson::from_value::<serde_json::Value>(value).and_then(|json| {
+            serde_yaml::to_string(&json)
+                .map(String::into_bytes)
+                .map_err(|e| Error::Conversion(e.to_string()))
+        }),
+    };
+    let bytes = match converted {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("kjson: {}: {}", name, e);
+            return Status::Failed;
+        }
+    };
+    match &args.output {
+        Some(path) => match std::fs::write(path, bytes) {
+            Ok(()) => Status::Ok,
+            Err(e) => {
+                eprintln!("kjson: {}: {}", path.display(), e);
+                Status::Failed
+            }
+        },
+        None => write_bytes(&bytes),
+    }
+}
+
+fn diff(args: &DiffArgs) -> Status {
+    let old = load(Some(&args.old));
+    let new = load(Some(&args.new));
+    let ((_, _, old), (_, _, new)) = match (old, new) {
+        (Ok(old), Ok(new)) => (old, new),
+        // Exit code 1 means "different", so any failure to load is 2
+        _ => return Status::Failed,
+    };
+    let changes = kjson::diff::diff(&old, &new);
+    let out = if args.patch {
+        format(&kjson::diff::to_patch(&changes), false)
+    } else if let Some(style) = args.style {
+        let style = match style {
+            Style::Unified => DiffStyle::Unified,
+            Style::SideBySide => DiffStyle::SideBySide,
+        };
+        Ok(if args.color {
+            render_diff(&old, &new, style)
+        } else {
+            render_diff_plain(&old, &new, style)
+        })
+    } else {
+        changes.iter().map(describe_change).collect()
+    };
+    match out {
+        Ok(out) => match write_stdout(&out) {
+            Status::Ok if !changes.is_empty() => Status::Invalid,
+            status => status,
+        },
+        Err(e) => {
+            eprintln!("kjson: {}", e);
+            Status::Failed
+        }
+    }
+}
+
+/// Values at a JSON Pointer, or a JSONPath starting with `$`
+fn select<'a>(value: &'a Value, path: &str) -> kjson::Result<Vec<&'a Value>> {
+    if path.starts_with('$') {
+        kjson::path::query(value, path)
+    } else {
+        Ok(value.pointer(path).into_iter().collect())
+    }
+}
+
+/// Run `each` over the values of an input one at a time, writing its output
+/// as it goes.
+///
+/// A top-level array stays an array, with one element per line; any other
+/// input is written one value per line. A value that fails to parse is
+/// reported and skipped.
+fn stream<F>(input: Option<&Path>, mut each: F) -> Status
+where
+    F: FnMut(Value) -> kjson::Result<Vec<String>>,
+{
+    let name = display_name(input);
+    let reader: Box<dyn BufRead> = match input {
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("kjson: {}: {}", name, e);
+                return Status::Failed;
+            }
+        },
+        None => Box::new(std::io::stdin().lock()),
+    };
+    let values = match StreamReader::detect(reader) {
+        Ok(values) => values,
+        Err(e) => {
+            eprintln!("kjson: {}: {}", name, e);
+            return Status::Failed;
+        }
+    };
+    let mut out = StreamOutput {
+        out: BufWriter::new(std::io::stdout().lock()),
+        array: values.is_array(),
+        items: 0,
+    };
+
+    let mut status = Status::Ok;
+    for value in values {
+        let texts = match value.and_then(&mut each) {
+            Ok(texts) => texts,
+            Err(Error::ParseError { position, message }) => {
+                eprintln!("{}: byte {}: {}", name, position, message);
+                status = status.max(Status::Invalid);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("kjson: {}: {}", name, e);
+                status = status.max(Status::Failed);
+                break;
+            }
+        };
+        for text in texts {
+            if let Err(e) = out.item(&text) {
+                eprintln!("kjson: {}", e);
+                return Status::Failed;
+            }
+        }
+    }
+    if let Err(e) = out.finish() {
+        eprintln!("kjson: {}", e);
+        return Status::Failed;
+    }
+    status
+}
+
+/// Streamed output, framed as an array or one value per line
+struct StreamOutput<W: Write> {
+    out: W,
+    array: bool,
+    items: usize,
+}
+
+impl<W: Write> StreamOutput<W> {
+    fn item(&mut self, text: &str) -> std::io::Result<()> {
+        if self.array {
+            let separator = if self.items == 0 { "[\n" } else { ",\n" };
+            write!(self.out, "{}  {}", separator, text)?;
+        } else {
+            writeln!(self.out, "{}", text)?;
+        }
+        self.items += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> std::io::Result<()> {
+        if self.array {
+            let close = if self.items == 0 { "[]\n" } else { "\n]\n" };
+            self.out.write_all(close.as_bytes())?;
+        }
+        self.out.flush()
+    }
+}
+
+/// One line of `diff` output, e.g. `~ /port: 80 -> 8080`
+fn describe_change(change: &Change) -> kjson::Result<String> {
+    let path = if change.path().is_empty() {
+        "(root)"
+    } else {
+        change.path()
+    };
+    Ok(match change {
+        Change::Added { value, .. } => format!("+ {}: {}\n", path, kjson::to_string(value)?),
+        Change::Removed { value, .. } => format!("- {}: {}\n", path, kjson::to_string(value)?),
+        Change::Changed { from, to, .. } => format!(
+            "~ {}: {} -> {}\n",
+            path,
+            kjson::to_string(from)?,
+            kjson::to_string(to)?
+        ),
+    })
+}
+
+/// Wrap each token of `text` in the ANSI color for its kind
+fn colorize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut end = 0;
+    for (span, kind) in kjson::highlight(text) {
+        out.push_str(&text[end..span.start]);
+        let color = match kind {
+            TokenKind::Key => "34",
+            TokenKind::String => "32",
+            TokenKind::Number | TokenKind::BigInt | TokenKind::Decimal128 => "33",
+            TokenKind::Uuid | TokenKind::Date | TokenKind::Duration => "36",
+            TokenKind::Bool | TokenKind::Null => "35",
+            TokenKind::Comment => "2",
+            TokenKind::Error => "31",
+            _ => "",
+        };
+        if color.is_empty() {
+            out.push_str(span.slice(text));
+        } else {
+            out.push_str(&format!("\x1b[{}m{}\x1b[0m", color, span.slice(text)));
+        }
+        end = span.end;
+    }
+    out.push_str(&text[end..]);
+    out
+}
+
+fn write_stdout(text: &str) -> Status {
+    write_bytes(text.as_bytes())
+}
+
+fn write_bytes(bytes: &[u8]) -> Status {
+    match std::io::stdout().write_all(bytes) {
+        Ok(()) => Status::Ok,
+        Err(e) => {
+            eprintln!("kjson: {}", e);
+            Status::Failed
+        }
+    }
+}
+
+/// The input of a single-file command
+fn single_input(file: &Option<PathBuf>) -> Option<&Path> {
+    file.as_deref().filter(|file| file.as_os_str() != "-")
+}
+
+/// Read and parse an input, reporting failures
+fn load(input: Option<&Path>) -> Result<(String, String, Value), Status> {
+    let name = display_name(input);
+    let text = read_input(input).map_err(|e| {
+        eprintln!("kjson: {}: {}", name, e);
+        Status::Failed
+    })?;
+    match kjson::parse(&text) {
+        Ok(value) => Ok((name, text, value)),
+        Err(e) => {
+            report_syntax_error(&name, &text, &e);
+            Err(Status::Invalid)
+        }
+    }
+}
+
+/// Files to process, `None` standing for standard input
+fn inputs(files: &[PathBuf]) -> Vec<Option<&Path>> {
+    if files.is_empty() {
+        return vec![None];
+    }
+    files
+        .iter()
+        .map(|file| (file.as_os_str() != "-").then_some(file.as_path()))
+        .collect()
+}
+
+fn display_name(input: Option<&Path>) -> String {
+    input.map_or_else(|| STDIN.to_string(), |path| path.display().to_string())
+}
+
+fn read_input(input: Option<&Path>) -> std::io::Result<String> {
+    match input {
+        Some(path) => std::fs::read_to_string(path),
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+fn load_schema(path: &Path) -> kjson::Result<Schema> {
+    Schema::parse(&std::fs::read_to_string(path)?)
+}
+
+fn format(value: &Value, compact: bool) -> kjson::Result<String> {
+    let mut text = if compact {
+        kjson::to_string(value)?
+    } else {
+        kjson::serializer_to_string_pretty(value)?
+    };
+    text.push('\n');
+    Ok(text)
+}
+
+/// Print a parse failure with the offending line, starting with
+/// `name:line:column: message`
+fn report_syntax_error(name: &str, text: &str, error: &Error) {
+    let report = Report::from_error(error, text);
+    if std::io::stderr().is_terminal() {
+        eprint!("{}", report.render(name, text));
+    } else {
+        eprint!("{}", report.render_plain(name, text));
+    }
+}
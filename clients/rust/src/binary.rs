@@ -0,0 +1,844 @@
+//! kJSONB binary format support
+//!
+//! [`to_kjsonb`] and [`from_kjsonb`] convert between values and kJSONB, the
+//! binary form described in `SPEC_BINARY.md`. Each value starts with a type
+//! byte:
+//!
+//! | kJSON | Type byte | Payload |
+//! |-------|-----------|---------|
+//! | Integer | `0x10`-`0x13` | smallest of i8, i16, i32, i64 |
+//! | Number | `0x16` | f64 (NaN and infinities are written as null) |
+//! | BigInt | `0x17` | varint `length << 1 \| negative`, decimal digits |
+//! | Decimal128 | `0x18` | varint length, decimal string |
+//! | Instant, Date | `0x30` | i64 milliseconds since the epoch |
+//! | UUID | `0x31` | 16 bytes |
+//! | Duration | `0x20` | ISO 8601 string |
+//!
+//! All fixed-width numbers are little-endian. kJSONB has no type bytes for
+//! instants, durations or timezone offsets, so instants and dates are
+//! truncated to milliseconds and read back as UTC Dates, and durations are
+//! read back as strings.
+//!
+//! kJSONB payloads can optionally be wrapped in a compressed frame. A frame
+//! starts with a magic header identifying the codec, so readers can detect
+//! compressed data and fall back to raw kJSONB when no header is present.
+//!
+//! Plain gzip and zstd files, such as `.kjson.gz` exports, are recognized
+//! by their own magic bytes: [`decompress_reader`] unwraps them, and
+//! [`from_reader`](crate::from_reader) and [`parse_file`](crate::parse_file)
+//! use it so compressed documents parse directly.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Date, Decimal128};
+use crate::value::{into_str, ordered_entries, Map, Value};
+use chrono::DateTime;
+use std::io::{Cursor, Read};
+use uuid::Uuid;
+
+/// Magic bytes that open every compressed kJSONB frame
+pub const FRAME_MAGIC: [u8; 4] = *b"KJBZ";
+
+/// Largest payload a compressed frame may hold, 1 GiB
+pub const MAX_FRAME_LEN: usize = 1 << 30;
+
+/// Magic bytes that open a gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Magic bytes that open a Zstandard frame
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Null
+pub const TYPE_NULL: u8 = 0x00;
+/// Boolean false
+pub const TYPE_FALSE: u8 = 0x01;
+/// Boolean true
+pub const TYPE_TRUE: u8 = 0x02;
+/// 8-bit signed integer
+pub const TYPE_INT8: u8 = 0x10;
+/// 16-bit signed integer
+pub const TYPE_INT16: u8 = 0x11;
+/// 32-bit signed integer
+pub const TYPE_INT32: u8 = 0x12;
+/// 64-bit signed integer
+pub const TYPE_INT64: u8 = 0x13;
+/// 64-bit unsigned integer
+pub const TYPE_UINT64: u8 = 0x14;
+/// 32-bit float
+pub const TYPE_FLOAT32: u8 = 0x15;
+/// 64-bit float
+pub const TYPE_FLOAT64: u8 = 0x16;
+/// Arbitrary-precision integer
+pub const TYPE_BIGINT: u8 = 0x17;
+/// Decimal, as a string
+pub const TYPE_DECIMAL128: u8 = 0x18;
+/// UTF-8 string
+pub const TYPE_STRING: u8 = 0x20;
+/// Raw bytes
+pub const TYPE_BINARY: u8 = 0x21;
+/// Date in milliseconds since the epoch
+pub const TYPE_DATE: u8 = 0x30;
+/// UUID
+pub const TYPE_UUID: u8 = 0x31;
+/// Array
+pub const TYPE_ARRAY: u8 = 0x40;
+/// Object
+pub const TYPE_OBJECT: u8 = 0x41;
+/// JavaScript `undefined`, read as null
+pub const TYPE_UNDEFINED: u8 = 0xf0;
+
+/// Deepest nesting of arrays and objects [`from_kjsonb`] accepts
+pub const MAX_DEPTH: usize = 512;
+
+/// Encode a value as kJSONB
+pub fn to_kjsonb(value: &Value) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_value(value, &mut out);
+    out
+}
+
+/// Decode a kJSONB value.
+///
+/// The input must hold exactly one value. Compressed frames are not
+/// unwrapped; pass them through [`decompress_frame`] first.
+pub fn from_kjsonb(data: &[u8]) -> Result<Value> {
+    let mut decoder = Decoder {
+        data,
+        pos: 0,
+        depth: 0,
+    };
+    let value = decoder.value()?;
+    if decoder.pos < data.len() {
+        return Err(Error::InvalidBinary(format!(
+            "Unexpected bytes after value at offset {}",
+            decoder.pos
+        )));
+    }
+    Ok(value)
+}
+
+fn encode_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(TYPE_NULL),
+        Value::Bool(false) => out.push(TYPE_FALSE),
+        Value::Bool(true) => out.push(TYPE_TRUE),
+        Value::Integer(n) => encode_integer(*n, out),
+        Value::UInteger(n) => match i64::try_from(*n) {
+            Ok(n) => encode_integer(n, out),
+            Err(_) => {
+                out.push(TYPE_UINT64);
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+        },
+        Value::Number(n) if n.is_finite() => {
+            out.push(TYPE_FLOAT64);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::Number(_) => out.push(TYPE_NULL),
+        Value::String(s) => {
+            out.push(TYPE_STRING);
+            write_bytes(out, s.as_bytes());
+        }
+        Value::Binary(bytes) => {
+            out.push(TYPE_BINARY);
+            write_bytes(out, bytes);
+        }
+        Value::BigInt(b) => {
+            let text = b.to_string();
+            let digits = text.trim_start_matches('-');
+            out.push(TYPE_BIGINT);
+            write_varint(out, (digits.len() as u64) << 1 | b.is_negative() as u64);
+            out.extend_from_slice(digits.as_bytes());
+        }
+        Value::Decimal128(d) => {
+            out.push(TYPE_DECIMAL128);
+            write_bytes(out, d.to_string().as_bytes());
+        }
+        Value::Uuid(u) => {
+            out.push(TYPE_UUID);
+            out.extend_from_slice(u.as_bytes());
+        }
+        Value::Date(d) => {
+            out.push(TYPE_DATE);
+            out.extend_from_slice(&d.utc.timestamp_millis().to_le_bytes());
+        }
+        Value::Instant(i) => {
+            out.push(TYPE_DATE);
+            out.extend_from_slice(&i.epoch_millis().to_le_bytes());
+        }
+        Value::Duration(d) => {
+            out.push(TYPE_STRING);
+            write_bytes(out, d.to_iso8601().as_bytes());
+        }
+        Value::Array(items) => {
+            out.push(TYPE_ARRAY);
+            write_varint(out, items.len() as u64);
+            for item in items {
+                encode_value(item, out);
+            }
+        }
+        Value::Object(map) => {
+            out.push(TYPE_OBJECT);
+            write_varint(out, map.len() as u64);
+            for (key, value) in ordered_entries(map) {
+                write_bytes(out, key.as_bytes());
+                encode_value(value, out);
+            }
+        }
+    }
+}
+
+/// Write an integer in the smallest type that holds it
+fn encode_integer(n: i64, out: &mut Vec<u8>) {
+    if let Ok(n) = i8::try_from(n) {
+        out.push(TYPE_INT8);
+        out.extend_from_slice(&n.to_le_bytes());
+    } else if let Ok(n) = i16::try_from(n) {
+        out.push(TYPE_INT16);
+        out.extend_from_slice(&n.to_le_bytes());
+    } else if let Ok(n) = i32::try_from(n) {
+        out.push(TYPE_INT32);
+        out.extend_from_slice(&n.to_le_bytes());
+    } else {
+        out.push(TYPE_INT64);
+        out.extend_from_slice(&n.to_le_bytes());
+    }
+}
+
+/// Write a varint length followed by the bytes
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Reads values from kJSONB input
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    /// Arrays and objects open around the current value
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn value(&mut self) -> Result<Value> {
+        let start = self.pos;
+        // Containers recurse, so scalars are decoded in their own frame to
+        // keep each level of nesting small on the stack
+        match self.take(1)?[0] {
+            TYPE_ARRAY => self.array(start),
+            TYPE_OBJECT => self.object(start),
+            tag => self.scalar(tag, start),
+        }
+    }
+
+    fn scalar(&mut self, tag: u8, start: usize) -> Result<Value> {
+        let value = match tag {
+            TYPE_NULL | TYPE_UNDEFINED => Value::Null,
+            TYPE_FALSE => Value::Bool(false),
+            TYPE_TRUE => Value::Bool(true),
+            TYPE_INT8 => Value::Integer(i8::from_le_bytes(self.fixed()?).into()),
+            TYPE_INT16 => Value::Integer(i16::from_le_bytes(self.fixed()?).into()),
+            TYPE_INT32 => Value::Integer(i32::from_le_bytes(self.fixed()?).into()),
+            TYPE_INT64 => Value::Integer(i64::from_le_bytes(self.fixed()?)),
+            TYPE_UINT64 => {
+                let n = u64::from_le_bytes(self.fixed()?);
+                match i64::try_from(n) {
+                    Ok(n) => Value::Integer(n),
+                    Err(_) => Value::UInteger(n),
+                }
+            }
+            TYPE_FLOAT32 => Value::Number(f32::from_le_bytes(self.fixed()?).into()),
+            TYPE_FLOAT64 => Value::Number(f64::from_le_bytes(self.fixed()?)),
+            TYPE_BIGINT => {
+                let flags = read_varint(self.data, &mut self.pos)?;
+                let digits = self.take(self.len(flags >> 1)?)?;
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Err(self.invalid(start, "BigInt digits"));
+                }
+                let sign = if flags & 1 == 1 { "-" } else { "" };
+                let digits = std::str::from_utf8(digits).unwrap_or_default();
+                Value::BigInt(BigInt::from_str(&format!("{}{}", sign, digits))?)
+            }
+            TYPE_DECIMAL128 => Value::Decimal128(Decimal128::from_str(self.str()?)?),
+            TYPE_STRING => Value::string(self.str()?),
+            TYPE_BINARY => {
+                let len = self.varint_len()?;
+                Value::Binary(self.take(len)?.to_vec())
+            }
+            TYPE_DATE => {
+                let millis = i64::from_le_bytes(self.fixed()?);
+                let utc = DateTime::from_timestamp_millis(millis)
+                    .ok_or_else(|| self.invalid(start, "Date"))?;
+                Value::Date(Date::from_utc(utc))
+            }
+            TYPE_UUID => Value::Uuid(Uuid::from_bytes(self.fixed()?)),
+            other => {
+                return Err(Error::InvalidBinary(format!(
+                    "Unknown type byte 0x{:02x} at offset {}",
+                    other, start
+                )))
+            }
+        };
+        Ok(value)
+    }
+
+    fn array(&mut self, start: usize) -> Result<Value> {
+        self.enter(start)?;
+        let count = self.varint_len()?;
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            items.push(self.value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Array(items))
+    }
+
+    fn object(&mut self, start: usize) -> Result<Value> {
+        self.enter(start)?;
+        let count = self.varint_len()?;
+        let mut map = Map::with_capacity(count);
+        for _ in 0..count {
+            let key = into_str(self.str()?);
+            map.insert(key, self.value()?);
+        }
+        self.depth -= 1;
+        Ok(Value::Object(map))
+    }
+
+    /// Open an array or object, failing past [`MAX_DEPTH`]
+    fn enter(&mut self, position: usize) -> Result<()> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(Error::DepthLimit {
+                position,
+                limit: MAX_DEPTH,
+            });
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or(Error::UnexpectedEof)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+
+    /// A length or count, which can be no more than the bytes left since
+    /// every byte or element takes at least one
+    fn len(&self, n: u64) -> Result<usize> {
+        match usize::try_from(n) {
+            Ok(n) if n <= self.data.len() - self.pos => Ok(n),
+            _ => Err(Error::UnexpectedEof),
+        }
+    }
+
+    fn varint_len(&mut self) -> Result<usize> {
+        let n = read_varint(self.data, &mut self.pos)?;
+        self.len(n)
+    }
+
+    fn str(&mut self) -> Result<&'a str> {
+        let start = self.pos;
+        let len = self.varint_len()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| self.invalid(start, "UTF-8"))
+    }
+
+    fn invalid(&self, position: usize, what: &str) -> Error {
+        Error::InvalidBinary(format!("Invalid {} at offset {}", what, position))
+    }
+}
+
+/// Compression codec applied to a kJSONB frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// No compression (payload stored as-is)
+    None,
+    /// gzip (DEFLATE) compression, requires the `gzip` feature
+    Gzip,
+    /// Zstandard compression, requires the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    /// Codec byte written after the frame magic
+    pub fn code(self) -> u8 {
+        match self {
+            Compression::None => 0x00,
+            Compression::Gzip => 0x01,
+            Compression::Zstd => 0x02,
+        }
+    }
+
+    /// Look up a codec from its frame byte
+    pub fn from_code(code: u8) -> Result<Self> {
+        match code {
+            0x00 => Ok(Compression::None),
+            0x01 => Ok(Compression::Gzip),
+            0x02 => Ok(Compression::Zstd),
+            _ => Err(Error::InvalidBinary(format!(
+                "Unknown compression codec: 0x{:02x}",
+                code
+            ))),
+        }
+    }
+
+    /// Codec name as used in content negotiation (e.g. `Accept-Encoding`)
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "identity",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Look up a codec from its negotiation name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "identity" | "none" => Some(Compression::None),
+            "gzip" => Some(Compression::Gzip),
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The codec of a plain gzip or zstd stream starting with `data`, from
+    /// its magic bytes; `Compression::None` for anything else, including
+    /// kJSONB frames
+    pub fn detect(data: &[u8]) -> Compression {
+        if data.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if data.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Whether this build can compress and decompress with the codec
+    pub fn is_supported(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Codecs supported by this build, in order of preference
+    pub fn supported() -> Vec<Compression> {
+        [Compression::Zstd, Compression::Gzip, Compression::None]
+            .into_iter()
+            .filter(|c| c.is_supported())
+            .collect()
+    }
+
+    /// Pick the preferred codec that both this build and the peer support.
+    ///
+    /// Falls back to `Compression::None`, which every reader understands.
+    pub fn negotiate(offered: &[Compression]) -> Compression {
+        Self::supported()
+            .into_iter()
+            .find(|c| offered.contains(c))
+            .unwrap_or(Compression::None)
+    }
+}
+
+/// Wrap a kJSONB payload in a compressed frame
+pub fn compress_frame(payload: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    check_frame_len(payload.len() as u64)?;
+    let mut frame = Vec::with_capacity(payload.len() / 2 + 16);
+    frame.extend_from_slice(&FRAME_MAGIC);
+    frame.push(compression.code());
+    write_varint(&mut frame, payload.len() as u64);
+
+    match compression {
+        Compression::None => frame.extend_from_slice(payload),
+        Compression::Gzip => gzip_compress(payload, &mut frame)?,
+        Compression::Zstd => zstd_compress(payload, &mut frame)?,
+    }
+
+    Ok(frame)
+}
+
+/// Unwrap a kJSONB frame, decompressing it if necessary.
+///
+/// Input without the frame magic is returned unchanged, so raw kJSONB
+/// payloads can be passed through the same code path. Frames whose
+/// payload is longer than [`MAX_FRAME_LEN`] are rejected, and the
+/// decompressed payload must be exactly the length the header gives.
+pub fn decompress_frame(data: &[u8]) -> Result<Vec<u8>> {
+    if !is_compressed_frame(data) {
+        return Ok(data.to_vec());
+    }
+
+    let compression = match data.get(FRAME_MAGIC.len()) {
+        Some(&code) => Compression::from_code(code)?,
+        None => return Err(Error::UnexpectedEof),
+    };
+    let mut pos = FRAME_MAGIC.len() + 1;
+    let expected_len = read_varint(data, &mut pos)?;
+    check_frame_len(expected_len)?;
+    let expected_len = expected_len as usize;
+    let body = &data[pos..];
+
+    let payload = match compression {
+        Compression::None => body.to_vec(),
+        Compression::Gzip => gzip_decompress(body, expected_len)?,
+        Compression::Zstd => zstd_decompress(body, expected_len)?,
+    };
+
+    if payload.len() != expected_len {
+        return Err(Error::InvalidBinary(format!(
+            "Frame length mismatch: header says {} bytes, got {}",
+            expected_len,
+            payload.len()
+        )));
+    }
+
+    Ok(payload)
+}
+
+/// Fail if a frame payload of `len` bytes is over [`MAX_FRAME_LEN`]
+fn check_frame_len(len: u64) -> Result<()> {
+    if len > MAX_FRAME_LEN as u64 {
+        return Err(Error::LimitExceeded(format!(
+            "frame payload of {} bytes is over the {} byte limit",
+            len, MAX_FRAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Wrap `reader` so gzip or zstd input is decompressed, detected from its
+/// first bytes. Other input is read unchanged.
+///
+/// Fails with [`Error::UnsupportedCompression`] for compressed input when
+/// the codec's feature is not enabled.
+pub fn decompress_reader<'a, R: Read + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>> {
+    let mut head = Vec::with_capacity(ZSTD_MAGIC.len());
+    reader
+        .by_ref()
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut head)?;
+    let compression = Compression::detect(&head);
+    let reader = Cursor::new(head).chain(reader);
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        Compression::Gzip => gzip_reader(reader),
+        Compression::Zstd => zstd_reader(reader),
+    }
+}
+
+/// Check whether data starts with the compressed frame magic
+pub fn is_compressed_frame(data: &[u8]) -> bool {
+    data.starts_with(&FRAME_MAGIC)
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_compress(payload: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+    encoder.write_all(payload)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_compress(_payload: &[u8], _out: &mut Vec<u8>) -> Result<()> {
+    Err(Error::UnsupportedCompression("gzip".to_string()))
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_decompress(body: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    // One byte past the header's length is enough to detect a mismatch,
+    // without inflating a decompression bomb
+    let mut payload = Vec::new();
+    flate2::read::GzDecoder::new(body)
+        .take(expected_len as u64 + 1)
+        .read_to_end(&mut payload)?;
+    Ok(payload)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_decompress(_body: &[u8], _expected_len: usize) -> Result<Vec<u8>> {
+    Err(Error::UnsupportedCompression("gzip".to_string()))
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_reader<'a, R: Read + 'a>(reader: R) -> Result<Box<dyn Read + 'a>> {
+    // Multi-member, as concatenated `.gz` files are
+    Ok(Box::new(flate2::read::MultiGzDecoder::new(reader)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_reader<'a, R: Read + 'a>(_reader: R) -> Result<Box<dyn Read + 'a>> {
+    Err(Error::UnsupportedCompression("gzip".to_string()))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_compress(payload: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    zstd::stream::copy_encode(payload, out, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_payload: &[u8], _out: &mut Vec<u8>) -> Result<()> {
+    Err(Error::UnsupportedCompression("zstd".to_string()))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decompress(body: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let mut payload = Vec::new();
+    zstd::stream::read::Decoder::with_buffer(body)?
+        .take(expected_len as u64 + 1)
+        .read_to_end(&mut payload)?;
+    Ok(payload)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decompress(_body: &[u8], _expected_len: usize) -> Result<Vec<u8>> {
+    Err(Error::UnsupportedCompression("zstd".to_string()))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_reader<'a, R: Read + 'a>(reader: R) -> Result<Box<dyn Read + 'a>> {
+    Ok(Box::new(zstd::stream::read::Decoder::new(reader)?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_reader<'a, R: Read + 'a>(_reader: R) -> Result<Box<dyn Read + 'a>> {
+    Err(Error::UnsupportedCompression("zstd".to_string()))
+}
+
+/// Write an unsigned LEB128 varint
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Read an unsigned LEB128 varint, advancing `pos`
+pub(crate) fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut result = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).ok_or(Error::UnexpectedEof)?;
+        *pos += 1;
+        if shift >= 64 {
+            return Err(Error::InvalidBinary("Varint overflow".to_string()));
+        }
+        result |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(result);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::{Duration, Instant};
+
+    const PAYLOAD: &[u8] = b"\x41\x02\x01\x61\x10\x01\x01\x62\x10\x02";
+
+    #[test]
+    fn test_kjsonb_roundtrip() {
+        let value = crate::parse(
+            "{ id: -98765432109876543210n, price: 19.990m, at: 2025-01-01T18:00:00.25Z, \
+             ratio: 0.5, big: -9223372036854775808, tags: ['a', [{}]] }",
+        )
+        .unwrap();
+        let bytes = to_kjsonb(&value);
+        let decoded = from_kjsonb(&bytes).unwrap();
+        assert_eq!(decoded, value);
+        assert_eq!(to_kjsonb(&decoded), bytes);
+    }
+
+    #[test]
+    fn test_kjsonb_lossy_types() {
+        let value = Value::Array(vec![
+            crate::parse("2025-01-01T10:00:00.250999-08:00").unwrap(),
+            Value::Instant(Instant::from_nanos(1_735_754_400_250_999_999)),
+            Value::Duration(Duration::from_nanos(-1_500_000_000)),
+        ]);
+        let bytes = to_kjsonb(&value);
+        assert_eq!(bytes[2], TYPE_DATE);
+        let decoded = from_kjsonb(&bytes).unwrap();
+        let date = Value::Date(Date::from_utc(
+            DateTime::from_timestamp_millis(1_735_754_400_250).unwrap(),
+        ));
+        assert_eq!(decoded[0], date);
+        assert_eq!(decoded[1], date);
+        assert_eq!(decoded[2], Value::string("-PT1.5S"));
+    }
+
+    #[test]
+    fn test_kjsonb_lenient_types() {
+        assert_eq!(to_kjsonb(&Value::Number(f64::NAN)), [TYPE_NULL]);
+        assert_eq!(from_kjsonb(&[TYPE_UNDEFINED]).unwrap(), Value::Null);
+        assert_eq!(
+            from_kjsonb(&[TYPE_FLOAT32, 0, 0, 0xc0, 0x3f]).unwrap(),
+            Value::Number(1.5)
+        );
+
+        let mut max = vec![TYPE_UINT64];
+        max.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(from_kjsonb(&max).unwrap(), Value::UInteger(u64::MAX)));
+    }
+
+    #[test]
+    fn test_invalid_kjsonb() {
+        assert!(matches!(from_kjsonb(&[]), Err(Error::UnexpectedEof)));
+        assert!(matches!(from_kjsonb(&[0x7f]), Err(Error::InvalidBinary(_))));
+        assert!(matches!(from_kjsonb(&[TYPE_NULL, TYPE_NULL]), Err(Error::InvalidBinary(_))));
+        assert!(matches!(from_kjsonb(&[TYPE_STRING, 2, 0xff, 0xfe]), Err(Error::InvalidBinary(_))));
+        assert!(matches!(from_kjsonb(&[TYPE_BIGINT, 2, b'x']), Err(Error::InvalidBinary(_))));
+        // A count larger than the input fails without allocating for it
+        assert!(matches!(
+            from_kjsonb(&[TYPE_ARRAY, 0xff, 0xff, 0xff, 0xff, 0x0f]),
+            Err(Error::UnexpectedEof)
+        ));
+
+        let mut nested = [TYPE_ARRAY, 1].repeat(MAX_DEPTH + 1);
+        nested.push(TYPE_NULL);
+        assert!(matches!(
+            from_kjsonb(&nested),
+            Err(Error::DepthLimit { limit: MAX_DEPTH, .. })
+        ));
+    }
+
+    #[test]
+    fn test_uncompressed_frame_roundtrip() {
+        let frame = compress_frame(PAYLOAD, Compression::None).unwrap();
+        assert!(is_compressed_frame(&frame));
+        assert_eq!(frame[4], 0x00);
+        assert_eq!(decompress_frame(&frame).unwrap(), PAYLOAD);
+    }
+
+    #[test]
+    fn test_raw_payload_passthrough() {
+        assert!(!is_compressed_frame(PAYLOAD));
+        assert_eq!(decompress_frame(PAYLOAD).unwrap(), PAYLOAD);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_frame_roundtrip() {
+        let payload = PAYLOAD.repeat(100);
+        let frame = compress_frame(&payload, Compression::Gzip).unwrap();
+        assert!(frame.len() < payload.len());
+        assert_eq!(decompress_frame(&frame).unwrap(), payload);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_frame_roundtrip() {
+        let payload = PAYLOAD.repeat(100);
+        let frame = compress_frame(&payload, Compression::Zstd).unwrap();
+        assert!(frame.len() < payload.len());
+        assert_eq!(decompress_frame(&frame).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_decompress_reader() {
+        let read = |data: &[u8]| -> Result<String> {
+            let mut text = String::new();
+            decompress_reader(data)?.read_to_string(&mut text)?;
+            Ok(text)
+        };
+        assert_eq!(read(b"[1]").unwrap(), "[1]");
+        assert_eq!(read(b"").unwrap(), "");
+        assert_eq!(Compression::detect(&FRAME_MAGIC), Compression::None);
+
+        #[cfg(feature = "gzip")]
+        {
+            use std::io::Write;
+            let mut gz = Vec::new();
+            for part in ["[1, ", "2]"] {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(part.as_bytes()).unwrap();
+                gz.extend(encoder.finish().unwrap());
+            }
+            assert_eq!(Compression::detect(&gz), Compression::Gzip);
+            assert_eq!(read(&gz).unwrap(), "[1, 2]");
+        }
+        #[cfg(not(feature = "gzip"))]
+        assert!(matches!(
+            read(&[0x1f, 0x8b, 8, 0]),
+            Err(Error::UnsupportedCompression(_))
+        ));
+
+        #[cfg(feature = "zstd")]
+        {
+            let zst = zstd::encode_all(&b"{ a: 1 }"[..], 0).unwrap();
+            assert_eq!(Compression::detect(&zst), Compression::Zstd);
+            assert_eq!(read(&zst).unwrap(), "{ a: 1 }");
+        }
+    }
+
+    #[test]
+    fn test_negotiation() {
+        assert_eq!(Compression::negotiate(&[]), Compression::None);
+        assert_eq!(
+            Compression::negotiate(&[Compression::Gzip]),
+            if cfg!(feature = "gzip") {
+                Compression::Gzip
+            } else {
+                Compression::None
+            }
+        );
+        assert_eq!(Compression::from_name(" ZSTD "), Some(Compression::Zstd));
+        assert_eq!(Compression::from_name("br"), None);
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let mut frame = compress_frame(PAYLOAD, Compression::None).unwrap();
+        frame[4] = 0x7f;
+        assert!(matches!(
+            decompress_frame(&frame),
+            Err(Error::InvalidBinary(_))
+        ));
+
+        let truncated = compress_frame(PAYLOAD, Compression::None).unwrap();
+        assert!(decompress_frame(&truncated[..truncated.len() - 1]).is_err());
+
+        // A header claiming 2^60 bytes must not be trusted for allocation
+        let huge = b"KJBZ\x01\x80\x80\x80\x80\x80\x80\x80\x80\x10\x1f\x8b\x00\x00";
+        assert!(matches!(decompress_frame(huge), Err(Error::LimitExceeded(_))));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_frame_length_mismatch() {
+        let frame = |claimed: u64| {
+            let body = compress_frame(&[0; 1 << 20], Compression::Gzip).unwrap();
+            let mut frame = b"KJBZ\x01".to_vec();
+            write_varint(&mut frame, claimed);
+            // Skip the magic, codec byte and three-byte length varint
+            frame.extend_from_slice(&body[FRAME_MAGIC.len() + 1 + 3..]);
+            frame
+        };
+        assert_eq!(decompress_frame(&frame(1 << 20)).unwrap().len(), 1 << 20);
+        // Over: only one byte past the claimed length is inflated
+        assert!(matches!(decompress_frame(&frame(16)), Err(Error::InvalidBinary(_))));
+        // Under
+        assert!(matches!(decompress_frame(&frame(2 << 20)), Err(Error::InvalidBinary(_))));
+    }
+}
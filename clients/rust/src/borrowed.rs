@@ -0,0 +1,235 @@
+//! Values borrowing their strings from the input
+//!
+//! [`parse_borrowed`](crate::parse_borrowed) parses into a [`BorrowedValue`],
+//! whose strings and object keys are `Cow<'a, str>`: borrowed from the
+//! input when they contain no escapes, owned otherwise. Reading a large
+//! document this way skips one allocation per string:
+//!
+//! ```
+//! use kjson::BorrowedValue;
+//! use std::borrow::Cow;
+//!
+//! let text = "{ name: 'widget', note: 'line\\nbreak', id: 7n }";
+//! let doc = kjson::parse_borrowed(text)?;
+//! assert!(matches!(doc.get("name"), Some(BorrowedValue::String(Cow::Borrowed("widget")))));
+//! assert!(matches!(doc.get("note"), Some(BorrowedValue::String(Cow::Owned(_)))));
+//!
+//! // Serializes like a Value, and converts to one when it must outlive the text
+//! assert_eq!(kjson::ser::to_string(&doc)?, "{name: 'widget', note: 'line\\nbreak', id: 7n}");
+//! assert_eq!(doc.into_owned(), kjson::parse(text)?);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Objects are lists of members in document order. Duplicate keys are kept
+//! as written; [`get`](BorrowedValue::get) and
+//! [`into_owned`](BorrowedValue::into_owned) take the last, as
+//! [`parse`](crate::parse) does.
+
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use serde::ser::{Serialize, Serializer};
+use std::borrow::Cow;
+use uuid::Uuid;
+
+/// A kJSON value whose strings and keys may borrow from the parsed text
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    /// Null value
+    Null,
+    /// Boolean value
+    Bool(bool),
+    /// Number value (f64)
+    Number(f64),
+    /// Integer number value, written without a fraction or exponent
+    Integer(i64),
+    /// Integer number value above `i64::MAX`, written without a fraction or
+    /// exponent
+    UInteger(u64),
+    /// String value
+    String(Cow<'a, str>),
+    /// Array of values
+    Array(Vec<BorrowedValue<'a>>),
+    /// Object members in document order
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+    /// BigInt value
+    BigInt(BigInt),
+    /// Decimal128 value
+    Decimal128(Decimal128),
+    /// UUID value
+    Uuid(Uuid),
+    /// Date value
+    Date(Date),
+    /// Instant value (nanosecond-precision UTC timestamp)
+    Instant(Instant),
+    /// Duration value
+    Duration(Duration),
+    /// Binary data, written as a `b64'...'` literal
+    Binary(Vec<u8>),
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Get the value type name, as [`Value::type_name`] gives it
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            BorrowedValue::Null => "null",
+            BorrowedValue::Bool(_) => "boolean",
+            BorrowedValue::Number(_)
+            | BorrowedValue::Integer(_)
+            | BorrowedValue::UInteger(_) => "number",
+            BorrowedValue::String(_) => "string",
+            BorrowedValue::Array(_) => "array",
+            BorrowedValue::Object(_) => "object",
+            BorrowedValue::BigInt(_) => "bigint",
+            BorrowedValue::Decimal128(_) => "decimal128",
+            BorrowedValue::Uuid(_) => "uuid",
+            BorrowedValue::Date(_) => "date",
+            BorrowedValue::Instant(_) => "instant",
+            BorrowedValue::Duration(_) => "duration",
+            BorrowedValue::Binary(_) => "binary",
+        }
+    }
+
+    /// Try to get as string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BorrowedValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The last member named `key`, if this is an object
+    pub fn get(&self, key: &str) -> Option<&BorrowedValue<'a>> {
+        match self {
+            BorrowedValue::Object(members) => members
+                .iter()
+                .rev()
+                .find_map(|(k, v)| (k == key).then_some(v)),
+            _ => None,
+        }
+    }
+
+    /// Copy borrowed strings so the value no longer depends on the input
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Bool(b) => Value::Bool(b),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::Integer(n) => Value::Integer(n),
+            BorrowedValue::UInteger(n) => Value::UInteger(n),
+            BorrowedValue::String(s) => Value::String(Str::from(s)),
+            BorrowedValue::Array(items) => {
+                Value::Array(items.into_iter().map(BorrowedValue::into_owned).collect())
+            }
+            BorrowedValue::Object(members) => {
+                let mut map = Map::with_capacity(members.len());
+                for (key, value) in members {
+                    map.insert(Str::from(key), value.into_owned());
+                }
+                Value::Object(map)
+            }
+            BorrowedValue::BigInt(n) => Value::BigInt(n),
+            BorrowedValue::Decimal128(d) => Value::Decimal128(d),
+            BorrowedValue::Uuid(u) => Value::Uuid(u),
+            BorrowedValue::Date(d) => Value::Date(d),
+            BorrowedValue::Instant(i) => Value::Instant(i),
+            BorrowedValue::Duration(d) => Value::Duration(d),
+            BorrowedValue::Binary(bytes) => Value::Binary(bytes),
+        }
+    }
+
+    /// A parsed value other than a string, array or object
+    pub(crate) fn from_scalar(mut value: Value) -> BorrowedValue<'static> {
+        match &mut value {
+            Value::Null => BorrowedValue::Null,
+            Value::Bool(b) => BorrowedValue::Bool(*b),
+            Value::Number(n) => BorrowedValue::Number(*n),
+            Value::Integer(n) => BorrowedValue::Integer(*n),
+            Value::UInteger(n) => BorrowedValue::UInteger(*n),
+            Value::BigInt(n) => BorrowedValue::BigInt(n.clone()),
+            Value::Decimal128(d) => BorrowedValue::Decimal128(d.clone()),
+            Value::Uuid(u) => BorrowedValue::Uuid(*u),
+            Value::Date(d) => BorrowedValue::Date(d.clone()),
+            Value::Instant(i) => BorrowedValue::Instant(i.clone()),
+            Value::Duration(d) => BorrowedValue::Duration(d.clone()),
+            Value::Binary(bytes) => BorrowedValue::Binary(std::mem::take(bytes)),
+            Value::String(_) | Value::Array(_) | Value::Object(_) => {
+                unreachable!("the parser borrows strings, arrays and objects itself")
+            }
+        }
+    }
+}
+
+impl From<BorrowedValue<'_>> for Value {
+    fn from(value: BorrowedValue<'_>) -> Value {
+        value.into_owned()
+    }
+}
+
+/// Writes kJSON literals through [`ser::Serializer`](crate::ser::Serializer);
+/// strings are always quoted, even where they look like a UUID or timestamp
+impl Serialize for BorrowedValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BorrowedValue::Null => serializer.serialize_unit(),
+            BorrowedValue::Bool(b) => serializer.serialize_bool(*b),
+            BorrowedValue::Number(n) => serializer.serialize_f64(*n),
+            BorrowedValue::Integer(n) => serializer.serialize_i64(*n),
+            BorrowedValue::UInteger(n) => serializer.serialize_u64(*n),
+            BorrowedValue::String(s) => {
+                serializer.serialize_newtype_struct(crate::ser::QUOTED, s.as_ref())
+            }
+            BorrowedValue::Array(items) => serializer.collect_seq(items),
+            BorrowedValue::Object(members) => {
+                serializer.collect_map(members.iter().map(|(k, v)| (k.as_ref(), v)))
+            }
+            BorrowedValue::BigInt(n) => n.serialize(serializer),
+            BorrowedValue::Decimal128(d) => d.serialize(serializer),
+            BorrowedValue::Uuid(u) => u.serialize(serializer),
+            BorrowedValue::Date(d) => d.serialize(serializer),
+            BorrowedValue::Instant(i) => i.serialize(serializer),
+            BorrowedValue::Duration(d) => d.serialize(serializer),
+            BorrowedValue::Binary(bytes) => serializer.serialize_bytes(bytes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_borrowed;
+
+    #[test]
+    fn test_borrowing() {
+        let text = r#"{ "a\u0062": ['x', "y\"", ``], id: 7n, n: 1.5 }"#;
+        let doc = parse_borrowed(text).unwrap();
+        let BorrowedValue::Object(members) = &doc else {
+            panic!("expected object")
+        };
+        assert!(matches!(&members[0].0, Cow::Owned(k) if k == "ab"));
+        assert!(matches!(&members[1].0, Cow::Borrowed("id")));
+        let items = match doc.get("ab") {
+            Some(BorrowedValue::Array(items)) => items,
+            other => panic!("{:?}", other),
+        };
+        assert!(matches!(&items[0], BorrowedValue::String(Cow::Borrowed("x"))));
+        assert!(matches!(&items[1], BorrowedValue::String(Cow::Owned(s)) if s == "y\""));
+        assert!(matches!(&items[2], BorrowedValue::String(Cow::Borrowed(""))));
+        assert_eq!(doc.get("n"), Some(&BorrowedValue::Number(1.5)));
+        assert_eq!(doc.clone().into_owned(), crate::parse(text).unwrap());
+
+        assert!(parse_borrowed("{ a: 1, }").is_ok());
+        assert!(matches!(parse_borrowed("[1, 2"), Err(crate::Error::UnexpectedEof)));
+        assert!(parse_borrowed("{ a 1 }").is_err());
+        assert!(parse_borrowed("[,]").is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let text = "{b: ['550e8400-e29b-41d4-a716-446655440000', 2n], a: {x: 1.5, x: 2}, c: PT1H}";
+        let doc = parse_borrowed(text).unwrap();
+        let written = crate::ser::to_string(&doc).unwrap();
+        assert_eq!(written, text);
+        assert_eq!(doc.get("a").unwrap().get("x"), Some(&BorrowedValue::Integer(2)));
+        assert_eq!(crate::parse(&written).unwrap(), doc.into_owned());
+    }
+}
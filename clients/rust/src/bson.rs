@@ -0,0 +1,298 @@
+//! BSON / MongoDB interop
+//!
+//! | kJSON | BSON | Decodes as |
+//! |-------|------|------------|
+//! | Number | Int32, Int64 when integral, otherwise Double | Integer, Number |
+//! | Integer | Int32, Int64 | Integer |
+//! | UInteger | Int64 when it fits, otherwise Decimal128 | Integer, Decimal128 |
+//! | BigInt | Int64 when it fits, otherwise Decimal128 | Integer, Decimal128 |
+//! | Decimal128 | Decimal128 | Decimal128 |
+//! | UUID | Binary subtype 4 | UUID |
+//! | Instant, Date | DateTime (millisecond precision, offset dropped) | Instant |
+//! | Duration | ISO 8601 string | String |
+//! | Binary | Binary subtype 0 | Binary |
+//!
+//! BSON Int32/Int64 values decode to Integer, Doubles to Number. ObjectIds
+//! decode to their 24-digit hex string; see
+//! [`ObjectIdPolicy`] for writing them back.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Decimal128, Instant};
+use crate::value::{into_str, ordered_entries, Map, Value};
+use ::bson::oid::ObjectId;
+use ::bson::spec::BinarySubtype;
+use ::bson::{Binary, Bson, DateTime, Document};
+use uuid::Uuid;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: i64 = 1 << 53;
+
+/// How hex strings are mapped back to ObjectIds when writing BSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObjectIdPolicy {
+    /// Strings are always written as BSON strings
+    #[default]
+    String,
+    /// `_id` fields holding a 24-digit hex string are written as ObjectIds
+    IdField,
+}
+
+/// Options for BSON conversion
+#[derive(Debug, Clone, Default)]
+pub struct BsonOptions {
+    /// How ObjectIds are restored when writing BSON
+    pub object_ids: ObjectIdPolicy,
+}
+
+/// Convert a value to BSON
+pub fn to_bson(value: &Value) -> Result<Bson> {
+    to_bson_with(value, &BsonOptions::default())
+}
+
+/// Convert a value to BSON with custom options
+pub fn to_bson_with(value: &Value, options: &BsonOptions) -> Result<Bson> {
+    Ok(match value {
+        Value::Null => Bson::Null,
+        Value::Bool(b) => Bson::Boolean(*b),
+        Value::Number(n) => {
+            if n.fract() == 0.0 && n.abs() < MAX_SAFE_INTEGER as f64 {
+                let i = *n as i64;
+                match i32::try_from(i) {
+                    Ok(i) => Bson::Int32(i),
+                    Err(_) => Bson::Int64(i),
+                }
+            } else {
+                Bson::Double(*n)
+            }
+        }
+        Value::Integer(i) => match i32::try_from(*i) {
+            Ok(i) => Bson::Int32(i),
+            Err(_) => Bson::Int64(*i),
+        },
+        Value::UInteger(n) => match i64::try_from(*n) {
+            Ok(i) => Bson::Int64(i),
+            Err(_) => Bson::Decimal128(decimal(&n.to_string())?),
+        },
+        Value::String(s) => Bson::String(s.to_string()),
+        Value::Array(arr) => Bson::Array(
+            arr.iter()
+                .map(|v| to_bson_with(v, options))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(obj) => Bson::Document(object_to_document(obj, options)?),
+        Value::BigInt(b) => match b.to_i64() {
+            Some(i) => Bson::Int64(i),
+            None => Bson::Decimal128(decimal(&b.to_string())?),
+        },
+        Value::Decimal128(d) => {
+            Bson::Decimal128(decimal(&format!("{}E{}", d.coefficient(), d.exponent()))?)
+        }
+        Value::Uuid(u) => Bson::Binary(Binary {
+            subtype: BinarySubtype::Uuid,
+            bytes: u.as_bytes().to_vec(),
+        }),
+        Value::Date(d) => Bson::DateTime(DateTime::from_millis(d.utc.timestamp_millis())),
+        Value::Instant(i) => Bson::DateTime(DateTime::from_millis(i.epoch_millis())),
+        Value::Duration(d) => Bson::String(d.to_iso8601()),
+        Value::Binary(bytes) => Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: bytes.clone(),
+        }),
+    })
+}
+
+/// Convert an object value to a BSON document
+pub fn to_document(value: &Value) -> Result<Document> {
+    to_document_with(value, &BsonOptions::default())
+}
+
+/// Convert an object value to a BSON document with custom options
+pub fn to_document_with(value: &Value, options: &BsonOptions) -> Result<Document> {
+    match value {
+        Value::Object(obj) => object_to_document(obj, options),
+        other => Err(Error::Conversion(format!(
+            "Only objects convert to BSON documents, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Convert BSON to a value
+pub fn from_bson(bson: Bson) -> Result<Value> {
+    match bson {
+        Bson::Null | Bson::Undefined => Ok(Value::Null),
+        Bson::Boolean(b) => Ok(Value::Bool(b)),
+        Bson::Double(f) => Ok(Value::Number(f)),
+        Bson::Int32(i) => Ok(Value::Integer(i.into())),
+        Bson::Int64(i) => Ok(Value::Integer(i)),
+        Bson::String(s) | Bson::Symbol(s) => Ok(Value::string(s)),
+        Bson::Array(items) => Ok(Value::Array(
+            items.into_iter().map(from_bson).collect::<Result<_>>()?,
+        )),
+        Bson::Document(doc) => from_document(doc),
+        Bson::Decimal128(d) => Ok(Value::Decimal128(parse_decimal(&d.to_string())?)),
+        Bson::Binary(Binary {
+            subtype: BinarySubtype::Uuid,
+            bytes,
+        }) => Uuid::from_slice(&bytes)
+            .map(Value::Uuid)
+            .map_err(|e| Error::Conversion(e.to_string())),
+        Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes,
+        }) => Ok(Value::Binary(bytes)),
+        Bson::ObjectId(oid) => Ok(Value::string(oid.to_hex())),
+        Bson::DateTime(dt) => Ok(Value::Instant(Instant::from_millis(dt.timestamp_millis()))),
+        Bson::Timestamp(ts) => Ok(Value::Instant(Instant::from_seconds(ts.time as i64))),
+        other => Err(Error::Conversion(format!(
+            "BSON {:?} has no kJSON equivalent",
+            other.element_type()
+        ))),
+    }
+}
+
+/// Convert a BSON document to an object value
+pub fn from_document(doc: Document) -> Result<Value> {
+    let mut map = Map::with_capacity(doc.len());
+    for (k, v) in doc {
+        map.insert(into_str(k), from_bson(v)?);
+    }
+    Ok(Value::Object(map))
+}
+
+fn object_to_document(obj: &Map, options: &BsonOptions) -> Result<Document> {
+    let entries = ordered_entries(obj);
+
+    let mut doc = Document::new();
+    for (k, v) in entries {
+        let bson = match (options.object_ids, k.as_str(), v) {
+            (ObjectIdPolicy::IdField, "_id", Value::String(s)) => match ObjectId::parse_str(s) {
+                Ok(oid) => Bson::ObjectId(oid),
+                Err(_) => Bson::String(s.to_string()),
+            },
+            _ => to_bson_with(v, options)?,
+        };
+        doc.insert(k.clone(), bson);
+    }
+    Ok(doc)
+}
+
+fn decimal(s: &str) -> Result<::bson::Decimal128> {
+    s.parse()
+        .map_err(|e| Error::Conversion(format!("{} does not fit BSON Decimal128: {}", s, e)))
+}
+
+/// Parse the string form of a BSON Decimal128, which may use exponent notation
+fn parse_decimal(s: &str) -> Result<Decimal128> {
+    let (mantissa, exponent) = match s.split_once(['E', 'e']) {
+        Some((m, e)) => (
+            m,
+            e.parse::<i32>()
+                .map_err(|_| Error::InvalidDecimal128(s.to_string()))?,
+        ),
+        None => (s, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let coefficient = BigInt::from_str(&format!("{}{}", integer, fraction))
+        .map_err(|_| Error::InvalidDecimal128(s.to_string()))?;
+    Ok(Decimal128::from_parts(
+        &coefficient,
+        exponent - fraction.len() as i32,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_roundtrip_extended_types() {
+        let value = parse(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                big: 123456789012345678901234567890n,
+                wide: 9007199254740993n,
+                key: b64'AQIDBA==',
+                price: 19.99m,
+                tiny: -0.000000123m,
+                created: 2025-01-10T12:00:00.123Z,
+                list: [1, 2.5, null, true, "text"],
+            }"#,
+        )
+        .unwrap();
+        let doc = to_document(&value).unwrap();
+        assert_eq!(
+            doc.get("id").unwrap().element_type(),
+            ::bson::spec::ElementType::Binary
+        );
+        assert_eq!(doc.get_i64("wide").unwrap(), 9007199254740993);
+
+        let decoded = from_document(doc).unwrap();
+        let (decoded, value) = (decoded.as_object().unwrap(), value.as_object().unwrap());
+        assert_eq!(decoded["id"], value["id"]);
+        assert_eq!(decoded["wide"], Value::Integer(9007199254740993));
+        assert_eq!(decoded["key"], Value::Binary(vec![1, 2, 3, 4]));
+        assert_eq!(decoded["price"], value["price"]);
+        assert_eq!(decoded["tiny"], value["tiny"]);
+        assert_eq!(decoded["list"], value["list"]);
+        // BigInts beyond i64 come back as Decimal128 with exponent 0
+        assert_eq!(
+            decoded["big"],
+            Value::Decimal128(Decimal128::from_str("123456789012345678901234567890").unwrap())
+        );
+        assert_eq!(
+            decoded["created"],
+            Value::Instant(Instant::from_millis(1_736_510_400_123))
+        );
+    }
+
+    #[test]
+    fn test_number_widths() {
+        assert_eq!(to_bson(&Value::Number(42.0)).unwrap(), Bson::Int32(42));
+        assert_eq!(
+            to_bson(&Value::Number(1e10)).unwrap(),
+            Bson::Int64(10_000_000_000)
+        );
+        assert_eq!(to_bson(&Value::Number(0.5)).unwrap(), Bson::Double(0.5));
+        assert_eq!(
+            to_bson(&Value::Integer(i64::MAX)).unwrap(),
+            Bson::Int64(i64::MAX)
+        );
+        assert!(matches!(
+            from_bson(Bson::Int64(i64::MAX)).unwrap(),
+            Value::Integer(i64::MAX)
+        ));
+    }
+
+    #[test]
+    fn test_object_id_policy() {
+        let oid = ObjectId::parse_str("65a1b2c3d4e5f60718293a4b").unwrap();
+        let mut doc = Document::new();
+        doc.insert("_id", oid);
+        let value = from_document(doc).unwrap();
+        assert_eq!(
+            value.as_object().unwrap()["_id"],
+            Value::String(oid.to_hex().into())
+        );
+
+        let plain = to_document(&value).unwrap();
+        assert_eq!(plain.get_str("_id").unwrap(), oid.to_hex());
+
+        let options = BsonOptions {
+            object_ids: ObjectIdPolicy::IdField,
+        };
+        let restored = to_document_with(&value, &options).unwrap();
+        assert_eq!(restored.get_object_id("_id").unwrap(), oid);
+    }
+
+    #[test]
+    fn test_decimal_exponent_notation() {
+        let d = "1.2345E+10".parse::<::bson::Decimal128>().unwrap();
+        assert_eq!(
+            from_bson(Bson::Decimal128(d)).unwrap(),
+            Value::Decimal128(Decimal128::from_str("12345000000").unwrap())
+        );
+        assert!(matches!(from_bson(Bson::MaxKey), Err(Error::Conversion(_))));
+    }
+}
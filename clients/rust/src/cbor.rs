@@ -0,0 +1,374 @@
+//! CBOR transcoding
+//!
+//! Extended types are carried with standard CBOR tags so they survive a
+//! round trip through CBOR-based protocols:
+//!
+//! | kJSON | CBOR |
+//! |-------|------|
+//! | BigInt | tag 2 / tag 3 bignum |
+//! | Decimal128 | tag 4 decimal fraction `[exponent, mantissa]` |
+//! | UUID | tag 37 over a 16-byte string |
+//! | Instant | tag 1 epoch seconds, or tag 1001 `{1: seconds, -9: nanoseconds}` when sub-second |
+//! | Duration | tag 1002 `{1: seconds, -9: nanoseconds}` |
+//! | Date | tag 0 RFC 3339 string (keeps the timezone offset) |
+//! | Binary | byte string |
+//!
+//! Integers and integral numbers are written as CBOR integers. CBOR
+//! integers decode to Integer, to UInteger above the i64 range, or to
+//! BigInt below it. Bignums
+//! small enough to fit a CBOR integer are indistinguishable from one once
+//! decoded, so a BigInt within the i64 range comes back as an integer.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{into_str, ordered_entries, Map, Value};
+use ciborium::value::{Integer, Value as Cbor};
+use num_bigint::{BigInt as NumBigInt, Sign};
+use uuid::Uuid;
+
+/// Standard date/time string
+pub const TAG_DATETIME: u64 = 0;
+/// Epoch-based date/time
+pub const TAG_EPOCH: u64 = 1;
+/// Unsigned bignum
+pub const TAG_POS_BIGNUM: u64 = 2;
+/// Negative bignum
+pub const TAG_NEG_BIGNUM: u64 = 3;
+/// Decimal fraction
+pub const TAG_DECIMAL: u64 = 4;
+/// Binary UUID
+pub const TAG_UUID: u64 = 37;
+/// Extended time (RFC 9581)
+pub const TAG_EXTENDED_TIME: u64 = 1001;
+/// Duration (RFC 9581)
+pub const TAG_DURATION: u64 = 1002;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: i128 = 1 << 53;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Encode a value as CBOR bytes
+pub fn to_cbor(value: &Value) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    ciborium::ser::into_writer(&to_cbor_value(value), &mut buf)
+        .map_err(|e| Error::SerializationError(e.to_string()))?;
+    Ok(buf)
+}
+
+/// Decode CBOR bytes into a value
+pub fn from_cbor(bytes: &[u8]) -> Result<Value> {
+    let cbor: Cbor =
+        ciborium::de::from_reader(bytes).map_err(|e| Error::Conversion(e.to_string()))?;
+    from_cbor_value(cbor)
+}
+
+/// Convert a value to a `ciborium` CBOR value
+pub fn to_cbor_value(value: &Value) -> Cbor {
+    match value {
+        Value::Null => Cbor::Null,
+        Value::Bool(b) => Cbor::Bool(*b),
+        Value::Number(n) => {
+            if n.fract() == 0.0 && (n.abs() as i128) < MAX_SAFE_INTEGER {
+                Cbor::Integer(Integer::from(*n as i64))
+            } else {
+                Cbor::Float(*n)
+            }
+        }
+        Value::Integer(n) => Cbor::Integer(Integer::from(*n)),
+        Value::UInteger(n) => Cbor::Integer(Integer::from(*n)),
+        Value::String(s) => Cbor::Text(s.to_string()),
+        Value::Array(arr) => Cbor::Array(arr.iter().map(to_cbor_value).collect()),
+        Value::Object(obj) => {
+            let entries = ordered_entries(obj);
+            Cbor::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (Cbor::Text(k.to_string()), to_cbor_value(v)))
+                    .collect(),
+            )
+        }
+        Value::BigInt(b) => bignum(b),
+        Value::Decimal128(d) => {
+            let coefficient = d.coefficient();
+            let mantissa = match coefficient.to_i64() {
+                Some(n) => Cbor::Integer(Integer::from(n)),
+                None => bignum(&coefficient),
+            };
+            Cbor::Tag(
+                TAG_DECIMAL,
+                Box::new(Cbor::Array(vec![
+                    Cbor::Integer(Integer::from(d.exponent())),
+                    mantissa,
+                ])),
+            )
+        }
+        Value::Uuid(u) => Cbor::Tag(TAG_UUID, Box::new(Cbor::Bytes(u.as_bytes().to_vec()))),
+        Value::Date(d) => Cbor::Tag(TAG_DATETIME, Box::new(Cbor::Text(d.to_iso8601()))),
+        Value::Instant(i) => {
+            let (seconds, nanos) = split_nanos(i.epoch_nanos());
+            if nanos == 0 {
+                Cbor::Tag(TAG_EPOCH, Box::new(Cbor::Integer(Integer::from(seconds))))
+            } else {
+                Cbor::Tag(TAG_EXTENDED_TIME, Box::new(time_map(seconds, nanos)))
+            }
+        }
+        Value::Duration(d) => {
+            let (seconds, nanos) = split_nanos(d.total_nanos());
+            Cbor::Tag(TAG_DURATION, Box::new(time_map(seconds, nanos)))
+        }
+        Value::Binary(bytes) => Cbor::Bytes(bytes.clone()),
+    }
+}
+
+/// Convert a `ciborium` CBOR value to a value
+pub fn from_cbor_value(cbor: Cbor) -> Result<Value> {
+    match cbor {
+        Cbor::Null => Ok(Value::Null),
+        Cbor::Bool(b) => Ok(Value::Bool(b)),
+        Cbor::Float(f) => Ok(Value::Number(f)),
+        Cbor::Integer(i) => match (i64::try_from(i), u64::try_from(i)) {
+            (Ok(n), _) => Ok(Value::Integer(n)),
+            (_, Ok(n)) => Ok(Value::UInteger(n)),
+            _ => Ok(Value::BigInt(BigInt::from_i128(i128::from(i)))),
+        },
+        Cbor::Text(s) => Ok(Value::string(s)),
+        Cbor::Bytes(bytes) => Ok(Value::Binary(bytes)),
+        Cbor::Array(items) => Ok(Value::Array(
+            items
+                .into_iter()
+                .map(from_cbor_value)
+                .collect::<Result<_>>()?,
+        )),
+        Cbor::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (k, v) in entries {
+                let key = match k {
+                    Cbor::Text(key) => key,
+                    other => {
+                        return Err(Error::Conversion(format!(
+                            "CBOR map key must be text, got {:?}",
+                            other
+                        )))
+                    }
+                };
+                map.insert(into_str(key), from_cbor_value(v)?);
+            }
+            Ok(Value::Object(map))
+        }
+        Cbor::Tag(tag, inner) => from_tagged(tag, *inner),
+        other => Err(Error::Conversion(format!(
+            "Unsupported CBOR value: {:?}",
+            other
+        ))),
+    }
+}
+
+fn from_tagged(tag: u64, inner: Cbor) -> Result<Value> {
+    match (tag, inner) {
+        (TAG_DATETIME, Cbor::Text(s)) => Ok(Value::Date(Date::from_iso8601(&s)?)),
+        (TAG_EPOCH, Cbor::Integer(i)) => {
+            let seconds = i64::try_from(i).map_err(|_| tag_error(tag))?;
+            Ok(Value::Instant(Instant::from_nanos(
+                seconds
+                    .checked_mul(NANOS_PER_SECOND)
+                    .ok_or_else(|| tag_error(tag))?,
+            )))
+        }
+        (TAG_EPOCH, Cbor::Float(f)) => Ok(Value::Instant(Instant::from_nanos(
+            (f * NANOS_PER_SECOND as f64).round() as i64,
+        ))),
+        (TAG_POS_BIGNUM, Cbor::Bytes(bytes)) => {
+            Ok(Value::BigInt(BigInt::from_bytes_be(false, &bytes)))
+        }
+        (TAG_NEG_BIGNUM, Cbor::Bytes(bytes)) => {
+            // Tag 3 encodes -1 - n
+            let magnitude = NumBigInt::from_bytes_be(Sign::Plus, &bytes) + 1u32;
+            Ok(Value::BigInt(BigInt::from_bytes_be(
+                true,
+                &magnitude.to_bytes_be().1,
+            )))
+        }
+        (TAG_DECIMAL, Cbor::Array(parts)) if parts.len() == 2 => {
+            let mut parts = parts.into_iter();
+            let exponent = match parts.next() {
+                Some(Cbor::Integer(e)) => i32::try_from(e).map_err(|_| tag_error(tag))?,
+                _ => return Err(tag_error(tag)),
+            };
+            let mantissa = match parts.next().map(from_cbor_value).transpose()?.as_ref() {
+                Some(Value::Integer(n)) => BigInt::from_i64(*n),
+                Some(Value::BigInt(b)) => b.clone(),
+                _ => return Err(tag_error(tag)),
+            };
+            Ok(Value::Decimal128(Decimal128::from_parts(
+                &mantissa, exponent,
+            )))
+        }
+        (TAG_UUID, Cbor::Bytes(bytes)) => Uuid::from_slice(&bytes)
+            .map(Value::Uuid)
+            .map_err(|_| tag_error(tag)),
+        (TAG_EXTENDED_TIME, Cbor::Map(entries)) => Ok(Value::Instant(Instant::from_nanos(
+            read_time_map(tag, entries)?,
+        ))),
+        (TAG_DURATION, Cbor::Map(entries)) => Ok(Value::Duration(Duration::from_nanos(
+            read_time_map(tag, entries)?,
+        ))),
+        // Unknown tags are transparent
+        (_, inner) => from_cbor_value(inner),
+    }
+}
+
+fn bignum(b: &BigInt) -> Cbor {
+    if b.is_negative() {
+        // Tag 3 encodes -1 - n
+        let (_, magnitude) = b.to_bytes_be();
+        let (_, bytes) = (NumBigInt::from_bytes_be(Sign::Plus, &magnitude) - 1u32).to_bytes_be();
+        Cbor::Tag(TAG_NEG_BIGNUM, Box::new(Cbor::Bytes(bytes)))
+    } else {
+        let (_, bytes) = b.to_bytes_be();
+        Cbor::Tag(TAG_POS_BIGNUM, Box::new(Cbor::Bytes(bytes)))
+    }
+}
+
+fn split_nanos(total: i64) -> (i64, i64) {
+    (
+        total.div_euclid(NANOS_PER_SECOND),
+        total.rem_euclid(NANOS_PER_SECOND),
+    )
+}
+
+fn time_map(seconds: i64, nanos: i64) -> Cbor {
+    let mut entries = vec![(
+        Cbor::Integer(Integer::from(1)),
+        Cbor::Integer(Integer::from(seconds)),
+    )];
+    if nanos != 0 {
+        entries.push((
+            Cbor::Integer(Integer::from(-9)),
+            Cbor::Integer(Integer::from(nanos)),
+        ));
+    }
+    Cbor::Map(entries)
+}
+
+fn read_time_map(tag: u64, entries: Vec<(Cbor, Cbor)>) -> Result<i64> {
+    let mut seconds = None;
+    let mut nanos = 0i64;
+    for (k, v) in entries {
+        let key = match k {
+            Cbor::Integer(k) => i128::from(k),
+            _ => return Err(tag_error(tag)),
+        };
+        let value = match v {
+            Cbor::Integer(v) => i64::try_from(v).map_err(|_| tag_error(tag))?,
+            _ => return Err(tag_error(tag)),
+        };
+        match key {
+            1 => seconds = Some(value),
+            -9 => nanos = value,
+            -3 => nanos = value * 1_000_000,
+            -6 => nanos = value * 1_000,
+            _ => {}
+        }
+    }
+    seconds
+        .ok_or_else(|| tag_error(tag))?
+        .checked_mul(NANOS_PER_SECOND)
+        .and_then(|n| n.checked_add(nanos))
+        .ok_or_else(|| tag_error(tag))
+}
+
+fn tag_error(tag: u64) -> Error {
+    Error::Conversion(format!("Malformed content for CBOR tag {}", tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn roundtrip(input: &str) -> Value {
+        let value = parse(input).unwrap();
+        let decoded = from_cbor(&to_cbor(&value).unwrap()).unwrap();
+        assert_eq!(decoded, value, "roundtrip of {}", input);
+        decoded
+    }
+
+    #[test]
+    fn test_roundtrip_extended_types() {
+        roundtrip(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                big: 123456789012345678901234567890n,
+                negative: -123456789012345678901234567890n,
+                price: 19.99m,
+                huge: -12345678901234567890.123456789m,
+                created: 2025-01-10T12:00:00+05:30,
+                ttl: PT1H0.5S,
+                key: b64'AQIDBA==',
+                list: [1, 2.5, null, true, "text"],
+            }"#,
+        );
+        let mut obj = Map::new();
+        obj.insert("at", Value::Instant(Instant::from_nanos(1_736_510_400_123_456_789)));
+        obj.insert("whole", Value::Instant(Instant::from_seconds(-86_400)));
+        let value = Value::Object(obj);
+        assert_eq!(from_cbor(&to_cbor(&value).unwrap()).unwrap(), value);
+
+        let small = parse("-1n").unwrap();
+        assert!(matches!(
+            from_cbor(&to_cbor(&small).unwrap()).unwrap(),
+            Value::Integer(-1)
+        ));
+    }
+
+    #[test]
+    fn test_standard_tags() {
+        let uuid = parse("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let bytes = to_cbor(&uuid).unwrap();
+        // tag 37 (0xd8 0x25), byte string of length 16 (0x50)
+        assert_eq!(&bytes[..3], &[0xd8, 0x25, 0x50]);
+
+        // 2^64 as a tag 2 bignum: 0xc2 0x49 0x01 0x00...
+        let big = parse("18446744073709551616n").unwrap();
+        assert_eq!(
+            to_cbor(&big).unwrap(),
+            vec![0xc2, 0x49, 1, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        // 273.15 as decimal fraction [-2, 27315]
+        let decimal = parse("273.15m").unwrap();
+        assert_eq!(
+            to_cbor(&decimal).unwrap(),
+            vec![0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3]
+        );
+
+        // tag 1 epoch seconds
+        let instant = Value::Instant(Instant::from_seconds(1_363_896_240));
+        assert_eq!(
+            to_cbor(&instant).unwrap(),
+            vec![0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]
+        );
+    }
+
+    #[test]
+    fn test_decode_foreign_cbor() {
+        // u64::MAX as a plain CBOR integer stays exact, and -2^64 decodes
+        // to BigInt
+        let value = from_cbor_value(Cbor::Integer(Integer::from(u64::MAX))).unwrap();
+        assert!(matches!(value, Value::UInteger(u64::MAX)));
+        let min = Integer::try_from(-(1i128 << 64)).unwrap();
+        assert_eq!(
+            from_cbor_value(Cbor::Integer(min)).unwrap(),
+            Value::BigInt(BigInt::from_str("-18446744073709551616").unwrap())
+        );
+
+        // tag 1 with a float keeps sub-second precision
+        let value = from_cbor_value(Cbor::Tag(TAG_EPOCH, Box::new(Cbor::Float(1.5)))).unwrap();
+        assert_eq!(value, Value::Instant(Instant::from_millis(1500)));
+
+        // Non-text map keys are rejected
+        let map = Cbor::Map(vec![(Cbor::Integer(Integer::from(1)), Cbor::Null)]);
+        assert!(matches!(from_cbor_value(map), Err(Error::Conversion(_))));
+    }
+}
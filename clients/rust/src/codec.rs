@@ -0,0 +1,404 @@
+//! tokio-util codecs for framed kJSON
+//!
+//! [`KjsonCodec`] turns a byte stream into [`Value`]s and back, for use
+//! with `tokio_util::codec::Framed` on TCP and Unix sockets.
+//! [`TypedKjsonCodec`] does the same for any serde type:
+//!
+//! ```
+//! use bytes::BytesMut;
+//! use kjson::codec::KjsonCodec;
+//! use tokio_util::codec::{Decoder, Encoder};
+//!
+//! let mut codec = KjsonCodec::new();
+//! let mut buf = BytesMut::new();
+//! codec.encode(kjson::parse("{ id: 7n }")?, &mut buf)?;
+//! assert_eq!(&buf[..], b"{id: 7n}\n");
+//!
+//! buf.extend_from_slice(b"[1, ");
+//! assert_eq!(codec.decode(&mut buf)?, Some(kjson::parse("{ id: 7n }")?));
+//! assert_eq!(codec.decode(&mut buf)?, None); // waiting for the rest
+//! buf.extend_from_slice(b"2]");
+//! assert_eq!(codec.decode(&mut buf)?, Some(kjson::parse("[1, 2]")?));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! With [`Framing::Delimited`] documents follow each other separated by
+//! whitespace, as NDJSON does; the decoder finds where each one ends,
+//! across reads and inside strings and comments. With
+//! [`Framing::LengthPrefixed`] each document is preceded by its length in
+//! bytes as a big-endian `u32`.
+//!
+//! Neither limits frame size by default. For untrusted peers,
+//! [`max_frame_len`](KjsonCodec::max_frame_len) fails with
+//! [`Error::LimitExceeded`] instead of buffering an oversized frame.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::scanner::Scanner;
+use crate::serializer::{to_writer_with, SerializeOptions};
+use crate::value::{from_value, Value};
+use bytes::{Buf, BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Bytes in a [`Framing::LengthPrefixed`] length
+const PREFIX_LEN: usize = 4;
+
+/// How documents are separated on the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Separated by whitespace; the encoder ends each document with `\n`
+    #[default]
+    Delimited,
+    /// Each preceded by its length as a big-endian `u32`
+    LengthPrefixed,
+}
+
+/// Decoder and encoder of kJSON [`Value`]s
+#[derive(Debug, Clone)]
+pub struct KjsonCodec {
+    framing: Framing,
+    max_frame_len: Option<usize>,
+    /// Boundary search through the delimited document being received
+    scanner: Scanner,
+    /// Bytes of the buffer the scanner has seen
+    scanned: usize,
+}
+
+impl Default for KjsonCodec {
+    fn default() -> Self {
+        KjsonCodec::new()
+    }
+}
+
+impl KjsonCodec {
+    /// A codec for whitespace-delimited documents
+    pub fn new() -> Self {
+        KjsonCodec::with_framing(Framing::Delimited)
+    }
+
+    /// A codec for length-prefixed documents
+    pub fn length_prefixed() -> Self {
+        KjsonCodec::with_framing(Framing::LengthPrefixed)
+    }
+
+    /// A codec for documents framed as `framing` says
+    pub fn with_framing(framing: Framing) -> Self {
+        KjsonCodec {
+            framing,
+            max_frame_len: None,
+            scanner: Scanner::new(),
+            scanned: 0,
+        }
+    }
+
+    /// Fail with [`Error::LimitExceeded`] on documents longer than `len`
+    /// bytes
+    pub fn max_frame_len(mut self, len: usize) -> Self {
+        self.max_frame_len = Some(len);
+        self
+    }
+
+    /// The framing in use
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Split the next complete document off the front of `src`
+    fn decode_frame(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        match self.framing {
+            Framing::Delimited => self.decode_delimited(src),
+            Framing::LengthPrefixed => self.decode_length_prefixed(src),
+        }
+    }
+
+    fn decode_delimited(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        match self.scanner.feed(&src[self.scanned..]) {
+            Some(n) => {
+                let frame = src.split_to(self.scanned + n);
+                self.scanner = Scanner::new();
+                self.scanned = 0;
+                self.check_len(frame.len())?;
+                Ok(Some(frame))
+            }
+            None => {
+                if self.scanner.started() {
+                    self.scanned = src.len();
+                    self.check_len(self.scanned)?;
+                } else {
+                    // Only whitespace and comments so far; the scanner
+                    // keeps any state it needs
+                    src.clear();
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_length_prefixed(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        let Some(prefix) = src.get(..PREFIX_LEN) else {
+            return Ok(None);
+        };
+        let len = u32::from_be_bytes(prefix.try_into().expect("four bytes")) as usize;
+        self.check_len(len)?;
+        if src.len() < PREFIX_LEN + len {
+            src.reserve(PREFIX_LEN + len - src.len());
+            return Ok(None);
+        }
+        src.advance(PREFIX_LEN);
+        Ok(Some(src.split_to(len)))
+    }
+
+    /// The rest of the input once the stream has ended, if it holds a
+    /// document
+    fn decode_frame_eof(&mut self, src: &mut BytesMut) -> Result<Option<BytesMut>> {
+        if let Some(frame) = self.decode_frame(src)? {
+            return Ok(Some(frame));
+        }
+        match self.framing {
+            Framing::Delimited if !self.scanner.started() => Ok(None),
+            // A number or literal that no delimiter followed
+            Framing::Delimited if self.scanner.complete_at_eof() => {
+                self.scanner = Scanner::new();
+                self.scanned = 0;
+                Ok(Some(src.split()))
+            }
+            _ if src.is_empty() => Ok(None),
+            _ => Err(Error::UnexpectedEof),
+        }
+    }
+
+    fn check_len(&self, len: usize) -> Result<()> {
+        match self.max_frame_len {
+            Some(limit) if len > limit => Err(Error::LimitExceeded(format!(
+                "frame is longer than {} bytes",
+                limit
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Frame the text that `write` serializes
+    fn encode_with(
+        &self,
+        dst: &mut BytesMut,
+        write: impl FnOnce(&mut Vec<u8>) -> Result<()>,
+    ) -> Result<()> {
+        let mut text = Vec::new();
+        write(&mut text)?;
+        self.check_len(text.len())?;
+        match self.framing {
+            Framing::Delimited => {
+                dst.reserve(text.len() + 1);
+                dst.put_slice(&text);
+                dst.put_u8(b'\n');
+            }
+            Framing::LengthPrefixed => {
+                let len = u32::try_from(text.len()).map_err(|_| {
+                    Error::LimitExceeded("frame is longer than u32::MAX bytes".to_string())
+                })?;
+                dst.reserve(PREFIX_LEN + text.len());
+                dst.put_u32(len);
+                dst.put_slice(&text);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_frame(frame: &[u8]) -> Result<Value> {
+    let text = std::str::from_utf8(frame).map_err(|e| Error::ParseError {
+        position: e.valid_up_to(),
+        message: "Invalid UTF-8".to_string(),
+    })?;
+    parse(text)
+}
+
+impl Decoder for KjsonCodec {
+    type Item = Value;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Value>> {
+        match self.decode_frame(src)? {
+            Some(frame) => parse_frame(&frame).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Value>> {
+        match self.decode_frame_eof(src)? {
+            Some(frame) => parse_frame(&frame).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<Value> for KjsonCodec {
+    type Error = Error;
+
+    fn encode(&mut self, value: Value, dst: &mut BytesMut) -> Result<()> {
+        self.encode(&value, dst)
+    }
+}
+
+impl Encoder<&Value> for KjsonCodec {
+    type Error = Error;
+
+    fn encode(&mut self, value: &Value, dst: &mut BytesMut) -> Result<()> {
+        self.encode_with(dst, |text| {
+            to_writer_with(text, value, &SerializeOptions::default())
+        })
+    }
+}
+
+/// A [`KjsonCodec`] that deserializes each document into `T`
+#[derive(Debug)]
+pub struct TypedKjsonCodec<T> {
+    inner: KjsonCodec,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TypedKjsonCodec<T> {
+    fn clone(&self) -> Self {
+        TypedKjsonCodec::from(self.inner.clone())
+    }
+}
+
+impl<T> Default for TypedKjsonCodec<T> {
+    fn default() -> Self {
+        TypedKjsonCodec::new()
+    }
+}
+
+impl<T> TypedKjsonCodec<T> {
+    /// A codec for whitespace-delimited documents
+    pub fn new() -> Self {
+        TypedKjsonCodec::from(KjsonCodec::new())
+    }
+}
+
+impl<T> From<KjsonCodec> for TypedKjsonCodec<T> {
+    /// Decode and encode `T` with the framing and limit of `codec`
+    fn from(codec: KjsonCodec) -> Self {
+        TypedKjsonCodec {
+            inner: codec,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: for<'de> Deserialize<'de>> Decoder for TypedKjsonCodec<T> {
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        self.inner.decode(src)?.map(from_value).transpose()
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<T>> {
+        self.inner.decode_eof(src)?.map(from_value).transpose()
+    }
+}
+
+/// Encodes any serializable item, so replies need not be the type that
+/// is decoded
+impl<T, U: Serialize> Encoder<U> for TypedKjsonCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: U, dst: &mut BytesMut) -> Result<()> {
+        self.inner
+            .encode_with(dst, |text| crate::ser::to_writer(text, &item))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `input` one byte at a time, then end the stream
+    fn decode_bytewise<D: Decoder<Error = Error>>(codec: &mut D, input: &[u8]) -> Vec<D::Item> {
+        let mut buf = BytesMut::new();
+        let mut items = Vec::new();
+        for &b in input {
+            buf.put_u8(b);
+            while let Some(item) = codec.decode(&mut buf).unwrap() {
+                items.push(item);
+            }
+        }
+        while let Some(item) = codec.decode_eof(&mut buf).unwrap() {
+            items.push(item);
+        }
+        items
+    }
+
+    #[test]
+    fn test_delimited() {
+        let input = "{ a: '}', /* { */ b: [`x\ny`] }\n// note\n12 'é' 2025-01-01T00:00:00Z";
+        let values = decode_bytewise(&mut KjsonCodec::new(), input.as_bytes());
+        let expected: Vec<Value> = crate::stream::StreamReader::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(values.len(), 4);
+        assert_eq!(values, expected);
+
+        let mut codec = KjsonCodec::new();
+        let mut buf = BytesMut::from("[1, 2");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert!(matches!(codec.decode_eof(&mut buf), Err(Error::UnexpectedEof)));
+        let mut buf = BytesMut::from("  // only a comment");
+        assert!(KjsonCodec::new().decode_eof(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let mut codec = KjsonCodec::length_prefixed();
+        let mut buf = BytesMut::new();
+        for text in ["{ id: 1n }", "'two\nlines'", "42"] {
+            codec.encode(parse(text).unwrap(), &mut buf).unwrap();
+        }
+        assert_eq!(&buf[..4], &[0, 0, 0, 8]);
+        let values = decode_bytewise(&mut KjsonCodec::length_prefixed(), &buf);
+        assert_eq!(
+            values,
+            [parse("{ id: 1n }").unwrap(), Value::string("two\nlines"), Value::Integer(42)]
+        );
+
+        let mut buf = BytesMut::from(&[0, 0, 0, 9, b'1'][..]);
+        assert!(matches!(codec.decode_eof(&mut buf), Err(Error::UnexpectedEof)));
+    }
+
+    #[test]
+    fn test_max_frame_len() {
+        let mut codec = KjsonCodec::new().max_frame_len(8);
+        let mut buf = BytesMut::from("'short' 'much longer'");
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert!(codec.decode(&mut buf).unwrap_err().is_limit());
+
+        let mut codec = KjsonCodec::length_prefixed().max_frame_len(8);
+        let mut buf = BytesMut::from(&[0xff, 0xff, 0xff, 0xff][..]);
+        assert!(codec.decode(&mut buf).unwrap_err().is_limit());
+        assert_eq!(buf.capacity(), 4);
+        let long = Value::string("much longer");
+        assert!(codec.encode(&long, &mut BytesMut::new()).unwrap_err().is_limit());
+    }
+
+    #[test]
+    fn test_typed() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Ping {
+            seq: u32,
+        }
+
+        for framing in [Framing::Delimited, Framing::LengthPrefixed] {
+            let mut codec = TypedKjsonCodec::<Ping>::from(KjsonCodec::with_framing(framing));
+            let mut buf = BytesMut::new();
+            codec.encode(Ping { seq: 1 }, &mut buf).unwrap();
+            codec.encode(Ping { seq: 2 }, &mut buf).unwrap();
+            assert_eq!(
+                decode_bytewise(&mut codec, &buf),
+                [Ping { seq: 1 }, Ping { seq: 2 }]
+            );
+        }
+    }
+}
@@ -0,0 +1,534 @@
+//! Code generation from schemas
+//!
+//! Turns a [`Schema`] (written by hand or inferred from sample documents)
+//! into TypeScript declarations or Rust structs.
+
+use crate::error::Result;
+use crate::parser::parse;
+use crate::schema::{Schema, Type};
+use crate::serializer;
+use crate::value::Value;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Package the kJSON JavaScript client is published as
+const TS_PACKAGE: &str = "@atikayda/kjson";
+
+/// Generate TypeScript declarations (`.d.ts`) for a schema.
+///
+/// Extended types use the kJSON JS client's classes: `bigint`, `Decimal128`,
+/// `UUID`, `Instant` and `Duration`. Nested object shapes become their own
+/// interfaces named after their parent and field.
+pub fn typescript(schema: &Schema, root_name: &str) -> String {
+    let mut gen = TsGenerator::default();
+    let root = pascal_case(root_name);
+
+    if is_plain_object(schema) {
+        gen.interface(schema, &root);
+    } else {
+        let ty = gen.type_expr(schema, &root);
+        gen.declarations.insert(0, format!("export type {} = {};\n", root, ty));
+    }
+
+    let mut out = String::from("// Generated by kjson. Do not edit.\n");
+    if !gen.imports.is_empty() {
+        let imports: Vec<_> = gen.imports.iter().copied().collect();
+        out.push_str(&format!(
+            "import type {{ {} }} from \"{}\";\n",
+            imports.join(", "),
+            TS_PACKAGE
+        ));
+    }
+    for decl in &gen.declarations {
+        out.push('\n');
+        out.push_str(decl);
+    }
+    out
+}
+
+#[derive(Default)]
+struct TsGenerator {
+    imports: BTreeSet<&'static str>,
+    declarations: Vec<String>,
+    names: BTreeSet<String>,
+}
+
+impl TsGenerator {
+    /// Emit an interface for an object schema and return its name
+    fn interface(&mut self, schema: &Schema, name: &str) -> String {
+        let name = unique_name(&mut self.names, name);
+        let slot = self.declarations.len();
+        self.declarations.push(String::new());
+
+        let mut body = format!("export interface {} {{\n", name);
+        for (field, field_schema) in &schema.properties {
+            let optional = if schema.required.contains(field) { "" } else { "?" };
+            let ty = self.type_expr(field_schema, &format!("{}{}", name, pascal_case(field)));
+            body.push_str(&format!("  {}{}: {};\n", ts_key(field), optional, ty));
+        }
+        body.push_str("}\n");
+
+        self.declarations[slot] = body;
+        name
+    }
+
+    /// TypeScript type expression for a schema
+    fn type_expr(&mut self, schema: &Schema, name_hint: &str) -> String {
+        if let Some(values) = &schema.enum_values {
+            if let Some(literals) = values.iter().map(ts_literal).collect::<Option<Vec<_>>>() {
+                return literals.join(" | ");
+            }
+        }
+
+        let types: Vec<Type> = if schema.types.is_empty() && !schema.properties.is_empty() {
+            vec![Type::Object]
+        } else {
+            schema.types.clone()
+        };
+        if types.is_empty() || types.contains(&Type::Any) {
+            return "unknown".to_string();
+        }
+
+        let parts: Vec<String> = types
+            .iter()
+            .map(|t| match t {
+                Type::Any => "unknown".to_string(),
+                Type::Null => "null".to_string(),
+                Type::Boolean => "boolean".to_string(),
+                Type::Number | Type::Integer => "number".to_string(),
+                Type::String => "string".to_string(),
+                Type::BigInt => "bigint".to_string(),
+                Type::Decimal => self.import("Decimal128"),
+                Type::Uuid => self.import("UUID"),
+                Type::Instant => self.import("Instant"),
+                Type::Duration => self.import("Duration"),
+                Type::Binary => "Uint8Array".to_string(),
+                Type::Array => match &schema.items {
+                    Some(items) => {
+                        let item = self.type_expr(items, &format!("{}Item", name_hint));
+                        if item.contains(' ') {
+                            format!("({})[]", item)
+                        } else {
+                            format!("{}[]", item)
+                        }
+                    }
+                    None => "unknown[]".to_string(),
+                },
+                Type::Object => {
+                    if schema.properties.is_empty() {
+                        "Record<string, unknown>".to_string()
+                    } else {
+                        self.interface(schema, name_hint)
+                    }
+                }
+            })
+            .collect();
+        parts.join(" | ")
+    }
+
+    fn import(&mut self, name: &'static str) -> String {
+        self.imports.insert(name);
+        name.to_string()
+    }
+}
+
+/// Options for Rust code generation
+#[derive(Debug, Clone)]
+pub struct RustOptions {
+    /// Derives added to every generated struct
+    pub derives: Vec<String>,
+    /// Make structs and fields `pub`
+    pub public: bool,
+}
+
+impl Default for RustOptions {
+    fn default() -> Self {
+        RustOptions {
+            derives: vec![
+                "Debug".to_string(),
+                "Clone".to_string(),
+                "PartialEq".to_string(),
+                "serde::Serialize".to_string(),
+                "serde::Deserialize".to_string(),
+            ],
+            public: true,
+        }
+    }
+}
+
+/// Generate Rust struct definitions for a schema.
+///
+/// Extended types map to `kjson::BigInt`, `kjson::Decimal128`, `kjson::Uuid`,
+/// `kjson::Instant` and `kjson::Duration`; optional or nullable fields become
+/// `Option<T>`, and mixed-type fields fall back to `kjson::Value`.
+pub fn rust(schema: &Schema, root_name: &str, options: &RustOptions) -> String {
+    let mut gen = RustGenerator {
+        options,
+        declarations: Vec::new(),
+        names: BTreeSet::new(),
+    };
+    let root = pascal_case(root_name);
+
+    if is_plain_object(schema) {
+        gen.structure(schema, &root);
+    } else {
+        let ty = gen.type_expr(schema, &root);
+        let vis = if options.public { "pub " } else { "" };
+        gen.declarations.insert(0, format!("{}type {} = {};\n", vis, root, ty));
+    }
+
+    let mut out = String::from("// Generated by kjson. Do not edit.\n");
+    for decl in &gen.declarations {
+        out.push('\n');
+        out.push_str(decl);
+    }
+    out
+}
+
+/// Infer a schema from sample kJSON documents and generate Rust structs for it
+pub fn rust_from_samples(samples: &[&str], root_name: &str, options: &RustOptions) -> Result<String> {
+    let values = samples.iter().map(|s| parse(s)).collect::<Result<Vec<_>>>()?;
+    Ok(rust(&Schema::infer(&values), root_name, options))
+}
+
+/// Build-script helper: generate Rust structs from sample files.
+///
+/// Each input file holds one sample document. The generated module is
+/// written to `out`, and Cargo is told to rerun the build script when a
+/// sample changes.
+///
+/// ```no_run
+/// // build.rs
+/// let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("order.rs");
+/// kjson::codegen::write_rust_module(&["samples/order.kjson"], "Order", &out).unwrap();
+/// ```
+pub fn write_rust_module<P: AsRef<Path>>(samples: &[P], root_name: &str, out: impl AsRef<Path>) -> Result<()> {
+    let mut values = Vec::with_capacity(samples.len());
+    for path in samples {
+        let path = path.as_ref();
+        println!("cargo:rerun-if-changed={}", path.display());
+        values.push(parse(&std::fs::read_to_string(path)?)?);
+    }
+    let code = rust(&Schema::infer(&values), root_name, &RustOptions::default());
+    std::fs::write(out, code)?;
+    Ok(())
+}
+
+struct RustGenerator<'a> {
+    options: &'a RustOptions,
+    declarations: Vec<String>,
+    names: BTreeSet<String>,
+}
+
+impl RustGenerator<'_> {
+    /// Emit a struct for an object schema and return its name
+    fn structure(&mut self, schema: &Schema, name: &str) -> String {
+        let name = unique_name(&mut self.names, name);
+        let slot = self.declarations.len();
+        self.declarations.push(String::new());
+        let vis = if self.options.public { "pub " } else { "" };
+
+        let mut body = String::new();
+        if !self.options.derives.is_empty() {
+            body.push_str(&format!("#[derive({})]\n", self.options.derives.join(", ")));
+        }
+        body.push_str(&format!("{}struct {} {{\n", vis, name));
+        for (field, field_schema) in &schema.properties {
+            let mut ty = self.type_expr(field_schema, &format!("{}{}", name, pascal_case(field)));
+            let ident = rust_ident(field);
+            let mut attrs = Vec::new();
+            if ident.trim_start_matches("r#") != field {
+                attrs.push(format!("rename = {:?}", field));
+            }
+            if !schema.required.contains(field) {
+                if !ty.starts_with("Option<") {
+                    ty = format!("Option<{}>", ty);
+                }
+                attrs.push("default".to_string());
+                attrs.push("skip_serializing_if = \"Option::is_none\"".to_string());
+            }
+            if !attrs.is_empty() {
+                body.push_str(&format!("    #[serde({})]\n", attrs.join(", ")));
+            }
+            body.push_str(&format!("    {}{}: {},\n", vis, ident, ty));
+        }
+        body.push_str("}\n");
+
+        self.declarations[slot] = body;
+        name
+    }
+
+    /// Rust type for a schema
+    fn type_expr(&mut self, schema: &Schema, name_hint: &str) -> String {
+        let mut types: Vec<Type> = if schema.types.is_empty() && !schema.properties.is_empty() {
+            vec![Type::Object]
+        } else {
+            schema.types.clone()
+        };
+        let nullable = types.contains(&Type::Null);
+        types.retain(|t| *t != Type::Null);
+
+        let inner = match types.as_slice() {
+            [Type::Boolean] => "bool".to_string(),
+            [Type::Integer] => "i64".to_string(),
+            [Type::Number] => "f64".to_string(),
+            [Type::String] => "String".to_string(),
+            [Type::BigInt] => "kjson::BigInt".to_string(),
+            [Type::Decimal] => "kjson::Decimal128".to_string(),
+            [Type::Uuid] => "kjson::Uuid".to_string(),
+            [Type::Instant] => "kjson::Instant".to_string(),
+            [Type::Duration] => "kjson::Duration".to_string(),
+            [Type::Binary] => "Vec<u8>".to_string(),
+            [Type::Array] => match &schema.items {
+                Some(items) => format!("Vec<{}>", self.type_expr(items, &format!("{}Item", name_hint))),
+                None => "Vec<kjson::Value>".to_string(),
+            },
+            [Type::Object] if !schema.properties.is_empty() => self.structure(schema, name_hint),
+            [Type::Object] => "std::collections::HashMap<String, kjson::Value>".to_string(),
+            _ => "kjson::Value".to_string(),
+        };
+
+        if nullable && inner != "kjson::Value" {
+            format!("Option<{}>", inner)
+        } else {
+            inner
+        }
+    }
+}
+
+fn unique_name(names: &mut BTreeSet<String>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while !names.insert(candidate.clone()) {
+        candidate = format!("{}{}", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// Convert a key to a snake_case Rust identifier
+fn rust_ident(key: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for ch in key.chars() {
+        if ch.is_alphanumeric() {
+            if ch.is_uppercase() && prev_lower {
+                out.push('_');
+            }
+            out.extend(ch.to_lowercase());
+            prev_lower = ch.is_lowercase() || ch.is_numeric();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+    let out = out.trim_end_matches('_').to_string();
+    if out.is_empty() || out.starts_with(|c: char| c.is_numeric()) {
+        format!("_{}", out)
+    } else if RUST_KEYWORDS.contains(&out.as_str()) {
+        if matches!(out.as_str(), "self" | "super" | "crate" | "Self") {
+            format!("{}_", out)
+        } else {
+            format!("r#{}", out)
+        }
+    } else {
+        out
+    }
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type",
+    "unsafe", "use", "where", "while", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+fn is_plain_object(schema: &Schema) -> bool {
+    schema.enum_values.is_none()
+        && (schema.types == [Type::Object]
+            || (schema.types.is_empty() && !schema.properties.is_empty()))
+}
+
+fn ts_literal(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("null".to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(_) | Value::Integer(_) | Value::UInteger(_) => {
+            serializer::to_string(value).ok()
+        }
+        Value::String(s) => serde_json::to_string(s).ok(),
+        _ => None,
+    }
+}
+
+fn ts_key(key: &str) -> String {
+    let mut chars = key.chars();
+    let is_ident = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    };
+    if is_ident {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_else(|_| format!("\"{}\"", key))
+    }
+}
+
+/// Convert a field or file name to PascalCase
+pub(crate) fn pascal_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut upper = true;
+    for ch in name.chars() {
+        if ch.is_alphanumeric() {
+            if upper {
+                out.extend(ch.to_uppercase());
+                upper = false;
+            } else {
+                out.push(ch);
+            }
+        } else {
+            upper = true;
+        }
+    }
+    if out.chars().next().is_none_or(|c| c.is_numeric()) {
+        out.insert(0, 'T');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_typescript_from_schema() {
+        let schema = Schema::parse(
+            r#"{
+                type: "object",
+                required: ["id", "price", "items"],
+                properties: {
+                    id: { type: "uuid" },
+                    price: { type: "decimal" },
+                    total: { type: "bigint" },
+                    status: { type: "string", enum: ["open", "closed"] },
+                    "created-at": { type: ["instant", "null"] },
+                    items: {
+                        type: "array",
+                        items: { type: "object", properties: { sku: { type: "string" } } },
+                    },
+                },
+            }"#,
+        )
+        .unwrap();
+
+        let expected = r#"// Generated by kjson. Do not edit.
+import type { Decimal128, Instant, UUID } from "@atikayda/kjson";
+
+export interface Order {
+  "created-at"?: Instant | null;
+  id: UUID;
+  items: OrderItemsItem[];
+  price: Decimal128;
+  status?: "open" | "closed";
+  total?: bigint;
+}
+
+export interface OrderItemsItem {
+  sku?: string;
+}
+"#;
+        assert_eq!(typescript(&schema, "order"), expected);
+    }
+
+    #[test]
+    fn test_typescript_from_inferred_schema() {
+        let samples = vec![parse("[1, 2.5, PT1H]").unwrap()];
+        let ts = typescript(&Schema::infer(&samples), "samples");
+        assert!(ts.contains("import type { Duration } from \"@atikayda/kjson\";"));
+        assert!(ts.contains("export type Samples = (number | Duration)[];"));
+    }
+
+    #[test]
+    fn test_rust_from_samples() {
+        let samples = [
+            r#"{
+                orderId: 550e8400-e29b-41d4-a716-446655440000,
+                total: 19.99m,
+                sequence: 12345678901234567890n,
+                created: 2025-01-10T12:00:00Z,
+                type: "retail",
+                lines: [{ sku: "A-1", qty: 2 }],
+                note: null,
+            }"#,
+            r#"{
+                orderId: 550e8400-e29b-41d4-a716-446655440001,
+                total: 5m,
+                sequence: 1n,
+                created: 2025-01-11T12:00:00Z,
+                type: "online",
+                lines: [],
+                note: "leave at door",
+                ttl: PT1H,
+            }"#,
+        ];
+        let expected = r#"// Generated by kjson. Do not edit.
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Order {
+    pub created: kjson::Instant,
+    pub lines: Vec<OrderLinesItem>,
+    pub note: Option<String>,
+    #[serde(rename = "orderId")]
+    pub order_id: kjson::Uuid,
+    pub sequence: kjson::BigInt,
+    pub total: kjson::Decimal128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<kjson::Duration>,
+    pub r#type: String,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OrderLinesItem {
+    pub qty: i64,
+    pub sku: String,
+}
+"#;
+        assert_eq!(
+            rust_from_samples(&samples, "order", &RustOptions::default()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_rust_options_and_fallbacks() {
+        let options = RustOptions {
+            derives: vec![],
+            public: false,
+        };
+        let code = rust_from_samples(&["[1, \"two\"]"], "mixed", &options).unwrap();
+        assert_eq!(code, "// Generated by kjson. Do not edit.\n\ntype Mixed = Vec<kjson::Value>;\n");
+        assert!(rust_from_samples(&["{"], "broken", &options).is_err());
+    }
+
+    #[test]
+    fn test_rust_ident() {
+        assert_eq!(rust_ident("orderId"), "order_id");
+        assert_eq!(rust_ident("created-at"), "created_at");
+        assert_eq!(rust_ident("type"), "r#type");
+        assert_eq!(rust_ident("self"), "self_");
+        assert_eq!(rust_ident("2fa"), "_2fa");
+    }
+
+    #[test]
+    fn test_pascal_case() {
+        assert_eq!(pascal_case("order_items"), "OrderItems");
+        assert_eq!(pascal_case("created-at"), "CreatedAt");
+        assert_eq!(pascal_case("2fa"), "T2fa");
+    }
+}
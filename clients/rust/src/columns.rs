@@ -0,0 +1,138 @@
+//! Shared helpers for converting arrays of objects to columnar formats
+
+use crate::error::{Error, Result};
+use crate::types::BigInt;
+use crate::value::{Str, Value};
+use std::collections::BTreeSet;
+
+/// Kind of values found in a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Kind {
+    Bool,
+    Number,
+    Integer,
+    UInteger,
+    String,
+    Decimal,
+    BigInt,
+    Uuid,
+    Timestamp,
+    Duration,
+    Binary,
+    Nested,
+}
+
+fn kind_of(value: &Value) -> Option<Kind> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(Kind::Bool),
+        Value::Number(_) => Some(Kind::Number),
+        Value::Integer(_) => Some(Kind::Integer),
+        Value::UInteger(_) => Some(Kind::UInteger),
+        Value::String(_) => Some(Kind::String),
+        Value::Decimal128(_) => Some(Kind::Decimal),
+        Value::BigInt(_) => Some(Kind::BigInt),
+        Value::Uuid(_) => Some(Kind::Uuid),
+        Value::Instant(_) | Value::Date(_) => Some(Kind::Timestamp),
+        Value::Duration(_) => Some(Kind::Duration),
+        Value::Binary(_) => Some(Kind::Binary),
+        Value::Array(_) | Value::Object(_) => Some(Kind::Nested),
+    }
+}
+
+/// Kind shared by every non-null cell, `Nested` if they differ, `None` if all are null.
+///
+/// Integers mixed with other numbers make a `Number` column.
+pub(crate) fn column_kind(cells: &[&Value]) -> Option<Kind> {
+    let mut kinds = cells.iter().filter_map(|v| kind_of(v));
+    let first = kinds.next()?;
+    let shared = kinds.try_fold(first, |shared, kind| match (shared, kind) {
+        _ if shared == kind => Some(shared),
+        (Kind::Integer | Kind::UInteger, Kind::Number)
+        | (Kind::Number, Kind::Integer | Kind::UInteger)
+        | (Kind::Integer, Kind::UInteger)
+        | (Kind::UInteger, Kind::Integer) => Some(Kind::Number),
+        _ => None,
+    });
+    Some(shared.unwrap_or(Kind::Nested))
+}
+
+/// Cell of a row without the column
+static NULL: Value = Value::Null;
+
+/// A named column of cells, one per row
+pub(crate) type Column<'a> = (&'a str, Vec<&'a Value>);
+
+/// Split an array of objects into columns sorted by key, filling missing keys with null.
+///
+/// Returns the row count alongside the columns.
+pub(crate) fn split_rows<'a>(value: &'a Value, target: &str) -> Result<(usize, Vec<Column<'a>>)> {
+    let rows = value
+        .as_array()
+        .ok_or_else(|| Error::Conversion(format!("{} input must be an array", target)))?;
+    let mut objects = Vec::with_capacity(rows.len());
+    for row in rows {
+        objects.push(row.as_object().ok_or_else(|| {
+            Error::Conversion(format!(
+                "{} rows must be objects, got {}",
+                target,
+                row.type_name()
+            ))
+        })?);
+    }
+
+    let names: BTreeSet<&str> = objects
+        .iter()
+        .flat_map(|obj| obj.keys().map(Str::as_str))
+        .collect();
+    let columns = names
+        .into_iter()
+        .map(|name| {
+            let cells = objects
+                .iter()
+                .map(|obj| obj.get(name).unwrap_or(&NULL))
+                .collect();
+            (name, cells)
+        })
+        .collect();
+    Ok((objects.len(), columns))
+}
+
+/// Rescale a decimal column to its widest scale.
+///
+/// Returns the scale, each cell's unscaled integer as a digit string (so the
+/// value is `unscaled * 10^-scale`), and the most digits any cell needs.
+pub(crate) fn unscaled_decimals(cells: &[&Value]) -> (i32, Vec<Option<String>>, usize) {
+    let scale = cells
+        .iter()
+        .filter_map(|v| v.as_decimal128())
+        .map(|d| -d.exponent())
+        .max()
+        .unwrap_or(0)
+        .max(0);
+    let unscaled: Vec<Option<String>> = cells
+        .iter()
+        .map(|v| {
+            v.as_decimal128().map(|d| {
+                let padding = (scale + d.exponent()) as usize;
+                format!("{}{}", d.coefficient(), "0".repeat(padding))
+            })
+        })
+        .collect();
+    let digits = unscaled
+        .iter()
+        .flatten()
+        .map(|s| s.trim_start_matches('-').len())
+        .max()
+        .unwrap_or(0);
+    (scale, unscaled, digits)
+}
+
+/// Integers above the i64 range become UInteger, beyond the u64 range BigInt
+pub(crate) fn integer(n: i128) -> Value {
+    match (i64::try_from(n), u64::try_from(n)) {
+        (Ok(n), _) => Value::Integer(n),
+        (_, Ok(n)) => Value::UInteger(n),
+        _ => Value::BigInt(BigInt::from_i128(n)),
+    }
+}
@@ -0,0 +1,445 @@
+//! Layered configuration loading
+//!
+//! A [`ConfigLoader`] reads kJSON sources in order, applies environment
+//! variable overrides, deep-merges the result and deserializes it:
+//!
+//! ```no_run
+//! #[derive(serde::Deserialize)]
+//! struct Settings {
+//!     db: Db,
+//! }
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Db {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let settings: Settings = kjson::config::ConfigLoader::new()
+//!     .file("config/base.kjson")
+//!     .optional_file("config/local.kjson")
+//!     .env_prefix("APP")
+//!     .load()?;
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Objects merge key by key; any other value, arrays included, replaces the
+//! earlier one, as in [`Value::deep_merge`]. With the prefix `APP`, the
+//! variable `APP__DB__PORT=5432` overrides `/db/port`: segments are split on
+//! `__` and lowercased, and the value is parsed as kJSON, falling back to a
+//! plain string.
+//!
+//! With [`ConfigLoader::schema`], the merged value is validated before it is
+//! deserialized, and all violations are reported together.
+//!
+//! With [`ConfigLoader::includes`], sources may pull in other files through
+//! `$include` directives, see [`crate::include`].
+//!
+//! With [`ConfigLoader::substitute`], `${VAR}` references in the string
+//! values of each source are expanded as described in [`crate::substitute`];
+//! override values from the environment are taken literally.
+//!
+//! Errors name the source or the path that failed, e.g.
+//! `Configuration error: /db/port: invalid type: string "x", expected u16`.
+
+use crate::error::{Error, Result};
+use crate::include::{self, FileAccess};
+use crate::parser::parse;
+use crate::pointer;
+use crate::schema::Schema;
+use crate::substitute::{self, Resolver};
+use crate::value::{kjson_value_to_json_value, Map, Str, Value};
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Default separator between path segments in variable names
+const ENV_SEPARATOR: &str = "__";
+
+/// A configuration source
+#[derive(Debug, Clone)]
+enum Source {
+    File { path: PathBuf, required: bool },
+    Text { name: String, text: String },
+}
+
+/// Loads and merges configuration sources
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    sources: Vec<Source>,
+    env_prefix: Option<String>,
+    env_separator: Option<String>,
+    env_vars: Option<Vec<(String, String)>>,
+    resolver: Option<SharedResolver>,
+    access: Option<SharedAccess>,
+    schema: Option<Schema>,
+}
+
+/// Resolver shared between clones of a loader
+#[derive(Clone)]
+struct SharedResolver(Arc<dyn Resolver + Send + Sync>);
+
+impl fmt::Debug for SharedResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedResolver")
+    }
+}
+
+/// File access shared between clones of a loader
+#[derive(Clone)]
+struct SharedAccess(Arc<dyn FileAccess + Send + Sync>);
+
+impl fmt::Debug for SharedAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedAccess")
+    }
+}
+
+impl ConfigLoader {
+    /// Create a loader with no sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file that must exist
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: true,
+        });
+        self
+    }
+
+    /// Add a file that is skipped if it does not exist
+    pub fn optional_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.sources.push(Source::File {
+            path: path.into(),
+            required: false,
+        });
+        self
+    }
+
+    /// Add kJSON text, e.g. built-in defaults; `name` appears in errors
+    pub fn text(mut self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        self.sources.push(Source::Text {
+            name: name.into(),
+            text: text.into(),
+        });
+        self
+    }
+
+    /// Apply overrides from variables starting with `prefix` and the separator
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Use another separator than `__` in variable names
+    pub fn env_separator(mut self, separator: impl Into<String>) -> Self {
+        self.env_separator = Some(separator.into());
+        self
+    }
+
+    /// Read overrides from these variables instead of the process environment
+    pub fn env_vars<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env_vars = Some(
+            vars.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// Validate the merged configuration against `schema`
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Expand `$include` directives, reading included files through `access`
+    pub fn includes(mut self, access: impl FileAccess + Send + Sync + 'static) -> Self {
+        self.access = Some(SharedAccess(Arc::new(access)));
+        self
+    }
+
+    /// Expand `${VAR}` references in the sources through `resolver`
+    pub fn substitute(mut self, resolver: impl Resolver + Send + Sync + 'static) -> Self {
+        self.resolver = Some(SharedResolver(Arc::new(resolver)));
+        self
+    }
+
+    /// Load and merge all sources
+    pub fn load_value(&self) -> Result<Value> {
+        let mut config = Value::Object(Map::new());
+        for source in &self.sources {
+            if let Some(value) = self.read_source(source)? {
+                config.deep_merge(value);
+            }
+        }
+        if let Some(prefix) = &self.env_prefix {
+            self.apply_env(&mut config, prefix);
+        }
+        if let Some(schema) = &self.schema {
+            let violations = schema.validate(&config);
+            if !violations.is_empty() {
+                let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+                return Err(Error::Config(messages.join("; ")));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Load, merge and deserialize all sources
+    pub fn load<T: DeserializeOwned>(&self) -> Result<T> {
+        deserialize(self.load_value()?)
+    }
+
+    /// Paths of the file sources
+    #[cfg(feature = "notify")]
+    pub(crate) fn files(&self) -> impl Iterator<Item = &Path> {
+        self.sources.iter().filter_map(|source| match source {
+            Source::File { path, .. } => Some(path.as_path()),
+            Source::Text { .. } => None,
+        })
+    }
+
+    fn apply_env(&self, config: &mut Value, prefix: &str) {
+        let separator = self.env_separator.as_deref().unwrap_or(ENV_SEPARATOR);
+        let start = format!("{}{}", prefix, separator);
+        let vars = match &self.env_vars {
+            Some(vars) => vars.clone(),
+            None => std::env::vars().collect(),
+        };
+        let mut overrides: Vec<(Vec<String>, String)> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let rest = name.strip_prefix(&start)?;
+                let segments: Vec<String> =
+                    rest.split(separator).map(|s| s.to_lowercase()).collect();
+                if segments.iter().any(|s| s.is_empty()) {
+                    return None;
+                }
+                Some((segments, value))
+            })
+            .collect();
+        // Shorter paths first, so `APP__DB` does not clobber `APP__DB__PORT`
+        overrides.sort();
+        for (segments, text) in overrides {
+            let value = parse(&text).unwrap_or(Value::string(text));
+            set_path(config, &segments, value);
+        }
+    }
+
+    fn read_source(&self, source: &Source) -> Result<Option<Value>> {
+        match source {
+            Source::File { path, required } => match std::fs::read_to_string(path) {
+                Ok(text) => self
+                    .parse_source(&text, path)
+                    .map(Some)
+                    .map_err(|e| Error::Config(format!("{}: {}", path.display(), e))),
+                Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(Error::Config(format!("{}: {}", path.display(), e))),
+            },
+            Source::Text { name, text } => self
+                .parse_source(text, Path::new(name))
+                .map(Some)
+                .map_err(|e| Error::Config(format!("{}: {}", name, e))),
+        }
+    }
+
+    /// Parse a source; includes are relative to the directory of `path`
+    fn parse_source(&self, text: &str, path: &Path) -> Result<Value> {
+        let mut value = parse(text)?;
+        if let Some(SharedAccess(access)) = &self.access {
+            include::expand_file(&mut value, path, access.as_ref())?;
+        }
+        if let Some(SharedResolver(resolver)) = &self.resolver {
+            substitute::substitute(&mut value, resolver.as_ref())?;
+        }
+        Ok(value)
+    }
+}
+
+/// Set a value at a path of object keys, creating objects along the way
+fn set_path(config: &mut Value, segments: &[String], value: Value) {
+    let mut current = config;
+    for segment in segments {
+        if current.as_object().is_none() {
+            *current = Value::Object(Map::new());
+        }
+        let Value::Object(map) = current else {
+            unreachable!()
+        };
+        current = map.entry(Str::from(segment.as_str())).or_insert(Value::Null);
+    }
+    *current = value;
+}
+
+/// Deserialize, naming the JSON Pointer of the field that failed
+pub(crate) fn deserialize<T: DeserializeOwned>(value: Value) -> Result<T> {
+    let json = kjson_value_to_json_value(value)?;
+    serde_path_to_error::deserialize(json).map_err(|e| {
+        let mut path = String::new();
+        for segment in e.path().iter() {
+            path = match segment {
+                Segment::Seq { index } => pointer::push_index(&path, *index),
+                Segment::Map { key } => pointer::push(&path, key),
+                Segment::Enum { variant } => pointer::push(&path, variant),
+                Segment::Unknown => pointer::push(&path, "?"),
+            };
+        }
+        let path = if path.is_empty() { "/" } else { &path };
+        Error::Config(format!("{}: {}", path, e.inner()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        name: String,
+        db: Db,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Db {
+        host: String,
+        port: u16,
+    }
+
+    const BASE: &str = "{
+        name: 'shop',
+        db: { host: 'localhost', port: 5432 },
+        tags: ['a', 'b'],
+    }";
+
+    #[test]
+    fn test_layers_merge_in_order() {
+        let settings: Settings = ConfigLoader::new()
+            .text("base", BASE)
+            .text("local", "{ db: { host: 'db.internal' }, tags: ['c'] }")
+            .optional_file("/nonexistent/kjson-config.kjson")
+            .load()
+            .unwrap();
+        assert_eq!(settings.name, "shop");
+        assert_eq!(settings.db.host, "db.internal");
+        assert_eq!(settings.db.port, 5432);
+        assert_eq!(settings.tags, ["c"]);
+
+        let missing = ConfigLoader::new()
+            .file("/nonexistent/kjson-config.kjson")
+            .load_value();
+        assert!(matches!(missing, Err(Error::Config(m)) if m.starts_with("/nonexistent")));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let settings: Settings = ConfigLoader::new()
+            .text("base", BASE)
+            .env_prefix("APP")
+            .env_vars([
+                ("APP__DB__PORT", "6543"),
+                ("APP__NAME", "shop 2"),
+                ("APP__TAGS", "['x', 'y']"),
+                ("OTHER__NAME", "ignored"),
+            ])
+            .load()
+            .unwrap();
+        assert_eq!(settings.db.port, 6543);
+        assert_eq!(settings.name, "shop 2");
+        assert_eq!(settings.tags, ["x", "y"]);
+    }
+
+    #[test]
+    fn test_path_qualified_errors() {
+        let err = ConfigLoader::new()
+            .text("base", BASE)
+            .env_prefix("APP")
+            .env_vars([("APP__DB__PORT", "high")])
+            .load::<Settings>()
+            .unwrap_err();
+        match err {
+            Error::Config(message) => assert!(message.starts_with("/db/port: "), "{}", message),
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let err = ConfigLoader::new().text("defaults", "{ a: ").load_value();
+        assert!(matches!(err, Err(Error::Config(m)) if m.starts_with("defaults: ")));
+    }
+
+    #[test]
+    fn test_schema() {
+        use crate::schema::Type;
+
+        let schema = Schema::object().field("name", Type::String).field(
+            "db",
+            Schema::object().field("port", Type::Number.max(65535.0)),
+        );
+        let loader = ConfigLoader::new().text("base", BASE).schema(schema);
+        assert!(loader.load::<Settings>().is_ok());
+
+        let err = loader
+            .text("local", "{ name: 1, db: { port: 70000 } }")
+            .load_value()
+            .unwrap_err();
+        match err {
+            Error::Config(message) => {
+                assert!(message.contains("/name: "), "{}", message);
+                assert!(message.contains("/db/port: "), "{}", message);
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_includes() {
+        let access = |path: &Path| match path.to_str() {
+            Some("conf/db.kjson") => Ok("{ host: 'localhost', port: 5432 }".to_string()),
+            _ => Err(Error::Config(format!("{}: not allowed", path.display()))),
+        };
+        let settings: Settings = ConfigLoader::new()
+            .text(
+                "conf/app.kjson",
+                "{ name: 'shop', db: { $include: 'db.kjson' }, tags: [] }",
+            )
+            .includes(access)
+            .load()
+            .unwrap();
+        assert_eq!(settings.db.host, "localhost");
+        assert_eq!(settings.db.port, 5432);
+    }
+
+    #[test]
+    fn test_substitution() {
+        let resolver = |name: &str| (name == "DB_HOST").then(|| "db.internal".to_string());
+        let loader = ConfigLoader::new()
+            .text(
+                "base",
+                "{ name: '${NAME:-shop}', db: { host: '${DB_HOST}', port: 5432 }, tags: [] }",
+            )
+            .env_prefix("APP")
+            .env_vars([("APP__TAGS", "['${DB_HOST}']")])
+            .substitute(resolver);
+        let settings: Settings = loader.load().unwrap();
+        assert_eq!(settings.name, "shop");
+        assert_eq!(settings.db.host, "db.internal");
+        assert_eq!(settings.tags, ["${DB_HOST}"]);
+
+        let err = loader
+            .text("local", "{ db: { host: '${DB_PASSWORD}' } }")
+            .load_value();
+        assert!(
+            matches!(err, Err(Error::Config(m)) if m.starts_with("local: Substitution error: /db/host"))
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Converters normalizing upstream data into domain types
+//!
+//! Messy sources spell the same thing many ways: country names instead of
+//! codes, big integers as strings. A [`Converters`] registry holds the
+//! fixes in one place and applies them before values reach consumers.
+//! Converters are selected by object key, with `*` matching any run of
+//! characters, or by a `convert` annotation in a [`Schema`]:
+//!
+//! ```
+//! use kjson::convert::Converters;
+//! use kjson::schema::Schema;
+//! use kjson::{BigInt, Value};
+//!
+//! let schema = Schema::parse("{ properties: { origin: { convert: 'country' } } }")?;
+//! let converters = Converters::new()
+//!     .field("*_id", |value| match &value {
+//!         Value::String(s) => Ok(Value::BigInt(BigInt::from_str(s.trim_end_matches('n'))?)),
+//!         _ => Ok(value),
+//!     })
+//!     .annotation("country", |value| match value.as_str() {
+//!         Some("Germany") => Ok(Value::string("DE")),
+//!         _ => Ok(value),
+//!     })
+//!     .schema(schema);
+//!
+//! let value = converters.parse("{ origin: 'Germany', order_id: '42n' }")?;
+//! assert_eq!(value, kjson::parse("{ origin: 'DE', order_id: 42n }")?);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Nested values are converted before the values containing them, and a
+//! value selected both ways goes through its annotation first. Errors
+//! from a converter are reported as [`Error::Conversion`] with the JSON
+//! Pointer of the value.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::pointer;
+use crate::schema::Schema;
+use crate::value::{from_value, Value};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+type ConvertFn = Box<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+
+struct FieldRule {
+    pattern: String,
+    matcher: Regex,
+    convert: ConvertFn,
+}
+
+/// Registry of converters applied to values by key or schema annotation
+#[derive(Default)]
+pub struct Converters {
+    fields: Vec<FieldRule>,
+    annotations: HashMap<String, ConvertFn>,
+    schema: Option<Schema>,
+}
+
+impl Converters {
+    /// Registry without converters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert object values whose key matches `pattern`, where `*`
+    /// matches any run of characters. When several patterns match a key,
+    /// the first registered wins.
+    pub fn field<F>(mut self, pattern: &str, convert: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        let glob = regex::escape(pattern).replace(r"\*", ".*");
+        self.fields.push(FieldRule {
+            pattern: pattern.to_string(),
+            matcher: Regex::new(&format!("^{}$", glob)).expect("escaped glob is a valid regex"),
+            convert: Box::new(convert),
+        });
+        self
+    }
+
+    /// Convert values whose schema is annotated with `convert: name`
+    pub fn annotation<F>(mut self, name: &str, convert: F) -> Self
+    where
+        F: Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.annotations.insert(name.to_string(), Box::new(convert));
+        self
+    }
+
+    /// Schema whose `convert` annotations select converters
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Convert `value` in place
+    pub fn apply(&self, value: &mut Value) -> Result<()> {
+        self.apply_at(value, self.schema.as_ref(), None, "")
+    }
+
+    /// Convert a value, then deserialize it
+    pub fn from_value<T>(&self, mut value: Value) -> Result<T>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.apply(&mut value)?;
+        from_value(value)
+    }
+
+    /// Parse kJSON text and convert the result
+    pub fn parse(&self, input: &str) -> Result<Value> {
+        let mut value = parse(input)?;
+        self.apply(&mut value)?;
+        Ok(value)
+    }
+
+    fn apply_at(
+        &self,
+        value: &mut Value,
+        schema: Option<&Schema>,
+        key: Option<&str>,
+        path: &str,
+    ) -> Result<()> {
+        match value {
+            Value::Array(items) => {
+                let items_schema = schema.and_then(|s| s.items.as_deref());
+                for (i, item) in items.iter_mut().enumerate() {
+                    self.apply_at(item, items_schema, None, &pointer::push_index(path, i))?;
+                }
+            }
+            Value::Object(map) => {
+                for (name, item) in map.iter_mut() {
+                    let item_schema = schema.and_then(|s| s.properties.get(name.as_str()));
+                    let item_path = pointer::push(path, name);
+                    self.apply_at(item, item_schema, Some(name), &item_path)?;
+                }
+            }
+            _ => {}
+        }
+
+        let annotated = match schema.and_then(|s| s.convert.as_deref()) {
+            Some(name) => Some(self.annotations.get(name).ok_or_else(|| {
+                conversion_error(path, format!("no converter for annotation '{}'", name))
+            })?),
+            None => None,
+        };
+        let by_key = key.and_then(|key| self.fields.iter().find(|rule| rule.matcher.is_match(key)));
+        for convert in annotated
+            .into_iter()
+            .chain(by_key.map(|rule| &rule.convert))
+        {
+            let input = std::mem::replace(value, Value::Null);
+            *value = convert(input).map_err(|e| conversion_error(path, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Converters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<_> = self.fields.iter().map(|rule| &rule.pattern).collect();
+        let mut annotations: Vec<_> = self.annotations.keys().collect();
+        annotations.sort();
+        f.debug_struct("Converters")
+            .field("fields", &fields)
+            .field("annotations", &annotations)
+            .field("schema", &self.schema)
+            .finish()
+    }
+}
+
+fn conversion_error(path: &str, message: impl fmt::Display) -> Error {
+    if path.is_empty() {
+        Error::Conversion(message.to_string())
+    } else {
+        Error::Conversion(format!("{}: {}", path, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BigInt;
+
+    fn upper(value: Value) -> Result<Value> {
+        match value.as_str() {
+            Some(s) => Ok(Value::string(s.to_uppercase())),
+            None => Err(Error::TypeMismatch {
+                expected: "string".to_string(),
+                actual: value.type_name().to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_field_patterns() {
+        let converters = Converters::new()
+            .field("*_code", upper)
+            .field("count", |value| match &value {
+                Value::String(s) => Ok(Value::BigInt(BigInt::from_str(s)?)),
+                _ => Ok(value),
+            });
+        let value = converters
+            .parse("{ country_code: 'de', lines: [{ count: '7', code: 'x' }], codes: 'y' }")
+            .unwrap();
+        assert_eq!(
+            value,
+            parse("{ country_code: 'DE', lines: [{ count: 7n, code: 'x' }], codes: 'y' }").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_schema_annotations() {
+        let schema = Schema::object().field(
+            "items",
+            Schema::array(Schema::object().field("sku", Schema::any().convert("upper"))),
+        );
+        let converters = Converters::new().annotation("upper", upper).schema(schema);
+        let value = converters
+            .parse("{ items: [{ sku: 'ab' }, { sku: 'cd' }] }")
+            .unwrap();
+        assert_eq!(
+            value,
+            parse("{ items: [{ sku: 'AB' }, { sku: 'CD' }] }").unwrap()
+        );
+
+        let unregistered = Converters::new().schema(Schema::any().convert("missing"));
+        assert!(matches!(unregistered.parse("1"), Err(Error::Conversion(_))));
+    }
+
+    #[test]
+    fn test_errors_carry_path() {
+        let converters = Converters::new().field("code", upper);
+        match converters.parse("{ lines: [{ code: 1 }] }") {
+            Err(Error::Conversion(message)) => assert!(message.starts_with("/lines/0/code: ")),
+            other => panic!("expected a conversion error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_value() {
+        #[derive(Deserialize)]
+        struct Order {
+            currency_code: String,
+        }
+        let converters = Converters::new().field("*_code", upper);
+        let order: Order = converters
+            .from_value(parse("{ currency_code: 'eur' }").unwrap())
+            .unwrap();
+        assert_eq!(order.currency_code, "EUR");
+    }
+}
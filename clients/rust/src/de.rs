@@ -0,0 +1,657 @@
+//! Native serde deserializer for [`Value`]
+//!
+//! Values are deserialized directly rather than through `serde_json::Value`,
+//! keeping track of where in the document each value sits, so a failure is
+//! reported as [`Error::Deserialize`] with the JSON Pointer of the value and
+//! its kJSON type: "expected u64 at /users/3/age, found string".
+//!
+//! Rust types see values as they would see the same document as JSON:
+//! whole numbers are integers, extended types are their kJSON literals
+//! (`"12n"`, `"1.5m"`, UUID and ISO 8601 strings) and binary data is a
+//! sequence of bytes, or a byte buffer for types asking for bytes.
+//! [`BigInt`](crate::BigInt), [`Decimal128`](crate::Decimal128),
+//! [`Instant`](crate::Instant), [`Duration`](crate::Duration) and
+//! [`Uuid`](crate::Uuid) parse those literals back, so fields of these
+//! types deserialize without loss.
+
+use crate::error::Error;
+use crate::pointer;
+use crate::value::{into_string, Map, Str, Value};
+use serde::de::value::{SeqDeserializer, StrDeserializer, StringDeserializer};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, Expected, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, VariantAccess, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Where the value being deserialized sits, kept on the stack while
+/// descending and only rendered as a pointer when something fails
+#[derive(Debug, Clone, Copy)]
+enum Path<'a> {
+    Root,
+    Key(&'a Path<'a>, &'a str),
+    Index(&'a Path<'a>, usize),
+}
+
+impl Path<'_> {
+    fn pointer(&self) -> String {
+        match self {
+            Path::Root => String::new(),
+            Path::Key(parent, key) => pointer::push(&parent.pointer(), key),
+            Path::Index(parent, i) => pointer::push_index(&parent.pointer(), *i),
+        }
+    }
+}
+
+/// Error raised while deserializing, located at the innermost value
+/// that failed
+#[derive(Debug)]
+pub(crate) struct DeError {
+    message: String,
+    found: Option<String>,
+    path: Option<String>,
+}
+
+impl DeError {
+    fn locate(mut self, path: &Path<'_>, found: &str) -> Self {
+        if self.path.is_none() {
+            self.path = Some(path.pointer());
+            if self.found.is_some() {
+                self.found = Some(found.to_string());
+            }
+        }
+        self
+    }
+}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError {
+            message: msg.to_string(),
+            found: None,
+            path: None,
+        }
+    }
+
+    fn invalid_type(unexp: Unexpected<'_>, exp: &dyn Expected) -> Self {
+        DeError {
+            message: format!("expected {}", exp),
+            found: Some(unexp.to_string()),
+            path: None,
+        }
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<DeError> for Error {
+    fn from(e: DeError) -> Self {
+        Error::Deserialize {
+            path: e.path.unwrap_or_default(),
+            message: e.message,
+            found: e.found,
+        }
+    }
+}
+
+/// Deserialize a `T` from a value
+pub(crate) fn from_value<T>(value: Value) -> Result<T, Error>
+where
+    T: for<'de> de::Deserialize<'de>,
+{
+    Ok(T::deserialize(ValueDeserializer {
+        value,
+        path: &Path::Root,
+    })?)
+}
+
+struct ValueDeserializer<'a> {
+    value: Value,
+    path: &'a Path<'a>,
+}
+
+impl<'a> ValueDeserializer<'a> {
+    fn any<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let path = self.path;
+        let mut value = self.value;
+        match &mut value {
+            Value::Null => visitor.visit_unit(),
+            Value::Bool(b) => visitor.visit_bool(*b),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+                if *n >= 0.0 {
+                    visitor.visit_u64(*n as u64)
+                } else {
+                    visitor.visit_i64(*n as i64)
+                }
+            }
+            Value::Number(n) => visitor.visit_f64(*n),
+            Value::Integer(n) => match u64::try_from(*n) {
+                Ok(n) => visitor.visit_u64(n),
+                Err(_) => visitor.visit_i64(*n),
+            },
+            Value::UInteger(n) => visitor.visit_u64(*n),
+            Value::String(s) => visitor.visit_string(into_string(std::mem::take(s))),
+            Value::Array(items) => {
+                let mut seq = SeqAccessor {
+                    items: std::mem::take(items).into_iter(),
+                    index: 0,
+                    path,
+                };
+                let result = visitor.visit_seq(&mut seq)?;
+                match seq.items.len() {
+                    0 => Ok(result),
+                    left => Err(de::Error::invalid_length(
+                        seq.index + left,
+                        &"fewer elements in array",
+                    )),
+                }
+            }
+            Value::Object(map) => {
+                let mut access = MapAccessor {
+                    entries: std::mem::take(map).into_iter(),
+                    pending: None,
+                    path,
+                };
+                let result = visitor.visit_map(&mut access)?;
+                match access.entries.len() {
+                    0 => Ok(result),
+                    _ => Err(de::Error::custom("expected fewer entries in map")),
+                }
+            }
+            Value::BigInt(b) => visitor.visit_string(b.to_kjson_string()),
+            Value::Decimal128(d) => visitor.visit_string(d.to_kjson_string()),
+            Value::Uuid(u) => visitor.visit_string(u.to_string()),
+            Value::Date(d) => visitor.visit_string(d.to_iso8601()),
+            Value::Instant(i) => visitor.visit_string(i.to_iso8601()),
+            Value::Duration(d) => visitor.visit_string(d.to_iso8601()),
+            Value::Binary(bytes) => {
+                let mut seq = SeqDeserializer::new(std::mem::take(bytes).into_iter());
+                let result = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(result)
+            }
+        }
+    }
+
+    fn enumeration<'de, V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut value = self.value;
+        match &mut value {
+            Value::String(s) => {
+                let variant: StringDeserializer<DeError> =
+                    into_string(std::mem::take(s)).into_deserializer();
+                visitor.visit_enum(variant)
+            }
+            Value::Object(map) if map.len() == 1 => {
+                let (variant, value) = std::mem::take(map)
+                    .into_iter()
+                    .next()
+                    .expect("map has one entry");
+                visitor.visit_enum(EnumAccessor {
+                    variant,
+                    value,
+                    path: self.path,
+                })
+            }
+            Value::Object(_) => Err(de::Error::invalid_value(
+                Unexpected::Map,
+                &"map with a single key",
+            )),
+            other => Err(de::Error::invalid_type(unexpected(other), &"string or map")),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let (path, found) = (self.path, self.value.type_name());
+        self.any(visitor).map_err(|e| e.locate(path, found))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let (path, found) = (self.path, self.value.type_name());
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+        .map_err(|e| e.locate(path, found))
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let (path, found) = (self.path, self.value.type_name());
+        visitor
+            .visit_newtype_struct(self)
+            .map_err(|e| e.locate(path, found))
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let (path, found) = (self.path, self.value.type_name());
+        self.enumeration(visitor).map_err(|e| e.locate(path, found))
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let (path, found) = (self.path, self.value.type_name());
+        let mut value = self.value;
+        match &mut value {
+            Value::Binary(bytes) => visitor.visit_byte_buf(std::mem::take(bytes)),
+            _ => ValueDeserializer { value, path }.any(visitor),
+        }
+        .map_err(|e| e.locate(path, found))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        drop(self);
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqAccessor<'a> {
+    items: std::vec::IntoIter<Value>,
+    index: usize,
+    path: &'a Path<'a>,
+}
+
+impl<'de> SeqAccess<'de> for SeqAccessor<'_> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        let Some(value) = self.items.next() else {
+            return Ok(None);
+        };
+        let path = Path::Index(self.path, self.index);
+        self.index += 1;
+        seed.deserialize(ValueDeserializer { value, path: &path })
+            .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapAccessor<'a> {
+    entries: <Map as IntoIterator>::IntoIter,
+    pending: Option<(Str, Value)>,
+    path: &'a Path<'a>,
+}
+
+impl<'de> MapAccess<'de> for MapAccessor<'_> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        let Some(entry) = self.entries.next() else {
+            return Ok(None);
+        };
+        let (key, _) = self.pending.insert(entry);
+        let path = Path::Key(self.path, key);
+        seed.deserialize(KeyDeserializer { key, path: &path })
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let (key, value) = self
+            .pending
+            .take()
+            .ok_or_else(|| de::Error::custom("value requested before key"))?;
+        let path = Path::Key(self.path, &key);
+        seed.deserialize(ValueDeserializer { value, path: &path })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Object keys, parsed for key types such as integers as serde_json does
+struct KeyDeserializer<'a> {
+    key: &'a str,
+    path: &'a Path<'a>,
+}
+
+macro_rules! deserialize_parsed_key {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {$(
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+            match self.key.parse::<$ty>() {
+                Ok(parsed) => visitor.$visit(parsed),
+                Err(_) => visitor.visit_str(self.key),
+            }
+            .map_err(|e: DeError| e.locate(self.path, "string"))
+        }
+    )*};
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'_> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor
+            .visit_str(self.key)
+            .map_err(|e: DeError| e.locate(self.path, "string"))
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool(bool),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_i128 => visit_i128(i128),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_u128 => visit_u128(u128),
+        deserialize_f32 => visit_f32(f32),
+        deserialize_f64 => visit_f64(f64),
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let variant: StrDeserializer<DeError> = self.key.into_deserializer();
+        visitor
+            .visit_enum(variant)
+            .map_err(|e: DeError| e.locate(self.path, "string"))
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct EnumAccessor<'a> {
+    variant: Str,
+    value: Value,
+    path: &'a Path<'a>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumAccessor<'a> {
+    type Error = DeError;
+    type Variant = VariantAccessor<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccessor<'a>), DeError> {
+        let name: StrDeserializer<DeError> = self.variant.as_str().into_deserializer();
+        let variant = seed
+            .deserialize(name)
+            .map_err(|e| e.locate(&Path::Key(self.path, &self.variant), "string"))?;
+        Ok((
+            variant,
+            VariantAccessor {
+                variant: self.variant,
+                value: self.value,
+                path: self.path,
+            },
+        ))
+    }
+}
+
+struct VariantAccessor<'a> {
+    variant: Str,
+    value: Value,
+    path: &'a Path<'a>,
+}
+
+impl<'de> VariantAccess<'de> for VariantAccessor<'_> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        match self.value {
+            Value::Null => Ok(()),
+            ref other => {
+                let error: DeError = de::Error::invalid_type(unexpected(other), &"unit variant");
+                Err(error.locate(&Path::Key(self.path, &self.variant), other.type_name()))
+            }
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, DeError> {
+        let path = Path::Key(self.path, &self.variant);
+        seed.deserialize(ValueDeserializer {
+            value: self.value,
+            path: &path,
+        })
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, DeError> {
+        let path = Path::Key(self.path, &self.variant);
+        de::Deserializer::deserialize_any(
+            ValueDeserializer {
+                value: self.value,
+                path: &path,
+            },
+            visitor,
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.tuple_variant(0, visitor)
+    }
+}
+
+/// How serde names a value in type errors
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::Bool(b) => Unexpected::Bool(*b),
+        Value::Number(n) => Unexpected::Float(*n),
+        Value::Integer(n) => Unexpected::Signed(*n),
+        Value::UInteger(n) => Unexpected::Unsigned(*n),
+        Value::String(s) => Unexpected::Str(s),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Object(_) => Unexpected::Map,
+        Value::Binary(bytes) => Unexpected::Bytes(bytes),
+        other => Unexpected::Other(other.type_name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct User {
+        name: String,
+        age: u64,
+        #[serde(default)]
+        role: Option<Role>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Role {
+        Admin,
+        Guest { until: String },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Team {
+        users: Vec<User>,
+    }
+
+    fn error<T: for<'de> Deserialize<'de> + fmt::Debug>(text: &str) -> String {
+        from_value::<T>(parse(text).unwrap()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let team: Team = from_value(
+            parse("{ users: [{ name: 'a', age: 3 }, { name: 'b', age: 4, role: 'admin' }] }")
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(team.users[1].role, Some(Role::Admin));
+
+        let guest: Role = from_value(parse("{ guest: { until: 2025-01-10T12:00:00Z } }").unwrap())
+            .unwrap();
+        assert_eq!(
+            guest,
+            Role::Guest {
+                until: "2025-01-10T12:00:00Z".to_string()
+            }
+        );
+
+        let keyed: BTreeMap<u32, Vec<u8>> =
+            from_value(parse("{ '1': b64'AQI=', '2': [3] }").unwrap()).unwrap();
+        assert_eq!(keyed[&1], [1, 2]);
+        let literals: Vec<String> = from_value(parse("[7n, 1.5m, PT1H]").unwrap()).unwrap();
+        assert_eq!(literals, ["7n", "1.5m", "PT1H"]);
+    }
+
+    #[test]
+    fn test_u64_range() {
+        assert_eq!(crate::from_str::<u64>("18446744073709551615").unwrap(), u64::MAX);
+        let value = crate::to_value([u64::MAX, 1 << 63, 7]).unwrap();
+        assert_eq!(value[0], Value::UInteger(u64::MAX));
+        let text = crate::to_string(&value).unwrap();
+        assert_eq!(text, "[18446744073709551615, 9223372036854775808, 7]");
+        assert_eq!(crate::from_str::<Vec<u64>>(&text).unwrap(), [u64::MAX, 1 << 63, 7]);
+        assert_eq!(
+            error::<i64>("9223372036854775808"),
+            "invalid value: integer `9223372036854775808`, expected i64"
+        );
+    }
+
+    #[test]
+    fn test_extended_types() {
+        #[derive(Debug, Deserialize)]
+        struct Account {
+            id: crate::Uuid,
+            balance: crate::BigInt,
+            price: crate::Decimal128,
+            opened: crate::Instant,
+            ttl: crate::Duration,
+            small: crate::BigInt,
+        }
+
+        let account: Account = from_value(
+            parse(
+                "{
+                    id: 550e8400-e29b-41d4-a716-446655440000,
+                    balance: 123456789012345678901234567890n,
+                    price: 19.990m,
+                    opened: 2025-01-10T12:00:00.123456789+02:00,
+                    ttl: PT1H30M,
+                    small: 42,
+                }",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(account.id.to_string(), "550e8400-e29b-41d4-a716-446655440000");
+        assert_eq!(account.balance.to_kjson_string(), "123456789012345678901234567890n");
+        assert_eq!(account.price.to_kjson_string(), "19.990m");
+        assert_eq!(account.opened.to_iso8601(), "2025-01-10T10:00:00.123456789Z");
+        assert_eq!(account.ttl.to_iso8601(), "PT1H30M");
+        assert_eq!(account.small, crate::BigInt::from_i64(42));
+
+        let balance: crate::BigInt = serde_json::from_str("\"-7n\"").unwrap();
+        assert_eq!(balance, crate::BigInt::from_i64(-7));
+        assert_eq!(
+            error::<crate::BigInt>("1.5"),
+            "invalid value: floating point `1.5`, expected an integer"
+        );
+        assert_eq!(
+            error::<crate::Instant>("{ at: 1 }"),
+            "expected an ISO 8601 timestamp, found object"
+        );
+    }
+
+    #[test]
+    fn test_errors_carry_path_and_type() {
+        assert_eq!(
+            error::<Team>("{ users: [{ name: 'a', age: 3 }, { name: 'b', age: 'old' }] }"),
+            "expected u64 at /users/1/age, found string"
+        );
+        assert_eq!(
+            error::<Team>("{ users: [{ name: 'a', age: 1.5m }] }"),
+            "expected u64 at /users/0/age, found decimal128"
+        );
+        assert_eq!(
+            error::<Team>("{ users: [{ age: 3 }] }"),
+            "missing field `name` at /users/0"
+        );
+        assert_eq!(
+            error::<Team>("{ users: [{ name: 'a', age: 3, role: 'root' }] }"),
+            "unknown variant `root`, expected `admin` or `guest` at /users/0/role"
+        );
+        assert_eq!(error::<u8>("300"), "invalid value: integer `300`, expected u8");
+        assert_eq!(
+            error::<BTreeMap<u32, bool>>("{ x: true }"),
+            "expected u32 at /x, found string"
+        );
+
+        match from_value::<Team>(parse("{ users: {} }").unwrap()) {
+            Err(Error::Deserialize {
+                path,
+                message,
+                found,
+            }) => {
+                assert_eq!(path, "/users");
+                assert_eq!(message, "expected a sequence");
+                assert_eq!(found.as_deref(), Some("object"));
+            }
+            other => panic!("expected a deserialize error, got {:?}", other),
+        }
+    }
+}
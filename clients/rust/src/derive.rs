@@ -0,0 +1,745 @@
+//! Conversion between Rust types and [`Value`] without serde
+//!
+//! [`KjsonSerialize`] and [`KjsonDeserialize`] convert a type to and from a
+//! [`Value`]. They are implemented for the primitive, collection and
+//! extended types, and with the `derive` feature
+//! `#[derive(KjsonSerialize, KjsonDeserialize)]` implements them for
+//! structs and enums:
+//!
+//! ```ignore
+//! use kjson::{KjsonDeserialize, KjsonSerialize};
+//!
+//! #[derive(KjsonSerialize, KjsonDeserialize)]
+//! #[kjson(rename_all = "camelCase")]
+//! struct Account {
+//!     #[kjson(as = "bigint")]
+//!     account_id: u64,
+//!     #[kjson(as = "decimal")]
+//!     balance: String,
+//!     #[kjson(as = "instant")]
+//!     opened_at: std::time::SystemTime,
+//!     #[kjson(default)]
+//!     tags: Vec<String>,
+//! }
+//!
+//! let account = Account::from_kjson(&kjson::parse(
+//!     "{ accountId: 12n, balance: 10.50m, openedAt: 2025-01-10T12:00:00Z }",
+//! )?)?;
+//! assert_eq!(
+//!     kjson::to_canonical_string(&account.to_kjson()?)?,
+//!     r#"{"accountId":12n,"balance":10.50m,"openedAt":2025-01-10T12:00:00Z,"tags":[]}"#
+//! );
+//! ```
+//!
+//! Container attributes are `rename_all` (`"lowercase"`, `"UPPERCASE"`,
+//! `"camelCase"`, `"PascalCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`,
+//! `"kebab-case"`) and `deny_unknown_fields`. Field attributes are
+//! `rename = "..."`, `skip`, `default`, and `as = "..."`, which writes the
+//! field as a `bigint`, `decimal`, `instant` or `duration` literal through
+//! [`AsBigInt`], [`AsDecimal`], [`AsInstant`] or [`AsDuration`], also
+//! inside an `Option`.
+//!
+//! Structs become objects, newtype structs their field, tuple structs
+//! arrays, unit variants strings and other variants `{ Variant: ... }`.
+//! A missing field is `None` for options and an error otherwise, unless it
+//! has `default`. Failures are [`Error::Deserialize`] with the pointer of
+//! the offending value.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use chrono::{DateTime, FixedOffset, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: i128 = 9_007_199_254_740_992;
+
+/// A type that converts to a [`Value`]
+pub trait KjsonSerialize {
+    /// The value representing `self`
+    fn to_kjson(&self) -> Result<Value>;
+}
+
+/// A type that converts from a [`Value`]
+pub trait KjsonDeserialize: Sized {
+    /// Convert `value`, failing with [`Error::Deserialize`] if it does not fit
+    fn from_kjson(value: &Value) -> Result<Self>;
+}
+
+/// A type written as a BigInt literal with `#[kjson(as = "bigint")]`
+pub trait AsBigInt: Sized {
+    /// `self` as a BigInt
+    fn to_bigint(&self) -> Result<BigInt>;
+    /// Convert a BigInt, failing if it does not fit
+    fn from_bigint(n: &BigInt) -> Result<Self>;
+}
+
+/// A type written as a Decimal128 literal with `#[kjson(as = "decimal")]`
+pub trait AsDecimal: Sized {
+    /// `self` as a Decimal128
+    fn to_decimal(&self) -> Result<Decimal128>;
+    /// Convert a Decimal128, failing if it does not fit
+    fn from_decimal(d: &Decimal128) -> Result<Self>;
+}
+
+/// A type written as an Instant literal with `#[kjson(as = "instant")]`
+pub trait AsInstant: Sized {
+    /// `self` as an Instant
+    fn to_instant(&self) -> Result<Instant>;
+    /// Convert an Instant, failing if it does not fit
+    fn from_instant(i: &Instant) -> Result<Self>;
+}
+
+/// A type written as a Duration literal with `#[kjson(as = "duration")]`
+pub trait AsDuration: Sized {
+    /// `self` as a Duration
+    fn to_duration(&self) -> Result<Duration>;
+    /// Convert a Duration, failing if it does not fit
+    fn from_duration(d: &Duration) -> Result<Self>;
+}
+
+/// A type error for `value`, which should have been `expected`
+fn mismatch(expected: &str, value: &Value) -> Error {
+    Error::Deserialize {
+        path: String::new(),
+        message: format!("expected {}", expected),
+        found: Some(value.type_name().to_string()),
+    }
+}
+
+fn invalid(message: impl std::fmt::Display) -> Error {
+    Error::Deserialize {
+        path: String::new(),
+        message: message.to_string(),
+        found: None,
+    }
+}
+
+fn out_of_range(value: &Value, ty: &str) -> Error {
+    let text = crate::to_string(value).unwrap_or_default();
+    invalid(format!("{} is out of range for {}", text, ty))
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {$(
+        impl KjsonSerialize for $ty {
+            fn to_kjson(&self) -> Result<Value> {
+                let n = *self as i128;
+                Ok(match (i64::try_from(n), u64::try_from(n)) {
+                    (Ok(n), _) => Value::Integer(n),
+                    (_, Ok(n)) => Value::UInteger(n),
+                    _ => Value::BigInt(BigInt::from_i128(n)),
+                })
+            }
+        }
+
+        impl KjsonDeserialize for $ty {
+            fn from_kjson(value: &Value) -> Result<Self> {
+                let n = match value {
+                    Value::Integer(n) => Some(*n as i128),
+                    Value::UInteger(n) => Some(*n as i128),
+                    Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER as f64 => {
+                        Some(*n as i128)
+                    }
+                    Value::BigInt(n) => n.to_i128(),
+                    _ => return Err(mismatch(stringify!($ty), value)),
+                };
+                n.and_then(|n| <$ty>::try_from(n).ok())
+                    .ok_or_else(|| out_of_range(value, stringify!($ty)))
+            }
+        }
+
+        impl AsBigInt for $ty {
+            fn to_bigint(&self) -> Result<BigInt> {
+                Ok(BigInt::from_i128(*self as i128))
+            }
+
+            fn from_bigint(n: &BigInt) -> Result<Self> {
+                n.to_i128()
+                    .and_then(|n| <$ty>::try_from(n).ok())
+                    .ok_or_else(|| invalid(format!("{} is out of range for {}", n, stringify!($ty))))
+            }
+        }
+
+        impl AsDecimal for $ty {
+            fn to_decimal(&self) -> Result<Decimal128> {
+                Decimal128::from_str(&self.to_string())
+            }
+
+            fn from_decimal(d: &Decimal128) -> Result<Self> {
+                d.to_string()
+                    .parse()
+                    .map_err(|_| invalid(format!("{} is not a valid {}", d, stringify!($ty))))
+            }
+        }
+    )*};
+}
+
+impl_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, i128);
+
+impl KjsonSerialize for u128 {
+    fn to_kjson(&self) -> Result<Value> {
+        match i128::try_from(*self) {
+            Ok(n) => n.to_kjson(),
+            Err(_) => Ok(Value::BigInt(self.to_bigint()?)),
+        }
+    }
+}
+
+impl KjsonDeserialize for u128 {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        match value {
+            Value::BigInt(n) => Self::from_bigint(n),
+            _ => u64::from_kjson(value).map(u128::from),
+        }
+    }
+}
+
+impl AsBigInt for u128 {
+    fn to_bigint(&self) -> Result<BigInt> {
+        BigInt::from_str(&self.to_string())
+    }
+
+    fn from_bigint(n: &BigInt) -> Result<Self> {
+        n.to_string()
+            .parse()
+            .map_err(|_| invalid(format!("{} is out of range for u128", n)))
+    }
+}
+
+macro_rules! impl_float {
+    ($($ty:ty),*) => {$(
+        impl KjsonSerialize for $ty {
+            fn to_kjson(&self) -> Result<Value> {
+                Ok(Value::Number(*self as f64))
+            }
+        }
+
+        impl KjsonDeserialize for $ty {
+            fn from_kjson(value: &Value) -> Result<Self> {
+                match value {
+                    Value::Number(n) => Ok(*n as $ty),
+                    Value::Integer(n) => Ok(*n as $ty),
+                    Value::UInteger(n) => Ok(*n as $ty),
+                    _ => Err(mismatch(stringify!($ty), value)),
+                }
+            }
+        }
+
+        impl AsDecimal for $ty {
+            fn to_decimal(&self) -> Result<Decimal128> {
+                if !self.is_finite() {
+                    return Err(invalid(format!("{} is not a decimal number", self)));
+                }
+                Decimal128::from_str(&self.to_string())
+            }
+
+            fn from_decimal(d: &Decimal128) -> Result<Self> {
+                Ok(d.to_f64() as $ty)
+            }
+        }
+    )*};
+}
+
+impl_float!(f32, f64);
+
+impl KjsonSerialize for bool {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(Value::Bool(*self))
+    }
+}
+
+impl KjsonDeserialize for bool {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        value.as_bool().ok_or_else(|| mismatch("a boolean", value))
+    }
+}
+
+impl KjsonSerialize for str {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(Value::string(self))
+    }
+}
+
+impl KjsonSerialize for String {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(Value::string(self.as_str()))
+    }
+}
+
+impl KjsonDeserialize for String {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            _ => Err(mismatch("a string", value)),
+        }
+    }
+}
+
+/// Strings hold the literal's text, such as `"12"` for `12n`
+impl AsBigInt for String {
+    fn to_bigint(&self) -> Result<BigInt> {
+        BigInt::from_str(self)
+    }
+
+    fn from_bigint(n: &BigInt) -> Result<Self> {
+        Ok(n.to_string())
+    }
+}
+
+impl AsDecimal for String {
+    fn to_decimal(&self) -> Result<Decimal128> {
+        Decimal128::from_str(self)
+    }
+
+    fn from_decimal(d: &Decimal128) -> Result<Self> {
+        Ok(d.to_string())
+    }
+}
+
+impl AsInstant for String {
+    fn to_instant(&self) -> Result<Instant> {
+        Instant::from_iso8601(self)
+    }
+
+    fn from_instant(i: &Instant) -> Result<Self> {
+        Ok(i.to_iso8601())
+    }
+}
+
+impl AsDuration for String {
+    fn to_duration(&self) -> Result<Duration> {
+        Duration::from_iso8601(self)
+    }
+
+    fn from_duration(d: &Duration) -> Result<Self> {
+        Ok(d.to_iso8601())
+    }
+}
+
+impl AsInstant for SystemTime {
+    fn to_instant(&self) -> Result<Instant> {
+        let nanos = match self.duration_since(UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_nanos()).ok(),
+            Err(e) => i64::try_from(e.duration().as_nanos()).ok().map(|n| -n),
+        };
+        nanos
+            .map(Instant::from_nanos)
+            .ok_or_else(|| invalid("time is out of range for an instant"))
+    }
+
+    fn from_instant(i: &Instant) -> Result<Self> {
+        let offset = std::time::Duration::from_nanos(i.nanoseconds.unsigned_abs());
+        Ok(if i.nanoseconds >= 0 {
+            UNIX_EPOCH + offset
+        } else {
+            UNIX_EPOCH - offset
+        })
+    }
+}
+
+impl AsInstant for DateTime<Utc> {
+    fn to_instant(&self) -> Result<Instant> {
+        self.timestamp_nanos_opt()
+            .map(Instant::from_nanos)
+            .ok_or_else(|| invalid(format!("{} is out of range for an instant", self)))
+    }
+
+    fn from_instant(i: &Instant) -> Result<Self> {
+        Ok(i.to_datetime())
+    }
+}
+
+impl AsInstant for DateTime<FixedOffset> {
+    fn to_instant(&self) -> Result<Instant> {
+        self.to_utc().to_instant()
+    }
+
+    fn from_instant(i: &Instant) -> Result<Self> {
+        Ok(i.to_datetime().fixed_offset())
+    }
+}
+
+impl AsDuration for std::time::Duration {
+    fn to_duration(&self) -> Result<Duration> {
+        i64::try_from(self.as_nanos())
+            .map(Duration::from_nanos)
+            .map_err(|_| invalid("duration is out of range"))
+    }
+
+    fn from_duration(d: &Duration) -> Result<Self> {
+        u64::try_from(d.nanoseconds)
+            .map(std::time::Duration::from_nanos)
+            .map_err(|_| invalid(format!("{} is negative", d)))
+    }
+}
+
+impl AsDuration for chrono::Duration {
+    fn to_duration(&self) -> Result<Duration> {
+        self.num_nanoseconds()
+            .map(Duration::from_nanos)
+            .ok_or_else(|| invalid("duration is out of range"))
+    }
+
+    fn from_duration(d: &Duration) -> Result<Self> {
+        Ok(chrono::Duration::nanoseconds(d.nanoseconds))
+    }
+}
+
+macro_rules! impl_extended {
+    ($($ty:ty => $variant:ident, $expected:literal;)*) => {$(
+        impl KjsonSerialize for $ty {
+            fn to_kjson(&self) -> Result<Value> {
+                Ok(Value::$variant(self.clone()))
+            }
+        }
+
+        impl KjsonDeserialize for $ty {
+            fn from_kjson(value: &Value) -> Result<Self> {
+                match value {
+                    Value::$variant(v) => Ok(v.clone()),
+                    _ => Err(mismatch($expected, value)),
+                }
+            }
+        }
+    )*};
+}
+
+impl_extended! {
+    BigInt => BigInt, "a bigint";
+    Decimal128 => Decimal128, "a decimal128";
+    Uuid => Uuid, "a uuid";
+    Duration => Duration, "a duration";
+    Date => Date, "a date";
+}
+
+impl KjsonSerialize for Instant {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(Value::Instant(self.clone()))
+    }
+}
+
+/// Timestamps parse as dates, so those are accepted too
+impl KjsonDeserialize for Instant {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        __private::instant_of(value)
+    }
+}
+
+impl KjsonSerialize for DateTime<Utc> {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(Value::Instant(self.to_instant()?))
+    }
+}
+
+impl KjsonDeserialize for DateTime<Utc> {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        Self::from_instant(&Instant::from_kjson(value)?)
+    }
+}
+
+impl KjsonSerialize for Value {
+    fn to_kjson(&self) -> Result<Value> {
+        Ok(self.clone())
+    }
+}
+
+impl KjsonDeserialize for Value {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+impl<T: KjsonSerialize + ?Sized> KjsonSerialize for &T {
+    fn to_kjson(&self) -> Result<Value> {
+        (**self).to_kjson()
+    }
+}
+
+impl<T: KjsonSerialize + ?Sized> KjsonSerialize for Box<T> {
+    fn to_kjson(&self) -> Result<Value> {
+        (**self).to_kjson()
+    }
+}
+
+impl<T: KjsonDeserialize> KjsonDeserialize for Box<T> {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        T::from_kjson(value).map(Box::new)
+    }
+}
+
+impl<T: KjsonSerialize> KjsonSerialize for Option<T> {
+    fn to_kjson(&self) -> Result<Value> {
+        match self {
+            Some(v) => v.to_kjson(),
+            None => Ok(Value::Null),
+        }
+    }
+}
+
+impl<T: KjsonDeserialize> KjsonDeserialize for Option<T> {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::from_kjson(value).map(Some),
+        }
+    }
+}
+
+impl<T: KjsonSerialize> KjsonSerialize for [T] {
+    fn to_kjson(&self) -> Result<Value> {
+        self.iter().map(T::to_kjson).collect::<Result<_>>().map(Value::Array)
+    }
+}
+
+impl<T: KjsonSerialize> KjsonSerialize for Vec<T> {
+    fn to_kjson(&self) -> Result<Value> {
+        self.as_slice().to_kjson()
+    }
+}
+
+impl<T: KjsonDeserialize> KjsonDeserialize for Vec<T> {
+    fn from_kjson(value: &Value) -> Result<Self> {
+        let items = value.as_array().ok_or_else(|| mismatch("an array", value))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_kjson(item).map_err(|e| __private::at(e, &i.to_string())))
+            .collect()
+    }
+}
+
+macro_rules! impl_map {
+    ($($map:ident),*) => {$(
+        impl<T: KjsonSerialize> KjsonSerialize for $map<String, T> {
+            fn to_kjson(&self) -> Result<Value> {
+                let mut map = Map::with_capacity(self.len());
+                for (key, value) in self {
+                    map.insert(Str::from(key.as_str()), value.to_kjson()?);
+                }
+                Ok(Value::Object(map))
+            }
+        }
+
+        impl<T: KjsonDeserialize> KjsonDeserialize for $map<String, T> {
+            fn from_kjson(value: &Value) -> Result<Self> {
+                let map = value.as_object().ok_or_else(|| mismatch("an object", value))?;
+                map.iter()
+                    .map(|(key, item)| {
+                        T::from_kjson(item)
+                            .map(|item| (key.to_string(), item))
+                            .map_err(|e| __private::at(e, key))
+                    })
+                    .collect()
+            }
+        }
+    )*};
+}
+
+impl_map!(HashMap, BTreeMap);
+
+/// Helpers for code generated by the derive macros; not a stable API
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+    use crate::pointer::escape_token;
+
+    /// `e` with `token` prepended to its path, as an [`Error::Deserialize`]
+    pub fn at(e: Error, token: &str) -> Error {
+        let token = escape_token(token);
+        match e {
+            Error::Deserialize {
+                path,
+                message,
+                found,
+            } => Error::Deserialize {
+                path: format!("/{}{}", token, path),
+                message,
+                found,
+            },
+            other => Error::Deserialize {
+                path: format!("/{}", token),
+                message: other.to_string(),
+                found: None,
+            },
+        }
+    }
+
+    pub fn expected(expected: &str, value: &Value) -> Error {
+        mismatch(expected, value)
+    }
+
+    pub fn object<'a>(value: &'a Value, expected: &str) -> Result<&'a Map> {
+        value.as_object().ok_or_else(|| mismatch(expected, value))
+    }
+
+    pub fn array<'a>(value: &'a Value, expected: &str, len: usize) -> Result<&'a [Value]> {
+        match value {
+            Value::Array(items) if items.len() == len => Ok(items),
+            Value::Array(items) => Err(invalid(format!(
+                "{} has {} elements, expected {}",
+                expected,
+                items.len(),
+                len
+            ))),
+            _ => Err(mismatch(expected, value)),
+        }
+    }
+
+    /// Convert the member `key` of `map`; a missing member converts `null`,
+    /// so options become `None`, or fails as a missing field
+    pub fn field<T>(
+        map: &Map,
+        key: &str,
+        convert: impl Fn(&Value) -> Result<T>,
+    ) -> Result<T> {
+        match map.get(key) {
+            Some(value) => convert(value).map_err(|e| at(e, key)),
+            None => convert(&Value::Null).map_err(|_| invalid(format!("missing field `{}`", key))),
+        }
+    }
+
+    /// Convert the member `key` of `map`, or `default` when it is missing
+    pub fn field_or<T>(
+        map: &Map,
+        key: &str,
+        convert: impl Fn(&Value) -> Result<T>,
+        default: impl FnOnce() -> T,
+    ) -> Result<T> {
+        match map.get(key) {
+            Some(value) => convert(value).map_err(|e| at(e, key)),
+            None => Ok(default()),
+        }
+    }
+
+    pub fn element<T>(items: &[Value], i: usize, convert: impl Fn(&Value) -> Result<T>) -> Result<T> {
+        convert(&items[i]).map_err(|e| at(e, &i.to_string()))
+    }
+
+    pub fn deny_unknown(map: &Map, fields: &[&str]) -> Result<()> {
+        match map.keys().find(|key| !fields.contains(&key.as_str())) {
+            Some(key) => Err(invalid(format!(
+                "unknown field `{}`, expected one of {}",
+                key,
+                fields
+                    .iter()
+                    .map(|f| format!("`{}`", f))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The single `{ variant: value }` entry of an externally tagged enum,
+    /// or the name of a unit variant
+    pub fn variant<'a>(value: &'a Value, expected: &str) -> Result<(&'a str, Option<&'a Value>)> {
+        match value {
+            Value::String(s) => Ok((s, None)),
+            Value::Object(map) if map.len() == 1 => {
+                let (key, value) = map.iter().next().expect("map has one entry");
+                Ok((key, Some(value)))
+            }
+            _ => Err(mismatch(expected, value)),
+        }
+    }
+
+    pub fn unknown_variant(variant: &str, variants: &[&str]) -> Error {
+        invalid(format!(
+            "unknown variant `{}`, expected one of {}",
+            variant,
+            variants
+                .iter()
+                .map(|v| format!("`{}`", v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    /// The value of a variant that carries one, at the variant's key
+    pub fn content<'a>(variant: &str, content: Option<&'a Value>) -> Result<&'a Value> {
+        content.ok_or_else(|| invalid(format!("variant `{}` expects a value", variant)))
+    }
+
+    pub fn to_object(entries: Vec<(&str, Value)>) -> Value {
+        Value::Object(entries.into_iter().map(|(k, v)| (Str::from(k), v)).collect())
+    }
+
+    pub fn bigint_of(value: &Value) -> Result<BigInt> {
+        match value {
+            Value::BigInt(n) => Ok(n.clone()),
+            Value::Integer(n) => Ok(BigInt::from_i64(*n)),
+            Value::UInteger(n) => Ok(BigInt::from_i128(*n as i128)),
+            Value::Number(n) if n.fract() == 0.0 => BigInt::from_str(&format!("{:.0}", n)),
+            _ => Err(mismatch("a bigint", value)),
+        }
+    }
+
+    pub fn decimal_of(value: &Value) -> Result<Decimal128> {
+        match value {
+            Value::Decimal128(d) => Ok(d.clone()),
+            Value::Integer(n) => Decimal128::from_str(&n.to_string()),
+            Value::UInteger(n) => Decimal128::from_str(&n.to_string()),
+            Value::Number(n) if n.is_finite() => Decimal128::from_str(&n.to_string()),
+            _ => Err(mismatch("a decimal128", value)),
+        }
+    }
+
+    pub fn instant_of(value: &Value) -> Result<Instant> {
+        match value {
+            Value::Instant(i) => Ok(i.clone()),
+            Value::Date(d) => d
+                .utc
+                .timestamp_nanos_opt()
+                .map(Instant::from_nanos)
+                .ok_or_else(|| invalid(format!("{} is out of range for an instant", d))),
+            _ => Err(mismatch("an instant", value)),
+        }
+    }
+
+    pub fn duration_of(value: &Value) -> Result<Duration> {
+        match value {
+            Value::Duration(d) => Ok(d.clone()),
+            _ => Err(mismatch("a duration", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_primitives() {
+        assert!(matches!(42u8.to_kjson().unwrap(), Value::Integer(42)));
+        assert!(matches!(i64::MIN.to_kjson().unwrap(), Value::Integer(i64::MIN)));
+        assert!(matches!(u64::MAX.to_kjson().unwrap(), Value::UInteger(u64::MAX)));
+        assert_eq!(u64::from_kjson(&parse("18446744073709551615n").unwrap()).unwrap(), u64::MAX);
+        assert_eq!(i32::from_kjson(&parse("-7").unwrap()).unwrap(), -7);
+        assert_eq!(
+            u8::from_kjson(&parse("300").unwrap()).unwrap_err().to_string(),
+            "300 is out of range for u8"
+        );
+        assert_eq!(
+            u8::from_kjson(&parse("1.5").unwrap()).unwrap_err().to_string(),
+            "expected u8, found number"
+        );
+
+        let map = HashMap::from([("a".to_string(), vec![Some(1i64), None])]);
+        assert_eq!(map.to_kjson().unwrap(), parse("{ a: [1, null] }").unwrap());
+        assert_eq!(
+            BTreeMap::<String, Vec<bool>>::from_kjson(&parse("{ a: [true, 1] }").unwrap())
+                .unwrap_err()
+                .to_string(),
+            "expected a boolean at /a/1, found number"
+        );
+
+        let instant = Instant::from_kjson(&parse("2025-01-10T12:00:00Z").unwrap()).unwrap();
+        assert_eq!(instant, Instant::from_iso8601("2025-01-10T12:00:00Z").unwrap());
+        let time = SystemTime::from_instant(&instant).unwrap();
+        assert_eq!(time.to_instant().unwrap(), instant);
+    }
+}
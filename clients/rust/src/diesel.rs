@@ -0,0 +1,173 @@
+//! Diesel `FromSql` / `ToSql` support on Postgres
+//!
+//! [`Kjson`] is the SQL type of the kjson extension's `kjson` columns, for
+//! use in `table!` declarations. [`Value`] reads from and writes to it:
+//!
+//! ```no_run
+//! # use diesel::prelude::*;
+//! diesel::table! {
+//!     use diesel::sql_types::*;
+//!     use kjson::diesel::Kjson;
+//!
+//!     orders (id) {
+//!         id -> Int8,
+//!         data -> Kjson,
+//!     }
+//! }
+//!
+//! # use diesel::connection::LoadConnection;
+//! # fn run(conn: &mut impl LoadConnection<Backend = diesel::pg::Pg>) -> QueryResult<()> {
+//! let doc = kjson::parse("{ price: 19.99m }").unwrap();
+//! diesel::insert_into(orders::table)
+//!     .values(orders::data.eq(&doc))
+//!     .execute(conn)?;
+//! let docs: Vec<kjson::Value> = orders::table.select(orders::data).load(conn)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Values use the extension's binary wire format, so neither side goes
+//! through text. The extended scalar types map to built-in column types:
+//!
+//! | kJSON | Diesel SQL type |
+//! |-------|-----------------|
+//! | Value | [`Kjson`] |
+//! | Decimal128 | `Numeric` |
+//! | BigInt | `Numeric` (reading fails for values with a fraction) |
+//! | Instant | `Timestamptz` (microsecond precision) |
+//! | Duration | `Interval` (without months or years) |
+//!
+//! The connection itself comes from Diesel's `postgres` feature.
+
+use crate::pg_scalar::{
+    decode_interval, decode_numeric, decode_timestamp, encode_interval, encode_numeric,
+    encode_timestamp, numeric_to_bigint,
+};
+use crate::pg_wire;
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::Value;
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::{Pg, PgValue};
+use diesel::query_builder::QueryId;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::{Interval, Numeric, SqlType, Timestamptz};
+use std::io::Write;
+
+/// The kjson extension's `kjson` column type
+#[derive(Debug, Clone, Copy, Default, QueryId, SqlType)]
+#[diesel(postgres_type(name = "kjson"))]
+pub struct Kjson;
+
+impl ToSql<Kjson, Pg> for Value {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let mut buf = Vec::new();
+        pg_wire::encode(self, &mut buf);
+        out.write_all(&buf)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Kjson, Pg> for Value {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        Ok(pg_wire::decode(value.as_bytes())?)
+    }
+}
+
+impl ToSql<Numeric, Pg> for Decimal128 {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let mut buf = Vec::new();
+        encode_numeric(&self.coefficient(), self.exponent(), &mut buf);
+        out.write_all(&buf)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Numeric, Pg> for Decimal128 {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let (coefficient, exponent) = decode_numeric(value.as_bytes())?;
+        Ok(Decimal128::from_parts(&coefficient, exponent))
+    }
+}
+
+impl ToSql<Numeric, Pg> for BigInt {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let mut buf = Vec::new();
+        encode_numeric(self, 0, &mut buf);
+        out.write_all(&buf)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Numeric, Pg> for BigInt {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        let (coefficient, exponent) = decode_numeric(value.as_bytes())?;
+        Ok(numeric_to_bigint(&coefficient, exponent)?)
+    }
+}
+
+impl ToSql<Timestamptz, Pg> for Instant {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let mut buf = Vec::new();
+        encode_timestamp(self, &mut buf);
+        out.write_all(&buf)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Timestamptz, Pg> for Instant {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        Ok(decode_timestamp(value.as_bytes())?)
+    }
+}
+
+impl ToSql<Interval, Pg> for Duration {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        let mut buf = Vec::new();
+        encode_interval(self, &mut buf);
+        out.write_all(&buf)?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Interval, Pg> for Duration {
+    fn from_sql(value: PgValue<'_>) -> deserialize::Result<Self> {
+        Ok(decode_interval(value.as_bytes())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::deserialize::FromSqlRow;
+    use diesel::prelude::*;
+
+    diesel::table! {
+        use diesel::sql_types::*;
+        use crate::diesel::Kjson;
+
+        orders (id) {
+            id -> Int8,
+            data -> Kjson,
+            total -> Numeric,
+            placed_at -> Timestamptz,
+            ttl -> Interval,
+        }
+    }
+
+    #[test]
+    fn test_bind_extended_types() {
+        let doc = crate::parse("{ total: 12n }").unwrap();
+        let query = orders::table
+            .filter(orders::data.eq(&doc))
+            .filter(orders::total.eq(BigInt::from_i64(12)))
+            .filter(orders::placed_at.lt(Instant::from_seconds(0)))
+            .filter(orders::ttl.gt(Duration::from_minutes(5)))
+            .select((orders::data, orders::total, orders::placed_at, orders::ttl));
+        let sql = diesel::debug_query::<Pg, _>(&query).to_string();
+        assert!(sql.contains("\"orders\".\"data\" = $1"), "{}", sql);
+
+        // Rows load into the kJSON types
+        fn loadable<T: FromSqlRow<(Kjson, Numeric, Timestamptz, Interval), Pg>>() {}
+        loadable::<(Value, BigInt, Instant, Duration)>();
+    }
+}
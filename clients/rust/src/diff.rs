@@ -0,0 +1,512 @@
+//! Structural differences between two values
+//!
+//! [`diff`] walks two documents side by side and lists what was added,
+//! removed or changed, each at a JSON Pointer:
+//!
+//! ```
+//! use kjson::diff::{diff, Change};
+//!
+//! let old = kjson::parse("{ name: 'shop', port: 80, tags: ['a'] }")?;
+//! let new = kjson::parse("{ name: 'shop', port: 8080, tags: ['a', 'b'] }")?;
+//! let changes = diff(&old, &new);
+//! assert_eq!(changes.len(), 2);
+//! assert!(matches!(&changes[0], Change::Changed { path, .. } if path == "/port"));
+//! assert!(matches!(&changes[1], Change::Added { path, .. } if path == "/tags/1"));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Objects are compared key by key, in key order, and arrays index by index.
+//! Scalars are compared by value: `1.50m` and `1.5m` are the same decimal,
+//! and `1` the same number as `1.0`.
+//! [`to_patch`] turns the changes into a JSON Patch (RFC 6902) document, and
+//! [`render_diff`] into text for people to read:
+//!
+//! ```
+//! use kjson::diff::{render_diff_plain, DiffStyle};
+//!
+//! let old = kjson::parse("{ port: 80 }")?;
+//! let new = kjson::parse("{ port: 8080, tls: true }")?;
+//! assert_eq!(
+//!     render_diff_plain(&old, &new, DiffStyle::Unified),
+//!     "@@ /port @@\n- 80\n+ 8080\n@@ /tls @@\n+ true\n"
+//! );
+//! assert_eq!(
+//!     render_diff_plain(&old, &new, DiffStyle::SideBySide),
+//!     "/port | 80 | 8080\n/tls  |    | true\n"
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! [`merge3`] is the three-way merge for two edits of the same document:
+//! changes to different members combine, and changes to the same value are
+//! reported as [`Conflict`]s.
+
+use crate::pointer;
+use crate::serializer::{to_string, to_string_pretty};
+use crate::value::{Map, Str, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// One difference between two values
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Present only in the new value
+    Added {
+        /// JSON Pointer of the value
+        path: String,
+        /// The added value
+        value: Value,
+    },
+    /// Present only in the old value
+    Removed {
+        /// JSON Pointer of the value
+        path: String,
+        /// The removed value
+        value: Value,
+    },
+    /// Present in both with different values
+    Changed {
+        /// JSON Pointer of the value
+        path: String,
+        /// The old value
+        from: Value,
+        /// The new value
+        to: Value,
+    },
+}
+
+impl Change {
+    /// JSON Pointer of the difference
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// Differences that turn `old` into `new`
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_at(old, new, "", &mut changes);
+    changes
+}
+
+fn diff_at(old: &Value, new: &Value, path: &str, out: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: BTreeSet<&Str> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = pointer::push(path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_at(x, y, &child, out),
+                    (Some(x), None) => out.push(Change::Removed {
+                        path: child,
+                        value: x.clone(),
+                    }),
+                    (None, Some(y)) => out.push(Change::Added {
+                        path: child,
+                        value: y.clone(),
+                    }),
+                    (None, None) => unreachable!(),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff_at(x, y, &pointer::push_index(path, i), out);
+            }
+            for (i, y) in b.iter().enumerate().skip(a.len()) {
+                out.push(Change::Added {
+                    path: pointer::push_index(path, i),
+                    value: y.clone(),
+                });
+            }
+            // Highest index first, so applying the removals in order works
+            for (i, x) in a.iter().enumerate().skip(b.len()).rev() {
+                out.push(Change::Removed {
+                    path: pointer::push_index(path, i),
+                    value: x.clone(),
+                });
+            }
+        }
+        (x, y) if same_value(x, y) => {}
+        (x, y) => out.push(Change::Changed {
+            path: path.to_string(),
+            from: x.clone(),
+            to: y.clone(),
+        }),
+    }
+}
+
+/// Equal scalars, treating decimals that differ only in scale as equal
+fn same_value(x: &Value, y: &Value) -> bool {
+    match (x, y) {
+        (Value::Decimal128(a), Value::Decimal128(b)) => a.normalize() == b.normalize(),
+        (x, y) => x == y,
+    }
+}
+
+/// JSON Patch (RFC 6902) operations for a list of changes
+pub fn to_patch(changes: &[Change]) -> Value {
+    let op = |name: &str, path: &str, value: Option<&Value>| {
+        let mut map = Map::new();
+        map.insert("op", Value::string(name));
+        map.insert("path", Value::string(path));
+        if let Some(value) = value {
+            map.insert("value", value.clone());
+        }
+        Value::Object(map)
+    };
+    Value::Array(
+        changes
+            .iter()
+            .map(|change| match change {
+                Change::Added { path, value } => op("add", path, Some(value)),
+                Change::Removed { path, .. } => op("remove", path, None),
+                Change::Changed { path, to, .. } => op("replace", path, Some(to)),
+            })
+            .collect(),
+    )
+}
+
+/// Layout for [`render_diff`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiffStyle {
+    /// A `@@ pointer @@` header per change, then `-` old and `+` new lines
+    #[default]
+    Unified,
+    /// One row per change: pointer, old value and new value in columns
+    SideBySide,
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// The differences between `old` and `new` as text with ANSI colors: red
+/// for old values, green for new ones. Empty if the values are equal.
+pub fn render_diff(old: &Value, new: &Value, style: DiffStyle) -> String {
+    render(&diff(old, new), style, true)
+}
+
+/// [`render_diff`] without colors, e.g. for test failure messages
+pub fn render_diff_plain(old: &Value, new: &Value, style: DiffStyle) -> String {
+    render(&diff(old, new), style, false)
+}
+
+fn render(changes: &[Change], style: DiffStyle, color: bool) -> String {
+    let paint = |text: &str, code: &str| {
+        if color && !text.is_empty() {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    // (pointer, old lines, new lines) of each change
+    let rows: Vec<(&str, Vec<String>, Vec<String>)> = changes
+        .iter()
+        .map(|change| {
+            let (from, to) = match change {
+                Change::Added { value, .. } => (None, Some(value)),
+                Change::Removed { value, .. } => (Some(value), None),
+                Change::Changed { from, to, .. } => (Some(from), Some(to)),
+            };
+            let path = match change.path() {
+                "" => "(root)",
+                path => path,
+            };
+            (path, lines(from, style), lines(to, style))
+        })
+        .collect();
+
+    let mut out = String::new();
+    match style {
+        DiffStyle::Unified => {
+            for (path, from, to) in &rows {
+                out.push_str(&paint(&format!("@@ {} @@", path), CYAN));
+                out.push('\n');
+                for (lines, sign, code) in [(from, '-', RED), (to, '+', GREEN)] {
+                    for line in lines {
+                        out.push_str(&paint(&format!("{} {}", sign, line), code));
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        DiffStyle::SideBySide => {
+            let width = |text: &str| text.chars().count();
+            let path_width = rows.iter().map(|row| width(row.0)).max().unwrap_or(0);
+            let old_width = rows
+                .iter()
+                .flat_map(|row| row.1.iter().map(|line| width(line)))
+                .max()
+                .unwrap_or(0);
+            for (path, from, to) in &rows {
+                for i in 0..from.len().max(to.len()) {
+                    let path = if i == 0 { *path } else { "" };
+                    let old = from.get(i).map_or("", String::as_str);
+                    let new = to.get(i).map_or("", String::as_str);
+                    // Pad before painting, so escape codes do not count
+                    let line = format!(
+                        "{}{} | {}{} | {}",
+                        paint(path, CYAN),
+                        " ".repeat(path_width - width(path)),
+                        paint(old, RED),
+                        " ".repeat(old_width - width(old)),
+                        paint(new, GREEN)
+                    );
+                    out.push_str(line.trim_end());
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The lines a value is shown as: compact side by side, pretty otherwise
+fn lines(value: Option<&Value>, style: DiffStyle) -> Vec<String> {
+    let Some(value) = value else {
+        return Vec::new();
+    };
+    let text = match (style, value) {
+        (DiffStyle::Unified, Value::Array(_) | Value::Object(_)) => to_string_pretty(value),
+        _ => to_string(value),
+    };
+    match text {
+        Ok(text) => text.lines().map(str::to_string).collect(),
+        Err(_) => vec![format!("{:?}", value)],
+    }
+}
+
+/// A value changed differently on both sides of a [`merge3`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// JSON Pointer of the value
+    pub path: String,
+    /// The common ancestor's value, `None` if absent
+    pub base: Option<Value>,
+    /// Our value, `None` if we removed it
+    pub ours: Option<Value>,
+    /// Their value, `None` if they removed it
+    pub theirs: Option<Value>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => to_string(value).unwrap_or_else(|_| format!("{:?}", value)),
+            None => "(absent)".to_string(),
+        };
+        let path = if self.path.is_empty() {
+            "(root)"
+        } else {
+            &self.path
+        };
+        write!(
+            f,
+            "{}: base {}, ours {}, theirs {}",
+            path,
+            show(&self.base),
+            show(&self.ours),
+            show(&self.theirs)
+        )
+    }
+}
+
+/// Merge two edits of `base`.
+///
+/// A value changed on one side only takes that side's change; objects
+/// changed on both sides are merged member by member, and arrays of
+/// unchanged length element by element. Anything else changed differently
+/// by both sides is a conflict.
+///
+/// ```
+/// use kjson::diff::merge3;
+///
+/// let base = kjson::parse("{ host: 'a', port: 80 }")?;
+/// let ours = kjson::parse("{ host: 'b', port: 80 }")?;
+/// let theirs = kjson::parse("{ host: 'a', port: 8080, tls: true }")?;
+/// let merged = merge3(&base, &ours, &theirs).unwrap();
+/// assert_eq!(merged, kjson::parse("{ host: 'b', port: 8080, tls: true }")?);
+/// # Ok::<(), kjson::Error>(())
+/// ```
+pub fn merge3(
+    base: &Value,
+    ours: &Value,
+    theirs: &Value,
+) -> std::result::Result<Value, Vec<Conflict>> {
+    let mut conflicts = Vec::new();
+    let merged = merge_at(Some(base), Some(ours), Some(theirs), "", &mut conflicts);
+    if conflicts.is_empty() {
+        Ok(merged.unwrap_or(Value::Null))
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// The merged value at `path`, `None` if it ends up absent
+fn merge_at(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &str,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    match (base, ours, theirs) {
+        // Both sides changed an object, or both added one
+        (Some(Value::Object(_)) | None, Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let empty = Map::new();
+            let o = match base {
+                Some(Value::Object(o)) => o,
+                _ => &empty,
+            };
+            let keys: BTreeSet<&Str> = o.keys().chain(a.keys()).chain(b.keys()).collect();
+            let merged = keys
+                .into_iter()
+                .filter_map(|key| {
+                    let child = pointer::push(path, key);
+                    merge_at(o.get(key), a.get(key), b.get(key), &child, conflicts)
+                        .map(|value| (key.clone(), value))
+                })
+                .collect();
+            Some(Value::Object(merged))
+        }
+        (Some(Value::Array(o)), Some(Value::Array(a)), Some(Value::Array(b)))
+            if a.len() == o.len() && b.len() == o.len() =>
+        {
+            let merged = o
+                .iter()
+                .zip(a)
+                .zip(b)
+                .enumerate()
+                .map(|(i, ((x, y), z))| {
+                    let child = pointer::push_index(path, i);
+                    // Neither side removed the element, so neither result is absent
+                    merge_at(Some(x), Some(y), Some(z), &child, conflicts).unwrap_or(Value::Null)
+                })
+                .collect();
+            Some(Value::Array(merged))
+        }
+        _ => {
+            conflicts.push(Conflict {
+                path: path.to_string(),
+                base: base.cloned(),
+                ours: ours.cloned(),
+                theirs: theirs.cloned(),
+            });
+            ours.cloned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_diff() {
+        let old = parse("{ a: 1, b: { c: [1, 2, 3] }, d: 10n, 'x/y': true }").unwrap();
+        let new = parse("{ a: 1, b: { c: [1, 5] }, d: 11n, e: null }").unwrap();
+        let changes = diff(&old, &new);
+        let paths: Vec<&str> = changes.iter().map(Change::path).collect();
+        assert_eq!(paths, ["/b/c/1", "/b/c/2", "/d", "/e", "/x~1y"]);
+        assert!(matches!(
+            &changes[1],
+            Change::Removed {
+                value: Value::Integer(3),
+                ..
+            }
+        ));
+        assert!(diff(&old, &old).is_empty());
+
+        let root = diff(&Value::Null, &Value::Bool(true));
+        assert_eq!(root[0].path(), "");
+
+        let old = parse("{ price: 1.50m, qty: 2, rate: 0.1m }").unwrap();
+        let new = parse("{ price: 1.5m, qty: 2.0, rate: 0.10m }").unwrap();
+        assert!(diff(&old, &new).is_empty());
+        let new = parse("{ price: 1.05m, qty: 2, rate: 0.1m }").unwrap();
+        assert_eq!(diff(&old, &new)[0].path(), "/price");
+    }
+
+    #[test]
+    fn test_patch() {
+        let old = parse("{ a: [1, 2, 3], b: 1 }").unwrap();
+        let new = parse("{ a: [1], b: 2, c: 'x' }").unwrap();
+        let patch = to_patch(&diff(&old, &new));
+        assert_eq!(
+            patch,
+            parse(
+                "[
+                    { op: 'remove', path: '/a/2' },
+                    { op: 'remove', path: '/a/1' },
+                    { op: 'replace', path: '/b', value: 2 },
+                    { op: 'add', path: '/c', value: 'x' },
+                ]"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let old = parse("{ a: 1, b: { c: [1, 2] }, name: 'x' }").unwrap();
+        let new = parse("{ a: 2, b: { c: [1] }, d: { e: true } }").unwrap();
+        assert_eq!(
+            render_diff_plain(&old, &new, DiffStyle::Unified),
+            "@@ /a @@\n- 1\n+ 2\n@@ /b/c/1 @@\n- 2\n@@ /d @@\n+ {\n+   e: true\n+ }\n@@ /name @@\n- 'x'\n"
+        );
+        assert_eq!(
+            render_diff_plain(&old, &new, DiffStyle::SideBySide),
+            "/a     | 1   | 2\n/b/c/1 | 2   |\n/d     |     | {e: true}\n/name  | 'x' |\n"
+        );
+        assert_eq!(
+            render_diff(&Value::Null, &Value::Bool(true), DiffStyle::Unified),
+            "\x1b[36m@@ (root) @@\x1b[0m\n\x1b[31m- null\x1b[0m\n\x1b[32m+ true\x1b[0m\n"
+        );
+        assert_eq!(render_diff(&old, &old, DiffStyle::SideBySide), "");
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = parse("{ a: 1, b: { c: [1, 2], d: 'x' }, e: true }").unwrap();
+        let ours = parse("{ a: 2, b: { c: [1, 3], d: 'x' }, e: true }").unwrap();
+        let theirs = parse("{ a: 1, b: { c: [5, 2], d: 'y' }, f: null }").unwrap();
+        assert_eq!(
+            merge3(&base, &ours, &theirs).unwrap(),
+            parse("{ a: 2, b: { c: [5, 3], d: 'y' }, f: null }").unwrap()
+        );
+        assert_eq!(merge3(&base, &base, &base).unwrap(), base);
+
+        // Both added the same member differently; one removed what the other changed
+        let ours = parse("{ a: 2, b: { c: [1, 2, 3] }, new: 1 }").unwrap();
+        let theirs = parse("{ b: { c: [1] }, e: true, new: 2 }").unwrap();
+        let conflicts = merge3(&base, &ours, &theirs).unwrap_err();
+        let paths: Vec<&str> = conflicts.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, ["/a", "/b/c", "/new"]);
+        assert_eq!(
+            conflicts[0],
+            Conflict {
+                path: "/a".to_string(),
+                base: Some(Value::Number(1.0)),
+                ours: Some(Value::Number(2.0)),
+                theirs: None,
+            }
+        );
+        assert_eq!(
+            conflicts[2].to_string(),
+            "/new: base (absent), ours 1, theirs 2"
+        );
+    }
+}
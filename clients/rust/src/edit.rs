@@ -0,0 +1,1202 @@
+//! Format-preserving editing
+//!
+//! [`Document`] is a lossless syntax tree: it keeps whitespace, comments,
+//! quote styles, literal forms, trailing commas and key order, and prints
+//! back exactly the text it was parsed from. Edits only touch the text of
+//! the values they change, so tools can rewrite a user's config file
+//! without reformatting it:
+//!
+//! ```
+//! use kjson::edit::Document;
+//!
+//! let mut doc: Document = "{
+//!     // Where to listen
+//!     server: { host: 'localhost', port: 80 },
+//! }"
+//! .parse()?;
+//! doc["server"]["port"] = 8080.into();
+//! doc["server"].as_object_mut().unwrap().insert("tls", true);
+//!
+//! assert_eq!(
+//!     doc.to_string(),
+//!     "{
+//!     // Where to listen
+//!     server: { host: 'localhost', port: 8080, tls: true },
+//! }"
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Whitespace and comments before a member belong to that member, so
+//! removing it removes its comments too. Values assigned or inserted are
+//! written in the compact form of [`to_string`](crate::to_string); new
+//! members copy the indentation of their siblings.
+//!
+//! For editors, [`Document::apply_edit`] takes a text change and reparses
+//! only the smallest value that contains it, and [`Document::span`] maps a
+//! JSON Pointer back to source offsets:
+//!
+//! ```
+//! use kjson::edit::Document;
+//!
+//! let mut doc = Document::parse("{ a: [1, 2], b: 'x' }")?;
+//! let reparsed = doc.apply_edit(9..10, "20, 30")?;
+//! assert_eq!(doc.to_string(), "{ a: [1, 20, 30], b: 'x' }");
+//! assert_eq!(reparsed, doc.span("/a").unwrap());
+//! # Ok::<(), kjson::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::parser::parse_partial;
+use crate::serializer::{key_to_string, to_string, write_canonical_string};
+use crate::span::Span;
+use crate::value::{Map, Str, Value};
+use std::fmt;
+use std::ops::{Index, IndexMut, Range};
+use std::str::FromStr;
+
+/// A parsed document that prints back exactly as it was written
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub(crate) prefix: String,
+    pub(crate) root: Item,
+    pub(crate) suffix: String,
+}
+
+/// A value in a [`Document`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// Anything other than an array or object
+    Scalar(Scalar),
+    /// An array
+    Array(Array),
+    /// An object
+    Object(Object),
+}
+
+/// A non-container value together with its source text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scalar {
+    pub(crate) raw: String,
+    pub(crate) value: Value,
+}
+
+/// An array, with the formatting around its elements
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Array {
+    pub(crate) elements: Vec<Element>,
+    pub(crate) close: String,
+}
+
+/// An object, with its members in document order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Object {
+    pub(crate) members: Vec<Member>,
+    pub(crate) close: String,
+}
+
+/// One array element and the text around it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Element {
+    /// Whitespace and comments before the value
+    pub(crate) prefix: String,
+    pub(crate) value: Item,
+    /// Whitespace and comments between the value and its comma
+    pub(crate) suffix: String,
+    pub(crate) comma: bool,
+}
+
+/// One object member and the text around it
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Member {
+    pub(crate) prefix: String,
+    /// The key as written, with its quotes
+    pub(crate) raw_key: String,
+    pub(crate) key: String,
+    pub(crate) before_colon: String,
+    pub(crate) after_colon: String,
+    pub(crate) value: Item,
+    pub(crate) suffix: String,
+    pub(crate) comma: bool,
+}
+
+impl Document {
+    /// Parse a document, keeping all of its formatting
+    pub fn parse(input: &str) -> Result<Document> {
+        let mut parser = CstParser { input, position: 0 };
+        let prefix = parser.trivia()?;
+        if parser.peek().is_none() {
+            return Err(Error::UnexpectedEof);
+        }
+        let root = parser.item()?;
+        let suffix = parser.trivia()?;
+        if parser.position < input.len() {
+            return Err(parser.error("Unexpected characters after value"));
+        }
+        Ok(Document {
+            prefix,
+            root,
+            suffix,
+        })
+    }
+
+    /// The top-level value
+    pub fn root(&self) -> &Item {
+        &self.root
+    }
+
+    /// The top-level value, for editing
+    pub fn root_mut(&mut self) -> &mut Item {
+        &mut self.root
+    }
+
+    /// The document's data, without formatting
+    pub fn to_value(&self) -> Value {
+        self.root.to_value()
+    }
+
+    /// Where the value at a JSON Pointer is in the printed document
+    pub fn span(&self, pointer: &str) -> Option<Span> {
+        let mut item = &self.root;
+        let mut start = self.prefix.len();
+        if !pointer.is_empty() {
+            for token in pointer.strip_prefix('/')?.split('/') {
+                let token = token.replace("~1", "/").replace("~0", "~");
+                let index = match item {
+                    Item::Array(_) => token.parse().ok()?,
+                    Item::Object(object) => object.position(&token)?,
+                    Item::Scalar(_) => return None,
+                };
+                let (offset, child) = item.children(start).nth(index)?;
+                start = offset;
+                item = child;
+            }
+        }
+        Some(Span::new(start, start + item.text_len()))
+    }
+
+    /// Replace the text in `range` with `replacement`, reparsing as little
+    /// as possible.
+    ///
+    /// Only the innermost value whose text contains the edit is parsed
+    /// again; if the changed text no longer fits there (say a comma or a
+    /// bracket was typed), its parents are tried in turn, up to the whole
+    /// document. Returns the span of the reparsed text. On a syntax error
+    /// the document is left unchanged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds or not on character boundaries,
+    /// like [`String::replace_range`].
+    pub fn apply_edit(&mut self, range: Range<usize>, replacement: &str) -> Result<Span> {
+        let mut text = self.to_string();
+        text.replace_range(range.clone(), replacement);
+        let growth = replacement.len() as isize - range.len() as isize;
+
+        // Innermost first: (child indices, start, old length) of each
+        // value containing the edit
+        let mut candidates = Vec::new();
+        let mut path = Vec::new();
+        let mut item = &self.root;
+        let mut start = self.prefix.len();
+        while item.contains(start, &range) {
+            candidates.push((path.clone(), start, item.text_len()));
+            let Some((index, (offset, child))) = item
+                .children(start)
+                .enumerate()
+                .find(|(_, (offset, child))| child.contains(*offset, &range))
+            else {
+                break;
+            };
+            path.push(index);
+            start = offset;
+            item = child;
+        }
+
+        for (path, start, len) in candidates.into_iter().rev() {
+            let end = (start + len).wrapping_add_signed(growth);
+            let mut parser = CstParser {
+                input: &text,
+                position: start,
+            };
+            // Parse in the context of the whole text, so a literal runs
+            // into what follows exactly as it would in a full parse
+            if let Ok(item) = parser.item() {
+                if parser.position == end {
+                    *self.root.descendant_mut(&path) = item;
+                    return Ok(Span::new(start, end));
+                }
+            }
+        }
+        *self = Document::parse(&text)?;
+        Ok(Span::new(0, text.len()))
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document> {
+        Document::parse(s)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.prefix, self.root, self.suffix)
+    }
+}
+
+impl Item {
+    /// The scalar, if this is one
+    pub fn as_scalar(&self) -> Option<&Scalar> {
+        match self {
+            Item::Scalar(scalar) => Some(scalar),
+            _ => None,
+        }
+    }
+
+    /// The array, if this is one
+    pub fn as_array(&self) -> Option<&Array> {
+        match self {
+            Item::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// The array, if this is one, for editing
+    pub fn as_array_mut(&mut self) -> Option<&mut Array> {
+        match self {
+            Item::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    /// The object, if this is one
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Item::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// The object, if this is one, for editing
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Item::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    /// Length of the item's text
+    fn text_len(&self) -> usize {
+        match self {
+            Item::Scalar(scalar) => scalar.raw.len(),
+            Item::Array(array) => {
+                let elements: usize = array
+                    .elements
+                    .iter()
+                    .map(|e| {
+                        e.prefix.len() + e.value.text_len() + e.suffix.len() + e.comma as usize
+                    })
+                    .sum();
+                elements + array.close.len() + 2
+            }
+            Item::Object(object) => {
+                let members: usize = object
+                    .members
+                    .iter()
+                    .map(|m| {
+                        m.prefix.len()
+                            + m.raw_key.len()
+                            + m.before_colon.len()
+                            + 1
+                            + m.after_colon.len()
+                            + m.value.text_len()
+                            + m.suffix.len()
+                            + m.comma as usize
+                    })
+                    .sum();
+                members + object.close.len() + 2
+            }
+        }
+    }
+
+    /// The child values with their offsets, given the item's own offset
+    fn children(&self, start: usize) -> impl Iterator<Item = (usize, &Item)> {
+        // (text before the value, value, text after it)
+        let entries: Vec<(usize, &Item, usize)> = match self {
+            Item::Scalar(_) => Vec::new(),
+            Item::Array(array) => array
+                .elements
+                .iter()
+                .map(|e| (e.prefix.len(), &e.value, e.suffix.len() + e.comma as usize))
+                .collect(),
+            Item::Object(object) => object
+                .members
+                .iter()
+                .map(|m| {
+                    let before = m.prefix.len()
+                        + m.raw_key.len()
+                        + m.before_colon.len()
+                        + 1
+                        + m.after_colon.len();
+                    (before, &m.value, m.suffix.len() + m.comma as usize)
+                })
+                .collect(),
+        };
+        let mut offset = start + 1;
+        entries.into_iter().map(move |(before, item, after)| {
+            let start = offset + before;
+            offset = start + item.text_len() + after;
+            (start, item)
+        })
+    }
+
+    /// Whether an edit of `range` falls within this item, at offset
+    /// `start`. Edits touching a container's brackets belong to its parent.
+    fn contains(&self, start: usize, range: &Range<usize>) -> bool {
+        let end = start + self.text_len();
+        match self {
+            Item::Scalar(_) => start <= range.start && range.end <= end,
+            _ => start < range.start && range.end < end,
+        }
+    }
+
+    /// The item reached by following child indices
+    fn descendant_mut(&mut self, path: &[usize]) -> &mut Item {
+        let Some((&index, rest)) = path.split_first() else {
+            return self;
+        };
+        let child = match self {
+            Item::Array(array) => &mut array.elements[index].value,
+            Item::Object(object) => &mut object.members[index].value,
+            Item::Scalar(_) => unreachable!(),
+        };
+        child.descendant_mut(rest)
+    }
+
+    /// The item's data, without formatting
+    pub fn to_value(&self) -> Value {
+        match self {
+            Item::Scalar(scalar) => scalar.value.clone(),
+            Item::Array(array) => Value::Array(array.iter().map(Item::to_value).collect()),
+            Item::Object(object) => {
+                // Later duplicates win, as in `parse`
+                let map: Map = object
+                    .iter()
+                    .map(|(key, item)| (Str::from(key), item.to_value()))
+                    .collect();
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Item::Scalar(scalar) => f.write_str(&scalar.raw),
+            Item::Array(array) => {
+                f.write_str("[")?;
+                for element in &array.elements {
+                    write!(f, "{}{}{}", element.prefix, element.value, element.suffix)?;
+                    if element.comma {
+                        f.write_str(",")?;
+                    }
+                }
+                write!(f, "{}]", array.close)
+            }
+            Item::Object(object) => {
+                f.write_str("{")?;
+                for member in &object.members {
+                    write!(
+                        f,
+                        "{}{}{}:{}{}{}",
+                        member.prefix,
+                        member.raw_key,
+                        member.before_colon,
+                        member.after_colon,
+                        member.value,
+                        member.suffix
+                    )?;
+                    if member.comma {
+                        f.write_str(",")?;
+                    }
+                }
+                write!(f, "{}}}", object.close)
+            }
+        }
+    }
+}
+
+impl From<Value> for Item {
+    fn from(mut value: Value) -> Item {
+        match &mut value {
+            Value::Array(items) => {
+                let items = std::mem::take(items);
+                let len = items.len();
+                Item::Array(Array {
+                    elements: items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, value)| Element {
+                            prefix: if i == 0 { "" } else { " " }.to_string(),
+                            value: value.into(),
+                            suffix: String::new(),
+                            comma: i + 1 < len,
+                        })
+                        .collect(),
+                    close: String::new(),
+                })
+            }
+            Value::Object(map) => {
+                let mut entries: Vec<(Str, Value)> = std::mem::take(map).into_iter().collect();
+                if !cfg!(feature = "preserve_order") {
+                    entries.sort_by(|a, b| a.0.cmp(&b.0));
+                }
+                let len = entries.len();
+                Item::Object(Object {
+                    members: entries
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, (key, value))| Member {
+                            prefix: if i == 0 { "" } else { " " }.to_string(),
+                            raw_key: key_to_string(&key).unwrap_or_default(),
+                            key: key.to_string(),
+                            before_colon: String::new(),
+                            after_colon: " ".to_string(),
+                            value: value.into(),
+                            suffix: String::new(),
+                            comma: i + 1 < len,
+                        })
+                        .collect(),
+                    close: String::new(),
+                })
+            }
+            _ => Item::Scalar(Scalar {
+                raw: to_string(&value).unwrap_or_default(),
+                value,
+            }),
+        }
+    }
+}
+
+impl From<bool> for Item {
+    fn from(b: bool) -> Item {
+        Value::Bool(b).into()
+    }
+}
+
+impl From<i32> for Item {
+    fn from(n: i32) -> Item {
+        Value::Integer(n.into()).into()
+    }
+}
+
+impl From<i64> for Item {
+    fn from(n: i64) -> Item {
+        Value::Integer(n).into()
+    }
+}
+
+impl From<f64> for Item {
+    fn from(n: f64) -> Item {
+        Value::Number(n).into()
+    }
+}
+
+impl From<&str> for Item {
+    fn from(s: &str) -> Item {
+        Value::string(s.to_string()).into()
+    }
+}
+
+impl From<String> for Item {
+    fn from(s: String) -> Item {
+        Value::string(s).into()
+    }
+}
+
+impl Index<&str> for Item {
+    type Output = Item;
+
+    /// The member `key`; panics if this is not an object or has no such member
+    fn index(&self, key: &str) -> &Item {
+        match self.as_object().and_then(|object| object.get(key)) {
+            Some(item) => item,
+            None => panic!("no member `{}`", key),
+        }
+    }
+}
+
+impl IndexMut<&str> for Item {
+    /// The member `key`, inserted as `null` if missing; panics if this is
+    /// not an object
+    fn index_mut(&mut self, key: &str) -> &mut Item {
+        let Some(object) = self.as_object_mut() else {
+            panic!("cannot index a non-object with `{}`", key);
+        };
+        if !object.contains_key(key) {
+            object.insert(key, Value::Null);
+        }
+        object.get_mut(key).unwrap()
+    }
+}
+
+impl Index<usize> for Item {
+    type Output = Item;
+
+    /// The element at `index`; panics if this is not an array or too short
+    fn index(&self, index: usize) -> &Item {
+        match self.as_array().and_then(|array| array.get(index)) {
+            Some(item) => item,
+            None => panic!("no element {}", index),
+        }
+    }
+}
+
+impl IndexMut<usize> for Item {
+    /// The element at `index`; panics if this is not an array or too short
+    fn index_mut(&mut self, index: usize) -> &mut Item {
+        match self.as_array_mut().and_then(|array| array.get_mut(index)) {
+            Some(item) => item,
+            None => panic!("no element {}", index),
+        }
+    }
+}
+
+impl<I> Index<I> for Document
+where
+    Item: Index<I, Output = Item>,
+{
+    type Output = Item;
+
+    fn index(&self, index: I) -> &Item {
+        &self.root[index]
+    }
+}
+
+impl<I> IndexMut<I> for Document
+where
+    Item: IndexMut<I, Output = Item>,
+{
+    fn index_mut(&mut self, index: I) -> &mut Item {
+        &mut self.root[index]
+    }
+}
+
+impl Scalar {
+    /// The value
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// The value as written in the source, e.g. `"a"` or `1.50m`
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl Array {
+    /// Number of elements
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The element at `index`
+    pub fn get(&self, index: usize) -> Option<&Item> {
+        self.elements.get(index).map(|element| &element.value)
+    }
+
+    /// The element at `index`, for editing
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Item> {
+        self.elements
+            .get_mut(index)
+            .map(|element| &mut element.value)
+    }
+
+    /// The elements in order
+    pub fn iter(&self) -> impl Iterator<Item = &Item> {
+        self.elements.iter().map(|element| &element.value)
+    }
+
+    /// Append an element, laid out like the existing ones
+    pub fn push(&mut self, value: impl Into<Item>) {
+        let (prefix, comma) = next_layout(
+            self.elements.iter().map(|e| (e.prefix.as_str(), e.comma)),
+            &mut self.close,
+            "",
+        );
+        if let Some(last) = self.elements.last_mut() {
+            last.comma = true;
+        }
+        self.elements.push(Element {
+            prefix,
+            value: value.into(),
+            suffix: String::new(),
+            comma,
+        });
+    }
+
+    /// Remove the element at `index`, with the comments before it
+    pub fn remove(&mut self, index: usize) -> Option<Item> {
+        if index >= self.elements.len() {
+            return None;
+        }
+        let removed = self.elements.remove(index);
+        remove_layout(
+            self.elements
+                .iter_mut()
+                .map(|e| (&mut e.prefix, &mut e.comma)),
+            index,
+            &removed.prefix,
+            removed.comma,
+            &mut self.close,
+        );
+        Some(removed.value)
+    }
+}
+
+impl Object {
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Whether there are no members
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Whether there is a member `key`
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// The value of member `key`
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.position(key).map(|i| &self.members[i].value)
+    }
+
+    /// The value of member `key`, for editing
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Item> {
+        self.position(key).map(|i| &mut self.members[i].value)
+    }
+
+    /// The members in document order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Item)> {
+        self.members
+            .iter()
+            .map(|member| (member.key.as_str(), &member.value))
+    }
+
+    /// Set member `key`, returning its previous value.
+    ///
+    /// An existing member is changed in place; a new one is appended after
+    /// the others and laid out like them.
+    pub fn insert(&mut self, key: &str, value: impl Into<Item>) -> Option<Item> {
+        let value = value.into();
+        if let Some(i) = self.position(key) {
+            return Some(std::mem::replace(&mut self.members[i].value, value));
+        }
+        // Follow the siblings' key quoting, e.g. double quotes in JSON files
+        let raw_key = match self.members.last() {
+            Some(last) if last.raw_key.starts_with('"') => {
+                let mut buf = Vec::new();
+                // Writing to a Vec cannot fail
+                let _ = write_canonical_string(&mut buf, key);
+                String::from_utf8_lossy(&buf).into_owned()
+            }
+            _ => key_to_string(key).unwrap_or_default(),
+        };
+        let (before_colon, after_colon) = match self.members.last() {
+            Some(last) => (last.before_colon.clone(), last.after_colon.clone()),
+            None => (String::new(), " ".to_string()),
+        };
+        let (prefix, comma) = next_layout(
+            self.members.iter().map(|m| (m.prefix.as_str(), m.comma)),
+            &mut self.close,
+            " ",
+        );
+        if let Some(last) = self.members.last_mut() {
+            last.comma = true;
+        }
+        self.members.push(Member {
+            prefix,
+            raw_key,
+            key: key.to_string(),
+            before_colon,
+            after_colon,
+            value,
+            suffix: String::new(),
+            comma,
+        });
+        None
+    }
+
+    /// Remove member `key`, with the comments before it
+    pub fn remove(&mut self, key: &str) -> Option<Item> {
+        let index = self.position(key)?;
+        let removed = self.members.remove(index);
+        remove_layout(
+            self.members
+                .iter_mut()
+                .map(|m| (&mut m.prefix, &mut m.comma)),
+            index,
+            &removed.prefix,
+            removed.comma,
+            &mut self.close,
+        );
+        Some(removed.value)
+    }
+
+    /// Index of the member that wins for `key`: the last one, as in `parse`
+    fn position(&self, key: &str) -> Option<usize> {
+        self.members.iter().rposition(|member| member.key == key)
+    }
+}
+
+/// Prefix and trailing comma for an entry appended after `entries`.
+///
+/// `padding` goes inside the brackets of an empty single-line container,
+/// giving `{ a: 1 }` and `[1]`.
+fn next_layout<'a>(
+    entries: impl DoubleEndedIterator<Item = (&'a str, bool)> + ExactSizeIterator,
+    close: &mut String,
+    padding: &str,
+) -> (String, bool) {
+    let len = entries.len();
+    let mut entries = entries.rev();
+    let Some((prefix, comma)) = entries.next() else {
+        return match close.rfind('\n') {
+            // A multi-line empty container: indent one level past the bracket
+            Some(i) => (format!("\n{}  ", &close[i + 1..]), false),
+            None => {
+                *close = padding.to_string();
+                (padding.to_string(), false)
+            }
+        };
+    };
+    let prefix = match prefix.rfind('\n') {
+        Some(i) => {
+            let indent = &prefix[i + 1..];
+            let indent = &indent[..indent.len() - indent.trim_start().len()];
+            format!("\n{}", indent)
+        }
+        // After `{` the spacing may differ from after a comma
+        None if len == 1 => " ".to_string(),
+        None => prefix.to_string(),
+    };
+    (prefix, comma)
+}
+
+/// Fix up commas and spacing after the entry at `index` was removed
+fn remove_layout<'a>(
+    entries: impl Iterator<Item = (&'a mut String, &'a mut bool)>,
+    index: usize,
+    removed_prefix: &str,
+    removed_comma: bool,
+    close: &mut String,
+) {
+    let mut entries: Vec<_> = entries.collect();
+    if entries.is_empty() {
+        if !close.contains('\n') {
+            close.clear();
+        }
+        return;
+    }
+    if index == entries.len() {
+        // The removed entry was last: keep the container's trailing comma style
+        *entries[index - 1].1 = removed_comma;
+    } else if index == 0 && !entries[0].0.contains('\n') {
+        // Keep the spacing after the opening bracket, minus any comments
+        let spacing = &removed_prefix[..removed_prefix.len() - removed_prefix.trim_start().len()];
+        *entries[0].0 = spacing.to_string();
+    }
+}
+
+/// Parser that records all trivia
+struct CstParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl CstParser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError {
+            position: self.position,
+            message: message.to_string(),
+        }
+    }
+
+    /// Whitespace and comments at the current position
+    fn trivia(&mut self) -> Result<String> {
+        let start = self.position;
+        let bytes = self.input.as_bytes();
+        while let Some(b) = self.peek() {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' => self.position += 1,
+                b'/' if bytes.get(self.position + 1) == Some(&b'/') => {
+                    self.position = match self.input[self.position..].find('\n') {
+                        Some(i) => self.position + i + 1,
+                        None => self.input.len(),
+                    };
+                }
+                b'/' if bytes.get(self.position + 1) == Some(&b'*') => {
+                    match self.input[self.position + 2..].find("*/") {
+                        Some(i) => self.position += 2 + i + 2,
+                        None => return Err(self.error("Unterminated comment")),
+                    }
+                }
+                _ => break,
+            }
+        }
+        Ok(self.input[start..self.position].to_string())
+    }
+
+    fn item(&mut self) -> Result<Item> {
+        match self.peek() {
+            Some(b'[') => self.array().map(Item::Array),
+            Some(b'{') => self.object().map(Item::Object),
+            Some(_) => self.scalar().map(Item::Scalar),
+            None => Err(Error::UnexpectedEof),
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Scalar> {
+        let start = self.position;
+        let (value, len) = parse_partial(&self.input[start..]).map_err(|e| match e {
+            Error::ParseError { position, message } => Error::ParseError {
+                position: start + position,
+                message,
+            },
+            other => other,
+        })?;
+        self.position += len;
+        Ok(Scalar {
+            raw: self.input[start..self.position].to_string(),
+            value,
+        })
+    }
+
+    fn array(&mut self) -> Result<Array> {
+        self.position += 1;
+        let mut elements = Vec::new();
+        loop {
+            let prefix = self.trivia()?;
+            if self.peek() == Some(b']') {
+                self.position += 1;
+                return Ok(Array {
+                    elements,
+                    close: prefix,
+                });
+            }
+            let value = self.item()?;
+            let suffix = self.trivia()?;
+            let comma = match self.peek() {
+                Some(b',') => true,
+                Some(b']') => false,
+                Some(_) => return Err(self.error("Expected ',' or ']'")),
+                None => return Err(Error::UnexpectedEof),
+            };
+            self.position += 1;
+            // Trivia before the closing bracket belongs to the container
+            let (suffix, close) = if comma {
+                (suffix, None)
+            } else {
+                (String::new(), Some(suffix))
+            };
+            elements.push(Element {
+                prefix,
+                value,
+                suffix,
+                comma,
+            });
+            if let Some(close) = close {
+                return Ok(Array { elements, close });
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Object> {
+        self.position += 1;
+        let mut members = Vec::new();
+        loop {
+            let prefix = self.trivia()?;
+            if self.peek() == Some(b'}') {
+                self.position += 1;
+                return Ok(Object {
+                    members,
+                    close: prefix,
+                });
+            }
+            let start = self.position;
+            let key = self.key()?;
+            let raw_key = self.input[start..self.position].to_string();
+            let before_colon = self.trivia()?;
+            if self.peek() != Some(b':') {
+                return Err(self.error("Expected ':' after key"));
+            }
+            self.position += 1;
+            let after_colon = self.trivia()?;
+            let value = self.item()?;
+            let suffix = self.trivia()?;
+            let comma = match self.peek() {
+                Some(b',') => true,
+                Some(b'}') => false,
+                Some(_) => return Err(self.error("Expected ',' or '}'")),
+                None => return Err(Error::UnexpectedEof),
+            };
+            self.position += 1;
+            let (suffix, close) = if comma {
+                (suffix, None)
+            } else {
+                (String::new(), Some(suffix))
+            };
+            members.push(Member {
+                prefix,
+                raw_key,
+                key,
+                before_colon,
+                after_colon,
+                value,
+                suffix,
+                comma,
+            });
+            if let Some(close) = close {
+                return Ok(Object { members, close });
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        if let Some(b'"' | b'\'' | b'`') = self.peek() {
+            return match &self.scalar()?.value {
+                Value::String(key) => Ok(key.to_string()),
+                _ => unreachable!(),
+            };
+        }
+        let rest = &self.input[self.position..];
+        let mut len = 0;
+        for (i, c) in rest.char_indices() {
+            let valid = if i == 0 {
+                c.is_alphabetic() || c == '_' || c == '$'
+            } else {
+                c.is_alphanumeric() || c == '_' || c == '$'
+            };
+            if !valid {
+                break;
+            }
+            len = i + c.len_utf8();
+        }
+        if len == 0 {
+            return Err(self.error("Invalid unquoted key"));
+        }
+        self.position += len;
+        Ok(rest[..len].to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    const CONFIG: &str = "// Service settings
+{
+  name: \"shop\",   // display name
+  'port': 80,
+  limits: { rps: 10, burst: 20n, },
+  /* feature flags */
+  flags: [ true,false ],
+  id: 550e8400-e29b-41d4-a716-446655440000,
+  price: 1.50m,
+}
+";
+
+    #[test]
+    fn test_lossless_roundtrip() {
+        let doc: Document = CONFIG.parse().unwrap();
+        assert_eq!(doc.to_string(), CONFIG);
+        assert_eq!(doc.to_value(), parse(CONFIG).unwrap());
+        assert_eq!(doc["price"].as_scalar().unwrap().raw(), "1.50m");
+
+        for input in ["[]", " 1 ", "{}", "[1,2 , 3 ,]", "{a:{b:[{}]}} // end"] {
+            assert_eq!(Document::parse(input).unwrap().to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_edit_changes_only_the_span() {
+        let mut doc: Document = CONFIG.parse().unwrap();
+        doc["port"] = 8080.into();
+        doc["limits"]["burst"] = Value::BigInt(crate::BigInt::from_i64(40)).into();
+        doc["flags"][1] = true.into();
+        assert_eq!(
+            doc.to_string(),
+            CONFIG
+                .replace("80,", "8080,")
+                .replace("20n", "40n")
+                .replace("true,false", "true,true")
+        );
+    }
+
+    #[test]
+    fn test_insert_follows_layout() {
+        let mut doc = Document::parse("{\n  a: 1,\n  // b\n  b: 2\n}").unwrap();
+        doc.root_mut()
+            .as_object_mut()
+            .unwrap()
+            .insert("new-key", "x");
+        assert_eq!(
+            doc.to_string(),
+            "{\n  a: 1,\n  // b\n  b: 2,\n  'new-key': 'x'\n}"
+        );
+
+        let mut doc = Document::parse("{ \"a\": 1, }").unwrap();
+        doc["b"] = Item::from(Value::Array(vec![Value::Null]));
+        assert_eq!(doc.to_string(), "{ \"a\": 1, \"b\": [null], }");
+
+        let mut doc = Document::parse("{}").unwrap();
+        doc["a"] = Item::from(Value::Object(Map::new()));
+        doc["a"]["b"] = 1.into();
+        assert_eq!(doc.to_string(), "{ a: { b: 1 } }");
+
+        let mut doc = Document::parse("[\n]").unwrap();
+        let array = doc.root_mut().as_array_mut().unwrap();
+        array.push(1);
+        array.push(2);
+        assert_eq!(doc.to_string(), "[\n  1,\n  2\n]");
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut doc = Document::parse("{\n  // a\n  a: 1,\n  // b\n  b: 2\n}").unwrap();
+        let object = doc.root_mut().as_object_mut().unwrap();
+        assert_eq!(object.remove("b").unwrap().to_value(), Value::Number(2.0));
+        assert_eq!(doc.to_string(), "{\n  // a\n  a: 1\n}");
+
+        let mut doc = Document::parse("{a: 1, b: 2}").unwrap();
+        doc.root_mut().as_object_mut().unwrap().remove("a");
+        assert_eq!(doc.to_string(), "{b: 2}");
+        doc.root_mut().as_object_mut().unwrap().remove("b");
+        assert_eq!(doc.to_string(), "{}");
+
+        let mut doc = Document::parse("[1, 2, 3,]").unwrap();
+        let array = doc.root_mut().as_array_mut().unwrap();
+        array.remove(2);
+        array.remove(0);
+        assert!(array.remove(5).is_none());
+        assert_eq!(doc.to_string(), "[2,]");
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let mut doc = Document::parse("{ a: 1, a: 2 }").unwrap();
+        assert_eq!(doc.to_value(), parse("{ a: 2 }").unwrap());
+        doc["a"] = 3.into();
+        assert_eq!(doc.to_string(), "{ a: 1, a: 3 }");
+    }
+
+    #[test]
+    fn test_apply_edit() {
+        let mut doc: Document = CONFIG.parse().unwrap();
+        let mut text = CONFIG.to_string();
+        for (find, replacement, reparsed) in [
+            // Within a scalar: only the scalar is reparsed
+            ("80", "8080", "8080"),
+            ("10", "15", "15"),
+            // A new element changes the array around it
+            ("true,false", "true, false, null", "[ true, false, null ]"),
+            // Keys are part of their object
+            ("rps", "rate", "{ rate: 15, burst: 20n, }"),
+            // A new member reparses the root object
+            ("/* feature flags */", "feature: 'flags',", ""),
+        ] {
+            let start = text.find(find).unwrap();
+            let range = start..start + find.len();
+            let span = doc.apply_edit(range.clone(), replacement).unwrap();
+            text.replace_range(range, replacement);
+            assert_eq!(doc.to_string(), text);
+            assert_eq!(doc, Document::parse(&text).unwrap());
+            if reparsed.is_empty() {
+                assert_eq!(span, doc.span("").unwrap());
+            } else {
+                assert_eq!(span.slice(&text), reparsed);
+            }
+        }
+
+        // A syntax error leaves the document as it was
+        let start = text.find("flags: [").unwrap();
+        assert!(doc.apply_edit(start..start, "/* ").is_err());
+        assert_eq!(doc.to_string(), text);
+
+        // Editing the comment before the root reparses everything
+        assert_eq!(
+            doc.apply_edit(3..11, "").unwrap(),
+            Span::new(0, text.len() - 8)
+        );
+        assert_eq!(doc.to_string(), text.replace("Service ", ""));
+
+        // Typing past the end of a literal reparses the container
+        let mut doc = Document::parse("[1, 2]").unwrap();
+        assert_eq!(doc.apply_edit(2..2, "0 ").unwrap(), Span::new(0, 8));
+        assert_eq!(doc.to_string(), "[10 , 2]");
+        assert!(doc.apply_edit(5..5, "x").is_err());
+        assert_eq!(doc.to_string(), "[10 , 2]");
+        assert_eq!(doc.apply_edit(0..8, "{}").unwrap(), Span::new(0, 2));
+        assert_eq!(doc.to_value(), parse("{}").unwrap());
+    }
+
+    #[test]
+    fn test_span() {
+        let doc: Document = CONFIG.parse().unwrap();
+        let text = doc.to_string();
+        for (pointer, expected) in [
+            ("", &text[text.find('{').unwrap()..text.len() - 1]),
+            ("/port", "80"),
+            ("/limits/burst", "20n"),
+            ("/flags/1", "false"),
+            ("/price", "1.50m"),
+        ] {
+            assert_eq!(
+                doc.span(pointer).unwrap().slice(&text),
+                expected,
+                "{}",
+                pointer
+            );
+        }
+        assert!(doc.span("/flags/2").is_none());
+        assert!(doc.span("/port/x").is_none());
+        assert!(doc.span("port").is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        for (input, position) in [("{ a: 1 ", None), ("[1 2]", Some(3)), ("{ a 1 }", Some(4))] {
+            match (Document::parse(input), position) {
+                (Err(Error::ParseError { position: p, .. }), Some(expected)) => {
+                    assert_eq!(p, expected, "{}", input)
+                }
+                (Err(Error::UnexpectedEof), None) => {}
+                (other, _) => panic!("{}: {:?}", input, other),
+            }
+        }
+        assert!(matches!(
+            Document::parse("{ a: 1x }"),
+            Err(Error::ParseError { .. })
+        ));
+        assert!(Document::parse("/* open").is_err());
+        assert!(Document::parse("1 2").is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Field-level encryption
+//!
+//! [`encrypt_fields`] replaces the values at chosen JSONPaths with sealed
+//! envelopes and leaves the rest of the document readable, so records with a
+//! few sensitive fields can still be searched and indexed:
+//!
+//! ```
+//! use kjson::encrypt::{decrypt_fields, encrypt_fields, FieldKey};
+//!
+//! let key = FieldKey::new([7; 32]).with_key_id("pii-2025");
+//! let original = kjson::parse("{ id: 1n, user: { name: 'Ada', ssn: '078-05-1120' } }")?;
+//! let mut doc = original.clone();
+//! encrypt_fields(&mut doc, &["$.user.ssn"], &key)?;
+//! assert_eq!(doc.pointer("/user/name"), original.pointer("/user/name"));
+//! assert_ne!(doc.pointer("/user/ssn"), original.pointer("/user/ssn"));
+//!
+//! decrypt_fields(&mut doc, &key)?;
+//! assert_eq!(doc, original);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! An encrypted field becomes an object with a single [`ENVELOPE_FIELD`]
+//! member:
+//!
+//! ```text
+//! {
+//!     "ssn": {
+//!         "$encrypted": { "alg": "C20P1305", "kid": "pii-2025", "nonce": b64'…', "ct": b64'…' }
+//!     }
+//! }
+//! ```
+//!
+//! The plaintext is the value's canonical form, so extended types come back
+//! as they went in. The field's JSON Pointer is authenticated along with it:
+//! an envelope copied to another field, or into an array at another index,
+//! fails to decrypt.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::path::query_pointers;
+use crate::pointer::{lookup_mut, push, push_index};
+use crate::serializer::to_canonical_string;
+use crate::value::{Map, Value};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::fmt;
+
+/// Member holding the sealed value in an envelope
+pub const ENVELOPE_FIELD: &str = "$encrypted";
+
+/// Name of the cipher in the `alg` field
+const ALGORITHM: &str = "C20P1305";
+
+/// Bytes of a ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit ChaCha20-Poly1305 key
+#[derive(Clone)]
+pub struct FieldKey {
+    cipher: ChaCha20Poly1305,
+    key_id: Option<String>,
+}
+
+impl FieldKey {
+    /// Key from 32 secret bytes
+    pub fn new(key: [u8; 32]) -> Self {
+        FieldKey {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            key_id: None,
+        }
+    }
+
+    /// Record `key_id` in envelopes sealed with this key, and refuse to
+    /// open envelopes that name a different one
+    pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// Identifier recorded in the `kid` field
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+}
+
+impl fmt::Debug for FieldKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FieldKey")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Encrypt the values every JSONPath in `paths` matches, returning how many
+/// were sealed.
+///
+/// Values that are already envelopes are left alone, so running this again
+/// over a partly encrypted document is harmless.
+pub fn encrypt_fields(value: &mut Value, paths: &[&str], key: &FieldKey) -> Result<usize> {
+    let mut pointers = Vec::new();
+    for path in paths {
+        pointers.extend(query_pointers(value, path)?);
+    }
+    pointers.sort();
+    pointers.dedup();
+
+    let mut sealed = 0;
+    for pointer in pointers {
+        // A match inside an earlier match that is now sealed is gone
+        let Some(field) = lookup_mut(value, &pointer) else {
+            continue;
+        };
+        if is_envelope(field) {
+            continue;
+        }
+        *field = seal(field, &pointer, key)?;
+        sealed += 1;
+    }
+    Ok(sealed)
+}
+
+/// Decrypt every envelope in `value`, wherever it is, returning how many
+/// were opened
+pub fn decrypt_fields(value: &mut Value, key: &FieldKey) -> Result<usize> {
+    let mut opened = 0;
+    decrypt_at(value, String::new(), key, &mut opened)?;
+    Ok(opened)
+}
+
+/// Whether `value` is an envelope written by [`encrypt_fields`]
+pub fn is_envelope(value: &Value) -> bool {
+    matches!(value, Value::Object(map) if map.len() == 1 && map.contains_key(ENVELOPE_FIELD))
+}
+
+fn decrypt_at(
+    value: &mut Value,
+    pointer: String,
+    key: &FieldKey,
+    opened: &mut usize,
+) -> Result<()> {
+    if is_envelope(value) {
+        *value = open(value, &pointer, key)?;
+        *opened += 1;
+        return Ok(());
+    }
+    match value {
+        Value::Object(map) => {
+            for (name, child) in map.iter_mut() {
+                decrypt_at(child, push(&pointer, name), key, opened)?;
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                decrypt_at(child, push_index(&pointer, i), key, opened)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn seal(value: &Value, pointer: &str, key: &FieldKey) -> Result<Value> {
+    let plaintext = to_canonical_string(value)?;
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = key
+        .cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: pointer.as_bytes(),
+            },
+        )
+        .map_err(|_| Error::Encryption(format!("could not encrypt {}", pointer)))?;
+
+    let mut sealed = Map::new();
+    sealed.insert("alg", Value::string(ALGORITHM.to_string()));
+    if let Some(kid) = &key.key_id {
+        sealed.insert("kid", Value::string(kid.clone()));
+    }
+    sealed.insert("nonce", Value::Binary(nonce.to_vec()));
+    sealed.insert("ct", Value::Binary(ciphertext));
+    let mut envelope = Map::new();
+    envelope.insert(ENVELOPE_FIELD, Value::Object(sealed));
+    Ok(Value::Object(envelope))
+}
+
+fn open(envelope: &Value, pointer: &str, key: &FieldKey) -> Result<Value> {
+    let invalid = |message: &str| Error::Encryption(format!("{} at {}", message, pointer));
+    let Some(Value::Object(sealed)) = envelope.as_object().and_then(|e| e.get(ENVELOPE_FIELD))
+    else {
+        return Err(invalid("malformed envelope"));
+    };
+    match sealed.get("alg") {
+        Some(Value::String(alg)) if alg.as_str() == ALGORITHM => {}
+        _ => return Err(invalid("unknown algorithm")),
+    }
+    let kid = match sealed.get("kid") {
+        None => None,
+        Some(Value::String(kid)) => Some(kid.as_str()),
+        Some(_) => return Err(invalid("malformed kid")),
+    };
+    if let (Some(kid), Some(expected)) = (kid, key.key_id()) {
+        if kid != expected {
+            return Err(invalid(&format!(
+                "sealed with key {}, not {}",
+                kid, expected
+            )));
+        }
+    }
+    let (Some(Value::Binary(nonce)), Some(Value::Binary(ciphertext))) =
+        (sealed.get("nonce"), sealed.get("ct"))
+    else {
+        return Err(invalid("malformed envelope"));
+    };
+    if nonce.len() != NONCE_LEN {
+        return Err(invalid("malformed nonce"));
+    }
+
+    let plaintext = key
+        .cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: pointer.as_bytes(),
+            },
+        )
+        .map_err(|_| invalid("wrong key or tampered envelope"))?;
+    let plaintext = String::from_utf8(plaintext).map_err(|_| invalid("plaintext is not UTF-8"))?;
+    parse(&plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "{
+        id: 1n,
+        users: [
+            { name: 'Ada', card: { number: '4111', expires: 2030-01-01T00:00:00Z } },
+            { name: 'Bob', card: null },
+        ],
+    }";
+
+    #[test]
+    fn test_roundtrip() {
+        let key = FieldKey::new([1; 32]);
+        let original = parse(DOC).unwrap();
+        let mut doc = original.clone();
+        assert_eq!(
+            encrypt_fields(&mut doc, &["$.users[*].card", "$..number"], &key).unwrap(),
+            2
+        );
+        assert!(is_envelope(doc.pointer("/users/0/card").unwrap()));
+        assert_eq!(
+            doc.pointer("/users/1/name"),
+            original.pointer("/users/1/name")
+        );
+
+        // Already sealed fields are skipped
+        assert_eq!(
+            encrypt_fields(&mut doc, &["$.users[*].card"], &key).unwrap(),
+            0
+        );
+        assert_eq!(decrypt_fields(&mut doc, &key).unwrap(), 2);
+        assert_eq!(doc, original);
+        assert!(encrypt_fields(&mut doc, &["users"], &key).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_large_integer() {
+        let key = FieldKey::new([1; 32]);
+        let original = parse("{ id: 9007199254740993 }").unwrap();
+        let mut doc = original.clone();
+        assert_eq!(encrypt_fields(&mut doc, &["$.id"], &key).unwrap(), 1);
+        assert_eq!(decrypt_fields(&mut doc, &key).unwrap(), 1);
+        assert_eq!(doc, original);
+        assert_eq!(doc.pointer("/id"), Some(&Value::Integer(9007199254740993)));
+
+        // A rounded whole float stays a Number, not the integer of its digits
+        let mut doc = Value::from_iter([("n", Value::Number(2.2397371079854246e18))]);
+        encrypt_fields(&mut doc, &["$.n"], &key).unwrap();
+        decrypt_fields(&mut doc, &key).unwrap();
+        assert!(matches!(doc.pointer("/n"), Some(Value::Number(n)) if *n == 2.2397371079854246e18));
+    }
+
+    #[test]
+    fn test_rejected_envelopes() {
+        let key = FieldKey::new([1; 32]).with_key_id("a");
+        let mut doc = parse(DOC).unwrap();
+        encrypt_fields(&mut doc, &["$.users[*].name"], &key).unwrap();
+
+        let err = decrypt_fields(&mut doc.clone(), &FieldKey::new([2; 32])).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::Encryption);
+        let err =
+            decrypt_fields(&mut doc.clone(), &FieldKey::new([1; 32]).with_key_id("b")).unwrap_err();
+        assert!(err.to_string().contains("sealed with key a, not b"));
+
+        // Swapping two sealed fields breaks both
+        let mut swapped = doc.clone();
+        if let Some(Value::Array(users)) = lookup_mut(&mut swapped, "/users") {
+            users.swap(0, 1);
+        }
+        let err = decrypt_fields(&mut swapped, &key).unwrap_err();
+        assert!(err.to_string().contains("tampered"));
+        assert_eq!(decrypt_fields(&mut doc, &key).unwrap(), 2);
+    }
+}
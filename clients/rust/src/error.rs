@@ -1,3 +1,4 @@
+use crate::span::{Location, SourceMap};
 use std::fmt;
 use thiserror::Error;
 
@@ -44,6 +45,54 @@ pub enum Error {
     #[error("Invalid Duration: {0}")]
     InvalidDuration(String),
 
+    /// Invalid kJSONB binary data
+    #[error("Invalid kJSONB: {0}")]
+    InvalidBinary(String),
+
+    /// Compression codec not enabled in this build
+    #[error("Unsupported compression: {0} (enable the corresponding feature)")]
+    UnsupportedCompression(String),
+
+    /// Invalid schema document
+    #[error("Invalid schema: {0}")]
+    InvalidSchema(String),
+
+    /// Conversion to or from another data format failed
+    #[error("Conversion error: {0}")]
+    Conversion(String),
+
+    /// Signature missing, malformed or not matching
+    #[error("Invalid signature: {0}")]
+    InvalidSignature(String),
+
+    /// Field could not be encrypted, or an envelope could not be decrypted
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    /// Configuration source or shape error
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    /// Variable reference that could not be expanded
+    #[error("Substitution error: {0}")]
+    Substitution(String),
+
+    /// Reference that is missing, malformed or cyclic
+    #[error("Invalid reference: {0}")]
+    InvalidReference(String),
+
+    /// JSON Pointer that is malformed or does not match the document
+    #[error("Invalid pointer: {0}")]
+    InvalidPointer(String),
+
+    /// Document could not be migrated to the current version
+    #[error("Migration error: {0}")]
+    Migration(String),
+
+    /// HTTP transport error or unexpected response
+    #[error("HTTP error: {0}")]
+    Http(String),
+
     /// Serialization error
     #[error("Serialization error: {0}")]
     SerializationError(String),
@@ -57,10 +106,34 @@ pub enum Error {
         actual: String,
     },
 
+    /// A value that could not be deserialized into a Rust type
+    #[error("{}", describe_deserialize(.path, .message, .found))]
+    Deserialize {
+        /// JSON Pointer of the value, empty for the document root
+        path: String,
+        /// What serde expected, e.g. "expected u64"
+        message: String,
+        /// kJSON type of the value when the error is a type mismatch
+        found: Option<String>,
+    },
+
     /// Unexpected end of input
     #[error("Unexpected end of input")]
     UnexpectedEof,
 
+    /// Arrays and objects nested deeper than the configured limit
+    #[error("Nesting deeper than {limit} levels at position {position}")]
+    DepthLimit {
+        /// Position of the array or object that went too deep
+        position: usize,
+        /// The configured maximum depth
+        limit: usize,
+    },
+
+    /// Input larger than a configured limit
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+
     /// Custom serde error
     #[error("Serde error: {0}")]
     Custom(String),
@@ -70,6 +143,163 @@ pub enum Error {
     IoError(#[from] std::io::Error),
 }
 
+/// What kind of failure an [`Error`] is, for branching without matching
+/// messages. New kinds may be added; match with a wildcard arm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input is not valid kJSON
+    Syntax,
+    /// The input ended in the middle of a value
+    UnexpectedEof,
+    /// Arrays and objects nested deeper than allowed
+    DepthLimit,
+    /// A literal of the right shape whose value is invalid, such as
+    /// `2025-02-30T00:00:00Z`
+    InvalidLiteral {
+        /// The type the literal was read as
+        kind: LiteralKind,
+    },
+    /// Reading or writing failed
+    Io,
+    /// A value of the wrong type where another was expected
+    TypeMismatch,
+    /// Input larger than a configured limit
+    LimitExceeded,
+    /// A value that does not fit the target format or Rust type
+    Conversion,
+    /// Malformed kJSONB data or an unavailable compression codec
+    Binary,
+    /// A schema that could not be compiled
+    Schema,
+    /// A signature that is missing, malformed or does not verify
+    Signature,
+    /// A field that could not be encrypted, or an envelope that is
+    /// malformed or fails to decrypt
+    Encryption,
+    /// Configuration loading, substitution, references or migrations
+    Config,
+    /// An HTTP transport failure or unexpected response
+    Http,
+    /// A JSON Pointer that is malformed or does not match the document
+    Pointer,
+}
+
+/// The type of an invalid literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LiteralKind {
+    /// A plain number
+    Number,
+    /// A number with the `n` suffix
+    BigInt,
+    /// A number with the `m` suffix
+    Decimal128,
+    /// A UUID
+    Uuid,
+    /// A date or timestamp
+    Date,
+    /// An instant
+    Instant,
+    /// An ISO 8601 duration
+    Duration,
+}
+
+impl Error {
+    /// What kind of failure this is
+    pub fn kind(&self) -> ErrorKind {
+        let literal = |kind| ErrorKind::InvalidLiteral { kind };
+        match self {
+            Error::ParseError { .. } => ErrorKind::Syntax,
+            Error::UnexpectedEof => ErrorKind::UnexpectedEof,
+            Error::DepthLimit { .. } => ErrorKind::DepthLimit,
+            Error::LimitExceeded(_) => ErrorKind::LimitExceeded,
+            Error::InvalidNumber(_) => literal(LiteralKind::Number),
+            Error::InvalidBigInt(_) => literal(LiteralKind::BigInt),
+            Error::InvalidDecimal128(_) => literal(LiteralKind::Decimal128),
+            Error::InvalidUuid(_) => literal(LiteralKind::Uuid),
+            Error::InvalidDate(_) => literal(LiteralKind::Date),
+            Error::InvalidInstant(_) => literal(LiteralKind::Instant),
+            Error::InvalidDuration(_) => literal(LiteralKind::Duration),
+            Error::IoError(_) => ErrorKind::Io,
+            Error::TypeMismatch { .. }
+            | Error::Deserialize {
+                found: Some(_), ..
+            } => ErrorKind::TypeMismatch,
+            Error::Deserialize { found: None, .. } => ErrorKind::Conversion,
+            Error::Conversion(_) | Error::SerializationError(_) | Error::Custom(_) => {
+                ErrorKind::Conversion
+            }
+            Error::InvalidBinary(_) | Error::UnsupportedCompression(_) => ErrorKind::Binary,
+            Error::InvalidSchema(_) => ErrorKind::Schema,
+            Error::InvalidSignature(_) => ErrorKind::Signature,
+            Error::Encryption(_) => ErrorKind::Encryption,
+            Error::Config(_)
+            | Error::Substitution(_)
+            | Error::InvalidReference(_)
+            | Error::Migration(_) => ErrorKind::Config,
+            Error::Http(_) => ErrorKind::Http,
+            Error::InvalidPointer(_) => ErrorKind::Pointer,
+        }
+    }
+
+    /// Whether the input ended too soon, so more of it might parse
+    pub fn is_eof(&self) -> bool {
+        self.kind() == ErrorKind::UnexpectedEof
+    }
+
+    /// Whether the input is not valid kJSON
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    /// Whether a literal had the right shape but an invalid value
+    pub fn is_invalid_literal(&self) -> bool {
+        matches!(self.kind(), ErrorKind::InvalidLiteral { .. })
+    }
+
+    /// Whether reading or writing failed
+    pub fn is_io(&self) -> bool {
+        self.kind() == ErrorKind::Io
+    }
+
+    /// Whether a depth or size limit was exceeded
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self.kind(),
+            ErrorKind::DepthLimit | ErrorKind::LimitExceeded
+        )
+    }
+
+    /// Byte offset in the input, for errors that have one
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Error::ParseError { position, .. } | Error::DepthLimit { position, .. } => {
+                Some(*position)
+            }
+            _ => None,
+        }
+    }
+
+    /// Line and column in the input, for errors that have a position
+    pub fn location(&self, map: &SourceMap) -> Option<Location> {
+        self.position().map(|position| map.location(position))
+    }
+}
+
+fn describe_deserialize(path: &str, message: &str, found: &Option<String>) -> String {
+    let mut text = message.to_string();
+    if !path.is_empty() {
+        text.push_str(" at ");
+        text.push_str(path);
+    }
+    if let Some(found) = found {
+        text.push_str(", found ");
+        text.push_str(found);
+    }
+    text
+}
+
 impl serde::de::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::Custom(msg.to_string())
@@ -80,4 +310,43 @@ impl serde::ser::Error for Error {
     fn custom<T: fmt::Display>(msg: T) -> Self {
         Error::Custom(msg.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_kind() {
+        let err = parse("{ a: 1,, }").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Syntax);
+        assert!(err.is_syntax() && !err.is_eof());
+        assert_eq!(err.position(), Some(7));
+        let map = SourceMap::new("{ a: 1,, }");
+        assert_eq!(err.location(&map), Some(Location { line: 1, column: 8 }));
+
+        let err = parse("[1, 2").unwrap_err();
+        assert!(err.is_eof());
+        assert_eq!(err.position(), None);
+
+        let err = parse("-").unwrap_err();
+        assert_eq!(
+            err.kind(),
+            ErrorKind::InvalidLiteral {
+                kind: LiteralKind::Number
+            }
+        );
+        assert!(err.is_invalid_literal());
+
+        let err = Error::InvalidDate("2025-02-30".to_string());
+        assert!(err.is_invalid_literal() && !err.is_syntax());
+
+        let err = Error::from(std::io::Error::other("closed"));
+        assert!(err.is_io());
+        assert_eq!(
+            Error::Substitution("x".to_string()).kind(),
+            ErrorKind::Config
+        );
+    }
+}
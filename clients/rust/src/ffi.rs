@@ -0,0 +1,576 @@
+//! C ABI
+//!
+//! Exports `kjson_*` functions for C, C++ and any language with a C foreign
+//! function interface, declared in `include/kjson_ffi.h`. As with the
+//! `python` feature, the symbols are exported from any `cdylib` or
+//! `staticlib` that depends on kjson with the `ffi` feature.
+//!
+//! ```c
+//! char *error = NULL;
+//! kjson_value *doc = kjson_parse(text, strlen(text), &error);
+//! if (!doc) {
+//!     fprintf(stderr, "%s\n", error);
+//!     kjson_string_free(error);
+//!     return;
+//! }
+//! const kjson_value *id = kjson_value_object_get(doc, "id", 2);
+//! char *digits = kjson_value_get_bigint(id);
+//! kjson_string_free(digits);
+//! kjson_value_free(doc);
+//! ```
+//!
+//! Ownership follows three rules:
+//!
+//! - `kjson_value *` returned by [`kjson_parse`] is owned by the caller and
+//!   freed with [`kjson_value_free`].
+//! - `const` pointers returned by the accessors (array items, object
+//!   values, keys, string and binary contents) borrow from the value they
+//!   were read from, and stay valid until the owning value is freed.
+//! - `char *` strings returned by the library are NUL-terminated, owned by
+//!   the caller and freed with [`kjson_string_free`].
+//!
+//! Strings passed in are UTF-8 with an explicit length and need not be
+//! NUL-terminated. Every function accepts null pointers, returning null,
+//! zero or `false`. Values are immutable, so a value may be read from
+//! several threads at once.
+
+use crate::parser::parse;
+use crate::serializer::{to_string, to_string_pretty};
+use crate::value::Value;
+use std::ffi::{c_char, CString};
+use std::ptr;
+
+/// Type of a value, as returned by [`kjson_value_type`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KjsonType {
+    /// Null, also returned for a null pointer
+    Null = 0,
+    /// Boolean
+    Bool = 1,
+    /// Floating-point number
+    Number = 2,
+    /// Integer within the i64 range
+    Integer = 3,
+    /// UTF-8 string
+    String = 4,
+    /// Array
+    Array = 5,
+    /// Object
+    Object = 6,
+    /// Arbitrary-precision integer
+    BigInt = 7,
+    /// Decimal
+    Decimal128 = 8,
+    /// UUID
+    Uuid = 9,
+    /// Date with an optional timezone offset
+    Date = 10,
+    /// Nanosecond-precision UTC timestamp
+    Instant = 11,
+    /// Duration
+    Duration = 12,
+    /// Binary data
+    Binary = 13,
+    /// Integer above the i64 range and within the u64 range
+    UInteger = 14,
+}
+
+/// Copy `s` into a caller-owned C string, or null if it holds a NUL byte
+fn into_c_string(s: String) -> *mut c_char {
+    CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Borrow a string from C, or `None` for a null pointer or invalid UTF-8
+unsafe fn str_from<'a>(ptr: *const c_char, len: usize) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    std::str::from_utf8(std::slice::from_raw_parts(ptr.cast(), len)).ok()
+}
+
+/// Parse `len` bytes of kJSON text.
+///
+/// Returns null on failure, and if `error` is not null, stores a message in
+/// it for the caller to free with [`kjson_string_free`].
+///
+/// # Safety
+///
+/// `input` must point to `len` readable bytes, and `error` must be null or
+/// point to writable storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_parse(
+    input: *const c_char,
+    len: usize,
+    error: *mut *mut c_char,
+) -> *mut Value {
+    let result = match str_from(input, len) {
+        Some(text) => parse(text).map_err(|e| e.to_string()),
+        None if input.is_null() => Err("input is null".to_string()),
+        None => Err("input is not valid UTF-8".to_string()),
+    };
+    match result {
+        Ok(value) => {
+            if !error.is_null() {
+                *error = ptr::null_mut();
+            }
+            Box::into_raw(Box::new(value))
+        }
+        Err(message) => {
+            if !error.is_null() {
+                *error = into_c_string(message);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Serialize a value as kJSON text, compact or pretty-printed.
+///
+/// Returns a string for the caller to free with [`kjson_string_free`].
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_stringify(value: *const Value, pretty: bool) -> *mut c_char {
+    let Some(value) = value.as_ref() else {
+        return ptr::null_mut();
+    };
+    let text = if pretty {
+        to_string_pretty(value)
+    } else {
+        to_string(value)
+    };
+    text.map_or(ptr::null_mut(), into_c_string)
+}
+
+/// Free a value returned by [`kjson_parse`]
+///
+/// # Safety
+///
+/// `value` must be null or an owned value from this library that has not
+/// been freed. Borrowed pointers into it are invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_free(value: *mut Value) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// Free a string returned by this library
+///
+/// # Safety
+///
+/// `s` must be null or a `char *` returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// The type of a value
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_type(value: *const Value) -> KjsonType {
+    match value.as_ref() {
+        None | Some(Value::Null) => KjsonType::Null,
+        Some(Value::Bool(_)) => KjsonType::Bool,
+        Some(Value::Number(_)) => KjsonType::Number,
+        Some(Value::Integer(_)) => KjsonType::Integer,
+        Some(Value::UInteger(_)) => KjsonType::UInteger,
+        Some(Value::String(_)) => KjsonType::String,
+        Some(Value::Array(_)) => KjsonType::Array,
+        Some(Value::Object(_)) => KjsonType::Object,
+        Some(Value::BigInt(_)) => KjsonType::BigInt,
+        Some(Value::Decimal128(_)) => KjsonType::Decimal128,
+        Some(Value::Uuid(_)) => KjsonType::Uuid,
+        Some(Value::Date(_)) => KjsonType::Date,
+        Some(Value::Instant(_)) => KjsonType::Instant,
+        Some(Value::Duration(_)) => KjsonType::Duration,
+        Some(Value::Binary(_)) => KjsonType::Binary,
+    }
+}
+
+/// Write `value` through `out` if it is `Some` and `out` is not null
+unsafe fn store<T>(out: *mut T, value: Option<T>) -> bool {
+    match value {
+        Some(value) if !out.is_null() => {
+            *out = value;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Read a boolean into `out`, returning whether the value is one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_bool(value: *const Value, out: *mut bool) -> bool {
+    store(out, value.as_ref().and_then(Value::as_bool))
+}
+
+/// Read an Integer, or a whole Number or BigInt within the i64 range, into
+/// `out`, returning whether the value is one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_i64(value: *const Value, out: *mut i64) -> bool {
+    let n = match value.as_ref() {
+        Some(Value::BigInt(b)) => b.to_i64(),
+        Some(value) => value.as_i64(),
+        None => None,
+    };
+    store(out, n)
+}
+
+/// Read a non-negative Integer, UInteger, or whole Number or BigInt within
+/// the u64 range, into `out`, returning whether the value is one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_u64(value: *const Value, out: *mut u64) -> bool {
+    let n = match value.as_ref() {
+        Some(Value::BigInt(b)) => b.to_i128().and_then(|n| u64::try_from(n).ok()),
+        Some(value) => value.as_u64(),
+        None => None,
+    };
+    store(out, n)
+}
+
+/// Read a Number, Integer or UInteger into `out`, returning whether the value is one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_f64(value: *const Value, out: *mut f64) -> bool {
+    store(out, value.as_ref().and_then(Value::as_f64))
+}
+
+/// The UTF-8 contents of a string, not NUL-terminated, with their length
+/// stored in `len`. Null if the value is not a string.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `len` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_string(
+    value: *const Value,
+    len: *mut usize,
+) -> *const c_char {
+    match value.as_ref().and_then(Value::as_str) {
+        Some(s) => {
+            store(len, Some(s.len()));
+            s.as_ptr().cast()
+        }
+        None => ptr::null(),
+    }
+}
+
+/// The bytes of binary data, with their length stored in `len`. Null if
+/// the value is not binary.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `len` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_binary(value: *const Value, len: *mut usize) -> *const u8 {
+    match value.as_ref().and_then(Value::as_bytes) {
+        Some(bytes) => {
+            store(len, Some(bytes.len()));
+            bytes.as_ptr()
+        }
+        None => ptr::null(),
+    }
+}
+
+/// The decimal digits of a BigInt, such as `-123`, for the caller to free
+/// with [`kjson_string_free`]. Null if the value is not a BigInt.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_bigint(value: *const Value) -> *mut c_char {
+    match value.as_ref().and_then(Value::as_bigint) {
+        Some(b) => into_c_string(b.to_string()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// A Decimal128 as a plain decimal string, such as `19.990`, for the
+/// caller to free with [`kjson_string_free`]. Null if the value is not a
+/// Decimal128.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_decimal(value: *const Value) -> *mut c_char {
+    match value.as_ref().and_then(Value::as_decimal128) {
+        Some(d) => into_c_string(d.to_string()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Copy the 16 bytes of a UUID into `out`, returning whether the value is
+/// one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable for 16 bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_uuid(value: *const Value, out: *mut [u8; 16]) -> bool {
+    store(out, value.as_ref().and_then(Value::as_uuid).map(|u| *u.as_bytes()))
+}
+
+/// Read an Instant, or a Date, as nanoseconds since the Unix epoch into
+/// `out`, returning whether the value is one. Dates outside the years 1677
+/// to 2262 do not fit and return `false`.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_timestamp(value: *const Value, out: *mut i64) -> bool {
+    let nanos = match value.as_ref() {
+        Some(Value::Instant(i)) => Some(i.epoch_nanos()),
+        Some(Value::Date(d)) => d.utc.timestamp_nanos_opt(),
+        _ => None,
+    };
+    store(out, nanos)
+}
+
+/// Read a Duration in nanoseconds into `out`, returning whether the value
+/// is one
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `out` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_get_duration(value: *const Value, out: *mut i64) -> bool {
+    store(out, value.as_ref().and_then(Value::as_duration).map(|d| d.total_nanos()))
+}
+
+/// Number of items in an array, or entries in an object; 0 for other values
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_len(value: *const Value) -> usize {
+    match value.as_ref() {
+        Some(Value::Array(items)) => items.len(),
+        Some(Value::Object(map)) => map.len(),
+        _ => 0,
+    }
+}
+
+/// The array item at `index`, borrowed from `value`. Null if the value is
+/// not an array or `index` is out of range.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_array_get(value: *const Value, index: usize) -> *const Value {
+    match value.as_ref() {
+        Some(Value::Array(items)) => items.get(index).map_or(ptr::null(), |item| item as *const _),
+        _ => ptr::null(),
+    }
+}
+
+/// The object value under the `key_len` bytes of `key`, borrowed from
+/// `value`. Null if the value is not an object or has no such key.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `key` point
+/// to `key_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_object_get(
+    value: *const Value,
+    key: *const c_char,
+    key_len: usize,
+) -> *const Value {
+    match (value.as_ref(), str_from(key, key_len)) {
+        (Some(Value::Object(map)), Some(key)) => {
+            map.get(key).map_or(ptr::null(), |item| item as *const _)
+        }
+        _ => ptr::null(),
+    }
+}
+
+/// The object entry at `index`, for iterating over every entry: its value
+/// is returned, and its key stored in `key` and `key_len` (not
+/// NUL-terminated), all borrowed from `value`. Null if the value is not an
+/// object or `index` is out of range.
+///
+/// Entries are in insertion order with the `preserve_order` feature, and
+/// otherwise in an unspecified order that stays the same for a given value.
+///
+/// # Safety
+///
+/// `value` must be null or a live value from this library, and `key` and
+/// `key_len` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn kjson_value_object_entry(
+    value: *const Value,
+    index: usize,
+    key: *mut *const c_char,
+    key_len: *mut usize,
+) -> *const Value {
+    let Some(Value::Object(map)) = value.as_ref() else {
+        return ptr::null();
+    };
+    match map.iter().nth(index) {
+        Some((k, v)) => {
+            store(key, Some(k.as_ptr().cast()));
+            store(key_len, Some(k.len()));
+            v as *const _
+        }
+        None => ptr::null(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    unsafe fn parse_ok(text: &str) -> *mut Value {
+        let mut error = ptr::null_mut();
+        let value = kjson_parse(text.as_ptr().cast(), text.len(), &mut error);
+        assert!(!value.is_null() && error.is_null());
+        value
+    }
+
+    unsafe fn take_string(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let owned = CStr::from_ptr(s).to_str().unwrap().to_string();
+        kjson_string_free(s);
+        owned
+    }
+
+    #[test]
+    fn test_parse_and_read() {
+        unsafe {
+            let doc = parse_ok(
+                "{ id: -12n, price: 19.990m, name: 'caf\u{e9}', ok: true, n: 2.5, \
+                 tags: [1, b64'AQI='], at: 2025-01-01T00:00:00Z, ttl: PT1S, \
+                 ref: 550e8400-e29b-41d4-a716-446655440000 }",
+            );
+            assert_eq!(kjson_value_type(doc), KjsonType::Object);
+            assert_eq!(kjson_value_len(doc), 9);
+            let get = |key: &str| kjson_value_object_get(doc, key.as_ptr().cast(), key.len());
+
+            let mut n = 0i64;
+            assert!(kjson_value_get_i64(get("id"), &mut n));
+            assert_eq!(n, -12);
+            assert_eq!(take_string(kjson_value_get_bigint(get("id"))), "-12");
+            assert_eq!(take_string(kjson_value_get_decimal(get("price"))), "19.990");
+
+            let mut len = 0;
+            let name = kjson_value_get_string(get("name"), &mut len);
+            assert_eq!(std::slice::from_raw_parts(name.cast::<u8>(), len), "café".as_bytes());
+
+            let mut ok = false;
+            assert!(kjson_value_get_bool(get("ok"), &mut ok) && ok);
+            let mut f = 0.0;
+            assert!(kjson_value_get_f64(get("n"), &mut f));
+            assert_eq!(f, 2.5);
+            assert!(!kjson_value_get_i64(get("n"), &mut n));
+
+            let tags = get("tags");
+            assert_eq!(kjson_value_type(kjson_value_array_get(tags, 1)), KjsonType::Binary);
+            let bytes = kjson_value_get_binary(kjson_value_array_get(tags, 1), &mut len);
+            assert_eq!(std::slice::from_raw_parts(bytes, len), [1, 2]);
+            assert!(kjson_value_array_get(tags, 2).is_null());
+
+            assert!(kjson_value_get_timestamp(get("at"), &mut n));
+            assert_eq!(n, 1_735_689_600_000_000_000);
+            assert!(kjson_value_get_duration(get("ttl"), &mut n));
+            assert_eq!(n, 1_000_000_000);
+            let mut uuid = [0u8; 16];
+            assert!(kjson_value_get_uuid(get("ref"), &mut uuid));
+            assert_eq!(uuid[0], 0x55);
+
+            let mut keys = Vec::new();
+            for i in 0..kjson_value_len(doc) {
+                let (mut key, mut key_len) = (ptr::null(), 0);
+                assert!(!kjson_value_object_entry(doc, i, &mut key, &mut key_len).is_null());
+                let key = std::slice::from_raw_parts(key.cast(), key_len);
+                keys.push(std::str::from_utf8(key).unwrap());
+            }
+            keys.sort();
+            assert_eq!(keys[0], "at");
+            assert!(kjson_value_object_entry(doc, 9, ptr::null_mut(), ptr::null_mut()).is_null());
+
+            assert!(get("missing").is_null());
+            assert_eq!(kjson_value_type(get("missing")), KjsonType::Null);
+            kjson_value_free(doc);
+        }
+    }
+
+    #[test]
+    fn test_read_u64() {
+        unsafe {
+            let doc = parse_ok("[18446744073709551615, 7, -1]");
+            let max = kjson_value_array_get(doc, 0);
+            assert_eq!(kjson_value_type(max), KjsonType::UInteger);
+            let mut n = 0u64;
+            assert!(kjson_value_get_u64(max, &mut n));
+            assert_eq!(n, u64::MAX);
+            assert!(kjson_value_get_u64(kjson_value_array_get(doc, 1), &mut n));
+            assert_eq!(n, 7);
+            assert!(!kjson_value_get_u64(kjson_value_array_get(doc, 2), &mut n));
+            let mut i = 0i64;
+            assert!(!kjson_value_get_i64(max, &mut i));
+            kjson_value_free(doc);
+        }
+    }
+
+    #[test]
+    fn test_stringify_and_errors() {
+        unsafe {
+            let doc = parse_ok("{ b: [1, 2n] }");
+            assert_eq!(take_string(kjson_stringify(doc, false)), "{b: [1, 2n]}");
+            assert!(take_string(kjson_stringify(doc, true)).contains('\n'));
+            kjson_value_free(doc);
+
+            let mut error = ptr::null_mut();
+            let text = "{ a: ";
+            assert!(kjson_parse(text.as_ptr().cast(), text.len(), &mut error).is_null());
+            assert!(!take_string(error).is_empty());
+            assert!(kjson_parse([0xffu8].as_ptr().cast(), 1, &mut error).is_null());
+            assert_eq!(take_string(error), "input is not valid UTF-8");
+            assert!(kjson_parse(ptr::null(), 0, ptr::null_mut()).is_null());
+
+            assert!(kjson_stringify(ptr::null(), false).is_null());
+            kjson_value_free(ptr::null_mut());
+            kjson_string_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Source formatting
+//!
+//! [`reformat`] rewrites a document in a consistent style without losing
+//! anything a person wrote: comments, key order, blank lines between
+//! members and the literal form of numbers (`1.50m` stays `1.50m`) are kept,
+//! while indentation, quotes and commas are normalized.
+//!
+//! ```
+//! use kjson::format::{reformat, FormatOptions};
+//!
+//! let input = "{\"name\":\"shop\", // display name\n ports:[80,443],}";
+//! assert_eq!(
+//!     reformat(input, &FormatOptions::default())?,
+//!     "{\n  name: 'shop', // display name\n  ports: [80, 443]\n}\n"
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! [`minify`] goes the other way, dropping everything that is not data.
+//!
+//! An array or object stays on one line when it has no comments and fits
+//! within [`FormatOptions::max_width`]; otherwise it gets one member per
+//! line.
+
+use crate::edit::{Document, Item, Member, Scalar};
+use crate::error::Result;
+use crate::serializer::{key_to_string, to_string, write_canonical_string};
+use crate::value::Value;
+
+/// How strings and keys are quoted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// The quote needing the fewest escapes, preferring single quotes, and
+    /// bare keys where possible, as [`to_string`](crate::to_string) writes
+    #[default]
+    Smart,
+    /// Double quotes for every string and key, as in JSON
+    Double,
+}
+
+/// Style settings for [`reformat`]
+#[derive(Debug, Clone, Copy)]
+pub struct FormatOptions {
+    /// Spaces per nesting level
+    pub indent: usize,
+    /// Line width that inline arrays and objects must fit in
+    pub max_width: usize,
+    /// Whether the last member of a multi-line array or object gets a comma
+    pub trailing_commas: bool,
+    /// Quoting of strings and keys
+    pub quotes: QuoteStyle,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: 2,
+            max_width: 80,
+            trailing_commas: false,
+            quotes: QuoteStyle::Smart,
+        }
+    }
+}
+
+/// Reformat a document, keeping its comments and key order
+pub fn reformat(input: &str, options: &FormatOptions) -> Result<String> {
+    let doc = Document::parse(input)?;
+    let mut out = Formatter {
+        buf: String::new(),
+        options,
+    };
+    let leading = comments(&doc.prefix);
+    for (i, comment) in leading.iter().enumerate() {
+        if i > 0 && comment.blank_before {
+            out.buf.push('\n');
+        }
+        out.buf.push_str(comment.text);
+        out.buf.push('\n');
+    }
+    if leading.last().is_some_and(|c| c.blank_after) {
+        out.buf.push('\n');
+    }
+    out.item(&doc.root, 0);
+    for comment in comments(&doc.suffix) {
+        out.buf.push(if comment.own_line { '\n' } else { ' ' });
+        out.buf.push_str(comment.text);
+    }
+    out.buf.push('\n');
+    Ok(out.buf)
+}
+
+/// Strip comments, whitespace and trailing commas.
+///
+/// Strings, keys and literals are copied exactly as written, so the result
+/// parses to the same value without a round trip through [`Value`].
+pub fn minify(input: &str) -> Result<String> {
+    let doc = Document::parse(input)?;
+    let mut out = String::with_capacity(input.len());
+    write_minified(&mut out, &doc.root);
+    Ok(out)
+}
+
+fn write_minified(out: &mut String, item: &Item) {
+    match item {
+        Item::Scalar(scalar) => out.push_str(&scalar.raw),
+        Item::Array(array) => {
+            out.push('[');
+            for (i, element) in array.elements.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_minified(out, &element.value);
+            }
+            out.push(']');
+        }
+        Item::Object(object) => {
+            out.push('{');
+            for (i, member) in object.members.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&member.raw_key);
+                out.push(':');
+                write_minified(out, &member.value);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// A comment found in trivia
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Comment<'a> {
+    /// The comment, without a trailing newline
+    text: &'a str,
+    /// Whether a line break comes before it
+    own_line: bool,
+    /// Whether an empty line comes before it
+    blank_before: bool,
+    /// Whether an empty line follows it
+    blank_after: bool,
+}
+
+/// The comments in a stretch of whitespace and comments
+fn comments(trivia: &str) -> Vec<Comment<'_>> {
+    let mut found: Vec<Comment<'_>> = Vec::new();
+    let mut rest = trivia;
+    loop {
+        let start = rest.trim_start();
+        // A line comment's newline is left in `rest`, so it is counted here
+        let newlines = rest[..rest.len() - start.len()].matches('\n').count();
+        if let Some(last) = found.last_mut() {
+            last.blank_after = newlines > 1;
+        }
+        if start.is_empty() {
+            return found;
+        }
+        let len = if start.starts_with("//") {
+            start.find('\n').unwrap_or(start.len())
+        } else {
+            start.find("*/").map_or(start.len(), |i| i + 2)
+        };
+        found.push(Comment {
+            text: start[..len].trim_end(),
+            own_line: newlines > 0,
+            blank_before: newlines > 1,
+            blank_after: false,
+        });
+        rest = &start[len..];
+    }
+}
+
+/// Whether trivia has an empty line after its last comment
+fn blank_line_at_end(trivia: &str) -> bool {
+    match comments(trivia).last() {
+        Some(comment) => comment.blank_after,
+        None => trivia.matches('\n').count() > 1,
+    }
+}
+
+/// One member of an array or object
+struct Entry<'a> {
+    prefix: &'a str,
+    key: Option<&'a Member>,
+    value: &'a Item,
+    suffix: &'a str,
+}
+
+struct Formatter<'o> {
+    buf: String,
+    options: &'o FormatOptions,
+}
+
+impl Formatter<'_> {
+    fn column(&self) -> usize {
+        let line = &self.buf[self.buf.rfind('\n').map_or(0, |i| i + 1)..];
+        line.chars().count()
+    }
+
+    fn newline(&mut self, level: usize) {
+        self.buf.push('\n');
+        let width = level * self.options.indent;
+        self.buf.push_str(&" ".repeat(width));
+    }
+
+    fn item(&mut self, item: &Item, level: usize) {
+        let (open, close, entries, trailing) = match item {
+            Item::Scalar(scalar) => {
+                let text = self.scalar(scalar);
+                self.buf.push_str(&text);
+                return;
+            }
+            Item::Array(array) => (
+                '[',
+                ']',
+                array
+                    .elements
+                    .iter()
+                    .map(|e| Entry {
+                        prefix: &e.prefix,
+                        key: None,
+                        value: &e.value,
+                        suffix: &e.suffix,
+                    })
+                    .collect::<Vec<_>>(),
+                &array.close,
+            ),
+            Item::Object(object) => (
+                '{',
+                '}',
+                object
+                    .members
+                    .iter()
+                    .map(|m| Entry {
+                        prefix: &m.prefix,
+                        key: Some(m),
+                        value: &m.value,
+                        suffix: &m.suffix,
+                    })
+                    .collect(),
+                &object.close,
+            ),
+        };
+
+        if let Some(inline) = self.inline(item) {
+            if entries.is_empty()
+                || self.column() + inline.chars().count() <= self.options.max_width
+            {
+                self.buf.push_str(&inline);
+                return;
+            }
+        }
+
+        self.buf.push(open);
+        for (i, entry) in entries.iter().enumerate() {
+            for comment in comments(entry.prefix) {
+                if comment.own_line {
+                    if comment.blank_before && i > 0 {
+                        self.buf.push('\n');
+                    }
+                    self.newline(level + 1);
+                } else {
+                    // On the line of the previous member, or of the bracket
+                    self.buf.push(' ');
+                }
+                self.buf.push_str(comment.text);
+            }
+            if i > 0 && blank_line_at_end(entry.prefix) {
+                self.buf.push('\n');
+            }
+            self.newline(level + 1);
+            if let Some(member) = entry.key {
+                let key = self.key(member);
+                self.buf.push_str(&key);
+                self.buf.push_str(": ");
+            }
+            self.item(entry.value, level + 1);
+            if i + 1 < entries.len() || self.options.trailing_commas {
+                self.buf.push(',');
+            }
+            // Comments around the colon move to the end of the line too
+            let around_colon = entry
+                .key
+                .map(|m| [m.before_colon.as_str(), m.after_colon.as_str()])
+                .unwrap_or_default();
+            for comment in around_colon
+                .into_iter()
+                .chain([entry.suffix])
+                .flat_map(comments)
+            {
+                self.buf.push(' ');
+                self.buf.push_str(comment.text);
+            }
+        }
+        for comment in comments(trailing) {
+            if comment.own_line {
+                self.newline(level + 1);
+            } else {
+                self.buf.push(' ');
+            }
+            self.buf.push_str(comment.text);
+        }
+        self.newline(level);
+        self.buf.push(close);
+    }
+
+    /// The one-line form of an item, if it has no comments to keep
+    fn inline(&self, item: &Item) -> Option<String> {
+        let clean = |trivia: &str| trivia.trim().is_empty();
+        match item {
+            Item::Scalar(scalar) => {
+                let text = self.scalar(scalar);
+                (!text.contains('\n')).then_some(text)
+            }
+            Item::Array(array) => {
+                let mut parts = Vec::new();
+                for element in &array.elements {
+                    if !clean(&element.prefix) || !clean(&element.suffix) {
+                        return None;
+                    }
+                    parts.push(self.inline(&element.value)?);
+                }
+                clean(&array.close).then(|| format!("[{}]", parts.join(", ")))
+            }
+            Item::Object(object) => {
+                let mut parts = Vec::new();
+                for member in &object.members {
+                    let trivia = [
+                        &member.prefix,
+                        &member.before_colon,
+                        &member.after_colon,
+                        &member.suffix,
+                    ];
+                    if !trivia.iter().all(|t| clean(t)) {
+                        return None;
+                    }
+                    let value = self.inline(&member.value)?;
+                    parts.push(format!("{}: {}", self.key(member), value));
+                }
+                clean(&object.close).then(|| format!("{{{}}}", parts.join(", ")))
+            }
+        }
+    }
+
+    fn scalar(&self, scalar: &Scalar) -> String {
+        match &scalar.value {
+            // Multi-line template strings read better as written
+            Value::String(s) if scalar.raw.starts_with('`') && s.contains('\n') => {
+                scalar.raw.clone()
+            }
+            Value::String(s) => match self.options.quotes {
+                QuoteStyle::Smart => to_string(&scalar.value).unwrap_or_else(|_| s.to_string()),
+                QuoteStyle::Double => double_quoted(s),
+            },
+            _ => scalar.raw.clone(),
+        }
+    }
+
+    fn key(&self, member: &Member) -> String {
+        match self.options.quotes {
+            QuoteStyle::Smart => {
+                key_to_string(&member.key).unwrap_or_else(|_| member.raw_key.clone())
+            }
+            QuoteStyle::Double => double_quoted(&member.key),
+        }
+    }
+}
+
+fn double_quoted(s: &str) -> String {
+    let mut buf = Vec::new();
+    // Writing to a Vec cannot fail
+    let _ = write_canonical_string(&mut buf, s);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn fmt(input: &str) -> String {
+        reformat(input, &FormatOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn test_keeps_comments_and_order() {
+        let input = "// Service
+{ \"zone\" : 'eu',   b: [1,2,],
+
+  /* limits */
+  limits: {rps:10, // per second
+  burst: 20n},
+  price: 1.50m, // trailing
+}";
+        let expected = "// Service
+{
+  zone: 'eu',
+  b: [1, 2],
+
+  /* limits */
+  limits: {
+    rps: 10, // per second
+    burst: 20n
+  },
+  price: 1.50m // trailing
+}
+";
+        assert_eq!(fmt(input), expected);
+        assert_eq!(fmt(expected), expected);
+        assert_eq!(
+            fmt("{ a /* x */ : // y\n 1 }"),
+            "{\n  a: 1 /* x */ // y\n}\n"
+        );
+        assert_eq!(parse(&fmt(input)).unwrap(), parse(input).unwrap());
+    }
+
+    #[test]
+    fn test_wraps_long_lines() {
+        let items: Vec<String> = (0..30).map(|i| format!("'item{}'", i)).collect();
+        let input = format!("{{ list: [{}] }}", items.join(","));
+        let output = fmt(&input);
+        assert!(output.lines().all(|line| line.len() <= 80), "{}", output);
+        assert!(output.starts_with("{\n  list: [\n    'item0',\n"));
+
+        let narrow = FormatOptions {
+            max_width: 12,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            reformat("{a: [1, 2], b: 3}", &narrow).unwrap(),
+            "{\n  a: [1, 2],\n  b: 3\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_minify() {
+        let input =
+            "// header\n{ \"a b\" : 'x  y', /* c */ n: [1.50m, 2n, ], d: 2024-01-01T00:00:00Z, }\n";
+        let output = minify(input).unwrap();
+        assert_eq!(
+            output,
+            "{\"a b\":'x  y',n:[1.50m,2n],d:2024-01-01T00:00:00Z}"
+        );
+        assert_eq!(parse(&output).unwrap(), parse(input).unwrap());
+        assert!(minify("{ a: }").is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let options = FormatOptions {
+            indent: 4,
+            max_width: 0,
+            trailing_commas: true,
+            quotes: QuoteStyle::Double,
+        };
+        assert_eq!(
+            reformat("{ a: 'x', 'b-c': [] }", &options).unwrap(),
+            "{\n    \"a\": \"x\",\n    \"b-c\": [],\n}\n"
+        );
+        assert_eq!(fmt("'it\"s'"), "'it\"s'\n");
+        assert_eq!(fmt("`line\nbreak`"), "`line\nbreak`\n");
+        assert_eq!(fmt("[ // empty\n]"), "[ // empty\n]\n");
+    }
+}
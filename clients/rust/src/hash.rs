@@ -0,0 +1,298 @@
+//! Content hashing
+//!
+//! Hashes are computed over the canonical form (see
+//! [`to_canonical_string`](crate::to_canonical_string)), so equal values
+//! hash the same regardless of key order, formatting or platform.
+//!
+//! ```
+//! use kjson::hash::Sha256;
+//!
+//! let a = kjson::parse("{ b: 2, a: 1.50m }").unwrap();
+//! let b = kjson::parse(r#"{"a": 1.50m, "b": 2}"#).unwrap();
+//! assert_eq!(a.content_hash::<Sha256>(), b.content_hash::<Sha256>());
+//! ```
+//!
+//! [`Value::hash_tree`] hashes every subtree, keyed by JSON Pointer, so two
+//! copies of a large document can be reconciled by exchanging hashes and
+//! descending only into subtrees that differ.
+
+use crate::pointer;
+use crate::serializer::{canonical_entries, write_canonical, write_canonical_string};
+use crate::value::Value;
+use sha2::digest::Output;
+pub use sha2::{Digest, Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Write};
+
+/// Node type prefixes, so a leaf can never collide with a container
+const LEAF: u8 = 0;
+const ARRAY: u8 = 1;
+const OBJECT: u8 = 2;
+
+impl Value {
+    /// Digest of the value's canonical form
+    pub fn content_hash<H: Digest>(&self) -> Output<H> {
+        let mut hasher = DigestWriter(H::new());
+        write_canonical(&mut hasher, self).expect("writing to a digest cannot fail");
+        hasher.0.finalize()
+    }
+
+    /// Lowercase hex digest of the value's canonical form
+    pub fn content_hash_hex<H: Digest>(&self) -> String {
+        hex(&self.content_hash::<H>())
+    }
+
+    /// Merkle hashes of every subtree, keyed by JSON Pointer
+    pub fn hash_tree<H: Digest>(&self) -> HashTree<H> {
+        let mut tree = HashTree {
+            nodes: BTreeMap::new(),
+        };
+        tree.insert(String::new(), self);
+        tree
+    }
+}
+
+/// A node in a [`HashTree`]
+pub struct HashNode<H: Digest> {
+    /// Hash of the subtree
+    pub hash: Output<H>,
+    /// Pointers of the direct children (empty for scalars)
+    pub children: Vec<String>,
+}
+
+/// Merkle hashes of a value's subtrees, keyed by JSON Pointer.
+///
+/// A scalar hashes as `H(0x00 || canonical form)`. An array hashes as
+/// `H(0x01 || child hashes...)`, and an object as
+/// `H(0x02 || (canonical key || child hash)...)` with keys in canonical order.
+pub struct HashTree<H: Digest> {
+    nodes: BTreeMap<String, HashNode<H>>,
+}
+
+// Manual impls: the derives would needlessly require the hasher itself to be Clone/PartialEq/Debug
+
+impl<H: Digest> Clone for HashNode<H> {
+    fn clone(&self) -> Self {
+        HashNode {
+            hash: self.hash.clone(),
+            children: self.children.clone(),
+        }
+    }
+}
+
+impl<H: Digest> PartialEq for HashNode<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.children == other.children
+    }
+}
+
+impl<H: Digest> fmt::Debug for HashNode<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashNode")
+            .field("hash", &hex(&self.hash))
+            .field("children", &self.children)
+            .finish()
+    }
+}
+
+impl<H: Digest> Clone for HashTree<H> {
+    fn clone(&self) -> Self {
+        HashTree {
+            nodes: self.nodes.clone(),
+        }
+    }
+}
+
+impl<H: Digest> fmt::Debug for HashTree<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.nodes.iter()).finish()
+    }
+}
+
+impl<H: Digest> HashTree<H> {
+    /// Hash of the whole document
+    pub fn root(&self) -> &Output<H> {
+        &self.nodes[""].hash
+    }
+
+    /// Node at a JSON Pointer
+    pub fn get(&self, pointer: &str) -> Option<&HashNode<H>> {
+        self.nodes.get(pointer)
+    }
+
+    /// All nodes, in pointer order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &HashNode<H>)> {
+        self.nodes.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Number of nodes in the tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree is empty (never true for a built tree)
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Pointers of the outermost subtrees that differ between two trees.
+    ///
+    /// Matching subtrees are skipped without descending into them. Subtrees
+    /// present in only one tree are reported at their own pointer.
+    pub fn changed(&self, other: &HashTree<H>) -> Vec<String> {
+        let mut changed = Vec::new();
+        self.collect_changes(other, "", &mut changed);
+        changed
+    }
+
+    fn collect_changes(&self, other: &HashTree<H>, pointer: &str, changed: &mut Vec<String>) {
+        let (a, b) = match (self.nodes.get(pointer), other.nodes.get(pointer)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return changed.push(pointer.to_string()),
+        };
+        if a.hash == b.hash {
+            return;
+        }
+        if a.children.is_empty() || b.children.is_empty() {
+            return changed.push(pointer.to_string());
+        }
+
+        let mut children: Vec<&String> = a.children.iter().collect();
+        children.extend(b.children.iter().filter(|c| !a.children.contains(c)));
+        for child in children {
+            self.collect_changes(other, child, changed);
+        }
+    }
+
+    fn insert(&mut self, pointer: String, value: &Value) -> Output<H> {
+        let mut hasher = DigestWriter(H::new());
+        let mut children = Vec::new();
+        match value {
+            Value::Array(items) => {
+                hasher.0.update([ARRAY]);
+                for (i, item) in items.iter().enumerate() {
+                    let child = pointer::push_index(&pointer, i);
+                    hasher.0.update(self.insert(child.clone(), item));
+                    children.push(child);
+                }
+            }
+            Value::Object(obj) => {
+                hasher.0.update([OBJECT]);
+                for (key, item) in canonical_entries(obj) {
+                    let child = pointer::push(&pointer, key);
+                    write_canonical_string(&mut hasher, key)
+                        .expect("writing to a digest cannot fail");
+                    hasher.0.update(self.insert(child.clone(), item));
+                    children.push(child);
+                }
+            }
+            leaf => {
+                hasher.0.update([LEAF]);
+                write_canonical(&mut hasher, leaf).expect("writing to a digest cannot fail");
+            }
+        }
+        let hash = hasher.0.finalize();
+        self.nodes.insert(
+            pointer,
+            HashNode {
+                hash: hash.clone(),
+                children,
+            },
+        );
+        hash
+    }
+}
+
+/// Feeds written bytes into a digest
+struct DigestWriter<H>(H);
+
+impl<H: Digest> Write for DigestWriter<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::serializer::to_canonical_string;
+
+    #[test]
+    fn test_content_hash_matches_canonical_bytes() {
+        let value = parse(
+            "{ name: 'kjson', tags: [1, 2.5, 123n], id: 550e8400-e29b-41d4-a716-446655440000 }",
+        )
+        .unwrap();
+        let canonical = to_canonical_string(&value).unwrap();
+        assert_eq!(
+            value.content_hash::<Sha256>(),
+            Sha256::digest(canonical.as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_hash_tree_changes() {
+        let before = parse(
+            "{ config: { retries: 3, hosts: ['a', 'b'] }, users: [{ name: 'x' }, { name: 'y' }], v: 1 }",
+        )
+        .unwrap();
+        let after = parse(
+            "{ config: { retries: 3, hosts: ['a', 'c'] }, users: [{ name: 'x' }], v: 1, new: null }",
+        )
+        .unwrap();
+        let (a, b) = (before.hash_tree::<Sha256>(), after.hash_tree::<Sha256>());
+
+        assert_ne!(a.root(), b.root());
+        assert_eq!(a.get("/config/retries"), b.get("/config/retries"));
+        assert_eq!(a.changed(&b), vec!["/config/hosts/1", "/users/1", "/new"]);
+        assert!(a.changed(&before.hash_tree()).is_empty());
+        assert_eq!(a.len(), 12);
+    }
+
+    #[test]
+    fn test_hash_tree_distinguishes_structure() {
+        // Same leaves in different shapes must not collide
+        let flat = parse("[1, 2]").unwrap().hash_tree::<Sha256>();
+        let nested = parse("[[1, 2]]").unwrap().hash_tree::<Sha256>();
+        let object = parse("{ '0': 1, '1': 2 }").unwrap().hash_tree::<Sha256>();
+        assert_ne!(flat.root(), nested.root());
+        assert_ne!(flat.root(), object.root());
+        assert_eq!(flat.get("/0"), object.get("/0"));
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        // Pinned so any change to the canonical form is caught
+        assert_eq!(
+            parse("{ b: [true, null], a: 'x' }")
+                .unwrap()
+                .content_hash_hex::<Sha256>(),
+            // sha256 of {"a":"x","b":[true,null]}
+            "d9ec2bee8e626fb331661b82f979e044e8a57c790db02151d54c3e7be8135bee"
+        );
+        assert_ne!(
+            parse("{ a: 1.5m }").unwrap().content_hash::<Sha256>(),
+            parse("{ a: 1.5 }").unwrap().content_hash::<Sha256>()
+        );
+    }
+
+    #[test]
+    fn test_content_hash_large_integers() {
+        // Above 2^53, where an f64 can no longer tell these apart
+        assert_ne!(
+            parse("{ id: 9007199254740993 }").unwrap().content_hash::<Sha256>(),
+            parse("{ id: 9007199254740992 }").unwrap().content_hash::<Sha256>()
+        );
+    }
+}
@@ -0,0 +1,337 @@
+//! `$include` directives for splitting documents across files
+//!
+//! An object with an `$include` key is replaced by the included document,
+//! with its remaining keys deep-merged on top:
+//!
+//! ```text
+//! // config/app.kjson
+//! {
+//!   $include: './base.kjson',
+//!   db: { host: 'db.internal' },
+//! }
+//! ```
+//!
+//! `$include` takes a path or an array of paths, merged in order. Paths are
+//! relative to the file containing the directive, included files may include
+//! others, and a file that includes itself, directly or not, is an error.
+//!
+//! Files are read through a [`FileAccess`], so callers decide what may be
+//! read. [`Sandbox`] only allows files below a root directory:
+//!
+//! ```no_run
+//! use kjson::include::{load_file, Sandbox};
+//!
+//! let config = load_file("config/app.kjson", &Sandbox::new("config")?)?;
+//! # Ok::<(), kjson::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::value::Value;
+use std::path::{Component, Path, PathBuf};
+
+/// Key of the include directive
+pub const INCLUDE_KEY: &str = "$include";
+
+/// Reads included files
+pub trait FileAccess {
+    /// Read the file at `path`, or refuse to
+    fn read(&self, path: &Path) -> Result<String>;
+}
+
+impl<F> FileAccess for F
+where
+    F: Fn(&Path) -> Result<String>,
+{
+    fn read(&self, path: &Path) -> Result<String> {
+        self(path)
+    }
+}
+
+/// Allows reading files below a root directory only
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+}
+
+impl Sandbox {
+    /// Sandbox rooted at an existing directory
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        Ok(Sandbox {
+            root: root.as_ref().canonicalize()?,
+        })
+    }
+}
+
+impl FileAccess for Sandbox {
+    fn read(&self, path: &Path) -> Result<String> {
+        // Canonicalize so `..` and symlinks cannot leave the root
+        let resolved = path.canonicalize()?;
+        if !resolved.starts_with(&self.root) {
+            return Err(Error::Config(format!(
+                "{}: outside of {}",
+                path.display(),
+                self.root.display()
+            )));
+        }
+        Ok(std::fs::read_to_string(resolved)?)
+    }
+}
+
+/// The files being loaded, outermost first, as written and as canonical
+/// paths where they exist
+type Stack = Vec<(PathBuf, PathBuf)>;
+
+/// Read and parse a file, expanding its includes
+pub fn load_file(path: impl AsRef<Path>, access: &dyn FileAccess) -> Result<Value> {
+    let mut stack = Vec::new();
+    load(&normalize(path.as_ref()), access, &mut stack)
+}
+
+/// Expand the includes of an already parsed value, relative to `base_dir`
+pub fn expand_includes(value: &mut Value, base_dir: &Path, access: &dyn FileAccess) -> Result<()> {
+    let mut stack = Vec::new();
+    expand(value, &normalize(base_dir), access, &mut stack)
+}
+
+/// Expand the includes of the parsed contents of `file`
+pub(crate) fn expand_file(value: &mut Value, file: &Path, access: &dyn FileAccess) -> Result<()> {
+    let file = normalize(file);
+    let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut stack = vec![(file.clone(), identity(&file))];
+    expand(value, &dir, access, &mut stack)
+}
+
+fn load(path: &Path, access: &dyn FileAccess, stack: &mut Stack) -> Result<Value> {
+    let id = identity(path);
+    if stack.iter().any(|(_, p)| *p == id) {
+        let chain: Vec<String> = stack
+            .iter()
+            .map(|(p, _)| p.as_path())
+            .chain(Some(path))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(Error::Config(format!(
+            "include cycle: {}",
+            chain.join(" -> ")
+        )));
+    }
+    let text = access
+        .read(path)
+        .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+    let mut value =
+        parse(&text).map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+    stack.push((path.to_path_buf(), id));
+    let dir = path.parent().unwrap_or(Path::new(""));
+    expand(&mut value, dir, access, stack)?;
+    stack.pop();
+    Ok(value)
+}
+
+fn expand(
+    value: &mut Value,
+    dir: &Path,
+    access: &dyn FileAccess,
+    stack: &mut Stack,
+) -> Result<()> {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                expand(item, dir, access, stack)?;
+            }
+        }
+        Value::Object(map) => {
+            let include = map.remove(INCLUDE_KEY);
+            for item in map.values_mut() {
+                expand(item, dir, access, stack)?;
+            }
+            if let Some(mut include) = include {
+                let paths = match &mut include {
+                    Value::String(path) => vec![std::mem::take(path)],
+                    Value::Array(items) => std::mem::take(items)
+                        .into_iter()
+                        .map(|mut item| match &mut item {
+                            Value::String(path) => Ok(std::mem::take(path)),
+                            other => Err(include_type_error(other)),
+                        })
+                        .collect::<Result<_>>()?,
+                    other => return Err(include_type_error(other)),
+                };
+                let mut included = Value::Null;
+                for (i, path) in paths.iter().enumerate() {
+                    let next = load(&normalize(&dir.join(path)), access, stack)?;
+                    if i == 0 {
+                        included = next;
+                    } else {
+                        included.deep_merge(next);
+                    }
+                }
+                let rest = std::mem::replace(value, included);
+                if !matches!(&rest, Value::Object(map) if map.is_empty()) {
+                    value.deep_merge(rest);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn include_type_error(value: &Value) -> Error {
+    Error::Config(format!(
+        "{} must be a path or an array of paths, got {}",
+        INCLUDE_KEY,
+        value.type_name()
+    ))
+}
+
+/// What a path names for cycle detection: the canonical path, so symlinks
+/// to a file already being loaded are caught, or the path itself if it
+/// doesn't exist on disk
+fn identity(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Resolve `.` and `..` without touching the file system.
+///
+/// A `..` only cancels a directory name before it; one that would climb
+/// above the start of the path is kept, for the [`FileAccess`] to judge.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                _ => out.push(".."),
+            },
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn files(entries: &[(&str, &str)]) -> impl Fn(&Path) -> Result<String> {
+        let files: HashMap<PathBuf, String> = entries
+            .iter()
+            .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+            .collect();
+        move |path: &Path| {
+            files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| Error::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)))
+        }
+    }
+
+    #[test]
+    fn test_include_and_merge() {
+        let access = files(&[
+            (
+                "config/app.kjson",
+                "{ $include: ['base/db.kjson', 'base/log.kjson'], db: { host: 'db.internal' } }",
+            ),
+            (
+                "config/base/db.kjson",
+                "{ db: { host: 'localhost', port: 5432 }, hosts: { $include: '../hosts.kjson' } }",
+            ),
+            ("config/base/log.kjson", "{ log: { level: 'info' } }"),
+            ("config/hosts.kjson", "['a', 'b']"),
+        ]);
+        let value = load_file("./config/app.kjson", &access).unwrap();
+        assert_eq!(
+            value,
+            parse(
+                "{
+                    db: { host: 'db.internal', port: 5432 },
+                    hosts: ['a', 'b'],
+                    log: { level: 'info' },
+                }"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let access = files(&[
+            ("a.kjson", "{ x: { $include: 'b.kjson' } }"),
+            ("b.kjson", "{ $include: './a.kjson' }"),
+            ("bad.kjson", "{ $include: 1 }"),
+        ]);
+        let err = load_file("a.kjson", &access).unwrap_err();
+        assert!(
+            matches!(&err, Error::Config(m) if m == "include cycle: a.kjson -> b.kjson -> a.kjson"),
+            "{}",
+            err
+        );
+        assert!(load_file("bad.kjson", &access).is_err());
+        let err = load_file("missing.kjson", &access).unwrap_err();
+        assert!(matches!(err, Error::Config(m) if m.starts_with("missing.kjson: ")));
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let root = std::env::temp_dir().join(format!("kjson-include-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("conf")).unwrap();
+        std::fs::write(
+            root.join("conf/app.kjson"),
+            "{ $include: 'base.kjson', a: 2 }",
+        )
+        .unwrap();
+        std::fs::write(root.join("conf/base.kjson"), "{ a: 1, b: 1 }").unwrap();
+        std::fs::write(
+            root.join("conf/escape.kjson"),
+            "{ $include: '../secret.kjson' }",
+        )
+        .unwrap();
+        std::fs::write(root.join("secret.kjson"), "{}").unwrap();
+
+        let sandbox = Sandbox::new(root.join("conf")).unwrap();
+        let value = load_file(root.join("conf/app.kjson"), &sandbox).unwrap();
+        assert_eq!(value, parse("{ a: 2, b: 1 }").unwrap());
+        assert!(load_file(root.join("conf/escape.kjson"), &sandbox).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("a/../../b")), Path::new("../b"));
+        assert_eq!(normalize(Path::new("../a/../../b")), Path::new("../../b"));
+        assert_eq!(normalize(Path::new("/../a")), Path::new("/../a"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_cycle() {
+        let root = std::env::temp_dir().join(format!("kjson-include-loop-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        // `loop` leads back to the same directory, so every path through it
+        // is new but names the same file
+        std::os::unix::fs::symlink(".", root.join("loop")).unwrap();
+        std::fs::write(root.join("app.kjson"), "{ $include: 'loop/app.kjson' }").unwrap();
+        std::os::unix::fs::symlink("app.kjson", root.join("alias.kjson")).unwrap();
+        std::fs::write(root.join("main.kjson"), "{ $include: 'alias.kjson' }").unwrap();
+
+        let sandbox = Sandbox::new(&root).unwrap();
+        for file in ["app.kjson", "main.kjson"] {
+            let err = load_file(root.join(file), &sandbox).unwrap_err();
+            assert!(
+                matches!(&err, Error::Config(m) if m.starts_with("include cycle: ")),
+                "{}",
+                err
+            );
+        }
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,448 @@
+//! Incremental parsing of byte streams
+//!
+//! [`Incremental`] builds a value from chunks as they arrive. Open arrays
+//! and objects live on an explicit stack, and only the token under the
+//! cursor (a string, number, literal or key) is buffered; each token is
+//! handed to the parser whole, so it parses exactly as it would in a
+//! complete document.
+
+use crate::error::{Error, Result};
+use crate::parser::{parse_partial, Parser};
+use crate::value::{Map, Str, Value};
+use crate::warnings::{self, Warning};
+
+/// Lexical state carried between chunks
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lex {
+    /// Between tokens
+    Code,
+    /// Saw a `/` that may start a comment
+    Slash,
+    /// Inside a `//` comment
+    LineComment,
+    /// Inside a `/* */` comment, `star` if the last byte was `*`
+    BlockComment { star: bool },
+    /// Inside a string or key opened with `quote`
+    String { quote: u8, escape: bool },
+    /// Inside a number, literal or unquoted key
+    Scalar,
+    /// Inside the quotes of a `b64'...'` literal
+    Binary { quote: u8 },
+}
+
+/// What may come next
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expect {
+    /// A value, or `]` to end the array if `close`
+    Value { close: bool },
+    /// A key, or `}` to end the object
+    Key,
+    /// The `:` after a key
+    Colon,
+    /// A `,` or the end of the innermost array or object
+    Comma,
+    /// Only whitespace and comments; the value is complete
+    Done,
+}
+
+/// An array or object that is still open
+#[derive(Debug)]
+enum Frame {
+    Array(Vec<Value>),
+    /// The members so far, and the key waiting for its value with the
+    /// position where the key starts
+    Object(Map, Option<(Str, usize)>),
+}
+
+/// Parses one value from a byte stream, a chunk at a time
+#[derive(Debug)]
+pub(crate) struct Incremental {
+    lex: Lex,
+    expect: Expect,
+    stack: Vec<Frame>,
+    /// Bytes of the token under the cursor
+    token: Vec<u8>,
+    /// Where the token starts in the stream
+    token_start: usize,
+    /// Bytes fed before the current chunk
+    offset: usize,
+    started: bool,
+    value: Option<Value>,
+}
+
+impl Incremental {
+    pub(crate) fn new() -> Self {
+        Incremental {
+            lex: Lex::Code,
+            expect: Expect::Value { close: false },
+            stack: Vec::new(),
+            token: Vec::new(),
+            token_start: 0,
+            offset: 0,
+            started: false,
+            value: None,
+        }
+    }
+
+    /// Parse the next chunk.
+    ///
+    /// Returns how many bytes of `chunk` belong to the value once it is
+    /// complete, or `None` if the value continues into the next chunk. Bytes
+    /// fed after that may only be whitespace and comments.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<Option<usize>> {
+        let mut i = 0;
+        while i < chunk.len() {
+            let complete = self.expect == Expect::Done;
+            if self.step(chunk[i], self.offset + i)? {
+                i += 1;
+            }
+            if !complete && self.expect == Expect::Done {
+                self.offset += i;
+                return Ok(Some(i));
+            }
+        }
+        self.offset += chunk.len();
+        Ok(None)
+    }
+
+    /// End the input, returning the value or `None` if there was only
+    /// whitespace and comments
+    pub(crate) fn finish(mut self) -> Result<Option<Value>> {
+        match self.lex {
+            // A number or literal ends with the input
+            Lex::Scalar => {
+                self.lex = Lex::Code;
+                self.end_token()?;
+            }
+            Lex::Slash => return Err(self.unexpected(self.offset - 1)),
+            _ => {}
+        }
+        match self.expect {
+            Expect::Done => Ok(self.value),
+            _ if !self.started => Ok(None),
+            _ => Err(Error::UnexpectedEof),
+        }
+    }
+
+    /// Advance by one byte at `position`, returning whether it was used up.
+    /// The byte that ends a number or literal is left for the next step.
+    fn step(&mut self, b: u8, position: usize) -> Result<bool> {
+        match self.lex {
+            Lex::String { quote, escape } => {
+                self.token.push(b);
+                if escape {
+                    self.lex = Lex::String {
+                        quote,
+                        escape: false,
+                    };
+                } else if b == b'\\' {
+                    self.lex = Lex::String {
+                        quote,
+                        escape: true,
+                    };
+                } else if b == quote {
+                    self.lex = Lex::Code;
+                    self.end_token()?;
+                }
+            }
+            Lex::Binary { quote } => {
+                self.token.push(b);
+                if b == quote {
+                    self.lex = Lex::Code;
+                    self.end_token()?;
+                }
+            }
+            Lex::Scalar => {
+                if matches!(b, b'\'' | b'"') && self.token == b"b64" {
+                    self.token.push(b);
+                    self.lex = Lex::Binary { quote: b };
+                } else if self.in_scalar(b) {
+                    self.token.push(b);
+                } else {
+                    self.lex = Lex::Code;
+                    self.end_token()?;
+                    return Ok(false);
+                }
+            }
+            Lex::LineComment => {
+                if b == b'\n' {
+                    self.lex = Lex::Code;
+                }
+            }
+            Lex::BlockComment { star } => {
+                self.lex = if star && b == b'/' {
+                    Lex::Code
+                } else {
+                    Lex::BlockComment { star: b == b'*' }
+                };
+            }
+            Lex::Slash => match b {
+                b'/' => self.lex = Lex::LineComment,
+                b'*' => self.lex = Lex::BlockComment { star: false },
+                _ => return Err(self.unexpected(position - 1)),
+            },
+            Lex::Code => match b {
+                b' ' | b'\t' | b'\n' | b'\r' => {}
+                b'/' => self.lex = Lex::Slash,
+                _ => self.code(b, position)?,
+            },
+        }
+        Ok(true)
+    }
+
+    /// Handle a byte outside any token: punctuation or the start of a token
+    fn code(&mut self, b: u8, position: usize) -> Result<()> {
+        let in_array = matches!(self.stack.last(), Some(Frame::Array(_)));
+        match (self.expect, b) {
+            (Expect::Value { .. }, b'[') => {
+                self.open(Frame::Array(Vec::new()));
+                self.expect = Expect::Value { close: true };
+            }
+            (Expect::Value { .. }, b'{') => {
+                self.open(Frame::Object(Map::new(), None));
+                self.expect = Expect::Key;
+            }
+            (Expect::Value { close: true } | Expect::Comma, b']') if in_array => self.close(),
+            (Expect::Key | Expect::Comma, b'}') if !in_array => self.close(),
+            (Expect::Comma, b',') => {
+                self.expect = if in_array {
+                    // Allow trailing comma
+                    Expect::Value { close: true }
+                } else {
+                    Expect::Key
+                };
+            }
+            (Expect::Colon, b':') => self.expect = Expect::Value { close: false },
+            (Expect::Value { .. } | Expect::Key, b'"' | b'\'' | b'`') => {
+                self.start_token(b, position);
+                self.lex = Lex::String {
+                    quote: b,
+                    escape: false,
+                };
+            }
+            (Expect::Value { .. } | Expect::Key, _)
+                if self.in_scalar(b) && !matches!(b, b'[' | b'{' | b':') =>
+            {
+                self.start_token(b, position);
+                self.lex = Lex::Scalar;
+            }
+            _ => return Err(self.unexpected(position)),
+        }
+        Ok(())
+    }
+
+    /// Whether `b` continues a number, literal or unquoted key
+    fn in_scalar(&self, b: u8) -> bool {
+        if self.expect == Expect::Key {
+            // Keys end at the first ASCII byte that can't be part of one;
+            // the parser checks any other characters
+            b.is_ascii_alphanumeric() || matches!(b, b'_' | b'$') || !b.is_ascii()
+        } else {
+            !matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}' | b'/')
+        }
+    }
+
+    fn start_token(&mut self, b: u8, position: usize) {
+        self.started = true;
+        self.token.clear();
+        self.token.push(b);
+        self.token_start = position;
+    }
+
+    fn open(&mut self, frame: Frame) {
+        self.started = true;
+        self.stack.push(frame);
+    }
+
+    fn close(&mut self) {
+        let value = match self.stack.pop() {
+            Some(Frame::Array(items)) => Value::Array(items),
+            Some(Frame::Object(map, _)) => Value::Object(map),
+            None => unreachable!("closed a container that was never opened"),
+        };
+        self.complete(value);
+    }
+
+    /// Place a finished value in its array or object, or keep it as the
+    /// result
+    fn complete(&mut self, value: Value) {
+        match self.stack.last_mut() {
+            None => {
+                self.value = Some(value);
+                self.expect = Expect::Done;
+            }
+            Some(Frame::Array(items)) => {
+                items.push(value);
+                self.expect = Expect::Comma;
+            }
+            Some(Frame::Object(map, key)) => {
+                let (key, position) = key.take().expect("a value follows its key");
+                // Later duplicates win
+                if let Some(slot) = map.get_mut(&key) {
+                    warnings::warn(|| Warning::DuplicateKey {
+                        key: key.to_string(),
+                        position,
+                    });
+                    *slot = value;
+                } else {
+                    map.insert(key, value);
+                }
+                self.expect = Expect::Comma;
+            }
+        }
+    }
+
+    /// Parse the buffered token as a key or value
+    fn end_token(&mut self) -> Result<()> {
+        // Keep the buffer for the next token
+        let token = std::mem::take(&mut self.token);
+        let result = self.parse_token(&token);
+        self.token = token;
+        result
+    }
+
+    fn parse_token(&mut self, token: &[u8]) -> Result<()> {
+        let start = self.token_start;
+        let text = std::str::from_utf8(token).map_err(|e| Error::ParseError {
+            position: start + e.valid_up_to(),
+            message: "Invalid UTF-8".to_string(),
+        })?;
+        let shift = |e: Error| match e {
+            Error::ParseError { position, message } => Error::ParseError {
+                position: start + position,
+                message,
+            },
+            other => other,
+        };
+
+        if self.expect == Expect::Key {
+            let key = if matches!(token[0], b'"' | b'\'' | b'`') {
+                match &mut parse_partial(text).map_err(shift)?.0 {
+                    Value::String(s) => std::mem::take(s),
+                    _ => unreachable!(),
+                }
+            } else {
+                let mut parser = Parser::new(text);
+                let key = Str::from(parser.parse_unquoted_key().map_err(shift)?);
+                if !parser.at_end() {
+                    self.expect = Expect::Colon;
+                    return Err(self.unexpected(start + parser.position()));
+                }
+                key
+            };
+            if let Some(Frame::Object(_, pending)) = self.stack.last_mut() {
+                *pending = Some((key, start));
+            }
+            self.expect = Expect::Colon;
+            return Ok(());
+        }
+
+        let (value, len) = parse_partial(text).map_err(shift)?;
+        self.complete(value);
+        if len < text.len() {
+            return Err(self.unexpected(start + len));
+        }
+        Ok(())
+    }
+
+    /// The error for an unexpected byte at `position`
+    fn unexpected(&self, position: usize) -> Error {
+        let message = match self.expect {
+            Expect::Value { .. } => "Expected value",
+            Expect::Key => "Invalid unquoted key",
+            Expect::Colon => "Expected ':' after key",
+            Expect::Comma => match self.stack.last() {
+                Some(Frame::Array(_)) => "Expected ',' or ']'",
+                _ => "Expected ',' or '}'",
+            },
+            Expect::Done => "Unexpected characters after value",
+        };
+        Error::ParseError {
+            position,
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    /// Parse `input` fed in chunks of `size` bytes
+    fn parse_chunked(input: &str, size: usize) -> Result<Option<Value>> {
+        let mut parser = Incremental::new();
+        for chunk in input.as_bytes().chunks(size) {
+            let mut chunk = chunk;
+            if let Some(end) = parser.feed(chunk)? {
+                chunk = &chunk[end..];
+                parser.feed(chunk)?;
+            }
+        }
+        parser.finish()
+    }
+
+    #[test]
+    fn test_matches_parser() {
+        let documents = [
+            "{ a: 1, 'b c': [true, null, -2.5e3], \"d\": { e: 'x\\'y', f: `z` } }",
+            "[1n, 99.99m, 550e8400-e29b-41d4-a716-446655440000, 2025-01-10T12:00:00Z, PT1H30M]",
+            "{ data: b64'aGVsbG8=', more: b64\"AQI\" } // trailing comment",
+            "/* lead */ [ 'é', { ключ: 'значение', $_k9: 0 }, [], {}, [1,], { a: 1, } ]",
+            "{ a: 1, a: 2 }",
+            "  -12  ",
+            "'\\u00e9\\n'",
+            "PT1H",
+        ];
+        for document in documents {
+            let expected = parse(document).unwrap();
+            for size in [1, 2, 3, 7, document.len()] {
+                let value = parse_chunked(document, size).unwrap().unwrap();
+                assert_eq!(value, expected, "{:?} in chunks of {}", document, size);
+            }
+        }
+        assert!(parse_chunked(" // nothing\n /* here */", 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let mut parser = Incremental::new();
+        assert_eq!(parser.feed(b"{ a: [1, ").unwrap(), None);
+        assert_eq!(parser.feed(b"2] } [").unwrap(), Some(4));
+        assert_eq!(parser.finish().unwrap(), parse("{ a: [1, 2] }").ok());
+
+        // A number ends at its delimiter, which is left unread
+        let mut parser = Incremental::new();
+        assert_eq!(parser.feed(b" 12").unwrap(), None);
+        assert_eq!(parser.feed(b"3.5m,").unwrap(), Some(4));
+        assert_eq!(parser.finish().unwrap(), parse("123.5m").ok());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |input: &str| parse_chunked(input, 2).unwrap_err();
+        assert!(matches!(error("{ a: 1"), Error::UnexpectedEof));
+        assert!(matches!(error("['abc"), Error::UnexpectedEof));
+        assert!(matches!(
+            error("[1, 2 3]"),
+            Error::ParseError { position: 6, ref message } if message == "Expected ',' or ']'"
+        ));
+        assert!(matches!(
+            error("{ a 1 }"),
+            Error::ParseError { position: 4, ref message } if message == "Expected ':' after key"
+        ));
+        assert!(matches!(
+            error("{ 1a: 1 }"),
+            Error::ParseError { position: 2, ref message } if message == "Invalid unquoted key"
+        ));
+        assert!(matches!(
+            error("[1, 'a\\q']"),
+            Error::ParseError { position: 7, .. }
+        ));
+        assert!(matches!(
+            error("[1] 2"),
+            Error::ParseError { position: 4, ref message }
+                if message == "Unexpected characters after value"
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+//! Random access into large kJSON lines files
+//!
+//! A [`LineIndex`] is built by scanning a file of one document per line
+//! once, recording where each record starts and, optionally, which record
+//! holds each value of a key. It is saved as a compact sidecar file, and an
+//! [`IndexedReader`] uses it to seek to single records and parse only
+//! those:
+//!
+//! ```
+//! use kjson::index::{IndexedReader, LineIndex};
+//! use std::io::Cursor;
+//!
+//! let data = "{ id: 1n, name: 'a' }\n{ id: 2n, name: 'b' }\n{ id: 3n, name: 'c' }\n";
+//! let index = LineIndex::build_keyed(data.as_bytes(), "/id")?;
+//! let sidecar = index.to_bytes();
+//!
+//! let mut reader = IndexedReader::new(Cursor::new(data), LineIndex::from_bytes(&sidecar)?)?;
+//! assert_eq!(reader.get(1)?, Some(kjson::parse("{ id: 2n, name: 'b' }")?));
+//! let record = reader.get_by_key(&kjson::parse("3n")?)?.unwrap();
+//! assert_eq!(record.pointer("/name"), Some(&kjson::Value::string("c")));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Records are numbered from 0 and blank lines are skipped. Keys are
+//! compared by their compact kJSON text, so `7n`, `7` and `'7'` are
+//! different keys; when several records share a key the last one wins,
+//! which for append-only journals is the latest.
+//!
+//! Offsets are stored as varint deltas, so the index costs a few bytes
+//! per record. It records the length of the data it covers, and
+//! [`IndexedReader::new`] refuses data shorter than that. Data appended
+//! since the index was built is not visible until it is rebuilt.
+
+use crate::binary::{read_varint, write_varint};
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::value::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Magic bytes that open a sidecar index
+pub const INDEX_MAGIC: [u8; 4] = *b"KJLX";
+
+/// Version of the sidecar layout
+const INDEX_VERSION: u8 = 1;
+
+/// Record offsets, and optionally key values, of a kJSON lines file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    offsets: Vec<u64>,
+    data_len: u64,
+    keys: Option<KeyIndex>,
+}
+
+/// Record of each value of the key at `pointer`
+#[derive(Debug, Clone, PartialEq)]
+struct KeyIndex {
+    pointer: String,
+    records: HashMap<String, usize>,
+}
+
+impl LineIndex {
+    /// Index the start of each record
+    pub fn build<R: BufRead>(reader: R) -> Result<Self> {
+        Self::scan(reader, None)
+    }
+
+    /// Index the start of each record and the value at `pointer` in each,
+    /// such as `/id`; records without it can only be read by number
+    pub fn build_keyed<R: BufRead>(reader: R, pointer: &str) -> Result<Self> {
+        Self::scan(reader, Some(pointer))
+    }
+
+    fn scan<R: BufRead>(mut reader: R, pointer: Option<&str>) -> Result<Self> {
+        let mut index = LineIndex {
+            offsets: Vec::new(),
+            data_len: 0,
+            keys: pointer.map(|pointer| KeyIndex {
+                pointer: pointer.to_string(),
+                records: HashMap::new(),
+            }),
+        };
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            let n = reader.read_until(b'\n', &mut line)?;
+            if n == 0 {
+                break;
+            }
+            let start = index.data_len;
+            index.data_len += n as u64;
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            if let Some(keys) = &mut index.keys {
+                let value = parse_record(&line, start)?;
+                if let Some(key) = value.pointer(&keys.pointer) {
+                    keys.records.insert(to_string(key)?, index.offsets.len());
+                }
+            }
+            index.offsets.push(start);
+        }
+        Ok(index)
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Whether the data holds no records
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Length in bytes of the data that was indexed
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Pointer of the indexed key, if the index has one
+    pub fn key_pointer(&self) -> Option<&str> {
+        self.keys.as_ref().map(|keys| keys.pointer.as_str())
+    }
+
+    /// Number of the last record whose key is `key`
+    pub fn find(&self, key: &Value) -> Option<usize> {
+        let keys = self.keys.as_ref()?;
+        keys.records.get(&to_string(key).ok()?).copied()
+    }
+
+    /// Byte range of record `record` in the data, including its line break
+    pub fn range(&self, record: usize) -> Option<(u64, u64)> {
+        let start = *self.offsets.get(record)?;
+        let end = self.offsets.get(record + 1).copied().unwrap_or(self.data_len);
+        Some((start, end))
+    }
+
+    /// Encode the index as a sidecar file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = INDEX_MAGIC.to_vec();
+        out.push(INDEX_VERSION);
+        write_varint(&mut out, self.data_len);
+        write_varint(&mut out, self.offsets.len() as u64);
+        let mut previous = 0;
+        for &offset in &self.offsets {
+            write_varint(&mut out, offset - previous);
+            previous = offset;
+        }
+        match &self.keys {
+            None => out.push(0),
+            Some(keys) => {
+                out.push(1);
+                write_bytes(&mut out, keys.pointer.as_bytes());
+                write_varint(&mut out, keys.records.len() as u64);
+                for (key, &record) in &keys.records {
+                    write_bytes(&mut out, key.as_bytes());
+                    write_varint(&mut out, record as u64);
+                }
+            }
+        }
+        out
+    }
+
+    /// Decode a sidecar file written by [`LineIndex::to_bytes`]
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let header = INDEX_MAGIC.len() + 1;
+        if data.len() < header || data[..INDEX_MAGIC.len()] != INDEX_MAGIC {
+            return Err(Error::InvalidBinary("Not a kJSON lines index".to_string()));
+        }
+        if data[INDEX_MAGIC.len()] != INDEX_VERSION {
+            return Err(Error::InvalidBinary(format!(
+                "Unsupported index version {}",
+                data[INDEX_MAGIC.len()]
+            )));
+        }
+        let mut pos = header;
+        let data_len = read_varint(data, &mut pos)?;
+        let count = read_count(data, &mut pos)?;
+        let mut offsets = Vec::with_capacity(count);
+        let mut offset = 0u64;
+        for _ in 0..count {
+            let delta = read_varint(data, &mut pos)?;
+            offset = match offset.checked_add(delta) {
+                Some(next) if next < data_len && (offsets.is_empty() || delta > 0) => next,
+                _ => return Err(Error::InvalidBinary("Record offset out of order".to_string())),
+            };
+            offsets.push(offset);
+        }
+        let keys = match data.get(pos) {
+            Some(0) => None,
+            Some(1) => {
+                pos += 1;
+                let pointer = read_string(data, &mut pos)?;
+                let entries = read_count(data, &mut pos)?;
+                let mut records = HashMap::with_capacity(entries);
+                for _ in 0..entries {
+                    let key = read_string(data, &mut pos)?;
+                    let record = read_varint(data, &mut pos)?;
+                    if record >= count as u64 {
+                        return Err(Error::InvalidBinary(format!(
+                            "Key refers to record {} of {}",
+                            record, count
+                        )));
+                    }
+                    records.insert(key, record as usize);
+                }
+                Some(KeyIndex { pointer, records })
+            }
+            Some(flag) => {
+                return Err(Error::InvalidBinary(format!("Unknown index flag {}", flag)));
+            }
+            None => return Err(Error::UnexpectedEof),
+        };
+        Ok(LineIndex {
+            offsets,
+            data_len,
+            keys,
+        })
+    }
+
+    /// Write the index to a sidecar file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Read an index from a sidecar file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Reads single records of indexed kJSON lines data
+#[derive(Debug)]
+pub struct IndexedReader<R> {
+    reader: R,
+    index: LineIndex,
+    buf: Vec<u8>,
+}
+
+impl<R: Read + Seek> IndexedReader<R> {
+    /// Read `reader` through `index`, which must have been built from the
+    /// same data or a prefix of it
+    pub fn new(mut reader: R, index: LineIndex) -> Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        if len < index.data_len {
+            return Err(Error::InvalidBinary(format!(
+                "Index covers {} bytes but the data has {}",
+                index.data_len, len
+            )));
+        }
+        Ok(IndexedReader {
+            reader,
+            index,
+            buf: Vec::new(),
+        })
+    }
+
+    /// The index records are looked up in
+    pub fn index(&self) -> &LineIndex {
+        &self.index
+    }
+
+    /// Number of indexed records
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Whether there are no indexed records
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Parse record `record`, or `None` past the last one
+    pub fn get(&mut self, record: usize) -> Result<Option<Value>> {
+        let Some((start, end)) = self.index.range(record) else {
+            return Ok(None);
+        };
+        self.buf.resize((end - start) as usize, 0);
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut self.buf)?;
+        parse_record(&self.buf, start).map(Some)
+    }
+
+    /// Parse the last record whose key is `key`
+    pub fn get_by_key(&mut self, key: &Value) -> Result<Option<Value>> {
+        match self.index.find(key) {
+            Some(record) => self.get(record),
+            None => Ok(None),
+        }
+    }
+
+    /// Consume the reader, returning the underlying one
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Parse one record, with error positions counted from the start of the data
+fn parse_record(bytes: &[u8], start: u64) -> Result<Value> {
+    let offset = |position: usize| start as usize + position;
+    let text = std::str::from_utf8(bytes).map_err(|e| Error::ParseError {
+        position: offset(e.valid_up_to()),
+        message: "Invalid UTF-8".to_string(),
+    })?;
+    parse(text).map_err(|e| match e {
+        Error::ParseError { position, message } => Error::ParseError {
+            position: offset(position),
+            message,
+        },
+        other => other,
+    })
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Read a count, rejecting ones the remaining data cannot hold
+fn read_count(data: &[u8], pos: &mut usize) -> Result<usize> {
+    let count = read_varint(data, pos)?;
+    if count > (data.len() - *pos) as u64 {
+        return Err(Error::UnexpectedEof);
+    }
+    Ok(count as usize)
+}
+
+fn read_string(data: &[u8], pos: &mut usize) -> Result<String> {
+    let len = read_count(data, pos)?;
+    let bytes = &data[*pos..*pos + len];
+    *pos += len;
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| Error::InvalidBinary("Invalid UTF-8 in index".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const DATA: &str = "{ id: 'a', n: 1 }\n\n{ id: 'b', n: 2 }\r\n{ n: 3 }\n{ id: 'a', n: 4 }";
+
+    #[test]
+    fn test_index_and_read() {
+        let index = LineIndex::build(DATA.as_bytes()).unwrap();
+        assert_eq!(index.len(), 4);
+        assert_eq!(index.range(1), Some((19, 38)));
+        assert_eq!(index.range(3), Some((47, DATA.len() as u64)));
+        assert_eq!(index.find(&Value::string("a")), None);
+
+        let index = LineIndex::build_keyed(DATA.as_bytes(), "/id").unwrap();
+        assert_eq!(index.key_pointer(), Some("/id"));
+        assert_eq!(index.find(&Value::string("a")), Some(3));
+        assert_eq!(LineIndex::from_bytes(&index.to_bytes()).unwrap(), index);
+
+        let mut reader = IndexedReader::new(Cursor::new(DATA), index).unwrap();
+        assert_eq!(reader.get(2).unwrap(), Some(parse("{ n: 3 }").unwrap()));
+        assert_eq!(reader.get(4).unwrap(), None);
+        let b = reader.get_by_key(&Value::string("b")).unwrap().unwrap();
+        assert_eq!(b.pointer("/n"), Some(&Value::Number(2.0)));
+        assert_eq!(reader.get_by_key(&Value::string("c")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = LineIndex::build_keyed("{ id: 1 }\n{ id: }\n".as_bytes(), "/id").unwrap_err();
+        assert!(matches!(err, Error::ParseError { position, .. } if position >= 10));
+
+        let index = LineIndex::build(DATA.as_bytes()).unwrap();
+        assert!(IndexedReader::new(Cursor::new(&DATA[..40]), index.clone()).is_err());
+        let bytes = index.to_bytes();
+        assert!(LineIndex::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(LineIndex::from_bytes(b"KJLX\x02").is_err());
+        assert!(LineIndex::from_bytes(b"KJBZ\x01\x00\x00\x00").is_err());
+        // A count larger than the data that follows
+        assert!(LineIndex::from_bytes(b"KJLX\x01\x10\xff\xff\x03").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+//! JSON Schema (draft 2020-12) validation of kJSON values
+//!
+//! Extended types are presented to the validator the way a JSON consumer
+//! would see them:
+//!
+//! | kJSON | JSON Schema instance |
+//! |-------|----------------------|
+//! | BigInt | `integer` |
+//! | Decimal128 | `number` |
+//! | UUID | `string` with `format: "uuid"` |
+//! | Instant, Date | `string` with `format: "date-time"` |
+//! | Duration | `string` with `format: "duration"` |
+//! | Binary | base64 `string`, as `contentEncoding: "base64"` describes |
+//!
+//! Formats are asserted, so `{"type": "string", "format": "uuid"}` accepts
+//! both UUID literals and quoted UUID strings.
+
+use crate::error::{Error, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crate::parser::parse;
+use crate::schema::Violation;
+use crate::value::Value;
+
+/// A compiled JSON Schema (draft 2020-12)
+pub struct JsonSchema {
+    validator: jsonschema::Validator,
+}
+
+impl JsonSchema {
+    /// Compile a JSON Schema document
+    pub fn new(schema: &serde_json::Value) -> Result<Self> {
+        let validator = jsonschema::draft202012::options()
+            .should_validate_formats(true)
+            .build(schema)
+            .map_err(|e| Error::InvalidSchema(e.to_string()))?;
+        Ok(JsonSchema { validator })
+    }
+
+    /// Compile a JSON Schema written as a kJSON document
+    pub fn from_value(schema: &Value) -> Result<Self> {
+        Self::new(&to_instance(schema))
+    }
+
+    /// Parse and compile a JSON Schema from kJSON (or plain JSON) text
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_value(&parse(input)?)
+    }
+
+    /// Validate a value, returning every violation found
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let instance = to_instance(value);
+        self.validator
+            .iter_errors(&instance)
+            .map(|e| Violation {
+                path: e.instance_path().to_string(),
+                message: e.to_string(),
+            })
+            .collect()
+    }
+
+    /// Check whether a value satisfies the schema
+    pub fn is_valid(&self, value: &Value) -> bool {
+        self.validator.is_valid(&to_instance(value))
+    }
+}
+
+/// Convert a value to the JSON instance seen by JSON Schema validators
+pub fn to_instance(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(*b),
+        Value::Number(n) => number(*n),
+        Value::Integer(n) => serde_json::Value::from(*n),
+        Value::UInteger(n) => serde_json::Value::from(*n),
+        Value::String(s) => serde_json::Value::String(s.to_string()),
+        Value::Array(arr) => serde_json::Value::Array(arr.iter().map(to_instance).collect()),
+        Value::Object(obj) => serde_json::Value::Object(
+            obj.iter()
+                .map(|(k, v)| (k.to_string(), to_instance(v)))
+                .collect(),
+        ),
+        Value::BigInt(b) => {
+            let digits = b.to_string();
+            if let Ok(i) = digits.parse::<i64>() {
+                serde_json::Value::from(i)
+            } else if let Ok(u) = digits.parse::<u64>() {
+                serde_json::Value::from(u)
+            } else {
+                number(b.to_f64())
+            }
+        }
+        Value::Decimal128(d) => number(d.to_f64()),
+        Value::Uuid(u) => serde_json::Value::String(u.to_string()),
+        Value::Date(d) => serde_json::Value::String(d.to_iso8601()),
+        Value::Instant(i) => serde_json::Value::String(i.to_iso8601()),
+        Value::Duration(d) => serde_json::Value::String(d.to_iso8601()),
+        Value::Binary(bytes) => serde_json::Value::String(STANDARD.encode(bytes)),
+    }
+}
+
+fn number(n: f64) -> serde_json::Value {
+    serde_json::Number::from_f64(n)
+        .map(serde_json::Value::Number)
+        .unwrap_or(serde_json::Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"{
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "required": ["id", "count", "price", "created"],
+        "properties": {
+            "id": { "type": "string", "format": "uuid" },
+            "count": { "type": "integer", "minimum": 0 },
+            "price": { "type": "number", "exclusiveMinimum": 0 },
+            "created": { "type": "string", "format": "date-time" },
+            "ttl": { "type": "string", "format": "duration" }
+        },
+        "additionalProperties": false
+    }"#;
+
+    #[test]
+    fn test_extended_types_validate() {
+        let schema = JsonSchema::parse(SCHEMA).unwrap();
+        let doc = parse(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                count: 123456789012345678901234567890n,
+                price: 19.99m,
+                created: 2025-01-10T12:00:00.123Z,
+                ttl: PT1H30M,
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(schema.validate(&doc), vec![]);
+        assert!(schema.is_valid(&doc));
+    }
+
+    #[test]
+    fn test_violations_have_pointer_paths() {
+        let schema = JsonSchema::parse(SCHEMA).unwrap();
+        let doc = parse(
+            r#"{
+                id: "not-a-uuid",
+                count: -1n,
+                price: 0m,
+                created: 2025-01-10T12:00:00Z,
+                extra: true,
+            }"#,
+        )
+        .unwrap();
+        let mut paths: Vec<_> = schema.validate(&doc).into_iter().map(|v| v.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec!["", "/count", "/id", "/price"]);
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(matches!(
+            JsonSchema::parse(r#"{ "type": 12 }"#),
+            Err(Error::InvalidSchema(_))
+        ));
+    }
+
+    #[test]
+    fn test_instance_mapping() {
+        let value = parse("[18446744073709551615n, 1.5m, 2025-01-10T12:00:00Z]").unwrap();
+        assert_eq!(
+            to_instance(&value),
+            serde_json::json!([18446744073709551615u64, 1.5, "2025-01-10T12:00:00Z"])
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Token-level view of kJSON source
+//!
+//! The [`Lexer`] splits text into classified [`Token`]s, covering every
+//! byte: whitespace and comments included. It never fails; text that is not
+//! valid kJSON becomes [`TokenKind::Error`] tokens, so editors can classify
+//! a document while it is being typed. [`highlight`] is the shared
+//! classification for syntax highlighting:
+//!
+//! ```
+//! use kjson::lexer::{highlight, TokenKind};
+//!
+//! let input = "{ id: 123n, at: 2025-01-01T00:00:00Z } // note";
+//! let kinds: Vec<TokenKind> = highlight(input).into_iter().map(|(_, kind)| kind).collect();
+//! assert_eq!(
+//!     kinds,
+//!     [
+//!         TokenKind::LeftBrace, TokenKind::Key, TokenKind::Colon, TokenKind::BigInt,
+//!         TokenKind::Comma, TokenKind::Key, TokenKind::Colon, TokenKind::Date,
+//!         TokenKind::RightBrace, TokenKind::Comment,
+//!     ]
+//! );
+//! ```
+//!
+//! Strings and bare words followed by `:` are classified as keys.
+
+use crate::parser::parse_partial;
+use crate::span::Span;
+use crate::value::Value;
+
+/// What a token is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// Spaces, tabs and line breaks
+    Whitespace,
+    /// A `//` or `/* */` comment
+    Comment,
+    /// `{`
+    LeftBrace,
+    /// `}`
+    RightBrace,
+    /// `[`
+    LeftBracket,
+    /// `]`
+    RightBracket,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+    /// An object key, quoted or bare
+    Key,
+    /// A string value
+    String,
+    /// A plain number
+    Number,
+    /// A number with the `n` suffix
+    BigInt,
+    /// A number with the `m` suffix
+    Decimal128,
+    /// An unquoted UUID
+    Uuid,
+    /// An unquoted date or timestamp
+    Date,
+    /// An unquoted ISO 8601 duration
+    Duration,
+    /// A `b64'...'` binary literal
+    Binary,
+    /// `true` or `false`
+    Bool,
+    /// `null`
+    Null,
+    /// Text that is not valid kJSON
+    Error,
+}
+
+impl TokenKind {
+    /// Lower-case name, e.g. for CSS classes: `key`, `bigint`, `comment`
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Comment => "comment",
+            TokenKind::LeftBrace => "left-brace",
+            TokenKind::RightBrace => "right-brace",
+            TokenKind::LeftBracket => "left-bracket",
+            TokenKind::RightBracket => "right-bracket",
+            TokenKind::Colon => "colon",
+            TokenKind::Comma => "comma",
+            TokenKind::Key => "key",
+            TokenKind::String => "string",
+            TokenKind::Number => "number",
+            TokenKind::BigInt => "bigint",
+            TokenKind::Decimal128 => "decimal128",
+            TokenKind::Uuid => "uuid",
+            TokenKind::Date => "date",
+            TokenKind::Duration => "duration",
+            TokenKind::Binary => "binary",
+            TokenKind::Bool => "bool",
+            TokenKind::Null => "null",
+            TokenKind::Error => "error",
+        }
+    }
+
+    /// Whether this is whitespace or a comment
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+/// A classified piece of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    /// What the token is
+    pub kind: TokenKind,
+    /// Where it is
+    pub span: Span,
+}
+
+/// Iterator over the tokens of a string
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Lexer<'a> {
+    /// Tokenize `input`
+    pub fn new(input: &'a str) -> Self {
+        Lexer { input, position: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    /// Length of the token at the current position, and its kind
+    fn next_token(&self) -> (usize, TokenKind) {
+        let rest = self.rest();
+        let first = rest.chars().next().unwrap_or_default();
+        match first {
+            ' ' | '\t' | '\n' | '\r' => {
+                let len = rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+                (len, TokenKind::Whitespace)
+            }
+            '/' if rest.starts_with("//") => {
+                (rest.find('\n').unwrap_or(rest.len()), TokenKind::Comment)
+            }
+            '/' if rest.starts_with("/*") => match rest[2..].find("*/") {
+                Some(i) => (i + 4, TokenKind::Comment),
+                None => (rest.len(), TokenKind::Error),
+            },
+            '{' => (1, TokenKind::LeftBrace),
+            '}' => (1, TokenKind::RightBrace),
+            '[' => (1, TokenKind::LeftBracket),
+            ']' => (1, TokenKind::RightBracket),
+            ':' => (1, TokenKind::Colon),
+            ',' => (1, TokenKind::Comma),
+            '"' | '\'' | '`' => match parse_partial(rest) {
+                Ok((_, len)) if self.key_follows(len) => (len, TokenKind::Key),
+                Ok((_, len)) => (len, TokenKind::String),
+                // An unterminated string runs to the end of its line
+                Err(_) => (rest.find('\n').unwrap_or(rest.len()), TokenKind::Error),
+            },
+            c => {
+                if c.is_alphabetic() || c == '_' || c == '$' {
+                    let len = rest
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(rest.len());
+                    if self.key_follows(len) {
+                        return (len, TokenKind::Key);
+                    }
+                }
+                match parse_partial(rest) {
+                    Ok((value, len)) if len > 0 => (len, literal_kind(&value)),
+                    _ => {
+                        let len = rest
+                            .find([' ', '\t', '\n', '\r', ',', ':', '[', ']', '{', '}'])
+                            .unwrap_or(rest.len());
+                        (len.max(c.len_utf8()), TokenKind::Error)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a `:` comes after the next `len` bytes, past any trivia
+    fn key_follows(&self, len: usize) -> bool {
+        let mut rest = &self.input[self.position + len..];
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', '\n', '\r']);
+            if let Some(comment) = rest.strip_prefix("//") {
+                rest = comment.find('\n').map_or("", |i| &comment[i..]);
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                rest = comment.find("*/").map_or("", |i| &comment[i + 2..]);
+            } else {
+                return rest.starts_with(':');
+            }
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        if self.position >= self.input.len() {
+            return None;
+        }
+        let (len, kind) = self.next_token();
+        let start = self.position;
+        self.position += len;
+        Some(Token {
+            kind,
+            span: Span::new(start, self.position),
+        })
+    }
+}
+
+fn literal_kind(value: &Value) -> TokenKind {
+    match value {
+        Value::Null => TokenKind::Null,
+        Value::Bool(_) => TokenKind::Bool,
+        Value::Number(_) | Value::Integer(_) | Value::UInteger(_) => TokenKind::Number,
+        Value::String(_) => TokenKind::String,
+        Value::BigInt(_) => TokenKind::BigInt,
+        Value::Decimal128(_) => TokenKind::Decimal128,
+        Value::Uuid(_) => TokenKind::Uuid,
+        Value::Date(_) | Value::Instant(_) => TokenKind::Date,
+        Value::Duration(_) => TokenKind::Duration,
+        Value::Binary(_) => TokenKind::Binary,
+        Value::Array(_) | Value::Object(_) => TokenKind::Error,
+    }
+}
+
+/// Classify `input` for syntax highlighting, skipping whitespace
+pub fn highlight(input: &str) -> Vec<(Span, TokenKind)> {
+    Lexer::new(input)
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .map(|token| (token.span, token.kind))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<(&str, TokenKind)> {
+        highlight(input)
+            .into_iter()
+            .map(|(span, kind)| (span.slice(input), kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_literals() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens("['a', \"b\" /* c */, 1.5e3, -2n, 0.10m, true, null, PT1H, 550e8400-e29b-41d4-a716-446655440000, b64'AQID']"),
+            [
+                ("[", LeftBracket),
+                ("'a'", String),
+                (",", Comma),
+                ("\"b\"", String),
+                ("/* c */", Comment),
+                (",", Comma),
+                ("1.5e3", Number),
+                (",", Comma),
+                ("-2n", BigInt),
+                (",", Comma),
+                ("0.10m", Decimal128),
+                (",", Comma),
+                ("true", Bool),
+                (",", Comma),
+                ("null", Null),
+                (",", Comma),
+                ("PT1H", Duration),
+                (",", Comma),
+                ("550e8400-e29b-41d4-a716-446655440000", Uuid),
+                (",", Comma),
+                ("b64'AQID'", Binary),
+                ("]", RightBracket),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keys() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens("{ 'a b' /* x */ : 1, null: true, $c:2024-01-01T00:00:00Z }"),
+            [
+                ("{", LeftBrace),
+                ("'a b'", Key),
+                ("/* x */", Comment),
+                (":", Colon),
+                ("1", Number),
+                (",", Comma),
+                ("null", Key),
+                (":", Colon),
+                ("true", Bool),
+                (",", Comma),
+                ("$c", Key),
+                (":", Colon),
+                ("2024-01-01T00:00:00Z", Date),
+                ("}", RightBrace),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_covers_every_byte() {
+        for input in ["{ a: 'unterminated\n, b: ~~ }", "[1, /* open", "é: ü", ""] {
+            let mut end = 0;
+            for token in Lexer::new(input) {
+                assert_eq!(token.span.start, end, "{}", input);
+                assert!(!token.span.is_empty());
+                end = token.span.end;
+            }
+            assert_eq!(end, input.len());
+        }
+        assert!(tokens("{ a: ~~ }").contains(&("~~", TokenKind::Error)));
+    }
+}
@@ -5,17 +5,134 @@
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "async")]
+pub mod async_io;
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod binary;
+pub mod borrowed;
+#[cfg(feature = "bson")]
+pub mod bson;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod codegen;
+pub mod config;
+pub mod convert;
+#[cfg(any(feature = "arrow", feature = "polars"))]
+mod columns;
+mod de;
+pub mod derive;
+#[cfg(feature = "diesel")]
+pub mod diesel;
+pub mod diff;
+pub mod edit;
+#[cfg(feature = "encrypt")]
+pub mod encrypt;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+#[cfg(feature = "sha2")]
+pub mod hash;
+pub mod include;
+#[cfg(feature = "async")]
+mod incremental;
+pub mod index;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+pub mod lexer;
+pub mod lines;
+pub mod lint;
+pub mod log;
+pub mod map;
+#[cfg(any(feature = "web", feature = "reqwest", feature = "utoipa"))]
+mod media_type;
+pub mod merge;
+pub mod migrate;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+pub mod observe;
 mod parser;
+pub mod path;
+pub mod pipeline;
+#[cfg(any(feature = "sqlx", feature = "diesel"))]
+mod pg_scalar;
+#[cfg(any(feature = "postgres", feature = "sqlx", feature = "diesel", feature = "redis"))]
+mod pg_wire;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+mod pointer;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "redis")]
+pub mod redis;
+pub mod refs;
+#[cfg(feature = "diagnostics")]
+pub mod report;
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+pub mod rpc;
+pub mod schema;
+mod search;
+#[cfg(feature = "schemars")]
+pub mod schemars;
+mod scanner;
+pub mod ser;
 mod serializer;
+#[cfg(any(feature = "ed25519", feature = "hmac"))]
+pub mod sign;
+mod span;
+#[cfg(feature = "sqlx")]
+pub mod sqlx;
+pub mod stream;
+mod string;
+pub mod substitute;
+pub mod template;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tokenizer;
 mod types;
+pub mod upgrade;
+#[cfg(feature = "utoipa")]
+pub mod utoipa;
+pub mod validate;
 mod value;
+pub mod warnings;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "notify")]
+pub mod watch;
+#[cfg(feature = "web")]
+pub mod web;
 
-pub use error::{Error, Result};
-pub use parser::parse;
-pub use serializer::{to_string, to_string_pretty as serializer_to_string_pretty};
+pub use binary::Compression;
+pub use borrowed::BorrowedValue;
+pub use diff::diff;
+pub use derive::{KjsonDeserialize, KjsonSerialize};
+pub use error::{Error, ErrorKind, LiteralKind, Result};
+pub use format::{minify, reformat, FormatOptions};
+pub use lexer::highlight;
+pub use parser::{parse, parse_borrowed, Parser};
+pub use span::{Location, SourceMap, Span};
+pub use serializer::{
+    to_canonical_string, to_string, to_string_pretty as serializer_to_string_pretty,
+    to_string_summarized, to_string_with, to_writer_with, BinaryEncoding, ExponentFormat, SerializeOptions,
+    SummaryOptions,
+};
+pub use ser::{to_writer, to_writer_pretty, Serializer};
+pub use validate::validate;
 pub use types::{BigInt, Instant, Duration, Date, Decimal128, uuid_v4, uuid_v7};
-pub use value::{from_value, to_value, Value};
+pub use value::{from_json, from_value, to_value, Map, Str, Value};
 
 // Re-export UUID type
 pub use uuid::Uuid;
@@ -30,18 +147,50 @@ where
     from_value(value)
 }
 
+/// Parse kJSON read from `reader` into a Rust value.
+///
+/// gzip and zstd input is decompressed when the `gzip` or `zstd` feature is
+/// enabled; see [`binary::decompress_reader`].
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: std::io::Read,
+    T: for<'de> serde::Deserialize<'de>,
+{
+    from_value(parse_reader(reader)?)
+}
+
+/// Parse a kJSON file, decompressing a gzip or zstd file such as
+/// `export.kjson.gz` when the matching feature is enabled
+pub fn parse_file(path: impl AsRef<std::path::Path>) -> Result<Value> {
+    let file = std::fs::File::open(path)?;
+    parse_reader(std::io::BufReader::new(file))
+}
+
+/// Parse kJSON read from `reader` into a Value, decompressing it as
+/// [`from_reader`] does
+pub fn parse_reader<R: std::io::Read>(reader: R) -> Result<Value> {
+    use std::io::Read;
+    let mut text = String::new();
+    binary::decompress_reader(reader)?.read_to_string(&mut text)?;
+    parse(&text)
+}
+
 /// Serialize a Rust value to a kJSON string
 pub fn to_string_pretty<T>(value: &T) -> Result<String>
 where
     T: serde::Serialize,
 {
-    let val = to_value(value)?;
-    serializer::to_string_pretty(&val)
+    ser::to_string_with(
+        value,
+        &SerializeOptions {
+            pretty: true,
+            ..SerializeOptions::default()
+        },
+    )
 }
 
-// Feature-gated derive macro re-export (coming soon)
-// #[cfg(feature = "derive")]
-// pub use kjson_derive::{Deserialize, Serialize};
+#[cfg(feature = "derive")]
+pub use kjson_derive::{KjsonDeserialize, KjsonSerialize};
 
 #[cfg(test)]
 mod tests {
@@ -52,4 +201,34 @@ mod tests {
         let result: f64 = from_str("123").unwrap();
         assert_eq!(result, 123.0);
     }
+
+    #[test]
+    fn test_from_reader_and_file() {
+        let ports: Vec<u16> = from_reader("[80, 443]".as_bytes()).unwrap();
+        assert_eq!(ports, [80, 443]);
+
+        let path = std::env::temp_dir().join(format!("kjson-file-{}.kjson", std::process::id()));
+        std::fs::write(&path, "{ id: 7n }").unwrap();
+        let value = parse_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(value.unwrap(), parse("{ id: 7n }").unwrap());
+        assert!(parse_file(&path).unwrap_err().is_io());
+    }
+
+    #[test]
+    fn test_reader_and_writer_values() {
+        let value = parse_reader("{ id: 7n, at: 2025-01-10T12:00:00Z }".as_bytes()).unwrap();
+        let mut out = Vec::new();
+        to_writer_with(&mut out, &value, &SerializeOptions::default()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), to_string(&value).unwrap());
+
+        #[derive(serde::Serialize)]
+        struct Port {
+            port: u16,
+        }
+        let mut out = Vec::new();
+        to_writer(&mut out, &Port { port: 80 }).unwrap();
+        assert_eq!(parse_reader(out.as_slice()).unwrap(), parse("{ port: 80 }").unwrap());
+        assert!(parse_reader("[1,".as_bytes()).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,325 @@
+//! kJSON Lines: one document per line
+//!
+//! [`LinesReader`] parses newline-delimited documents from any `BufRead`,
+//! and [`LinesWriter`] writes them, for log pipelines and bulk exports:
+//!
+//! ```
+//! use kjson::lines::{LinesReader, LinesWriter};
+//!
+//! let mut out = Vec::new();
+//! let mut writer = LinesWriter::new(&mut out);
+//! writer.write_value(&kjson::parse("{ id: 1n, note: 'two\\nlines' }")?)?;
+//! writer.write_value(&kjson::parse("{ id: 2n }")?)?;
+//! assert_eq!(out, b"{id: 1n, note: 'two\\nlines'}\n{id: 2n}\n");
+//!
+//! let ids: Vec<String> = LinesReader::new(&out[..])
+//!     .map(|doc| kjson::to_string(&doc?.as_object().unwrap()["id"]))
+//!     .collect::<kjson::Result<_>>()?;
+//! assert_eq!(ids, ["1n", "2n"]);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! A line break ends a document, except inside a backtick string or a
+//! `/* */` comment, which may run on over several lines. Lines holding only
+//! whitespace and comments are skipped. A document that fails to parse
+//! yields an error naming its line, and reading carries on with the next
+//! line; I/O errors end the stream.
+//!
+//! The writer escapes line breaks in strings, so every document it writes
+//! takes exactly one line.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::{to_writer_with, SerializeOptions};
+use crate::value::{from_value, Value};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+
+/// Lexical state at the end of a line
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Between tokens
+    Code,
+    /// Inside a string opened with `quote`
+    Quoted { quote: u8, escape: bool },
+    /// Inside a `/* */` comment, `star` if the last byte was `*`
+    BlockComment { star: bool },
+}
+
+/// Scan one line from `state`, setting `content` if it holds anything but
+/// whitespace and comments. Returns the state the next line starts in.
+fn scan_line(line: &[u8], mut state: State, content: &mut bool) -> State {
+    let mut i = 0;
+    while i < line.len() {
+        let b = line[i];
+        state = match state {
+            State::Quoted {
+                quote,
+                escape: false,
+            } if b == b'\\' => State::Quoted {
+                quote,
+                escape: true,
+            },
+            State::Quoted {
+                quote,
+                escape: false,
+            } if b == quote => State::Code,
+            State::Quoted { quote, .. } => State::Quoted {
+                quote,
+                escape: false,
+            },
+            State::BlockComment { star } if star && b == b'/' => State::Code,
+            State::BlockComment { .. } => State::BlockComment { star: b == b'*' },
+            State::Code => match (b, line.get(i + 1)) {
+                // The rest of the line is a comment
+                (b'/', Some(b'/')) => return State::Code,
+                (b'/', Some(b'*')) => {
+                    i += 1;
+                    State::BlockComment { star: false }
+                }
+                (b' ' | b'\t' | b'\r' | b'\n', _) => State::Code,
+                (b'"' | b'\'' | b'`', _) => {
+                    *content = true;
+                    State::Quoted {
+                        quote: b,
+                        escape: false,
+                    }
+                }
+                _ => {
+                    *content = true;
+                    State::Code
+                }
+            },
+        };
+        i += 1;
+    }
+    match state {
+        // Other strings end at the line break, and fail to parse
+        State::Quoted { quote, .. } if quote != b'`' => State::Code,
+        state => state,
+    }
+}
+
+/// Iterator over the documents of kJSON Lines input
+#[derive(Debug)]
+pub struct LinesReader<R> {
+    reader: R,
+    /// Lines read so far
+    line: usize,
+    /// Text of the document being read
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> LinesReader<R> {
+    /// Read documents from a buffered reader
+    pub fn new(reader: R) -> Self {
+        LinesReader {
+            reader,
+            line: 0,
+            buf: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Deserialize each document into `T`
+    pub fn deserialize<T>(self) -> impl Iterator<Item = Result<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.map(|value| value.and_then(from_value))
+    }
+
+    /// Number of lines read so far
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Consume the reader, returning the underlying `BufRead`
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the next document, or `None` at the end of the input
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.buf.clear();
+        let mut first = self.line + 1;
+        let mut state = State::Code;
+        let mut content = false;
+        loop {
+            let start = self.buf.len();
+            let n = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e.into());
+                }
+            };
+            if n == 0 {
+                self.done = true;
+                if !content {
+                    return Ok(None);
+                }
+                break;
+            }
+            self.line += 1;
+            state = scan_line(&self.buf[start..], state, &mut content);
+            if state == State::Code {
+                if content {
+                    break;
+                }
+                // Blank or only comments
+                self.buf.clear();
+                first = self.line + 1;
+            }
+        }
+
+        let at_eof = self.done;
+        let located = |position: usize, message: &str, text: &[u8]| {
+            let line = first + text[..position].iter().filter(|&&b| b == b'\n').count();
+            Error::ParseError {
+                position,
+                message: format!("line {}: {}", line, message),
+            }
+        };
+        let text = match std::str::from_utf8(&self.buf) {
+            Ok(text) => text,
+            Err(e) => return Err(located(e.valid_up_to(), "Invalid UTF-8", &self.buf)),
+        };
+        parse(text).map(Some).map_err(|e| match e {
+            Error::ParseError { position, message } => {
+                located(position.min(text.len()), &message, &self.buf)
+            }
+            // The document stopped at its line break, not the input's end
+            Error::UnexpectedEof if !at_eof => {
+                located(text.trim_end().len(), "Unexpected end of line", &self.buf)
+            }
+            other => other,
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for LinesReader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+/// Writes one compact document per line
+#[derive(Debug)]
+pub struct LinesWriter<W: Write> {
+    writer: W,
+    /// Text of the document being written
+    buf: Vec<u8>,
+}
+
+impl<W: Write> LinesWriter<W> {
+    /// Write documents to `writer`. Wrap unbuffered writers such as a
+    /// `File` in a `BufWriter`.
+    pub fn new(writer: W) -> Self {
+        LinesWriter {
+            writer,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Write any serializable value as one line
+    pub fn write<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.buf.clear();
+        crate::ser::to_writer(&mut self.buf, value)?;
+        self.finish_line()
+    }
+
+    /// Write a value as one line
+    pub fn write_value(&mut self, value: &Value) -> Result<()> {
+        self.buf.clear();
+        to_writer_with(&mut self.buf, value, &SerializeOptions::default())?;
+        self.finish_line()
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// Consume the writer, returning the underlying `Write`
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Write the serialized document and its line break together, so a
+    /// value that fails to serialize leaves no partial line behind
+    fn finish_line(&mut self) -> Result<()> {
+        self.buf.push(b'\n');
+        self.writer.write_all(&self.buf)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_multiline_documents() {
+        let input = "{ a: 1 } // first\n\n  // nothing here\n\
+                     /* a comment\n spanning */ { b: `x\ny\\`\n` }\n{ c: '//' }";
+        // A tiny buffer splits every line across reads
+        let docs: Vec<Value> = LinesReader::new(BufReader::with_capacity(3, input.as_bytes()))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            docs,
+            [
+                parse("{ a: 1 }").unwrap(),
+                parse("{ b: 'x\\ny`\\n' }").unwrap(),
+                parse("{ c: '//' }").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors_name_lines() {
+        let input = "1\n{ a: 'open\n[2,\n{ b: `x\ny` c }\n3 4\n5";
+        let mut reader = LinesReader::new(input.as_bytes());
+        let results: Vec<_> = reader.by_ref().map(|r| r.map_err(|e| e.to_string())).collect();
+        assert_eq!(results.len(), 6);
+        assert_eq!(results[0], Ok(Value::Integer(1)));
+        for (result, line) in results[1..5].iter().zip(["line 2", "line 3", "line 5", "line 6"]) {
+            assert!(result.as_ref().is_err_and(|e| e.contains(line)), "{:?}", result);
+        }
+        assert_eq!(results[5], Ok(Value::Integer(5)));
+        assert_eq!(reader.line(), 7);
+    }
+
+    #[test]
+    fn test_writer() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Event {
+            name: String,
+            at: u32,
+        }
+
+        let mut writer = LinesWriter::new(Vec::new());
+        let events = [
+            Event { name: "start\nup".to_string(), at: 1 },
+            Event { name: "`tick`".to_string(), at: 2 },
+        ];
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let out = writer.into_inner();
+        assert_eq!(out.iter().filter(|&&b| b == b'\n').count(), 2);
+        let read: Vec<Event> = LinesReader::new(&out[..])
+            .deserialize()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(read, events);
+    }
+}
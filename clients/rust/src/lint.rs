@@ -0,0 +1,452 @@
+//! Style and correctness checks for kJSON source
+//!
+//! A [`Linter`] reports [`Diagnostic`]s with the [`Span`] of the offending
+//! text, for CI checks and editor integrations:
+//!
+//! | Rule                          | Default   | Reports                                             |
+//! |-------------------------------|-----------|-----------------------------------------------------|
+//! | [`Rule::DuplicateKeys`]       | error     | a key repeated in one object (the last one wins)    |
+//! | [`Rule::InconsistentQuotes`]  | warning   | a string or key not using the document's main quote |
+//! | [`Rule::MissingNumberSuffix`] | warning   | a plain number too precise for a float, which needs `n` or `m` |
+//! | [`Rule::DeepNesting`]         | warning   | arrays and objects nested beyond the maximum depth  |
+//! | [`Rule::TrailingComma`]       | info      | a comma after the last member                       |
+//!
+//! ```
+//! use kjson::lint::{Linter, Rule, Severity};
+//!
+//! let linter = Linter::new()
+//!     .severity(Rule::TrailingComma, Severity::Error)
+//!     .disable(Rule::InconsistentQuotes);
+//! let diagnostics = linter.lint("{ id: 12345678901234567890, id: 2, }")?;
+//! let rules: Vec<Rule> = diagnostics.iter().map(|d| d.rule).collect();
+//! assert_eq!(rules, [Rule::MissingNumberSuffix, Rule::DuplicateKeys, Rule::TrailingComma]);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Syntax errors are returned as `Err`; diagnostics are only produced for
+//! documents that parse.
+
+use crate::edit::{Document, Item, Member, Scalar};
+use crate::error::Result;
+use crate::span::Span;
+use crate::value::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Largest integer an f64 holds exactly, 2^53
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+/// Significant digits an f64 always round-trips
+const FLOAT_DIGITS: usize = 15;
+
+/// A lint check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rule {
+    /// The same key twice in one object
+    DuplicateKeys,
+    /// Strings or keys quoted differently from the rest of the document
+    InconsistentQuotes,
+    /// Numbers that lose precision as floats and should be BigInt or Decimal128
+    MissingNumberSuffix,
+    /// Arrays and objects nested deeper than the limit
+    DeepNesting,
+    /// A comma after the last element or member
+    TrailingComma,
+}
+
+impl Rule {
+    /// Every rule, in order
+    pub const ALL: [Rule; 5] = [
+        Rule::DuplicateKeys,
+        Rule::InconsistentQuotes,
+        Rule::MissingNumberSuffix,
+        Rule::DeepNesting,
+        Rule::TrailingComma,
+    ];
+
+    /// The rule's name in kebab case, e.g. `duplicate-keys`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::DuplicateKeys => "duplicate-keys",
+            Rule::InconsistentQuotes => "inconsistent-quotes",
+            Rule::MissingNumberSuffix => "missing-number-suffix",
+            Rule::DeepNesting => "deep-nesting",
+            Rule::TrailingComma => "trailing-comma",
+        }
+    }
+
+    /// Severity when not configured
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::DuplicateKeys => Severity::Error,
+            Rule::TrailingComma => Severity::Info,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth knowing
+    Info,
+    /// Probably a mistake
+    Warning,
+    /// Almost certainly a mistake
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One problem found by the linter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// The rule that found it
+    pub rule: Rule,
+    /// How serious it is
+    pub severity: Severity,
+    /// Where it is in the input
+    pub span: Span,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] at {}: {}",
+            self.severity, self.rule, self.span, self.message
+        )
+    }
+}
+
+/// Configured set of lint rules
+#[derive(Debug, Clone)]
+pub struct Linter {
+    severities: HashMap<Rule, Option<Severity>>,
+    max_depth: usize,
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter::new()
+    }
+}
+
+impl Linter {
+    /// All rules at their default severities, with a maximum depth of 16
+    pub fn new() -> Self {
+        Linter {
+            severities: Rule::ALL
+                .iter()
+                .map(|rule| (*rule, Some(rule.default_severity())))
+                .collect(),
+            max_depth: 16,
+        }
+    }
+
+    /// Report `rule` at `severity`
+    pub fn severity(mut self, rule: Rule, severity: Severity) -> Self {
+        self.severities.insert(rule, Some(severity));
+        self
+    }
+
+    /// Turn `rule` off
+    pub fn disable(mut self, rule: Rule) -> Self {
+        self.severities.insert(rule, None);
+        self
+    }
+
+    /// Deepest nesting allowed by [`Rule::DeepNesting`]; the top level is depth 1
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Check a document, returning diagnostics in source order
+    pub fn lint(&self, input: &str) -> Result<Vec<Diagnostic>> {
+        let doc = Document::parse(input)?;
+        let mut walk = Walk {
+            linter: self,
+            quoted: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        walk.item(&doc.root, doc.prefix.len(), 1);
+        walk.check_quotes();
+        walk.diagnostics
+            .sort_by_key(|d| (d.span.start, d.span.end, d.rule));
+        Ok(walk.diagnostics)
+    }
+}
+
+/// Check a document with the default rules
+pub fn lint(input: &str) -> Result<Vec<Diagnostic>> {
+    Linter::new().lint(input)
+}
+
+/// A quoted string or key seen during the walk
+struct Quoted<'a> {
+    span: Span,
+    quote: char,
+    content: &'a str,
+    key: bool,
+}
+
+struct Walk<'a> {
+    linter: &'a Linter,
+    quoted: Vec<Quoted<'a>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Walk<'a> {
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        if let Some(Some(severity)) = self.linter.severities.get(&rule) {
+            self.diagnostics.push(Diagnostic {
+                rule,
+                severity: *severity,
+                span,
+                message,
+            });
+        }
+    }
+
+    /// Check `item`, which starts at `start`, returning its length
+    fn item(&mut self, item: &'a Item, start: usize, depth: usize) -> usize {
+        let end = match item {
+            Item::Scalar(scalar) => {
+                self.scalar(scalar, start);
+                return scalar.raw.len();
+            }
+            Item::Array(array) => {
+                let mut offset = start + 1;
+                for (i, element) in array.elements.iter().enumerate() {
+                    offset += element.prefix.len();
+                    offset += self.item(&element.value, offset, depth + 1);
+                    offset += element.suffix.len();
+                    if element.comma {
+                        if i + 1 == array.elements.len() {
+                            self.trailing_comma(offset);
+                        }
+                        offset += 1;
+                    }
+                }
+                offset + array.close.len() + 1
+            }
+            Item::Object(object) => {
+                let mut offset = start + 1;
+                let mut seen = HashSet::new();
+                for (i, member) in object.members.iter().enumerate() {
+                    offset += member.prefix.len();
+                    self.key(member, offset, &mut seen);
+                    offset += member.raw_key.len()
+                        + member.before_colon.len()
+                        + 1
+                        + member.after_colon.len();
+                    offset += self.item(&member.value, offset, depth + 1);
+                    offset += member.suffix.len();
+                    if member.comma {
+                        if i + 1 == object.members.len() {
+                            self.trailing_comma(offset);
+                        }
+                        offset += 1;
+                    }
+                }
+                offset + object.close.len() + 1
+            }
+        };
+        if depth == self.linter.max_depth + 1 {
+            // Report the outermost container past the limit only
+            self.report(
+                Rule::DeepNesting,
+                Span::new(start, end),
+                format!(
+                    "nested {} levels deep, more than {}",
+                    depth, self.linter.max_depth
+                ),
+            );
+        }
+        end - start
+    }
+
+    fn key(&mut self, member: &'a Member, start: usize, seen: &mut HashSet<&'a str>) {
+        let span = Span::new(start, start + member.raw_key.len());
+        if !seen.insert(&member.key) {
+            self.report(
+                Rule::DuplicateKeys,
+                span,
+                format!(
+                    "duplicate key `{}`; this value replaces the earlier one",
+                    member.key
+                ),
+            );
+        }
+        if let Some(quote) = member
+            .raw_key
+            .chars()
+            .next()
+            .filter(|c| *c == '\'' || *c == '"')
+        {
+            self.quoted.push(Quoted {
+                span,
+                quote,
+                content: &member.key,
+                key: true,
+            });
+        }
+    }
+
+    fn scalar(&mut self, scalar: &'a Scalar, start: usize) {
+        let span = Span::new(start, start + scalar.raw.len());
+        match &scalar.value {
+            Value::String(content) => {
+                if let Some(quote) = scalar
+                    .raw
+                    .chars()
+                    .next()
+                    .filter(|c| *c == '\'' || *c == '"')
+                {
+                    self.quoted.push(Quoted {
+                        span,
+                        quote,
+                        content,
+                        key: false,
+                    });
+                }
+            }
+            // Integers beyond 2^53 parse exactly here, but not as the floats
+            // other implementations read them into
+            number @ (Value::Number(_) | Value::Integer(_) | Value::UInteger(_)) => {
+                let n = number.as_f64().unwrap_or_default();
+                let raw = scalar.raw.as_str();
+                let integer = !raw.contains(['.', 'e', 'E']);
+                if integer && n.abs() > MAX_SAFE_INTEGER {
+                    self.report(
+                        Rule::MissingNumberSuffix,
+                        span,
+                        format!(
+                            "{} is too large for a float; write {}n for a BigInt",
+                            raw, raw
+                        ),
+                    );
+                } else if !integer && significant_digits(raw) > FLOAT_DIGITS {
+                    self.report(
+                        Rule::MissingNumberSuffix,
+                        span,
+                        format!(
+                            "{} loses precision as a float; write {}m for a Decimal128",
+                            raw, raw
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn trailing_comma(&mut self, offset: usize) {
+        self.report(
+            Rule::TrailingComma,
+            Span::new(offset, offset + 1),
+            "trailing comma after the last member".to_string(),
+        );
+    }
+
+    /// Report strings and keys that do not use the most common quote
+    fn check_quotes(&mut self) {
+        let doubles = self.quoted.iter().filter(|q| q.quote == '"').count();
+        // Ties go to single quotes, as in `to_string`
+        let main = if doubles > self.quoted.len() - doubles {
+            '"'
+        } else {
+            '\''
+        };
+        let quoted = std::mem::take(&mut self.quoted);
+        for q in quoted {
+            // Switching would mean escaping the main quote
+            if q.quote != main && !q.content.contains(main) {
+                let what = if q.key { "key" } else { "string" };
+                self.report(
+                    Rule::InconsistentQuotes,
+                    q.span,
+                    format!("{} uses {} but the document uses {}", what, q.quote, main),
+                );
+            }
+        }
+    }
+}
+
+/// Significant digits in a decimal number literal
+fn significant_digits(raw: &str) -> usize {
+    let mantissa = raw.split(['e', 'E']).next().unwrap_or(raw);
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    digits.trim_start_matches('0').trim_end_matches('0').len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(input: &str) -> Vec<(Rule, &str)> {
+        lint(input)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.rule, d.span.slice(input)))
+            .collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(
+            rules("{ a: 1, 'b': 'x', 'c': \"it's\", a: 2, d: \"y\" }"),
+            [
+                (Rule::DuplicateKeys, "a"),
+                (Rule::InconsistentQuotes, "\"y\"")
+            ]
+        );
+        assert_eq!(
+            rules("[12345678901234567890, 9007199254740992, 3.14159265358979323, 0.5, 1.50m,]"),
+            [
+                (Rule::MissingNumberSuffix, "12345678901234567890"),
+                (Rule::MissingNumberSuffix, "3.14159265358979323"),
+                (Rule::TrailingComma, ","),
+            ]
+        );
+        assert!(rules("// clean\n{ a: [1, 2], b: { c: 'd' } }").is_empty());
+    }
+
+    #[test]
+    fn test_configuration() {
+        let deep = "[[[[1]]], { a: { b: [] } }]";
+        let linter = Linter::new().max_depth(2);
+        let found = linter.lint(deep).unwrap();
+        let spans: Vec<&str> = found.iter().map(|d| d.span.slice(deep)).collect();
+        assert_eq!(spans, ["[[1]]", "{ b: [] }"]);
+        assert_eq!(found[0].severity, Severity::Warning);
+
+        let linter = Linter::new()
+            .disable(Rule::DeepNesting)
+            .severity(Rule::TrailingComma, Severity::Error);
+        let found = linter.lint("[1,]").unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert_eq!(
+            found[0].to_string(),
+            "error [trailing-comma] at 2..3: trailing comma after the last member"
+        );
+        assert!(linter.lint("[1").is_err());
+    }
+}
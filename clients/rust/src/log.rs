@@ -0,0 +1,348 @@
+//! Append-only kJSON lines journals
+//!
+//! A [`KjsonLogWriter`] appends one compact document per line to a file,
+//! for event journals and audit trails that are read back with
+//! [`StreamReader`](crate::stream::StreamReader):
+//!
+//! ```
+//! use kjson::log::{KjsonLogWriter, LogOptions, SyncPolicy};
+//!
+//! # let dir = std::env::temp_dir().join(format!("kjson-log-doc-{}", std::process::id()));
+//! # std::fs::create_dir_all(&dir)?;
+//! let options = LogOptions {
+//!     sync: SyncPolicy::EveryWrite,
+//!     max_bytes: Some(64 << 20),
+//!     max_files: Some(10),
+//!     ..LogOptions::default()
+//! };
+//! let mut log = KjsonLogWriter::open_with(dir.join("events.kjsonl"), &options)?;
+//! log.append_value(&kjson::parse("{ event: 'created', id: 7n }")?)?;
+//! # std::fs::remove_dir_all(&dir)?;
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Each document goes to the file in a single write, so a crash can leave
+//! at most the last line incomplete. Opening the file cuts such a torn line
+//! off, so the journal always ends with a whole document.
+//!
+//! When the file passes [`LogOptions::max_bytes`] or is older than
+//! [`LogOptions::max_age`], it is renamed to `events.kjsonl.1`,
+//! `events.kjsonl.2` and so on, counting up, and a new file is started.
+
+use crate::error::Result;
+use crate::serializer::to_string;
+use crate::value::{to_value, Value};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Bytes read at a time while looking for the last line break
+const SCAN_CHUNK: u64 = 4096;
+
+/// When appended lines are flushed to disk with `fsync`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system
+    #[default]
+    Never,
+    /// After every line, so an acknowledged append survives a power loss
+    EveryWrite,
+    /// After a line once this long has passed since the last sync
+    Interval(Duration),
+}
+
+/// Settings for [`KjsonLogWriter::open_with`]
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// When lines are synced to disk
+    pub sync: SyncPolicy,
+    /// Start a new file before one would grow past this many bytes
+    pub max_bytes: Option<u64>,
+    /// Start a new file once the current one has been written for this long
+    pub max_age: Option<Duration>,
+    /// Rotated files to keep, deleting the oldest beyond it
+    pub max_files: Option<usize>,
+}
+
+/// Appends documents to a kJSON lines file
+#[derive(Debug)]
+pub struct KjsonLogWriter {
+    path: PathBuf,
+    options: LogOptions,
+    file: File,
+    len: u64,
+    started: Instant,
+    last_sync: Instant,
+    /// Number of the next rotated file
+    next_archive: u64,
+}
+
+impl KjsonLogWriter {
+    /// Open `path` for appending, creating it if needed
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::open_with(path, &LogOptions::default())
+    }
+
+    /// Open `path` for appending with custom options
+    pub fn open_with(path: impl Into<PathBuf>, options: &LogOptions) -> Result<Self> {
+        let path = path.into();
+        let (file, len) = open_segment(&path)?;
+        let next_archive = archives(&path)?.last().map_or(1, |(n, _)| n + 1);
+        let now = Instant::now();
+        Ok(KjsonLogWriter {
+            path,
+            options: options.clone(),
+            file,
+            len,
+            started: now,
+            last_sync: now,
+            next_archive,
+        })
+    }
+
+    /// Path of the file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the file being written, in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file being written is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Serialize `value` and append it as one line
+    pub fn append<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        self.append_value(&to_value(value)?)
+    }
+
+    /// Append a value as one line
+    pub fn append_value(&mut self, value: &Value) -> Result<()> {
+        let mut line = to_string(value)?;
+        line.push('\n');
+        if self.len > 0 && self.should_rotate(line.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.len += line.len() as u64;
+        match self.options.sync {
+            SyncPolicy::Never => {}
+            SyncPolicy::EveryWrite => self.sync()?,
+            SyncPolicy::Interval(interval) => {
+                if self.last_sync.elapsed() >= interval {
+                    self.sync()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush appended lines to disk now, whatever the policy
+    pub fn sync(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Move the current file aside and start a new one, even if no limit
+    /// has been reached
+    pub fn rotate(&mut self) -> Result<()> {
+        self.file.sync_data()?;
+        fs::rename(&self.path, archive_path(&self.path, self.next_archive))?;
+        self.next_archive += 1;
+        let (file, len) = open_segment(&self.path)?;
+        self.file = file;
+        self.len = len;
+        self.started = Instant::now();
+        self.last_sync = self.started;
+
+        if let Some(keep) = self.options.max_files {
+            let archives = archives(&self.path)?;
+            let excess = archives.len().saturating_sub(keep);
+            for (_, path) in &archives[..excess] {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn should_rotate(&self, incoming: u64) -> bool {
+        self.options
+            .max_bytes
+            .is_some_and(|max| self.len + incoming > max)
+            || self
+                .options
+                .max_age
+                .is_some_and(|max| self.started.elapsed() >= max)
+    }
+}
+
+/// Open `path` for appending, cutting off a torn last line, and return its
+/// length
+fn open_segment(path: &Path) -> Result<(File, u64)> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    let len = file.metadata()?.len();
+    let whole = whole_lines_len(&mut file, len)?;
+    if whole < len {
+        file.set_len(whole)?;
+        file.sync_data()?;
+    }
+    Ok((file, whole))
+}
+
+/// Length of `file` up to and including its last line break
+fn whole_lines_len(file: &mut File, len: u64) -> Result<u64> {
+    let mut end = len;
+    let mut buf = vec![0; SCAN_CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(SCAN_CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(i) = chunk.iter().rposition(|&b| b == b'\n') {
+            return Ok(start + i as u64 + 1);
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+fn archive_path(path: &Path, n: u64) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Rotated files of `path`, oldest first
+fn archives(path: &Path) -> Result<Vec<(u64, PathBuf)>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+        return Ok(Vec::new());
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let number = file_name
+            .to_str()
+            .and_then(|f| f.strip_prefix(name))
+            .and_then(|rest| rest.strip_prefix('.'))
+            .and_then(|n| n.parse::<u64>().ok());
+        if let Some(n) = number {
+            found.push((n, entry.path()));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("kjson-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_append_and_repair() {
+        let dir = temp_dir("repair");
+        let path = dir.join("events.kjsonl");
+        let mut log = KjsonLogWriter::open(&path).unwrap();
+        log.append_value(&parse("{ id: 1n, note: 'a\\nb' }").unwrap())
+            .unwrap();
+        log.append(&[1, 2]).unwrap();
+        log.sync().unwrap();
+        drop(log);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{id: 1n, note: 'a\\nb'}\n[1, 2]\n"
+        );
+
+        // A crash in the middle of a line
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{id: 3").unwrap();
+        drop(file);
+        let mut log = KjsonLogWriter::open(&path).unwrap();
+        assert_eq!(log.len(), 30);
+        log.append(&3).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{id: 1n, note: 'a\\nb'}\n[1, 2]\n3\n"
+        );
+
+        // Nothing whole survives
+        fs::write(&path, "x".repeat(10_000)).unwrap();
+        assert!(KjsonLogWriter::open(&path).unwrap().is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = temp_dir("rotate");
+        let path = dir.join("events.kjsonl");
+        let options = LogOptions {
+            sync: SyncPolicy::Interval(Duration::from_secs(60)),
+            max_bytes: Some(10),
+            max_files: Some(2),
+            ..LogOptions::default()
+        };
+        let mut log = KjsonLogWriter::open_with(&path, &options).unwrap();
+        for i in 0..5 {
+            log.append(&format!("line{}", i)).unwrap();
+        }
+        drop(log);
+
+        let names = |dir: &Path| {
+            let mut names: Vec<_> = fs::read_dir(dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().into_string().unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(&dir),
+            ["events.kjsonl", "events.kjsonl.3", "events.kjsonl.4"]
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "'line4'\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("events.kjsonl.4")).unwrap(),
+            "'line3'\n"
+        );
+
+        // Numbering carries on after reopening
+        let mut log = KjsonLogWriter::open_with(&path, &options).unwrap();
+        log.rotate().unwrap();
+        assert!(dir.join("events.kjsonl.5").exists());
+        assert!(!dir.join("events.kjsonl.3").exists());
+
+        let options = LogOptions {
+            max_age: Some(Duration::ZERO),
+            ..LogOptions::default()
+        };
+        let mut log = KjsonLogWriter::open_with(&path, &options).unwrap();
+        log.append(&1).unwrap();
+        log.append(&2).unwrap();
+        assert!(dir.join("events.kjsonl.6").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,430 @@
+//! The map type of object values
+//!
+//! [`Map`] holds the members of a [`Value::Object`]. It is a `HashMap`, or
+//! with the `preserve_order` feature an `IndexMap` that keeps keys in the
+//! order they were parsed or inserted. The API is the same either way, so
+//! enabling the feature never breaks code that compiles without it:
+//!
+//! ```
+//! use kjson::{Map, Value};
+//!
+//! let mut map = Map::new();
+//! map.insert("name", Value::String("kjson".into()));
+//! *map.entry("count").or_insert(Value::Integer(0)) = Value::Integer(1);
+//! assert_eq!(map["count"], Value::Integer(1));
+//! assert_eq!(map.remove("name"), Some(Value::String("kjson".into())));
+//! assert_eq!(map.len(), 1);
+//! ```
+
+use crate::value::{Str, Value};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::iter::FusedIterator;
+use std::ops;
+
+#[cfg(not(feature = "preserve_order"))]
+use std::collections::hash_map as imp;
+#[cfg(feature = "preserve_order")]
+use indexmap::map as imp;
+
+#[cfg(not(feature = "preserve_order"))]
+type MapImpl = imp::HashMap<Str, Value>;
+#[cfg(feature = "preserve_order")]
+type MapImpl = imp::IndexMap<Str, Value>;
+
+/// The members of an object, keyed by [`Str`]
+///
+/// Removing a member never reorders the others, so with `preserve_order`
+/// the remaining keys stay in insertion order. Equality ignores order.
+#[derive(Clone, Default, PartialEq)]
+pub struct Map {
+    map: MapImpl,
+}
+
+impl Map {
+    /// An empty map
+    pub fn new() -> Self {
+        Map { map: MapImpl::new() }
+    }
+
+    /// An empty map with room for `capacity` members
+    pub fn with_capacity(capacity: usize) -> Self {
+        Map { map: MapImpl::with_capacity(capacity) }
+    }
+
+    /// Remove every member
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    /// The value stored under `key`
+    pub fn get<Q>(&self, key: &Q) -> Option<&Value>
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get(key)
+    }
+
+    /// A mutable reference to the value stored under `key`
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Value>
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get_mut(key)
+    }
+
+    /// The stored key and value for `key`
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&Str, &Value)>
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.get_key_value(key)
+    }
+
+    /// Whether the map has a member named `key`
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Store `value` under `key`, returning the value it replaces.
+    ///
+    /// A replaced member keeps its position with `preserve_order`; a new
+    /// one goes last.
+    pub fn insert(&mut self, key: impl Into<Str>, value: Value) -> Option<Value> {
+        self.map.insert(key.into(), value)
+    }
+
+    /// Remove the member named `key`, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Value>
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Remove the member named `key`, returning its key and value
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(Str, Value)>
+    where
+        Str: Borrow<Q>,
+        Q: ?Sized + Hash + Eq,
+    {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.map.remove_entry(key);
+        #[cfg(feature = "preserve_order")]
+        return self.map.shift_remove_entry(key);
+    }
+
+    /// Keep only the members for which `keep` returns true
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Str, &mut Value) -> bool,
+    {
+        self.map.retain(keep);
+    }
+
+    /// The entry for `key`, for in-place insertion or update
+    pub fn entry(&mut self, key: impl Into<Str>) -> Entry<'_> {
+        match self.map.entry(key.into()) {
+            imp::Entry::Vacant(vacant) => Entry::Vacant(VacantEntry { vacant }),
+            imp::Entry::Occupied(occupied) => Entry::Occupied(OccupiedEntry { occupied }),
+        }
+    }
+
+    /// The number of members
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Whether the map has no members
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// The members, in insertion order with `preserve_order` and arbitrary
+    /// order otherwise
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { iter: self.map.iter() }
+    }
+
+    /// The members, with mutable values
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut { iter: self.map.iter_mut() }
+    }
+
+    /// The keys
+    pub fn keys(&self) -> Keys<'_> {
+        Keys { iter: self.map.keys() }
+    }
+
+    /// The values
+    pub fn values(&self) -> Values<'_> {
+        Values { iter: self.map.values() }
+    }
+
+    /// The values, mutably
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
+        ValuesMut { iter: self.map.values_mut() }
+    }
+
+    /// The values, consuming the map
+    pub fn into_values(self) -> IntoValues {
+        IntoValues { iter: self.map.into_values() }
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.map, f)
+    }
+}
+
+/// Look up a member, panicking if it is missing
+impl<Q> ops::Index<&Q> for Map
+where
+    Str: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    type Output = Value;
+
+    fn index(&self, key: &Q) -> &Value {
+        self.map.index(key)
+    }
+}
+
+/// Look up a member mutably, panicking if it is missing
+impl<Q> ops::IndexMut<&Q> for Map
+where
+    Str: Borrow<Q>,
+    Q: ?Sized + Hash + Eq,
+{
+    fn index_mut(&mut self, key: &Q) -> &mut Value {
+        self.map.get_mut(key).expect("no entry found for key")
+    }
+}
+
+impl<K: Into<Str>> FromIterator<(K, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (K, Value)>>(members: I) -> Self {
+        Map { map: members.into_iter().map(|(key, value)| (key.into(), value)).collect() }
+    }
+}
+
+impl<K: Into<Str>> Extend<(K, Value)> for Map {
+    fn extend<I: IntoIterator<Item = (K, Value)>>(&mut self, members: I) {
+        self.map.extend(members.into_iter().map(|(key, value)| (key.into(), value)));
+    }
+}
+
+impl<'a> IntoIterator for &'a Map {
+    type Item = (&'a Str, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Map {
+    type Item = (&'a Str, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (Str, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter { iter: self.map.into_iter() }
+    }
+}
+
+/// A member of a [`Map`], from [`Map::entry`]
+pub enum Entry<'a> {
+    /// No member has the key yet
+    Vacant(VacantEntry<'a>),
+    /// A member has the key
+    Occupied(OccupiedEntry<'a>),
+}
+
+/// An [`Entry`] for a missing key
+pub struct VacantEntry<'a> {
+    vacant: imp::VacantEntry<'a, Str, Value>,
+}
+
+/// An [`Entry`] for a key the map holds
+pub struct OccupiedEntry<'a> {
+    occupied: imp::OccupiedEntry<'a, Str, Value>,
+}
+
+impl<'a> Entry<'a> {
+    /// The entry's key
+    pub fn key(&self) -> &Str {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    /// The value, inserting `default` first if the key is missing
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// The value, inserting the result of `default` first if the key is
+    /// missing
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    /// Run `f` on the value if the key is present
+    pub fn and_modify<F: FnOnce(&mut Value)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// The key that would be inserted
+    pub fn key(&self) -> &Str {
+        self.vacant.key()
+    }
+
+    /// Insert `value` under the key
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        self.vacant.insert(value)
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// The stored key
+    pub fn key(&self) -> &Str {
+        self.occupied.key()
+    }
+
+    /// The stored value
+    pub fn get(&self) -> &Value {
+        self.occupied.get()
+    }
+
+    /// The stored value, mutably
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.occupied.get_mut()
+    }
+
+    /// The stored value, borrowed for as long as the map
+    pub fn into_mut(self) -> &'a mut Value {
+        self.occupied.into_mut()
+    }
+
+    /// Replace the value, returning the old one
+    pub fn insert(&mut self, value: Value) -> Value {
+        self.occupied.insert(value)
+    }
+
+    /// Remove the member, returning its value
+    pub fn remove(self) -> Value {
+        #[cfg(not(feature = "preserve_order"))]
+        return self.occupied.remove();
+        #[cfg(feature = "preserve_order")]
+        return self.occupied.shift_remove();
+    }
+}
+
+macro_rules! delegate_iterator {
+    ($(#[$doc:meta] $name:ident $(<$a:lifetime>)? => $inner:ty, $item:ty;)*) => {$(
+        #[$doc]
+        pub struct $name $(<$a>)? {
+            iter: $inner,
+        }
+
+        impl $(<$a>)? Iterator for $name $(<$a>)? {
+            type Item = $item;
+
+            fn next(&mut self) -> Option<$item> {
+                self.iter.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.iter.size_hint()
+            }
+
+            fn nth(&mut self, n: usize) -> Option<$item> {
+                self.iter.nth(n)
+            }
+        }
+
+        impl $(<$a>)? ExactSizeIterator for $name $(<$a>)? {
+            fn len(&self) -> usize {
+                self.iter.len()
+            }
+        }
+
+        impl $(<$a>)? FusedIterator for $name $(<$a>)? {}
+    )*};
+}
+
+delegate_iterator! {
+    /// The members of a [`Map`], from [`Map::iter`]
+    Iter<'a> => imp::Iter<'a, Str, Value>, (&'a Str, &'a Value);
+    /// The members of a [`Map`] with mutable values, from [`Map::iter_mut`]
+    IterMut<'a> => imp::IterMut<'a, Str, Value>, (&'a Str, &'a mut Value);
+    /// The members of a [`Map`], from its `into_iter`
+    IntoIter => imp::IntoIter<Str, Value>, (Str, Value);
+    /// The keys of a [`Map`], from [`Map::keys`]
+    Keys<'a> => imp::Keys<'a, Str, Value>, &'a Str;
+    /// The values of a [`Map`], from [`Map::values`]
+    Values<'a> => imp::Values<'a, Str, Value>, &'a Value;
+    /// The values of a [`Map`], from [`Map::values_mut`]
+    ValuesMut<'a> => imp::ValuesMut<'a, Str, Value>, &'a mut Value;
+    /// The values of a [`Map`], from [`Map::into_values`]
+    IntoValues => imp::IntoValues<Str, Value>, Value;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_api() {
+        let mut map: Map =
+            [("b", Value::Integer(2)), ("a", Value::Integer(1))].into_iter().collect();
+        map.insert(String::from("c"), Value::Null);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("a"), Some(&Value::Integer(1)));
+        assert_eq!(map["b"], Value::Integer(2));
+        assert!(map.contains_key("c"));
+
+        map.entry("a").and_modify(|v| *v = Value::Bool(true)).or_insert(Value::Null);
+        map.entry("d").or_insert_with(|| Value::Integer(4));
+        assert_eq!(map["a"], Value::Bool(true));
+        match map.entry("c") {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), Value::Null),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        map.retain(|key, _| key != "d");
+
+        let keys: Vec<_> = map.keys().map(Str::as_str).collect();
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(keys, ["b", "a"]);
+        }
+        assert_eq!(map, [("a", Value::Bool(true)), ("b", Value::Integer(2))].into_iter().collect());
+        assert_eq!(map.into_values().count(), 2);
+    }
+}
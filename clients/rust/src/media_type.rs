@@ -0,0 +1,30 @@
+//! The kJSON media type, shared by the HTTP integrations
+
+/// Media type of kJSON bodies
+pub const CONTENT_TYPE: &str = "application/kjson";
+
+/// Whether a `Content-Type` value declares kJSON (`application/kjson` or a `+kjson` suffix)
+#[cfg_attr(not(any(feature = "web", feature = "reqwest")), allow(dead_code))]
+pub(crate) fn is_kjson(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence.eq_ignore_ascii_case(CONTENT_TYPE)
+        || essence.rsplit_once('+').is_some_and(|(ty, suffix)| {
+            ty.to_ascii_lowercase().starts_with("application/")
+                && suffix.eq_ignore_ascii_case("kjson")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_kjson() {
+        assert!(is_kjson("application/kjson"));
+        assert!(is_kjson("Application/KJSON; charset=utf-8"));
+        assert!(is_kjson("application/vnd.orders+kjson"));
+        assert!(!is_kjson("application/json"));
+        assert!(!is_kjson("text/plain+kjson"));
+        assert!(!is_kjson(""));
+    }
+}
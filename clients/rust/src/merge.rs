@@ -0,0 +1,114 @@
+//! Deep merging of values
+//!
+//! [`Value::deep_merge`] layers one document over another, the way
+//! configuration files override each other: objects merge key by key and
+//! any other value in the overlay replaces the one below it.
+//!
+//! ```
+//! use kjson::merge::MergeStrategy;
+//!
+//! let mut config = kjson::parse("{ db: { host: 'localhost', port: 5432 }, tags: ['a'] }")?;
+//! let local = kjson::parse("{ db: { port: 6543 }, tags: ['b'] }")?;
+//! config.deep_merge_with(local, MergeStrategy::ConcatArrays);
+//! assert_eq!(
+//!     config,
+//!     kjson::parse("{ db: { host: 'localhost', port: 6543 }, tags: ['a', 'b'] }")?
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! A `null` in the overlay replaces the value too; it does not delete the
+//! member.
+
+use crate::value::Value;
+
+/// How [`Value::deep_merge_with`] combines two arrays.
+///
+/// Objects always merge key by key, and values of different types are
+/// always replaced by the overlay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The overlay's array replaces the base array
+    #[default]
+    ReplaceArrays,
+    /// The overlay's items are appended to the base array
+    ConcatArrays,
+    /// Arrays merge index by index, like objects; extra overlay items are
+    /// appended
+    MergeArrays,
+}
+
+impl Value {
+    /// Merge `other` into this value with [`MergeStrategy::ReplaceArrays`]
+    pub fn deep_merge(&mut self, other: Value) {
+        self.deep_merge_with(other, MergeStrategy::default());
+    }
+
+    /// Merge `other` into this value: objects key by key, arrays as
+    /// `strategy` says, and anything else replaced by `other`
+    pub fn deep_merge_with(&mut self, mut other: Value, strategy: MergeStrategy) {
+        match (self, &mut other, strategy) {
+            (Value::Object(base), Value::Object(overlay), _) => {
+                for (key, value) in std::mem::take(overlay) {
+                    match base.get_mut(&key) {
+                        Some(existing) => existing.deep_merge_with(value, strategy),
+                        None => {
+                            base.insert(key, value);
+                        }
+                    }
+                }
+            }
+            (Value::Array(base), Value::Array(overlay), MergeStrategy::ConcatArrays) => {
+                base.append(overlay);
+            }
+            (Value::Array(base), Value::Array(overlay), MergeStrategy::MergeArrays) => {
+                let mut items = std::mem::take(overlay).into_iter();
+                for existing in base.iter_mut() {
+                    match items.next() {
+                        Some(value) => existing.deep_merge_with(value, strategy),
+                        None => break,
+                    }
+                }
+                base.extend(items);
+            }
+            (base, _, _) => *base = other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_deep_merge() {
+        let base = parse("{ a: { x: 1, y: [1, { k: 1 }] }, b: 'keep', c: 2 }").unwrap();
+        let overlay = parse("{ a: { y: [{ k: 2 }], z: true }, c: null, d: 1.5m }").unwrap();
+        let merged = |strategy| {
+            let mut value = base.clone();
+            value.deep_merge_with(overlay.clone(), strategy);
+            value
+        };
+
+        let expected = "{ a: { x: 1, y: [{ k: 2 }], z: true }, b: 'keep', c: null, d: 1.5m }";
+        assert_eq!(merged(MergeStrategy::ReplaceArrays), parse(expected).unwrap());
+        let mut value = base.clone();
+        value.deep_merge(overlay.clone());
+        assert_eq!(value, parse(expected).unwrap());
+
+        let concat = merged(MergeStrategy::ConcatArrays);
+        assert_eq!(concat.get_path("a.y"), Some(&parse("[1, { k: 1 }, { k: 2 }]").unwrap()));
+        assert_eq!(concat.get_path("c"), Some(&Value::Null));
+        let by_index = merged(MergeStrategy::MergeArrays);
+        assert_eq!(by_index.get_path("a.y"), Some(&parse("[{ k: 2 }, { k: 1 }]").unwrap()));
+
+        let mut value = parse("[{ a: 1 }]").unwrap();
+        value.deep_merge_with(parse("[{ b: 2 }, 3]").unwrap(), MergeStrategy::MergeArrays);
+        assert_eq!(value, parse("[{ a: 1, b: 2 }, 3]").unwrap());
+
+        let mut value = parse("{ a: 1 }").unwrap();
+        value.deep_merge(parse("[1]").unwrap());
+        assert_eq!(value, parse("[1]").unwrap());
+    }
+}
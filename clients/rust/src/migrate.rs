@@ -0,0 +1,242 @@
+//! Versioned document migrations
+//!
+//! Long-lived documents (save files, user settings) carry a version field.
+//! A [`Migrator`] holds one step per version and upgrades old documents to
+//! the current shape:
+//!
+//! ```
+//! use kjson::migrate::Migrator;
+//! use kjson::Value;
+//!
+//! let migrator = Migrator::new("version")
+//!     .step(1, "rename name to title", |doc| {
+//!         if let Value::Object(map) = doc {
+//!             if let Some(name) = map.remove("name") {
+//!                 map.insert("title", name);
+//!             }
+//!         }
+//!         Ok(())
+//!     })
+//!     .step(2, "add tags", |doc| {
+//!         if let Value::Object(map) = doc {
+//!             map.entry("tags").or_insert(Value::Array(vec![]));
+//!         }
+//!         Ok(())
+//!     });
+//!
+//! let mut doc = kjson::parse("{ version: 1, name: 'notes' }")?;
+//! let report = migrator.migrate(&mut doc)?;
+//! assert_eq!((report.from, report.to), (1, 3));
+//! assert_eq!(doc, kjson::parse("{ version: 3, title: 'notes', tags: [] }")?);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! The step registered for version `n` upgrades a document from `n` to
+//! `n + 1`, and the current version is one past the last step. A document
+//! without the version field is taken to be version 0. Steps run on a copy,
+//! so a failing step leaves the document untouched.
+
+use crate::error::{Error, Result};
+use crate::value::{Str, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+
+type StepFn = Box<dyn Fn(&mut Value) -> Result<()> + Send + Sync>;
+
+struct Step {
+    description: String,
+    apply: StepFn,
+}
+
+/// Ordered migration steps keyed by document version
+pub struct Migrator {
+    version_key: String,
+    steps: BTreeMap<u64, Step>,
+}
+
+/// What [`Migrator::migrate`] did to a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Version before migrating
+    pub from: u64,
+    /// Version after migrating
+    pub to: u64,
+    /// Descriptions of the steps applied, in order
+    pub applied: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Whether the document was already current
+    pub fn is_noop(&self) -> bool {
+        self.applied.is_empty()
+    }
+}
+
+impl Migrator {
+    /// Migrator reading the version from the top-level `version_key`
+    pub fn new(version_key: impl Into<String>) -> Self {
+        Migrator {
+            version_key: version_key.into(),
+            steps: BTreeMap::new(),
+        }
+    }
+
+    /// Register the step upgrading documents from `version` to `version + 1`
+    pub fn step<F>(mut self, version: u64, description: impl Into<String>, apply: F) -> Self
+    where
+        F: Fn(&mut Value) -> Result<()> + Send + Sync + 'static,
+    {
+        self.steps.insert(
+            version,
+            Step {
+                description: description.into(),
+                apply: Box::new(apply),
+            },
+        );
+        self
+    }
+
+    /// Version documents are migrated to
+    pub fn current_version(&self) -> u64 {
+        self.steps.keys().next_back().map_or(0, |v| v + 1)
+    }
+
+    /// Version of a document; 0 if it has no version field
+    pub fn version_of(&self, doc: &Value) -> Result<u64> {
+        let map = doc.as_object().ok_or_else(|| Error::TypeMismatch {
+            expected: "object".to_string(),
+            actual: doc.type_name().to_string(),
+        })?;
+        match map.get(self.version_key.as_str()) {
+            None => Ok(0),
+            Some(version) => version.as_u64().ok_or_else(|| {
+                Error::Migration(format!(
+                    "{} must be a non-negative integer, got {}",
+                    self.version_key,
+                    version.type_name()
+                ))
+            }),
+        }
+    }
+
+    /// Upgrade `doc` to the current version
+    pub fn migrate(&self, doc: &mut Value) -> Result<MigrationReport> {
+        let from = self.version_of(doc)?;
+        let to = self.current_version();
+        if from > to {
+            return Err(Error::Migration(format!(
+                "document version {} is newer than {}",
+                from, to
+            )));
+        }
+        let mut report = MigrationReport {
+            from,
+            to,
+            applied: Vec::new(),
+        };
+        if from == to {
+            return Ok(report);
+        }
+
+        let mut migrated = doc.clone();
+        for version in from..to {
+            let step = self
+                .steps
+                .get(&version)
+                .ok_or_else(|| Error::Migration(format!("no step from version {}", version)))?;
+            (step.apply)(&mut migrated).map_err(|e| {
+                Error::Migration(format!(
+                    "{} -> {} ({}): {}",
+                    version,
+                    version + 1,
+                    step.description,
+                    e
+                ))
+            })?;
+            report.applied.push(step.description.clone());
+        }
+        match &mut migrated {
+            Value::Object(map) => {
+                map.insert(Str::from(self.version_key.as_str()), Value::Integer(to as i64));
+            }
+            other => {
+                return Err(Error::Migration(format!(
+                    "a step replaced the document with {}",
+                    other.type_name()
+                )))
+            }
+        }
+        *doc = migrated;
+        Ok(report)
+    }
+}
+
+impl fmt::Debug for Migrator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: BTreeMap<_, _> = self
+            .steps
+            .iter()
+            .map(|(version, step)| (version, &step.description))
+            .collect();
+        f.debug_struct("Migrator")
+            .field("version_key", &self.version_key)
+            .field("steps", &steps)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn migrator() -> Migrator {
+        Migrator::new("v")
+            .step(0, "add v", |_| Ok(()))
+            .step(1, "double count", |doc| {
+                let Value::Object(map) = doc else {
+                    unreachable!()
+                };
+                match map.get_mut("count") {
+                    Some(Value::Integer(n)) => {
+                        *n *= 2;
+                        Ok(())
+                    }
+                    _ => Err(Error::Custom("count missing".to_string())),
+                }
+            })
+    }
+
+    #[test]
+    fn test_migrate() {
+        let migrator = migrator();
+        assert_eq!(migrator.current_version(), 2);
+
+        let mut doc = parse("{ count: 2 }").unwrap();
+        let report = migrator.migrate(&mut doc).unwrap();
+        assert_eq!(report.applied, ["add v", "double count"]);
+        assert_eq!(doc, parse("{ v: 2, count: 4 }").unwrap());
+
+        let report = migrator.migrate(&mut doc).unwrap();
+        assert!(report.is_noop());
+        assert_eq!(doc, parse("{ v: 2, count: 4 }").unwrap());
+    }
+
+    #[test]
+    fn test_failures_leave_document_unchanged() {
+        let migrator = migrator();
+        let mut doc = parse("{ v: 0, other: 1 }").unwrap();
+        let err = migrator.migrate(&mut doc).unwrap_err();
+        assert!(matches!(&err, Error::Migration(m) if m.starts_with("1 -> 2 (double count)")));
+        assert_eq!(doc, parse("{ v: 0, other: 1 }").unwrap());
+
+        let mut newer = parse("{ v: 5 }").unwrap();
+        assert!(migrator.migrate(&mut newer).is_err());
+        let mut gap = parse("{ v: 0, count: 1 }").unwrap();
+        assert!(Migrator::new("v")
+            .step(1, "only step", |_| Ok(()))
+            .migrate(&mut gap)
+            .is_err());
+        assert!(migrator.version_of(&parse("{ v: '1' }").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,328 @@
+//! MessagePack transcoding
+//!
+//! Extended types are carried as MessagePack ext values. Instants use the
+//! standard timestamp extension; the others use application type codes:
+//!
+//! | kJSON | Ext type | Payload |
+//! |-------|----------|---------|
+//! | Instant | -1 | standard timestamp 32/64/96 |
+//! | BigInt | 1 | sign byte (0 or 1), big-endian magnitude |
+//! | Decimal128 | 2 | exponent as big-endian i32, sign byte, big-endian coefficient magnitude |
+//! | UUID | 3 | 16 bytes |
+//! | Duration | 4 | nanoseconds as big-endian i64 |
+//! | Date | 5 | RFC 3339 string (keeps the timezone offset) |
+//!
+//! Binary data is written as MessagePack bin.
+//!
+//! Integers and integral numbers are written as MessagePack integers, which
+//! decode to Integer, or to UInteger above the i64 range.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{into_str, ordered_entries, Map, Value};
+use rmpv::Value as MsgPack;
+use uuid::Uuid;
+
+/// Standard timestamp extension
+pub const EXT_TIMESTAMP: i8 = -1;
+/// Arbitrary-precision integer
+pub const EXT_BIGINT: i8 = 1;
+/// Decimal with arbitrary-precision coefficient
+pub const EXT_DECIMAL: i8 = 2;
+/// UUID
+pub const EXT_UUID: i8 = 3;
+/// Signed duration in nanoseconds
+pub const EXT_DURATION: i8 = 4;
+/// Date with timezone offset
+pub const EXT_DATE: i8 = 5;
+
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: i128 = 1 << 53;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Encode a value as MessagePack bytes
+pub fn to_msgpack(value: &Value) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    rmpv::encode::write_value(&mut buf, &to_msgpack_value(value))
+        .map_err(|e| Error::SerializationError(e.to_string()))?;
+    Ok(buf)
+}
+
+/// Decode MessagePack bytes into a value
+pub fn from_msgpack(bytes: &[u8]) -> Result<Value> {
+    let mut reader = bytes;
+    let value =
+        rmpv::decode::read_value(&mut reader).map_err(|e| Error::Conversion(e.to_string()))?;
+    from_msgpack_value(value)
+}
+
+/// Convert a value to an `rmpv` MessagePack value
+pub fn to_msgpack_value(value: &Value) -> MsgPack {
+    match value {
+        Value::Null => MsgPack::Nil,
+        Value::Bool(b) => MsgPack::Boolean(*b),
+        Value::Number(n) => {
+            if n.fract() == 0.0 && (n.abs() as i128) < MAX_SAFE_INTEGER {
+                MsgPack::from(*n as i64)
+            } else {
+                MsgPack::F64(*n)
+            }
+        }
+        Value::Integer(n) => MsgPack::from(*n),
+        Value::UInteger(n) => MsgPack::from(*n),
+        Value::String(s) => MsgPack::from(s.as_str()),
+        Value::Array(arr) => MsgPack::Array(arr.iter().map(to_msgpack_value).collect()),
+        Value::Object(obj) => {
+            let entries = ordered_entries(obj);
+            MsgPack::Map(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (MsgPack::from(k.as_str()), to_msgpack_value(v)))
+                    .collect(),
+            )
+        }
+        Value::BigInt(b) => MsgPack::Ext(EXT_BIGINT, signed_magnitude(b)),
+        Value::Decimal128(d) => {
+            let mut payload = d.exponent().to_be_bytes().to_vec();
+            payload.extend(signed_magnitude(&d.coefficient()));
+            MsgPack::Ext(EXT_DECIMAL, payload)
+        }
+        Value::Uuid(u) => MsgPack::Ext(EXT_UUID, u.as_bytes().to_vec()),
+        Value::Date(d) => MsgPack::Ext(EXT_DATE, d.to_iso8601().into_bytes()),
+        Value::Instant(i) => MsgPack::Ext(EXT_TIMESTAMP, timestamp(i.epoch_nanos())),
+        Value::Duration(d) => MsgPack::Ext(EXT_DURATION, d.total_nanos().to_be_bytes().to_vec()),
+        Value::Binary(bytes) => MsgPack::Binary(bytes.clone()),
+    }
+}
+
+/// Convert an `rmpv` MessagePack value to a value
+pub fn from_msgpack_value(value: MsgPack) -> Result<Value> {
+    match value {
+        MsgPack::Nil => Ok(Value::Null),
+        MsgPack::Boolean(b) => Ok(Value::Bool(b)),
+        MsgPack::F32(f) => Ok(Value::Number(f as f64)),
+        MsgPack::F64(f) => Ok(Value::Number(f)),
+        MsgPack::Integer(i) => match (i.as_i64(), i.as_u64()) {
+            (Some(n), _) => Ok(Value::Integer(n)),
+            (None, Some(n)) => Ok(Value::UInteger(n)),
+            (None, None) => unreachable!("MessagePack integers fit i64 or u64"),
+        },
+        MsgPack::String(s) => s
+            .into_str()
+            .map(Value::string)
+            .ok_or_else(|| Error::Conversion("MessagePack string is not valid UTF-8".to_string())),
+        MsgPack::Binary(bytes) => Ok(Value::Binary(bytes)),
+        MsgPack::Array(items) => Ok(Value::Array(
+            items
+                .into_iter()
+                .map(from_msgpack_value)
+                .collect::<Result<_>>()?,
+        )),
+        MsgPack::Map(entries) => {
+            let mut map = Map::with_capacity(entries.len());
+            for (k, v) in entries {
+                let key = match k {
+                    MsgPack::String(key) => key.into_str().ok_or_else(|| {
+                        Error::Conversion("MessagePack map key is not valid UTF-8".to_string())
+                    })?,
+                    other => {
+                        return Err(Error::Conversion(format!(
+                            "MessagePack map key must be a string, got {}",
+                            other
+                        )))
+                    }
+                };
+                map.insert(into_str(key), from_msgpack_value(v)?);
+            }
+            Ok(Value::Object(map))
+        }
+        MsgPack::Ext(code, payload) => from_ext(code, &payload),
+    }
+}
+
+fn from_ext(code: i8, payload: &[u8]) -> Result<Value> {
+    match code {
+        EXT_TIMESTAMP => Ok(Value::Instant(Instant::from_nanos(read_timestamp(
+            payload,
+        )?))),
+        EXT_BIGINT => Ok(Value::BigInt(read_signed_magnitude(code, payload)?)),
+        EXT_DECIMAL => {
+            if payload.len() < 5 {
+                return Err(ext_error(code));
+            }
+            let exponent = i32::from_be_bytes(payload[..4].try_into().unwrap());
+            let coefficient = read_signed_magnitude(code, &payload[4..])?;
+            Ok(Value::Decimal128(Decimal128::from_parts(
+                &coefficient,
+                exponent,
+            )))
+        }
+        EXT_UUID => Uuid::from_slice(payload)
+            .map(Value::Uuid)
+            .map_err(|_| ext_error(code)),
+        EXT_DURATION => {
+            let bytes: [u8; 8] = payload.try_into().map_err(|_| ext_error(code))?;
+            Ok(Value::Duration(Duration::from_nanos(i64::from_be_bytes(
+                bytes,
+            ))))
+        }
+        EXT_DATE => {
+            let s = std::str::from_utf8(payload).map_err(|_| ext_error(code))?;
+            Ok(Value::Date(Date::from_iso8601(s)?))
+        }
+        _ => Err(Error::Conversion(format!(
+            "Unknown MessagePack ext type {}",
+            code
+        ))),
+    }
+}
+
+fn signed_magnitude(b: &BigInt) -> Vec<u8> {
+    let (negative, magnitude) = b.to_bytes_be();
+    let mut payload = Vec::with_capacity(magnitude.len() + 1);
+    payload.push(negative as u8);
+    payload.extend(magnitude);
+    payload
+}
+
+fn read_signed_magnitude(code: i8, payload: &[u8]) -> Result<BigInt> {
+    match payload.split_first() {
+        Some((0, magnitude)) => Ok(BigInt::from_bytes_be(false, magnitude)),
+        Some((1, magnitude)) => Ok(BigInt::from_bytes_be(true, magnitude)),
+        _ => Err(ext_error(code)),
+    }
+}
+
+/// Encode epoch nanoseconds in the smallest timestamp format that fits
+fn timestamp(epoch_nanos: i64) -> Vec<u8> {
+    let seconds = epoch_nanos.div_euclid(NANOS_PER_SECOND);
+    let nanos = epoch_nanos.rem_euclid(NANOS_PER_SECOND) as u32;
+    if seconds >> 34 == 0 {
+        if nanos == 0 && seconds <= u32::MAX as i64 {
+            // timestamp 32
+            (seconds as u32).to_be_bytes().to_vec()
+        } else {
+            // timestamp 64
+            (((nanos as u64) << 34) | seconds as u64)
+                .to_be_bytes()
+                .to_vec()
+        }
+    } else {
+        // timestamp 96
+        let mut payload = nanos.to_be_bytes().to_vec();
+        payload.extend(seconds.to_be_bytes());
+        payload
+    }
+}
+
+fn read_timestamp(payload: &[u8]) -> Result<i64> {
+    let (seconds, nanos) = match payload.len() {
+        4 => (u32::from_be_bytes(payload.try_into().unwrap()) as i64, 0u32),
+        8 => {
+            let n = u64::from_be_bytes(payload.try_into().unwrap());
+            ((n & 0x3_ffff_ffff) as i64, (n >> 34) as u32)
+        }
+        12 => (
+            i64::from_be_bytes(payload[4..].try_into().unwrap()),
+            u32::from_be_bytes(payload[..4].try_into().unwrap()),
+        ),
+        _ => return Err(ext_error(EXT_TIMESTAMP)),
+    };
+    if nanos as i64 >= NANOS_PER_SECOND {
+        return Err(ext_error(EXT_TIMESTAMP));
+    }
+    seconds
+        .checked_mul(NANOS_PER_SECOND)
+        .and_then(|n| n.checked_add(nanos as i64))
+        .ok_or_else(|| ext_error(EXT_TIMESTAMP))
+}
+
+fn ext_error(code: i8) -> Error {
+    Error::Conversion(format!("Malformed MessagePack ext type {}", code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_roundtrip_extended_types() {
+        let value = parse(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                big: 123456789012345678901234567890n,
+                negative: -123456789012345678901234567890n,
+                small: -1n,
+                price: 19.99m,
+                huge: -12345678901234567890.123456789m,
+                created: 2025-01-10T12:00:00+05:30,
+                ttl: -PT1H0.5S,
+                key: b64'AQIDBA==',
+                list: [1, -2.5, null, true, "text"],
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(from_msgpack(&to_msgpack(&value).unwrap()).unwrap(), value);
+
+        for nanos in [
+            0,
+            1_363_896_240_000_000_000,
+            1_736_510_400_123_456_789,
+            -86_400_123_000_000,
+            i64::MAX,
+        ] {
+            let value = Value::Instant(Instant::from_nanos(nanos));
+            assert_eq!(from_msgpack(&to_msgpack(&value).unwrap()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_ext_encoding() {
+        let uuid = parse("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        // fixext 16 (0xd8) with type 3
+        assert_eq!(&to_msgpack(&uuid).unwrap()[..2], &[0xd8, 0x03]);
+
+        // ext 8 (0xc7), length 3, type 1: positive sign, 0x01 0x00
+        let big = parse("256n").unwrap();
+        assert_eq!(
+            to_msgpack(&big).unwrap(),
+            vec![0xc7, 0x03, 0x01, 0x00, 0x01, 0x00]
+        );
+
+        // Whole seconds use timestamp 32: fixext 4 (0xd6) with type -1
+        let instant = Value::Instant(Instant::from_seconds(1_363_896_240));
+        assert_eq!(
+            to_msgpack(&instant).unwrap(),
+            vec![0xd6, 0xff, 0x51, 0x4b, 0x67, 0xb0]
+        );
+
+        // Sub-second instants use timestamp 64: fixext 8 (0xd7)
+        let instant = Value::Instant(Instant::from_millis(1500));
+        assert_eq!(&to_msgpack(&instant).unwrap()[..2], &[0xd7, 0xff]);
+
+        // Pre-epoch instants use timestamp 96: ext 8 (0xc7), length 12
+        let instant = Value::Instant(Instant::from_seconds(-1));
+        assert_eq!(&to_msgpack(&instant).unwrap()[..3], &[0xc7, 0x0c, 0xff]);
+    }
+
+    #[test]
+    fn test_decode_foreign_msgpack() {
+        // u64::MAX as a plain integer decodes to UInteger
+        let value = from_msgpack_value(MsgPack::from(u64::MAX)).unwrap();
+        assert!(matches!(value, Value::UInteger(u64::MAX)));
+
+        assert!(matches!(
+            from_msgpack_value(MsgPack::Ext(42, vec![])),
+            Err(Error::Conversion(_))
+        ));
+        assert!(matches!(
+            from_msgpack_value(MsgPack::Ext(EXT_UUID, vec![0; 3])),
+            Err(Error::Conversion(_))
+        ));
+
+        let map = MsgPack::Map(vec![(MsgPack::from(1), MsgPack::Nil)]);
+        assert!(matches!(from_msgpack_value(map), Err(Error::Conversion(_))));
+    }
+}
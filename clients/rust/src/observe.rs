@@ -0,0 +1,520 @@
+//! Change tracking for values
+//!
+//! An [`ObservedValue`] wraps a document and records every mutation made
+//! through it, so state-sync and collaborative systems can send deltas
+//! instead of diffing snapshots:
+//!
+//! ```
+//! use kjson::observe::ObservedValue;
+//! use kjson::Value;
+//!
+//! let mut doc = ObservedValue::new(kjson::parse("{ name: 'shop', tags: ['a'], port: 80 }")?);
+//! doc.set("/port", Value::Number(8080.0))?;
+//! doc.push("/tags", Value::string("b"))?;
+//! doc.remove("/name")?;
+//!
+//! assert_eq!(
+//!     doc.to_patch(),
+//!     kjson::parse(
+//!         "[
+//!             { op: 'replace', path: '/port', value: 8080 },
+//!             { op: 'add', path: '/tags/1', value: 'b' },
+//!             { op: 'remove', path: '/name' },
+//!         ]"
+//!     )?
+//! );
+//! assert_eq!(
+//!     doc.to_merge_patch(),
+//!     kjson::parse("{ name: null, tags: ['a', 'b'], port: 8080 }")?
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! [`ObservedValue::to_patch`] replays the mutations as a JSON Patch
+//! (RFC 6902). [`ObservedValue::to_merge_patch`] gives the net effect as a
+//! JSON Merge Patch (RFC 7396), which replaces arrays whole and cannot set
+//! a member to `null`. [`ObservedValue::take_changes`] starts a new delta.
+//!
+//! Reading goes through `Deref` to the wrapped [`Value`]; there is no
+//! untracked mutable access.
+
+use crate::diff::{diff, to_patch, Change};
+use crate::error::{Error, Result};
+use crate::pointer;
+use crate::value::{Map, Str, Value};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+/// A value that records the mutations made through it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObservedValue {
+    value: Value,
+    changes: Vec<Change>,
+    /// Values before the first change, as `None` where nothing was, by the
+    /// merge patch path they cover. No path is a prefix of another.
+    originals: BTreeMap<Vec<String>, Option<Value>>,
+}
+
+impl ObservedValue {
+    /// Start observing a value
+    pub fn new(value: Value) -> Self {
+        ObservedValue {
+            value,
+            changes: Vec::new(),
+            originals: BTreeMap::new(),
+        }
+    }
+
+    /// The current value
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Stop observing and return the current value
+    pub fn into_inner(self) -> Value {
+        self.value
+    }
+
+    /// Changes recorded since the value was wrapped or last taken
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Whether anything was changed since the value was wrapped or changes
+    /// were last taken
+    pub fn has_changes(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Return the recorded changes and start recording afresh
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.originals.clear();
+        std::mem::take(&mut self.changes)
+    }
+
+    /// JSON Patch (RFC 6902) replaying the recorded changes in order
+    pub fn to_patch(&self) -> Value {
+        to_patch(&self.changes)
+    }
+
+    /// JSON Merge Patch (RFC 7396) with the net effect of the recorded
+    /// changes
+    pub fn to_merge_patch(&self) -> Value {
+        let mut patch = Value::Object(Map::new());
+        for (path, original) in &self.originals {
+            let Some(entry) = merge_diff(original.as_ref(), lookup(&self.value, path)) else {
+                continue;
+            };
+            let Some((last, parents)) = path.split_last() else {
+                // A change to the root is the only recorded path
+                return entry;
+            };
+            let mut node = &mut patch;
+            for token in parents {
+                node = match node {
+                    Value::Object(map) => map
+                        .entry(Str::from(token.as_str()))
+                        .or_insert_with(|| Value::Object(Map::new())),
+                    _ => unreachable!("merge patch paths are prefix-free"),
+                };
+            }
+            if let Value::Object(map) = node {
+                map.insert(Str::from(last.as_str()), entry);
+            }
+        }
+        patch
+    }
+
+    /// Set the value at `pointer`, replacing what is there or adding an
+    /// object member. `-` or the array length as the last token appends
+    /// to an array.
+    pub fn set(&mut self, pointer: &str, value: Value) -> Result<()> {
+        let tokens = split(pointer)?;
+        self.record_original(&tokens);
+        let Some((last, parents)) = tokens.split_last() else {
+            let from = std::mem::replace(&mut self.value, value.clone());
+            self.changes.push(Change::Changed {
+                path: String::new(),
+                from,
+                to: value,
+            });
+            return Ok(());
+        };
+        let change = match container(&mut self.value, parents, pointer)? {
+            Value::Object(map) => match map.insert(Str::from(last.as_str()), value.clone()) {
+                Some(from) => Change::Changed {
+                    path: pointer.to_string(),
+                    from,
+                    to: value,
+                },
+                None => Change::Added {
+                    path: pointer.to_string(),
+                    value,
+                },
+            },
+            Value::Array(items) => match index(last, items.len(), pointer)? {
+                i if i == items.len() => {
+                    items.push(value.clone());
+                    Change::Added {
+                        path: pointer::push_index(&parent_pointer(pointer), i),
+                        value,
+                    }
+                }
+                i => Change::Changed {
+                    path: pointer.to_string(),
+                    from: std::mem::replace(&mut items[i], value.clone()),
+                    to: value,
+                },
+            },
+            _ => unreachable!(),
+        };
+        self.changes.push(change);
+        Ok(())
+    }
+
+    /// Add a value as JSON Patch `add` does: object members are set, and
+    /// array elements are inserted before the element at the index
+    pub fn insert(&mut self, pointer: &str, value: Value) -> Result<()> {
+        let tokens = split(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return self.set(pointer, value);
+        };
+        match container(&mut self.value, parents, pointer)? {
+            Value::Object(_) => self.set(pointer, value),
+            Value::Array(items) => {
+                let i = index(last, items.len(), pointer)?;
+                self.record_original(&tokens);
+                if let Value::Array(items) = container(&mut self.value, parents, pointer)? {
+                    items.insert(i, value.clone());
+                }
+                self.changes.push(Change::Added {
+                    path: pointer::push_index(&parent_pointer(pointer), i),
+                    value,
+                });
+                Ok(())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Append a value to the array at `pointer`
+    pub fn push(&mut self, pointer: &str, value: Value) -> Result<()> {
+        match self.value.pointer(pointer) {
+            Some(Value::Array(_)) => self.set(&format!("{}/-", pointer), value),
+            Some(other) => Err(Error::InvalidPointer(format!(
+                "{}: expected array, got {}",
+                pointer,
+                other.type_name()
+            ))),
+            None => Err(not_found(pointer)),
+        }
+    }
+
+    /// Remove and return the value at `pointer`; later array elements
+    /// shift down
+    pub fn remove(&mut self, pointer: &str) -> Result<Value> {
+        let tokens = split(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Err(Error::InvalidPointer("cannot remove the root".to_string()));
+        };
+        if self.value.pointer(pointer).is_none() {
+            return Err(not_found(pointer));
+        }
+        self.record_original(&tokens);
+        let removed = match container(&mut self.value, parents, pointer)? {
+            Value::Object(map) => map.remove(last.as_str()),
+            Value::Array(items) => Some(items.remove(index(last, items.len(), pointer)?)),
+            _ => None,
+        }
+        .ok_or_else(|| not_found(pointer))?;
+        self.changes.push(Change::Removed {
+            path: pointer.to_string(),
+            value: removed.clone(),
+        });
+        Ok(removed)
+    }
+
+    /// Change the value at `pointer` in place, recording the difference
+    /// made as one change per modified member
+    pub fn update<F: FnOnce(&mut Value)>(&mut self, pointer: &str, f: F) -> Result<()> {
+        let tokens = split(pointer)?;
+        let before = self
+            .value
+            .pointer(pointer)
+            .ok_or_else(|| not_found(pointer))?
+            .clone();
+        self.record_original(&tokens);
+        let target = lookup_mut(&mut self.value, &tokens).ok_or_else(|| not_found(pointer))?;
+        f(target);
+        let prefix = |path: String| format!("{}{}", pointer, path);
+        for change in diff(&before, target) {
+            self.changes.push(match change {
+                Change::Added { path, value } => Change::Added {
+                    path: prefix(path),
+                    value,
+                },
+                Change::Removed { path, value } => Change::Removed {
+                    path: prefix(path),
+                    value,
+                },
+                Change::Changed { path, from, to } => Change::Changed {
+                    path: prefix(path),
+                    from,
+                    to,
+                },
+            });
+        }
+        Ok(())
+    }
+
+    /// Remember the current value at the merge patch path covering
+    /// `tokens`, unless a change there or above already did
+    fn record_original(&mut self, tokens: &[String]) {
+        // Merge patches replace arrays whole, so a change inside one is
+        // recorded at the outermost array containing it
+        let mut len = tokens.len();
+        let mut node = Some(&self.value);
+        for (i, token) in tokens.iter().enumerate() {
+            match node {
+                Some(Value::Object(map)) => node = map.get(token.as_str()),
+                Some(Value::Array(_)) => {
+                    len = i;
+                    break;
+                }
+                _ => {
+                    len = i;
+                    break;
+                }
+            }
+        }
+        let path = &tokens[..len];
+        if (0..=path.len()).any(|n| self.originals.contains_key(&path[..n])) {
+            return;
+        }
+
+        let mut original = lookup(&self.value, path).cloned();
+        let descendants: Vec<Vec<String>> = self
+            .originals
+            .range(path.to_vec()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(path))
+            .cloned()
+            .collect();
+        for key in descendants {
+            let value = self.originals.remove(&key).flatten();
+            let (last, parents) = key[path.len()..].split_last().expect("descendants are longer");
+            let parent = original.as_mut().and_then(|o| lookup_mut(o, parents));
+            if let Some(Value::Object(map)) = parent {
+                match value {
+                    Some(value) => map.insert(Str::from(last.as_str()), value),
+                    None => map.remove(last.as_str()),
+                };
+            }
+        }
+        self.originals.insert(path.to_vec(), original);
+    }
+}
+
+impl Deref for ObservedValue {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl From<Value> for ObservedValue {
+    fn from(value: Value) -> Self {
+        ObservedValue::new(value)
+    }
+}
+
+/// Merge patch turning `original` into `current`; `None` if they are equal
+fn merge_diff(original: Option<&Value>, current: Option<&Value>) -> Option<Value> {
+    match (original, current) {
+        (None, None) => None,
+        (Some(_), None) => Some(Value::Null),
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let mut out = Map::new();
+            for key in a.keys().filter(|key| !b.contains_key(*key)) {
+                out.insert(key.clone(), Value::Null);
+            }
+            for (key, value) in b {
+                if let Some(entry) = merge_diff(a.get(key), Some(value)) {
+                    out.insert(key.clone(), entry);
+                }
+            }
+            (!out.is_empty()).then_some(Value::Object(out))
+        }
+        (Some(a), Some(b)) if a == b => None,
+        (_, Some(b)) => Some(b.clone()),
+    }
+}
+
+fn split(pointer: &str) -> Result<Vec<String>> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = pointer.strip_prefix('/').ok_or_else(|| {
+        Error::InvalidPointer(format!("{}: pointers start with '/'", pointer))
+    })?;
+    Ok(rest
+        .split('/')
+        .map(|token| pointer::unescape_token(token).into_owned())
+        .collect())
+}
+
+fn parent_pointer(pointer: &str) -> String {
+    pointer[..pointer.rfind('/').unwrap_or(0)].to_string()
+}
+
+fn not_found(pointer: &str) -> Error {
+    Error::InvalidPointer(format!("{}: no such value", pointer))
+}
+
+/// Index for `token` in an array of `len` elements, where `-` and `len`
+/// mean the end
+fn index(token: &str, len: usize, pointer: &str) -> Result<usize> {
+    match token {
+        "-" => Ok(len),
+        token => token
+            .parse::<usize>()
+            .ok()
+            .filter(|i| *i <= len)
+            .ok_or_else(|| not_found(pointer)),
+    }
+}
+
+/// The array or object holding the value at `pointer`
+fn container<'a>(value: &'a mut Value, parents: &[String], pointer: &str) -> Result<&'a mut Value> {
+    match lookup_mut(value, parents) {
+        Some(parent @ (Value::Object(_) | Value::Array(_))) => Ok(parent),
+        Some(other) => Err(Error::InvalidPointer(format!(
+            "{}: cannot index into {}",
+            pointer,
+            other.type_name()
+        ))),
+        None => Err(not_found(pointer)),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(value, |node, token| match node {
+        Value::Object(map) => map.get(token.as_str()),
+        Value::Array(items) => items.get(token.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn lookup_mut<'a>(value: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(value, |node, token| match node {
+        Value::Object(map) => map.get_mut(token.as_str()),
+        Value::Array(items) => items.get_mut(token.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    /// Apply a JSON Merge Patch (RFC 7396)
+    fn merge(target: &mut Value, patch: &Value) {
+        let Value::Object(patch) = patch else {
+            *target = patch.clone();
+            return;
+        };
+        if !matches!(target, Value::Object(_)) {
+            *target = Value::Object(Map::new());
+        }
+        if let Value::Object(map) = target {
+            for (key, value) in patch {
+                if *value == Value::Null {
+                    map.remove(key);
+                } else {
+                    merge(map.entry(key.clone()).or_insert(Value::Null), value);
+                }
+            }
+        }
+    }
+
+    fn assert_merge_patch_applies(original: &Value, doc: &ObservedValue) {
+        let mut patched = original.clone();
+        merge(&mut patched, &doc.to_merge_patch());
+        assert_eq!(&patched, doc.value());
+    }
+
+    #[test]
+    fn test_patch_operations() {
+        let original = parse("{ a: { b: 1, c: [1, 2, 3] }, d: 'x' }").unwrap();
+        let mut doc = ObservedValue::new(original.clone());
+        doc.set("/a/b", Value::Number(2.0)).unwrap();
+        doc.insert("/a/c/0", Value::Number(0.0)).unwrap();
+        doc.remove("/a/c/3").unwrap();
+        doc.set("/e", parse("{ f: 1n }").unwrap()).unwrap();
+        doc.update("/a", |a| {
+            if let Value::Object(map) = a {
+                map.remove("b");
+            }
+        })
+        .unwrap();
+
+        assert_eq!(*doc, parse("{ a: { c: [0, 1, 2] }, d: 'x', e: { f: 1n } }").unwrap());
+        assert_eq!(
+            doc.to_patch(),
+            parse(
+                "[
+                    { op: 'replace', path: '/a/b', value: 2 },
+                    { op: 'add', path: '/a/c/0', value: 0 },
+                    { op: 'remove', path: '/a/c/3' },
+                    { op: 'add', path: '/e', value: { f: 1n } },
+                    { op: 'remove', path: '/a/b' },
+                ]"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            doc.to_merge_patch(),
+            parse("{ a: { b: null, c: [0, 1, 2] }, e: { f: 1n } }").unwrap()
+        );
+        assert_merge_patch_applies(&original, &doc);
+    }
+
+    #[test]
+    fn test_merge_patch_after_overlapping_changes() {
+        let original = parse("{ a: { x: 1, y: 2 }, list: [{ k: 1 }] }").unwrap();
+        let mut doc = ObservedValue::new(original.clone());
+        doc.remove("/a/y").unwrap();
+        doc.set("/list/0/k", Value::Number(2.0)).unwrap();
+        doc.set("/a/z", Value::Bool(true)).unwrap();
+        // Replacing the parent keeps the earlier removal in the net effect
+        doc.set("/a", parse("{ x: 1, w: 3 }").unwrap()).unwrap();
+        assert_eq!(
+            doc.to_merge_patch(),
+            parse("{ a: { y: null, w: 3 }, list: [{ k: 2 }] }").unwrap()
+        );
+        assert_merge_patch_applies(&original, &doc);
+
+        // Undone changes leave nothing to merge
+        doc.take_changes();
+        doc.set("/a/x", Value::Number(5.0)).unwrap();
+        doc.set("/a/x", Value::Number(1.0)).unwrap();
+        assert_eq!(doc.to_merge_patch(), parse("{}").unwrap());
+        assert_eq!(doc.changes().len(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut doc = ObservedValue::new(parse("{ a: [1], s: 'x' }").unwrap());
+        assert!(matches!(doc.set("a", Value::Null), Err(Error::InvalidPointer(_))));
+        assert!(doc.set("/missing/b", Value::Null).is_err());
+        assert!(doc.set("/a/5", Value::Null).is_err());
+        assert!(doc.set("/s/0", Value::Null).is_err());
+        assert!(doc.push("/s", Value::Null).is_err());
+        assert!(doc.remove("/a/1").is_err());
+        assert!(doc.remove("").is_err());
+        assert!(!doc.has_changes());
+        assert_eq!(doc.to_merge_patch(), parse("{}").unwrap());
+    }
+}
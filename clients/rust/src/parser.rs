@@ -1,101 +1,202 @@
+use crate::borrowed::BorrowedValue;
 use crate::error::{Error, Result};
-use crate::types::{BigInt, Date, Decimal128};
-use crate::value::Value;
-use std::collections::HashMap;
+use crate::map::Entry;
+use crate::search;
+use crate::types::{BigInt, Date, Decimal128, Duration};
+use crate::value::{Map, Str, Value};
+use crate::warnings::{self, Warning};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{alphabet, DecodeError, Engine};
+use std::borrow::Cow;
 use uuid::Uuid;
 
-/// Parser state
+/// A parser that keeps its scratch buffers between documents.
+///
+/// Strings with escapes, array elements and object members are collected in
+/// buffers owned by the parser and copied out at their final size, so after
+/// the first few documents parsing allocates only the returned [`Value`].
+/// [`reset`](Parser::reset) moves it on to the next input:
+///
+/// ```
+/// use kjson::Parser;
+///
+/// let log = "{ level: 'info', n: 1n }\n{ level: 'warn', n: 2n }\n";
+/// let mut parser = Parser::new("");
+/// for line in log.lines() {
+///     parser.reset(line);
+///     let entry = parser.parse()?;
+///     assert_eq!(entry, kjson::parse(line)?);
+/// }
+/// # Ok::<(), kjson::Error>(())
+/// ```
+///
+/// Every input passed to one parser must outlive it, as the lines of one
+/// buffer do.
+///
+/// Nesting is unlimited by default. For untrusted input,
+/// [`max_depth`](Parser::max_depth) fails with [`Error::DepthLimit`] before
+/// a deeply nested document can exhaust the stack.
+#[derive(Debug, Default)]
 pub struct Parser<'a> {
     input: &'a str,
     position: usize,
+    /// Contents of the string being parsed
+    string: String,
+    /// Elements of the open arrays, innermost last
+    items: Vec<Value>,
+    /// Members of the open objects and where their keys start, innermost
+    /// last
+    members: Vec<(Str, Value, usize)>,
+    /// Number of open arrays and objects
+    depth: usize,
+    max_depth: Option<usize>,
 }
 
 /// Parse a kJSON string into a Value
 pub fn parse(input: &str) -> Result<Value> {
-    let mut parser = Parser { input, position: 0 };
-    parser.skip_whitespace();
+    Parser::new(input).parse()
+}
+
+/// Parse a kJSON string into a [`BorrowedValue`], whose strings and keys
+/// borrow from `input` unless they contain escapes
+pub fn parse_borrowed(input: &str) -> Result<BorrowedValue<'_>> {
+    Parser::new(input).parse_borrowed()
+}
+
+/// Parse one value at the start of `input`, returning it and the number of
+/// bytes it took. Anything after the value is left alone.
+pub(crate) fn parse_partial(input: &str) -> Result<(Value, usize)> {
+    let mut parser = Parser::new(input);
     let value = parser.parse_value()?;
-    parser.skip_whitespace();
-    if parser.position < parser.input.len() {
-        return Err(Error::ParseError {
-            position: parser.position,
-            message: "Unexpected characters after value".to_string(),
-        });
-    }
-    Ok(value)
+    Ok((value, parser.position))
 }
 
 impl<'a> Parser<'a> {
-    /// Current character
-    fn current(&self) -> Option<char> {
-        self.input.chars().nth(self.position)
+    /// A parser for `input`
+    pub fn new(input: &'a str) -> Self {
+        Parser {
+            input,
+            ..Parser::default()
+        }
+    }
+
+    /// Fail with [`Error::DepthLimit`] on arrays and objects nested more
+    /// than `depth` deep
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Start over on `input`, keeping the scratch buffers
+    pub fn reset(&mut self, input: &'a str) {
+        self.input = input;
+        self.position = 0;
+        self.depth = 0;
+        // Left over if the last document failed part way
+        self.items.clear();
+        self.members.clear();
+    }
+
+    /// Parse the input as one document, as [`parse`] does
+    pub fn parse(&mut self) -> Result<Value> {
+        self.document(Self::parse_value)
     }
 
-    /// Peek at character without advancing
-    fn peek(&self) -> Option<char> {
-        self.current()
+    /// Parse the input as one document borrowing from it, as
+    /// [`parse_borrowed`] does
+    pub fn parse_borrowed(&mut self) -> Result<BorrowedValue<'a>> {
+        self.document(Self::parse_borrowed_value)
     }
 
-    /// Advance position by one character
-    fn advance(&mut self) {
+    /// Parse the whole input as one value
+    fn document<T>(&mut self, parse: fn(&mut Self) -> Result<T>) -> Result<T> {
+        self.skip_whitespace();
+        let value = parse(self).map_err(|e| match e {
+            // Truncated input, such as an unclosed array or string
+            Error::ParseError { position, .. } if position >= self.input.len() => {
+                Error::UnexpectedEof
+            }
+            other => other,
+        })?;
+        self.skip_whitespace();
         if self.position < self.input.len() {
-            self.position += self.current().unwrap().len_utf8();
+            return Err(Error::ParseError {
+                position: self.position,
+                message: "Unexpected characters after value".to_string(),
+            });
         }
+        Ok(value)
+    }
+
+    /// Byte offset of the cursor
+    pub(crate) fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Whether the cursor is past the last byte
+    pub(crate) fn at_end(&self) -> bool {
+        self.position >= self.input.len()
+    }
+
+    /// Byte at the cursor
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    /// Character at the cursor, for the few places that look past ASCII
+    fn peek_char(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    /// Move past the byte at the cursor, which the caller has matched as
+    /// ASCII
+    pub(crate) fn advance(&mut self) {
+        self.position += 1;
     }
 
     /// Skip whitespace and comments
-    fn skip_whitespace(&mut self) {
-        while let Some(ch) = self.current() {
-            match ch {
-                ' ' | '\t' | '\n' | '\r' => self.advance(),
-                '/' => {
-                    let next_pos = self.position + 1;
-                    if next_pos < self.input.len() {
-                        let next_ch = self.input.chars().nth(next_pos);
-                        match next_ch {
-                            Some('/') => {
-                                // Line comment
-                                self.advance(); // Skip first /
-                                self.advance(); // Skip second /
-                                while let Some(c) = self.current() {
-                                    self.advance();
-                                    if c == '\n' {
-                                        break;
-                                    }
-                                }
-                            }
-                            Some('*') => {
-                                // Block comment
-                                self.advance(); // Skip /
-                                self.advance(); // Skip *
-                                let mut star_seen = false;
-                                while let Some(c) = self.current() {
-                                    self.advance();
-                                    if star_seen && c == '/' {
-                                        break;
-                                    }
-                                    star_seen = c == '*';
-                                }
-                            }
-                            _ => return,
-                        }
-                    } else {
-                        return;
-                    }
+    pub(crate) fn skip_whitespace(&mut self) {
+        let bytes = self.input.as_bytes();
+        loop {
+            self.position += search::whitespace_len(&bytes[self.position..]);
+            if bytes.get(self.position) != Some(&b'/') {
+                return;
+            }
+            let body = self.position + 2;
+            let rest = bytes.get(body..).unwrap_or_default();
+            match bytes.get(self.position + 1) {
+                Some(b'/') => {
+                    // Line comment, up to and including the line break
+                    self.position = match search::find(b'\n', rest) {
+                        Some(i) => body + i + 1,
+                        None => self.input.len(),
+                    };
+                }
+                Some(b'*') => {
+                    // Block comment
+                    self.position = match search::find_comment_end(rest) {
+                        Some(i) => body + i + 2,
+                        None => self.input.len(),
+                    };
                 }
                 _ => return,
             }
         }
     }
 
+    /// Skip a run of ASCII digits
+    fn skip_digits(&mut self) {
+        self.position += search::digits_len(&self.input.as_bytes()[self.position..]);
+    }
+
     /// Parse any value
-    fn parse_value(&mut self) -> Result<Value> {
+    pub(crate) fn parse_value(&mut self) -> Result<Value> {
         self.skip_whitespace();
 
         match self.peek() {
             None => Err(Error::UnexpectedEof),
-            Some('n') => self.parse_null(),
-            Some('t') | Some('f') => {
+            Some(b'n') => self.parse_null(),
+            Some(b't' | b'f') => {
                 // Could be boolean or UUID starting with 't' or 'f'
                 if let Ok(literal) = self.try_parse_unquoted_literal() {
                     Ok(literal)
@@ -103,10 +204,10 @@ impl<'a> Parser<'a> {
                     self.parse_bool()
                 }
             }
-            Some('"') | Some('\'') | Some('`') => self.parse_string(),
-            Some('[') => self.parse_array(),
-            Some('{') => self.parse_object(),
-            Some('-') | Some('0'..='9') => {
+            Some(b'"' | b'\'' | b'`') => self.parse_string(),
+            Some(b'[') => self.nested(Self::parse_array),
+            Some(b'{') => self.nested(Self::parse_object),
+            Some(b'-' | b'0'..=b'9') => {
                 // Could be number or date/UUID
                 if let Ok(literal) = self.try_parse_unquoted_literal() {
                     Ok(literal)
@@ -149,8 +250,14 @@ impl<'a> Parser<'a> {
 
     /// Parse string
     fn parse_string(&mut self) -> Result<Value> {
-        let quote_char = match self.current() {
-            Some('"') | Some('\'') | Some('`') => self.current().unwrap(),
+        Ok(Value::string(self.parse_str()?))
+    }
+
+    /// Parse a quoted string, borrowing it from the input if it has no
+    /// escapes
+    pub(crate) fn parse_str(&mut self) -> Result<Cow<'a, str>> {
+        let quote = match self.peek() {
+            Some(quote @ (b'"' | b'\'' | b'`')) => quote,
             _ => {
                 return Err(Error::ParseError {
                     position: self.position,
@@ -160,83 +267,108 @@ impl<'a> Parser<'a> {
         };
         self.advance(); // Skip opening quote
 
-        let mut result = String::new();
-        let mut escape = false;
-
-        while let Some(ch) = self.current() {
-            if escape {
-                match ch {
-                    '"' => result.push('"'),
-                    '\'' => result.push('\''),
-                    '`' => result.push('`'),
-                    '\\' => result.push('\\'),
-                    '/' => result.push('/'),
-                    'b' => result.push('\u{0008}'),
-                    'f' => result.push('\u{000C}'),
-                    'n' => result.push('\n'),
-                    'r' => result.push('\r'),
-                    't' => result.push('\t'),
-                    'u' => {
-                        // Unicode escape
-                        self.advance();
-                        let mut hex = String::new();
-                        for _ in 0..4 {
-                            if let Some(ch) = self.current() {
-                                hex.push(ch);
-                                self.advance();
-                            } else {
-                                return Err(Error::ParseError {
-                                    position: self.position,
-                                    message: "Invalid unicode escape".to_string(),
-                                });
-                            }
-                        }
-                        let code_point = u32::from_str_radix(&hex, 16)
-                            .map_err(|_| Error::ParseError {
-                                position: self.position,
-                                message: "Invalid unicode escape".to_string(),
-                            })?;
-                        if let Some(ch) = char::from_u32(code_point) {
-                            result.push(ch);
-                        } else {
-                            return Err(Error::ParseError {
-                                position: self.position,
-                                message: "Invalid unicode code point".to_string(),
-                            });
-                        }
-                        escape = false;
-                        continue;
-                    }
-                    _ => {
-                        return Err(Error::ParseError {
-                            position: self.position,
-                            message: format!("Invalid escape sequence: \\{}", ch),
-                        })
-                    }
-                }
-                escape = false;
-                self.advance();
-            } else if ch == '\\' {
-                escape = true;
-                self.advance();
-            } else if ch == quote_char {
-                self.advance();
-                return Ok(Value::String(result));
-            } else {
-                result.push(ch);
+        let mut result = std::mem::take(&mut self.string);
+        result.clear();
+
+        // Copy the runs between escapes in one go; quotes and backslashes
+        // are ASCII, so every run ends on a character boundary
+        let input = self.input;
+        let start = self.position;
+        let mut run = start;
+        while let Some(len) = search::find2(quote, b'\\', &input.as_bytes()[self.position..]) {
+            self.position += len;
+            if self.peek() == Some(quote) {
+                let text = &input[run..self.position];
                 self.advance();
+                let value = if run == start {
+                    Cow::Borrowed(text)
+                } else {
+                    result.push_str(text);
+                    Cow::Owned(String::from(result.as_str()))
+                };
+                self.string = result;
+                return Ok(value);
             }
+            result.push_str(&input[run..self.position]);
+            self.advance(); // Skip backslash
+            let ch = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\'') => '\'',
+                Some(b'`') => '`',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{0008}',
+                Some(b'f') => '\u{000C}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    self.advance();
+                    result.push(self.parse_unicode_escape()?);
+                    run = self.position;
+                    continue;
+                }
+                Some(_) => {
+                    let ch = self.peek_char().unwrap_or_default();
+                    return Err(Error::ParseError {
+                        position: self.position,
+                        message: format!("Invalid escape sequence: \\{}", ch),
+                    });
+                }
+                None => break,
+            };
+            result.push(ch);
+            self.advance();
+            run = self.position;
         }
 
+        self.string = result;
+        self.position = self.input.len();
         Err(Error::ParseError {
             position: self.position,
             message: "Unterminated string".to_string(),
         })
     }
 
+    /// Parse the four hex digits of a `\u` escape
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let end = self.position + 4;
+        let Some(hex) = self.input.get(self.position..end) else {
+            return Err(Error::ParseError {
+                position: end.min(self.input.len()),
+                message: "Invalid unicode escape".to_string(),
+            });
+        };
+        self.position = end;
+        let code_point = u32::from_str_radix(hex, 16).map_err(|_| Error::ParseError {
+            position: self.position,
+            message: "Invalid unicode escape".to_string(),
+        })?;
+        char::from_u32(code_point).ok_or_else(|| Error::ParseError {
+            position: self.position,
+            message: "Invalid unicode code point".to_string(),
+        })
+    }
+
+    /// Parse an array or object one level deeper
+    fn nested<T>(&mut self, parse: fn(&mut Self) -> Result<T>) -> Result<T> {
+        if let Some(limit) = self.max_depth {
+            if self.depth >= limit {
+                return Err(Error::DepthLimit {
+                    position: self.position,
+                    limit,
+                });
+            }
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
     /// Parse array
     fn parse_array(&mut self) -> Result<Value> {
-        if self.current() != Some('[') {
+        if self.peek() != Some(b'[') {
             return Err(Error::ParseError {
                 position: self.position,
                 message: "Expected '['".to_string(),
@@ -244,29 +376,30 @@ impl<'a> Parser<'a> {
         }
         self.advance();
 
-        let mut items = Vec::new();
         self.skip_whitespace();
 
-        if self.current() == Some(']') {
+        if self.peek() == Some(b']') {
             self.advance();
-            return Ok(Value::Array(items));
+            return Ok(Value::Array(Vec::new()));
         }
 
+        let base = self.items.len();
         loop {
-            items.push(self.parse_value()?);
+            let item = self.parse_value()?;
+            self.items.push(item);
             self.skip_whitespace();
 
-            match self.current() {
-                Some(',') => {
+            match self.peek() {
+                Some(b',') => {
                     self.advance();
                     self.skip_whitespace();
                     // Allow trailing comma
-                    if self.current() == Some(']') {
+                    if self.peek() == Some(b']') {
                         self.advance();
                         break;
                     }
                 }
-                Some(']') => {
+                Some(b']') => {
                     self.advance();
                     break;
                 }
@@ -279,12 +412,12 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok(Value::Array(items))
+        Ok(Value::Array(self.items.drain(base..).collect()))
     }
 
     /// Parse object
     fn parse_object(&mut self) -> Result<Value> {
-        if self.current() != Some('{') {
+        if self.peek() != Some(b'{') {
             return Err(Error::ParseError {
                 position: self.position,
                 message: "Expected '{'".to_string(),
@@ -292,33 +425,34 @@ impl<'a> Parser<'a> {
         }
         self.advance();
 
-        let mut map = HashMap::new();
         self.skip_whitespace();
 
-        if self.current() == Some('}') {
+        if self.peek() == Some(b'}') {
             self.advance();
-            return Ok(Value::Object(map));
+            return Ok(Value::Object(Map::new()));
         }
 
+        let base = self.members.len();
         loop {
             // Parse key
             self.skip_whitespace();
-            let key = match self.current() {
-                Some('"') | Some('\'') | Some('`') => {
+            let key_position = self.position;
+            let key = match self.peek() {
+                Some(b'"' | b'\'' | b'`') => {
                     // Quoted key
-                    match self.parse_string()? {
-                        Value::String(s) => s,
+                    match &mut self.parse_string()? {
+                        Value::String(s) => std::mem::take(s),
                         _ => unreachable!(),
                     }
                 }
                 _ => {
                     // Unquoted key (JSON5 style)
-                    self.parse_unquoted_key()?
+                    Str::from(self.parse_unquoted_key()?)
                 }
             };
 
             self.skip_whitespace();
-            if self.current() != Some(':') {
+            if self.peek() != Some(b':') {
                 return Err(Error::ParseError {
                     position: self.position,
                     message: "Expected ':' after key".to_string(),
@@ -328,20 +462,20 @@ impl<'a> Parser<'a> {
 
             // Parse value
             let value = self.parse_value()?;
-            map.insert(key, value);
+            self.members.push((key, value, key_position));
 
             self.skip_whitespace();
-            match self.current() {
-                Some(',') => {
+            match self.peek() {
+                Some(b',') => {
                     self.advance();
                     self.skip_whitespace();
                     // Allow trailing comma
-                    if self.current() == Some('}') {
+                    if self.peek() == Some(b'}') {
                         self.advance();
                         break;
                     }
                 }
-                Some('}') => {
+                Some(b'}') => {
                     self.advance();
                     break;
                 }
@@ -354,18 +488,119 @@ impl<'a> Parser<'a> {
             }
         }
 
+        // Later duplicates win
+        let mut map = Map::with_capacity(self.members.len() - base);
+        for (key, value, position) in self.members.drain(base..) {
+            match map.entry(key) {
+                Entry::Occupied(mut entry) => {
+                    warnings::warn(|| Warning::DuplicateKey {
+                        key: entry.key().to_string(),
+                        position,
+                    });
+                    entry.insert(value);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
         Ok(Value::Object(map))
     }
 
-    /// Parse unquoted key (JSON5 style)
-    fn parse_unquoted_key(&mut self) -> Result<String> {
-        let start = self.position;
+    /// Parse any value, borrowing strings and keys from the input
+    fn parse_borrowed_value(&mut self) -> Result<BorrowedValue<'a>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'"' | b'\'' | b'`') => Ok(BorrowedValue::String(self.parse_str()?)),
+            Some(b'[') => self.nested(Self::parse_borrowed_array),
+            Some(b'{') => self.nested(Self::parse_borrowed_object),
+            _ => Ok(BorrowedValue::from_scalar(self.parse_value()?)),
+        }
+    }
 
-        // First character must be letter, underscore, or dollar sign
-        match self.current() {
-            Some(ch) if ch.is_alphabetic() || ch == '_' || ch == '$' => {
+    /// Parse array of borrowed values
+    fn parse_borrowed_array(&mut self) -> Result<BorrowedValue<'a>> {
+        self.advance(); // Skip '['
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            // Empty array or trailing comma
+            if self.peek() == Some(b']') {
+                self.advance();
+                break;
+            }
+            items.push(self.parse_borrowed_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b']') => {
+                    self.advance();
+                    break;
+                }
+                _ => {
+                    return Err(Error::ParseError {
+                        position: self.position,
+                        message: "Expected ',' or ']'".to_string(),
+                    })
+                }
+            }
+        }
+        Ok(BorrowedValue::Array(items))
+    }
+
+    /// Parse object of borrowed keys and values, keeping duplicate keys
+    fn parse_borrowed_object(&mut self) -> Result<BorrowedValue<'a>> {
+        self.advance(); // Skip '{'
+        let mut members = Vec::new();
+        loop {
+            self.skip_whitespace();
+            // Empty object or trailing comma
+            if self.peek() == Some(b'}') {
                 self.advance();
+                break;
+            }
+            let key = match self.peek() {
+                Some(b'"' | b'\'' | b'`') => self.parse_str()?,
+                _ => Cow::Borrowed(self.parse_unquoted_key()?),
+            };
+
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Err(Error::ParseError {
+                    position: self.position,
+                    message: "Expected ':' after key".to_string(),
+                });
+            }
+            self.advance();
+            members.push((key, self.parse_borrowed_value()?));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b'}') => {
+                    self.advance();
+                    break;
+                }
+                _ => {
+                    return Err(Error::ParseError {
+                        position: self.position,
+                        message: "Expected ',' or '}'".to_string(),
+                    })
+                }
             }
+        }
+        Ok(BorrowedValue::Object(members))
+    }
+
+    /// Parse unquoted key (JSON5 style)
+    pub(crate) fn parse_unquoted_key(&mut self) -> Result<&'a str> {
+        let input = self.input;
+        let rest = &input[self.position..];
+        let mut chars = rest.char_indices();
+
+        // First character must be letter, underscore, or dollar sign
+        match chars.next() {
+            Some((_, ch)) if ch.is_alphabetic() || ch == '_' || ch == '$' => {}
             _ => {
                 return Err(Error::ParseError {
                     position: self.position,
@@ -375,15 +610,12 @@ impl<'a> Parser<'a> {
         }
 
         // Subsequent characters
-        while let Some(ch) = self.current() {
-            if ch.is_alphanumeric() || ch == '_' || ch == '$' {
-                self.advance();
-            } else {
-                break;
-            }
-        }
+        let len = chars
+            .find(|&(_, ch)| !(ch.is_alphanumeric() || ch == '_' || ch == '$'))
+            .map_or(rest.len(), |(i, _)| i);
+        self.position += len;
 
-        Ok(self.input[start..self.position].to_string())
+        Ok(&rest[..len])
     }
 
     /// Parse number (including BigInt and Decimal128)
@@ -391,35 +623,23 @@ impl<'a> Parser<'a> {
         let start = self.position;
 
         // Optional negative sign
-        if self.current() == Some('-') {
+        if self.peek() == Some(b'-') {
             self.advance();
         }
 
         // Integer part
-        if self.current() == Some('0') {
+        if self.peek() == Some(b'0') {
             self.advance();
         } else {
-            while let Some(ch) = self.current() {
-                if ch.is_ascii_digit() {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+            self.skip_digits();
         }
 
         // Fractional part
-        let has_decimal = self.current() == Some('.');
+        let has_decimal = self.peek() == Some(b'.');
         if has_decimal {
             self.advance();
             let frac_start = self.position;
-            while let Some(ch) = self.current() {
-                if ch.is_ascii_digit() {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+            self.skip_digits();
             if self.position == frac_start {
                 return Err(Error::ParseError {
                     position: self.position,
@@ -429,20 +649,14 @@ impl<'a> Parser<'a> {
         }
 
         // Exponent part
-        let has_exponent = matches!(self.current(), Some('e') | Some('E'));
+        let has_exponent = matches!(self.peek(), Some(b'e' | b'E'));
         if has_exponent {
             self.advance();
-            if matches!(self.current(), Some('+') | Some('-')) {
+            if matches!(self.peek(), Some(b'+' | b'-')) {
                 self.advance();
             }
             let exp_start = self.position;
-            while let Some(ch) = self.current() {
-                if ch.is_ascii_digit() {
-                    self.advance();
-                } else {
-                    break;
-                }
-            }
+            self.skip_digits();
             if self.position == exp_start {
                 return Err(Error::ParseError {
                     position: self.position,
@@ -452,7 +666,7 @@ impl<'a> Parser<'a> {
         }
 
         // Check for BigInt suffix
-        if self.current() == Some('n') {
+        if self.peek() == Some(b'n') {
             self.advance();
             let num_str = &self.input[start..self.position - 1];
             let bigint = BigInt::from_str(num_str)?;
@@ -460,22 +674,31 @@ impl<'a> Parser<'a> {
         }
 
         // Check for Decimal128 suffix
-        if self.current() == Some('m') {
+        if self.peek() == Some(b'm') {
             self.advance();
             let num_str = &self.input[start..self.position - 1];
             let decimal = Decimal128::from_str(num_str)?;
             return Ok(Value::Decimal128(decimal));
         }
 
-        // Regular number
+        // Regular number, an integer when it has no fraction or exponent and
+        // fits in an i64 or u64; `-0` stays a float to keep its sign
         let num_str = &self.input[start..self.position];
+        if !has_decimal && !has_exponent && num_str != "-0" {
+            if let Ok(n) = num_str.parse::<i64>() {
+                return Ok(Value::Integer(n));
+            }
+            if let Ok(n) = num_str.parse::<u64>() {
+                return Ok(Value::UInteger(n));
+            }
+        }
         let num = num_str
             .parse::<f64>()
             .map_err(|_| Error::InvalidNumber(num_str.to_string()))?;
         Ok(Value::Number(num))
     }
 
-    /// Try to parse unquoted literal (UUID, Date)
+    /// Try to parse unquoted literal (UUID, Date, Duration)
     fn try_parse_unquoted_literal(&mut self) -> Result<Value> {
         let saved_pos = self.position;
         match self.parse_unquoted_literal() {
@@ -490,18 +713,21 @@ impl<'a> Parser<'a> {
         }
     }
 
-    /// Parse unquoted literal (UUID, Date)
+    /// Parse unquoted literal (UUID, Date, Duration, binary)
     fn parse_unquoted_literal(&mut self) -> Result<Value> {
         let start = self.position;
-
-        // Read until delimiter
-        while let Some(ch) = self.current() {
-            match ch {
-                ' ' | '\t' | '\n' | '\r' | ',' | ']' | '}' => break,
-                _ => self.advance(),
+        if let Some(rest) = self.input[start..].strip_prefix("b64") {
+            if rest.starts_with(['\'', '"']) {
+                return self.parse_binary();
             }
         }
 
+        // Read until delimiter
+        self.position += self.input.as_bytes()[start..]
+            .iter()
+            .position(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}'))
+            .unwrap_or(self.input.len() - start);
+
         let literal = &self.input[start..self.position];
 
         // Try to parse as UUID
@@ -514,37 +740,126 @@ impl<'a> Parser<'a> {
             return Ok(Value::Date(date));
         }
 
+        // Try to parse as Duration
+        if let Ok(duration) = Duration::from_iso8601(literal) {
+            return Ok(Value::Duration(duration));
+        }
+
         Err(Error::ParseError {
             position: start,
             message: format!("Invalid literal: {}", literal),
         })
     }
+
+    /// Parse a `b64'...'` binary literal
+    fn parse_binary(&mut self) -> Result<Value> {
+        let open = self.position + 3;
+        let quote = self.input.as_bytes()[open];
+        let body = open + 1;
+        let Some(len) = search::find(quote, &self.input.as_bytes()[body..]) else {
+            return Err(Error::ParseError {
+                position: self.input.len(),
+                message: "Unterminated binary literal".to_string(),
+            });
+        };
+        let text = &self.input[body..body + len];
+        let bytes = BASE64.decode(text).map_err(|e| Error::ParseError {
+            position: match e {
+                DecodeError::InvalidByte(i, _) | DecodeError::InvalidLastSymbol(i, _) => body + i,
+                _ => body + len,
+            },
+            message: "Invalid base64 in binary literal".to_string(),
+        })?;
+        self.position = body + len + 1;
+        Ok(Value::Binary(bytes))
+    }
 }
 
+/// Base64 of binary literals: the standard alphabet, padding optional
+pub(crate) const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_parse_primitives() {
         assert_eq!(parse("null").unwrap(), Value::Null);
         assert_eq!(parse("true").unwrap(), Value::Bool(true));
         assert_eq!(parse("false").unwrap(), Value::Bool(false));
         assert_eq!(parse("123").unwrap(), Value::Number(123.0));
         assert_eq!(parse("3.14").unwrap(), Value::Number(3.14));
-        assert_eq!(parse("\"hello\"").unwrap(), Value::String("hello".to_string()));
+        assert!(matches!(parse("123").unwrap(), Value::Integer(123)));
+        assert!(matches!(
+            parse("9007199254740993").unwrap(),
+            Value::Integer(9007199254740993)
+        ));
+        assert!(matches!(
+            parse("18446744073709551615").unwrap(),
+            Value::UInteger(u64::MAX)
+        ));
+        assert!(matches!(parse("18446744073709551616").unwrap(), Value::Number(_)));
+        assert!(matches!(parse("1e3").unwrap(), Value::Number(_)));
+        assert!(matches!(parse("-0").unwrap(), Value::Number(n) if n.is_sign_negative()));
+        assert!(matches!(
+            parse("9223372036854775808").unwrap(),
+            Value::UInteger(9223372036854775808)
+        ));
+        assert_eq!(parse("\"hello\"").unwrap(), Value::String("hello".into()));
+    }
+
+    #[test]
+    fn test_reuse_parser() {
+        let mut parser = Parser::new("[1, { a: 'x\\ty', b: [2, 3] }, 'z']");
+        let first = parser.parse().unwrap();
+        assert_eq!(first, parse("[1, { a: 'x\\ty', b: [2, 3] }, 'z']").unwrap());
+        assert!(parser.items.capacity() >= 3);
+        assert!(parser.string.capacity() >= 3);
+
+        // A failed document leaves nothing behind for the next
+        parser.reset("[1, [2, { a: 3 ");
+        assert!(parser.parse().is_err());
+        parser.reset("{ k: ['v'], }");
+        assert_eq!(parser.parse().unwrap(), parse("{ k: ['v'] }").unwrap());
+        assert!(parser.items.is_empty() && parser.members.is_empty());
+
+        parser.reset("{ a: 1, a: 2 }");
+        assert_eq!(parser.parse().unwrap(), parse("{ a: 2 }").unwrap());
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut parser = Parser::new("[{ a: [1] }, []]").max_depth(3);
+        assert!(parser.parse().is_ok());
+
+        parser.reset("[{ a: [[1]] }]");
+        let err = parser.parse().unwrap_err();
+        assert!(matches!(err, Error::DepthLimit { position: 7, limit: 3 }));
+        assert!(err.is_limit());
+
+        // The depth starts over after a failure
+        parser.reset("[[[]]]");
+        assert!(parser.parse().is_ok());
+
+        let deep = "[".repeat(100_000);
+        let err = Parser::new(&deep).max_depth(64).parse().unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::DepthLimit);
     }
 
     #[test]
     fn test_parse_extended_types() {
         // BigInt
-        match parse("123456789012345678n").unwrap() {
+        match &parse("123456789012345678n").unwrap() {
             Value::BigInt(b) => assert_eq!(b.to_string(), "123456789012345678"),
             _ => panic!("Expected BigInt"),
         }
 
         // Decimal128
-        match parse("99.99m").unwrap() {
+        match &parse("99.99m").unwrap() {
             Value::Decimal128(d) => assert_eq!(d.to_string(), "99.99"),
             _ => panic!("Expected Decimal128"),
         }
@@ -560,12 +875,32 @@ mod tests {
             Value::Date(_) => (), // Date parsing tested in types module
             _ => panic!("Expected Date"),
         }
+
+        // Duration
+        match &parse("PT1H30M").unwrap() {
+            Value::Duration(d) => assert_eq!(d.to_iso8601(), "PT1H30M"),
+            _ => panic!("Expected Duration"),
+        }
+        match &parse("[-PT1.5S]").unwrap() {
+            Value::Array(arr) => assert_eq!(arr[0], Value::Duration(Duration::from_millis(-1500))),
+            _ => panic!("Expected array"),
+        }
+
+        // Binary, with or without padding
+        assert_eq!(parse("b64'AQIDBA=='").unwrap(), Value::Binary(vec![1, 2, 3, 4]));
+        assert_eq!(
+            parse("{ key: b64\"AQIDBA\", b64: b64'' }").unwrap(),
+            parse("{ key: b64'AQIDBA==', b64: b64\"\" }").unwrap()
+        );
+        let err = parse("[b64'AQ!D']").unwrap_err();
+        assert_eq!(err.position(), Some(7));
+        assert!(parse("b64'AQID").unwrap_err().is_eof());
     }
 
     #[test]
     fn test_parse_array() {
         let result = parse("[1, 2, 3]").unwrap();
-        match result {
+        match &result {
             Value::Array(arr) => {
                 assert_eq!(arr.len(), 3);
                 assert_eq!(arr[0], Value::Number(1.0));
@@ -579,9 +914,9 @@ mod tests {
     #[test]
     fn test_parse_object() {
         let result = parse(r#"{"name": "test", "value": 42}"#).unwrap();
-        match result {
+        match &result {
             Value::Object(obj) => {
-                assert_eq!(obj.get("name"), Some(&Value::String("test".to_string())));
+                assert_eq!(obj.get("name"), Some(&Value::String("test".into())));
                 assert_eq!(obj.get("value"), Some(&Value::Number(42.0)));
             }
             _ => panic!("Expected object"),
@@ -592,9 +927,9 @@ mod tests {
     fn test_parse_json5_features() {
         // Unquoted keys
         let result = parse("{name: \"test\", value: 42}").unwrap();
-        match result {
+        match &result {
             Value::Object(obj) => {
-                assert_eq!(obj.get("name"), Some(&Value::String("test".to_string())));
+                assert_eq!(obj.get("name"), Some(&Value::String("test".into())));
                 assert_eq!(obj.get("value"), Some(&Value::Number(42.0)));
             }
             _ => panic!("Expected object"),
@@ -604,4 +939,26 @@ mod tests {
         assert!(parse("[1, 2, 3,]").is_ok());
         assert!(parse("{a: 1,}").is_ok());
     }
+
+    #[test]
+    fn test_parse_non_ascii() {
+        let value = parse("{ 'clé': ['日本', '𐠂'] /* ü */ }").unwrap();
+        assert_eq!(
+            value.as_object().unwrap()["clé"],
+            Value::Array(vec![
+                Value::String("日本".into()),
+                Value::String("𐠂".into())
+            ])
+        );
+
+        let value = parse("{ größe: '\\u00e9t\\u00e9 😀', ñ_2: 1 }").unwrap();
+        assert_eq!(value.as_object().unwrap()["größe"], Value::string("été 😀"));
+        assert!(value.as_object().unwrap().contains_key("ñ_2"));
+        let err = parse("'a\\é'").unwrap_err();
+        assert!(
+            matches!(&err, Error::ParseError { position: 3, message } if message.ends_with("\\é"))
+        );
+        assert!(matches!(parse("'\\u00zz'"), Err(Error::ParseError { .. })));
+        assert!(matches!(parse("'\\u00"), Err(Error::UnexpectedEof)));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,447 @@
+//! JSONPath queries
+//!
+//! [`query`] evaluates the commonly used subset of JSONPath (RFC 9535):
+//!
+//! | Syntax               | Selects                                        |
+//! |----------------------|------------------------------------------------|
+//! | `$`                  | the root                                       |
+//! | `.name`, `['name']`  | an object member                               |
+//! | `[2]`, `[-1]`        | an array element, negative from the end        |
+//! | `.*`, `[*]`          | every member or element                        |
+//! | `..name`, `..*`      | the selector applied at any depth              |
+//!
+//! ```
+//! let doc = kjson::parse("{ orders: [{ id: 1n }, { id: 2n }] }")?;
+//! let ids = kjson::path::query(&doc, "$.orders[*].id")?;
+//! assert_eq!(ids.len(), 2);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Object members are visited in key order, so results are deterministic.
+//! Filters and slices are not supported.
+//!
+//! [`query_as`] deserializes the matches in the same step:
+//!
+//! ```
+//! #[derive(serde::Deserialize)]
+//! struct Order {
+//!     id: u64,
+//! }
+//!
+//! let doc = kjson::parse("{ orders: [{ id: 1 }, { id: 2 }] }")?;
+//! let orders: Vec<Order> = doc.query_as("$.orders[*]")?;
+//! assert_eq!(orders[1].id, 2);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! For reaching a single value, [`Value::get_path`] takes a shorter dotted
+//! path without the `$`. Keys containing dots or brackets are quoted:
+//!
+//! ```
+//! let doc = kjson::parse("{ order: { items: [{ id: 7 }], 'ship.to': 'Oslo' } }")?;
+//! assert_eq!(doc.get_path("order.items[0].id").and_then(|v| v.as_i64()), Some(7));
+//! assert_eq!(doc.get_path("order.'ship.to'").and_then(|v| v.as_str()), Some("Oslo"));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+
+use crate::error::{Error, Result};
+use crate::pointer::{push, push_index};
+use crate::value::{from_value, ordered_entries, Value};
+use serde::de::DeserializeOwned;
+
+/// One step of a parsed path
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    selector: Selector,
+    descendants: bool,
+}
+
+/// Evaluate a JSONPath against `value`, returning every match in order
+pub fn query<'a>(value: &'a Value, path: &str) -> Result<Vec<&'a Value>> {
+    Ok(evaluate(value, path)?
+        .into_iter()
+        .map(|(_, node)| node)
+        .collect())
+}
+
+/// Evaluate a JSONPath against `value`, returning a JSON Pointer to every
+/// match in order
+#[cfg(feature = "encrypt")]
+pub(crate) fn query_pointers(value: &Value, path: &str) -> Result<Vec<String>> {
+    Ok(evaluate(value, path)?
+        .into_iter()
+        .map(|(pointer, _)| pointer)
+        .collect())
+}
+
+/// Every match with its JSON Pointer
+fn evaluate<'a>(value: &'a Value, path: &str) -> Result<Vec<(String, &'a Value)>> {
+    let segments = parse_path(path)?;
+    let mut current = vec![(String::new(), value)];
+    for segment in &segments {
+        let mut next = Vec::new();
+        for node in current {
+            if segment.descendants {
+                let mut nodes = Vec::new();
+                collect_descendants(node, &mut nodes);
+                for node in nodes {
+                    select(node, &segment.selector, &mut next);
+                }
+            } else {
+                select(node, &segment.selector, &mut next);
+            }
+        }
+        current = next;
+    }
+    Ok(current)
+}
+
+/// Evaluate a JSONPath against `value` and deserialize the matches, as an
+/// array, into `T`, typically a `Vec`.
+///
+/// A match that does not fit is reported with the path and the expected
+/// type, e.g. `$.orders[*] as Vec<Order>: missing field `id``.
+pub fn query_as<T: DeserializeOwned>(value: &Value, path: &str) -> Result<T> {
+    let matches = query(value, path)?.into_iter().cloned().collect();
+    from_value(Value::Array(matches)).map_err(|e| {
+        let message = match e {
+            Error::Custom(message) => message,
+            other => other.to_string(),
+        };
+        Error::Custom(format!("{} as {}: {}", path, type_name::<T>(), message))
+    })
+}
+
+impl Value {
+    /// Evaluate a JSONPath against this value; see [`query`]
+    pub fn query(&self, path: &str) -> Result<Vec<&Value>> {
+        query(self, path)
+    }
+
+    /// Evaluate a JSONPath and deserialize the matches; see [`query_as`]
+    pub fn query_as<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        query_as(self, path)
+    }
+
+    /// Look up a single value by dotted path, e.g. `order.items[2].id`.
+    ///
+    /// Segments are member names, `[n]` indexes (negative from the end) or
+    /// quoted names such as `'a.b'` and `["a.b"]`. A bare number also
+    /// indexes an array, so `items.2` works too. The empty path is the value
+    /// itself; a malformed path or a missing value gives `None`.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        parse_get_path(path)?
+            .iter()
+            .try_fold(self, |node, selector| match (selector, node) {
+                (Selector::Key(key), Value::Object(map)) => map.get(key.as_str()),
+                (selector, Value::Array(items)) => items.get(array_index(selector, items.len())?),
+                _ => None,
+            })
+    }
+
+    /// Mutable counterpart of [`Value::get_path`]
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        parse_get_path(path)?
+            .iter()
+            .try_fold(self, |node, selector| match (selector, node) {
+                (Selector::Key(key), Value::Object(map)) => map.get_mut(key.as_str()),
+                (selector, Value::Array(items)) => {
+                    let index = array_index(selector, items.len())?;
+                    items.get_mut(index)
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Resolve a [`Value::get_path`] segment against an array of `len` items
+fn array_index(selector: &Selector, len: usize) -> Option<usize> {
+    let index = match selector {
+        Selector::Index(index) => *index,
+        Selector::Key(key) => return key.parse().ok(),
+        Selector::Wildcard => return None,
+    };
+    if index < 0 {
+        len.checked_sub(index.unsigned_abs() as usize)
+    } else {
+        Some(index as usize)
+    }
+}
+
+/// Parse a [`Value::get_path`] path into member and index selectors
+fn parse_get_path(path: &str) -> Option<Vec<Selector>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if !segments.is_empty() && !rest.starts_with('[') {
+            rest = rest.strip_prefix('.')?;
+        }
+        let (selector, after) = if let Some(after) = rest.strip_prefix('[') {
+            match parse_bracket(after)? {
+                (Selector::Wildcard, _) => return None,
+                parsed => parsed,
+            }
+        } else if rest.starts_with(['\'', '"']) {
+            let (key, after) = parse_quoted(rest)?;
+            (Selector::Key(key), after)
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return None;
+            }
+            (Selector::Key(rest[..end].to_string()), &rest[end..])
+        };
+        segments.push(selector);
+        rest = after;
+    }
+    Some(segments)
+}
+
+/// Name of `T` without module paths, e.g. `Vec<Order>`
+fn type_name<T>() -> String {
+    std::any::type_name::<T>()
+        .split_inclusive(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|part| part.rsplit("::").next().unwrap_or(part))
+        .collect()
+}
+
+type Node<'a> = (String, &'a Value);
+
+fn select<'a>((pointer, node): Node<'a>, selector: &Selector, out: &mut Vec<Node<'a>>) {
+    match (selector, node) {
+        (Selector::Key(key), Value::Object(map)) => {
+            if let Some(child) = map.get(key.as_str()) {
+                out.push((push(&pointer, key), child));
+            }
+        }
+        (Selector::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 {
+                items.len() as i64 + index
+            } else {
+                *index
+            };
+            if index >= 0 {
+                if let Some(child) = items.get(index as usize) {
+                    out.push((push_index(&pointer, index as usize), child));
+                }
+            }
+        }
+        (Selector::Wildcard, Value::Array(items)) => out.extend(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, child)| (push_index(&pointer, i), child)),
+        ),
+        (Selector::Wildcard, Value::Object(map)) => {
+            out.extend(
+                ordered_entries(map)
+                    .into_iter()
+                    .map(|(key, child)| (push(&pointer, key), child)),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// `node` and everything below it, parents first
+fn collect_descendants<'a>(node: Node<'a>, out: &mut Vec<Node<'a>>) {
+    let mut children = Vec::new();
+    select(node.clone(), &Selector::Wildcard, &mut children);
+    out.push(node);
+    for child in children {
+        collect_descendants(child, out);
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<Segment>> {
+    let error = |position: usize, message: &str| Error::ParseError {
+        position,
+        message: message.to_string(),
+    };
+    let Some(mut rest) = path.strip_prefix('$') else {
+        return Err(error(0, "JSONPath must start with '$'"));
+    };
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        let position = path.len() - rest.len();
+        let descendants = rest.starts_with("..");
+        if descendants {
+            rest = &rest[2..];
+        } else if let Some(after) = rest.strip_prefix('.') {
+            rest = after;
+        } else if !rest.starts_with('[') {
+            return Err(error(position, "Expected '.' or '['"));
+        }
+
+        let (selector, after) = if let Some(after) = rest.strip_prefix('[') {
+            parse_bracket(after).ok_or_else(|| error(position, "Invalid bracket selector"))?
+        } else if let Some(after) = rest.strip_prefix('*') {
+            (Selector::Wildcard, after)
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(error(position, "Expected a member name"));
+            }
+            (Selector::Key(rest[..end].to_string()), &rest[end..])
+        };
+        segments.push(Segment {
+            selector,
+            descendants,
+        });
+        rest = after;
+    }
+    Ok(segments)
+}
+
+/// Parse the inside of `[...]`, returning the selector and the text after `]`
+fn parse_bracket(input: &str) -> Option<(Selector, &str)> {
+    let input = input.trim_start();
+    if let Some(after) = input.strip_prefix('*') {
+        return Some((Selector::Wildcard, after.trim_start().strip_prefix(']')?));
+    }
+    if input.starts_with(['\'', '"']) {
+        let (key, after) = parse_quoted(input)?;
+        return Some((Selector::Key(key), after.trim_start().strip_prefix(']')?));
+    }
+    let end = input.find(']')?;
+    let index = input[..end].trim().parse().ok()?;
+    Some((Selector::Index(index), &input[end + 1..]))
+}
+
+/// Parse a quoted name with backslash escapes, returning it and the text
+/// after the closing quote
+fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let quote = input.chars().next()?;
+    let mut key = String::new();
+    let mut chars = input[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => key.push(chars.next()?.1),
+            c if c == quote => return Some((key, &input[1 + i + 1..])),
+            c => key.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn strings(values: Vec<&Value>) -> Vec<&str> {
+        values.into_iter().filter_map(Value::as_str).collect()
+    }
+
+    #[test]
+    fn test_query() {
+        let doc = parse(
+            "{
+                store: {
+                    books: [{ title: 'a', tags: ['x'] }, { title: 'b' }, { title: 'c' }],
+                    'the.shelf': { title: 'd' },
+                },
+            }",
+        )
+        .unwrap();
+        assert_eq!(query(&doc, "$").unwrap(), vec![&doc]);
+        assert_eq!(
+            strings(query(&doc, "$.store.books[1].title").unwrap()),
+            ["b"]
+        );
+        assert_eq!(
+            strings(query(&doc, "$.store.books[-1].title").unwrap()),
+            ["c"]
+        );
+        assert_eq!(
+            strings(query(&doc, "$.store.books[*].title").unwrap()),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            strings(query(&doc, "$['store'][\"the.shelf\"].title").unwrap()),
+            ["d"]
+        );
+        assert_eq!(
+            strings(query(&doc, "$..title").unwrap()),
+            ["a", "b", "c", "d"]
+        );
+        assert_eq!(strings(query(&doc, "$..tags[0]").unwrap()), ["x"]);
+        assert!(query(&doc, "$.missing.title").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_query_as() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Order {
+            id: u64,
+            status: String,
+        }
+
+        let doc = parse(
+            "{ orders: [{ id: 1, status: 'open' }, { id: 2, status: 'shipped' }], count: 2 }",
+        )
+        .unwrap();
+        let orders: Vec<Order> = doc.query_as("$.orders[*]").unwrap();
+        assert_eq!(orders[1].status, "shipped");
+        let statuses: Vec<String> = doc.query_as("$..status").unwrap();
+        assert_eq!(statuses, ["open", "shipped"]);
+        let count: Option<u64> = doc.query_as::<Vec<u64>>("$.count").unwrap().pop();
+        assert_eq!(count, Some(2));
+
+        let err = doc.query_as::<Vec<Order>>("$.orders").unwrap_err();
+        assert!(matches!(&err, Error::Custom(m) if m.starts_with("$.orders as Vec<Order>: ")));
+        let err = doc.query_as::<Vec<u64>>("$..status").unwrap_err();
+        assert!(err.to_string().contains("$..status as Vec<u64>: expected u64 at /0, found string"));
+        assert!(doc.query_as::<Vec<u64>>("orders").is_err());
+    }
+
+    #[test]
+    fn test_get_path() {
+        let mut doc = parse(
+            "{ order: { items: [{ id: 1 }, { id: 2 }, { id: 3 }], 'ship.to': { city: 'Oslo' } } }",
+        )
+        .unwrap();
+        let id = |doc: &Value, path: &str| doc.get_path(path).and_then(Value::as_i64);
+        assert_eq!(id(&doc, "order.items[2].id"), Some(3));
+        assert_eq!(id(&doc, "order.items.1.id"), Some(2));
+        assert_eq!(id(&doc, "order['items'][-3].id"), Some(1));
+        assert_eq!(doc.get_path(""), Some(&doc));
+        assert_eq!(
+            doc.get_path("order.'ship.to'.city").and_then(Value::as_str),
+            Some("Oslo")
+        );
+        assert_eq!(
+            doc.get_path("order[\"ship.to\"].city").and_then(Value::as_str),
+            Some("Oslo")
+        );
+        for path in ["order.ship.to", "order.items[3]", "order.items[-4]", "order.items.x"] {
+            assert_eq!(doc.get_path(path), None, "{}", path);
+        }
+        for path in [".order", "order.", "order..items", "order[*]", "order['items'", "$.order"] {
+            assert_eq!(doc.get_path(path), None, "{}", path);
+        }
+
+        *doc.get_path_mut("order.items[0].id").unwrap() = Value::Integer(10);
+        assert_eq!(id(&doc, "order.items[0].id"), Some(10));
+        assert!(doc.get_path_mut("order.items[0].id.x").is_none());
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let doc = Value::Null;
+        for path in ["store", "$.", "$[", "$['a'", "$[x]", "$a"] {
+            assert!(
+                matches!(query(&doc, path), Err(Error::ParseError { .. })),
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Binary formats of the built-in Postgres types the extended scalars map to
+//!
+//! Shared by the `sqlx` and `diesel` integrations: Decimal128 and BigInt as
+//! `numeric`, Instant as `timestamptz` and Duration as `interval`.
+
+use crate::error::{Error, Result};
+use crate::types::{BigInt, Duration, Instant};
+
+/// Seconds from the Unix epoch to the Postgres epoch (2000-01-01)
+pub(crate) const PG_EPOCH_SECONDS: i64 = 946_684_800;
+
+const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+// Sign field of the numeric binary format
+const NUMERIC_POS: u16 = 0x0000;
+const NUMERIC_NEG: u16 = 0x4000;
+
+/// Write an instant as `timestamptz`, microseconds since the Postgres epoch
+pub(crate) fn encode_timestamp(instant: &Instant, out: &mut Vec<u8>) {
+    let micros = instant.epoch_nanos().div_euclid(1_000) - PG_EPOCH_SECONDS * 1_000_000;
+    out.extend_from_slice(&micros.to_be_bytes());
+}
+
+/// Read a `timestamptz`
+pub(crate) fn decode_timestamp(raw: &[u8]) -> Result<Instant> {
+    let micros = i64::from_be_bytes(
+        raw.try_into()
+            .map_err(|_| Error::Conversion("timestamptz must be 8 bytes".to_string()))?,
+    );
+    micros
+        .checked_add(PG_EPOCH_SECONDS * 1_000_000)
+        .and_then(|m| m.checked_mul(1_000))
+        .map(Instant::from_nanos)
+        .ok_or_else(|| Error::Conversion("timestamptz out of range".to_string()))
+}
+
+/// Write a duration as an `interval` of microseconds, without days or months
+pub(crate) fn encode_interval(duration: &Duration, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(duration.total_nanos() / 1_000).to_be_bytes());
+    out.extend_from_slice(&0i32.to_be_bytes()); // days
+    out.extend_from_slice(&0i32.to_be_bytes()); // months
+}
+
+/// Read an `interval`, counting days as 24 hours. Months have no fixed
+/// length, so intervals with months or years fail.
+pub(crate) fn decode_interval(raw: &[u8]) -> Result<Duration> {
+    let raw: [u8; 16] = raw
+        .try_into()
+        .map_err(|_| Error::Conversion("interval must be 16 bytes".to_string()))?;
+    let micros = i64::from_be_bytes(raw[..8].try_into().unwrap());
+    let days = i32::from_be_bytes(raw[8..12].try_into().unwrap());
+    let months = i32::from_be_bytes(raw[12..].try_into().unwrap());
+    if months != 0 {
+        return Err(Error::Conversion(
+            "intervals with months or years have no fixed length".to_string(),
+        ));
+    }
+    (days as i64 * MICROS_PER_DAY)
+        .checked_add(micros)
+        .and_then(|m| m.checked_mul(1_000))
+        .map(Duration::from_nanos)
+        .ok_or_else(|| Error::Conversion("interval out of range".to_string()))
+}
+
+/// Write `coefficient * 10^exponent` in the numeric binary format
+pub(crate) fn encode_numeric(coefficient: &BigInt, exponent: i32, out: &mut Vec<u8>) {
+    let digits = coefficient.to_string();
+    let mut digits = digits.trim_start_matches('-').to_string();
+    if exponent > 0 {
+        digits.push_str(&"0".repeat(exponent as usize));
+    }
+    let scale = exponent.min(0).unsigned_abs() as usize;
+    if digits.len() < scale {
+        digits.insert_str(0, &"0".repeat(scale - digits.len()));
+    }
+
+    // Align the decimal point to base-10000 groups
+    let (integer, fraction) = digits.split_at(digits.len() - scale);
+    let integer = format!("{}{}", "0".repeat((4 - integer.len() % 4) % 4), integer);
+    let fraction = format!("{}{}", fraction, "0".repeat((4 - fraction.len() % 4) % 4));
+    let mut weight = (integer.len() / 4) as i16 - 1;
+    let mut groups: Vec<i16> = format!("{}{}", integer, fraction)
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap().parse().unwrap())
+        .collect();
+
+    let leading = groups.iter().take_while(|&&g| g == 0).count();
+    groups.drain(..leading);
+    weight -= leading as i16;
+    while groups.last() == Some(&0) {
+        groups.pop();
+    }
+    let sign = if coefficient.is_negative() && !groups.is_empty() {
+        NUMERIC_NEG
+    } else {
+        NUMERIC_POS
+    };
+    if groups.is_empty() {
+        weight = 0;
+    }
+
+    out.extend_from_slice(&(groups.len() as i16).to_be_bytes());
+    out.extend_from_slice(&weight.to_be_bytes());
+    out.extend_from_slice(&sign.to_be_bytes());
+    out.extend_from_slice(&(scale as u16).to_be_bytes());
+    for group in groups {
+        out.extend_from_slice(&group.to_be_bytes());
+    }
+}
+
+/// Read the numeric binary format as `(coefficient, exponent)`, keeping its display scale
+pub(crate) fn decode_numeric(raw: &[u8]) -> Result<(BigInt, i32)> {
+    let field = |i: usize| -> Result<[u8; 2]> {
+        raw.get(i * 2..i * 2 + 2)
+            .map(|b| [b[0], b[1]])
+            .ok_or_else(|| Error::Conversion("truncated numeric".to_string()))
+    };
+    let count = i16::from_be_bytes(field(0)?) as usize;
+    let weight = i16::from_be_bytes(field(1)?) as i32;
+    let sign = u16::from_be_bytes(field(2)?);
+    let scale = u16::from_be_bytes(field(3)?) as i32;
+    if sign != NUMERIC_POS && sign != NUMERIC_NEG {
+        return Err(Error::Conversion(
+            "NaN and infinite numerics have no kJSON equivalent".to_string(),
+        ));
+    }
+
+    let mut digits = if sign == NUMERIC_NEG {
+        "-".to_string()
+    } else {
+        String::new()
+    };
+    for i in 0..count {
+        digits.push_str(&format!("{:04}", u16::from_be_bytes(field(4 + i)?)));
+    }
+    if count == 0 {
+        digits.push('0');
+    }
+
+    // Adjust from whole groups to the display scale
+    let mut exponent = 4 * (weight - count as i32 + 1);
+    if exponent > -scale {
+        digits.push_str(&"0".repeat((exponent + scale) as usize));
+    } else {
+        let excess = (-scale - exponent) as usize;
+        digits.truncate(digits.len() - excess.min(digits.len()));
+    }
+    exponent = -scale;
+    let coefficient = match digits.as_str() {
+        "" | "-" => BigInt::from_i64(0),
+        _ => BigInt::from_str(&digits)?,
+    };
+    Ok((coefficient, exponent))
+}
+
+/// Convert `coefficient * 10^exponent` to an integer, failing if it has a fraction
+pub(crate) fn numeric_to_bigint(coefficient: &BigInt, exponent: i32) -> Result<BigInt> {
+    let digits = coefficient.to_string();
+    let fraction = exponent.min(0).unsigned_abs() as usize;
+    let (integer, rest) = digits.split_at(digits.len().saturating_sub(fraction));
+    if rest.trim_start_matches('-').bytes().any(|b| b != b'0') {
+        return Err(Error::Conversion(format!(
+            "numeric {}e{} is not an integer",
+            digits, exponent
+        )));
+    }
+    match integer {
+        "" | "-" => Ok(BigInt::from_i64(0)),
+        _ => BigInt::from_str(integer),
+    }
+}
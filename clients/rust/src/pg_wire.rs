@@ -0,0 +1,392 @@
+//! Wire format of the kjson Postgres extension's `kjson` type
+//!
+//! Shared by the `postgres` and `sqlx` integrations. Like `jsonb`, a value
+//! is a version byte followed by the payload: version 1 carries kJSON text,
+//! version 2 the extension's internal binary representation.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use chrono::DateTime;
+use uuid::Uuid;
+
+/// Name of the extension's column type
+#[cfg_attr(not(any(feature = "postgres", feature = "sqlx")), allow(dead_code))]
+pub const KJSON_TYPE: &str = "kjson";
+
+/// `kjson` wire format version carrying kJSON text
+pub const WIRE_TEXT: u8 = 1;
+
+/// `kjson` wire format version carrying the extension's internal representation
+pub const WIRE_INTERNAL: u8 = 2;
+
+/// Write a value in the current wire format, version byte included.
+///
+/// The internal representation has no binary type, so values holding
+/// binary data are sent as kJSON text instead.
+pub(crate) fn encode(value: &Value, out: &mut Vec<u8>) {
+    if has_binary(value) {
+        out.push(WIRE_TEXT);
+        out.extend_from_slice(to_string(value).expect("values serialize").as_bytes());
+        return;
+    }
+    out.push(WIRE_INTERNAL);
+    encode_internal(value, out);
+}
+
+pub(crate) fn has_binary(value: &Value) -> bool {
+    match value {
+        Value::Binary(_) => true,
+        Value::Array(arr) => arr.iter().any(has_binary),
+        Value::Object(obj) => obj.values().any(has_binary),
+        _ => false,
+    }
+}
+
+/// Read a value in either wire format version
+pub(crate) fn decode(raw: &[u8]) -> Result<Value> {
+    match raw.split_first() {
+        Some((&WIRE_TEXT, text)) => parse(
+            std::str::from_utf8(text)
+                .map_err(|e| Error::InvalidBinary(format!("invalid UTF-8 in kjson text: {}", e)))?,
+        ),
+        Some((&WIRE_INTERNAL, data)) => decode_internal(data),
+        Some((version, _)) => Err(Error::InvalidBinary(format!(
+            "unsupported kjson wire format version {}",
+            version
+        ))),
+        None => Err(Error::InvalidBinary("empty kjson value".to_string())),
+    }
+}
+
+// Type bytes of the extension's internal representation (kjson_binary.c).
+// Fixed-width fields are little-endian, matching the extension on
+// little-endian servers.
+const NULL: u8 = 0x00;
+const TRUE: u8 = 0x01;
+const FALSE: u8 = 0x02;
+const INT8: u8 = 0x03;
+const INT16: u8 = 0x04;
+const INT32: u8 = 0x05;
+const INT64: u8 = 0x06;
+const FLOAT64: u8 = 0x07;
+const STRING: u8 = 0x08;
+const ARRAY: u8 = 0x09;
+const OBJECT: u8 = 0x0A;
+const BIGINT: u8 = 0x0B;
+const DECIMAL128: u8 = 0x0C;
+const UUID: u8 = 0x0D;
+const DATE: u8 = 0x0E;
+const DURATION: u8 = 0x0F;
+
+const NANOS_PER_MINUTE: i64 = 60_000_000_000;
+const NANOS_PER_HOUR: i64 = 60 * NANOS_PER_MINUTE;
+const NANOS_PER_DAY: i64 = 24 * NANOS_PER_HOUR;
+
+pub(crate) fn encode_internal(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(NULL),
+        Value::Bool(true) => out.push(TRUE),
+        Value::Bool(false) => out.push(FALSE),
+        Value::Number(n) => {
+            out.push(FLOAT64);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::Integer(n) => {
+            out.push(INT64);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+        Value::String(s) => {
+            out.push(STRING);
+            put_bytes(out, s.as_bytes());
+        }
+        Value::Array(arr) => {
+            out.push(ARRAY);
+            put_varint(out, arr.len() as u64);
+            for item in arr {
+                encode_internal(item, out);
+            }
+        }
+        Value::Object(obj) => {
+            out.push(OBJECT);
+            put_varint(out, obj.len() as u64);
+            for (k, v) in obj {
+                put_bytes(out, k.as_bytes());
+                encode_internal(v, out);
+            }
+        }
+        Value::UInteger(n) => {
+            // The extension has no unsigned type, so keep the digits exact
+            out.push(BIGINT);
+            out.push(0);
+            put_bytes(out, n.to_string().as_bytes());
+        }
+        Value::BigInt(b) => {
+            out.push(BIGINT);
+            let digits = b.to_string();
+            out.push(b.is_negative() as u8);
+            put_bytes(out, digits.trim_start_matches('-').as_bytes());
+        }
+        Value::Decimal128(d) => {
+            out.push(DECIMAL128);
+            let coefficient = d.coefficient();
+            out.push(coefficient.is_negative() as u8);
+            let exponent = d.exponent();
+            let zigzag = if exponent < 0 {
+                (exponent.unsigned_abs() << 1) | 1
+            } else {
+                (exponent as u32) << 1
+            };
+            put_varint(out, zigzag as u64);
+            put_bytes(
+                out,
+                coefficient.to_string().trim_start_matches('-').as_bytes(),
+            );
+        }
+        Value::Uuid(u) => {
+            out.push(UUID);
+            out.extend_from_slice(u.as_bytes());
+        }
+        Value::Instant(i) => {
+            out.push(DATE);
+            out.extend_from_slice(&i.epoch_nanos().to_le_bytes());
+            out.extend_from_slice(&0i16.to_le_bytes());
+        }
+        Value::Date(d) => {
+            out.push(DATE);
+            out.extend_from_slice(
+                &d.utc
+                    .timestamp_nanos_opt()
+                    .unwrap_or_default()
+                    .to_le_bytes(),
+            );
+            out.extend_from_slice(&d.tz_offset.unwrap_or(0).to_le_bytes());
+        }
+        Value::Duration(d) => {
+            out.push(DURATION);
+            let mut remaining = d.total_nanos().unsigned_abs() as i64;
+            out.extend_from_slice(&0i32.to_le_bytes()); // years
+            out.extend_from_slice(&0i32.to_le_bytes()); // months
+            for unit in [NANOS_PER_DAY, NANOS_PER_HOUR, NANOS_PER_MINUTE] {
+                out.extend_from_slice(&((remaining / unit) as i32).to_le_bytes());
+                remaining %= unit;
+            }
+            out.extend_from_slice(&remaining.to_le_bytes());
+            out.push(d.is_negative() as u8);
+        }
+        Value::Binary(_) => unreachable!("encode sends values holding binary data as text"),
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7F) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Decode a complete internal-representation payload
+pub(crate) fn decode_internal(data: &[u8]) -> Result<Value> {
+    let mut reader = Reader { data, pos: 0 };
+    let value = reader.value()?;
+    if reader.pos != data.len() {
+        return Err(reader.error("trailing bytes after value"));
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidBinary(format!("{} at offset {}", message, self.pos))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.error("unexpected end of data"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.array::<1>()?[0];
+            n |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.error("varint overflow"))
+    }
+
+    fn text(&mut self) -> Result<&'a str> {
+        let len = self.varint()? as usize;
+        std::str::from_utf8(self.take(len)?).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let tag = self.array::<1>()?[0];
+        Ok(match tag {
+            NULL => Value::Null,
+            TRUE => Value::Bool(true),
+            FALSE => Value::Bool(false),
+            INT8 => Value::Integer(i8::from_le_bytes(self.array()?).into()),
+            INT16 => Value::Integer(i16::from_le_bytes(self.array()?).into()),
+            INT32 => Value::Integer(self.i32()?.into()),
+            INT64 => Value::Integer(self.i64()?),
+            FLOAT64 => Value::Number(f64::from_le_bytes(self.array()?)),
+            STRING => Value::string(self.text()?.to_string()),
+            ARRAY => {
+                let count = self.varint()? as usize;
+                let mut items = Vec::with_capacity(count.min(self.data.len()));
+                for _ in 0..count {
+                    items.push(self.value()?);
+                }
+                Value::Array(items)
+            }
+            OBJECT => {
+                let count = self.varint()? as usize;
+                let mut map = Map::with_capacity(count.min(self.data.len()));
+                for _ in 0..count {
+                    let key = Str::from(self.text()?);
+                    map.insert(key, self.value()?);
+                }
+                Value::Object(map)
+            }
+            BIGINT => {
+                let negative = self.array::<1>()?[0] != 0;
+                let digits = self.text()?;
+                let sign = if negative { "-" } else { "" };
+                Value::BigInt(BigInt::from_str(&format!("{}{}", sign, digits))?)
+            }
+            DECIMAL128 => {
+                let negative = self.array::<1>()?[0] != 0;
+                let zigzag = self.varint()?;
+                let magnitude = (zigzag >> 1) as i32;
+                let exponent = if zigzag & 1 == 1 {
+                    -magnitude
+                } else {
+                    magnitude
+                };
+                let digits = self.text()?;
+                let sign = if negative { "-" } else { "" };
+                let coefficient = BigInt::from_str(&format!("{}{}", sign, digits))?;
+                Value::Decimal128(Decimal128::from_parts(&coefficient, exponent))
+            }
+            UUID => Value::Uuid(Uuid::from_bytes(self.array()?)),
+            DATE => {
+                let nanos = self.i64()?;
+                let tz_offset = i16::from_le_bytes(self.array()?);
+                if tz_offset == 0 {
+                    Value::Instant(Instant::from_nanos(nanos))
+                } else {
+                    Value::Date(Date {
+                        utc: DateTime::from_timestamp_nanos(nanos),
+                        tz_offset: Some(tz_offset),
+                    })
+                }
+            }
+            DURATION => {
+                let (years, months) = (self.i32()?, self.i32()?);
+                if years != 0 || months != 0 {
+                    return Err(self.error("durations with years or months are not supported"));
+                }
+                let mut nanos = 0i64;
+                for unit in [NANOS_PER_DAY, NANOS_PER_HOUR, NANOS_PER_MINUTE] {
+                    nanos += self.i32()? as i64 * unit;
+                }
+                nanos += self.i64()?;
+                let negative = self.array::<1>()?[0] != 0;
+                Value::Duration(Duration::from_nanos(if negative { -nanos } else { nanos }))
+            }
+            other => return Err(self.error(&format!("unknown type byte 0x{:02x}", other))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let value = parse("{ total: 12n }").unwrap();
+        let mut buf = Vec::new();
+        encode(&value, &mut buf);
+        assert_eq!(buf[0], WIRE_INTERNAL);
+        assert_eq!(decode(&buf).unwrap(), value);
+
+        let mut text = vec![WIRE_TEXT];
+        text.extend_from_slice(b"{ total: 12n }");
+        assert_eq!(decode(&text).unwrap(), value);
+
+        let value = parse("{ key: [b64'AQI='] }").unwrap();
+        let mut buf = Vec::new();
+        encode(&value, &mut buf);
+        assert_eq!(buf[0], WIRE_TEXT);
+        assert_eq!(decode(&buf).unwrap(), value);
+
+        assert!(matches!(decode(&[9, b'1']), Err(Error::InvalidBinary(_))));
+        assert!(decode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_internal_representation() {
+        // Byte layout written by the extension's kjson_binary.c
+        let value = parse("[-1.5m, -12n, PT1H30M, 'hi']").unwrap();
+        let mut buf = Vec::new();
+        encode_internal(&value, &mut buf);
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            ARRAY, 4,
+            DECIMAL128, 1, 3, 2, b'1', b'5',
+            BIGINT, 1, 2, b'1', b'2',
+            DURATION, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 30, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0,
+            STRING, 2, b'h', b'i',
+        ];
+        assert_eq!(&buf[..], expected);
+        assert_eq!(decode_internal(expected).unwrap(), value);
+
+        let instant = Value::Instant(Instant::from_nanos(1_736_510_400_123_456_789));
+        let mut buf = Vec::new();
+        encode_internal(&instant, &mut buf);
+        assert_eq!(buf.len(), 11);
+        assert_eq!(decode_internal(&buf).unwrap(), instant);
+
+        assert!(matches!(
+            decode_internal(&[ARRAY, 2, NULL]),
+            Err(Error::InvalidBinary(_))
+        ));
+        assert!(decode_internal(&[NULL, NULL]).is_err());
+    }
+}
@@ -0,0 +1,293 @@
+//! Streaming NDJSON transforms
+//!
+//! A [`Pipeline`] reads one document at a time, passes it through a chain
+//! of filters and maps, and writes what comes out as one document per line.
+//! Only a batch of documents is held at once, however long the input:
+//!
+//! ```
+//! use kjson::pipeline::Pipeline;
+//!
+//! let input = "{ id: 1n, status: 'open', items: [1, 2] }\n{ id: 2n, status: 'done', items: [3] }\n";
+//! let mut output = Vec::new();
+//! let stats = Pipeline::new()
+//!     .filter(|order| order.pointer("/status") == Some(&kjson::Value::string("open")))
+//!     .select("$.items[*]")?
+//!     .run(input.as_bytes(), &mut output)?;
+//! assert_eq!(String::from_utf8(output).unwrap(), "1\n2\n");
+//! assert_eq!((stats.read, stats.written), (2, 2));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! The input may be NDJSON or any whitespace-separated sequence of values.
+//! With [`threads`](Pipeline::threads) above one, each batch is split across
+//! that many threads; the output keeps the input order either way.
+
+use crate::error::Result;
+use crate::path::query;
+use crate::serializer::to_string;
+use crate::stream::StreamReader;
+use crate::value::Value;
+use std::io::{BufRead, BufWriter, Write};
+
+/// Documents read before they are transformed and written
+const DEFAULT_BATCH_SIZE: usize = 1024;
+
+/// One step, turning a document into any number of documents
+type Stage<'a> = Box<dyn Fn(Value) -> Result<Vec<Value>> + Send + Sync + 'a>;
+
+/// Counts from a [`Pipeline::run`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// Documents read
+    pub read: usize,
+    /// Documents written
+    pub written: usize,
+    /// Documents that failed to parse or transform and were skipped
+    pub skipped: usize,
+}
+
+/// A chain of transforms from an NDJSON reader to an NDJSON writer
+pub struct Pipeline<'a> {
+    stages: Vec<Stage<'a>>,
+    threads: usize,
+    batch_size: usize,
+    skip_errors: bool,
+}
+
+impl Default for Pipeline<'_> {
+    fn default() -> Self {
+        Pipeline::new()
+    }
+}
+
+impl<'a> Pipeline<'a> {
+    /// A pipeline that copies documents unchanged
+    pub fn new() -> Self {
+        Pipeline {
+            stages: Vec::new(),
+            threads: 1,
+            batch_size: DEFAULT_BATCH_SIZE,
+            skip_errors: false,
+        }
+    }
+
+    /// Keep only documents for which `keep` returns true
+    pub fn filter(mut self, keep: impl Fn(&Value) -> bool + Send + Sync + 'a) -> Self {
+        self.stages.push(Box::new(move |value| {
+            Ok(if keep(&value) {
+                vec![value]
+            } else {
+                Vec::new()
+            })
+        }));
+        self
+    }
+
+    /// Replace each document with the result of `f`
+    pub fn map(mut self, f: impl Fn(Value) -> Result<Value> + Send + Sync + 'a) -> Self {
+        self.stages
+            .push(Box::new(move |value| f(value).map(|value| vec![value])));
+        self
+    }
+
+    /// Replace each document with any number of documents
+    pub fn flat_map(mut self, f: impl Fn(Value) -> Result<Vec<Value>> + Send + Sync + 'a) -> Self {
+        self.stages.push(Box::new(f));
+        self
+    }
+
+    /// Keep only documents where the JSONPath matches something
+    pub fn filter_path(self, path: &str) -> Result<Self> {
+        check_path(path)?;
+        let path = path.to_string();
+        Ok(self.filter(move |value| query(value, &path).is_ok_and(|m| !m.is_empty())))
+    }
+
+    /// Replace each document with the values the JSONPath matches in it,
+    /// each written as a document of its own
+    pub fn select(self, path: &str) -> Result<Self> {
+        check_path(path)?;
+        let path = path.to_string();
+        Ok(self.flat_map(move |value| Ok(query(&value, &path)?.into_iter().cloned().collect())))
+    }
+
+    /// Transform each batch on this many threads
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Read this many documents at a time; memory use grows with it
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Skip documents that fail to parse or transform, counting them in
+    /// [`PipelineStats::skipped`], rather than stopping at the first
+    pub fn skip_errors(mut self, skip: bool) -> Self {
+        self.skip_errors = skip;
+        self
+    }
+
+    /// Read documents from `reader` until it ends, writing the results to
+    /// `writer` one per line.
+    ///
+    /// I/O errors always stop the run; the documents before them have been
+    /// written.
+    pub fn run<R: BufRead, W: Write>(&self, reader: R, writer: W) -> Result<PipelineStats> {
+        let mut writer = BufWriter::new(writer);
+        let mut stats = PipelineStats::default();
+        let mut input = StreamReader::new(reader);
+        loop {
+            let mut batch = Vec::with_capacity(self.batch_size);
+            for value in input.by_ref().take(self.batch_size) {
+                batch.push(value);
+            }
+            if batch.is_empty() {
+                break;
+            }
+            stats.read += batch.len();
+            for result in self.transform(batch) {
+                match result {
+                    Ok(values) => {
+                        for value in values {
+                            writer.write_all(to_string(&value)?.as_bytes())?;
+                            writer.write_all(b"\n")?;
+                            stats.written += 1;
+                        }
+                    }
+                    Err(_) if self.skip_errors => stats.skipped += 1,
+                    Err(e) => {
+                        writer.flush()?;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(stats)
+    }
+
+    /// Run the stages over a batch, in order
+    fn transform(&self, batch: Vec<Result<Value>>) -> Vec<Result<Vec<Value>>> {
+        if self.threads == 1 || batch.len() == 1 {
+            return batch.into_iter().map(|v| self.apply(v)).collect();
+        }
+        let chunk_size = batch.len().div_ceil(self.threads);
+        let mut chunks = Vec::new();
+        let mut batch = batch.into_iter();
+        loop {
+            let chunk: Vec<_> = batch.by_ref().take(chunk_size).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            chunks.push(chunk);
+        }
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = chunks
+                .into_iter()
+                .map(|chunk| {
+                    scope
+                        .spawn(move || chunk.into_iter().map(|v| self.apply(v)).collect::<Vec<_>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("pipeline stage panicked"))
+                .collect()
+        })
+    }
+
+    fn apply(&self, value: Result<Value>) -> Result<Vec<Value>> {
+        let mut values = vec![value?];
+        for stage in &self.stages {
+            let mut next = Vec::with_capacity(values.len());
+            for value in values {
+                next.extend(stage(value)?);
+            }
+            values = next;
+        }
+        Ok(values)
+    }
+}
+
+/// Reject a malformed JSONPath before any input is read
+fn check_path(path: &str) -> Result<()> {
+    query(&Value::Null, path).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    fn run(pipeline: &Pipeline, input: &str) -> (Result<PipelineStats>, String) {
+        let mut output = Vec::new();
+        let stats = pipeline.run(input.as_bytes(), &mut output);
+        (stats, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn test_stages() {
+        let pipeline = Pipeline::new()
+            .filter_path("$.tags[0]")
+            .unwrap()
+            .map(|mut value| {
+                if let Value::Object(map) = &mut value {
+                    map.remove("tags");
+                }
+                Ok(value)
+            })
+            .flat_map(|value| Ok(vec![value.clone(), value]));
+        let (stats, output) = run(
+            &pipeline,
+            "{ id: 1, tags: ['a'] }\n{ id: 2, tags: [] }\n{ id: 3n, tags: ['b'] }",
+        );
+        assert_eq!(output, "{id: 1}\n{id: 1}\n{id: 3n}\n{id: 3n}\n");
+        assert_eq!(
+            stats.unwrap(),
+            PipelineStats {
+                read: 3,
+                written: 4,
+                skipped: 0
+            }
+        );
+        assert!(Pipeline::new().select("items").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = "1\n{ a: }\n2\n";
+        let (result, output) = run(&Pipeline::new(), input);
+        assert!(matches!(result, Err(Error::ParseError { .. })));
+        assert_eq!(output, "1\n");
+
+        let (stats, output) = run(&Pipeline::new().skip_errors(true), input);
+        assert_eq!(stats.unwrap().skipped, 1);
+        assert_eq!(output, "1\n2\n");
+
+        let failing = Pipeline::new().map(|value| match value {
+            Value::Integer(n) if n > 1 => Err(Error::Custom("too big".to_string())),
+            value => Ok(value),
+        });
+        assert!(run(&failing, "1 2 3").0.is_err());
+    }
+
+    #[test]
+    fn test_threads_keep_order() {
+        let input: String = (0..1000).map(|i| format!("{}\n", i)).collect();
+        let pipeline = Pipeline::new()
+            .threads(4)
+            .batch_size(64)
+            .filter(|value| value.as_f64().is_some_and(|n| (n as u32).is_multiple_of(3)))
+            .map(|value| Ok(Value::Number(value.as_f64().unwrap() * 2.0)));
+        let (stats, output) = run(&pipeline, &input);
+        let expected: String = (0..1000)
+            .filter(|i| i % 3 == 0)
+            .map(|i| format!("{}\n", i * 2))
+            .collect();
+        assert_eq!(output, expected);
+        assert_eq!(stats.unwrap().written, 334);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::value::Value;
+use std::borrow::Cow;
+
+/// Escape a single JSON Pointer reference token (RFC 6901)
+pub(crate) fn escape_token(token: &str) -> Cow<'_, str> {
+    if token.contains(['~', '/']) {
+        Cow::Owned(token.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// Append a reference token to a JSON Pointer
+pub(crate) fn push(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, escape_token(token))
+}
+
+/// Append an array index to a JSON Pointer
+pub(crate) fn push_index(pointer: &str, index: usize) -> String {
+    format!("{}/{}", pointer, index)
+}
+
+/// Undo [`escape_token`]
+pub(crate) fn unescape_token(token: &str) -> Cow<'_, str> {
+    if token.contains('~') {
+        Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/// Look up the value a JSON Pointer refers to
+pub(crate) fn lookup<'a>(value: &'a Value, pointer: &str) -> Option<&'a Value> {
+    if pointer.is_empty() {
+        return Some(value);
+    }
+    let mut current = value;
+    for token in pointer.strip_prefix('/')?.split('/') {
+        let token = unescape_token(token);
+        current = match current {
+            Value::Object(map) => map.get(token.as_ref())?,
+            Value::Array(items) => items.get(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Mutable counterpart of [`lookup`]
+#[cfg(feature = "encrypt")]
+pub(crate) fn lookup_mut<'a>(value: &'a mut Value, pointer: &str) -> Option<&'a mut Value> {
+    if pointer.is_empty() {
+        return Some(value);
+    }
+    let mut current = value;
+    for token in pointer.strip_prefix('/')?.split('/') {
+        let token = unescape_token(token);
+        current = match current {
+            Value::Object(map) => map.get_mut(token.as_ref())?,
+            Value::Array(items) => items.get_mut(token.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_token() {
+        assert_eq!(escape_token("plain"), "plain");
+        assert_eq!(escape_token("a/b~c"), "a~1b~0c");
+        assert_eq!(push("/users", "x/y"), "/users/x~1y");
+        assert_eq!(push_index("", 3), "/3");
+        assert_eq!(unescape_token("a~1b~0c"), "a/b~c");
+    }
+
+    #[test]
+    fn test_lookup() {
+        let value = crate::parse("{ 'a/b': [1, { c: true }] }").unwrap();
+        assert_eq!(lookup(&value, ""), Some(&value));
+        assert_eq!(lookup(&value, "/a~1b/1/c"), Some(&Value::Bool(true)));
+        assert_eq!(lookup(&value, "/a~1b/2"), None);
+        assert_eq!(lookup(&value, "a~1b"), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! polars `DataFrame` conversion
+//!
+//! An array of objects becomes one row per object and one column per key:
+//!
+//! | kJSON | polars |
+//! |-------|--------|
+//! | Boolean | `Boolean` |
+//! | Number | `Float64` |
+//! | Integer | `Int64`, or `Float64` mixed with other numbers |
+//! | String, UUID | `String` |
+//! | Decimal128 | `Decimal(38, s)` |
+//! | BigInt | `Int64`, or `Decimal(38, 0)` when wider |
+//! | Instant, Date | `Datetime(ns)` in UTC |
+//! | Duration | `Duration(ns)` |
+//! | Binary | `Binary` |
+//!
+//! Columns that mix types, hold nested arrays or objects, or overflow 38
+//! digits become `String` columns of kJSON text. UUIDs and text columns read
+//! back as strings.
+//!
+//! ```no_run
+//! use polars::prelude::DataFrame;
+//!
+//! let rows = kjson::parse("[{ id: 1, price: 9.99m }, { id: 2, price: 12.50m }]").unwrap();
+//! let df = DataFrame::try_from(&rows).unwrap();
+//! let back = kjson::Value::from_dataframe(&df).unwrap();
+//! ```
+
+use crate::columns::{column_kind, integer, split_rows, unscaled_decimals, Kind};
+use crate::error::{Error, Result};
+use crate::serializer::to_string;
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use ::polars::prelude::{
+    Column, DataFrame, DataType, Int128Chunked, Int64Chunked, IntoColumn, IntoSeries, NamedFrom,
+    NewChunkedArray, PolarsError, Series, TimeUnit,
+};
+
+impl From<PolarsError> for Error {
+    fn from(e: PolarsError) -> Self {
+        Error::Conversion(e.to_string())
+    }
+}
+
+/// Convert an array of objects to a polars `DataFrame`
+pub fn to_dataframe(value: &Value) -> Result<DataFrame> {
+    let (row_count, cells) = split_rows(value, "DataFrame")?;
+    let columns = cells
+        .into_iter()
+        .map(|(name, cells)| build_series(name, &cells).map(IntoColumn::into_column))
+        .collect::<Result<Vec<Column>>>()?;
+    Ok(DataFrame::new_with_height(row_count, columns)?)
+}
+
+/// Convert a polars `DataFrame` to an array of objects
+pub fn from_dataframe(df: &DataFrame) -> Result<Value> {
+    let mut rows = vec![Map::with_capacity(df.width()); df.height()];
+    for column in df.get_columns() {
+        let values = read_series(column.as_materialized_series())?;
+        for (row, value) in rows.iter_mut().zip(values) {
+            row.insert(Str::from(column.name().as_str()), value);
+        }
+    }
+    Ok(Value::Array(rows.into_iter().map(Value::Object).collect()))
+}
+
+impl TryFrom<&Value> for DataFrame {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        to_dataframe(value)
+    }
+}
+
+impl Value {
+    /// Convert a polars `DataFrame` to an array of objects
+    pub fn from_dataframe(df: &DataFrame) -> Result<Value> {
+        from_dataframe(df)
+    }
+}
+
+fn build_series(name: &str, cells: &[&Value]) -> Result<Series> {
+    let kind = match column_kind(cells) {
+        Some(kind) => kind,
+        None => return Ok(Series::full_null(name.into(), cells.len(), &DataType::Null)),
+    };
+
+    Ok(match kind {
+        Kind::Bool => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_bool()).collect::<Vec<_>>(),
+        ),
+        Kind::Number => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_f64()).collect::<Vec<_>>(),
+        ),
+        Kind::Integer => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_i64()).collect::<Vec<_>>(),
+        ),
+        Kind::UInteger => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_u64()).collect::<Vec<_>>(),
+        ),
+        Kind::String => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_str()).collect::<Vec<_>>(),
+        ),
+        Kind::Uuid => Series::new(
+            name.into(),
+            cells
+                .iter()
+                .map(|v| v.as_uuid().map(|u| u.to_string()))
+                .collect::<Vec<_>>(),
+        ),
+        Kind::Decimal => {
+            let (scale, unscaled, digits) = unscaled_decimals(cells);
+            if digits > 38 || scale > 38 {
+                return text_series(name, cells);
+            }
+            let values: Vec<Option<i128>> = unscaled
+                .iter()
+                .map(|s| s.as_ref().map(|s| s.parse().unwrap()))
+                .collect();
+            Int128Chunked::from_slice_options(name.into(), &values)
+                .into_decimal_unchecked(Some(38), scale as usize)
+                .into_series()
+        }
+        Kind::BigInt => {
+            let narrow: Option<Vec<Option<i64>>> = cells
+                .iter()
+                .map(|v| v.as_bigint().map_or(Some(None), |b| b.to_i64().map(Some)))
+                .collect();
+            let wide: Option<Vec<Option<i128>>> = cells
+                .iter()
+                .map(|v| match v.as_bigint() {
+                    Some(b) if b.to_string().trim_start_matches('-').len() <= 38 => {
+                        b.to_i128().map(Some)
+                    }
+                    Some(_) => None,
+                    None => Some(None),
+                })
+                .collect();
+            match (narrow, wide) {
+                (Some(values), _) => Series::new(name.into(), values),
+                (None, Some(values)) => Int128Chunked::from_slice_options(name.into(), &values)
+                    .into_decimal_unchecked(Some(38), 0)
+                    .into_series(),
+                (None, None) => return text_series(name, cells),
+            }
+        }
+        Kind::Timestamp => {
+            let values: Vec<Option<i64>> = cells
+                .iter()
+                .map(|v| match v {
+                    Value::Instant(i) => Some(i.epoch_nanos()),
+                    Value::Date(d) => d.utc.timestamp_nanos_opt(),
+                    _ => None,
+                })
+                .collect();
+            Int64Chunked::from_slice_options(name.into(), &values)
+                .into_datetime(TimeUnit::Nanoseconds, None)
+                .into_series()
+        }
+        Kind::Duration => {
+            let values: Vec<Option<i64>> = cells
+                .iter()
+                .map(|v| v.as_duration().map(|d| d.total_nanos()))
+                .collect();
+            Int64Chunked::from_slice_options(name.into(), &values)
+                .into_duration(TimeUnit::Nanoseconds)
+                .into_series()
+        }
+        Kind::Binary => Series::new(
+            name.into(),
+            cells.iter().map(|v| v.as_bytes()).collect::<Vec<_>>(),
+        ),
+        Kind::Nested => return text_series(name, cells),
+    })
+}
+
+fn text_series(name: &str, cells: &[&Value]) -> Result<Series> {
+    let texts = cells
+        .iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            v => to_string(v).map(Some),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Series::new(name.into(), texts))
+}
+
+fn read_series(series: &Series) -> Result<Vec<Value>> {
+    let or_null = |v: Option<Value>| v.unwrap_or(Value::Null);
+    Ok(match series.dtype() {
+        DataType::Null => vec![Value::Null; series.len()],
+        DataType::Boolean => series
+            .bool()?
+            .iter()
+            .map(|b| or_null(b.map(Value::Bool)))
+            .collect(),
+        DataType::Int64 => series
+            .i64()?
+            .iter()
+            .map(|n| or_null(n.map(|n| integer(n as i128))))
+            .collect(),
+        DataType::UInt64 => series
+            .u64()?
+            .iter()
+            .map(|n| or_null(n.map(|n| integer(n as i128))))
+            .collect(),
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32 => series
+            .cast(&DataType::Int64)?
+            .i64()?
+            .iter()
+            .map(|n| or_null(n.map(Value::Integer)))
+            .collect(),
+        DataType::Float32 | DataType::Float64 => series
+            .cast(&DataType::Float64)?
+            .f64()?
+            .iter()
+            .map(|n| or_null(n.map(Value::Number)))
+            .collect(),
+        DataType::String => series
+            .str()?
+            .iter()
+            .map(|s| or_null(s.map(|s| Value::string(s.to_string()))))
+            .collect(),
+        DataType::Binary => series
+            .binary()?
+            .iter()
+            .map(|b| or_null(b.map(|b| Value::Binary(b.to_vec()))))
+            .collect(),
+        DataType::Decimal(_, _) => {
+            let decimals = series.decimal()?;
+            let exponent = -(decimals.scale() as i32);
+            decimals
+                .physical()
+                .iter()
+                .map(|n| {
+                    or_null(n.map(|n| {
+                        let coefficient = BigInt::from_i128(n);
+                        Value::Decimal128(Decimal128::from_parts(&coefficient, exponent))
+                    }))
+                })
+                .collect()
+        }
+        DataType::Datetime(unit, _) => {
+            let scale = nanos_per_unit(*unit);
+            series
+                .datetime()?
+                .physical()
+                .iter()
+                .map(|n| or_null(n.map(|n| Value::Instant(Instant::from_nanos(n * scale)))))
+                .collect()
+        }
+        DataType::Duration(unit) => {
+            let scale = nanos_per_unit(*unit);
+            series
+                .duration()?
+                .physical()
+                .iter()
+                .map(|n| or_null(n.map(|n| Value::Duration(Duration::from_nanos(n * scale)))))
+                .collect()
+        }
+        other => {
+            return Err(Error::Conversion(format!(
+                "polars column {} has unsupported type {}",
+                series.name(),
+                other
+            )))
+        }
+    })
+}
+
+fn nanos_per_unit(unit: TimeUnit) -> i64 {
+    match unit {
+        TimeUnit::Nanoseconds => 1,
+        TimeUnit::Microseconds => 1_000,
+        TimeUnit::Milliseconds => 1_000_000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_roundtrip() {
+        let value = parse(
+            r#"[
+                { name: "widget", price: 19.99m, stock: 12, total: 9007199254740993, ttl: PT1H, active: true, key: b64'AQI=' },
+                { name: "gadget", price: 5.50m, stock: 3.5, total: -1, ttl: PT0S, active: null, key: null },
+            ]"#,
+        )
+        .unwrap();
+        let df = DataFrame::try_from(&value).unwrap();
+        assert_eq!(df.shape(), (2, 7));
+        assert_eq!(
+            df.column("price").unwrap().dtype(),
+            &DataType::Decimal(Some(38), Some(2))
+        );
+        assert_eq!(df.column("total").unwrap().dtype(), &DataType::Int64);
+        assert_eq!(df.column("key").unwrap().dtype(), &DataType::Binary);
+        assert_eq!(Value::from_dataframe(&df).unwrap(), value);
+    }
+
+    #[test]
+    fn test_temporal_and_text_columns() {
+        let value = parse(
+            r#"[
+                { at: 2025-01-10T12:00:00.123456789Z, id: 550e8400-e29b-41d4-a716-446655440000, tags: ["a"] },
+                { at: null, id: null, tags: { b: 1 } },
+            ]"#,
+        )
+        .unwrap();
+        let df = to_dataframe(&value).unwrap();
+        assert_eq!(
+            df.column("at").unwrap().dtype(),
+            &DataType::Datetime(TimeUnit::Nanoseconds, None)
+        );
+
+        let rows = from_dataframe(&df).unwrap();
+        let first = rows.as_array().unwrap()[0].as_object().unwrap();
+        assert_eq!(
+            first["at"],
+            Value::Instant(Instant::from_nanos(1_736_510_400_123_456_789))
+        );
+        assert_eq!(
+            first["id"],
+            Value::String("550e8400-e29b-41d4-a716-446655440000".into())
+        );
+        assert_eq!(first["tags"], Value::String("['a']".into()));
+    }
+
+    #[test]
+    fn test_non_tabular_input() {
+        assert!(matches!(
+            to_dataframe(&parse("{ a: 1 }").unwrap()),
+            Err(Error::Conversion(_))
+        ));
+    }
+}
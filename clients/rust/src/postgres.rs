@@ -0,0 +1,214 @@
+//! PostgreSQL `ToSql` / `FromSql` support
+//!
+//! [`Value`] binds to and reads from columns of the kjson extension's `kjson`
+//! type, as well as `text`, `varchar`, `json` and `jsonb`:
+//!
+//! ```no_run
+//! # async fn run(client: tokio_postgres::Client) -> Result<(), Box<dyn std::error::Error>> {
+//! let doc = kjson::parse("{ price: 19.99m }")?;
+//! client.execute("INSERT INTO orders (data) VALUES ($1)", &[&doc]).await?;
+//! let row = client.query_one("SELECT data FROM orders", &[]).await?;
+//! let doc: kjson::Value = row.get(0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! On the wire, `kjson` uses a version byte followed by the payload, like
+//! `jsonb`. Version 1 carries kJSON text; version 2 carries the extension's
+//! internal binary representation, so neither side re-parses text. Values
+//! are sent as version 2 and both versions are read. `json` and `jsonb` get
+//! plain JSON, with extended types written as strings.
+//!
+//! [`to_wire`] and [`from_wire`] produce and read the same payloads
+//! directly, for `COPY ... (FORMAT binary)` streams and other clients that
+//! hand over raw column bytes. [`to_internal`] and [`from_internal`] work on
+//! the internal representation alone, as the extension stores it.
+
+use crate::error::Error;
+use crate::parser::parse;
+use crate::pg_wire;
+use crate::serializer::to_string;
+use crate::value::{kjson_value_to_json_value, Value};
+use bytes::{BufMut, BytesMut};
+use postgres_types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::error::Error as StdError;
+
+pub use crate::pg_wire::{KJSON_TYPE, WIRE_INTERNAL, WIRE_TEXT};
+
+type BoxError = Box<dyn StdError + Sync + Send>;
+
+/// `jsonb` wire format version
+const JSONB_VERSION: u8 = 1;
+
+/// Encode a value as a `kjson` binary wire payload, version byte included.
+///
+/// Values holding binary data are sent as version 1 text, since the
+/// internal representation has no binary type.
+pub fn to_wire(value: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    pg_wire::encode(value, &mut buf);
+    buf
+}
+
+/// Decode a `kjson` binary wire payload of either version
+pub fn from_wire(raw: &[u8]) -> crate::Result<Value> {
+    pg_wire::decode(raw)
+}
+
+/// Encode a value in the extension's internal representation, without a
+/// version byte.
+///
+/// Fails for values holding binary data, which the representation cannot
+/// hold.
+pub fn to_internal(value: &Value) -> crate::Result<Vec<u8>> {
+    if pg_wire::has_binary(value) {
+        return Err(Error::SerializationError(
+            "binary data has no kjson internal representation".to_string(),
+        ));
+    }
+    let mut buf = Vec::new();
+    pg_wire::encode_internal(value, &mut buf);
+    Ok(buf)
+}
+
+/// Decode a value in the extension's internal representation
+pub fn from_internal(data: &[u8]) -> crate::Result<Value> {
+    pg_wire::decode_internal(data)
+}
+
+fn is_kjson(ty: &Type) -> bool {
+    ty.name() == KJSON_TYPE
+}
+
+fn is_supported(ty: &Type) -> bool {
+    is_kjson(ty)
+        || matches!(
+            *ty,
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::UNKNOWN | Type::JSON | Type::JSONB
+        )
+}
+
+impl ToSql for Value {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, BoxError> {
+        match *ty {
+            Type::JSON | Type::JSONB => {
+                if *ty == Type::JSONB {
+                    out.put_u8(JSONB_VERSION);
+                }
+                let json = kjson_value_to_json_value(self.clone())?;
+                serde_json::to_writer(out.writer(), &json)?;
+            }
+            _ if is_kjson(ty) => {
+                let mut buf = Vec::new();
+                pg_wire::encode(self, &mut buf);
+                out.put_slice(&buf);
+            }
+            _ => out.put_slice(to_string(self)?.as_bytes()),
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        is_supported(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for Value {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, BoxError> {
+        if is_kjson(ty) {
+            return Ok(pg_wire::decode(raw)?);
+        }
+        let text = if *ty == Type::JSONB {
+            match raw.split_first() {
+                Some((&JSONB_VERSION, text)) => text,
+                _ => return Err("unsupported jsonb wire format".into()),
+            }
+        } else {
+            raw
+        };
+        Ok(parse(std::str::from_utf8(text)?)?)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        is_supported(ty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres_types::Kind;
+
+    fn kjson_type() -> Type {
+        Type::new(
+            KJSON_TYPE.to_string(),
+            16_385,
+            Kind::Simple,
+            "public".to_string(),
+        )
+    }
+
+    fn encode(value: &Value, ty: &Type) -> BytesMut {
+        let mut buf = BytesMut::new();
+        value.to_sql_checked(ty, &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_kjson_roundtrip() {
+        let ty = kjson_type();
+        let value =
+            parse("{ id: 550e8400-e29b-41d4-a716-446655440000, total: 12n, price: 19.99m }")
+                .unwrap();
+        let buf = encode(&value, &ty);
+        assert_eq!(buf[0], WIRE_INTERNAL);
+        assert_eq!(Value::from_sql(&ty, &buf).unwrap(), value);
+
+        let mut text = vec![WIRE_TEXT];
+        text.extend_from_slice(b"{ total: 12n }");
+        assert_eq!(
+            Value::from_sql(&ty, &text).unwrap(),
+            parse("{ total: 12n }").unwrap()
+        );
+        assert!(Value::from_sql(&ty, &[9, b'1']).is_err());
+        assert!(!<Value as ToSql>::accepts(&Type::INT4));
+    }
+
+    #[test]
+    fn test_raw_payloads() {
+        let value = parse("{ id: 550e8400-e29b-41d4-a716-446655440000, ttl: PT5M, n: [1n, -2n] }")
+            .unwrap();
+        let wire = to_wire(&value);
+        assert_eq!(wire, encode(&value, &kjson_type()));
+        assert_eq!(from_wire(&wire).unwrap(), value);
+
+        let internal = to_internal(&value).unwrap();
+        assert_eq!(internal, wire[1..]);
+        assert_eq!(from_internal(&internal).unwrap(), value);
+
+        let binary = parse("{ data: b64'AQID' }").unwrap();
+        assert_eq!(to_wire(&binary)[0], WIRE_TEXT);
+        assert_eq!(from_wire(&to_wire(&binary)).unwrap(), binary);
+        assert!(to_internal(&binary).is_err());
+    }
+
+    #[test]
+    fn test_text_and_json_columns() {
+        let value = parse("{ price: 19.99m, tags: ['a'] }").unwrap();
+
+        let text = encode(&value, &Type::TEXT);
+        assert_eq!(Value::from_sql(&Type::TEXT, &text).unwrap(), value);
+
+        // JSON columns get plain JSON, so extended types come back as strings
+        let jsonb = encode(&value, &Type::JSONB);
+        assert_eq!(jsonb[0], JSONB_VERSION);
+        assert_eq!(
+            Value::from_sql(&Type::JSONB, &jsonb).unwrap(),
+            parse("{ price: '19.99m', tags: ['a'] }").unwrap()
+        );
+        let json = encode(&value, &Type::JSON);
+        assert_eq!(&json[..], &jsonb[1..]);
+    }
+}
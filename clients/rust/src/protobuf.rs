@@ -0,0 +1,202 @@
+//! `google.protobuf.Struct` conversion
+//!
+//! `Struct` only has JSON's types, so extended types are degraded according
+//! to a [`Degradation`] policy. With [`Degradation::Tagged`], each extended
+//! value becomes a one-field struct `{"$kjson": "<literal>"}` holding its
+//! kJSON literal (e.g. `"19.99m"`), which converts back to the original type.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::value::{Map, Str, Value};
+use crate::warnings;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use prost_types::value::Kind;
+use prost_types::{ListValue, NullValue, Struct};
+use std::collections::BTreeMap;
+
+/// Field name of tagged extended values
+pub const TAG_FIELD: &str = "$kjson";
+
+/// How extended types are represented in a `Struct`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Degradation {
+    /// Their string form, e.g. `"19.99"` or `"2025-01-10T12:00:00Z"`, with
+    /// binary data in base64
+    #[default]
+    String,
+    /// BigInt and Decimal128 as (possibly lossy) numbers, the rest as strings.
+    /// A lossy conversion raises a [`Warning::PrecisionLoss`](crate::warnings::Warning::PrecisionLoss).
+    Number,
+    /// A `{"$kjson": "<literal>"}` struct that converts back losslessly
+    Tagged,
+    /// Refuse to convert
+    Reject,
+}
+
+/// Convert a value to a protobuf `Value`
+pub fn to_proto_value(value: &Value, policy: Degradation) -> Result<prost_types::Value> {
+    let kind = match value {
+        Value::Null => Kind::NullValue(NullValue::NullValue as i32),
+        Value::Bool(b) => Kind::BoolValue(*b),
+        Value::Number(n) => Kind::NumberValue(*n),
+        Value::Integer(n) => Kind::NumberValue(warnings::integer_to_f64(*n)),
+        Value::UInteger(n) => Kind::NumberValue(warnings::unsigned_to_f64(*n)),
+        Value::String(s) => Kind::StringValue(s.to_string()),
+        Value::Array(arr) => Kind::ListValue(ListValue {
+            values: arr
+                .iter()
+                .map(|v| to_proto_value(v, policy))
+                .collect::<Result<_>>()?,
+        }),
+        Value::Object(obj) => Kind::StructValue(object_to_struct(obj, policy)?),
+        extended => degrade(extended, policy)?,
+    };
+    Ok(prost_types::Value { kind: Some(kind) })
+}
+
+/// Convert an object value to a protobuf `Struct`
+pub fn to_struct(value: &Value, policy: Degradation) -> Result<Struct> {
+    match value {
+        Value::Object(obj) => object_to_struct(obj, policy),
+        other => Err(Error::Conversion(format!(
+            "Only objects convert to a protobuf Struct, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+/// Convert a protobuf `Value` to a value, restoring tagged extended types
+pub fn from_proto_value(value: &prost_types::Value) -> Result<Value> {
+    match &value.kind {
+        None | Some(Kind::NullValue(_)) => Ok(Value::Null),
+        Some(Kind::BoolValue(b)) => Ok(Value::Bool(*b)),
+        Some(Kind::NumberValue(n)) => Ok(Value::Number(*n)),
+        Some(Kind::StringValue(s)) => Ok(Value::string(s.clone())),
+        Some(Kind::ListValue(list)) => Ok(Value::Array(
+            list.values
+                .iter()
+                .map(from_proto_value)
+                .collect::<Result<_>>()?,
+        )),
+        Some(Kind::StructValue(s)) => from_struct(s),
+    }
+}
+
+/// Convert a protobuf `Struct` to an object value, restoring tagged extended types
+pub fn from_struct(s: &Struct) -> Result<Value> {
+    if let Some(literal) = tagged_literal(s) {
+        return parse(literal);
+    }
+    let mut map = Map::with_capacity(s.fields.len());
+    for (k, v) in &s.fields {
+        map.insert(Str::from(k.as_str()), from_proto_value(v)?);
+    }
+    Ok(Value::Object(map))
+}
+
+fn object_to_struct(obj: &Map, policy: Degradation) -> Result<Struct> {
+    let fields = obj
+        .iter()
+        .map(|(k, v)| Ok((k.to_string(), to_proto_value(v, policy)?)))
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    Ok(Struct { fields })
+}
+
+fn degrade(value: &Value, policy: Degradation) -> Result<Kind> {
+    match policy {
+        Degradation::String => Ok(Kind::StringValue(plain_string(value))),
+        Degradation::Number => Ok(match value {
+            Value::BigInt(b) => Kind::NumberValue(warnings::bigint_to_f64(b)),
+            Value::Decimal128(d) => Kind::NumberValue(warnings::decimal_to_f64(d)),
+            other => Kind::StringValue(plain_string(other)),
+        }),
+        Degradation::Tagged => {
+            let literal = prost_types::Value {
+                kind: Some(Kind::StringValue(to_string(value)?)),
+            };
+            Ok(Kind::StructValue(Struct {
+                fields: BTreeMap::from([(TAG_FIELD.to_string(), literal)]),
+            }))
+        }
+        Degradation::Reject => Err(Error::Conversion(format!(
+            "{} has no protobuf Struct equivalent",
+            value.type_name()
+        ))),
+    }
+}
+
+/// String form of an extended value, without kJSON type suffixes
+fn plain_string(value: &Value) -> String {
+    match value {
+        Value::BigInt(b) => b.to_string(),
+        Value::Decimal128(d) => d.to_string(),
+        Value::Uuid(u) => u.to_string(),
+        Value::Date(d) => d.to_iso8601(),
+        Value::Instant(i) => i.to_iso8601(),
+        Value::Duration(d) => d.to_iso8601(),
+        Value::Binary(bytes) => STANDARD.encode(bytes),
+        other => unreachable!("{} is not an extended type", other.type_name()),
+    }
+}
+
+fn tagged_literal(s: &Struct) -> Option<&str> {
+    if s.fields.len() != 1 {
+        return None;
+    }
+    match s.fields.get(TAG_FIELD)?.kind.as_ref()? {
+        Kind::StringValue(literal) => Some(literal),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"{
+        id: 550e8400-e29b-41d4-a716-446655440000,
+        total: 123456789012345678901234567890n,
+        price: 19.99m,
+        ttl: PT1H30M,
+        items: [1, "two", null, { ok: true }],
+    }"#;
+
+    fn field<'a>(s: &'a Struct, name: &str) -> &'a Kind {
+        s.fields[name].kind.as_ref().unwrap()
+    }
+
+    #[test]
+    fn test_tagged_roundtrip() {
+        let value = parse(DOC).unwrap();
+        let s = to_struct(&value, Degradation::Tagged).unwrap();
+        match field(&s, "price") {
+            Kind::StructValue(tagged) => assert_eq!(tagged_literal(tagged), Some("19.99m")),
+            other => panic!("expected tagged struct, got {:?}", other),
+        }
+        assert_eq!(from_struct(&s).unwrap(), value);
+    }
+
+    #[test]
+    fn test_degradation_policies() {
+        let value = parse(DOC).unwrap();
+
+        let s = to_struct(&value, Degradation::String).unwrap();
+        assert_eq!(field(&s, "price"), &Kind::StringValue("19.99".to_string()));
+        assert_eq!(field(&s, "ttl"), &Kind::StringValue("PT1H30M".to_string()));
+
+        let s = to_struct(&value, Degradation::Number).unwrap();
+        assert_eq!(field(&s, "price"), &Kind::NumberValue(19.99));
+        assert_eq!(
+            field(&s, "id"),
+            &Kind::StringValue("550e8400-e29b-41d4-a716-446655440000".to_string())
+        );
+
+        assert!(matches!(
+            to_struct(&value, Degradation::Reject),
+            Err(Error::Conversion(_))
+        ));
+        assert!(to_struct(&parse("{ a: [1, true] }").unwrap(), Degradation::Reject).is_ok());
+    }
+}
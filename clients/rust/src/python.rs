@@ -0,0 +1,410 @@
+//! Python bindings
+//!
+//! Exports a `kjson` module to Python through pyo3, with `loads`, `dumps`
+//! and a `Value` class, converting values as follows:
+//!
+//! | kJSON | Python |
+//! |-------|--------|
+//! | null | `None` |
+//! | Boolean, String | `bool`, `str` |
+//! | Number | `int` when whole and within ±2^53, otherwise `float` |
+//! | Integer, UInteger | `int` |
+//! | Array, Object | `list` (and `tuple` on input), `dict` |
+//! | BigInt | `int` |
+//! | Decimal128 | `decimal.Decimal` |
+//! | UUID | `uuid.UUID` |
+//! | Instant, Date | aware `datetime.datetime` (microsecond precision) |
+//! | Duration | `datetime.timedelta` (microsecond precision) |
+//! | Binary | `bytes` (and `bytearray` on input) |
+//!
+//! On input, `int`s become Integers, UIntegers above the i64 range, or
+//! BigInts beyond the u64 range, naive datetimes are taken as UTC, and a
+//! `Value` is used as is. Since Python has a single integer type, `7n` comes
+//! back from `loads` as `7` and is written by `dumps` as an Integer;
+//! `Value.parse` keeps the document as kJSON values, so it serializes back
+//! exactly:
+//!
+//! ```python
+//! import kjson
+//!
+//! order = kjson.loads("{ id: 7n, price: 19.99m, at: 2025-01-10T12:00:00Z }")
+//! order["price"]  # Decimal('19.99')
+//! kjson.dumps(order, pretty=True)
+//! kjson.Value.parse("{ id: 7n }").dumps()  # '{id: 7n}'
+//! ```
+//!
+//! The module is exported from any `cdylib` that depends on kjson with the
+//! `python` feature, so a one-line wrapper crate built with maturin (with
+//! pyo3's `extension-module` feature) is enough.
+
+use crate::error::Error;
+use crate::parser::parse;
+use crate::serializer::{to_string, to_string_pretty};
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple,
+};
+
+/// Largest integer a Number holds exactly, 2^53
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+const NANOS_PER_MICRO: i64 = 1_000;
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        PyValueError::new_err(e.to_string())
+    }
+}
+
+/// Parse kJSON text into Python objects
+#[pyfunction]
+fn loads<'py>(py: Python<'py>, text: &str) -> PyResult<Bound<'py, PyAny>> {
+    to_py(py, &parse(text)?)
+}
+
+/// Serialize Python objects as kJSON, compact unless `pretty` is set
+#[pyfunction]
+#[pyo3(signature = (obj, *, pretty = false))]
+fn dumps(obj: &Bound<'_, PyAny>, pretty: bool) -> PyResult<String> {
+    PyValue::new(obj)?.dumps(pretty)
+}
+
+/// A kJSON value kept as is, without converting to Python objects
+#[pyclass(name = "Value", module = "kjson", frozen, skip_from_py_object)]
+#[derive(Debug, Clone)]
+pub struct PyValue(pub Value);
+
+#[pymethods]
+impl PyValue {
+    /// Convert a Python object
+    #[new]
+    fn new(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(PyValue(from_py(obj)?))
+    }
+
+    /// Parse kJSON text
+    #[staticmethod]
+    fn parse(text: &str) -> PyResult<Self> {
+        Ok(PyValue(parse(text)?))
+    }
+
+    /// Serialize as kJSON, compact unless `pretty` is set
+    #[pyo3(signature = (*, pretty = false))]
+    fn dumps(&self, pretty: bool) -> PyResult<String> {
+        Ok(if pretty {
+            to_string_pretty(&self.0)?
+        } else {
+            to_string(&self.0)?
+        })
+    }
+
+    /// Convert to Python objects
+    fn to_python<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.0)
+    }
+
+    /// kJSON type name, such as `"decimal128"`
+    #[getter]
+    fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+
+    /// The value at a JSON Pointer, or `None`
+    fn pointer(&self, pointer: &str) -> Option<PyValue> {
+        self.0.pointer(pointer).cloned().map(PyValue)
+    }
+
+    fn __getitem__(&self, key: &Bound<'_, PyAny>) -> PyResult<PyValue> {
+        let item = match &self.0 {
+            Value::Object(map) => {
+                let name: String = key.extract()?;
+                map.get(name.as_str())
+                    .ok_or_else(|| PyKeyError::new_err(name))?
+            }
+            Value::Array(items) => {
+                let index: isize = key.extract()?;
+                let i = if index < 0 { index + items.len() as isize } else { index };
+                usize::try_from(i)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .ok_or_else(|| PyIndexError::new_err("index out of range"))?
+            }
+            other => {
+                return Err(PyTypeError::new_err(format!(
+                    "{} is not subscriptable",
+                    other.type_name()
+                )))
+            }
+        };
+        Ok(PyValue(item.clone()))
+    }
+
+    fn __eq__(&self, other: &Bound<'_, PyAny>) -> bool {
+        match other.cast::<PyValue>() {
+            Ok(other) => self.0 == other.get().0,
+            Err(_) => false,
+        }
+    }
+
+    fn __str__(&self) -> PyResult<String> {
+        self.dumps(false)
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Value({})", self.dumps(false)?))
+    }
+}
+
+/// The `kjson` Python module
+#[pymodule]
+#[pyo3(name = "kjson")]
+fn kjson_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(loads, m)?)?;
+    m.add_function(wrap_pyfunction!(dumps, m)?)?;
+    m.add_class::<PyValue>()?;
+    Ok(())
+}
+
+/// Convert a value to Python objects
+pub fn to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+            (*n as i64).into_pyobject(py)?.into_any()
+        }
+        Value::Number(n) => PyFloat::new(py, *n).into_any(),
+        Value::Integer(n) => n.into_pyobject(py)?.into_any(),
+        Value::UInteger(n) => n.into_pyobject(py)?.into_any(),
+        Value::String(s) => PyString::new(py, s).into_any(),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| to_py(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key.as_str(), to_py(py, item)?)?;
+            }
+            dict.into_any()
+        }
+        Value::BigInt(b) => py.get_type::<PyInt>().call1((b.to_string(),))?,
+        Value::Decimal128(d) => py
+            .import("decimal")?
+            .getattr("Decimal")?
+            .call1((d.to_string(),))?,
+        Value::Uuid(u) => py.import("uuid")?.getattr("UUID")?.call1((u.to_string(),))?,
+        Value::Instant(i) => datetime(py, i.epoch_nanos(), 0)?,
+        Value::Date(d) => datetime(
+            py,
+            d.utc.timestamp_nanos_opt().unwrap_or_else(|| d.utc.timestamp() * 1_000_000_000),
+            d.tz_offset.unwrap_or(0),
+        )?,
+        Value::Duration(d) => timedelta(py, d.total_nanos())?,
+        Value::Binary(bytes) => PyBytes::new(py, bytes).into_any(),
+    })
+}
+
+/// Convert Python objects to a value
+pub fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let py = obj.py();
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    if let Ok(value) = obj.cast::<PyValue>() {
+        return Ok(value.get().0.clone());
+    }
+    // bool is a subclass of int, so it must be checked first
+    if let Ok(b) = obj.cast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if obj.is_instance_of::<PyInt>() {
+        if let Ok(n) = obj.extract::<i64>() {
+            return Ok(Value::Integer(n));
+        }
+        return Ok(match obj.extract::<u64>() {
+            Ok(n) => Value::UInteger(n),
+            Err(_) => Value::BigInt(BigInt::from_str(&obj.str()?.to_cow()?)?),
+        });
+    }
+    if let Ok(f) = obj.cast::<PyFloat>() {
+        return Ok(Value::Number(f.value()));
+    }
+    if let Ok(s) = obj.cast::<PyString>() {
+        return Ok(Value::string(s.to_cow()?.as_ref()));
+    }
+    if let Ok(bytes) = obj.cast::<PyBytes>() {
+        return Ok(Value::Binary(bytes.as_bytes().to_vec()));
+    }
+    if let Ok(bytes) = obj.cast::<PyByteArray>() {
+        return Ok(Value::Binary(bytes.to_vec()));
+    }
+    if obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>() {
+        return Ok(Value::Array(
+            obj.try_iter()?
+                .map(|item| from_py(&item?))
+                .collect::<PyResult<_>>()?,
+        ));
+    }
+    if let Ok(dict) = obj.cast::<PyDict>() {
+        let mut map = Map::with_capacity(dict.len());
+        for (key, item) in dict.iter() {
+            let key = key.cast::<PyString>().map_err(|_| {
+                PyTypeError::new_err("kJSON object keys must be strings")
+            })?;
+            map.insert(Str::from(key.to_cow()?.as_ref()), from_py(&item)?);
+        }
+        return Ok(Value::Object(map));
+    }
+
+    let decimal = py.import("decimal")?.getattr("Decimal")?;
+    if obj.is_instance(&decimal)? {
+        return decimal_from_py(obj);
+    }
+    let uuid = py.import("uuid")?.getattr("UUID")?;
+    if obj.is_instance(&uuid)? {
+        let bytes: [u8; 16] = obj.getattr("bytes")?.extract()?;
+        return Ok(Value::Uuid(uuid::Uuid::from_bytes(bytes)));
+    }
+    let module = py.import("datetime")?;
+    if obj.is_instance(&module.getattr("datetime")?)? {
+        let utc = module.getattr("timezone")?.getattr("utc")?;
+        let aware = if obj.getattr("tzinfo")?.is_none() {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("tzinfo", &utc)?;
+            obj.call_method("replace", (), Some(&kwargs))?
+        } else {
+            obj.clone()
+        };
+        let epoch = module
+            .getattr("datetime")?
+            .call_method1("fromtimestamp", (0, utc))?;
+        let since_epoch = aware.call_method1("__sub__", (epoch,))?;
+        return Ok(Value::Instant(Instant::from_nanos(timedelta_nanos(&since_epoch)?)));
+    }
+    if obj.is_instance(&module.getattr("timedelta")?)? {
+        return Ok(Value::Duration(Duration::from_nanos(timedelta_nanos(obj)?)));
+    }
+
+    Err(PyTypeError::new_err(format!(
+        "cannot convert {} to kJSON",
+        obj.get_type().name()?
+    )))
+}
+
+fn decimal_from_py(obj: &Bound<'_, PyAny>) -> PyResult<Value> {
+    let (sign, digits, exponent): (u8, Vec<u8>, Bound<'_, PyAny>) =
+        obj.call_method0("as_tuple")?.extract()?;
+    let exponent: i32 = exponent
+        .extract()
+        .map_err(|_| PyValueError::new_err(format!("{} is not a finite decimal", obj)))?;
+    let mut coefficient: String = digits.iter().map(|d| char::from(b'0' + d)).collect();
+    if sign == 1 {
+        coefficient.insert(0, '-');
+    }
+    let coefficient = BigInt::from_str(&coefficient)?;
+    Ok(Value::Decimal128(Decimal128::from_parts(&coefficient, exponent)))
+}
+
+/// Aware datetime for nanoseconds since the epoch, in a zone `offset`
+/// minutes east of UTC
+fn datetime(py: Python<'_>, nanos: i64, offset: i16) -> PyResult<Bound<'_, PyAny>> {
+    let module = py.import("datetime")?;
+    let timezone = module.getattr("timezone")?;
+    let epoch = module
+        .getattr("datetime")?
+        .call_method1("fromtimestamp", (0, timezone.getattr("utc")?))?;
+    let instant = epoch.call_method1("__add__", (timedelta(py, nanos)?,))?;
+    if offset == 0 {
+        return Ok(instant);
+    }
+    let zone = timezone.call1((timedelta(py, i64::from(offset) * 60_000_000_000)?,))?;
+    instant.call_method1("astimezone", (zone,))
+}
+
+fn timedelta(py: Python<'_>, nanos: i64) -> PyResult<Bound<'_, PyAny>> {
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("microseconds", nanos.div_euclid(NANOS_PER_MICRO))?;
+    py.import("datetime")?
+        .getattr("timedelta")?
+        .call((), Some(&kwargs))
+}
+
+fn timedelta_nanos(delta: &Bound<'_, PyAny>) -> PyResult<i64> {
+    let days: i64 = delta.getattr("days")?.extract()?;
+    let seconds: i64 = delta.getattr("seconds")?.extract()?;
+    let micros: i64 = delta.getattr("microseconds")?.extract()?;
+    ((days * 86_400 + seconds) * 1_000_000 + micros)
+        .checked_mul(NANOS_PER_MICRO)
+        .ok_or_else(|| PyValueError::new_err("timedelta out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(py: Python<'_>, text: &str) -> Value {
+        from_py(&to_py(py, &parse(text).unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_conversions() {
+        Python::initialize();
+        Python::attach(|py| {
+            let value = parse(
+                "{ id: 550e8400-e29b-41d4-a716-446655440000, price: 19.99m, big: 12345678901234567890n, n: 3, \
+                 x: 1.5, ttl: PT1H30M, at: 2025-01-10T12:00:00.123456Z, blob: b64'AQI=', tags: ['a', null, true] }",
+            )
+            .unwrap();
+            let obj = to_py(py, &value).unwrap();
+            let price = obj.get_item("price").unwrap();
+            assert_eq!(price.get_type().name().unwrap(), "Decimal");
+            assert_eq!(price.str().unwrap().to_cow().unwrap(), "19.99");
+            let big: u64 = obj.get_item("big").unwrap().extract().unwrap();
+            assert_eq!(big, 12345678901234567890);
+            assert!(obj.get_item("n").unwrap().is_instance_of::<PyInt>());
+
+            let back = from_py(&obj).unwrap();
+            let expected = parse(
+                "{ id: 550e8400-e29b-41d4-a716-446655440000, price: 19.99m, big: 12345678901234567890, n: 3, \
+                 x: 1.5, ttl: PT1H30M, blob: b64'AQI=', tags: ['a', null, true] }",
+            )
+            .unwrap();
+            for (key, item) in expected.as_object().unwrap() {
+                assert_eq!(&back.as_object().unwrap()[key], item, "{}", key);
+            }
+            assert_eq!(
+                back.as_object().unwrap()["at"],
+                Value::Instant(Instant::from_nanos(1_736_510_400_123_456_000))
+            );
+
+            assert_eq!(roundtrip(py, "-1.50m"), parse("-1.50m").unwrap());
+            assert_eq!(roundtrip(py, "-PT1S"), parse("-PT1S").unwrap());
+        });
+    }
+
+    #[test]
+    fn test_module_functions() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "kjson").unwrap();
+            kjson_module(&module).unwrap();
+            let obj = module.call_method1("loads", ("{ a: [1, 2.5m] }",)).unwrap();
+            let text: String = module.call_method1("dumps", (obj,)).unwrap().extract().unwrap();
+            assert_eq!(parse(&text).unwrap(), parse("{ a: [1, 2.5m] }").unwrap());
+
+            let value = PyValue::parse("{ id: 7n, list: [1, 2] }").unwrap();
+            assert_eq!(parse(&value.dumps(false).unwrap()).unwrap(), value.0);
+            assert_eq!(value.type_name(), "object");
+            assert_eq!(value.pointer("/list/1").unwrap().0, Value::Number(2.0));
+            assert!(module.call_method1("loads", ("{ broken",)).is_err());
+            assert!(from_py(&py.eval(c"object()", None, None).unwrap()).is_err());
+        });
+    }
+}
@@ -0,0 +1,185 @@
+//! redis-rs `ToRedisArgs` / `FromRedisValue` support
+//!
+//! [`Value`] is written as compact kJSON text, or in the kjson Postgres
+//! extension's binary format when wrapped in [`PgInternal`], and read back
+//! from either:
+//!
+//! ```no_run
+//! # fn run(con: &mut redis::Connection) -> redis::RedisResult<()> {
+//! use kjson::redis::PgInternal;
+//! use redis::Commands;
+//!
+//! let doc = kjson::parse("{ id: 7n, price: 19.99m }").unwrap();
+//! let _: () = con.set("order:1", &doc)?;
+//! let _: () = con.set("order:2", PgInternal(&doc))?;
+//! let doc: kjson::Value = con.get("order:2")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The binary format is the Postgres extension's wire format, not kJSONB: a
+//! version byte followed by the extension's internal representation, or by
+//! kJSON text for documents holding binary data. Neither version byte can start kJSON text, so
+//! reads tell the two apart without configuration.
+//!
+//! [`hset_document`] and [`hget_document`] store an object's top-level
+//! fields as the fields of a hash, so single fields can be read and updated
+//! with `HGET` and `HSET`.
+
+use crate::error::Error;
+use crate::parser::parse;
+use crate::pg_wire::{self, WIRE_INTERNAL, WIRE_TEXT};
+use crate::serializer::to_string;
+use crate::value::{Str, Value};
+use redis::{
+    ConnectionLike, ErrorKind, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs,
+};
+use std::collections::HashMap;
+
+/// A value written in the Postgres extension's binary format rather than
+/// kJSON text
+#[derive(Debug, Clone, Copy)]
+pub struct PgInternal<'a>(pub &'a Value);
+
+impl ToRedisArgs for Value {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_arg(to_string(self).expect("values serialize").as_bytes());
+    }
+}
+
+impl ToRedisArgs for PgInternal<'_> {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        let mut buf = Vec::new();
+        pg_wire::encode(self.0, &mut buf);
+        out.write_arg(&buf);
+    }
+}
+
+impl FromRedisValue for Value {
+    fn from_redis_value(v: &redis::Value) -> RedisResult<Self> {
+        let bytes = match v {
+            redis::Value::BulkString(bytes) => bytes.as_slice(),
+            redis::Value::SimpleString(text) => text.as_bytes(),
+            redis::Value::VerbatimString { text, .. } => text.as_bytes(),
+            other => {
+                return Err(RedisError::from((
+                    ErrorKind::TypeError,
+                    "response is not a kJSON document",
+                    format!("{:?}", other),
+                )))
+            }
+        };
+        decode(bytes).map_err(redis_error)
+    }
+}
+
+fn decode(bytes: &[u8]) -> crate::Result<Value> {
+    match bytes.first() {
+        Some(&WIRE_TEXT | &WIRE_INTERNAL) => pg_wire::decode(bytes),
+        _ => parse(
+            std::str::from_utf8(bytes)
+                .map_err(|e| Error::Conversion(format!("invalid UTF-8 in kJSON text: {}", e)))?,
+        ),
+    }
+}
+
+fn redis_error(e: Error) -> RedisError {
+    RedisError::from((
+        ErrorKind::TypeError,
+        "invalid kJSON document",
+        e.to_string(),
+    ))
+}
+
+/// Replace the hash at `key` with the top-level fields of `doc`, each
+/// stored as compact kJSON text.
+///
+/// Fails with a type error if `doc` is not an object.
+pub fn hset_document<C: ConnectionLike>(con: &mut C, key: &str, doc: &Value) -> RedisResult<()> {
+    let map = doc.as_object().ok_or_else(|| {
+        redis_error(Error::TypeMismatch {
+            expected: "object".to_string(),
+            actual: doc.type_name().to_string(),
+        })
+    })?;
+    let fields: Vec<(&str, &Value)> = map.iter().map(|(k, v)| (k.as_str(), v)).collect();
+    let mut pipe = redis::pipe();
+    pipe.atomic().del(key).ignore();
+    if !fields.is_empty() {
+        pipe.hset_multiple(key, &fields).ignore();
+    }
+    pipe.query(con)
+}
+
+/// Read a document stored by [`hset_document`]; `None` if `key` does not
+/// exist
+pub fn hget_document<C: ConnectionLike>(con: &mut C, key: &str) -> RedisResult<Option<Value>> {
+    let fields: HashMap<String, Value> = redis::cmd("HGETALL").arg(key).query(con)?;
+    Ok(from_fields(fields))
+}
+
+fn from_fields(fields: HashMap<String, Value>) -> Option<Value> {
+    if fields.is_empty() {
+        return None;
+    }
+    Some(Value::Object(
+        fields.into_iter().map(|(k, v)| (Str::from(k), v)).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(args: Vec<Vec<u8>>) -> Value {
+        let [arg] = <[Vec<u8>; 1]>::try_from(args).unwrap();
+        Value::from_redis_value(&redis::Value::BulkString(arg)).unwrap()
+    }
+
+    #[test]
+    fn test_text_and_pg_internal() {
+        let doc = parse("{ id: 7n, price: 19.99m, tags: ['a'], blob: b64'AQI=' }").unwrap();
+        let text = doc.to_redis_args();
+        assert_eq!(parse(std::str::from_utf8(&text[0]).unwrap()).unwrap(), doc);
+        assert_eq!(roundtrip(text), doc);
+
+        let plain = parse("{ id: 7n, price: 19.99m, tags: ['a'] }").unwrap();
+        let binary = PgInternal(&plain).to_redis_args();
+        assert_eq!(binary[0][0], WIRE_INTERNAL);
+        assert_eq!(roundtrip(binary), plain);
+        // Binary data falls back to kJSON text inside the wire format
+        assert_eq!(roundtrip(PgInternal(&doc).to_redis_args()), doc);
+    }
+
+    #[test]
+    fn test_from_redis_value_errors() {
+        assert!(Value::from_redis_value(&redis::Value::Nil).is_err());
+        assert!(Value::from_redis_value(&redis::Value::BulkString(b"{ broken".to_vec())).is_err());
+        assert_eq!(
+            Option::<Value>::from_redis_value(&redis::Value::Nil).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hash_fields() {
+        let reply = redis::Value::Array(vec![
+            redis::Value::BulkString(b"id".to_vec()),
+            redis::Value::BulkString(b"7n".to_vec()),
+            redis::Value::BulkString(b"tags".to_vec()),
+            redis::Value::BulkString(b"['a', 'b']".to_vec()),
+        ]);
+        let fields = HashMap::<String, Value>::from_redis_value(&reply).unwrap();
+        assert_eq!(
+            from_fields(fields),
+            Some(parse("{ id: 7n, tags: ['a', 'b'] }").unwrap())
+        );
+        assert_eq!(from_fields(HashMap::new()), None);
+    }
+}
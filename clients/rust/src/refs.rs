@@ -0,0 +1,181 @@
+//! Intra-document `$ref` resolution
+//!
+//! An object of the form `{ $ref: '#/definitions/address' }` refers to
+//! another part of the same document by JSON Pointer. [`resolve_refs`]
+//! replaces every such object with a copy of its target:
+//!
+//! ```
+//! let doc = kjson::parse("{
+//!     definitions: { address: { city: 'Paris' } },
+//!     home: { $ref: '#/definitions/address' },
+//!     work: { $ref: '#/definitions/address', floor: 3 },
+//! }")?;
+//! let resolved = kjson::refs::resolve_refs(&doc)?;
+//! assert_eq!(
+//!     resolved.as_object().unwrap()["work"],
+//!     kjson::parse("{ city: 'Paris', floor: 3 }")?,
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Keys next to `$ref` are deep-merged over the target. References to other
+//! documents (anything not starting with `#`) are left as they are. A target
+//! that refers back to itself cannot be expanded and is an
+//! [`Error::InvalidReference`], as is a pointer that matches nothing.
+//!
+//! [`Refs`] resolves targets on demand and hands out shared [`Arc`] nodes,
+//! so a target referenced many times is expanded and stored once.
+
+use crate::error::{Error, Result};
+use crate::pointer;
+use crate::value::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Key of a reference object
+pub const REF_KEY: &str = "$ref";
+
+/// Replace every internal reference in `doc` with a copy of its target
+pub fn resolve_refs(doc: &Value) -> Result<Value> {
+    Refs::new(doc).expand(doc.clone(), &mut Vec::new())
+}
+
+/// Resolved reference targets of one document, shared between uses
+#[derive(Debug)]
+pub struct Refs<'a> {
+    root: &'a Value,
+    resolved: HashMap<String, Arc<Value>>,
+}
+
+impl<'a> Refs<'a> {
+    /// Resolver for references within `root`
+    pub fn new(root: &'a Value) -> Self {
+        Refs {
+            root,
+            resolved: HashMap::new(),
+        }
+    }
+
+    /// The expanded target of a reference such as `#/definitions/address`
+    ///
+    /// Every call for the same target returns the same node.
+    pub fn resolve(&mut self, reference: &str) -> Result<Arc<Value>> {
+        let pointer = fragment(reference)?;
+        self.target(pointer, &mut Vec::new())
+    }
+
+    fn target(&mut self, pointer: &str, stack: &mut Vec<String>) -> Result<Arc<Value>> {
+        if let Some(node) = self.resolved.get(pointer) {
+            return Ok(Arc::clone(node));
+        }
+        if stack.iter().any(|p| p == pointer) {
+            stack.push(pointer.to_string());
+            return Err(Error::InvalidReference(format!(
+                "cycle: #{}",
+                stack.join(" -> #")
+            )));
+        }
+        let target = pointer::lookup(self.root, pointer)
+            .ok_or_else(|| Error::InvalidReference(format!("#{} not found", pointer)))?
+            .clone();
+        stack.push(pointer.to_string());
+        let expanded = self.expand(target, stack)?;
+        stack.pop();
+        let node = Arc::new(expanded);
+        self.resolved.insert(pointer.to_string(), Arc::clone(&node));
+        Ok(node)
+    }
+
+    fn expand(&mut self, mut value: Value, stack: &mut Vec<String>) -> Result<Value> {
+        match &mut value {
+            Value::Array(items) => std::mem::take(items)
+                .into_iter()
+                .map(|item| self.expand(item, stack))
+                .collect::<Result<_>>()
+                .map(Value::Array),
+            Value::Object(map) => {
+                let mut map = std::mem::take(map);
+                let reference = match map.get(REF_KEY) {
+                    Some(Value::String(r)) if r.starts_with('#') => map.remove(REF_KEY),
+                    _ => None,
+                };
+                let mut rest = Map::with_capacity(map.len());
+                for (key, item) in map {
+                    rest.insert(key, self.expand(item, stack)?);
+                }
+                let Some(Value::String(reference)) = &reference else {
+                    return Ok(Value::Object(rest));
+                };
+                let pointer = fragment(reference)?;
+                let mut value = Value::clone(&*self.target(pointer, stack)?);
+                if !rest.is_empty() {
+                    value.deep_merge(Value::Object(rest));
+                }
+                Ok(value)
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
+/// JSON Pointer of an internal reference
+fn fragment(reference: &str) -> Result<&str> {
+    reference.strip_prefix('#').ok_or_else(|| {
+        Error::InvalidReference(format!(
+            "{} is not a reference within the document",
+            reference
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_resolve_copies() {
+        let doc = parse(
+            "{
+                definitions: {
+                    city: { type: 'string' },
+                    address: { type: 'object', properties: { city: { $ref: '#/definitions/city' } } },
+                },
+                items: [{ $ref: '#/definitions/address' }, { $ref: 'https://example.com/x' }],
+            }",
+        )
+        .unwrap();
+        let resolved = resolve_refs(&doc).unwrap();
+        let items = resolved.as_object().unwrap()["items"].as_array().unwrap();
+        assert_eq!(
+            items[0],
+            parse("{ type: 'object', properties: { city: { type: 'string' } } }").unwrap()
+        );
+        assert_eq!(
+            items[1],
+            parse("{ $ref: 'https://example.com/x' }").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shared_nodes() {
+        let doc = parse("{ a: { b: { $ref: '#/c' } }, c: [1, 2] }").unwrap();
+        let mut refs = Refs::new(&doc);
+        let first = refs.resolve("#/a").unwrap();
+        let second = refs.resolve("#/a").unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(*first, parse("{ b: [1, 2] }").unwrap());
+        assert!(refs.resolve("/a").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let doc = parse("{ a: { next: { $ref: '#/b' } }, b: { $ref: '#/a' } }").unwrap();
+        let err = resolve_refs(&doc).unwrap_err();
+        assert!(matches!(err, Error::InvalidReference(m) if m.contains("cycle")));
+
+        let doc = parse("{ a: { $ref: '#/missing' } }").unwrap();
+        let err = resolve_refs(&doc).unwrap_err();
+        assert!(matches!(err, Error::InvalidReference(m) if m == "#/missing not found"));
+    }
+}
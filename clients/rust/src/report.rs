@@ -0,0 +1,424 @@
+//! Errors rendered with the source line they point at
+//!
+//! A [`Report`] places an error in its input and adds what is known about
+//! fixing it, so a typo deep in a config file reads as more than a byte
+//! offset:
+//!
+//! ```
+//! use kjson::report::Report;
+//!
+//! let source = "{\n  name: 'shop,\n  port: 80,\n}";
+//! let error = kjson::parse(source).unwrap_err();
+//! let report = Report::from_error(&error, source);
+//! assert_eq!(
+//!     report.render_plain("shop.kjson", source),
+//!     "shop.kjson:2:9: Unterminated string
+//!   |
+//! 2 |   name: 'shop,
+//!   |         ^^^^^^ this string is never closed
+//!   |
+//!   = help: did you forget a closing quote?
+//! "
+//! );
+//! ```
+//!
+//! The first line is `name:line:column: message`, as compilers print it, so
+//! editors and CI annotations still pick it up. Reports can also be made
+//! from [`validate`](crate::validate) and [`lint`](crate::lint) diagnostics,
+//! or built by hand for errors found by other tools.
+
+use crate::error::{Error, ErrorKind};
+use crate::lexer::{Lexer, TokenKind};
+use crate::lint;
+use crate::span::{SourceMap, Span};
+use crate::validate::{self, Validator};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Columns a tab is shown as
+const TAB_WIDTH: usize = 4;
+
+/// A problem placed in its source, with hints for fixing it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// What is wrong
+    pub message: String,
+    /// Where it is, if known
+    pub span: Option<Span>,
+    /// Text shown beside the carets
+    pub label: Option<String>,
+    /// Context, such as where an unclosed bracket was opened
+    pub notes: Vec<String>,
+    /// A likely fix
+    pub help: Option<String>,
+}
+
+impl Report {
+    /// A report with only a message
+    pub fn new(message: impl Into<String>) -> Self {
+        Report {
+            message: message.into(),
+            ..Report::default()
+        }
+    }
+
+    /// Point at `span` in the source
+    pub fn span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// Show `label` beside the carets
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Add a note
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Suggest a fix
+    pub fn help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Explain `error`, which came from parsing `source`.
+    ///
+    /// Syntax errors are located again with the validator, which gives the
+    /// span of the offending text rather than a single offset.
+    pub fn from_error(error: &Error, source: &str) -> Self {
+        let mut report = match error {
+            Error::ParseError { position, message } => {
+                Report::new(message.as_str()).span(char_span(source, *position))
+            }
+            Error::DepthLimit { position, limit } => Report::new("Nested too deeply")
+                .span(char_span(source, *position))
+                .note(format!("nesting is limited to {} levels", limit)),
+            Error::UnexpectedEof => {
+                Report::new(error.to_string()).span(char_span(source, source.len()))
+            }
+            other => Report::new(other.to_string()),
+        };
+        let kind = error.kind();
+        if matches!(
+            kind,
+            ErrorKind::Syntax | ErrorKind::UnexpectedEof | ErrorKind::InvalidLiteral { .. }
+        ) {
+            if let Err(diagnostics) = Validator::new().extended_literals(true).validate(source) {
+                // Malformed literals come first and a syntax error last
+                let diagnostic = if kind == ErrorKind::Syntax || kind == ErrorKind::UnexpectedEof {
+                    diagnostics.last()
+                } else {
+                    diagnostics.first()
+                };
+                if let Some(diagnostic) = diagnostic {
+                    report.message.clone_from(&diagnostic.message);
+                    report.span = Some(diagnostic.span);
+                }
+            }
+        }
+        report.explain(source);
+        report
+    }
+
+    /// Explain a [`validate`](crate::validate) diagnostic for `source`
+    pub fn from_diagnostic(diagnostic: &validate::Diagnostic, source: &str) -> Self {
+        let mut report = Report::new(diagnostic.message.as_str()).span(diagnostic.span);
+        report.explain(source);
+        report
+    }
+
+    /// Add a label, notes and help for the syntax errors the parser reports
+    fn explain(&mut self, source: &str) {
+        let Some(found) = self.span.and_then(|span| source.get(span.start..)) else {
+            return;
+        };
+        let span = self.span.unwrap_or_default();
+        let found = found.chars().next();
+        let help = match self.message.as_str() {
+            "Unterminated string" => {
+                // The lexer runs the string to the end of its line
+                let string = Lexer::new(source).filter(|token| {
+                    token.kind == TokenKind::Error
+                        && token.span.start <= span.start
+                        && matches!(source.as_bytes()[token.span.start], b'"' | b'\'' | b'`')
+                });
+                if let Some(token) = string.last() {
+                    self.span = Some(token.span);
+                    self.label = Some("this string is never closed".to_string());
+                }
+                "did you forget a closing quote?".to_string()
+            }
+            "Unexpected end of input" => match self.note_unclosed(source) {
+                Some(close) => format!("did you forget a closing `{}`?", close),
+                None => "the document ends where a value was expected".to_string(),
+            },
+            "Expected ',' or ']'" | "Expected ',' or '}'" => {
+                let close = if self.message.ends_with("']'") {
+                    ']'
+                } else {
+                    '}'
+                };
+                match found {
+                    Some(c) if starts_value(c) => "did you forget a comma?".to_string(),
+                    Some(_) => format!("did you forget a closing `{}`?", close),
+                    None => {
+                        let close = self.note_unclosed(source).unwrap_or(close);
+                        format!("did you forget a closing `{}`?", close)
+                    }
+                }
+            }
+            "Expected ':' after key" => "separate each key from its value with `:`".to_string(),
+            "Expected value" => "did you add an extra comma?".to_string(),
+            "Invalid unquoted key" => {
+                "quote keys that are not plain identifiers, e.g. `'my key': 1`".to_string()
+            }
+            "Unexpected characters after value" => {
+                "a document holds one value; wrap several values in an array".to_string()
+            }
+            message if message.starts_with("Invalid literal") => {
+                "quote text to make it a string".to_string()
+            }
+            message if message.starts_with("Invalid escape sequence") => {
+                "write a backslash as `\\\\`".to_string()
+            }
+            _ => return,
+        };
+        self.help.get_or_insert(help);
+    }
+
+    /// Note where the innermost bracket left open was opened, returning
+    /// the bracket that closes it
+    fn note_unclosed(&mut self, source: &str) -> Option<char> {
+        let (open, close) = unclosed(source)?;
+        let location = SourceMap::new(source).location(open);
+        self.notes.push(format!(
+            "the `{}` at line {}, column {} is never closed",
+            &source[open..open + 1],
+            location.line,
+            location.column
+        ));
+        Some(close)
+    }
+
+    /// The report as text with ANSI colors, for a terminal. `name` is shown
+    /// as the file name, and `source` must be the text the span is in.
+    pub fn render(&self, name: &str, source: &str) -> String {
+        self.render_with(name, &SourceMap::new(source), true)
+    }
+
+    /// [`render`](Report::render) without colors, e.g. for log files
+    pub fn render_plain(&self, name: &str, source: &str) -> String {
+        self.render_with(name, &SourceMap::new(source), false)
+    }
+
+    /// Render with or without colors, reusing the [`SourceMap`] of the
+    /// source for the many reports of one input
+    pub fn render_with(&self, name: &str, map: &SourceMap, color: bool) -> String {
+        let paint = |text: &str, code: &str| {
+            if color && !text.is_empty() {
+                format!("{}{}{}", code, text, RESET)
+            } else {
+                text.to_string()
+            }
+        };
+        let mut out = String::new();
+        let Some(span) = self.span else {
+            out.push_str(&format!("{}: {}\n", name, paint(&self.message, RED)));
+            for note in &self.notes {
+                out.push_str(&format!("  = note: {}\n", note));
+            }
+            if let Some(help) = &self.help {
+                out.push_str(&paint(&format!("  = help: {}", help), CYAN));
+                out.push('\n');
+            }
+            return out;
+        };
+
+        let source = map.source();
+        let location = map.location(span.start);
+        let line_span = map.line_span(location.line).unwrap_or_default();
+        let line = line_span.slice(source);
+        // A span starting on a line break is shown just past the line
+        let start = map.offset(location).unwrap_or(line_span.end);
+        let number = location.line;
+        let column = location.column;
+        let number = number.to_string();
+        let pad = " ".repeat(number.len());
+        let bar = paint("|", BLUE);
+
+        out.push_str(&format!(
+            "{}:{}:{}: {}\n",
+            name,
+            number,
+            column,
+            paint(&self.message, RED)
+        ));
+        out.push_str(&format!("{} {}\n", pad, bar));
+        out.push_str(&format!(
+            "{} {} {}\n",
+            paint(&number, BLUE),
+            bar,
+            expand_tabs(line)
+        ));
+
+        // Carets under the part of the span on this line, at least one
+        let before = &source[line_span.start..start];
+        let end = map
+            .offset(map.location(span.end.clamp(start, line_span.end)))
+            .unwrap_or(start);
+        let carets = "^".repeat(width(&source[start..end]).max(1));
+        let mut marker = format!("{}{}", " ".repeat(width(before)), paint(&carets, RED));
+        if let Some(label) = &self.label {
+            marker.push(' ');
+            marker.push_str(&paint(label, RED));
+        }
+        out.push_str(&format!("{} {} {}\n", pad, bar, marker));
+
+        if !self.notes.is_empty() || self.help.is_some() {
+            out.push_str(&format!("{} {}\n", pad, bar));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", pad, note));
+        }
+        if let Some(help) = &self.help {
+            out.push_str(&format!(
+                "{} {}\n",
+                pad,
+                paint(&format!("= help: {}", help), CYAN)
+            ));
+        }
+        out
+    }
+}
+
+impl From<&lint::Diagnostic> for Report {
+    fn from(diagnostic: &lint::Diagnostic) -> Self {
+        Report::new(format!(
+            "{} [{}]: {}",
+            diagnostic.severity, diagnostic.rule, diagnostic.message
+        ))
+        .span(diagnostic.span)
+    }
+}
+
+/// The span of the character at `position`, empty at the end of `source`
+fn char_span(source: &str, position: usize) -> Span {
+    let start = floor_char_boundary(source, position);
+    let len = source[start..].chars().next().map_or(0, char::len_utf8);
+    Span::new(start, start + len)
+}
+
+/// `position`, moved back to the start of the character it falls in
+fn floor_char_boundary(source: &str, position: usize) -> usize {
+    let mut position = position.min(source.len());
+    while !source.is_char_boundary(position) {
+        position -= 1;
+    }
+    position
+}
+
+/// Columns `text` takes up once tabs are expanded
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+/// Whether `c` can start a value or key, so that a missing comma is the
+/// likely mistake before it
+fn starts_value(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '"' | '\'' | '`' | '-' | '[' | '{' | '_' | '$')
+}
+
+/// Offset of the innermost bracket left open at the end of `source`, and
+/// the bracket that closes it
+fn unclosed(source: &str) -> Option<(usize, char)> {
+    let mut open = Vec::new();
+    for token in Lexer::new(source) {
+        match token.kind {
+            TokenKind::LeftBrace => open.push((token.span.start, '}')),
+            TokenKind::LeftBracket => open.push((token.span.start, ']')),
+            TokenKind::RightBrace | TokenKind::RightBracket => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn render(source: &str) -> String {
+        Report::from_error(&parse(source).unwrap_err(), source).render_plain("t", source)
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        assert_eq!(
+            render("[1, 2 3]"),
+            "t:1:7: Expected ',' or ']'\n  |\n1 | [1, 2 3]\n  |       ^\n  |\n  = help: did you forget a comma?\n"
+        );
+        assert_eq!(
+            render("{\n  a: [1,\n  2\n  3],\n}"),
+            "t:4:3: Expected ',' or ']'\n  |\n4 |   3],\n  |   ^\n  |\n  = help: did you forget a comma?\n"
+        );
+        assert!(render("{ a: { b: 1 ").contains(
+            "= note: the `{` at line 1, column 6 is never closed\n  = help: did you forget a closing `}`?\n"
+        ));
+        assert!(render("{ a: hello }").contains("  |      ^^^^^\n  |\n  = help: quote text"));
+        assert!(render("1 2")
+            .ends_with("= help: a document holds one value; wrap several values in an array\n"));
+    }
+
+    #[test]
+    fn test_tabs_and_wide_characters() {
+        let source = "{\n\tname: 'é',\n\tx: 1 1\n}";
+        assert_eq!(
+            render(source),
+            "t:3:7: Expected ',' or '}'\n  |\n3 |     x: 1 1\n  |          ^\n  |\n  = help: did you forget a comma?\n"
+        );
+    }
+
+    #[test]
+    fn test_other_reports() {
+        let source = "[[[1]]]";
+        let error = crate::Parser::new(source).max_depth(2).parse().unwrap_err();
+        let report = Report::from_error(&error, source);
+        assert_eq!(report.span, Some(Span::new(2, 3)));
+        assert_eq!(report.notes, ["nesting is limited to 2 levels"]);
+
+        let report = Report::new("no such file").help("check the path");
+        assert_eq!(
+            report.render_plain("a.kjson", ""),
+            "a.kjson: no such file\n  = help: check the path\n"
+        );
+        let colored = Report::new("x").span(Span::new(0, 1)).render("t", "x");
+        assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+
+        let diagnostics = crate::validate("[1, 2 3]").unwrap_err();
+        let report = Report::from_diagnostic(&diagnostics[0], "[1, 2 3]");
+        assert_eq!(report.help.as_deref(), Some("did you forget a comma?"));
+
+        let diagnostics = crate::lint::lint("[1, 2,]").unwrap();
+        let report = Report::from(&diagnostics[0]);
+        assert!(report
+            .render_plain("t", "[1, 2,]")
+            .starts_with("t:1:6: info [trailing-comma]: "));
+    }
+}
@@ -0,0 +1,133 @@
+//! reqwest helpers for kJSON bodies
+//!
+//! The extension traits add kJSON counterparts of reqwest's `.json()`:
+//!
+//! ```no_run
+//! use kjson::reqwest::{RequestBuilderExt, ResponseExt};
+//!
+//! # #[derive(serde::Deserialize, serde::Serialize)]
+//! # struct Order {
+//! #     id: String,
+//! # }
+//! # async fn run(client: reqwest::Client, order: Order) -> kjson::Result<()> {
+//! let created: Order = client
+//!     .post("https://api.example.com/orders")
+//!     .kjson(&order)?
+//!     .send()
+//!     .await
+//!     .map_err(|e| kjson::Error::Http(e.to_string()))?
+//!     .kjson()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::media_type::{self, CONTENT_TYPE};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::value::{from_value, to_value};
+use reqwest::header::{self, HeaderValue};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+
+/// kJSON request bodies
+pub trait RequestBuilderExt: Sized {
+    /// Serialize `value` as the request body and set `Content-Type: application/kjson`.
+    ///
+    /// Unlike `.json()`, serialization errors are returned here rather than
+    /// when the request is sent.
+    fn kjson<T: Serialize + ?Sized>(self, value: &T) -> Result<Self>;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    fn kjson<T: Serialize + ?Sized>(self, value: &T) -> Result<Self> {
+        let body = to_string(&to_value(value)?)?;
+        Ok(self
+            .header(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE))
+            .body(body))
+    }
+}
+
+/// kJSON response bodies
+pub trait ResponseExt {
+    /// Read the body as kJSON and deserialize it.
+    ///
+    /// Fails with [`Error::Http`] if the response is not declared as kJSON.
+    fn kjson<T: DeserializeOwned>(self) -> impl Future<Output = Result<T>> + Send;
+}
+
+impl ResponseExt for Response {
+    async fn kjson<T: DeserializeOwned>(self) -> Result<T> {
+        let content_type = self
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        if !media_type::is_kjson(content_type) {
+            return Err(Error::Http(format!(
+                "expected Content-Type: {}, got {:?}",
+                CONTENT_TYPE, content_type
+            )));
+        }
+        let text = self.text().await.map_err(|e| Error::Http(e.to_string()))?;
+        from_value(parse(&text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, serde::Deserialize)]
+    struct Order {
+        id: String,
+        qty: f64,
+    }
+
+    fn response(content_type: &str, body: &'static str) -> Response {
+        http::Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_request_body() {
+        let order = Order {
+            id: "a".to_string(),
+            qty: 2.0,
+        };
+        let request = reqwest::Client::new()
+            .post("http://localhost/orders")
+            .kjson(&order)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = std::str::from_utf8(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(parse(body).unwrap(), parse("{ id: 'a', qty: 2 }").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_response_body() {
+        let order: Order = response("application/kjson", "{ id: 'a', qty: 2 }")
+            .kjson()
+            .await
+            .unwrap();
+        assert_eq!(order.qty, 2.0);
+
+        let vendor: Result<Order> = response("application/vnd.orders+kjson", "{ id: 'b', qty: 1 }")
+            .kjson()
+            .await;
+        assert_eq!(vendor.unwrap().id, "b");
+
+        let wrong: Result<Order> = response("application/json", "{}").kjson().await;
+        assert!(matches!(wrong, Err(Error::Http(_))));
+        let broken: Result<Order> = response(CONTENT_TYPE, "{ id: ] }").kjson().await;
+        assert!(matches!(broken, Err(Error::ParseError { .. })));
+    }
+}
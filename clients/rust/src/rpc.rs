@@ -0,0 +1,584 @@
+//! JSON-RPC 2.0 over kJSON
+//!
+//! Messages follow [JSON-RPC 2.0](https://www.jsonrpc.org/specification),
+//! with params, results and error data carried as [`Value`]s so extended
+//! types survive the call, and UUIDs and BigInts accepted as ids. Nothing
+//! here does I/O: serialize a message with [`Message::to_string`], send it
+//! however the service talks, and hand incoming text to [`handle`]:
+//!
+//! ```
+//! use kjson::rpc::{self, ErrorObject};
+//!
+//! let response = rpc::handle(
+//!     r#"{ jsonrpc: "2.0", id: 7n, method: "add", params: [1.5m, 2.25m] }"#,
+//!     |method, _params| match method {
+//!         "add" => Ok(kjson::parse("3.75m")?),
+//!         _ => Err(ErrorObject::method_not_found(method)),
+//!     },
+//! );
+//! let expected = rpc::Response::success(kjson::BigInt::from_i64(7), kjson::parse("3.75m")?);
+//! assert_eq!(rpc::Message::parse(&response.unwrap())?, expected.into());
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! A batch is answered with an array holding a response for every request
+//! in it; notifications are never answered, so a batch of notifications
+//! yields no response at all.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::types::BigInt;
+use crate::value::{Map, Str, Value};
+use std::fmt;
+use uuid::Uuid;
+
+/// Invalid JSON was received
+pub const PARSE_ERROR: i64 = -32700;
+/// The message is not a valid request
+pub const INVALID_REQUEST: i64 = -32600;
+/// The method does not exist or is not available
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Invalid method parameters
+pub const INVALID_PARAMS: i64 = -32602;
+/// Internal error while handling the call
+pub const INTERNAL_ERROR: i64 = -32603;
+
+const VERSION: &str = "2.0";
+
+/// Request id matching a response to its request
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Id {
+    /// `null`, used in responses to requests whose id could not be read
+    Null,
+    /// An integer
+    Number(i64),
+    /// A string
+    String(String),
+    /// A BigInt (`123n`)
+    BigInt(BigInt),
+    /// A UUID
+    Uuid(Uuid),
+}
+
+impl Id {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Null => Some(Id::Null),
+            Value::Number(_) | Value::Integer(_) => value.as_i64().map(Id::Number),
+            Value::String(s) => Some(Id::String(s.to_string())),
+            Value::BigInt(b) => Some(Id::BigInt(b.clone())),
+            Value::Uuid(u) => Some(Id::Uuid(*u)),
+            _ => None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Id::Null => Value::Null,
+            Id::Number(n) => Value::Integer(*n),
+            Id::String(s) => Value::string(s.as_str()),
+            Id::BigInt(b) => Value::BigInt(b.clone()),
+            Id::Uuid(u) => Value::Uuid(*u),
+        }
+    }
+}
+
+impl From<i64> for Id {
+    fn from(n: i64) -> Self {
+        Id::Number(n)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(s: &str) -> Self {
+        Id::String(s.to_string())
+    }
+}
+
+impl From<String> for Id {
+    fn from(s: String) -> Self {
+        Id::String(s)
+    }
+}
+
+impl From<BigInt> for Id {
+    fn from(b: BigInt) -> Self {
+        Id::BigInt(b)
+    }
+}
+
+impl From<Uuid> for Id {
+    fn from(u: Uuid) -> Self {
+        Id::Uuid(u)
+    }
+}
+
+/// The error member of a failed response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorObject {
+    /// Error code, such as [`METHOD_NOT_FOUND`]
+    pub code: i64,
+    /// Short description of the error
+    pub message: String,
+    /// Further details defined by the server
+    pub data: Option<Value>,
+}
+
+impl ErrorObject {
+    /// Error with a code and message but no data
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        ErrorObject {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// Attach details to the error
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// [`PARSE_ERROR`] with the parser's message as data
+    pub fn parse_error(detail: impl fmt::Display) -> Self {
+        Self::new(PARSE_ERROR, "Parse error").with_data(Value::string(detail.to_string()))
+    }
+
+    /// [`INVALID_REQUEST`] explaining what is wrong with the message
+    pub fn invalid_request(detail: impl fmt::Display) -> Self {
+        Self::new(INVALID_REQUEST, "Invalid Request").with_data(Value::string(detail.to_string()))
+    }
+
+    /// [`METHOD_NOT_FOUND`] naming the method
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, "Method not found").with_data(Value::string(method))
+    }
+
+    /// [`INVALID_PARAMS`] explaining what is wrong with them
+    pub fn invalid_params(detail: impl fmt::Display) -> Self {
+        Self::new(INVALID_PARAMS, "Invalid params").with_data(Value::string(detail.to_string()))
+    }
+
+    /// [`INTERNAL_ERROR`] with a description as data
+    pub fn internal(detail: impl fmt::Display) -> Self {
+        Self::new(INTERNAL_ERROR, "Internal error").with_data(Value::string(detail.to_string()))
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let obj = value.as_object()?;
+        let code = obj.get("code")?.as_i64()?;
+        Some(ErrorObject {
+            code,
+            message: obj.get("message")?.as_str()?.to_string(),
+            data: obj.get("data").cloned(),
+        })
+    }
+
+    /// The detail given as data, or the message without one
+    fn detail(&self) -> String {
+        match self.data.as_ref().and_then(Value::as_str) {
+            Some(detail) => detail.to_string(),
+            None => self.message.clone(),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        let mut obj = Map::new();
+        obj.insert(Str::from("code"), Value::Number(self.code as f64));
+        obj.insert(Str::from("message"), Value::string(self.message.as_str()));
+        if let Some(data) = &self.data {
+            obj.insert(Str::from("data"), data.clone());
+        }
+        Value::Object(obj)
+    }
+}
+
+impl fmt::Display for ErrorObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for ErrorObject {}
+
+/// Errors from a handler are reported to the caller as internal errors
+impl From<Error> for ErrorObject {
+    fn from(e: Error) -> Self {
+        ErrorObject::internal(e)
+    }
+}
+
+/// A call expecting a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    /// Id echoed in the response
+    pub id: Id,
+    /// Method to call
+    pub method: String,
+    /// Array or object of parameters
+    pub params: Option<Value>,
+}
+
+impl Request {
+    /// Request for `method` with an id and parameters
+    pub fn new(id: impl Into<Id>, method: &str, params: Option<Value>) -> Self {
+        Request {
+            id: id.into(),
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// A call without an id, which is never answered
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Method to call
+    pub method: String,
+    /// Array or object of parameters
+    pub params: Option<Value>,
+}
+
+impl Notification {
+    /// Notification for `method` with parameters
+    pub fn new(method: &str, params: Option<Value>) -> Self {
+        Notification {
+            method: method.to_string(),
+            params,
+        }
+    }
+}
+
+/// The answer to a [`Request`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    /// Id of the request
+    pub id: Id,
+    /// The method's result, or why the call failed
+    pub result: std::result::Result<Value, ErrorObject>,
+}
+
+impl Response {
+    /// Successful response
+    pub fn success(id: impl Into<Id>, result: Value) -> Self {
+        Response {
+            id: id.into(),
+            result: Ok(result),
+        }
+    }
+
+    /// Failed response
+    pub fn error(id: impl Into<Id>, error: ErrorObject) -> Self {
+        Response {
+            id: id.into(),
+            result: Err(error),
+        }
+    }
+}
+
+/// A single JSON-RPC message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// A call expecting a response
+    Request(Request),
+    /// A call without a response
+    Notification(Notification),
+    /// The answer to a request
+    Response(Response),
+    /// Several messages sent together
+    Batch(Vec<Message>),
+}
+
+impl Message {
+    /// Parse a message or batch from kJSON text
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_value(&parse(input)?)
+    }
+
+    /// Read a message or batch from a parsed document
+    pub fn from_value(value: &Value) -> Result<Self> {
+        match value {
+            Value::Array(items) if items.is_empty() => Err(rpc_error("empty batch")),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::Array(_) => Err(rpc_error("batches cannot be nested")),
+                    item => Self::from_value(item),
+                })
+                .collect::<Result<_>>()
+                .map(Message::Batch),
+            value => read_message(value).map_err(|e| rpc_error(e.detail())),
+        }
+    }
+
+    /// Build the document for this message
+    pub fn to_value(&self) -> Value {
+        let mut obj = Map::new();
+        let mut set = |key: &str, value: Value| obj.insert(Str::from(key), value);
+        set("jsonrpc", Value::string(VERSION));
+        match self {
+            Message::Request(request) => {
+                set("id", request.id.to_value());
+                set("method", Value::string(request.method.as_str()));
+                if let Some(params) = &request.params {
+                    set("params", params.clone());
+                }
+            }
+            Message::Notification(notification) => {
+                set("method", Value::string(notification.method.as_str()));
+                if let Some(params) = &notification.params {
+                    set("params", params.clone());
+                }
+            }
+            Message::Response(response) => {
+                set("id", response.id.to_value());
+                match &response.result {
+                    Ok(result) => set("result", result.clone()),
+                    Err(error) => set("error", error.to_value()),
+                };
+            }
+            Message::Batch(messages) => {
+                return Value::Array(messages.iter().map(Message::to_value).collect());
+            }
+        }
+        Value::Object(obj)
+    }
+
+    /// Serialize this message as compact kJSON text
+    #[allow(clippy::inherent_to_string)]
+    pub fn to_string(&self) -> String {
+        to_string(&self.to_value()).expect("messages serialize")
+    }
+}
+
+impl From<Request> for Message {
+    fn from(request: Request) -> Self {
+        Message::Request(request)
+    }
+}
+
+impl From<Notification> for Message {
+    fn from(notification: Notification) -> Self {
+        Message::Notification(notification)
+    }
+}
+
+impl From<Response> for Message {
+    fn from(response: Response) -> Self {
+        Message::Response(response)
+    }
+}
+
+fn rpc_error(message: impl fmt::Display) -> Error {
+    Error::Conversion(format!("invalid JSON-RPC message: {}", message))
+}
+
+/// Read a single message, failing with an invalid request error
+fn read_message(value: &Value) -> std::result::Result<Message, ErrorObject> {
+    let obj = value
+        .as_object()
+        .ok_or_else(|| ErrorObject::invalid_request("expected an object"))?;
+    if obj.get("jsonrpc").and_then(Value::as_str) != Some(VERSION) {
+        return Err(ErrorObject::invalid_request("jsonrpc must be \"2.0\""));
+    }
+    let id = match obj.get("id") {
+        Some(id) => Some(Id::from_value(id).ok_or_else(|| {
+            ErrorObject::invalid_request("id must be a string, integer, bigint, uuid or null")
+        })?),
+        None => None,
+    };
+
+    if let Some(method) = obj.get("method") {
+        let method = method
+            .as_str()
+            .ok_or_else(|| ErrorObject::invalid_request("method must be a string"))?
+            .to_string();
+        let params = match obj.get("params") {
+            Some(params @ (Value::Array(_) | Value::Object(_))) => Some(params.clone()),
+            Some(_) => {
+                return Err(ErrorObject::invalid_request(
+                    "params must be an array or object",
+                ))
+            }
+            None => None,
+        };
+        return Ok(match id {
+            Some(id) => Message::Request(Request { id, method, params }),
+            None => Message::Notification(Notification { method, params }),
+        });
+    }
+
+    let id = id.ok_or_else(|| ErrorObject::invalid_request("response without id"))?;
+    let result = match (obj.get("result"), obj.get("error")) {
+        (Some(result), None) => Ok(result.clone()),
+        (None, Some(error)) => Err(ErrorObject::from_value(error)
+            .ok_or_else(|| ErrorObject::invalid_request("malformed error object"))?),
+        _ => {
+            return Err(ErrorObject::invalid_request(
+                "response needs exactly one of result and error",
+            ))
+        }
+    };
+    Ok(Message::Response(Response { id, result }))
+}
+
+/// Answer a request, notification or batch in kJSON text.
+///
+/// `handler` is called with the method and params of every call. The
+/// response text is `None` when nothing needs answering: the input held
+/// only notifications. Text that does not parse is answered with a
+/// [`PARSE_ERROR`], and malformed calls with [`INVALID_REQUEST`].
+pub fn handle<F>(input: &str, handler: F) -> Option<String>
+where
+    F: FnMut(&str, Option<Value>) -> std::result::Result<Value, ErrorObject>,
+{
+    let response = match parse(input) {
+        Ok(value) => handle_value(&value, handler)?,
+        Err(e) => Message::Response(Response::error(Id::Null, ErrorObject::parse_error(e))),
+    };
+    Some(response.to_string())
+}
+
+/// Answer a request, notification or batch that is already parsed; see
+/// [`handle`]
+pub fn handle_value<F>(value: &Value, mut handler: F) -> Option<Message>
+where
+    F: FnMut(&str, Option<Value>) -> std::result::Result<Value, ErrorObject>,
+{
+    match value {
+        Value::Array(items) if items.is_empty() => Some(Message::Response(Response::error(
+            Id::Null,
+            ErrorObject::invalid_request("empty batch"),
+        ))),
+        Value::Array(items) => {
+            let responses: Vec<Message> = items
+                .iter()
+                .filter_map(|item| handle_one(item, &mut handler))
+                .map(Message::Response)
+                .collect();
+            (!responses.is_empty()).then_some(Message::Batch(responses))
+        }
+        value => handle_one(value, &mut handler).map(Message::Response),
+    }
+}
+
+fn handle_one<F>(value: &Value, handler: &mut F) -> Option<Response>
+where
+    F: FnMut(&str, Option<Value>) -> std::result::Result<Value, ErrorObject>,
+{
+    match read_message(value) {
+        Ok(Message::Request(request)) => Some(Response {
+            result: handler(&request.method, request.params),
+            id: request.id,
+        }),
+        Ok(Message::Notification(notification)) => {
+            // Notifications are not answered, not even with errors
+            let _ = handler(&notification.method, notification.params);
+            None
+        }
+        Ok(_) => Some(Response::error(
+            Id::Null,
+            ErrorObject::invalid_request("expected a request"),
+        )),
+        Err(error) => {
+            let id = value
+                .as_object()
+                .and_then(|obj| obj.get("id"))
+                .and_then(Id::from_value)
+                .unwrap_or(Id::Null);
+            Some(Response::error(id, error))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(method: &str, params: Option<Value>) -> std::result::Result<Value, ErrorObject> {
+        match method {
+            "echo" => Ok(params.unwrap_or(Value::Null)),
+            "fail" => Err(ErrorObject::new(-1, "failed").with_data(parse("{ at: 1n }").unwrap())),
+            _ => Err(ErrorObject::method_not_found(method)),
+        }
+    }
+
+    fn reply(input: &str) -> Message {
+        Message::parse(&handle(input, echo).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_messages() {
+        let id = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let messages = [
+            Message::from(Request::new(id, "sum", Some(parse("[1.5m, 2n]").unwrap()))),
+            Message::from(Request::new(BigInt::from_i64(7), "ping", None)),
+            Message::from(Notification::new("log", Some(parse("{ at: PT1H }").unwrap()))),
+            Message::from(Response::success("a", parse("{ total: 3.5m }").unwrap())),
+            Message::from(Response::error(Id::Null, ErrorObject::invalid_params("missing amount"))),
+            Message::Batch(vec![Message::from(Request::new(1, "ping", None))]),
+        ];
+        for message in messages {
+            assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let response = reply(r#"{ jsonrpc: "2.0", id: 7n, method: "echo", params: [1.5m] }"#);
+        assert_eq!(
+            response,
+            Message::from(Response::success(BigInt::from_i64(7), parse("[1.5m]").unwrap()))
+        );
+
+        match reply(r#"{ jsonrpc: "2.0", id: "x", method: "fail" }"#) {
+            Message::Response(Response { id, result: Err(error) }) => {
+                assert_eq!(id, Id::from("x"));
+                assert_eq!(error.code, -1);
+                assert_eq!(error.data, Some(parse("{ at: 1n }").unwrap()));
+            }
+            other => panic!("expected an error response, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_handle_errors() {
+        let code = |message: Message| match message {
+            Message::Response(Response { result: Err(e), .. }) => e.code,
+            other => panic!("expected an error response, got {:?}", other),
+        };
+        assert_eq!(code(reply("{ jsonrpc: ")), PARSE_ERROR);
+        assert_eq!(code(reply("[]")), INVALID_REQUEST);
+        let bad_version = r#"{ jsonrpc: "1.0", id: 1, method: "echo" }"#;
+        assert_eq!(code(reply(bad_version)), INVALID_REQUEST);
+        let scalar_params = r#"{ jsonrpc: "2.0", id: 1, method: "echo", params: 1 }"#;
+        assert_eq!(code(reply(scalar_params)), INVALID_REQUEST);
+        let unknown = r#"{ jsonrpc: "2.0", id: 1, method: "nope" }"#;
+        assert_eq!(code(reply(unknown)), METHOD_NOT_FOUND);
+    }
+
+    #[test]
+    fn test_handle_batch() {
+        let input = r#"[
+            { jsonrpc: "2.0", id: 1, method: "echo", params: { a: 1n } },
+            { jsonrpc: "2.0", method: "echo" },
+            { jsonrpc: "2.0", id: 2, method: "nope" },
+            { foo: "bar" },
+        ]"#;
+        match reply(input) {
+            Message::Batch(responses) => {
+                assert_eq!(responses.len(), 3);
+                assert_eq!(
+                    responses[0],
+                    Message::from(Response::success(1, parse("{ a: 1n }").unwrap()))
+                );
+            }
+            other => panic!("expected a batch, got {:?}", other),
+        }
+
+        let notifications = r#"[{ jsonrpc: "2.0", method: "echo" }, { jsonrpc: "2.0", method: "x" }]"#;
+        assert_eq!(handle(notifications, echo), None);
+    }
+}
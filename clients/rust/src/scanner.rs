@@ -0,0 +1,208 @@
+//! Incremental value boundary detection
+//!
+//! The parser works on complete input, so readers that receive bytes in
+//! chunks use a [`Scanner`] to find where the first top-level value ends
+//! without parsing it. It tracks nesting, strings and comments across chunk
+//! boundaries; all the bytes it inspects are ASCII, so multibyte characters
+//! pass through untouched.
+
+/// Lexical state carried between chunks
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Between tokens
+    Code,
+    /// Saw a `/` that may start a comment
+    Slash,
+    /// Inside a `//` comment
+    LineComment,
+    /// Inside a `/* */` comment, `star` if the last byte was `*`
+    BlockComment { star: bool },
+    /// Inside a string opened with `quote`
+    String { quote: u8, escape: bool },
+    /// Inside an unquoted top-level scalar (number, literal, UUID, ...)
+    Scalar,
+}
+
+/// Finds the end of the first top-level value in a byte stream
+#[derive(Debug, Clone)]
+pub(crate) struct Scanner {
+    state: State,
+    depth: usize,
+    started: bool,
+    done: bool,
+}
+
+impl Scanner {
+    pub(crate) fn new() -> Self {
+        Scanner {
+            state: State::Code,
+            depth: 0,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Whether any part of a value (not just whitespace or comments) has been seen
+    pub(crate) fn started(&self) -> bool {
+        self.started
+    }
+
+    /// Whether the input seen so far ends a complete value at end of input
+    pub(crate) fn complete_at_eof(&self) -> bool {
+        self.done || (self.state == State::Scalar && self.depth == 0)
+    }
+
+    /// Scan the next chunk.
+    ///
+    /// Returns how many bytes of `chunk` belong to the value once it is
+    /// complete, or `None` if the value continues into the next chunk.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Option<usize> {
+        if self.done {
+            return Some(0);
+        }
+        for (i, &b) in chunk.iter().enumerate() {
+            if self.step(b) {
+                self.done = true;
+                // A scalar ends at its delimiter, which is not part of it
+                return Some(if self.state == State::Scalar {
+                    i
+                } else {
+                    i + 1
+                });
+            }
+        }
+        None
+    }
+
+    /// Advance by one byte, returning whether the value just ended
+    fn step(&mut self, b: u8) -> bool {
+        match self.state {
+            State::String { quote, escape } => {
+                if escape {
+                    self.state = State::String {
+                        quote,
+                        escape: false,
+                    };
+                } else if b == b'\\' {
+                    self.state = State::String {
+                        quote,
+                        escape: true,
+                    };
+                } else if b == quote {
+                    self.state = State::Code;
+                    return self.depth == 0;
+                }
+                false
+            }
+            State::LineComment => {
+                if b == b'\n' {
+                    self.state = State::Code;
+                }
+                false
+            }
+            State::BlockComment { star } => {
+                self.state = if star && b == b'/' {
+                    State::Code
+                } else {
+                    State::BlockComment { star: b == b'*' }
+                };
+                false
+            }
+            State::Slash => match b {
+                b'/' => {
+                    self.state = State::LineComment;
+                    false
+                }
+                b'*' => {
+                    self.state = State::BlockComment { star: false };
+                    false
+                }
+                _ => {
+                    // Not a comment; let the parser report the stray `/`
+                    self.state = State::Code;
+                    self.started = true;
+                    self.step(b)
+                }
+            },
+            State::Scalar => matches!(
+                b,
+                b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}' | b'/'
+            ),
+            State::Code => match b {
+                b' ' | b'\t' | b'\n' | b'\r' => false,
+                b'/' => {
+                    self.state = State::Slash;
+                    false
+                }
+                b'"' | b'\'' | b'`' => {
+                    self.started = true;
+                    self.state = State::String {
+                        quote: b,
+                        escape: false,
+                    };
+                    false
+                }
+                b'[' | b'{' => {
+                    self.started = true;
+                    self.depth += 1;
+                    false
+                }
+                b']' | b'}' => {
+                    self.started = true;
+                    self.depth = self.depth.saturating_sub(1);
+                    self.depth == 0
+                }
+                _ => {
+                    self.started = true;
+                    if self.depth == 0 {
+                        self.state = State::Scalar;
+                    }
+                    false
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boundary(chunks: &[&str]) -> Option<usize> {
+        let mut scanner = Scanner::new();
+        let mut offset = 0;
+        for chunk in chunks {
+            if let Some(n) = scanner.feed(chunk.as_bytes()) {
+                return Some(offset + n);
+            }
+            offset += chunk.len();
+        }
+        None
+    }
+
+    #[test]
+    fn test_container_boundaries() {
+        assert_eq!(boundary(&["{ a: [1, 2] } trailing"]), Some(13));
+        assert_eq!(
+            boundary(&["{ a: '}', b", ": \"]\\\"\" }", "[next]"]),
+            Some(20)
+        );
+        assert_eq!(boundary(&["// }\n[1, /* ] */ 2", "]"]), Some(19));
+        assert_eq!(boundary(&["{ a: 1"]), None);
+    }
+
+    #[test]
+    fn test_scalar_boundaries() {
+        assert_eq!(boundary(&["  12", "3.5m\n"]), Some(8));
+        assert_eq!(boundary(&["'a b' x"]), Some(5));
+
+        let mut scanner = Scanner::new();
+        assert_eq!(scanner.feed(b"PT1H"), None);
+        assert!(scanner.complete_at_eof());
+
+        let mut scanner = Scanner::new();
+        assert_eq!(scanner.feed(b" /* only a comment */ "), None);
+        assert!(!scanner.started());
+        assert!(!scanner.complete_at_eof());
+    }
+}
@@ -0,0 +1,1249 @@
+//! Schema validation for kJSON values
+//!
+//! Schemas are themselves kJSON documents:
+//!
+//! ```text
+//! {
+//!   type: "object",
+//!   required: ["id", "price"],
+//!   properties: {
+//!     id: { type: "uuid" },
+//!     price: { type: "decimal", min: 0m },
+//!     tags: { type: "array", items: { type: "string", pattern: "^[a-z]+$" } },
+//!   },
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::pointer;
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use crate::warnings::{self, Warning};
+use rand::Rng;
+use regex::Regex;
+use regex_syntax::hir::{Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Attempts at a string that matches a pattern within the length limits
+const PATTERN_ATTEMPTS: usize = 32;
+/// Default range of generated timestamps, 2020 through 2029
+const GENERATED_NANOS: (i128, i128) = (1_577_836_800_000_000_000, 1_893_455_999_999_999_999);
+
+/// Value types a schema can require
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    /// Matches any value
+    Any,
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Boolean,
+    /// Any number
+    Number,
+    /// A number without a fractional part
+    Integer,
+    /// A string
+    String,
+    /// An array
+    Array,
+    /// An object
+    Object,
+    /// A BigInt (`123n`)
+    BigInt,
+    /// A Decimal128 (`1.5m`)
+    Decimal,
+    /// A UUID
+    Uuid,
+    /// A timestamp (Instant or legacy Date)
+    Instant,
+    /// An ISO 8601 duration
+    Duration,
+    /// Binary data
+    Binary,
+}
+
+impl Type {
+    /// Schema keyword for this type
+    pub fn name(self) -> &'static str {
+        match self {
+            Type::Any => "any",
+            Type::Null => "null",
+            Type::Boolean => "boolean",
+            Type::Number => "number",
+            Type::Integer => "integer",
+            Type::String => "string",
+            Type::Array => "array",
+            Type::Object => "object",
+            Type::BigInt => "bigint",
+            Type::Decimal => "decimal",
+            Type::Uuid => "uuid",
+            Type::Instant => "instant",
+            Type::Duration => "duration",
+            Type::Binary => "binary",
+        }
+    }
+
+    /// Look up a type from its schema keyword
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "any" => Some(Type::Any),
+            "null" => Some(Type::Null),
+            "boolean" => Some(Type::Boolean),
+            "number" => Some(Type::Number),
+            "integer" => Some(Type::Integer),
+            "string" => Some(Type::String),
+            "array" => Some(Type::Array),
+            "object" => Some(Type::Object),
+            "bigint" => Some(Type::BigInt),
+            "decimal" | "decimal128" => Some(Type::Decimal),
+            "uuid" => Some(Type::Uuid),
+            "instant" | "date" => Some(Type::Instant),
+            "duration" => Some(Type::Duration),
+            "binary" => Some(Type::Binary),
+            _ => None,
+        }
+    }
+
+    /// Check whether a value has this type
+    pub fn matches(self, value: &Value) -> bool {
+        match (self, value) {
+            (Type::Any, _) => true,
+            (Type::Null, Value::Null) => true,
+            (Type::Boolean, Value::Bool(_)) => true,
+            (Type::Number, Value::Number(_) | Value::Integer(_) | Value::UInteger(_)) => true,
+            (Type::Integer, Value::Number(n)) => n.fract() == 0.0,
+            (Type::Integer, Value::Integer(_) | Value::UInteger(_)) => true,
+            (Type::String, Value::String(_)) => true,
+            (Type::Array, Value::Array(_)) => true,
+            (Type::Object, Value::Object(_)) => true,
+            (Type::BigInt, Value::BigInt(_)) => true,
+            (Type::Decimal, Value::Decimal128(_)) => true,
+            (Type::Uuid, Value::Uuid(_)) => true,
+            (Type::Instant, Value::Instant(_) | Value::Date(_)) => true,
+            (Type::Duration, Value::Duration(_)) => true,
+            (Type::Binary, Value::Binary(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A single schema violation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON Pointer to the offending value (empty for the root)
+    pub path: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "(root): {}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// A compiled schema
+///
+/// Build one from a kJSON document with [`Schema::parse`], or in code:
+///
+/// ```
+/// use kjson::schema::{Schema, Type};
+///
+/// let schema = Schema::object()
+///     .field("id", Type::Uuid)
+///     .field("price", Type::Decimal.min("0m"))
+///     .optional("tags", Schema::array(Type::String));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Schema {
+    pub(crate) types: Vec<Type>,
+    pub(crate) required: Vec<String>,
+    pub(crate) properties: BTreeMap<String, Schema>,
+    pub(crate) additional_properties: bool,
+    pub(crate) items: Option<Box<Schema>>,
+    pub(crate) min: Option<Value>,
+    pub(crate) max: Option<Value>,
+    pub(crate) min_length: Option<usize>,
+    pub(crate) max_length: Option<usize>,
+    pub(crate) min_items: Option<usize>,
+    pub(crate) max_items: Option<usize>,
+    pub(crate) pattern: Option<Regex>,
+    pub(crate) enum_values: Option<Vec<Value>>,
+    pub(crate) convert: Option<String>,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Schema {
+            types: Vec::new(),
+            required: Vec::new(),
+            properties: BTreeMap::new(),
+            additional_properties: true,
+            items: None,
+            min: None,
+            max: None,
+            min_length: None,
+            max_length: None,
+            min_items: None,
+            max_items: None,
+            pattern: None,
+            enum_values: None,
+            convert: None,
+        }
+    }
+}
+
+impl Schema {
+    /// Parse a schema from kJSON text
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_value(&parse(input)?)
+    }
+
+    /// Compile a schema from a parsed kJSON document
+    pub fn from_value(value: &Value) -> Result<Self> {
+        Self::compile(value, "")
+    }
+
+    fn compile(value: &Value, path: &str) -> Result<Self> {
+        let obj = match value {
+            Value::Object(obj) => obj,
+            other => {
+                return Err(invalid(path, format!("expected object, got {}", other.type_name())));
+            }
+        };
+
+        let mut schema = Schema::default();
+
+        for (key, val) in obj {
+            let key_path = pointer::push(path, key);
+            match key.as_str() {
+                "type" => {
+                    schema.types = match val {
+                        Value::String(name) => vec![type_from_name(name, &key_path)?],
+                        Value::Array(names) => names
+                            .iter()
+                            .map(|n| match n {
+                                Value::String(name) => type_from_name(name, &key_path),
+                                _ => Err(invalid(&key_path, "type names must be strings")),
+                            })
+                            .collect::<Result<_>>()?,
+                        _ => return Err(invalid(&key_path, "expected string or array")),
+                    };
+                }
+                "required" => {
+                    let names = val
+                        .as_array()
+                        .ok_or_else(|| invalid(&key_path, "expected array of strings"))?;
+                    for name in names {
+                        let name = name
+                            .as_str()
+                            .ok_or_else(|| invalid(&key_path, "expected array of strings"))?;
+                        schema.required.push(name.to_string());
+                    }
+                }
+                "properties" => {
+                    let props = val
+                        .as_object()
+                        .ok_or_else(|| invalid(&key_path, "expected object"))?;
+                    for (name, prop) in props {
+                        let prop_path = pointer::push(&key_path, name);
+                        schema
+                            .properties
+                            .insert(name.to_string(), Self::compile(prop, &prop_path)?);
+                    }
+                }
+                "additionalProperties" => {
+                    schema.additional_properties = val
+                        .as_bool()
+                        .ok_or_else(|| invalid(&key_path, "expected boolean"))?;
+                }
+                "items" => schema.items = Some(Box::new(Self::compile(val, &key_path)?)),
+                "min" => schema.min = Some(bound(val, &key_path)?),
+                "max" => schema.max = Some(bound(val, &key_path)?),
+                "minLength" => schema.min_length = Some(count(val, &key_path)?),
+                "maxLength" => schema.max_length = Some(count(val, &key_path)?),
+                "minItems" => schema.min_items = Some(count(val, &key_path)?),
+                "maxItems" => schema.max_items = Some(count(val, &key_path)?),
+                "pattern" => {
+                    let pattern = val
+                        .as_str()
+                        .ok_or_else(|| invalid(&key_path, "expected string"))?;
+                    let re = Regex::new(pattern)
+                        .map_err(|e| invalid(&key_path, format!("invalid pattern: {}", e)))?;
+                    schema.pattern = Some(re);
+                }
+                "enum" => {
+                    let values = val
+                        .as_array()
+                        .ok_or_else(|| invalid(&key_path, "expected array"))?;
+                    schema.enum_values = Some(values.clone());
+                }
+                "convert" => {
+                    let name = val
+                        .as_str()
+                        .ok_or_else(|| invalid(&key_path, "expected string"))?;
+                    schema.convert = Some(name.to_string());
+                }
+                "title" | "description" => {}
+                _ => return Err(invalid(&key_path, "unknown schema keyword")),
+            }
+        }
+
+        Ok(schema)
+    }
+
+    /// Infer a schema describing all of the sample documents.
+    ///
+    /// Object fields are required when every sample object has them, and
+    /// values seen with different types become unions.
+    pub fn infer(samples: &[Value]) -> Self {
+        Self::infer_from(samples.iter())
+    }
+
+    fn infer_from<'a>(values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut schema = Schema::default();
+        let mut objects = Vec::new();
+        let mut elements = Vec::new();
+        let mut saw_array = false;
+
+        for value in values {
+            let t = match value {
+                Value::Null => Type::Null,
+                Value::Bool(_) => Type::Boolean,
+                Value::Number(n) if n.fract() == 0.0 => Type::Integer,
+                Value::Number(_) => Type::Number,
+                Value::Integer(_) | Value::UInteger(_) => Type::Integer,
+                Value::String(_) => Type::String,
+                Value::Array(items) => {
+                    saw_array = true;
+                    elements.extend(items.iter());
+                    Type::Array
+                }
+                Value::Object(obj) => {
+                    objects.push(obj);
+                    Type::Object
+                }
+                Value::BigInt(_) => Type::BigInt,
+                Value::Decimal128(_) => Type::Decimal,
+                Value::Uuid(_) => Type::Uuid,
+                Value::Date(_) | Value::Instant(_) => Type::Instant,
+                Value::Duration(_) => Type::Duration,
+                Value::Binary(_) => Type::Binary,
+            };
+            if !schema.types.contains(&t) {
+                schema.types.push(t);
+            }
+        }
+
+        // Integers are numbers too; keep the wider type when both appear
+        if schema.types.contains(&Type::Number) {
+            schema.types.retain(|t| *t != Type::Integer);
+        }
+
+        if !objects.is_empty() {
+            let mut keys: Vec<&Str> = objects.iter().flat_map(|o| o.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let present: Vec<&Value> = objects.iter().filter_map(|o| o.get(key)).collect();
+                if present.len() == objects.len() {
+                    schema.required.push(key.to_string());
+                }
+                schema
+                    .properties
+                    .insert(key.to_string(), Self::infer_from(present.into_iter()));
+            }
+        }
+
+        if saw_array && !elements.is_empty() {
+            schema.items = Some(Box::new(Self::infer_from(elements.into_iter())));
+        }
+
+        schema
+    }
+
+    /// Validate a value, returning every violation found.
+    ///
+    /// Members missing from the `properties` of a schema that allows them
+    /// raise a [`Warning::UnknownField`].
+    pub fn validate(&self, value: &Value) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.validate_at(value, "", &mut violations);
+        violations
+    }
+
+    /// Check whether a value satisfies the schema
+    pub fn is_valid(&self, value: &Value) -> bool {
+        self.validate(value).is_empty()
+    }
+
+    fn validate_at(&self, value: &Value, path: &str, out: &mut Vec<Violation>) {
+        let mut report = |message: String| {
+            out.push(Violation {
+                path: path.to_string(),
+                message,
+            })
+        };
+
+        if !self.types.is_empty() && !self.types.iter().any(|t| t.matches(value)) {
+            let expected: Vec<_> = self.types.iter().map(|t| t.name()).collect();
+            report(format!(
+                "expected {}, got {}",
+                expected.join(" or "),
+                value.type_name()
+            ));
+            // Further checks would only repeat the type mismatch
+            return;
+        }
+
+        if let Some(allowed) = &self.enum_values {
+            if !allowed.contains(value) {
+                report("value is not one of the allowed values".to_string());
+            }
+        }
+
+        if let Some(min) = &self.min {
+            match compare(value, min) {
+                Some(Ordering::Less) => report(format!("value is below minimum {}", describe(min))),
+                None => report(format!("value cannot be compared with {}", describe(min))),
+                _ => {}
+            }
+        }
+        if let Some(max) = &self.max {
+            match compare(value, max) {
+                Some(Ordering::Greater) => report(format!("value is above maximum {}", describe(max))),
+                None => report(format!("value cannot be compared with {}", describe(max))),
+                _ => {}
+            }
+        }
+
+        match value {
+            Value::String(s) => {
+                let len = s.chars().count();
+                if let Some(min) = self.min_length {
+                    if len < min {
+                        report(format!("string is shorter than {} characters", min));
+                    }
+                }
+                if let Some(max) = self.max_length {
+                    if len > max {
+                        report(format!("string is longer than {} characters", max));
+                    }
+                }
+                if let Some(re) = &self.pattern {
+                    if !re.is_match(s) {
+                        report(format!("string does not match pattern {}", re.as_str()));
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = self.min_items {
+                    if items.len() < min {
+                        report(format!("array has fewer than {} items", min));
+                    }
+                }
+                if let Some(max) = self.max_items {
+                    if items.len() > max {
+                        report(format!("array has more than {} items", max));
+                    }
+                }
+                if let Some(item_schema) = &self.items {
+                    for (i, item) in items.iter().enumerate() {
+                        item_schema.validate_at(item, &pointer::push_index(path, i), out);
+                    }
+                }
+            }
+            Value::Object(obj) => {
+                for name in &self.required {
+                    if !obj.contains_key(name.as_str()) {
+                        out.push(Violation {
+                            path: pointer::push(path, name),
+                            message: "required field is missing".to_string(),
+                        });
+                    }
+                }
+                for (name, prop_schema) in &self.properties {
+                    if let Some(prop) = obj.get(name.as_str()) {
+                        prop_schema.validate_at(prop, &pointer::push(path, name), out);
+                    }
+                }
+                if !self.additional_properties || !self.properties.is_empty() {
+                    let mut extra: Vec<_> = obj
+                        .keys()
+                        .filter(|k| !self.properties.contains_key(k.as_str()))
+                        .collect();
+                    extra.sort();
+                    for name in extra {
+                        if self.additional_properties {
+                            warnings::warn(|| Warning::UnknownField {
+                                path: pointer::push(path, name),
+                            });
+                            continue;
+                        }
+                        out.push(Violation {
+                            path: pointer::push(path, name),
+                            message: "unexpected field".to_string(),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Generate a random value satisfying the schema, for load tests and
+    /// property-based tests.
+    ///
+    /// Optional fields are included about half the time. Missing bounds
+    /// default to small ranges, timestamps to 2020 through 2029, and
+    /// strings are drawn from `pattern` when there is one. Patterns
+    /// combined with length limits or word boundaries are retried a few
+    /// times and may still yield an invalid string.
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Value {
+        if let Some(values) = self.enum_values.as_ref().filter(|v| !v.is_empty()) {
+            return values[rng.gen_range(0..values.len())].clone();
+        }
+        let t = match self.types.as_slice() {
+            [] => self.implied_type(rng),
+            types => match types[rng.gen_range(0..types.len())] {
+                Type::Any => self.implied_type(rng),
+                t => t,
+            },
+        };
+        match t {
+            Type::Any | Type::Null => Value::Null,
+            Type::Boolean => Value::Bool(rng.gen()),
+            Type::Number => {
+                let lo = self.min.as_ref().and_then(numeric);
+                let hi = self.max.as_ref().and_then(numeric);
+                let (lo, hi) = match (lo, hi) {
+                    (Some(lo), Some(hi)) => (lo, hi.max(lo)),
+                    (Some(lo), None) => (lo, lo + 1000.0),
+                    (None, Some(hi)) => (hi - 1000.0, hi),
+                    (None, None) => (-1000.0, 1000.0),
+                };
+                Value::Number(rng.gen_range(lo..=hi))
+            }
+            Type::Integer => Value::Integer(self.int_in_range(rng, 1.0, (-1000, 1000)) as i64),
+            Type::BigInt => Value::BigInt(BigInt::from_i128(
+                self.int_in_range(rng, 1.0, (-1_000_000, 1_000_000)),
+            )),
+            Type::Decimal => {
+                let cents = self.int_in_range(rng, 100.0, (0, 100_000));
+                Value::Decimal128(Decimal128::from_parts(&BigInt::from_i128(cents), -2))
+            }
+            Type::Uuid => Value::Uuid(uuid::Builder::from_random_bytes(rng.gen()).into_uuid()),
+            Type::Instant => {
+                let nanos = self.int_in_range(rng, 1.0, GENERATED_NANOS);
+                Value::Instant(Instant::from_nanos(nanos as i64))
+            }
+            Type::Duration => {
+                let day = 86_400 * 1_000_000_000;
+                Value::Duration(Duration::from_nanos(self.int_in_range(rng, 1.0, (0, day)) as i64))
+            }
+            Type::String => Value::string(self.generate_string(rng)),
+            Type::Binary => {
+                let len = rng.gen_range(0..=32);
+                Value::Binary((0..len).map(|_| rng.gen()).collect())
+            }
+            Type::Array => {
+                let min = self.min_items.unwrap_or(0);
+                let max = self.max_items.unwrap_or(min + 4).max(min);
+                let items = self.items.as_deref().cloned().unwrap_or_default();
+                Value::Array((0..rng.gen_range(min..=max)).map(|_| items.generate(rng)).collect())
+            }
+            Type::Object => {
+                let mut obj = Map::new();
+                for (name, prop) in &self.properties {
+                    if self.required.contains(name) || rng.gen_bool(0.5) {
+                        obj.insert(Str::from(name.as_str()), prop.generate(rng));
+                    }
+                }
+                for name in &self.required {
+                    if !obj.contains_key(name.as_str()) {
+                        obj.insert(Str::from(name.as_str()), Schema::any().generate(rng));
+                    }
+                }
+                Value::Object(obj)
+            }
+        }
+    }
+
+    /// Type to generate for a schema without one, guessed from its
+    /// constraints
+    fn implied_type<R: Rng + ?Sized>(&self, rng: &mut R) -> Type {
+        if !self.properties.is_empty() || !self.required.is_empty() {
+            return Type::Object;
+        }
+        if self.items.is_some() || self.min_items.is_some() || self.max_items.is_some() {
+            return Type::Array;
+        }
+        if self.pattern.is_some() || self.min_length.is_some() || self.max_length.is_some() {
+            return Type::String;
+        }
+        match self.min.as_ref().or(self.max.as_ref()) {
+            Some(Value::BigInt(_)) => Type::BigInt,
+            Some(Value::Decimal128(_)) => Type::Decimal,
+            Some(Value::Instant(_) | Value::Date(_)) => Type::Instant,
+            Some(Value::Duration(_)) => Type::Duration,
+            Some(_) => Type::Number,
+            None => [Type::Null, Type::Boolean, Type::Integer, Type::String][rng.gen_range(0..4)],
+        }
+    }
+
+    /// Random integer between the bounds multiplied by `scale`, or in
+    /// `default` (shifted to meet a single bound) when they are missing
+    fn int_in_range<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        scale: f64,
+        default: (i128, i128),
+    ) -> i128 {
+        let lo = self.min.as_ref().and_then(|b| int_bound(b, scale, true));
+        let hi = self.max.as_ref().and_then(|b| int_bound(b, scale, false));
+        let width = default.1 - default.0;
+        let (lo, hi) = match (lo, hi) {
+            (Some(lo), Some(hi)) => (lo, hi.max(lo)),
+            (Some(lo), None) => (lo, lo.saturating_add(width)),
+            (None, Some(hi)) => (hi.saturating_sub(width), hi),
+            (None, None) => default,
+        };
+        rng.gen_range(lo..=hi)
+    }
+
+    fn generate_string<R: Rng + ?Sized>(&self, rng: &mut R) -> String {
+        let min = self.min_length.unwrap_or(0);
+        let max = self.max_length.unwrap_or(min + 16).max(min);
+        let hir = self
+            .pattern
+            .as_ref()
+            .and_then(|re| regex_syntax::Parser::new().parse(re.as_str()).ok());
+        let Some(hir) = hir else {
+            return (0..rng.gen_range(min..=max))
+                .map(|_| rng.sample(rand::distributions::Alphanumeric) as char)
+                .collect();
+        };
+        let mut out = String::new();
+        for _ in 0..PATTERN_ATTEMPTS {
+            out.clear();
+            sample_pattern(&hir, rng, &mut out);
+            let matches = self.pattern.as_ref().is_some_and(|re| re.is_match(&out));
+            if matches && (min..=max).contains(&out.chars().count()) {
+                break;
+            }
+        }
+        out
+    }
+}
+
+/// Validate a value against a schema
+pub fn validate(schema: &Schema, value: &Value) -> Vec<Violation> {
+    schema.validate(value)
+}
+
+/// Generate a random value satisfying a schema
+///
+/// ```
+/// use kjson::schema::{generate, Schema};
+/// use rand::SeedableRng;
+///
+/// let schema = Schema::parse("{ type: 'decimal', min: 1m, max: 5m }")?;
+/// let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+/// assert!(schema.is_valid(&generate(&schema, &mut rng)));
+/// # Ok::<(), kjson::Error>(())
+/// ```
+pub fn generate<R: Rng + ?Sized>(schema: &Schema, rng: &mut R) -> Value {
+    schema.generate(rng)
+}
+
+/// A range bound for [`Schema::min`] and [`Schema::max`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bound(Value);
+
+impl From<f64> for Bound {
+    fn from(n: f64) -> Self {
+        Bound(Value::Number(n))
+    }
+}
+
+impl From<i64> for Bound {
+    fn from(n: i64) -> Self {
+        Bound(Value::Integer(n))
+    }
+}
+
+impl From<i32> for Bound {
+    fn from(n: i32) -> Self {
+        Bound(Value::Integer(n.into()))
+    }
+}
+
+impl From<BigInt> for Bound {
+    fn from(b: BigInt) -> Self {
+        Bound(Value::BigInt(b))
+    }
+}
+
+impl From<Decimal128> for Bound {
+    fn from(d: Decimal128) -> Self {
+        Bound(Value::Decimal128(d))
+    }
+}
+
+impl From<Instant> for Bound {
+    fn from(i: Instant) -> Self {
+        Bound(Value::Instant(i))
+    }
+}
+
+impl From<Duration> for Bound {
+    fn from(d: Duration) -> Self {
+        Bound(Value::Duration(d))
+    }
+}
+
+/// Parse a kJSON literal such as `"0m"`, `"10n"` or `"PT1H"`.
+///
+/// # Panics
+///
+/// Panics if the literal is not a number, BigInt, Decimal128, timestamp or
+/// duration.
+impl From<&str> for Bound {
+    fn from(literal: &str) -> Self {
+        match parse(literal).and_then(|v| bound(&v, "")) {
+            Ok(value) => Bound(value),
+            Err(_) => panic!("invalid schema bound literal: {:?}", literal),
+        }
+    }
+}
+
+impl From<Type> for Schema {
+    fn from(t: Type) -> Self {
+        Schema::of(t)
+    }
+}
+
+impl Type {
+    /// Schema of this type with a lower bound
+    pub fn min(self, bound: impl Into<Bound>) -> Schema {
+        Schema::of(self).min(bound)
+    }
+
+    /// Schema of this type with an upper bound
+    pub fn max(self, bound: impl Into<Bound>) -> Schema {
+        Schema::of(self).max(bound)
+    }
+
+    /// Schema of this type restricted to a regex pattern
+    pub fn pattern(self, pattern: &str) -> Schema {
+        Schema::of(self).pattern(pattern)
+    }
+
+    /// Schema of this type restricted to a fixed set of values
+    pub fn one_of(self, values: impl IntoIterator<Item = Value>) -> Schema {
+        Schema::of(self).one_of(values)
+    }
+
+    /// Schema accepting either this type or another
+    pub fn or(self, other: Type) -> Schema {
+        Schema::of(self).or(other)
+    }
+}
+
+impl Schema {
+    /// Schema accepting any value
+    pub fn any() -> Self {
+        Schema::default()
+    }
+
+    /// Schema requiring a single type
+    pub fn of(t: Type) -> Self {
+        Schema {
+            types: vec![t],
+            ..Schema::default()
+        }
+    }
+
+    /// Object schema; add fields with [`Schema::field`] and [`Schema::optional`]
+    pub fn object() -> Self {
+        Schema::of(Type::Object)
+    }
+
+    /// Array schema whose items all match `items`
+    pub fn array(items: impl Into<Schema>) -> Self {
+        Schema::of(Type::Array).items(items)
+    }
+
+    /// Add a required field
+    pub fn field(mut self, name: &str, schema: impl Into<Schema>) -> Self {
+        if !self.required.iter().any(|r| r == name) {
+            self.required.push(name.to_string());
+        }
+        self.properties.insert(name.to_string(), schema.into());
+        self
+    }
+
+    /// Add an optional field
+    pub fn optional(mut self, name: &str, schema: impl Into<Schema>) -> Self {
+        self.required.retain(|r| r != name);
+        self.properties.insert(name.to_string(), schema.into());
+        self
+    }
+
+    /// Reject object fields that are not declared
+    pub fn deny_unknown_fields(mut self) -> Self {
+        self.additional_properties = false;
+        self
+    }
+
+    /// Set the schema for array items
+    pub fn items(mut self, items: impl Into<Schema>) -> Self {
+        self.items = Some(Box::new(items.into()));
+        self
+    }
+
+    /// Also accept another type
+    pub fn or(mut self, t: Type) -> Self {
+        if !self.types.contains(&t) {
+            self.types.push(t);
+        }
+        self
+    }
+
+    /// Set an inclusive lower bound
+    pub fn min(mut self, bound: impl Into<Bound>) -> Self {
+        self.min = Some(bound.into().0);
+        self
+    }
+
+    /// Set an inclusive upper bound
+    pub fn max(mut self, bound: impl Into<Bound>) -> Self {
+        self.max = Some(bound.into().0);
+        self
+    }
+
+    /// Set the minimum string length in characters
+    pub fn min_length(mut self, len: usize) -> Self {
+        self.min_length = Some(len);
+        self
+    }
+
+    /// Set the maximum string length in characters
+    pub fn max_length(mut self, len: usize) -> Self {
+        self.max_length = Some(len);
+        self
+    }
+
+    /// Set the minimum number of array items
+    pub fn min_items(mut self, n: usize) -> Self {
+        self.min_items = Some(n);
+        self
+    }
+
+    /// Set the maximum number of array items
+    pub fn max_items(mut self, n: usize) -> Self {
+        self.max_items = Some(n);
+        self
+    }
+
+    /// Require strings to match a regex pattern
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern` is not a valid regular expression.
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(re) => self.pattern = Some(re),
+            Err(e) => panic!("invalid schema pattern {:?}: {}", pattern, e),
+        }
+        self
+    }
+
+    /// Restrict values to a fixed set
+    pub fn one_of(mut self, values: impl IntoIterator<Item = Value>) -> Self {
+        self.enum_values = Some(values.into_iter().collect());
+        self
+    }
+
+    /// Name the [`Converters`](crate::convert::Converters) annotation
+    /// applied to matching values
+    pub fn convert(mut self, name: &str) -> Self {
+        self.convert = Some(name.to_string());
+        self
+    }
+}
+
+fn invalid(path: &str, message: impl fmt::Display) -> Error {
+    if path.is_empty() {
+        Error::InvalidSchema(message.to_string())
+    } else {
+        Error::InvalidSchema(format!("{}: {}", path, message))
+    }
+}
+
+fn type_from_name(name: &str, path: &str) -> Result<Type> {
+    Type::from_name(name).ok_or_else(|| invalid(path, format!("unknown type '{}'", name)))
+}
+
+fn count(value: &Value, path: &str) -> Result<usize> {
+    value
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| invalid(path, "expected non-negative integer"))
+}
+
+fn bound(value: &Value, path: &str) -> Result<Value> {
+    match value {
+        Value::Number(_)
+        | Value::Integer(_)
+        | Value::UInteger(_)
+        | Value::BigInt(_)
+        | Value::Decimal128(_)
+        | Value::Instant(_)
+        | Value::Date(_)
+        | Value::Duration(_) => Ok(value.clone()),
+        other => Err(invalid(
+            path,
+            format!("{} cannot be used as a bound", other.type_name()),
+        )),
+    }
+}
+
+fn describe(value: &Value) -> String {
+    crate::serializer::to_string(value).unwrap_or_else(|_| value.type_name().to_string())
+}
+
+/// Order two values for range checks; `None` if they are not comparable
+fn compare(value: &Value, bound: &Value) -> Option<Ordering> {
+    match (value, bound) {
+        (Value::BigInt(a), Value::BigInt(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::UInteger(a), Value::UInteger(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::UInteger(b)) => Some(i128::from(*a).cmp(&i128::from(*b))),
+        (Value::UInteger(a), Value::Integer(b)) => Some(i128::from(*a).cmp(&i128::from(*b))),
+        (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+        (Value::Instant(_) | Value::Date(_), Value::Instant(_) | Value::Date(_)) => {
+            Some(timestamp_nanos(value)?.cmp(&timestamp_nanos(bound)?))
+        }
+        _ => numeric(value)?.partial_cmp(&numeric(bound)?),
+    }
+}
+
+fn numeric(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(*n),
+        Value::Integer(n) => Some(*n as f64),
+        Value::UInteger(n) => Some(*n as f64),
+        Value::BigInt(b) => Some(b.to_f64()),
+        Value::Decimal128(d) => Some(d.to_f64()),
+        _ => None,
+    }
+}
+
+/// Integer form of a bound for generation, rounded to stay inside it
+fn int_bound(bound: &Value, scale: f64, round_up: bool) -> Option<i128> {
+    match bound {
+        Value::BigInt(b) if scale == 1.0 => b.to_i128(),
+        Value::Integer(n) if scale == 1.0 => Some((*n).into()),
+        Value::UInteger(n) if scale == 1.0 => Some((*n).into()),
+        Value::Instant(_) | Value::Date(_) => timestamp_nanos(bound).map(i128::from),
+        Value::Duration(d) => Some(d.total_nanos().into()),
+        _ => {
+            let n = numeric(bound)? * scale;
+            Some(if round_up { n.ceil() } else { n.floor() } as i128)
+        }
+    }
+}
+
+/// Append a random string matching `hir`, preferring printable ASCII
+fn sample_pattern<R: Rng + ?Sized>(hir: &Hir, rng: &mut R, out: &mut String) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(lit) => out.push_str(&String::from_utf8_lossy(&lit.0)),
+        HirKind::Class(Class::Unicode(class)) => {
+            let mut ascii = ClassUnicode::new([ClassUnicodeRange::new(' ', '~')]);
+            ascii.intersect(class);
+            let ranges = match ascii.ranges() {
+                [] => class.ranges(),
+                ranges => ranges,
+            };
+            if let Some(range) = ranges.get(rng.gen_range(0..ranges.len().max(1))) {
+                let c = rng.gen_range(u32::from(range.start())..=u32::from(range.end()));
+                out.push(char::from_u32(c).unwrap_or(range.start()));
+            }
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            let ranges = class.ranges();
+            if let Some(range) = ranges.get(rng.gen_range(0..ranges.len().max(1))) {
+                out.push(char::from(rng.gen_range(range.start()..=range.end())));
+            }
+        }
+        HirKind::Repetition(rep) => {
+            let max = rep.max.unwrap_or(rep.min + 4).min(rep.min + 16);
+            for _ in 0..rng.gen_range(rep.min..=max) {
+                sample_pattern(&rep.sub, rng, out);
+            }
+        }
+        HirKind::Capture(cap) => sample_pattern(&cap.sub, rng, out),
+        HirKind::Concat(hirs) => {
+            for hir in hirs {
+                sample_pattern(hir, rng, out);
+            }
+        }
+        HirKind::Alternation(hirs) => {
+            sample_pattern(&hirs[rng.gen_range(0..hirs.len())], rng, out);
+        }
+    }
+}
+
+fn timestamp_nanos(value: &Value) -> Option<i64> {
+    match value {
+        Value::Instant(i) => Some(i.epoch_nanos()),
+        Value::Date(d) => d.utc.timestamp_nanos_opt(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER_SCHEMA: &str = r#"{
+        type: "object",
+        required: ["id", "price", "items"],
+        additionalProperties: false,
+        properties: {
+            id: { type: "uuid" },
+            price: { type: "decimal", min: 0m },
+            quantity: { type: ["integer", "bigint"], min: 1 },
+            created: { type: "instant", min: 2020-01-01T00:00:00Z },
+            ttl: { type: "duration", max: PT24H },
+            items: {
+                type: "array",
+                minItems: 1,
+                items: {
+                    type: "object",
+                    required: ["sku"],
+                    properties: { sku: { type: "string", pattern: "^[A-Z]{3}-\\d+$" } },
+                },
+            },
+        },
+    }"#;
+
+    fn paths(violations: &[Violation]) -> Vec<&str> {
+        violations.iter().map(|v| v.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let schema = Schema::parse(ORDER_SCHEMA).unwrap();
+        let doc = parse(
+            r#"{
+                id: 550e8400-e29b-41d4-a716-446655440000,
+                price: 19.99m,
+                quantity: 12345678901234567890n,
+                created: 2025-01-10T12:00:00Z,
+                ttl: PT1H,
+                items: [{ sku: "ABC-1" }],
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(schema.validate(&doc), vec![]);
+        assert!(schema.is_valid(&doc));
+    }
+
+    #[test]
+    fn test_violation_paths() {
+        let schema = Schema::parse(ORDER_SCHEMA).unwrap();
+        let doc = parse(
+            r#"{
+                id: "not-a-uuid",
+                price: -1m,
+                created: 2019-06-01T00:00:00Z,
+                ttl: P2D,
+                items: [{ sku: "ABC-1" }, { sku: "bad" }, {}],
+                extra: true,
+            }"#,
+        )
+        .unwrap();
+        let violations = schema.validate(&doc);
+        assert_eq!(
+            paths(&violations),
+            vec![
+                "/created",
+                "/id",
+                "/items/1/sku",
+                "/items/2/sku",
+                "/price",
+                "/ttl",
+                "/extra",
+            ]
+        );
+        assert_eq!(violations[1].message, "expected uuid, got string");
+        assert_eq!(violations[3].message, "required field is missing");
+    }
+
+    #[test]
+    fn test_unknown_field_warnings() {
+        let schema = Schema::object().field("id", Type::Uuid).optional("tags", Schema::any());
+        let doc = parse("{ id: 550e8400-e29b-41d4-a716-446655440000, colour: 'red', tgas: [] }").unwrap();
+        let (violations, warnings) = crate::warnings::collect(|| schema.validate(&doc));
+        assert_eq!(violations, vec![]);
+        assert_eq!(
+            warnings,
+            [
+                Warning::UnknownField { path: "/colour".to_string() },
+                Warning::UnknownField { path: "/tgas".to_string() },
+            ]
+        );
+
+        // Denied fields are violations, not warnings
+        let (violations, warnings) =
+            crate::warnings::collect(|| schema.deny_unknown_fields().validate(&doc));
+        assert_eq!(paths(&violations), vec!["/colour", "/tgas"]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_missing_required_and_type_mismatch() {
+        let schema = Schema::parse(ORDER_SCHEMA).unwrap();
+        let violations = schema.validate(&parse("{items: []}").unwrap());
+        assert_eq!(paths(&violations), vec!["/id", "/price", "/items"]);
+
+        let violations = schema.validate(&Value::Array(vec![]));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].to_string(), "(root): expected object, got array");
+    }
+
+    #[test]
+    fn test_string_constraints_and_enum() {
+        let schema = Schema::parse(
+            r#"{ type: "string", minLength: 2, maxLength: 4, enum: ["ab", "abc", "abcde"] }"#,
+        )
+        .unwrap();
+        assert!(schema.is_valid(&Value::String("abc".into())));
+        assert_eq!(schema.validate(&Value::String("abcde".into())).len(), 1);
+        assert_eq!(schema.validate(&Value::String("x".into())).len(), 2);
+    }
+
+    #[test]
+    fn test_builder_matches_document_schema() {
+        let built = Schema::object()
+            .field("id", Type::Uuid)
+            .field("price", Type::Decimal.min("0m"))
+            .optional("quantity", Type::Integer.or(Type::BigInt).min(1))
+            .optional("created", Type::Instant.min("2020-01-01T00:00:00Z"))
+            .optional("ttl", Type::Duration.max(Duration::from_hours(24)))
+            .field(
+                "items",
+                Schema::array(Schema::object().field("sku", Type::String.pattern("^[A-Z]{3}-\\d+$")))
+                    .min_items(1),
+            )
+            .deny_unknown_fields();
+        let parsed = Schema::parse(ORDER_SCHEMA).unwrap();
+
+        let doc = parse(
+            r#"{
+                id: "not-a-uuid",
+                price: -1m,
+                quantity: 0,
+                ttl: P2D,
+                items: [{ sku: "ABC-1" }, { sku: "bad" }],
+                extra: true,
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(built.validate(&doc), parsed.validate(&doc));
+        assert_eq!(built.validate(&doc).len(), 6);
+    }
+
+    #[test]
+    fn test_builder_constraints() {
+        let schema = Schema::of(Type::String)
+            .min_length(1)
+            .max_length(3)
+            .one_of(vec![Value::String("a".into()), Value::String("abcd".into())]);
+        assert!(schema.is_valid(&Value::String("a".into())));
+        assert_eq!(schema.validate(&Value::String("abcd".into())).len(), 1);
+        assert!(Schema::any().is_valid(&Value::Null));
+
+        let bigint = Type::BigInt.max(BigInt::from_i64(10));
+        assert!(bigint.is_valid(&Value::BigInt(BigInt::from_i64(10))));
+        assert!(!bigint.is_valid(&Value::BigInt(BigInt::from_i64(11))));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid schema bound literal")]
+    fn test_builder_rejects_bad_bound() {
+        let _ = Type::Decimal.min("zero");
+    }
+
+    #[test]
+    fn test_infer_from_samples() {
+        let samples = vec![
+            parse(r#"{ id: 550e8400-e29b-41d4-a716-446655440000, n: 1, tags: ["a"] }"#).unwrap(),
+            parse(r#"{ id: 550e8400-e29b-41d4-a716-446655440001, n: 1.5, extra: null }"#).unwrap(),
+        ];
+        let schema = Schema::infer(&samples);
+        assert_eq!(schema.types, vec![Type::Object]);
+        assert_eq!(schema.required, vec!["id", "n"]);
+        assert_eq!(schema.properties["id"].types, vec![Type::Uuid]);
+        assert_eq!(schema.properties["n"].types, vec![Type::Number]);
+        assert_eq!(schema.properties["tags"].items.as_ref().unwrap().types, vec![Type::String]);
+        for sample in &samples {
+            assert!(schema.is_valid(sample));
+        }
+    }
+
+    #[test]
+    fn test_generate_valid_documents() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let schemas = [
+            Schema::parse(ORDER_SCHEMA).unwrap(),
+            Schema::parse(r#"{ type: "string", pattern: "^[a-f0-9]{2,6}(-x)?$", maxLength: 4 }"#)
+                .unwrap(),
+            Schema::of(Type::Decimal).min("0.05m").max("0.10m"),
+            Schema::of(Type::Instant).max("2001-02-03T04:05:06Z"),
+            Schema::array(Type::Integer.min(-3).max(3)).min_items(2).max_items(2),
+            Schema::any().one_of(vec![Value::string("a"), Value::Null]),
+            Schema::object().field("n", Schema::any()).optional("d", Type::Duration.min("PT1M")),
+        ];
+        for schema in &schemas {
+            for _ in 0..100 {
+                let value = generate(schema, &mut rng);
+                assert_eq!(schema.validate(&value), vec![], "{:?}", value);
+            }
+        }
+
+        let seeded = |seed| generate(&schemas[0], &mut StdRng::seed_from_u64(seed));
+        assert_eq!(seeded(1), seeded(1));
+    }
+
+    #[test]
+    fn test_invalid_schemas() {
+        assert!(matches!(
+            Schema::parse(r#"{ type: "float" }"#),
+            Err(Error::InvalidSchema(_))
+        ));
+        assert!(Schema::parse(r#"{ properties: { a: { minLenght: 1 } } }"#).is_err());
+        assert!(Schema::parse(r#"{ pattern: "(" }"#).is_err());
+        assert!(Schema::parse(r#"{ min: "zero" }"#).is_err());
+        assert!(Schema::parse("[]").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! schemars `JsonSchema` support
+//!
+//! [`Value`] and the extended types implement [`JsonSchema`], so models
+//! that contain them can be documented with schemars and the OpenAPI tools
+//! built on it. Schemas describe the JSON form, where extended types are
+//! strings:
+//!
+//! | Type | JSON Schema |
+//! |------|-------------|
+//! | `BigInt` | `string` matching `^-?[0-9]+n$` |
+//! | `Decimal128` | `string` matching `^-?[0-9]+(\.[0-9]+)?m$` |
+//! | `Instant` | `string` with `format: "date-time"` |
+//! | `Duration` | `string` with `format: "duration"` |
+//! | `Value` | any value |
+//!
+//! ```
+//! let schema = schemars::schema_for!(kjson::BigInt);
+//! assert_eq!(schema.get("type").unwrap(), "string");
+//! ```
+
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::Value;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+
+impl JsonSchema for BigInt {
+    fn schema_name() -> Cow<'static, str> {
+        "BigInt".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "kjson::BigInt".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^-?[0-9]+n$",
+            "description": "Arbitrary-precision integer with an `n` suffix",
+            "examples": ["12345678901234567890n"],
+        })
+    }
+}
+
+impl JsonSchema for Decimal128 {
+    fn schema_name() -> Cow<'static, str> {
+        "Decimal128".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "kjson::Decimal128".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": "^-?[0-9]+(\\.[0-9]+)?m$",
+            "description": "Decimal number with an `m` suffix",
+            "examples": ["19.99m"],
+        })
+    }
+}
+
+impl JsonSchema for Instant {
+    fn schema_name() -> Cow<'static, str> {
+        "Instant".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "kjson::Instant".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "date-time",
+            "description": "UTC timestamp with nanosecond precision",
+        })
+    }
+}
+
+impl JsonSchema for Duration {
+    fn schema_name() -> Cow<'static, str> {
+        "Duration".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "kjson::Duration".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "duration",
+            "description": "ISO 8601 duration",
+            "examples": ["PT1H30M"],
+        })
+    }
+}
+
+impl JsonSchema for Value {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        "Value".into()
+    }
+
+    fn schema_id() -> Cow<'static, str> {
+        "kjson::Value".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "description": "Any kJSON value",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schemars::schema_for;
+
+    #[allow(dead_code)]
+    #[derive(JsonSchema)]
+    struct Order {
+        id: BigInt,
+        total: Decimal128,
+        placed: Instant,
+        ttl: Option<Duration>,
+        extra: Value,
+    }
+
+    #[test]
+    fn test_model_schema() {
+        let schema = serde_json::to_value(schema_for!(Order)).unwrap();
+        let properties = &schema["properties"];
+        assert_eq!(properties["id"]["$ref"], "#/$defs/BigInt");
+        assert_eq!(properties["extra"]["description"], "Any kJSON value");
+        assert_eq!(schema["$defs"]["Instant"]["format"], "date-time");
+        assert_eq!(schema["$defs"]["Decimal128"]["type"], "string");
+        assert_eq!(
+            schema["required"],
+            serde_json::json!(["id", "total", "placed", "extra"])
+        );
+
+        // The JSON form of each value matches its schema's pattern
+        let bigint = regex::Regex::new(schema["$defs"]["BigInt"]["pattern"].as_str().unwrap());
+        assert!(bigint
+            .unwrap()
+            .is_match(&BigInt::from_i64(-42).to_kjson_string()));
+        let decimal = regex::Regex::new(schema["$defs"]["Decimal128"]["pattern"].as_str().unwrap());
+        let price: Decimal128 = "19.99".parse().unwrap();
+        assert!(decimal.unwrap().is_match(&price.to_kjson_string()));
+    }
+}
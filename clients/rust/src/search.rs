@@ -0,0 +1,125 @@
+//! Fast byte searches for the parser and validator
+//!
+//! With the `simd` feature the single-byte searches use `memchr`, which
+//! picks SSE2, AVX2 or NEON at runtime. The runs of whitespace and digits
+//! are checked eight bytes at a time, which pays off on indented documents
+//! and long numbers whether or not the feature is enabled.
+
+/// Position of the first `needle` in `haystack`
+#[inline]
+pub(crate) fn find(needle: u8, haystack: &[u8]) -> Option<usize> {
+    #[cfg(feature = "simd")]
+    {
+        memchr::memchr(needle, haystack)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        haystack.iter().position(|&b| b == needle)
+    }
+}
+
+/// Position of the first `a` or `b` in `haystack`
+#[inline]
+pub(crate) fn find2(a: u8, b: u8, haystack: &[u8]) -> Option<usize> {
+    #[cfg(feature = "simd")]
+    {
+        memchr::memchr2(a, b, haystack)
+    }
+    #[cfg(not(feature = "simd"))]
+    {
+        haystack.iter().position(|&c| c == a || c == b)
+    }
+}
+
+/// Position of the first `*/` in `haystack`
+#[inline]
+pub(crate) fn find_comment_end(haystack: &[u8]) -> Option<usize> {
+    let mut from = 0;
+    while let Some(i) = find(b'/', &haystack[from..]) {
+        let slash = from + i;
+        if slash > 0 && haystack[slash - 1] == b'*' {
+            return Some(slash - 1);
+        }
+        from = slash + 1;
+    }
+    None
+}
+
+const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+const SPACES: u64 = u64::from_ne_bytes([b' '; 8]);
+
+/// Eight bytes starting at `i`
+#[inline]
+fn word(bytes: &[u8], i: usize) -> u64 {
+    u64::from_ne_bytes(bytes[i..i + 8].try_into().unwrap())
+}
+
+/// Number of leading spaces, tabs and line breaks
+#[inline]
+pub(crate) fn whitespace_len(bytes: &[u8]) -> usize {
+    let mut i = 0;
+    loop {
+        // Indentation is mostly spaces, so skip those a word at a time
+        while i + 8 <= bytes.len() && word(bytes, i) == SPACES {
+            i += 8;
+        }
+        match bytes.get(i) {
+            Some(b' ' | b'\t' | b'\n' | b'\r') => i += 1,
+            _ => return i,
+        }
+    }
+}
+
+/// Number of leading ASCII digits
+#[inline]
+pub(crate) fn digits_len(bytes: &[u8]) -> usize {
+    let mut i = 0;
+    while i + 8 <= bytes.len() && all_digits(word(bytes, i)) {
+        i += 8;
+    }
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        i += 1;
+    }
+    i
+}
+
+/// Whether all eight bytes of `x` are `0`..=`9`: each has the high nibble
+/// 3, and adding 6 does not carry into it
+#[inline]
+fn all_digits(x: u64) -> bool {
+    const HIGH: u64 = ONES * 0xF0;
+    (x & HIGH) == ONES * 0x30 && (x.wrapping_add(ONES * 0x06) & HIGH) == ONES * 0x30
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find(b'"', b"abc\"d\""), Some(3));
+        assert_eq!(find(b'"', b"abc"), None);
+        assert_eq!(find2(b'"', b'\\', b"ab\\c\"d"), Some(2));
+        assert_eq!(find_comment_end(b"a / * */ b */"), Some(6));
+        assert_eq!(find_comment_end(b"*/"), Some(0));
+        assert_eq!(find_comment_end(b"/ *"), None);
+    }
+
+    #[test]
+    fn test_runs() {
+        assert_eq!(whitespace_len(b""), 0);
+        assert_eq!(whitespace_len(b"                  \t\r\n   x"), 24);
+        assert_eq!(whitespace_len(b"          "), 10);
+        assert_eq!(digits_len(b"1234567890123456789n"), 19);
+        assert_eq!(digits_len(b"12345678"), 8);
+        assert_eq!(digits_len(b"1234567:"), 7);
+        // Bytes either side of the digits in every position of a word
+        for c in [b'/', b':', b'\0', 0xb0, 0xff] {
+            for i in 0..8 {
+                let mut bytes = *b"99999999";
+                bytes[i] = c;
+                assert_eq!(digits_len(&bytes), i);
+            }
+        }
+    }
+}
@@ -0,0 +1,794 @@
+//! Native serde serializer writing kJSON text
+//!
+//! A [`Serializer`] writes any `T: Serialize` straight to an `io::Write`,
+//! without building a [`Value`](crate::Value) first. Struct fields keep
+//! their declared order, and extended types are written as literals:
+//!
+//! - [`BigInt`](crate::BigInt), [`Decimal128`](crate::Decimal128),
+//!   [`Instant`](crate::Instant), [`Duration`](crate::Duration) and
+//!   [`Date`](crate::Date) always, as `12n`, `1.50m`, timestamps and
+//!   `PT1H`.
+//! - Strings that are exactly how a UUID or a timestamp literal is written
+//!   back, which is what [`Uuid`](crate::Uuid) and `chrono::DateTime` give
+//!   serializers, so such fields parse back as extended types. Reading
+//!   them into `String` fields gives the same text again.
+//! - Byte buffers, from `serialize_bytes`, as binary in the encoding the
+//!   options ask for.
+//!
+//! Output matches [`crate::to_string_with`] for the same
+//! options, except for the order of object keys.
+
+use crate::error::{Error, Result};
+use crate::serializer::{key_to_string, write_binary, write_number, write_string, SerializeOptions};
+use crate::types::Date;
+use serde::ser::{self, Impossible, Serialize};
+use std::io::Write;
+use uuid::Uuid;
+
+/// Name of the newtype struct through which extended types hand their
+/// literal text to [`Serializer`]; other serializers see the text as a
+/// string
+pub(crate) const LITERAL: &str = "$kjson::private::Literal";
+
+/// Name of the newtype struct through which string values ask
+/// [`Serializer`] to quote them even if they look like literals
+pub(crate) const QUOTED: &str = "$kjson::private::Quoted";
+
+/// How [`Serializer`] writes the next string
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Text {
+    /// Unquoted if it reads back as the same literal, else quoted
+    Auto,
+    /// Unquoted, as handed over by an extended type
+    Literal,
+    /// Quoted
+    Quoted,
+}
+
+/// Serialize `value` as compact kJSON to `writer`
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    value.serialize(&mut Serializer::new(writer))
+}
+
+/// Serialize `value` as pretty-printed kJSON to `writer`
+pub fn to_writer_pretty<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<()> {
+    let options = SerializeOptions {
+        pretty: true,
+        ..SerializeOptions::default()
+    };
+    value.serialize(&mut Serializer::with_options(writer, options))
+}
+
+/// Serialize `value` to a compact kJSON string
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    to_string_with(value, &SerializeOptions::default())
+}
+
+/// Serialize `value` to a kJSON string with custom options
+pub fn to_string_with<T: Serialize + ?Sized>(
+    value: &T,
+    options: &SerializeOptions,
+) -> Result<String> {
+    let mut buf = Vec::new();
+    value.serialize(&mut Serializer::with_options(&mut buf, options.clone()))?;
+    Ok(String::from_utf8(buf).expect("serializer writes UTF-8"))
+}
+
+/// Streaming serializer writing kJSON text to an `io::Write`
+#[derive(Debug)]
+pub struct Serializer<W> {
+    writer: W,
+    options: SerializeOptions,
+    indent: usize,
+    text: Text,
+}
+
+impl<W: Write> Serializer<W> {
+    /// Write compact kJSON to `writer`
+    pub fn new(writer: W) -> Self {
+        Serializer::with_options(writer, SerializeOptions::default())
+    }
+
+    /// Write kJSON to `writer` as `options` say
+    pub fn with_options(writer: W, options: SerializeOptions) -> Self {
+        Serializer {
+            writer,
+            options,
+            indent: 0,
+            text: Text::Auto,
+        }
+    }
+
+    /// Consume the serializer, returning the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn open(&mut self, bracket: &str) -> Result<()> {
+        self.writer.write_all(bracket.as_bytes())?;
+        self.indent += 1;
+        Ok(())
+    }
+
+    /// Start an item of an array or object, after the separator if needed
+    fn item(&mut self, first: &mut bool) -> Result<()> {
+        if !std::mem::take(first) {
+            self.writer.write_all(b",")?;
+            if !self.options.pretty {
+                self.writer.write_all(b" ")?;
+            }
+        }
+        if self.options.pretty {
+            write!(self.writer, "\n{}", "  ".repeat(self.indent))?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, bracket: &str, empty: bool) -> Result<()> {
+        self.indent -= 1;
+        if self.options.pretty && !empty {
+            write!(self.writer, "\n{}", "  ".repeat(self.indent))?;
+        }
+        self.writer.write_all(bracket.as_bytes())?;
+        Ok(())
+    }
+
+    fn key(&mut self, key: &str) -> Result<()> {
+        write!(self.writer, "{}: ", key_to_string(key)?)?;
+        Ok(())
+    }
+
+    /// Open `{variant: ` around the value of an enum variant
+    fn open_variant(&mut self, variant: &str) -> Result<()> {
+        self.open("{")?;
+        self.item(&mut true)?;
+        self.key(variant)
+    }
+
+    fn integer(&mut self, n: impl std::fmt::Display) -> Result<()> {
+        write!(self.writer, "{}", n)?;
+        Ok(())
+    }
+}
+
+/// Whether `s` is exactly how a UUID or timestamp literal is written, so
+/// writing it unquoted reads back as the same text
+fn is_literal_text(s: &str) -> bool {
+    if s.len() == 36 && Uuid::parse_str(s).is_ok_and(|u| u.hyphenated().to_string() == s) {
+        return true;
+    }
+    s.len() >= 20
+        && s.as_bytes()[10] == b'T'
+        && Date::from_iso8601(s).is_ok_and(|d| d.to_iso8601() == s)
+}
+
+impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        // Through the shortest text of the f32, so 0.1f32 stays 0.1
+        let n = v.to_string().parse().unwrap_or(v as f64);
+        write_number(&mut self.writer, n, self.options.exponent)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        write_number(&mut self.writer, v, self.options.exponent)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        write_string(&mut self.writer, v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        match std::mem::replace(&mut self.text, Text::Auto) {
+            Text::Literal => {}
+            Text::Auto if is_literal_text(v) => {}
+            Text::Auto | Text::Quoted => return write_string(&mut self.writer, v),
+        }
+        self.writer.write_all(v.as_bytes())?;
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        write_binary(&mut self.writer, v, self.options.binary)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.writer.write_all(b"null")?;
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        write_string(&mut self.writer, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.text = match name {
+            LITERAL => Text::Literal,
+            QUOTED => Text::Quoted,
+            _ => Text::Auto,
+        };
+        let result = value.serialize(&mut *self);
+        self.text = Text::Auto;
+        result
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.open_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.close("}", false)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.open("[")?;
+        Ok(Compound::new(self, "]"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.open_variant(variant)?;
+        self.open("[")?;
+        Ok(Compound {
+            variant: true,
+            ..Compound::new(self, "]")
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.open("{")?;
+        Ok(Compound::new(self, "}"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.open_variant(variant)?;
+        self.open("{")?;
+        Ok(Compound {
+            variant: true,
+            ..Compound::new(self, "}")
+        })
+    }
+}
+
+/// State of an array or object being written
+#[derive(Debug)]
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    first: bool,
+    bracket: &'static str,
+    /// Inside `{variant: ...}`, which closes too
+    variant: bool,
+}
+
+impl<'a, W: Write> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>, bracket: &'static str) -> Self {
+        Compound {
+            ser,
+            first: true,
+            bracket,
+            variant: false,
+        }
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.ser.item(&mut self.first)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        self.ser.item(&mut self.first)?;
+        self.ser.key(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn finish(self) -> Result<()> {
+        self.ser.close(self.bracket, self.first)?;
+        if self.variant {
+            self.ser.close("}", false)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.ser.item(&mut self.first)?;
+        let key = key.serialize(KeySerializer)?;
+        self.ser.key(&key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Map keys as text: strings, and numbers, booleans and unit variants as
+/// they would be written
+struct KeySerializer;
+
+fn key_error() -> Error {
+    Error::SerializationError("object keys must be strings, numbers or booleans".to_string())
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_error())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_error())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_error())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_error())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BigInt, Decimal128, Duration, Instant};
+    use crate::{from_str, parse, BinaryEncoding};
+    use chrono::{DateTime, TimeZone, Utc};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Order {
+        id: Uuid,
+        placed: DateTime<Utc>,
+        total: Decimal128,
+        sequence: BigInt,
+        due: Instant,
+        window: Duration,
+        note: Option<String>,
+        lines: Vec<Line>,
+        status: Status,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Line(u32, f32);
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Status {
+        Open,
+        Held(String),
+        Moved { to: String },
+    }
+
+    fn order() -> Order {
+        Order {
+            id: Uuid::parse_str("a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11").unwrap(),
+            placed: Utc.with_ymd_and_hms(2025, 1, 10, 12, 0, 0).unwrap()
+                + chrono::Duration::milliseconds(250),
+            total: Decimal128::from_str("19.990").unwrap(),
+            sequence: BigInt::from_str("123456789012345678901234567890").unwrap(),
+            due: Instant::from_iso8601("2025-02-01T00:00:00Z").unwrap(),
+            window: Duration::from_iso8601("PT1H30M").unwrap(),
+            note: None,
+            lines: vec![Line(2, 0.1)],
+            status: Status::Moved {
+                to: "it's done".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_literals_and_round_trip() {
+        let text = to_string(&order()).unwrap();
+        assert_eq!(
+            text,
+            "{id: a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11, placed: 2025-01-10T12:00:00.250Z, \
+             total: 19.990m, sequence: 123456789012345678901234567890n, \
+             due: 2025-02-01T00:00:00Z, window: PT1H30M, note: null, lines: [[2, 0.1]], \
+             status: {Moved: {to: \"it's done\"}}}"
+        );
+        assert!(matches!(
+            parse(&text).unwrap().pointer("/id"),
+            Some(crate::Value::Uuid(_))
+        ));
+        assert_eq!(from_str::<Order>(&text).unwrap(), order());
+
+        let pretty = crate::to_string_pretty(&order()).unwrap();
+        assert_eq!(from_str::<Order>(&pretty).unwrap(), order());
+
+        // chrono writes fractions in 3, 6 or 9 digits, like Date
+        let placed = DateTime::parse_from_rfc3339("2025-01-10T12:00:00.000250+02:00").unwrap();
+        let times = (placed, placed.with_timezone(&Utc) + chrono::Duration::nanoseconds(1));
+        assert_eq!(
+            to_string(&times).unwrap(),
+            "[2025-01-10T12:00:00.000250+02:00, 2025-01-10T10:00:00.000250001Z]"
+        );
+
+        // Strings that only look like literals stay strings
+        let strings = [
+            "A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11",
+            "2025-01-10T12:00:00",
+            "2025-01-10T12:00:00.25Z",
+            "12n",
+        ];
+        let text = to_string(&strings).unwrap();
+        assert_eq!(
+            text,
+            "['A0EEBC99-9C0B-4EF8-BB6D-6BB9BD380A11', '2025-01-10T12:00:00', \
+             '2025-01-10T12:00:00.25Z', '12n']"
+        );
+        assert_eq!(from_str::<Vec<String>>(&text).unwrap(), strings);
+    }
+
+    #[test]
+    fn test_layout() {
+        let statuses = vec![Status::Open, Status::Held("x".to_string())];
+        let mut buf = Vec::new();
+        to_writer_pretty(&mut buf, &statuses).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "[\n  'Open',\n  {\n    Held: 'x'\n  }\n]"
+        );
+
+        let map = BTreeMap::from([(1, vec![]), (2, vec![3.5])]);
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &map).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "{'1': [], '2': [3.5]}");
+
+        let options = SerializeOptions {
+            binary: BinaryEncoding::Hex,
+            ..SerializeOptions::default()
+        };
+        struct Bytes;
+        impl Serialize for Bytes {
+            fn serialize<S: ser::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+                s.serialize_bytes(&[0xca, 0xfe])
+            }
+        }
+        assert_eq!(to_string_with(&Bytes, &options).unwrap(), "'cafe'");
+        assert_eq!(
+            to_string(&Bytes).unwrap(),
+            "b64'yv4='"
+        );
+
+        let err = to_string_with(&BTreeMap::from([((1, 2), 3)]), &options).unwrap_err();
+        assert!(matches!(err, Error::SerializationError(_)));
+    }
+}
@@ -1,51 +1,385 @@
 use crate::error::Result;
-use crate::value::Value;
+use crate::value::{ordered_entries, Map, Str, Value};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use std::borrow::Cow;
 use std::io::Write;
 
+/// How [`Value::Binary`] is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BinaryEncoding {
+    /// A `b64'...'` literal, the only form that parses back to binary
+    #[default]
+    Literal,
+    /// A string of standard base64
+    Base64,
+    /// A string of URL-safe base64 without padding
+    Base64Url,
+    /// A string of lowercase hex digits
+    Hex,
+}
+
+/// When [`Value::Number`] is written with an exponent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExponentFormat {
+    /// Plain decimal digits, however large or small the number
+    #[default]
+    Never,
+    /// Always with an exponent, e.g. `4.2e1`
+    Always,
+    /// With an exponent once its magnitude reaches the threshold, so
+    /// `Threshold(21)` writes `1e21` and `1e-21` but `1e20` in full
+    Threshold(u32),
+}
+
+/// Options for [`to_string_with`]
+#[derive(Debug, Clone, Default)]
+pub struct SerializeOptions {
+    /// Indent arrays and objects over several lines
+    pub pretty: bool,
+    /// How binary data is written
+    pub binary: BinaryEncoding,
+    /// When numbers are written with an exponent
+    pub exponent: ExponentFormat,
+}
+
+/// Limits for [`to_string_summarized`]
+#[derive(Debug, Clone)]
+pub struct SummaryOptions {
+    /// Arrays and objects nested deeper than this are written as `[…]` and `{…}`
+    pub max_depth: usize,
+    /// Strings and binary literals longer than this many characters are cut
+    /// short, ending in `…`
+    pub max_string_len: usize,
+    /// Arrays show at most this many items, followed by how many were left out
+    pub max_array_items: usize,
+    /// Objects show at most this many entries, followed by how many were left out
+    pub max_object_entries: usize,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions {
+            max_depth: 4,
+            max_string_len: 120,
+            max_array_items: 10,
+            max_object_entries: 32,
+        }
+    }
+}
+
 /// Serialize a Value to a kJSON string
 pub fn to_string(value: &Value) -> Result<String> {
-    let mut buf = Vec::new();
-    write_value(&mut buf, value, 0, false)?;
-    Ok(String::from_utf8_lossy(&buf).into_owned())
+    to_string_with(value, &SerializeOptions::default())
 }
 
 /// Serialize a Value to a pretty-printed kJSON string
 pub fn to_string_pretty(value: &Value) -> Result<String> {
+    to_string_with(
+        value,
+        &SerializeOptions {
+            pretty: true,
+            ..SerializeOptions::default()
+        },
+    )
+}
+
+/// Serialize a Value to a kJSON string with custom options
+pub fn to_string_with(value: &Value, options: &SerializeOptions) -> Result<String> {
     let mut buf = Vec::new();
-    write_value(&mut buf, value, 0, true)?;
+    to_writer_with(&mut buf, value, options)?;
     Ok(String::from_utf8_lossy(&buf).into_owned())
 }
 
-/// Write a value to a writer
-fn write_value<W: Write>(writer: &mut W, value: &Value, indent: usize, pretty: bool) -> Result<()> {
+/// Serialize a Value to `writer` with custom options, without building the
+/// text in memory first. Wrap unbuffered writers such as a `File` in a
+/// `BufWriter`.
+pub fn to_writer_with<W: Write>(
+    mut writer: W,
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<()> {
+    write_value(&mut writer, value, 0, options)
+}
+
+/// Serialize a Value to a compact, bounded preview for logs.
+///
+/// Deep subtrees, long strings and long arrays and objects are elided as
+/// `options` says, so the output is no longer kJSON but stays short however
+/// large the document, e.g. `{items: [1, 2, … 98 more], user: {…}}`.
+pub fn to_string_summarized(value: &Value, options: &SummaryOptions) -> Result<String> {
+    let mut buf = Vec::new();
+    write_summary(&mut buf, value, 0, options)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Serialize a Value to its canonical kJSON form.
+///
+/// The canonical form is stable across platforms and implementations: no
+/// whitespace, object keys sorted by UTF-16 code units, strings always in
+/// double quotes with minimal escaping, integers as their exact digits and
+/// other numbers formatted like ECMAScript's `Number.prototype.toString`.
+pub fn to_canonical_string(value: &Value) -> Result<String> {
+    let mut buf = Vec::new();
+    write_canonical(&mut buf, value)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Write the canonical form of a value to a writer
+pub(crate) fn write_canonical<W: Write>(writer: &mut W, value: &Value) -> Result<()> {
     match value {
-        Value::Null => write!(writer, "null")?,
-        Value::Bool(b) => write!(writer, "{}", b)?,
-        Value::Number(n) => {
-            if n.is_finite() {
-                // Write number ensuring proper formatting
-                if n.fract() == 0.0 && n.abs() < 1e15 {
-                    write!(writer, "{:.0}", n)?;
-                } else {
-                    write!(writer, "{}", n)?;
+        Value::Number(n) => write_canonical_number(writer, *n)?,
+        // Exact digits: rounding to f64 above 2^53 would make distinct
+        // documents hash and sign alike
+        Value::Integer(n) => write!(writer, "{}", n)?,
+        Value::UInteger(n) => write!(writer, "{}", n)?,
+        Value::String(s) => write_canonical_string(writer, s)?,
+        Value::Array(arr) => {
+            write!(writer, "[")?;
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    write!(writer, ",")?;
+                }
+                write_canonical(writer, item)?;
+            }
+            write!(writer, "]")?;
+        }
+        Value::Object(obj) => {
+            write!(writer, "{{")?;
+            for (i, (key, value)) in canonical_entries(obj).into_iter().enumerate() {
+                if i > 0 {
+                    write!(writer, ",")?;
                 }
-            } else {
-                write!(writer, "null")?; // JSON doesn't support Infinity/NaN
+                write_canonical_string(writer, key)?;
+                write!(writer, ":")?;
+                write_canonical(writer, value)?;
             }
+            write!(writer, "}}")?;
+        }
+        other => write_value(writer, other, 0, &SerializeOptions::default())?,
+    }
+    Ok(())
+}
+
+/// Object entries in canonical order (by UTF-16 code units)
+pub(crate) fn canonical_entries(obj: &Map) -> Vec<(&Str, &Value)> {
+    let mut items: Vec<_> = obj.iter().collect();
+    items.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+    items
+}
+
+/// Write a number the way ECMAScript's `Number.prototype.toString` does
+fn write_canonical_number<W: Write>(writer: &mut W, n: f64) -> Result<()> {
+    if !n.is_finite() {
+        write!(writer, "null")?;
+        return Ok(());
+    }
+    if n == 0.0 {
+        write!(writer, "0")?;
+        return Ok(());
+    }
+    if n < 0.0 {
+        write!(writer, "-")?;
+    }
+
+    // Shortest round-trip digits and exponent, e.g. "1.2345e4"
+    let sci = format!("{:e}", n.abs());
+    let (mantissa, exponent) = sci.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let point = exponent.parse::<i32>().unwrap() + 1;
+
+    // Whole numbers in integer range whose shortest digits aren't exact,
+    // such as 2^60, take the exponent form: as plain digits they would
+    // read back as a different integer
+    let whole = (k <= point && point <= 21)
+        .then(|| format!("{}{}", digits, "0".repeat((point - k) as usize)))
+        .filter(|whole| whole.parse::<u64>().map_or(true, |d| d == n.abs() as u64));
+    if let Some(whole) = whole {
+        write!(writer, "{}", whole)?;
+    } else if 0 < point && point < k {
+        let (int, frac) = digits.split_at(point as usize);
+        write!(writer, "{}.{}", int, frac)?;
+    } else if -6 < point && point <= 0 {
+        write!(writer, "0.{}{}", "0".repeat(-point as usize), digits)?;
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let sign = if point > 0 { "+" } else { "-" };
+        if rest.is_empty() {
+            write!(writer, "{}e{}{}", first, sign, (point - 1).abs())?;
+        } else {
+            write!(writer, "{}.{}e{}{}", first, rest, sign, (point - 1).abs())?;
         }
+    }
+    Ok(())
+}
+
+/// Write a double-quoted string, escaping only what JSON requires
+pub(crate) fn write_canonical_string<W: Write>(writer: &mut W, s: &str) -> Result<()> {
+    write!(writer, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\u{0008}' => write!(writer, "\\b")?,
+            '\u{000C}' => write!(writer, "\\f")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(writer, "\\u{:04x}", ch as u32)?,
+            ch => write!(writer, "{}", ch)?,
+        }
+    }
+    write!(writer, "\"")?;
+    Ok(())
+}
+
+/// Write a value to a writer
+fn write_value<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    indent: usize,
+    options: &SerializeOptions,
+) -> Result<()> {
+    match value {
+        Value::Null => write!(writer, "null")?,
+        Value::Bool(b) => write!(writer, "{}", b)?,
+        Value::Number(n) => write_number(writer, *n, options.exponent)?,
+        Value::Integer(n) => write!(writer, "{}", n)?,
+        Value::UInteger(n) => write!(writer, "{}", n)?,
         Value::String(s) => write_string(writer, s)?,
-        Value::Array(arr) => write_array(writer, arr, indent, pretty)?,
-        Value::Object(obj) => write_object(writer, obj, indent, pretty)?,
+        Value::Array(arr) => write_array(writer, arr, indent, options)?,
+        Value::Object(obj) => write_object(writer, obj, indent, options)?,
         Value::BigInt(b) => write!(writer, "{}", b.to_kjson_string())?,
         Value::Decimal128(d) => write!(writer, "{}", d.to_kjson_string())?,
         Value::Uuid(u) => write!(writer, "{}", u)?,
         Value::Date(d) => write!(writer, "{}", d.to_iso8601())?,
+        Value::Instant(i) => write!(writer, "{}", i.to_iso8601())?,
+        Value::Duration(d) => write!(writer, "{}", d.to_iso8601())?,
+        Value::Binary(bytes) => write_binary(writer, bytes, options.binary)?,
+    }
+    Ok(())
+}
+
+/// Write binary data in the given encoding
+pub(crate) fn write_binary<W: Write>(
+    writer: &mut W,
+    bytes: &[u8],
+    encoding: BinaryEncoding,
+) -> Result<()> {
+    match encoding {
+        BinaryEncoding::Literal => write!(writer, "b64'{}'", STANDARD.encode(bytes))?,
+        BinaryEncoding::Base64 => write_string(writer, &STANDARD.encode(bytes))?,
+        BinaryEncoding::Base64Url => write_string(writer, &URL_SAFE_NO_PAD.encode(bytes))?,
+        BinaryEncoding::Hex => {
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            write_string(writer, &hex)?
+        }
     }
     Ok(())
 }
 
+/// Write a number, with an exponent where `exponent` calls for one
+pub(crate) fn write_number<W: Write>(writer: &mut W, n: f64, exponent: ExponentFormat) -> Result<()> {
+    if !n.is_finite() {
+        write!(writer, "null")?; // JSON doesn't support Infinity/NaN
+        return Ok(());
+    }
+    // Shortest round-trip digits with their exact decimal exponent
+    let sci = format!("{:e}", n);
+    let with_exponent = match exponent {
+        ExponentFormat::Never => false,
+        ExponentFormat::Always => true,
+        ExponentFormat::Threshold(threshold) => {
+            let (_, power) = sci.split_once('e').unwrap();
+            power.parse::<i32>().unwrap().unsigned_abs() >= threshold
+        }
+    };
+    if with_exponent {
+        write!(writer, "{}", sci)?;
+    } else if n.fract() == 0.0 && n.abs() < 1e15 {
+        write!(writer, "{:.0}", n)?;
+    } else if n.fract() == 0.0 {
+        // The digits of larger whole numbers may be rounded, and without a
+        // fraction they would read back as a different integer
+        write!(writer, "{}.0", n)?;
+    } else {
+        write!(writer, "{}", n)?;
+    }
+    Ok(())
+}
+
+/// Write the summary of a value at `depth` levels of nesting
+fn write_summary<W: Write>(
+    writer: &mut W,
+    value: &Value,
+    depth: usize,
+    options: &SummaryOptions,
+) -> Result<()> {
+    match value {
+        Value::String(s) => write_string(writer, &truncate(s, options.max_string_len))?,
+        Value::Binary(bytes) => {
+            let encoded = STANDARD.encode(bytes);
+            write!(
+                writer,
+                "b64'{}'",
+                truncate(&encoded, options.max_string_len)
+            )?
+        }
+        Value::Array(arr) if !arr.is_empty() && depth >= options.max_depth => {
+            write!(writer, "[…]")?
+        }
+        Value::Object(obj) if !obj.is_empty() && depth >= options.max_depth => {
+            write!(writer, "{{…}}")?
+        }
+        Value::Array(arr) => {
+            write!(writer, "[")?;
+            for (i, item) in arr.iter().take(options.max_array_items).enumerate() {
+                if i > 0 {
+                    write!(writer, ", ")?;
+                }
+                write_summary(writer, item, depth + 1, options)?;
+            }
+            write_elided(writer, arr.len(), options.max_array_items)?;
+            write!(writer, "]")?;
+        }
+        Value::Object(obj) => {
+            let items = ordered_entries(obj);
+            write!(writer, "{{")?;
+            for (i, (key, item)) in items.iter().take(options.max_object_entries).enumerate() {
+                if i > 0 {
+                    write!(writer, ", ")?;
+                }
+                write!(writer, "{}: ", key_to_string(key)?)?;
+                write_summary(writer, item, depth + 1, options)?;
+            }
+            write_elided(writer, items.len(), options.max_object_entries)?;
+            write!(writer, "}}")?;
+        }
+        other => write_value(writer, other, 0, &SerializeOptions::default())?,
+    }
+    Ok(())
+}
+
+/// Note how many of `len` items past `shown` were left out
+fn write_elided<W: Write>(writer: &mut W, len: usize, shown: usize) -> Result<()> {
+    if len > shown {
+        let separator = if shown > 0 { ", " } else { "" };
+        write!(writer, "{}… {} more", separator, len - shown)?;
+    }
+    Ok(())
+}
+
+/// `s` cut to `max` characters, ending in `…` if it was longer
+fn truncate(s: &str, max: usize) -> Cow<'_, str> {
+    match s.char_indices().nth(max) {
+        Some((end, _)) => Cow::Owned(format!("{}…", &s[..end])),
+        None => Cow::Borrowed(s),
+    }
+}
+
 /// Write a string with smart quote selection
-fn write_string<W: Write>(writer: &mut W, s: &str) -> Result<()> {
+pub(crate) fn write_string<W: Write>(writer: &mut W, s: &str) -> Result<()> {
     let quote_char = select_quote_char(s);
     
     write!(writer, "{}", quote_char)?;
@@ -105,8 +439,9 @@ fn write_array<W: Write>(
     writer: &mut W,
     arr: &[Value],
     indent: usize,
-    pretty: bool,
+    options: &SerializeOptions,
 ) -> Result<()> {
+    let pretty = options.pretty;
     write!(writer, "[")?;
     
     if arr.is_empty() {
@@ -119,7 +454,7 @@ fn write_array<W: Write>(
             write!(writer, "\n{}", "  ".repeat(indent + 1))?;
         }
         
-        write_value(writer, item, indent + 1, pretty)?;
+        write_value(writer, item, indent + 1, options)?;
         
         if i < arr.len() - 1 {
             write!(writer, ",")?;
@@ -138,10 +473,11 @@ fn write_array<W: Write>(
 /// Write an object
 fn write_object<W: Write>(
     writer: &mut W,
-    obj: &std::collections::HashMap<String, Value>,
+    obj: &Map,
     indent: usize,
-    pretty: bool,
+    options: &SerializeOptions,
 ) -> Result<()> {
+    let pretty = options.pretty;
     write!(writer, "{{")?;
     
     if obj.is_empty() {
@@ -149,8 +485,7 @@ fn write_object<W: Write>(
         return Ok(());
     }
 
-    let mut items: Vec<_> = obj.iter().collect();
-    items.sort_by_key(|(k, _)| k.as_str());
+    let items = ordered_entries(obj);
 
     for (i, (key, value)) in items.iter().enumerate() {
         if pretty {
@@ -167,7 +502,7 @@ fn write_object<W: Write>(
         write!(writer, ":")?;
         write!(writer, " ")?;
         
-        write_value(writer, value, indent + 1, pretty)?;
+        write_value(writer, value, indent + 1, options)?;
         
         if i < items.len() - 1 {
             write!(writer, ",")?;
@@ -183,6 +518,16 @@ fn write_object<W: Write>(
     Ok(())
 }
 
+/// An object key as written in kJSON, quoted only if necessary
+pub(crate) fn key_to_string(key: &str) -> Result<String> {
+    if !needs_quotes(key) {
+        return Ok(key.to_string());
+    }
+    let mut buf = Vec::new();
+    write_string(&mut buf, key)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
 /// Check if a key needs quotes (JSON5 style)
 fn needs_quotes(key: &str) -> bool {
     if key.is_empty() {
@@ -211,16 +556,16 @@ fn needs_quotes(key: &str) -> bool {
 mod tests {
     use super::*;
     use crate::types::{BigInt, Decimal128};
-    use std::collections::HashMap;
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_serialize_primitives() {
         assert_eq!(to_string(&Value::Null).unwrap(), "null");
         assert_eq!(to_string(&Value::Bool(true)).unwrap(), "true");
         assert_eq!(to_string(&Value::Bool(false)).unwrap(), "false");
         assert_eq!(to_string(&Value::Number(42.0)).unwrap(), "42");
         assert_eq!(to_string(&Value::Number(3.14)).unwrap(), "3.14");
-        assert_eq!(to_string(&Value::String("hello".to_string())).unwrap(), "'hello'");
+        assert_eq!(to_string(&Value::String("hello".into())).unwrap(), "'hello'");
     }
 
     #[test]
@@ -236,6 +581,10 @@ mod tests {
             to_string(&Value::Uuid(uuid)).unwrap(),
             "550e8400-e29b-41d4-a716-446655440000"
         );
+
+        let binary = Value::Binary(vec![0xfb, 0xff, 0x00]);
+        assert_eq!(to_string(&binary).unwrap(), "b64'+/8A'");
+        assert_eq!(crate::parse("b64'+/8A'").unwrap(), binary);
     }
 
     #[test]
@@ -250,8 +599,8 @@ mod tests {
 
     #[test]
     fn test_serialize_object() {
-        let mut obj = HashMap::new();
-        obj.insert("name".to_string(), Value::String("test".to_string()));
+        let mut obj = Map::new();
+        obj.insert("name".to_string(), Value::String("test".into()));
         obj.insert("value".to_string(), Value::Number(42.0));
         
         let result = to_string(&Value::Object(obj)).unwrap();
@@ -261,7 +610,7 @@ mod tests {
 
     #[test]
     fn test_serialize_pretty() {
-        let mut obj = HashMap::new();
+        let mut obj = Map::new();
         obj.insert("a".to_string(), Value::Number(1.0));
         obj.insert("b".to_string(), Value::Array(vec![
             Value::Number(2.0),
@@ -276,14 +625,14 @@ mod tests {
     #[test]
     fn test_string_escaping() {
         let s = "Hello\n\"World\"\t\\";
-        let result = to_string(&Value::String(s.to_string())).unwrap();
+        let result = to_string(&Value::String(s.into())).unwrap();
         // String has double quotes, so single quotes should be used
         assert_eq!(result, r#"'Hello\n"World"\t\\'"#);
     }
 
     #[test]
     fn test_key_quoting() {
-        let mut obj = HashMap::new();
+        let mut obj = Map::new();
         obj.insert("validKey".to_string(), Value::Number(1.0));
         obj.insert("needs-quotes".to_string(), Value::Number(2.0));
         obj.insert("123invalid".to_string(), Value::Number(3.0));
@@ -298,30 +647,199 @@ mod tests {
     #[test]
     fn test_smart_quote_selection() {
         // No quotes - use single quotes
-        let result = to_string(&Value::String("hello".to_string())).unwrap();
+        let result = to_string(&Value::String("hello".into())).unwrap();
         assert_eq!(result, "'hello'");
         
         // Has single quotes - use double quotes
-        let result = to_string(&Value::String("it's nice".to_string())).unwrap();
+        let result = to_string(&Value::String("it's nice".into())).unwrap();
         assert_eq!(result, r#""it's nice""#);
         
         // Has double quotes - use single quotes
-        let result = to_string(&Value::String(r#"He said "hi""#.to_string())).unwrap();
+        let result = to_string(&Value::String(r#"He said "hi""#.into())).unwrap();
         assert_eq!(result, r#"'He said "hi"'"#);
         
         // Has both single and double quotes - use backticks
-        let result = to_string(&Value::String(r#"He said "hello" and 'hi'"#.to_string())).unwrap();
+        let result = to_string(&Value::String(r#"He said "hello" and 'hi'"#.into())).unwrap();
         assert_eq!(result, r#"`He said "hello" and 'hi'`"#);
     }
 
+    #[test]
+    fn test_canonical_form() {
+        let mut obj = match &mut crate::parser::parse(
+            r#"{ "b": [1, 'it\'s', null], "a": { "z": 1e21, "y": 0.000001, "x": 1.5e-7 }, "a\nb": -0 }"#,
+        )
+        .unwrap()
+        {
+            Value::Object(obj) => std::mem::take(obj),
+            _ => unreachable!(),
+        };
+        // U+FF61 sorts after U+1F600 by UTF-16 code units, before it by code point
+        obj.insert("\u{e9}", Value::BigInt(BigInt::from_i64(123)));
+        obj.insert("\u{1f600}", Value::Decimal128(Decimal128::from_str("1.50").unwrap()));
+        obj.insert("\u{ff61}", Value::Bool(true));
+        let value = Value::Object(obj);
+        assert_eq!(
+            to_canonical_string(&value).unwrap(),
+            r#"{"a":{"x":1.5e-7,"y":0.000001,"z":1e+21},"a\nb":0,"b":[1,"it's",null],"é":123n,"😀":1.50m,"｡":true}"#
+        );
+    }
+
+    #[test]
+    fn test_canonical_numbers() {
+        let cases = [
+            (0.1 + 0.2, "0.30000000000000004"),
+            (100.0, "100"),
+            (123456789012345680000.0, "123456789012345680000"),
+            (1e-7, "1e-7"),
+            (-2.5e30, "-2.5e+30"),
+            (2f64.powi(60), "1.152921504606847e+18"),
+            (2f64.powi(53), "9007199254740992"),
+            (333333333.3333333, "333333333.3333333"),
+        ];
+        for (n, expected) in cases {
+            let mut buf = Vec::new();
+            write_canonical_number(&mut buf, n).unwrap();
+            assert_eq!(String::from_utf8(buf).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_canonical_large_integers() {
+        let a = crate::parse("{ id: 9007199254740993 }").unwrap();
+        let b = crate::parse("{ id: 9007199254740992 }").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(to_canonical_string(&a).unwrap(), r#"{"id":9007199254740993}"#);
+        assert_eq!(to_canonical_string(&b).unwrap(), r#"{"id":9007199254740992}"#);
+        assert_eq!(
+            to_canonical_string(&Value::Integer(i64::MIN)).unwrap(),
+            "-9223372036854775808"
+        );
+        // Integers and whole numbers that compare equal stay alike
+        assert_eq!(
+            to_canonical_string(&Value::Integer(100)).unwrap(),
+            to_canonical_string(&Value::Number(100.0)).unwrap()
+        );
+        // and unequal ones don't
+        let number = Value::Number(2.2397371079854246e18);
+        let integer = Value::Integer(2239737107985424600);
+        assert_ne!(number, integer);
+        assert_ne!(to_canonical_string(&number).unwrap(), to_canonical_string(&integer).unwrap());
+        assert_eq!(crate::parse(&to_canonical_string(&number).unwrap()).unwrap(), number);
+    }
+
     #[test]
     fn test_backtick_strings() {
         // Template string with both quote types
-        let result = to_string(&Value::String("Mix 'both' \"types\"".to_string())).unwrap();
+        let result = to_string(&Value::String("Mix 'both' \"types\"".into())).unwrap();
         assert_eq!(result, "`Mix 'both' \"types\"`");
         
         // String with backticks uses different quote (single wins in tie)
-        let result = to_string(&Value::String("template `string`".to_string())).unwrap();
+        let result = to_string(&Value::String("template `string`".into())).unwrap();
         assert_eq!(result, "'template `string`'");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_binary_encodings() {
+        let value = Value::Array(vec![Value::Binary(vec![0xfb, 0xff, 0x01])]);
+        let with = |binary| {
+            let options = SerializeOptions {
+                binary,
+                ..SerializeOptions::default()
+            };
+            to_string_with(&value, &options).unwrap()
+        };
+        assert_eq!(with(BinaryEncoding::Literal), "[b64'+/8B']");
+        assert_eq!(with(BinaryEncoding::Base64), "['+/8B']");
+        assert_eq!(with(BinaryEncoding::Base64Url), "['-_8B']");
+        assert_eq!(with(BinaryEncoding::Hex), "['fbff01']");
+
+        let options = SerializeOptions {
+            pretty: true,
+            binary: BinaryEncoding::Hex,
+            ..SerializeOptions::default()
+        };
+        assert_eq!(to_string_with(&value, &options).unwrap(), "[\n  'fbff01'\n]");
+        assert_eq!(to_canonical_string(&value).unwrap(), "[b64'+/8B']");
+    }
+
+    #[test]
+    fn test_exponent_formats() {
+        // Integer literals are written as they are in every format
+        let value = crate::parse("[1e21, 1.5e-7, 42, 0.25, -3e15]").unwrap();
+        let with = |exponent| {
+            let options = SerializeOptions {
+                exponent,
+                ..SerializeOptions::default()
+            };
+            to_string_with(&value, &options).unwrap()
+        };
+        assert_eq!(
+            with(ExponentFormat::Never),
+            "[1000000000000000000000.0, 0.00000015, 42, 0.25, -3000000000000000.0]"
+        );
+        assert_eq!(
+            with(ExponentFormat::Always),
+            "[1e21, 1.5e-7, 42, 2.5e-1, -3e15]"
+        );
+        assert_eq!(
+            with(ExponentFormat::Threshold(7)),
+            "[1e21, 1.5e-7, 42, 0.25, -3e15]"
+        );
+        for exponent in [ExponentFormat::Always, ExponentFormat::Threshold(7)] {
+            assert_eq!(crate::parse(&with(exponent)).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_large_whole_numbers() {
+        // Past 1e15 the digits are rounded, so the fraction keeps it a Number
+        let text = to_string(&Value::Number(2.2397371079854246e18)).unwrap();
+        assert_eq!(text, "2239737107985424600.0");
+        assert!(matches!(
+            crate::parse(&text).unwrap(),
+            Value::Number(n) if n == 2.2397371079854246e18
+        ));
+        assert_eq!(to_string(&Value::Number(-3e15)).unwrap(), "-3000000000000000.0");
+        assert_eq!(to_string(&Value::Number(42.0)).unwrap(), "42");
+    }
+
+    #[test]
+    fn test_summarized() {
+        let value = crate::parse(
+            r#"{
+                blob: b64'AAECAwQFBgcICQ==',
+                id: 7n,
+                items: [1, 2, 3, 4, 5],
+                nested: { a: { b: { c: 1 } }, empty: [] },
+                note: 'a rather long note',
+            }"#,
+        )
+        .unwrap();
+        let options = SummaryOptions {
+            max_depth: 2,
+            max_string_len: 8,
+            max_array_items: 2,
+            max_object_entries: 8,
+        };
+        assert_eq!(
+            to_string_summarized(&value, &options).unwrap(),
+            "{blob: b64'AAECAwQF…', id: 7n, items: [1, 2, … 3 more], \
+             nested: {a: {…}, empty: []}, note: 'a rather…'}"
+        );
+
+        let options = SummaryOptions {
+            max_depth: 0,
+            ..SummaryOptions::default()
+        };
+        assert_eq!(to_string_summarized(&value, &options).unwrap(), "{…}");
+
+        let options = SummaryOptions {
+            max_object_entries: 0,
+            ..SummaryOptions::default()
+        };
+        assert_eq!(
+            to_string_summarized(&value, &options).unwrap(),
+            "{… 5 more}"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Detached signatures over the canonical form
+//!
+//! Signatures cover the bytes of [`to_canonical_string`], so any kJSON or
+//! JSON client that produces the canonical form can verify them. A
+//! [`Signature`] travels either as a sidecar value or embedded in the signed
+//! object under [`SIGNATURE_FIELD`], which is left out of the signed bytes:
+//!
+//! ```text
+//! {
+//!     "amount": 19.99m,
+//!     "$signature": { "alg": "HS256", "kid": "billing-1", "sig": "5f1c…" }
+//! }
+//! ```
+//!
+//! Keys implement [`Signer`] and [`Verifier`]: `ed25519_dalek::SigningKey` and
+//! `VerifyingKey` with the `ed25519` feature, and [`HmacKey`] with the `hmac`
+//! feature.
+
+use crate::error::{Error, Result};
+use crate::serializer::to_canonical_string;
+use crate::value::{Map, Value};
+use std::fmt;
+
+/// Field holding an embedded signature
+pub const SIGNATURE_FIELD: &str = "$signature";
+
+/// Signature algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// Ed25519 (RFC 8032)
+    Ed25519,
+    /// HMAC with SHA-256
+    HmacSha256,
+}
+
+impl Algorithm {
+    /// Name used in the `alg` field
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Ed25519 => "Ed25519",
+            Algorithm::HmacSha256 => "HS256",
+        }
+    }
+
+    /// Look up an algorithm by its `alg` name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Ed25519" => Some(Algorithm::Ed25519),
+            "HS256" => Some(Algorithm::HmacSha256),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A signature over a value's canonical form
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    /// Algorithm that produced the signature
+    pub algorithm: Algorithm,
+    /// Identifier of the signing key, if the signer has one
+    pub key_id: Option<String>,
+    /// Raw signature bytes
+    pub bytes: Vec<u8>,
+}
+
+impl Signature {
+    /// Encode as `{ alg, kid?, sig }` with the signature bytes in lowercase hex
+    pub fn to_value(&self) -> Value {
+        let mut map = Map::new();
+        map.insert("alg", Value::string(self.algorithm.name().to_string()));
+        if let Some(kid) = &self.key_id {
+            map.insert("kid", Value::string(kid.clone()));
+        }
+        map.insert("sig", Value::string(hex(&self.bytes)));
+        Value::Object(map)
+    }
+
+    /// Decode from the form written by [`Signature::to_value`]
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let obj = value
+            .as_object()
+            .ok_or_else(|| invalid(format!("expected an object, got {}", value.type_name())))?;
+        let field = |name: &str| -> Result<Option<&str>> {
+            match obj.get(name) {
+                None => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s)),
+                Some(other) => Err(invalid(format!(
+                    "{} must be a string, got {}",
+                    name,
+                    other.type_name()
+                ))),
+            }
+        };
+
+        let alg = field("alg")?.ok_or_else(|| invalid("missing alg".to_string()))?;
+        let algorithm = Algorithm::from_name(alg)
+            .ok_or_else(|| invalid(format!("unknown algorithm {}", alg)))?;
+        let sig = field("sig")?.ok_or_else(|| invalid("missing sig".to_string()))?;
+        let bytes = unhex(sig).ok_or_else(|| invalid("sig is not hex".to_string()))?;
+        Ok(Signature {
+            algorithm,
+            key_id: field("kid")?.map(str::to_string),
+            bytes,
+        })
+    }
+}
+
+/// A key that produces signatures
+pub trait Signer {
+    /// Algorithm of the signatures produced
+    fn algorithm(&self) -> Algorithm;
+
+    /// Identifier recorded in the signature's `kid` field
+    fn key_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Sign a message
+    fn sign_bytes(&self, message: &[u8]) -> Vec<u8>;
+}
+
+/// A key that checks signatures
+pub trait Verifier {
+    /// Algorithm of the signatures accepted
+    fn algorithm(&self) -> Algorithm;
+
+    /// Whether `signature` is valid for `message`
+    fn verify_bytes(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// Sign a value's canonical form, returning a detached signature
+pub fn sign(value: &Value, signer: &impl Signer) -> Result<Signature> {
+    let message = to_canonical_string(value)?;
+    Ok(Signature {
+        algorithm: signer.algorithm(),
+        key_id: signer.key_id().map(str::to_string),
+        bytes: signer.sign_bytes(message.as_bytes()),
+    })
+}
+
+/// Verify a detached signature over a value's canonical form
+pub fn verify(value: &Value, signature: &Signature, verifier: &impl Verifier) -> Result<()> {
+    if signature.algorithm != verifier.algorithm() {
+        return Err(Error::InvalidSignature(format!(
+            "signed with {}, verifying with {}",
+            signature.algorithm,
+            verifier.algorithm()
+        )));
+    }
+    let message = to_canonical_string(value)?;
+    if verifier.verify_bytes(message.as_bytes(), &signature.bytes) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSignature(
+            "signature does not match".to_string(),
+        ))
+    }
+}
+
+/// Sign an object and store the signature in its [`SIGNATURE_FIELD`].
+///
+/// Any existing signature is replaced and is not part of the signed bytes.
+pub fn sign_embedded(value: &mut Value, signer: &impl Signer) -> Result<()> {
+    let obj = as_object_mut(value)?;
+    obj.remove(SIGNATURE_FIELD);
+    let signature = sign(value, signer)?;
+    as_object_mut(value)?.insert(SIGNATURE_FIELD, signature.to_value());
+    Ok(())
+}
+
+/// Verify the signature stored in an object's [`SIGNATURE_FIELD`]
+pub fn verify_embedded(value: &Value, verifier: &impl Verifier) -> Result<()> {
+    let obj = value.as_object().ok_or_else(|| {
+        Error::InvalidSignature(format!(
+            "embedded signatures need an object, got {}",
+            value.type_name()
+        ))
+    })?;
+    let signature = obj
+        .get(SIGNATURE_FIELD)
+        .ok_or_else(|| Error::InvalidSignature(format!("missing {} field", SIGNATURE_FIELD)))?;
+    let signature = Signature::from_value(signature)?;
+
+    let mut unsigned = obj.clone();
+    unsigned.remove(SIGNATURE_FIELD);
+    verify(&Value::Object(unsigned), &signature, verifier)
+}
+
+fn as_object_mut(value: &mut Value) -> Result<&mut Map> {
+    match value {
+        Value::Object(obj) => Ok(obj),
+        other => Err(Error::InvalidSignature(format!(
+            "embedded signatures need an object, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidSignature(format!("malformed {}: {}", SIGNATURE_FIELD, message))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(feature = "ed25519")]
+mod ed25519 {
+    use super::{Algorithm, Signer, Verifier};
+    use ed25519_dalek::{Signer as _, SigningKey, VerifyingKey};
+
+    impl Signer for SigningKey {
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::Ed25519
+        }
+
+        fn sign_bytes(&self, message: &[u8]) -> Vec<u8> {
+            self.sign(message).to_bytes().to_vec()
+        }
+    }
+
+    impl Verifier for VerifyingKey {
+        fn algorithm(&self) -> Algorithm {
+            Algorithm::Ed25519
+        }
+
+        fn verify_bytes(&self, message: &[u8], signature: &[u8]) -> bool {
+            ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|sig| self.verify_strict(message, &sig).is_ok())
+        }
+    }
+}
+
+/// A shared HMAC-SHA256 key
+#[cfg(feature = "hmac")]
+#[derive(Clone)]
+pub struct HmacKey {
+    secret: Vec<u8>,
+    key_id: Option<String>,
+}
+
+#[cfg(feature = "hmac")]
+impl HmacKey {
+    /// Key from a shared secret
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        HmacKey {
+            secret: secret.into(),
+            key_id: None,
+        }
+    }
+
+    /// Record `key_id` in signatures made with this key
+    pub fn with_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    fn mac(&self, message: &[u8]) -> hmac::Hmac<sha2::Sha256> {
+        use hmac::{KeyInit, Mac};
+        let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(&self.secret)
+            .expect("HMAC accepts keys of any length");
+        mac.update(message);
+        mac
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl fmt::Debug for HmacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacKey")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl Signer for HmacKey {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::HmacSha256
+    }
+
+    fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    fn sign_bytes(&self, message: &[u8]) -> Vec<u8> {
+        use hmac::Mac;
+        self.mac(message).finalize().into_bytes().to_vec()
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl Verifier for HmacKey {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::HmacSha256
+    }
+
+    fn verify_bytes(&self, message: &[u8], signature: &[u8]) -> bool {
+        use hmac::Mac;
+        self.mac(message).verify_slice(signature).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    const DOC: &str = "{ id: 550e8400-e29b-41d4-a716-446655440000, amount: 19.99m, items: [1, 2] }";
+
+    #[test]
+    fn test_signature_value_roundtrip() {
+        let signature = Signature {
+            algorithm: Algorithm::HmacSha256,
+            key_id: Some("k1".to_string()),
+            bytes: vec![0x00, 0xab, 0xff],
+        };
+        let value = signature.to_value();
+        assert_eq!(
+            value.as_object().unwrap()["sig"],
+            Value::String("00abff".into())
+        );
+        assert_eq!(Signature::from_value(&value).unwrap(), signature);
+        assert!(matches!(
+            Signature::from_value(&parse("{ alg: 'RS256', sig: '00' }").unwrap()),
+            Err(Error::InvalidSignature(_))
+        ));
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn test_hmac_sign_and_verify() {
+        let key = HmacKey::new(b"secret".to_vec()).with_key_id("k1");
+        let value = parse(DOC).unwrap();
+
+        // Key order and formatting don't affect the signature
+        let signature = sign(&value, &key).unwrap();
+        let reordered =
+            parse("{items:[1,2],amount:19.99m,id:550e8400-e29b-41d4-a716-446655440000}").unwrap();
+        assert!(verify(&reordered, &signature, &key).is_ok());
+        assert_eq!(signature.key_id.as_deref(), Some("k1"));
+
+        let tampered =
+            parse("{ id: 550e8400-e29b-41d4-a716-446655440000, amount: 1.99m, items: [1, 2] }")
+                .unwrap();
+        assert!(matches!(
+            verify(&tampered, &signature, &key),
+            Err(Error::InvalidSignature(_))
+        ));
+        assert!(verify(&value, &signature, &HmacKey::new(b"other".to_vec())).is_err());
+    }
+
+    #[cfg(feature = "hmac")]
+    #[test]
+    fn test_embedded_signature() {
+        let key = HmacKey::new(b"secret".to_vec());
+        let mut value = parse(DOC).unwrap();
+        sign_embedded(&mut value, &key).unwrap();
+        assert!(value.as_object().unwrap().contains_key(SIGNATURE_FIELD));
+        assert!(verify_embedded(&value, &key).is_ok());
+
+        // Re-signing replaces the old signature rather than signing it
+        sign_embedded(&mut value, &key).unwrap();
+        assert!(verify_embedded(&value, &key).is_ok());
+
+        if let Value::Object(obj) = &mut value {
+            obj.insert("extra", Value::Bool(true));
+        }
+        assert!(verify_embedded(&value, &key).is_err());
+        assert!(verify_embedded(&parse(DOC).unwrap(), &key).is_err());
+    }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn test_ed25519_sign_and_verify() {
+        let signing = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let verifying = signing.verifying_key();
+        let mut value = parse(DOC).unwrap();
+
+        sign_embedded(&mut value, &signing).unwrap();
+        assert!(verify_embedded(&value, &verifying).is_ok());
+
+        let other = ed25519_dalek::SigningKey::from_bytes(&[8; 32]).verifying_key();
+        assert!(verify_embedded(&value, &other).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! Byte ranges in source text, and their lines and columns
+
+use crate::search;
+use std::fmt;
+use std::ops::Range;
+
+/// A range of bytes in the input, `start` inclusive and `end` exclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Span {
+    /// Offset of the first byte
+    pub start: usize,
+    /// Offset just past the last byte
+    pub end: usize,
+}
+
+impl Span {
+    /// The span from `start` to `end`
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Length in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the span covers no bytes
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The text the span covers
+    pub fn slice<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..self.end]
+    }
+}
+
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Span::new(range.start, range.end)
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// A 1-based line and column, counting columns in characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    /// Line number, from 1
+    pub line: usize,
+    /// Column in characters, from 1
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Line starts of one input, for converting byte offsets to lines and
+/// columns and back.
+///
+/// Building the map scans the input once; each lookup after that is a
+/// binary search over the lines plus a walk along one of them:
+///
+/// ```
+/// use kjson::{Location, SourceMap};
+///
+/// let source = "{\n  name: 'café',\n  port: 80,\n}";
+/// let map = SourceMap::new(source);
+/// let port = source.find("port").unwrap();
+/// assert_eq!(map.location(port), Location { line: 3, column: 3 });
+/// assert_eq!(map.offset(Location { line: 3, column: 3 }), Some(port));
+/// assert_eq!(map.line(2), Some("  name: 'café',"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+    /// Offset of the first byte of each line
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourceMap<'a> {
+    /// Index the lines of `source`
+    pub fn new(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let mut line_starts = vec![0];
+        let mut from = 0;
+        while let Some(i) = search::find(b'\n', &bytes[from..]) {
+            from += i + 1;
+            line_starts.push(from);
+        }
+        SourceMap {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The text the map was built from
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Number of lines, counting the one after a final line break
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Line and column of a byte offset. Offsets past the end are taken as
+    /// the end, and offsets inside a character as its start.
+    pub fn location(&self, offset: usize) -> Location {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let index = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let column = self.source[self.line_starts[index]..offset].chars().count() + 1;
+        Location {
+            line: index + 1,
+            column,
+        }
+    }
+
+    /// Byte offset of a line and column, or `None` if the input has no
+    /// such position. The column just past the end of a line is allowed.
+    pub fn offset(&self, location: Location) -> Option<usize> {
+        let span = self.line_span(location.line)?;
+        let line = span.slice(self.source);
+        if location.column == 0 {
+            return None;
+        }
+        match line.char_indices().nth(location.column - 1) {
+            Some((i, _)) => Some(span.start + i),
+            None if location.column - 1 == line.chars().count() => Some(span.end),
+            None => None,
+        }
+    }
+
+    /// Span of a 1-based line, without its line break
+    pub fn line_span(&self, line: usize) -> Option<Span> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = match self.line_starts.get(line) {
+            Some(next) => next - 1,
+            None => self.source.len(),
+        };
+        let text = &self.source[start..end];
+        Some(Span::new(
+            start,
+            start + text.strip_suffix('\r').unwrap_or(text).len(),
+        ))
+    }
+
+    /// Text of a 1-based line, without its line break
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        self.line_span(line).map(|span| span.slice(self.source))
+    }
+
+    /// Locations of the start and end of `span`
+    pub fn span_locations(&self, span: Span) -> (Location, Location) {
+        (self.location(span.start), self.location(span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let source = "ab\r\néx\n\nlast";
+        let map = SourceMap::new(source);
+        assert_eq!(map.line_count(), 4);
+        assert_eq!(map.line(1), Some("ab"));
+        assert_eq!(map.line(2), Some("éx"));
+        assert_eq!(map.line(3), Some(""));
+        assert_eq!(map.line(4), Some("last"));
+        assert_eq!(map.line(0), None);
+        assert_eq!(map.line(5), None);
+
+        for (offset, line, column) in [
+            (0, 1, 1),
+            (2, 1, 3),
+            (4, 2, 1),
+            (6, 2, 2),
+            (8, 3, 1),
+            (9, 4, 1),
+            (13, 4, 5),
+        ] {
+            let location = Location { line, column };
+            assert_eq!(map.location(offset), location, "{}", offset);
+            assert_eq!(map.offset(location), Some(offset));
+        }
+        // Inside `é`, and past the end
+        assert_eq!(map.location(5), Location { line: 2, column: 1 });
+        assert_eq!(map.location(99), Location { line: 4, column: 5 });
+
+        assert_eq!(map.offset(Location { line: 4, column: 6 }), None);
+        assert_eq!(map.offset(Location { line: 1, column: 0 }), None);
+
+        assert_eq!(
+            map.span_locations(Span::new(1, 7)),
+            (
+                Location { line: 1, column: 2 },
+                Location { line: 2, column: 3 }
+            )
+        );
+        assert_eq!(SourceMap::new("").line_count(), 1);
+        assert_eq!(
+            SourceMap::new("").location(0),
+            Location { line: 1, column: 1 }
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! sqlx `Type` / `Encode` / `Decode` support on Postgres
+//!
+//! [`Value`] maps to the kjson extension's `kjson` type, so rows can carry
+//! kjson columns:
+//!
+//! ```no_run
+//! # async fn run(pool: sqlx::PgPool) -> Result<(), sqlx::Error> {
+//! let doc = kjson::parse("{ price: 19.99m }").unwrap();
+//! sqlx::query("INSERT INTO orders (data) VALUES ($1)")
+//!     .bind(&doc)
+//!     .execute(&pool)
+//!     .await?;
+//! let orders: Vec<(i64, kjson::Value)> = sqlx::query_as("SELECT id, data FROM orders")
+//!     .fetch_all(&pool)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Values are bound as `kjson`, and decoded from `kjson`, `text`, `varchar`,
+//! `json` and `jsonb` columns. The extended scalar types map to built-in
+//! column types:
+//!
+//! | kJSON | Postgres |
+//! |-------|----------|
+//! | Value | `kjson` (also read from `text`, `json`, `jsonb`) |
+//! | Decimal128 | `numeric` |
+//! | BigInt | `numeric` (also read from `int2`, `int4`, `int8`) |
+//! | Instant | `timestamptz` (microsecond precision) |
+//! | Duration | `interval` (without months or years) |
+//!
+//! Each also binds in a `Vec` or slice as the matching array type, such as
+//! `numeric[]` for `Vec<BigInt>`.
+//!
+//! For the `query_as!` macros, override the column type with
+//! `SELECT data AS "data: kjson::Value"`.
+
+use crate::parser::parse;
+use crate::pg_scalar::{
+    decode_interval, decode_numeric, decode_timestamp, encode_interval, encode_numeric,
+    encode_timestamp, numeric_to_bigint,
+};
+use crate::pg_wire;
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::Value;
+use sqlx::encode::IsNull;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{
+    PgArgumentBuffer, PgHasArrayType, PgTypeInfo, PgValueFormat, PgValueRef, Postgres,
+};
+use sqlx::{Decode, Encode, Type};
+
+const KJSON: PgTypeInfo = PgTypeInfo::with_name(pg_wire::KJSON_TYPE);
+const TEXT: PgTypeInfo = PgTypeInfo::with_oid(Oid(25));
+const VARCHAR: PgTypeInfo = PgTypeInfo::with_oid(Oid(1043));
+const BPCHAR: PgTypeInfo = PgTypeInfo::with_oid(Oid(1042));
+const UNKNOWN: PgTypeInfo = PgTypeInfo::with_oid(Oid(705));
+const JSON: PgTypeInfo = PgTypeInfo::with_oid(Oid(114));
+const JSONB: PgTypeInfo = PgTypeInfo::with_oid(Oid(3802));
+const INT2: PgTypeInfo = PgTypeInfo::with_oid(Oid(21));
+const INT4: PgTypeInfo = PgTypeInfo::with_oid(Oid(23));
+const INT8: PgTypeInfo = PgTypeInfo::with_oid(Oid(20));
+const NUMERIC: PgTypeInfo = PgTypeInfo::with_oid(Oid(1700));
+const TIMESTAMPTZ: PgTypeInfo = PgTypeInfo::with_oid(Oid(1184));
+const INTERVAL: PgTypeInfo = PgTypeInfo::with_oid(Oid(1186));
+const NUMERIC_ARRAY: PgTypeInfo = PgTypeInfo::with_oid(Oid(1231));
+const TIMESTAMPTZ_ARRAY: PgTypeInfo = PgTypeInfo::with_oid(Oid(1185));
+const INTERVAL_ARRAY: PgTypeInfo = PgTypeInfo::with_oid(Oid(1187));
+
+/// `jsonb` wire format version
+const JSONB_VERSION: u8 = 1;
+
+impl Type<Postgres> for Value {
+    fn type_info() -> PgTypeInfo {
+        KJSON
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        [KJSON, TEXT, VARCHAR, BPCHAR, UNKNOWN, JSON, JSONB].contains(ty)
+    }
+}
+
+impl PgHasArrayType for Value {
+    fn array_type_info() -> PgTypeInfo {
+        PgTypeInfo::array_of(pg_wire::KJSON_TYPE)
+    }
+}
+
+impl Encode<'_, Postgres> for Value {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        pg_wire::encode(self, buf);
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Value {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        let ty = sqlx::ValueRef::type_info(&value).into_owned();
+        let raw = value.as_bytes()?;
+        if value.format() == PgValueFormat::Binary {
+            if ty == KJSON {
+                return Ok(pg_wire::decode(raw)?);
+            }
+            if ty == JSONB {
+                return match raw.split_first() {
+                    Some((&JSONB_VERSION, text)) => Ok(parse(std::str::from_utf8(text)?)?),
+                    _ => Err("unsupported jsonb wire format".into()),
+                };
+            }
+        }
+        Ok(parse(std::str::from_utf8(raw)?)?)
+    }
+}
+
+impl Type<Postgres> for Decimal128 {
+    fn type_info() -> PgTypeInfo {
+        NUMERIC
+    }
+}
+
+impl PgHasArrayType for Decimal128 {
+    fn array_type_info() -> PgTypeInfo {
+        NUMERIC_ARRAY
+    }
+}
+
+impl Encode<'_, Postgres> for Decimal128 {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        encode_numeric(&self.coefficient(), self.exponent(), buf);
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Decimal128 {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        Ok(match value.format() {
+            PgValueFormat::Binary => {
+                let (coefficient, exponent) = decode_numeric(value.as_bytes()?)?;
+                Decimal128::from_parts(&coefficient, exponent)
+            }
+            PgValueFormat::Text => Decimal128::from_str(value.as_str()?)?,
+        })
+    }
+}
+
+impl Type<Postgres> for BigInt {
+    fn type_info() -> PgTypeInfo {
+        NUMERIC
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        [NUMERIC, INT2, INT4, INT8].contains(ty)
+    }
+}
+
+impl PgHasArrayType for BigInt {
+    fn array_type_info() -> PgTypeInfo {
+        NUMERIC_ARRAY
+    }
+}
+
+impl Encode<'_, Postgres> for BigInt {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        encode_numeric(self, 0, buf);
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for BigInt {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        if value.format() == PgValueFormat::Text {
+            return Ok(BigInt::from_str(value.as_str()?)?);
+        }
+        let raw = value.as_bytes()?;
+        let ty = sqlx::ValueRef::type_info(&value).into_owned();
+        if ty == NUMERIC {
+            let (coefficient, exponent) = decode_numeric(raw)?;
+            return Ok(numeric_to_bigint(&coefficient, exponent)?);
+        }
+        let n = match raw.len() {
+            2 => i16::from_be_bytes(raw.try_into()?) as i64,
+            4 => i32::from_be_bytes(raw.try_into()?) as i64,
+            8 => i64::from_be_bytes(raw.try_into()?),
+            len => return Err(format!("unexpected {}-byte integer", len).into()),
+        };
+        Ok(BigInt::from_i64(n))
+    }
+}
+
+impl Type<Postgres> for Instant {
+    fn type_info() -> PgTypeInfo {
+        TIMESTAMPTZ
+    }
+}
+
+impl PgHasArrayType for Instant {
+    fn array_type_info() -> PgTypeInfo {
+        TIMESTAMPTZ_ARRAY
+    }
+}
+
+impl Encode<'_, Postgres> for Instant {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        encode_timestamp(self, buf);
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Instant {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        if value.format() == PgValueFormat::Text {
+            return Err("decoding timestamptz in text format is not supported".into());
+        }
+        Ok(decode_timestamp(value.as_bytes()?)?)
+    }
+}
+
+impl Type<Postgres> for Duration {
+    fn type_info() -> PgTypeInfo {
+        INTERVAL
+    }
+}
+
+impl PgHasArrayType for Duration {
+    fn array_type_info() -> PgTypeInfo {
+        INTERVAL_ARRAY
+    }
+}
+
+impl Encode<'_, Postgres> for Duration {
+    fn encode_by_ref(
+        &self,
+        buf: &mut PgArgumentBuffer,
+    ) -> std::result::Result<IsNull, BoxDynError> {
+        encode_interval(self, buf);
+        Ok(IsNull::No)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for Duration {
+    fn decode(value: PgValueRef<'r>) -> std::result::Result<Self, BoxDynError> {
+        if value.format() == PgValueFormat::Text {
+            return Err("decoding interval in text format is not supported".into());
+        }
+        Ok(decode_interval(value.as_bytes()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pg_scalar::PG_EPOCH_SECONDS;
+
+    fn numeric(value: &str) -> Vec<u8> {
+        let mut buf = PgArgumentBuffer::default();
+        let _ = Decimal128::from_str(value)
+            .unwrap()
+            .encode_by_ref(&mut buf)
+            .unwrap();
+        buf.to_vec()
+    }
+
+    fn roundtrip(value: &str) -> String {
+        let (coefficient, exponent) = decode_numeric(&numeric(value)).unwrap();
+        Decimal128::from_parts(&coefficient, exponent).to_string()
+    }
+
+    #[test]
+    fn test_array_types() {
+        assert_eq!(<Vec<BigInt> as Type<Postgres>>::type_info(), NUMERIC_ARRAY);
+        assert_eq!(<Vec<Decimal128> as Type<Postgres>>::type_info(), NUMERIC_ARRAY);
+        assert_eq!(<&[Instant] as Type<Postgres>>::type_info(), TIMESTAMPTZ_ARRAY);
+        assert_eq!(<Vec<Duration> as Type<Postgres>>::type_info(), INTERVAL_ARRAY);
+    }
+
+    #[test]
+    fn test_numeric_layout() {
+        // 12345.678: groups 1 2345 6780, weight 1, scale 3
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0, 3, 0, 1, 0, 0, 0, 3,
+            0x00, 0x01, 0x09, 0x29, 0x1A, 0x7C,
+        ];
+        assert_eq!(numeric("12345.678"), expected);
+
+        // -0.0001: one group, weight -1
+        assert_eq!(
+            numeric("-0.0001"),
+            [0, 1, 0xFF, 0xFF, 0x40, 0x00, 0, 4, 0, 1]
+        );
+        assert_eq!(numeric("0.00"), [0, 0, 0, 0, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_numeric_roundtrip() {
+        for value in [
+            "0",
+            "19.99",
+            "-19.990",
+            "10000",
+            "123456789012345678901234567890.5",
+            "0.000000000000000000015",
+            "-0.5",
+        ] {
+            assert_eq!(roundtrip(value), value);
+        }
+        assert!(decode_numeric(&[0, 0, 0, 0, 0xC0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_numeric_to_bigint() {
+        let big = BigInt::from_str("123456789012345678901234567890").unwrap();
+        let mut buf = PgArgumentBuffer::default();
+        let _ = big.encode_by_ref(&mut buf).unwrap();
+        let (coefficient, exponent) = decode_numeric(&buf).unwrap();
+        assert_eq!(numeric_to_bigint(&coefficient, exponent).unwrap(), big);
+
+        let whole = BigInt::from_str("-1200").unwrap();
+        assert_eq!(
+            numeric_to_bigint(&whole, -2).unwrap(),
+            BigInt::from_i64(-12)
+        );
+        assert!(numeric_to_bigint(&BigInt::from_i64(1205), -2).is_err());
+    }
+
+    #[test]
+    fn test_value_encoding() {
+        let value = parse("{ total: 12n, price: 19.99m }").unwrap();
+        let mut buf = PgArgumentBuffer::default();
+        let _ = value.encode_by_ref(&mut buf).unwrap();
+        assert_eq!(buf[0], pg_wire::WIRE_INTERNAL);
+        assert_eq!(pg_wire::decode(&buf).unwrap(), value);
+
+        assert!(<Value as Type<Postgres>>::compatible(&JSONB));
+        assert!(<BigInt as Type<Postgres>>::compatible(&INT8));
+        assert!(!<BigInt as Type<Postgres>>::compatible(&TEXT));
+    }
+
+    #[test]
+    fn test_time_encoding() {
+        let mut buf = PgArgumentBuffer::default();
+        let _ = Instant::from_seconds(PG_EPOCH_SECONDS + 1)
+            .encode_by_ref(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..], 1_000_000i64.to_be_bytes());
+
+        let mut buf = PgArgumentBuffer::default();
+        let _ = Duration::from_iso8601("PT1H30M")
+            .unwrap()
+            .encode_by_ref(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..8], 5_400_000_000i64.to_be_bytes());
+        assert_eq!(&buf[8..], [0; 8]);
+    }
+}
@@ -0,0 +1,509 @@
+//! Reading huge inputs one value at a time
+//!
+//! A [`StreamReader`] pulls values from a `BufRead` without holding the
+//! whole input, so memory use is bounded by the largest single value rather
+//! than the file. It reads either a sequence of values (NDJSON, or values
+//! separated by any whitespace) or the elements of one top-level array:
+//!
+//! ```
+//! use kjson::stream::StreamReader;
+//!
+//! let input = "[{ id: 1n }, { id: 2n }, // more to come\n]";
+//! let reader = StreamReader::detect(input.as_bytes())?;
+//! assert!(reader.is_array());
+//! assert_eq!(reader.count(), 2);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Input that arrives in chunks, such as from a socket, goes to a
+//! [`PushParser`] instead, which returns each value as its last byte comes
+//! in:
+//!
+//! ```
+//! use kjson::stream::PushParser;
+//!
+//! let mut parser = PushParser::new();
+//! assert_eq!(parser.feed(b"{ id: 1n, name: 'caf").count(), 0);
+//! let done: Vec<_> = parser.feed("é' } [2".as_bytes()).collect::<kjson::Result<_>>()?;
+//! assert_eq!(done, [kjson::parse("{ id: 1n, name: 'café' }")?]);
+//! assert_eq!(parser.feed(b"]\n42").count(), 1);
+//! assert_eq!(parser.finish()?, Some(kjson::Value::Integer(42)));
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Parse error positions are byte offsets from the start of the stream. A
+//! value that fails to parse is reported and reading carries on with the
+//! next one; I/O and framing errors end the stream.
+//!
+//! Memory is bounded only if the values are. For untrusted input,
+//! [`StreamReader::max_value_len`] ends the stream with
+//! [`Error::LimitExceeded`] instead of buffering an oversized value.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::scanner::Scanner;
+use crate::value::Value;
+use std::io::BufRead;
+
+/// Where the reader is inside a top-level array
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ArrayState {
+    /// Before the opening `[`
+    Open,
+    /// After `[`, before the first element
+    First,
+    /// After an element
+    Next,
+    /// After the closing `]`
+    Closed,
+}
+
+/// Iterator over the values of a stream
+#[derive(Debug)]
+pub struct StreamReader<R> {
+    reader: R,
+    array: Option<ArrayState>,
+    offset: usize,
+    done: bool,
+    max_value_len: Option<usize>,
+}
+
+impl<R: BufRead> StreamReader<R> {
+    /// Read a sequence of values separated by whitespace, such as NDJSON
+    pub fn new(reader: R) -> Self {
+        StreamReader {
+            reader,
+            array: None,
+            offset: 0,
+            done: false,
+            max_value_len: None,
+        }
+    }
+
+    /// Fail with [`Error::LimitExceeded`] on values longer than `len` bytes
+    pub fn max_value_len(mut self, len: usize) -> Self {
+        self.max_value_len = Some(len);
+        self
+    }
+
+    /// Read the elements of a single top-level array
+    pub fn array(reader: R) -> Self {
+        StreamReader {
+            array: Some(ArrayState::Open),
+            ..StreamReader::new(reader)
+        }
+    }
+
+    /// Read array elements if the input starts with `[`, values otherwise.
+    ///
+    /// An NDJSON stream whose first value is an array is read as an array
+    /// and fails at the second line; use [`StreamReader::new`] for those.
+    pub fn detect(reader: R) -> Result<Self> {
+        let mut stream = StreamReader::new(reader);
+        if stream.skip_trivia()? == Some(b'[') {
+            stream.array = Some(ArrayState::Open);
+        }
+        Ok(stream)
+    }
+
+    /// Whether the elements of a top-level array are being read
+    pub fn is_array(&self) -> bool {
+        self.array.is_some()
+    }
+
+    /// Read the next value, or `None` at the end of the stream
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        if self.done {
+            return Ok(None);
+        }
+        let (start, bytes) = match self.next_text() {
+            Ok(Some(next)) => next,
+            Ok(None) => {
+                self.done = true;
+                return Ok(None);
+            }
+            Err(e) => {
+                self.done = true;
+                return Err(e);
+            }
+        };
+        parse_at(start, bytes).map(Some)
+    }
+
+    /// Consume the reader, returning the underlying `BufRead`
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The offset and text of the next value
+    fn next_text(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        let Some(state) = self.array else {
+            return self.read_text();
+        };
+        match state {
+            ArrayState::Open => {
+                if self.skip_trivia()? != Some(b'[') {
+                    return Err(self.error("Expected '[' at the start of the array"));
+                }
+                self.consume();
+                self.array = Some(ArrayState::First);
+                self.next_text()
+            }
+            ArrayState::First => match self.skip_trivia()? {
+                Some(b']') => self.close(),
+                Some(_) => self.element(),
+                None => Err(Error::UnexpectedEof),
+            },
+            ArrayState::Next => match self.skip_trivia()? {
+                Some(b']') => self.close(),
+                Some(b',') => {
+                    self.consume();
+                    match self.skip_trivia()? {
+                        // JSON5 allows a trailing comma
+                        Some(b']') => self.close(),
+                        Some(_) => self.element(),
+                        None => Err(Error::UnexpectedEof),
+                    }
+                }
+                Some(_) => Err(self.error("Expected ',' or ']' in array")),
+                None => Err(Error::UnexpectedEof),
+            },
+            ArrayState::Closed => match self.skip_trivia()? {
+                Some(_) => Err(self.error("Unexpected characters after value")),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn element(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        self.array = Some(ArrayState::Next);
+        self.read_text()?.map(Some).ok_or(Error::UnexpectedEof)
+    }
+
+    fn close(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        self.consume();
+        self.array = Some(ArrayState::Closed);
+        self.next_text()
+    }
+
+    /// Collect the text of the next value, consuming nothing past its end
+    fn read_text(&mut self) -> Result<Option<(usize, Vec<u8>)>> {
+        let start = self.offset;
+        let mut scanner = Scanner::new();
+        let mut bytes = Vec::new();
+        loop {
+            let chunk = self.reader.fill_buf()?;
+            if chunk.is_empty() {
+                if !scanner.started() {
+                    return Ok(None);
+                }
+                if !scanner.complete_at_eof() {
+                    return Err(Error::UnexpectedEof);
+                }
+                break;
+            }
+            let (n, complete) = match scanner.feed(chunk) {
+                Some(n) => (n, true),
+                None => (chunk.len(), false),
+            };
+            bytes.extend_from_slice(&chunk[..n]);
+            self.reader.consume(n);
+            self.offset += n;
+            if let Some(limit) = self.max_value_len {
+                if bytes.len() > limit {
+                    return Err(Error::LimitExceeded(format!(
+                        "value at byte {} is longer than {} bytes",
+                        start, limit
+                    )));
+                }
+            }
+            if complete {
+                break;
+            }
+        }
+        Ok(Some((start, bytes)))
+    }
+
+    /// Skip whitespace and comments, returning the next byte without consuming it
+    fn skip_trivia(&mut self) -> Result<Option<u8>> {
+        loop {
+            match self.peek()? {
+                Some(b' ' | b'\t' | b'\n' | b'\r') => self.consume(),
+                Some(b'/') => {
+                    self.consume();
+                    match self.peek()? {
+                        Some(b'/') => {
+                            while let Some(b) = self.peek()? {
+                                self.consume();
+                                if b == b'\n' {
+                                    break;
+                                }
+                            }
+                        }
+                        Some(b'*') => {
+                            self.consume();
+                            let mut star = false;
+                            loop {
+                                let b = self.peek()?.ok_or(Error::UnexpectedEof)?;
+                                self.consume();
+                                if star && b == b'/' {
+                                    break;
+                                }
+                                star = b == b'*';
+                            }
+                        }
+                        _ => return Err(self.error("Unexpected character: /")),
+                    }
+                }
+                other => return Ok(other),
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn consume(&mut self) {
+        self.reader.consume(1);
+        self.offset += 1;
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError {
+            position: self.offset,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamReader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+/// Parser that is handed input in chunks as it arrives
+#[derive(Debug)]
+pub struct PushParser {
+    scanner: Scanner,
+    /// Text of the value being received
+    buf: Vec<u8>,
+    /// Stream offset of the start of `buf`
+    offset: usize,
+    /// Values completed by the last [`feed`](PushParser::feed)
+    ready: Vec<Result<Value>>,
+    failed: bool,
+    max_value_len: Option<usize>,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        PushParser::new()
+    }
+}
+
+impl PushParser {
+    /// A parser for a sequence of values separated by whitespace
+    pub fn new() -> Self {
+        PushParser {
+            scanner: Scanner::new(),
+            buf: Vec::new(),
+            offset: 0,
+            ready: Vec::new(),
+            failed: false,
+            max_value_len: None,
+        }
+    }
+
+    /// Fail with [`Error::LimitExceeded`] on values longer than `len` bytes
+    pub fn max_value_len(mut self, len: usize) -> Self {
+        self.max_value_len = Some(len);
+        self
+    }
+
+    /// Take in the next chunk, returning the values it completes.
+    ///
+    /// Chunks may split the input anywhere, including inside strings,
+    /// comments and multibyte characters. After a
+    /// [`LimitExceeded`](Error::LimitExceeded) error further input is
+    /// ignored.
+    pub fn feed(&mut self, chunk: &[u8]) -> std::vec::Drain<'_, Result<Value>> {
+        self.ready.clear();
+        let mut rest = chunk;
+        while !rest.is_empty() && !self.failed {
+            let (n, complete) = match self.scanner.feed(rest) {
+                Some(n) => (n, true),
+                None => (rest.len(), false),
+            };
+            self.buf.extend_from_slice(&rest[..n]);
+            rest = &rest[n..];
+            if let Some(limit) = self.max_value_len {
+                if self.buf.len() > limit {
+                    self.failed = true;
+                    self.ready.push(Err(Error::LimitExceeded(format!(
+                        "value at byte {} is longer than {} bytes",
+                        self.offset, limit
+                    ))));
+                    break;
+                }
+            }
+            if complete {
+                let value = self.take();
+                self.ready.push(value);
+            }
+        }
+        self.ready.drain(..)
+    }
+
+    /// End the input, returning the last value if it was still open, such
+    /// as a number that no delimiter has followed yet
+    pub fn finish(mut self) -> Result<Option<Value>> {
+        if self.failed || !self.scanner.started() {
+            return Ok(None);
+        }
+        if !self.scanner.complete_at_eof() {
+            return Err(Error::UnexpectedEof);
+        }
+        self.take().map(Some)
+    }
+
+    /// Parse the received value and start on the next
+    fn take(&mut self) -> Result<Value> {
+        let start = self.offset;
+        self.offset += self.buf.len();
+        self.scanner = Scanner::new();
+        parse_at(start, std::mem::take(&mut self.buf))
+    }
+}
+
+/// Parse the text of a value found at byte `start` of a stream
+fn parse_at(start: usize, bytes: Vec<u8>) -> Result<Value> {
+    let text = String::from_utf8(bytes).map_err(|e| Error::ParseError {
+        position: start + e.utf8_error().valid_up_to(),
+        message: "Invalid UTF-8".to_string(),
+    })?;
+    parse(&text).map_err(|e| match e {
+        Error::ParseError { position, message } => Error::ParseError {
+            position: start + position,
+            message,
+        },
+        other => other,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serializer::to_string;
+    use std::io::BufReader;
+
+    fn texts<R: BufRead>(reader: StreamReader<R>) -> Vec<String> {
+        reader
+            .map(|value| to_string(&value.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_sequence() {
+        let input = "{ a: 1 }\n[1, 2n]\n\n3.5m 'x' // end\n";
+        assert_eq!(
+            texts(StreamReader::new(input.as_bytes())),
+            ["{a: 1}", "[1, 2n]", "3.5m", "'x'"]
+        );
+        assert!(StreamReader::new(&b" // nothing\n"[..]).next().is_none());
+    }
+
+    #[test]
+    fn test_array_elements() {
+        let input = "/* header */ [\n  { a: ']' },\n  12, 'b' // note\n  , [3],\n]\n";
+        // A tiny buffer forces every value across chunk boundaries
+        let reader = StreamReader::detect(BufReader::with_capacity(2, input.as_bytes())).unwrap();
+        assert!(reader.is_array());
+        assert_eq!(texts(reader), ["{a: ']'}", "12", "'b'", "[3]"]);
+
+        assert!(StreamReader::array(&b"[]"[..]).next().is_none());
+        assert!(!StreamReader::detect(&b"{}"[..]).unwrap().is_array());
+    }
+
+    #[test]
+    fn test_max_value_len() {
+        let input = "'short'\n'a much longer string value'\n'x'\n";
+        let mut reader =
+            StreamReader::new(BufReader::with_capacity(4, input.as_bytes())).max_value_len(10);
+        assert_eq!(reader.next().unwrap().unwrap(), Value::string("short"));
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err, Error::LimitExceeded(ref m) if m.contains("byte 7")));
+        assert!(err.is_limit());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_push_parser_chunks() {
+        let input = "{ a: '} \\' // ]', /* { */ b: [1, `x\ny`] } 12 'ü'\n// tail\n[3, ] 4.5m";
+        let expected: Vec<Value> = StreamReader::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(expected.len(), 5);
+        // Split the input at every possible pair of points
+        let bytes = input.as_bytes();
+        for i in 0..=bytes.len() {
+            for j in i..=bytes.len() {
+                let mut parser = PushParser::new();
+                let mut values = Vec::new();
+                for chunk in [&bytes[..i], &bytes[i..j], &bytes[j..]] {
+                    values.extend(parser.feed(chunk).map(Result::unwrap));
+                }
+                values.extend(parser.finish().unwrap());
+                assert_eq!(values, expected, "split at {} and {}", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_parser_errors() {
+        let mut parser = PushParser::new();
+        let results: Vec<_> = parser.feed(b"1 {a: } [2").collect();
+        assert!(matches!(
+            results[..],
+            [Ok(_), Err(Error::ParseError { position: 6, .. })]
+        ));
+        assert!(matches!(parser.finish(), Err(Error::UnexpectedEof)));
+        assert_eq!(PushParser::new().finish().unwrap(), None);
+
+        let mut parser = PushParser::new().max_value_len(8);
+        assert_eq!(parser.feed(b"'short' 'much").count(), 1);
+        let err = parser.feed(b" longer'").next().unwrap().unwrap_err();
+        assert!(err.is_limit());
+        assert_eq!(parser.feed(b" 1 2 3").count(), 0);
+        assert_eq!(parser.finish().unwrap(), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut reader = StreamReader::new(&b"1 {a: } 2"[..]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(Error::ParseError { position: 6, .. }))
+        ));
+        assert_eq!(reader.next().unwrap().unwrap(), Value::Number(2.0));
+
+        let mut reader = StreamReader::array(&b"[1 2]"[..]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(Error::ParseError { position: 3, .. }))
+        ));
+        assert!(reader.next().is_none());
+
+        let mut reader = StreamReader::array(&b"[1, [2"[..]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(reader.next(), Some(Err(Error::UnexpectedEof))));
+
+        let reader = StreamReader::array(&b"[1] [2]"[..]);
+        assert!(reader.last().unwrap().is_err());
+    }
+}
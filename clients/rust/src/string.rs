@@ -0,0 +1,288 @@
+//! The string type of values and object keys
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::ops::Deref;
+
+#[cfg(not(feature = "small-strings"))]
+type Repr = String;
+#[cfg(feature = "small-strings")]
+type Repr = compact_str::CompactString;
+
+/// A string value or object key.
+///
+/// It holds a `String`, or with the `small-strings` feature a
+/// `CompactString`, which keeps strings of up to 24 bytes inline instead of
+/// on the heap. The API is the same either way, so enabling the feature
+/// never breaks code that compiles without it. `Str` dereferences to `str`
+/// and converts to and from `String`:
+///
+/// ```
+/// use kjson::{Str, Value};
+///
+/// let value = Value::String("hello".into());
+/// if let Value::String(s) = &value {
+///     assert_eq!(s, "hello");
+///     assert_eq!(s.to_uppercase(), "HELLO");
+///     assert_eq!(String::from(s.clone()), "hello");
+/// }
+/// let key: Str = "name".into();
+/// assert_eq!(key.as_str(), "name");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Str(Repr);
+
+impl Str {
+    /// An empty string
+    pub fn new() -> Self {
+        Str(Repr::default())
+    }
+
+    /// The string as a `&str`
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Append `s` to the end
+    pub fn push_str(&mut self, s: &str) {
+        self.0.push_str(s);
+    }
+
+    /// Append a character to the end
+    pub fn push(&mut self, ch: char) {
+        self.0.push(ch);
+    }
+
+    /// Convert into a `String`, moving the text where possible
+    pub fn into_string(self) -> String {
+        #[cfg(not(feature = "small-strings"))]
+        return self.0;
+        #[cfg(feature = "small-strings")]
+        return self.0.into_string();
+    }
+
+    /// Whether the text is stored on the heap rather than inline
+    #[cfg(all(test, feature = "small-strings"))]
+    pub(crate) fn is_heap_allocated(&self) -> bool {
+        self.0.is_heap_allocated()
+    }
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl AsRef<str> for Str {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Str {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<std::ffi::OsStr> for Str {
+    fn as_ref(&self) -> &std::ffi::OsStr {
+        self.as_str().as_ref()
+    }
+}
+
+impl AsRef<std::path::Path> for Str {
+    fn as_ref(&self) -> &std::path::Path {
+        self.as_str().as_ref()
+    }
+}
+
+impl Borrow<str> for Str {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Write for Str {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Str {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Str::from(s))
+    }
+}
+
+impl From<&str> for Str {
+    fn from(s: &str) -> Self {
+        Str(Repr::from(s))
+    }
+}
+
+impl From<&mut str> for Str {
+    fn from(s: &mut str) -> Self {
+        Str::from(&*s)
+    }
+}
+
+impl From<String> for Str {
+    fn from(s: String) -> Self {
+        Str(Repr::from(s))
+    }
+}
+
+impl From<&String> for Str {
+    fn from(s: &String) -> Self {
+        Str::from(s.as_str())
+    }
+}
+
+impl From<Box<str>> for Str {
+    fn from(s: Box<str>) -> Self {
+        Str::from(String::from(s))
+    }
+}
+
+impl From<Cow<'_, str>> for Str {
+    fn from(s: Cow<'_, str>) -> Self {
+        match s {
+            Cow::Borrowed(s) => Str::from(s),
+            Cow::Owned(s) => Str::from(s),
+        }
+    }
+}
+
+impl From<char> for Str {
+    fn from(ch: char) -> Self {
+        let mut s = Str::new();
+        s.push(ch);
+        s
+    }
+}
+
+impl From<&Str> for Str {
+    fn from(s: &Str) -> Self {
+        s.clone()
+    }
+}
+
+impl From<Str> for String {
+    fn from(s: Str) -> String {
+        s.into_string()
+    }
+}
+
+impl<'a> From<&'a Str> for Cow<'a, str> {
+    fn from(s: &'a Str) -> Cow<'a, str> {
+        Cow::Borrowed(s.as_str())
+    }
+}
+
+impl FromIterator<char> for Str {
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        Str(chars.into_iter().collect())
+    }
+}
+
+impl<'a> FromIterator<&'a str> for Str {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(parts: I) -> Self {
+        Str(parts.into_iter().collect())
+    }
+}
+
+impl Extend<char> for Str {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, chars: I) {
+        self.0.extend(chars);
+    }
+}
+
+impl<'a> Extend<&'a str> for Str {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, parts: I) {
+        self.0.extend(parts);
+    }
+}
+
+macro_rules! eq_str {
+    ($($ty:ty),*) => {$(
+        impl PartialEq<$ty> for Str {
+            fn eq(&self, other: &$ty) -> bool {
+                self.as_str() == AsRef::<str>::as_ref(other)
+            }
+        }
+
+        impl PartialEq<Str> for $ty {
+            fn eq(&self, other: &Str) -> bool {
+                AsRef::<str>::as_ref(self) == other.as_str()
+            }
+        }
+    )*};
+}
+
+eq_str!(str, &str, String, Cow<'_, str>);
+
+impl serde::Serialize for Str {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Str {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Repr::deserialize(deserializer).map(Str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_str_api() {
+        let s = Str::from("key");
+        assert_eq!(s, "key");
+        assert_eq!("key", s);
+        assert_eq!(s, String::from("key"));
+        assert_eq!(s.len(), 3);
+        assert_eq!(format!("{} {:?}", s, s), "key \"key\"");
+        assert_eq!(String::from(s.clone()), "key");
+
+        // Looked up by `&str`, so `Hash` and `Eq` agree with `str`
+        let map: HashMap<Str, u8> = HashMap::from([(s, 1)]);
+        assert_eq!(map.get("key"), Some(&1));
+
+        let mut s: Str = ["a", "b"].into_iter().collect();
+        s.push('c');
+        s.push_str("de");
+        assert_eq!(s.as_str(), "abcde");
+        assert_eq!(Str::from("a").cmp(&Str::from("b")), std::cmp::Ordering::Less);
+        assert_eq!(serde_json::to_string(&s).unwrap(), "\"abcde\"");
+        assert_eq!(serde_json::from_str::<Str>("\"x\"").unwrap(), "x");
+    }
+}
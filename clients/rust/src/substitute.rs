@@ -0,0 +1,191 @@
+//! Variable substitution in string values
+//!
+//! Opt-in expansion of `${VAR}` references inside string values, for
+//! configuration files that refer to secrets and deployment-specific values
+//! without embedding them:
+//!
+//! ```no_run
+//! use kjson::substitute::{parse_with_resolver, EnvResolver};
+//!
+//! let value = parse_with_resolver(
+//!     "{ url: 'postgres://${DB_HOST:-localhost}:5432/app', password: '${DB_PASSWORD}' }",
+//!     &EnvResolver,
+//! )?;
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! | Form              | Expands to                                      |
+//! |-------------------|-------------------------------------------------|
+//! | `${VAR}`          | the value of `VAR`; an error if it is unset     |
+//! | `${VAR:-default}` | the value of `VAR`, or `default` if it is unset |
+//! | `$${`             | a literal `${`                                  |
+//!
+//! Only string values are expanded; object keys and other values are left
+//! as written. Values are looked up through a [`Resolver`], so variables can
+//! come from the process environment, a secret store or a plain map.
+//! Expanded text is not parsed again, so a value containing `${` stays as is.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::pointer;
+use crate::value::{into_str, Value};
+use std::collections::HashMap;
+
+/// Looks up variables referenced from string values
+pub trait Resolver {
+    /// The value of `name`, or `None` if it is not defined
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+impl<F> Resolver for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn resolve(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+impl Resolver for HashMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+/// Resolves variables from the process environment
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvResolver;
+
+impl Resolver for EnvResolver {
+    fn resolve(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// Parse a kJSON string, expanding variables in its string values
+pub fn parse_with_resolver(input: &str, resolver: &dyn Resolver) -> Result<Value> {
+    let mut value = parse(input)?;
+    substitute(&mut value, resolver)?;
+    Ok(value)
+}
+
+/// Expand variables in the string values of an already parsed value
+pub fn substitute(value: &mut Value, resolver: &dyn Resolver) -> Result<()> {
+    substitute_at(value, resolver, "")
+}
+
+fn substitute_at(value: &mut Value, resolver: &dyn Resolver, path: &str) -> Result<()> {
+    match value {
+        Value::String(s) if s.contains('$') => {
+            let expanded = expand(s, resolver).map_err(|message| {
+                let path = if path.is_empty() { "/" } else { path };
+                Error::Substitution(format!("{}: {}", path, message))
+            })?;
+            *s = into_str(expanded);
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                substitute_at(item, resolver, &pointer::push_index(path, index))?;
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                substitute_at(item, resolver, &pointer::push(path, key))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand the references in one string
+fn expand(input: &str, resolver: &dyn Resolver) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| "unterminated `${` reference".to_string())?;
+            let reference = &after[..end];
+            let (name, default) = match reference.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (reference, None),
+            };
+            if !is_variable_name(name) {
+                return Err(format!("invalid variable name `{}`", name));
+            }
+            match resolver.resolve(name) {
+                Some(value) => out.push_str(&value),
+                None => match default {
+                    Some(default) => out.push_str(default),
+                    None => return Err(format!("undefined variable `{}`", name)),
+                },
+            }
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("HOST".to_string(), "db.internal".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ])
+    }
+
+    #[test]
+    fn test_expand() {
+        let vars = vars();
+        assert_eq!(expand("${HOST}:5432", &vars).unwrap(), "db.internal:5432");
+        assert_eq!(expand("${PORT:-5432}", &vars).unwrap(), "5432");
+        assert_eq!(expand("[${EMPTY:-x}]", &vars).unwrap(), "[]");
+        assert_eq!(
+            expand("$${HOST} costs $5", &vars).unwrap(),
+            "${HOST} costs $5"
+        );
+        assert!(expand("${PORT}", &vars).unwrap_err().contains("`PORT`"));
+        assert!(expand("${HOST", &vars).is_err());
+        assert!(expand("${1X}", &vars).is_err());
+    }
+
+    #[test]
+    fn test_parse_with_resolver() {
+        let value = parse_with_resolver(
+            "{ '${HOST}': 1, db: { url: 'pg://${HOST}' }, list: ['${USER:-app}', 2] }",
+            &vars(),
+        )
+        .unwrap();
+        let map = value.as_object().unwrap();
+        assert!(map.contains_key("${HOST}"));
+        assert_eq!(
+            map["db"].as_object().unwrap()["url"],
+            Value::String("pg://db.internal".into())
+        );
+        assert_eq!(map["list"], parse("['app', 2]").unwrap());
+
+        let resolver = |name: &str| (name == "HOST").then(|| "h".to_string());
+        let err = parse_with_resolver("{ a: ['${HOST}', '${PASSWORD}'] }", &resolver).unwrap_err();
+        assert!(matches!(err, Error::Substitution(m) if m.starts_with("/a/1: ")));
+    }
+}
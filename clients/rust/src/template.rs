@@ -0,0 +1,240 @@
+//! Template interpolation in backtick strings
+//!
+//! Backtick strings may contain `${expr}` placeholders, where `expr` is a
+//! dotted path into a context value. [`parse_with_context`] fills them in
+//! while parsing; single- and double-quoted strings are left as written:
+//!
+//! ```
+//! use kjson::template::parse_with_context;
+//!
+//! let context = kjson::parse("{ env: 'prod', db: { hosts: ['db1', 'db2'], port: 5432 } }")?;
+//! let value = parse_with_context(
+//!     "{ url: `pg://${db.hosts.0}:${db.port}/${env}`, port: `${db.port}`, raw: '${env}' }",
+//!     &context,
+//! )?;
+//! assert_eq!(
+//!     value,
+//!     kjson::parse("{ url: 'pg://db1:5432/prod', port: 5432, raw: '${env}' }")?
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! | Form          | Expands to                                                    |
+//! |---------------|---------------------------------------------------------------|
+//! | `${a.b.0}`    | member `b` of `a`, then element 0; an error if missing        |
+//! | `` `${a}` ``  | a whole-string placeholder keeps the value's type             |
+//! | `$${`         | a literal `${`                                                |
+//!
+//! Strings are inserted as they are and other values in their compact kJSON
+//! form. [`Template`] exposes the parts of a template string for tools that
+//! evaluate placeholders themselves.
+
+use crate::edit::{Document, Item};
+use crate::error::{Error, Result};
+use crate::pointer;
+use crate::serializer::to_string;
+use crate::value::{Map, Str, Value};
+
+/// A backtick string split into text and placeholders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// One piece of a [`Template`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    /// Literal text, with `$${` already unescaped
+    Text(String),
+    /// The expression inside `${...}`, trimmed
+    Placeholder(String),
+}
+
+impl Template {
+    /// Split a string into text and placeholders
+    pub fn parse(input: &str) -> Result<Template> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = input;
+        while let Some(start) = rest.find('$') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+            if let Some(after) = rest.strip_prefix("$${") {
+                text.push_str("${");
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("${") {
+                let end = after.find('}').ok_or_else(|| {
+                    Error::Substitution("unterminated `${` placeholder".to_string())
+                })?;
+                let expr = after[..end].trim();
+                if expr.is_empty() || expr.split('.').any(str::is_empty) {
+                    return Err(Error::Substitution(format!(
+                        "invalid placeholder `{}`",
+                        expr
+                    )));
+                }
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(Part::Placeholder(expr.to_string()));
+                rest = &after[end + 1..];
+            } else {
+                text.push('$');
+                rest = &rest[1..];
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// The text and placeholders in order
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    /// Whether there are any placeholders
+    pub fn has_placeholders(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, Part::Placeholder(_)))
+    }
+
+    /// Fill in the placeholders from `context`.
+    ///
+    /// A template that is a single placeholder gives the referenced value
+    /// itself; anything else gives a string.
+    pub fn render(&self, context: &Value) -> Result<Value> {
+        if let [Part::Placeholder(expr)] = self.parts.as_slice() {
+            return lookup(context, expr).cloned();
+        }
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => out.push_str(text),
+                Part::Placeholder(expr) => match lookup(context, expr)? {
+                    Value::String(s) => out.push_str(s),
+                    value => out.push_str(&to_string(value)?),
+                },
+            }
+        }
+        Ok(Value::string(out))
+    }
+}
+
+/// The value at a dotted path
+fn lookup<'a>(context: &'a Value, expr: &str) -> Result<&'a Value> {
+    let mut value = context;
+    for segment in expr.split('.') {
+        let next = match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
+        value = next.ok_or_else(|| Error::Substitution(format!("undefined `{}`", expr)))?;
+    }
+    Ok(value)
+}
+
+/// Parse a kJSON string, filling in the placeholders of its backtick
+/// string values from `context`
+pub fn parse_with_context(input: &str, context: &Value) -> Result<Value> {
+    let document = Document::parse(input)?;
+    render_item(document.root(), context, "")
+}
+
+fn render_item(item: &Item, context: &Value, path: &str) -> Result<Value> {
+    match item {
+        Item::Scalar(scalar) => {
+            let Value::String(s) = scalar.value() else {
+                return Ok(scalar.value().clone());
+            };
+            if !scalar.raw().starts_with('`') || !s.contains('$') {
+                return Ok(scalar.value().clone());
+            }
+            Template::parse(s)
+                .and_then(|template| template.render(context))
+                .map_err(|e| match e {
+                    Error::Substitution(message) => {
+                        let path = if path.is_empty() { "/" } else { path };
+                        Error::Substitution(format!("{}: {}", path, message))
+                    }
+                    other => other,
+                })
+        }
+        Item::Array(array) => array
+            .iter()
+            .enumerate()
+            .map(|(i, item)| render_item(item, context, &pointer::push_index(path, i)))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        Item::Object(object) => {
+            // Later duplicates win, as in `parse`
+            let mut map = Map::new();
+            for (key, item) in object.iter() {
+                let value = render_item(item, context, &pointer::push(path, key))?;
+                map.insert(Str::from(key), value);
+            }
+            Ok(Value::Object(map))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_template_parts() {
+        let template = Template::parse("a ${ x.y } $${b} $5${z}").unwrap();
+        assert_eq!(
+            template.parts(),
+            [
+                Part::Text("a ".to_string()),
+                Part::Placeholder("x.y".to_string()),
+                Part::Text(" ${b} $5".to_string()),
+                Part::Placeholder("z".to_string()),
+            ]
+        );
+        assert!(!Template::parse("$${x}").unwrap().has_placeholders());
+        assert!(Template::parse("${x").is_err());
+        assert!(Template::parse("${a..b}").is_err());
+        assert!(Template::parse("${}").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let context = parse("{ n: 1.5, list: [true, 'x'], id: 7n }").unwrap();
+        let render = |s: &str| Template::parse(s).unwrap().render(&context);
+        assert_eq!(render("${list}").unwrap(), parse("[true, 'x']").unwrap());
+        assert_eq!(
+            render("${n}/${list.1}/${list}/${id}").unwrap(),
+            Value::String("1.5/x/[true, 'x']/7n".into())
+        );
+        assert_eq!(render("plain").unwrap(), Value::String("plain".into()));
+        assert!(render("${list.2}").is_err());
+        assert!(render("${n.x}").is_err());
+    }
+
+    #[test]
+    fn test_parse_with_context() {
+        let context = parse("{ host: 'h', ports: [80] }").unwrap();
+        let value = parse_with_context(
+            "{ `${host}`: `${host}`, a: [\"${host}\", `${ports.0}`, `$${host}`], b: `${ports}x` }",
+            &context,
+        )
+        .unwrap();
+        assert_eq!(
+            value,
+            parse("{ '${host}': 'h', a: ['${host}', 80, '${host}'], b: '[80]x' }").unwrap()
+        );
+
+        let err = parse_with_context("{ a: [1, `${missing}`] }", &context).unwrap_err();
+        assert!(matches!(err, Error::Substitution(m) if m == "/a/1: undefined `missing`"));
+        let err = parse_with_context("{ a: `${host` }", &context).unwrap_err();
+        assert!(matches!(err, Error::Substitution(m) if m.starts_with("/a: unterminated")));
+    }
+}
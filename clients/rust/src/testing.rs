@@ -0,0 +1,482 @@
+//! Generators for property-based tests and fuzzing
+//!
+//! With the `testing` feature, [`Value`] and the extended types implement
+//! [`arbitrary::Arbitrary`] for fuzz targets, and this module provides
+//! [proptest](https://docs.rs/proptest) strategies:
+//!
+//! ```no_run
+//! use kjson::testing::{value_with, ValueConfig};
+//! use proptest::prelude::*;
+//!
+//! proptest! {
+//!     #[test]
+//!     fn roundtrip(value in value_with(ValueConfig { depth: 3, ..ValueConfig::default() })) {
+//!         let text = kjson::to_string(&value).unwrap();
+//!         prop_assert_eq!(kjson::parse(&text).unwrap(), value);
+//!     }
+//! }
+//! ```
+//!
+//! Generated values survive a text round trip: numbers are finite, dates
+//! have whole seconds and minute offsets, and decimals stay within the 34
+//! digits of Decimal128.
+//!
+//! [`assert_kjson_eq!`](crate::assert_kjson_eq) compares documents as
+//! values, so key order and quote style do not matter, and prints a diff
+//! annotated with JSON Pointers when they differ.
+//! [`assert_kjson_snapshot!`](crate::assert_kjson_snapshot) compares a value
+//! against a stored snapshot in canonical pretty kJSON:
+//!
+//! ```no_run
+//! use kjson::{assert_kjson_eq, assert_kjson_snapshot};
+//!
+//! let config = kjson::parse("{ port: 8080, name: 'shop' }")?;
+//! assert_kjson_eq!(config, r#"{ "name": "shop", "port": 8080 }"#);
+//! // Compared with tests/snapshots/config.kjson, written on the first run
+//! assert_kjson_snapshot!("config", config);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Set `KJSON_UPDATE_SNAPSHOTS=1` to overwrite snapshots that differ; by
+//! default the new value is written next to the snapshot as `.kjson.new`.
+
+use crate::diff::{render_diff_plain, DiffStyle};
+use crate::parser::parse;
+use crate::serializer::to_string_pretty;
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::value::{Map, Value};
+use arbitrary::{Arbitrary, Unstructured};
+use chrono::{DateTime, FixedOffset, Utc};
+use proptest::prelude::*;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Largest Decimal128 coefficient, 34 nines
+const MAX_COEFFICIENT: i128 = 9_999_999_999_999_999_999_999_999_999_999_999;
+/// Exponents generated for decimals
+const EXPONENTS: std::ops::RangeInclusive<i32> = -30..=0;
+/// Seconds generated for dates, years 1900 to 2200
+const DATE_SECONDS: std::ops::Range<i64> = -2_208_988_800..7_258_118_400;
+/// Time zone offsets generated for dates, in minutes
+const OFFSET_MINUTES: std::ops::RangeInclusive<i16> = -14 * 60..=14 * 60;
+/// Nesting limit for [`Arbitrary`] values
+const ARBITRARY_DEPTH: u32 = 4;
+
+/// Shape limits for generated values
+#[derive(Debug, Clone, Copy)]
+pub struct ValueConfig {
+    /// Maximum nesting of arrays and objects
+    pub depth: u32,
+    /// Target total number of nodes
+    pub size: u32,
+    /// Maximum elements of one array or object
+    pub branch: u32,
+}
+
+impl Default for ValueConfig {
+    fn default() -> Self {
+        ValueConfig {
+            depth: 4,
+            size: 64,
+            branch: 8,
+        }
+    }
+}
+
+/// Strategy for values of any type, with the default [`ValueConfig`]
+pub fn value() -> BoxedStrategy<Value> {
+    value_with(ValueConfig::default())
+}
+
+/// Strategy for values of any type within `config`
+pub fn value_with(config: ValueConfig) -> BoxedStrategy<Value> {
+    let branch = config.branch as usize;
+    scalar()
+        .prop_recursive(config.depth, config.size, config.branch, move |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..=branch).prop_map(Value::Array),
+                prop::collection::hash_map(any::<String>(), inner, 0..=branch)
+                    .prop_map(Value::from),
+            ]
+        })
+        .boxed()
+}
+
+/// Strategy for non-container values
+pub fn scalar() -> BoxedStrategy<Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        (prop::num::f64::NORMAL | prop::num::f64::ZERO | prop::num::f64::SUBNORMAL)
+            .prop_map(Value::Number),
+        any::<i64>().prop_map(Value::Integer),
+        (1u64 << 63..).prop_map(Value::UInteger),
+        any::<String>().prop_map(Value::from),
+        bigint().prop_map(Value::BigInt),
+        decimal128().prop_map(Value::Decimal128),
+        uuid().prop_map(Value::Uuid),
+        date().prop_map(Value::Date),
+        instant().prop_map(Value::Instant),
+        duration().prop_map(Value::Duration),
+        prop::collection::vec(any::<u8>(), 0..32).prop_map(Value::Binary),
+    ]
+    .boxed()
+}
+
+/// Strategy for BigInts, including values beyond 64 bits
+pub fn bigint() -> BoxedStrategy<BigInt> {
+    prop_oneof![
+        any::<i64>().prop_map(BigInt::from_i64),
+        any::<i128>().prop_map(BigInt::from_i128),
+    ]
+    .boxed()
+}
+
+/// Strategy for Decimal128s with up to 34 significant digits
+pub fn decimal128() -> BoxedStrategy<Decimal128> {
+    (-MAX_COEFFICIENT..=MAX_COEFFICIENT, EXPONENTS)
+        .prop_map(|(coefficient, exponent)| {
+            Decimal128::from_parts(&BigInt::from_i128(coefficient), exponent)
+        })
+        .boxed()
+}
+
+/// Strategy for UUIDs
+pub fn uuid() -> BoxedStrategy<Uuid> {
+    any::<u128>().prop_map(Uuid::from_u128).boxed()
+}
+
+/// Strategy for dates, in UTC or with an offset
+pub fn date() -> BoxedStrategy<Date> {
+    (DATE_SECONDS, prop::option::of(OFFSET_MINUTES))
+        .prop_map(|(seconds, offset)| make_date(seconds, offset))
+        .boxed()
+}
+
+/// Strategy for nanosecond instants
+pub fn instant() -> BoxedStrategy<Instant> {
+    any::<i64>().prop_map(Instant::from_nanos).boxed()
+}
+
+/// Strategy for nanosecond durations, positive or negative
+pub fn duration() -> BoxedStrategy<Duration> {
+    any::<i64>().prop_map(Duration::from_nanos).boxed()
+}
+
+fn make_date(seconds: i64, offset: Option<i16>) -> Date {
+    let utc = DateTime::<Utc>::from_timestamp(seconds, 0).unwrap_or_default();
+    match offset {
+        None => Date::from_utc(utc),
+        Some(minutes) => {
+            let offset = FixedOffset::east_opt(i32::from(minutes) * 60).unwrap();
+            Date::from_datetime(utc.with_timezone(&offset))
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for BigInt {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(BigInt::from_i128(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Decimal128 {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let coefficient = u.int_in_range(-MAX_COEFFICIENT..=MAX_COEFFICIENT)?;
+        let exponent = u.int_in_range(EXPONENTS)?;
+        Ok(Decimal128::from_parts(
+            &BigInt::from_i128(coefficient),
+            exponent,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Date {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let seconds = u.int_in_range(DATE_SECONDS.start..=DATE_SECONDS.end - 1)?;
+        let offset = if u.arbitrary()? {
+            Some(u.int_in_range(OFFSET_MINUTES)?)
+        } else {
+            None
+        };
+        Ok(make_date(seconds, offset))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Instant {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Instant::from_nanos(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Duration {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Duration::from_nanos(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        arbitrary_value(u, ARBITRARY_DEPTH)
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: u32) -> arbitrary::Result<Value> {
+    let kinds = if depth == 0 { 13 } else { 15 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => {
+            let n: f64 = u.arbitrary()?;
+            Value::Number(if n.is_finite() { n } else { 0.0 })
+        }
+        3 => Value::String(u.arbitrary::<String>()?.into()),
+        4 => Value::BigInt(u.arbitrary()?),
+        5 => Value::Decimal128(u.arbitrary()?),
+        6 => Value::Uuid(Uuid::from_u128(u.arbitrary()?)),
+        7 => Value::Date(u.arbitrary()?),
+        8 => Value::Instant(u.arbitrary()?),
+        9 => Value::Duration(u.arbitrary()?),
+        10 => Value::Binary(u.arbitrary()?),
+        11 => Value::Integer(u.arbitrary()?),
+        12 => Value::from(u.arbitrary::<u64>()?),
+        13 => {
+            let mut items = Vec::new();
+            u.arbitrary_loop(None, Some(8), |u| {
+                items.push(arbitrary_value(u, depth - 1)?);
+                Ok(std::ops::ControlFlow::Continue(()))
+            })?;
+            Value::Array(items)
+        }
+        _ => {
+            let mut map = Map::new();
+            u.arbitrary_loop(None, Some(8), |u| {
+                map.insert(u.arbitrary::<String>()?, arbitrary_value(u, depth - 1)?);
+                Ok(std::ops::ControlFlow::Continue(()))
+            })?;
+            Value::Object(map)
+        }
+    })
+}
+
+/// Something [`assert_kjson_eq!`](crate::assert_kjson_eq) can compare:
+/// a [`Value`], or kJSON text, which is parsed
+pub trait AsValue {
+    /// The value; panics if text does not parse
+    fn as_value(&self) -> Value;
+}
+
+impl AsValue for Value {
+    fn as_value(&self) -> Value {
+        self.clone()
+    }
+}
+
+impl AsValue for str {
+    fn as_value(&self) -> Value {
+        parse(self).unwrap_or_else(|e| panic!("invalid kJSON in assertion: {}\n{}", e, self))
+    }
+}
+
+impl AsValue for String {
+    fn as_value(&self) -> Value {
+        self.as_str().as_value()
+    }
+}
+
+impl<T: AsValue + ?Sized> AsValue for &T {
+    fn as_value(&self) -> Value {
+        (**self).as_value()
+    }
+}
+
+/// Assert that two documents are equal as values.
+///
+/// Either side may be a [`Value`] or kJSON text. On failure the panic
+/// message lists the differences, `-` for `expected` and `+` for `actual`.
+#[macro_export]
+macro_rules! assert_kjson_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::testing::assert_eq_values(&$actual, &$expected, ::std::option::Option::None)
+    };
+    ($actual:expr, $expected:expr, $($message:tt)+) => {
+        $crate::testing::assert_eq_values(
+            &$actual,
+            &$expected,
+            ::std::option::Option::Some(::std::format!($($message)+)),
+        )
+    };
+}
+
+/// Assert that a value matches the snapshot `name` in the calling crate's
+/// `tests/snapshots` directory; see [`assert_snapshot`](crate::testing::assert_snapshot)
+#[macro_export]
+macro_rules! assert_kjson_snapshot {
+    ($name:expr, $value:expr $(,)?) => {
+        $crate::testing::assert_snapshot(
+            ::std::path::Path::new(::std::env!("CARGO_MANIFEST_DIR"))
+                .join("tests")
+                .join("snapshots")
+                .join(::std::format!("{}.kjson", $name)),
+            &$value,
+        )
+    };
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_eq_values(actual: &dyn AsValue, expected: &dyn AsValue, message: Option<String>) {
+    let (actual, expected) = (actual.as_value(), expected.as_value());
+    if actual != expected {
+        panic!(
+            "kJSON values differ{}\n{}",
+            message.map(|m| format!(": {}", m)).unwrap_or_default(),
+            render_diff_plain(&expected, &actual, DiffStyle::Unified)
+        );
+    }
+}
+
+/// Assert that `value` matches the snapshot stored at `path`.
+///
+/// Snapshots are canonical pretty kJSON and are compared as values, so
+/// reformatting one by hand does not break it. A missing snapshot is
+/// written and the assertion passes. On a mismatch the new value is written
+/// to `path` with `.new` appended, or over the snapshot if the
+/// `KJSON_UPDATE_SNAPSHOTS` environment variable is set, and the assertion
+/// fails with a diff.
+#[track_caller]
+pub fn assert_snapshot(path: impl AsRef<Path>, value: &dyn AsValue) {
+    let path = path.as_ref();
+    let value = value.as_value();
+    let text = to_string_pretty(&value)
+        .unwrap_or_else(|e| panic!("cannot serialize snapshot {}: {}", path.display(), e))
+        + "\n";
+    let write = |path: &Path| {
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        std::fs::write(path, &text)
+            .unwrap_or_else(|e| panic!("cannot write snapshot {}: {}", path.display(), e));
+    };
+
+    let stored = match std::fs::read_to_string(path) {
+        Ok(stored) => stored,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return write(path),
+        Err(e) => panic!("cannot read snapshot {}: {}", path.display(), e),
+    };
+    let stored =
+        parse(&stored).unwrap_or_else(|e| panic!("invalid snapshot {}: {}", path.display(), e));
+    if stored == value {
+        return;
+    }
+    let new_path = if std::env::var_os("KJSON_UPDATE_SNAPSHOTS").is_some() {
+        path.to_path_buf()
+    } else {
+        let mut new_path = path.as_os_str().to_owned();
+        new_path.push(".new");
+        new_path.into()
+    };
+    write(&new_path);
+    panic!(
+        "snapshot {} does not match, new value written to {}\n{}",
+        path.display(),
+        new_path.display(),
+        render_diff_plain(&stored, &value, DiffStyle::Unified)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_string};
+
+    /// Instants are written as timestamps, which parse as dates
+    fn instants_as_dates(mut value: Value) -> Value {
+        match &mut value {
+            Value::Instant(instant) => Value::Date(Date::from_utc(instant.to_datetime())),
+            Value::Array(items) => Value::Array(
+                std::mem::take(items)
+                    .into_iter()
+                    .map(instants_as_dates)
+                    .collect(),
+            ),
+            Value::Object(map) => Value::Object(
+                std::mem::take(map)
+                    .into_iter()
+                    .map(|(k, v)| (k, instants_as_dates(v)))
+                    .collect(),
+            ),
+            _ => value,
+        }
+    }
+
+    fn roundtrip(value: &Value) -> std::result::Result<(), String> {
+        let text = to_string(value).map_err(|e| e.to_string())?;
+        let parsed = parse(&text).map_err(|e| format!("{}: {}", e, text))?;
+        if parsed != instants_as_dates(value.clone()) {
+            return Err(format!("{:?} != {:?}", parsed, value));
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn test_text_roundtrip(value in value()) {
+            prop_assert_eq!(roundtrip(&value), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_arbitrary() {
+        let bytes: Vec<u8> = (0..4096u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        let mut containers = 0;
+        while !u.is_empty() {
+            let value = Value::arbitrary(&mut u).unwrap();
+            containers += matches!(value, Value::Array(_) | Value::Object(_)) as usize;
+            assert_eq!(roundtrip(&value), Ok(()));
+        }
+        assert!(containers > 0);
+    }
+
+    #[test]
+    fn test_assert_kjson_eq() {
+        let value = parse("{ b: [1, 2], a: 'x' }").unwrap();
+        crate::assert_kjson_eq!(value, "{ \"a\": \"x\", \"b\": [1, 2] }");
+        crate::assert_kjson_eq!("[1n]".to_string(), "[1n]", "context {}", 1);
+
+        let panic = std::panic::catch_unwind(|| {
+            crate::assert_kjson_eq!(value, "{ a: 'y', b: [1, 2] }", "case {}", 2);
+        })
+        .unwrap_err();
+        let message = panic.downcast_ref::<String>().unwrap();
+        assert_eq!(
+            message,
+            "kJSON values differ: case 2\n@@ /a @@\n- 'y'\n+ 'x'\n"
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = std::env::temp_dir().join(format!("kjson-snapshot-{}", std::process::id()));
+        let path = dir.join("nested").join("a.kjson");
+        let value = parse("{ b: 1, a: [true] }").unwrap();
+
+        assert_snapshot(&path, &value);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            to_string_pretty(&value).unwrap() + "\n"
+        );
+        // Compared as values, not text
+        std::fs::write(&path, "{a:[true],b:1}").unwrap();
+        assert_snapshot(&path, &value);
+
+        let changed = parse("{ b: 2, a: [true] }").unwrap();
+        assert!(std::panic::catch_unwind(|| assert_snapshot(&path, &changed)).is_err());
+        let new = std::fs::read_to_string(dir.join("nested").join("a.kjson.new")).unwrap();
+        assert_eq!(parse(&new).unwrap(), changed);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,361 @@
+//! Pull-based events over a kJSON document
+//!
+//! A [`Tokenizer`] walks a document and yields one [`Event`] per key,
+//! scalar and container boundary, each with the [`Span`] it covers, without
+//! building a [`Value`](crate::Value). Memory use grows only with nesting
+//! depth, so log processors and indexers can scan documents far larger than
+//! the tree they would parse to:
+//!
+//! ```
+//! use kjson::tokenizer::{Event, Tokenizer};
+//!
+//! let input = "[{ id: 1n, at: 2025-01-01T00:00:00Z }, { id: 2n }]";
+//! let ids: Vec<String> = Tokenizer::new(input)
+//!     .filter_map(|event| match event {
+//!         Ok((_, Event::BigInt(id))) => Some(Ok(id.to_string())),
+//!         Ok(_) => None,
+//!         Err(e) => Some(Err(e)),
+//!     })
+//!     .collect::<kjson::Result<_>>()?;
+//! assert_eq!(ids, ["1", "2"]);
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Strings and keys borrow from the input unless they contain escapes.
+//! Syntax is checked as the events are pulled, so a document that fails
+//! part way yields the events before the error, then the error, then
+//! nothing.
+
+use crate::error::{Error, Result};
+use crate::parser::Parser;
+use crate::span::Span;
+use crate::types::{BigInt, Date, Decimal128, Duration};
+use crate::value::Value;
+use std::borrow::Cow;
+use uuid::Uuid;
+
+/// One step through a document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event<'a> {
+    /// `{`
+    ObjectStart,
+    /// `}`
+    ObjectEnd,
+    /// `[`
+    ArrayStart,
+    /// `]`
+    ArrayEnd,
+    /// An object key, quoted or bare; its value follows
+    Key(Cow<'a, str>),
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number with a fraction or exponent, or outside the `i64` and `u64`
+    /// ranges
+    Number(f64),
+    /// An integer number
+    Integer(i64),
+    /// An integer number above `i64::MAX`
+    UInteger(u64),
+    /// A string value
+    String(Cow<'a, str>),
+    /// A number with the `n` suffix
+    BigInt(BigInt),
+    /// A number with the `m` suffix
+    Decimal128(Decimal128),
+    /// An unquoted UUID
+    Uuid(Uuid),
+    /// An unquoted date or timestamp
+    Date(Date),
+    /// An unquoted ISO 8601 duration
+    Duration(Duration),
+    /// A `b64'...'` binary literal
+    Binary(Vec<u8>),
+}
+
+impl Event<'_> {
+    /// Whether this is a complete value: anything but a key or container
+    /// boundary
+    pub fn is_scalar(&self) -> bool {
+        !matches!(
+            self,
+            Event::ObjectStart
+                | Event::ObjectEnd
+                | Event::ArrayStart
+                | Event::ArrayEnd
+                | Event::Key(_)
+        )
+    }
+
+    fn scalar(mut value: Value) -> Event<'static> {
+        match &mut value {
+            Value::Null => Event::Null,
+            Value::Bool(b) => Event::Bool(*b),
+            Value::Number(n) => Event::Number(*n),
+            Value::Integer(n) => Event::Integer(*n),
+            Value::UInteger(n) => Event::UInteger(*n),
+            Value::BigInt(n) => Event::BigInt(n.clone()),
+            Value::Decimal128(d) => Event::Decimal128(d.clone()),
+            Value::Uuid(u) => Event::Uuid(*u),
+            Value::Date(d) => Event::Date(d.clone()),
+            Value::Duration(d) => Event::Duration(d.clone()),
+            Value::Binary(bytes) => Event::Binary(std::mem::take(bytes)),
+            Value::String(_) | Value::Array(_) | Value::Object(_) | Value::Instant(_) => {
+                unreachable!("strings and containers are read by the tokenizer")
+            }
+        }
+    }
+}
+
+/// An open array or object
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+/// What the tokenizer reads next
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// A value: the document, or a member after its key
+    Value,
+    /// An array element, or the `]` after `[` or a trailing comma
+    Item,
+    /// An object key, or the `}` after `{` or a trailing comma
+    Member,
+    /// A `,` or the closing bracket of the innermost container
+    After,
+    /// Nothing: the document ended or failed
+    Done,
+}
+
+/// Iterator over the events of a document and the spans they cover
+#[derive(Debug)]
+pub struct Tokenizer<'a> {
+    parser: Parser<'a>,
+    /// Open containers, innermost last
+    stack: Vec<Container>,
+    state: State,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Tokenize `input`
+    pub fn new(input: &'a str) -> Self {
+        Tokenizer {
+            parser: Parser::new(input),
+            stack: Vec::new(),
+            state: State::Value,
+        }
+    }
+
+    /// Number of arrays and objects open after the last event
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn step(&mut self) -> Result<Option<(Span, Event<'a>)>> {
+        loop {
+            self.parser.skip_whitespace();
+            let close = match self.stack.last() {
+                Some(Container::Array) => b']',
+                Some(Container::Object) => b'}',
+                None => 0,
+            };
+            match self.state {
+                State::Item | State::Member if self.parser.peek() == Some(close) => {
+                    return Ok(Some(self.close()));
+                }
+                State::Value | State::Item => return self.value().map(Some),
+                State::Member => return self.key().map(Some),
+                State::After => {
+                    if self.stack.is_empty() {
+                        if !self.parser.at_end() {
+                            return Err(self.error("Unexpected characters after value"));
+                        }
+                        return Ok(None);
+                    }
+                    match self.parser.peek() {
+                        Some(b',') => {
+                            self.parser.advance();
+                            self.state = match self.stack.last() {
+                                Some(Container::Object) => State::Member,
+                                _ => State::Item,
+                            };
+                        }
+                        Some(b) if b == close => return Ok(Some(self.close())),
+                        _ if close == b']' => return Err(self.error("Expected ',' or ']'")),
+                        _ => return Err(self.error("Expected ',' or '}'")),
+                    }
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// The value at the cursor, or the start of one
+    fn value(&mut self) -> Result<(Span, Event<'a>)> {
+        let start = self.parser.position();
+        let event = match self.parser.peek() {
+            Some(b'[') => return Ok(self.open(Container::Array)),
+            Some(b'{') => return Ok(self.open(Container::Object)),
+            Some(b'"' | b'\'' | b'`') => Event::String(self.parser.parse_str()?),
+            _ => Event::scalar(self.parser.parse_value()?),
+        };
+        self.state = State::After;
+        Ok((Span::new(start, self.parser.position()), event))
+    }
+
+    /// A key and its colon
+    fn key(&mut self) -> Result<(Span, Event<'a>)> {
+        let start = self.parser.position();
+        let key = match self.parser.peek() {
+            Some(b'"' | b'\'' | b'`') => self.parser.parse_str()?,
+            _ => Cow::Borrowed(self.parser.parse_unquoted_key()?),
+        };
+        let span = Span::new(start, self.parser.position());
+        self.parser.skip_whitespace();
+        if self.parser.peek() != Some(b':') {
+            return Err(self.error("Expected ':' after key"));
+        }
+        self.parser.advance();
+        self.state = State::Value;
+        Ok((span, Event::Key(key)))
+    }
+
+    fn open(&mut self, container: Container) -> (Span, Event<'a>) {
+        let start = self.parser.position();
+        self.parser.advance();
+        self.stack.push(container);
+        let event = match container {
+            Container::Array => {
+                self.state = State::Item;
+                Event::ArrayStart
+            }
+            Container::Object => {
+                self.state = State::Member;
+                Event::ObjectStart
+            }
+        };
+        (Span::new(start, start + 1), event)
+    }
+
+    fn close(&mut self) -> (Span, Event<'a>) {
+        let start = self.parser.position();
+        self.parser.advance();
+        self.state = State::After;
+        let event = match self.stack.pop() {
+            Some(Container::Array) => Event::ArrayEnd,
+            _ => Event::ObjectEnd,
+        };
+        (Span::new(start, start + 1), event)
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::ParseError {
+            position: self.parser.position(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<(Span, Event<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.state == State::Done {
+            return None;
+        }
+        match self.step() {
+            Ok(Some(event)) => Some(Ok(event)),
+            Ok(None) => {
+                self.state = State::Done;
+                None
+            }
+            Err(e) => {
+                self.state = State::Done;
+                // Truncated input, such as an unclosed array or string
+                Some(Err(match e {
+                    Error::ParseError { .. } if self.parser.at_end() => Error::UnexpectedEof,
+                    other => other,
+                }))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Tokenizer<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(input: &str) -> Vec<(&str, Event<'_>)> {
+        Tokenizer::new(input)
+            .map(|event| event.map(|(span, event)| (span.slice(input), event)))
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_events() {
+        let input = r#"{ "a\u0062": [1, 2.5, 'x', null,], /* c */
+            id: 550e8400-e29b-41d4-a716-446655440000, at: 2025-01-01T00:00:00Z,
+            ttl: PT1H, big: 3n, price: 1.50m, raw: b64'AQID', e: {}, }"#;
+        let tokens = events(input);
+        assert_eq!(
+            tokens.iter().map(|(text, _)| *text).collect::<Vec<_>>(),
+            [
+                "{", "\"a\\u0062\"", "[", "1", "2.5", "'x'", "null", "]", "id",
+                "550e8400-e29b-41d4-a716-446655440000", "at", "2025-01-01T00:00:00Z", "ttl",
+                "PT1H", "big", "3n", "price", "1.50m", "raw", "b64'AQID'", "e", "{", "}", "}",
+            ]
+        );
+        assert!(matches!(&tokens[1].1, Event::Key(Cow::Owned(k)) if k == "ab"));
+        assert!(matches!(&tokens[5].1, Event::String(Cow::Borrowed("x"))));
+        assert!(matches!(&tokens[8].1, Event::Key(Cow::Borrowed("id"))));
+        assert_eq!(tokens[3].1, Event::Integer(1));
+        assert!(matches!(tokens[9].1, Event::Uuid(_)));
+        assert!(matches!(tokens[11].1, Event::Date(_)));
+        assert!(matches!(tokens[15].1, Event::BigInt(_)));
+        assert_eq!(tokens[19].1, Event::Binary(vec![1, 2, 3]));
+        assert_eq!(tokens.iter().filter(|(_, e)| e.is_scalar()).count(), 10);
+
+        assert_eq!(events("  true // done\n"), [("true", Event::Bool(true))]);
+    }
+
+    #[test]
+    fn test_depth() {
+        let mut tokenizer = Tokenizer::new("[[{a: []}]]");
+        let depths: Vec<usize> = std::iter::from_fn(|| {
+            tokenizer.next().map(|event| {
+                event.unwrap();
+                tokenizer.depth()
+            })
+        })
+        .collect();
+        assert_eq!(depths, [1, 2, 3, 3, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut tokenizer = Tokenizer::new("[1, 2 3]");
+        assert_eq!(tokenizer.next().unwrap().unwrap().1, Event::ArrayStart);
+        assert_eq!(tokenizer.next().unwrap().unwrap().1, Event::Integer(1));
+        assert_eq!(tokenizer.next().unwrap().unwrap().1, Event::Integer(2));
+        assert!(matches!(
+            tokenizer.next(),
+            Some(Err(Error::ParseError { position: 6, .. }))
+        ));
+        assert!(tokenizer.next().is_none());
+
+        for input in ["", "[1,", "{a", "'open", "{a: 1 b: 2}", "[,]", "1 2"] {
+            let last = Tokenizer::new(input).last();
+            assert!(matches!(last, Some(Err(_))), "{}", input);
+        }
+        assert!(matches!(
+            Tokenizer::new("{a: [1").last(),
+            Some(Err(Error::UnexpectedEof))
+        ));
+    }
+}
@@ -0,0 +1,372 @@
+//! tracing-subscriber layer writing kJSON lines
+//!
+//! [`KjsonLayer`] writes each event as one kJSON object per line, with
+//! native kJSON types where JSON logs would need string conventions:
+//!
+//! | Field | Type |
+//! |-------|------|
+//! | `timestamp` | Instant |
+//! | `trace_id`, `span_id` | UUID (v7, so ids sort by creation time) |
+//! | `elapsed`, `busy` on span close | Duration |
+//! | integers | Integer, or BigInt beyond the i64 range |
+//! | byte slices | Binary |
+//!
+//! ```no_run
+//! use tracing_subscriber::layer::SubscriberExt;
+//!
+//! let subscriber = tracing_subscriber::registry().with(kjson::tracing::KjsonLayer::new());
+//! tracing::subscriber::set_global_default(subscriber).unwrap();
+//! ```
+//!
+//! A line looks like:
+//!
+//! ```text
+//! {fields: {order: 42}, level: 'INFO', message: 'shipped', span: {name: 'checkout', user: 'ada'},
+//!  span_id: 0192..., target: 'shop', timestamp: 2025-01-10T12:00:00.123Z, trace_id: 0192...}
+//! ```
+
+use crate::serializer::to_string;
+use crate::types::{BigInt, Duration, Instant};
+use crate::value::{Map, Str, Value};
+use std::fmt;
+use std::io::Write;
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{Attributes, Id, Record};
+use tracing_core::{Event, Subscriber};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+use uuid::Uuid;
+
+/// Layer that writes events as kJSON lines
+pub struct KjsonLayer<W = fn() -> std::io::Stdout> {
+    make_writer: W,
+    span_close: bool,
+}
+
+impl KjsonLayer {
+    /// Write to standard output
+    pub fn new() -> Self {
+        KjsonLayer {
+            make_writer: std::io::stdout,
+            span_close: false,
+        }
+    }
+}
+
+impl Default for KjsonLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W> KjsonLayer<W> {
+    /// Write lines to another writer
+    pub fn with_writer<W2>(self, make_writer: W2) -> KjsonLayer<W2>
+    where
+        W2: for<'a> MakeWriter<'a> + 'static,
+    {
+        KjsonLayer {
+            make_writer,
+            span_close: self.span_close,
+        }
+    }
+
+    /// Also write a line when a span closes, with its `elapsed` and `busy` durations
+    pub fn with_span_close(mut self, span_close: bool) -> Self {
+        self.span_close = span_close;
+        self
+    }
+}
+
+/// Per-span state kept in the registry's extensions
+struct SpanData {
+    trace_id: Uuid,
+    span_id: Uuid,
+    fields: Map,
+    created: std::time::Instant,
+    entered: Option<std::time::Instant>,
+    busy: std::time::Duration,
+}
+
+/// Collects event and span fields as values
+struct FieldVisitor<'a> {
+    fields: &'a mut Map,
+}
+
+impl FieldVisitor<'_> {
+    fn insert(&mut self, field: &Field, value: Value) {
+        self.fields.insert(Str::from(field.name()), value);
+    }
+}
+
+impl Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, Value::Number(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, integer(value as i128));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, integer(value as i128));
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.insert(field, integer(value));
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        match i128::try_from(value) {
+            Ok(value) => self.insert(field, integer(value)),
+            Err(_) => self.insert(
+                field,
+                Value::BigInt(BigInt::from_str(&value.to_string()).expect("decimal digits")),
+            ),
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::Bool(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::string(value.to_string()));
+    }
+
+    fn record_bytes(&mut self, field: &Field, value: &[u8]) {
+        self.insert(field, Value::Binary(value.to_vec()));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.insert(field, Value::string(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::string(format!("{:?}", value)));
+    }
+}
+
+/// Integer if it fits an i64, UInteger if it fits a u64, BigInt otherwise
+fn integer(value: i128) -> Value {
+    match (i64::try_from(value), u64::try_from(value)) {
+        (Ok(value), _) => Value::Integer(value),
+        (_, Ok(value)) => Value::UInteger(value),
+        _ => Value::BigInt(BigInt::from_i128(value)),
+    }
+}
+
+fn duration(d: std::time::Duration) -> Value {
+    Value::Duration(Duration::from_nanos(
+        i64::try_from(d.as_nanos()).unwrap_or(i64::MAX),
+    ))
+}
+
+impl<W> KjsonLayer<W>
+where
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn write(&self, line: Map) {
+        if let Ok(mut text) = to_string(&Value::Object(line)) {
+            text.push('\n');
+            // Logging must never take the application down
+            let _ = self.make_writer.make_writer().write_all(text.as_bytes());
+        }
+    }
+}
+
+impl<S, W> Layer<S> for KjsonLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let span_id = crate::types::uuid_v7();
+        let trace_id = span
+            .parent()
+            .and_then(|parent| parent.extensions().get::<SpanData>().map(|d| d.trace_id))
+            .unwrap_or(span_id);
+        let mut fields = Map::new();
+        attrs.record(&mut FieldVisitor {
+            fields: &mut fields,
+        });
+        span.extensions_mut().insert(SpanData {
+            trace_id,
+            span_id,
+            fields,
+            created: std::time::Instant::now(),
+            entered: None,
+            busy: std::time::Duration::ZERO,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                values.record(&mut FieldVisitor {
+                    fields: &mut data.fields,
+                });
+            }
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                data.entered = Some(std::time::Instant::now());
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+                if let Some(entered) = data.entered.take() {
+                    data.busy += entered.elapsed();
+                }
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut fields = Map::new();
+        event.record(&mut FieldVisitor {
+            fields: &mut fields,
+        });
+
+        let mut line = Map::new();
+        line.insert("timestamp", Value::Instant(Instant::now()));
+        line.insert("level", Value::string(meta.level().to_string()));
+        line.insert("target", Value::string(meta.target().to_string()));
+        if let Some(message) = fields.remove("message") {
+            line.insert("message", message);
+        }
+        if !fields.is_empty() {
+            line.insert("fields", Value::Object(fields));
+        }
+        if let Some(span) = ctx.event_span(event) {
+            if let Some(data) = span.extensions().get::<SpanData>() {
+                let mut span_fields = data.fields.clone();
+                span_fields.insert("name", Value::string(span.name().to_string()));
+                line.insert("span", Value::Object(span_fields));
+                line.insert("span_id", Value::Uuid(data.span_id));
+                line.insert("trace_id", Value::Uuid(data.trace_id));
+            }
+        }
+        self.write(line);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        if !self.span_close {
+            return;
+        }
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let Some(data) = extensions.get::<SpanData>() else {
+            return;
+        };
+        let meta = span.metadata();
+        let mut span_fields = data.fields.clone();
+        span_fields.insert("name", Value::string(span.name().to_string()));
+
+        let mut line = Map::new();
+        line.insert("timestamp", Value::Instant(Instant::now()));
+        line.insert("level", Value::string(meta.level().to_string()));
+        line.insert("target", Value::string(meta.target().to_string()));
+        line.insert("message", Value::string("close"));
+        line.insert("span", Value::Object(span_fields));
+        line.insert("span_id", Value::Uuid(data.span_id));
+        line.insert("trace_id", Value::Uuid(data.trace_id));
+        line.insert("elapsed", duration(data.created.elapsed()));
+        line.insert("busy", duration(data.busy));
+        self.write(line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn capture(span_close: bool, f: impl FnOnce()) -> Vec<Map> {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let layer = KjsonLayer::new()
+            .with_writer(move || writer.clone())
+            .with_span_close(span_close);
+        let subscriber = tracing_subscriber::registry().with(layer);
+        ::tracing::subscriber::with_default(subscriber, f);
+
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        text.lines()
+            .map(|line| parse(line).unwrap().as_object().unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_event_lines() {
+        let lines = capture(false, || {
+            let span = ::tracing::info_span!("checkout", user = "ada");
+            let _guard = span.enter();
+            ::tracing::warn!(order = 42, total = 9_007_199_254_740_993u64, "shipped");
+        });
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line["level"], Value::String("WARN".into()));
+        assert_eq!(line["message"], Value::String("shipped".into()));
+        assert!(matches!(line["timestamp"], Value::Date(_) | Value::Instant(_)));
+        assert!(line["span_id"].as_uuid().is_some());
+        assert_eq!(line["trace_id"], line["span_id"]);
+
+        let fields = line["fields"].as_object().unwrap();
+        assert_eq!(fields["order"], Value::Integer(42));
+        assert_eq!(fields["total"], Value::Integer(9_007_199_254_740_993));
+        let span = line["span"].as_object().unwrap();
+        assert_eq!(span["name"], Value::String("checkout".into()));
+        assert_eq!(span["user"], Value::String("ada".into()));
+    }
+
+    #[test]
+    fn test_span_close() {
+        let lines = capture(true, || {
+            let outer = ::tracing::info_span!("request");
+            let _outer = outer.enter();
+            let inner = ::tracing::debug_span!("query");
+            inner.in_scope(|| ::tracing::info!("running"));
+        });
+        // The event, then the inner span closing, then the outer one
+        let close: Vec<_> = lines
+            .iter()
+            .filter(|line| line["message"] == Value::String("close".into()))
+            .collect();
+        assert_eq!(close.len(), 2);
+        assert!(close[0]["elapsed"].as_duration().is_some());
+        assert!(close[0]["busy"].as_duration().is_some());
+
+        // Child spans share the root's trace id
+        assert_eq!(lines[0]["trace_id"], close[1]["trace_id"]);
+        assert_ne!(lines[0]["span_id"], close[1]["span_id"]);
+    }
+}
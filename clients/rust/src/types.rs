@@ -1,12 +1,24 @@
 use crate::error::{Error, Result};
 use chrono::{DateTime, FixedOffset, TimeZone, Utc, Offset};
 use num_bigint::BigInt as NumBigInt;
-use num_traits::Num;
+use num_traits::{Num, ToPrimitive};
+use regex::Regex;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// `pattern` compiled on first use and kept for the life of the program
+fn cached(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).expect("valid pattern"))
+}
 
 /// BigInt type for arbitrary precision integers
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Numeric)
+)]
 pub struct BigInt {
     value: NumBigInt,
 }
@@ -20,6 +32,7 @@ impl BigInt {
     }
 
     /// Create a new BigInt from a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
         let s = s.trim_end_matches('n');
         match NumBigInt::from_str_radix(s, 10) {
@@ -29,6 +42,7 @@ impl BigInt {
     }
 
     /// Convert to string representation without suffix
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         self.value.to_string()
     }
@@ -37,6 +51,51 @@ impl BigInt {
     pub fn to_kjson_string(&self) -> String {
         format!("{}n", self.value)
     }
+
+    /// Convert to the nearest f64 (may lose precision)
+    pub fn to_f64(&self) -> f64 {
+        self.value.to_f64().unwrap_or(f64::NAN)
+    }
+
+    /// Create a new BigInt from an i128
+    pub fn from_i128(n: i128) -> Self {
+        BigInt {
+            value: NumBigInt::from(n),
+        }
+    }
+
+    /// Convert to i64 if the value fits
+    pub fn to_i64(&self) -> Option<i64> {
+        self.value.to_i64()
+    }
+
+    /// Convert to i128 if the value fits
+    pub fn to_i128(&self) -> Option<i128> {
+        self.value.to_i128()
+    }
+
+    /// Whether the value is below zero
+    pub fn is_negative(&self) -> bool {
+        self.value.sign() == num_bigint::Sign::Minus
+    }
+
+    /// Sign flag and big-endian magnitude bytes
+    pub fn to_bytes_be(&self) -> (bool, Vec<u8>) {
+        let (sign, bytes) = self.value.to_bytes_be();
+        (sign == num_bigint::Sign::Minus, bytes)
+    }
+
+    /// Create from a sign flag and big-endian magnitude bytes
+    pub fn from_bytes_be(negative: bool, bytes: &[u8]) -> Self {
+        let sign = if negative {
+            num_bigint::Sign::Minus
+        } else {
+            num_bigint::Sign::Plus
+        };
+        BigInt {
+            value: NumBigInt::from_bytes_be(sign, bytes),
+        }
+    }
 }
 
 impl fmt::Display for BigInt {
@@ -55,6 +114,11 @@ impl FromStr for BigInt {
 
 /// Decimal128 type for high-precision decimal numbers
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Numeric)
+)]
 pub struct Decimal128 {
     /// The digits of the decimal number (without decimal point)
     digits: String,
@@ -66,16 +130,27 @@ pub struct Decimal128 {
 
 impl Decimal128 {
     /// Create a new Decimal128 from a string
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Result<Self> {
+        let original = s;
         let s = s.trim_end_matches('m');
         let negative = s.starts_with('-');
-        let s = s.trim_start_matches('-');
+        let s = s.strip_prefix('-').unwrap_or(s);
+
+        let digit_count = s.chars().filter(|c| c.is_ascii_digit()).count();
+        if digit_count == 0
+            || s.chars().any(|c| !c.is_ascii_digit() && c != '.')
+            || s.matches('.').count() > 1
+        {
+            return Err(Error::InvalidDecimal128(original.to_string()));
+        }
 
         // Find decimal point
         if let Some(dot_pos) = s.find('.') {
             let integer_part = &s[..dot_pos];
             let decimal_part = &s[dot_pos + 1..];
             let digits = format!("{}{}", integer_part, decimal_part);
+            let digits = strip_leading_zeros(&digits);
             let exponent = -(decimal_part.len() as i32);
 
             Ok(Decimal128 {
@@ -85,7 +160,7 @@ impl Decimal128 {
             })
         } else {
             Ok(Decimal128 {
-                digits: s.to_string(),
+                digits: strip_leading_zeros(s),
                 exponent: 0,
                 negative,
             })
@@ -103,6 +178,7 @@ impl Decimal128 {
     }
 
     /// Convert to string representation without suffix
+    #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
         if self.exponent == 0 {
             format!("{}{}", if self.negative { "-" } else { "" }, self.digits)
@@ -110,7 +186,7 @@ impl Decimal128 {
             let exp = (-self.exponent) as usize;
             let len = self.digits.len();
             let result = if exp >= len {
-                let zeros = "0".repeat(exp - len + 1);
+                let zeros = "0".repeat(exp - len);
                 format!("0.{}{}", zeros, self.digits)
             } else {
                 let (integer, decimal) = self.digits.split_at(len - exp);
@@ -133,6 +209,71 @@ impl Decimal128 {
     pub fn to_kjson_string(&self) -> String {
         format!("{}m", self.to_string())
     }
+
+    /// Convert to the nearest f64 (may lose precision)
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Create from an unscaled coefficient and exponent (`coefficient * 10^exponent`)
+    pub fn from_parts(coefficient: &BigInt, exponent: i32) -> Self {
+        let mut digits = coefficient.value.magnitude().to_string();
+        // Keep the same form as parsed literals, which never have a positive exponent
+        if exponent > 0 && digits != "0" {
+            digits.push_str(&"0".repeat(exponent as usize));
+        }
+        Decimal128 {
+            digits,
+            exponent: exponent.min(0),
+            negative: coefficient.is_negative(),
+        }
+    }
+
+    /// Unscaled coefficient, so that the value is `coefficient * 10^exponent`
+    pub fn coefficient(&self) -> BigInt {
+        let magnitude = NumBigInt::from_str_radix(&self.digits, 10).unwrap_or_default();
+        BigInt {
+            value: if self.negative { -magnitude } else { magnitude },
+        }
+    }
+
+    /// Decimal exponent (negative for digits after the decimal point)
+    pub fn exponent(&self) -> i32 {
+        self.exponent
+    }
+
+    /// The same value without trailing zeros after the decimal point, so
+    /// that `1.50m` and `1.5m` normalize to equal values
+    pub fn normalize(&self) -> Self {
+        if self.digits.bytes().all(|b| b == b'0') {
+            return Decimal128 {
+                digits: "0".to_string(),
+                exponent: 0,
+                negative: false,
+            };
+        }
+        let mut digits = self.digits.clone();
+        let mut exponent = self.exponent;
+        while exponent < 0 && digits.ends_with('0') {
+            digits.pop();
+            exponent += 1;
+        }
+        Decimal128 {
+            digits,
+            exponent,
+            negative: self.negative,
+        }
+    }
+}
+
+/// Drop leading zeros from a digit string, keeping at least one digit
+fn strip_leading_zeros(digits: &str) -> String {
+    let trimmed = digits.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
 }
 
 impl fmt::Display for Decimal128 {
@@ -151,6 +292,11 @@ impl FromStr for Decimal128 {
 
 /// Instant type representing a nanosecond-precision timestamp in Zulu time (UTC)
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Timestamptz)
+)]
 pub struct Instant {
     /// Nanoseconds since Unix epoch (UTC)
     pub nanoseconds: i64,
@@ -201,8 +347,8 @@ impl Instant {
         };
 
         // Parse the Zulu string manually to preserve nanosecond precision
-        let re = regex::Regex::new(r"^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2}):(\d{2})(?:\.(\d+))?Z$")
-            .map_err(|_| Error::InvalidDate(s.to_string()))?;
+        static ZULU: OnceLock<Regex> = OnceLock::new();
+        let re = cached(&ZULU, r"^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2}):(\d{2})(?:\.(\d+))?Z$");
         
         let captures = re.captures(&zulu_string)
             .ok_or_else(|| Error::InvalidDate(s.to_string()))?;
@@ -231,7 +377,7 @@ impl Instant {
         // Handle fractional seconds
         if let Some(fraction_str) = captures.get(7) {
             // Pad or truncate to 9 digits (nanoseconds)
-            let padded_fraction = format!("{:<09}", fraction_str.as_str());
+            let padded_fraction = format!("{:0<9}", fraction_str.as_str());
             let truncated_fraction = &padded_fraction[..9];
             let fraction_nanos: i64 = truncated_fraction.parse()
                 .map_err(|_| Error::InvalidDate(s.to_string()))?;
@@ -246,28 +392,30 @@ impl Instant {
 
     /// Convert to ISO 8601 string with nanosecond precision
     pub fn to_iso8601(&self) -> String {
-        let seconds = self.nanoseconds / 1_000_000_000;
-        let nanos_remainder = self.nanoseconds % 1_000_000_000;
+        // Floor division, so instants before the epoch keep a positive fraction
+        let seconds = self.nanoseconds.div_euclid(1_000_000_000);
+        let nanos_remainder = self.nanoseconds.rem_euclid(1_000_000_000);
 
         // Create datetime from seconds
         let dt = DateTime::from_timestamp(seconds, 0)
-            .unwrap_or_else(|| Utc::now());
+            .unwrap_or_else(Utc::now);
 
         if nanos_remainder == 0 {
             dt.format("%Y-%m-%dT%H:%M:%SZ").to_string()
         } else {
             // Format nanoseconds (remove trailing zeros)
-            let fractional_str = format!("{:09}", nanos_remainder).trim_end_matches('0');
+            let padded = format!("{:09}", nanos_remainder);
+            let fractional_str = padded.trim_end_matches('0');
             dt.format(&format!("%Y-%m-%dT%H:%M:%S.{}Z", fractional_str)).to_string()
         }
     }
 
     /// Convert to DateTime<Utc> (loses nanosecond precision)
     pub fn to_datetime(&self) -> DateTime<Utc> {
-        let seconds = self.nanoseconds / 1_000_000_000;
-        let nanos_remainder = (self.nanoseconds % 1_000_000_000) as u32;
+        let seconds = self.nanoseconds.div_euclid(1_000_000_000);
+        let nanos_remainder = self.nanoseconds.rem_euclid(1_000_000_000) as u32;
         DateTime::from_timestamp(seconds, nanos_remainder)
-            .unwrap_or_else(|| Utc::now())
+            .unwrap_or_else(Utc::now)
     }
 
     /// Get nanoseconds since epoch
@@ -302,6 +450,11 @@ impl FromStr for Instant {
 
 /// Duration type representing a time span with nanosecond precision
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = diesel::sql_types::Interval)
+)]
 pub struct Duration {
     /// Duration in nanoseconds
     pub nanoseconds: i64,
@@ -350,40 +503,52 @@ impl Duration {
 
     /// Parse ISO 8601 duration string
     pub fn from_iso8601(s: &str) -> Result<Self> {
-        let re = regex::Regex::new(r"^P(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+(?:\.\d+)?)S)?)?$")
-            .map_err(|_| Error::InvalidDuration(s.to_string()))?;
+        static DURATION: OnceLock<Regex> = OnceLock::new();
+        let re = cached(
+            &DURATION,
+            r"^(-)?P(?:(\d+)D)?(?:T(?:(\d+)H)?(?:(\d+)M)?(?:(\d+(?:\.\d+)?)S)?)?$",
+        );
         
         let captures = re.captures(s)
             .ok_or_else(|| Error::InvalidDuration(s.to_string()))?;
 
+        // "P" and "PT" on their own carry no components
+        if (2..=5).all(|i| captures.get(i).is_none()) || s.ends_with('T') {
+            return Err(Error::InvalidDuration(s.to_string()));
+        }
+
         let mut total_nanos = 0i64;
 
         // Days
-        if let Some(days_str) = captures.get(1) {
+        if let Some(days_str) = captures.get(2) {
             let days: i64 = days_str.as_str().parse()
                 .map_err(|_| Error::InvalidDuration(s.to_string()))?;
             total_nanos += days * 86400 * 1_000_000_000;
         }
 
         // Hours
-        if let Some(hours_str) = captures.get(2) {
+        if let Some(hours_str) = captures.get(3) {
             let hours: i64 = hours_str.as_str().parse()
                 .map_err(|_| Error::InvalidDuration(s.to_string()))?;
             total_nanos += hours * 3600 * 1_000_000_000;
         }
 
         // Minutes
-        if let Some(minutes_str) = captures.get(3) {
+        if let Some(minutes_str) = captures.get(4) {
             let minutes: i64 = minutes_str.as_str().parse()
                 .map_err(|_| Error::InvalidDuration(s.to_string()))?;
             total_nanos += minutes * 60 * 1_000_000_000;
         }
 
         // Seconds
-        if let Some(seconds_str) = captures.get(4) {
+        if let Some(seconds_str) = captures.get(5) {
             let seconds: f64 = seconds_str.as_str().parse()
                 .map_err(|_| Error::InvalidDuration(s.to_string()))?;
-            total_nanos += (seconds * 1_000_000_000.0) as i64;
+            total_nanos += (seconds * 1_000_000_000.0).round() as i64;
+        }
+
+        if captures.get(1).is_some() {
+            total_nanos = -total_nanos;
         }
 
         Ok(Duration { nanoseconds: total_nanos })
@@ -430,7 +595,8 @@ impl Duration {
                 if nanos_part == 0 {
                     result.push_str(&format!("{}S", seconds));
                 } else {
-                    let fractional_str = format!("{:09}", nanos_part).trim_end_matches('0');
+                    let padded = format!("{:09}", nanos_part);
+                    let fractional_str = padded.trim_end_matches('0');
                     result.push_str(&format!("{}.{}S", seconds, fractional_str));
                 }
             }
@@ -594,8 +760,9 @@ impl Date {
             let dt = self.utc.with_timezone(&offset);
             dt.to_rfc3339()
         } else {
-            // Format as "Z" instead of "+00:00"
-            self.utc.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+            // Format as "Z" instead of "+00:00", with fractional seconds
+            // in 3, 6 or 9 digits as chrono writes them
+            self.utc.format("%Y-%m-%dT%H:%M:%S%.fZ").to_string()
         }
     }
 }
@@ -614,6 +781,108 @@ impl FromStr for Date {
     }
 }
 
+// Deserializing from kJSON literal text, which is how the native
+// deserializer hands extended types to visitors and how JSON carries them
+
+macro_rules! deserialize_literal {
+    ($ty:ident, $expecting:literal $(, $method:item)*) => {
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct LiteralVisitor;
+
+                impl serde::de::Visitor<'_> for LiteralVisitor {
+                    type Value = $ty;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        f.write_str($expecting)
+                    }
+
+                    fn visit_str<E>(self, s: &str) -> std::result::Result<$ty, E>
+                    where
+                        E: serde::de::Error,
+                    {
+                        $ty::from_str(s)
+                            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(s), &self))
+                    }
+
+                    $($method)*
+                }
+
+                // Self-describing formats may hold a number instead of the text
+                if deserializer.is_human_readable() {
+                    deserializer.deserialize_any(LiteralVisitor)
+                } else {
+                    deserializer.deserialize_str(LiteralVisitor)
+                }
+            }
+        }
+    };
+}
+
+deserialize_literal!(
+    BigInt,
+    "an integer",
+    fn visit_i64<E: serde::de::Error>(self, n: i64) -> std::result::Result<BigInt, E> {
+        Ok(BigInt::from_i64(n))
+    },
+    fn visit_u64<E: serde::de::Error>(self, n: u64) -> std::result::Result<BigInt, E> {
+        Ok(BigInt::from_i128(n as i128))
+    },
+    fn visit_i128<E: serde::de::Error>(self, n: i128) -> std::result::Result<BigInt, E> {
+        Ok(BigInt::from_i128(n))
+    },
+    fn visit_f64<E: serde::de::Error>(self, n: f64) -> std::result::Result<BigInt, E> {
+        if n.is_finite() && n.fract() == 0.0 {
+            BigInt::from_str(&format!("{:.0}", n)).map_err(E::custom)
+        } else {
+            Err(E::invalid_value(serde::de::Unexpected::Float(n), &self))
+        }
+    }
+);
+
+deserialize_literal!(
+    Decimal128,
+    "a decimal number",
+    fn visit_i64<E: serde::de::Error>(self, n: i64) -> std::result::Result<Decimal128, E> {
+        Decimal128::from_str(&n.to_string()).map_err(E::custom)
+    },
+    fn visit_u64<E: serde::de::Error>(self, n: u64) -> std::result::Result<Decimal128, E> {
+        Decimal128::from_str(&n.to_string()).map_err(E::custom)
+    },
+    fn visit_f64<E: serde::de::Error>(self, n: f64) -> std::result::Result<Decimal128, E> {
+        Decimal128::from_str(&n.to_string())
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Float(n), &self))
+    }
+);
+
+deserialize_literal!(Instant, "an ISO 8601 timestamp");
+deserialize_literal!(Duration, "an ISO 8601 duration");
+deserialize_literal!(Date, "an ISO 8601 timestamp");
+
+// Serializing as kJSON literals; other serializers see the literal text
+
+macro_rules! serialize_literal {
+    ($ty:ident, $text:ident) => {
+        impl serde::Serialize for $ty {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.serialize_newtype_struct(crate::ser::LITERAL, &self.$text())
+            }
+        }
+    };
+}
+
+serialize_literal!(BigInt, to_kjson_string);
+serialize_literal!(Decimal128, to_kjson_string);
+serialize_literal!(Instant, to_iso8601);
+serialize_literal!(Duration, to_iso8601);
+serialize_literal!(Date, to_iso8601);
+
 // UUID generation functions
 
 /// Generate a UUID v4 (random)
@@ -652,9 +921,6 @@ pub fn uuid_v7() -> uuid::Uuid {
     uuid::Uuid::from_bytes(bytes)
 }
 
-// Add rand dependency for uuid_v7
-use rand;
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -677,6 +943,42 @@ mod tests {
 
         let d2 = Decimal128::from_str("99.99m").unwrap();
         assert_eq!(d2.to_string(), "99.99");
+
+        assert!(Decimal128::from_str("abc").is_err());
+        assert!(Decimal128::from_str("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_decimal128_parts() {
+        let d = Decimal128::from_str("-12.345").unwrap();
+        assert_eq!(d.coefficient(), BigInt::from_i64(-12345));
+        assert_eq!(d.exponent(), -3);
+        assert_eq!(Decimal128::from_parts(&d.coefficient(), d.exponent()), d);
+
+        let small = Decimal128::from_parts(&BigInt::from_i64(5), -2);
+        assert_eq!(small.to_string(), "0.05");
+        assert_eq!(small, Decimal128::from_str("0.05").unwrap());
+        let large = Decimal128::from_parts(&BigInt::from_i64(5), 2);
+        assert_eq!(large.to_string(), "500");
+
+        let d = Decimal128::from_str("1.500").unwrap();
+        assert_ne!(d, Decimal128::from_str("1.5").unwrap());
+        assert_eq!(d.normalize(), Decimal128::from_str("1.5").unwrap());
+        assert_eq!(large.normalize(), large);
+        assert_eq!(
+            Decimal128::from_str("-0.00").unwrap().normalize(),
+            Decimal128::from_str("0").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bigint_bytes() {
+        let b = BigInt::from_i128(-(1i128 << 100));
+        let (negative, bytes) = b.to_bytes_be();
+        assert!(negative);
+        assert_eq!(BigInt::from_bytes_be(negative, &bytes), b);
+        assert_eq!(b.to_i64(), None);
+        assert_eq!(BigInt::from_i64(7).to_i64(), Some(7));
     }
 
     #[test]
@@ -686,6 +988,96 @@ mod tests {
         let iso = date.to_iso8601();
         let parsed = Date::from_iso8601(&iso).unwrap();
         assert_eq!(date.utc.timestamp(), parsed.utc.timestamp());
+
+        // Fractional seconds are kept, in 3, 6 or 9 digits
+        let text = "2025-01-10T12:00:00.250Z";
+        let date = Date::from_iso8601(text).unwrap();
+        assert_eq!(date.utc.timestamp_subsec_millis(), 250);
+        assert_eq!(date.to_iso8601(), text);
+        let value = crate::parse(text).unwrap();
+        assert_eq!(crate::to_string(&value).unwrap(), text);
+        assert_eq!(crate::parse(&crate::to_string(&value).unwrap()).unwrap(), value);
+        let nanos = "2025-01-10T12:00:00.000000001Z";
+        assert_eq!(Date::from_iso8601(nanos).unwrap().to_iso8601(), nanos);
+        assert_eq!(
+            Date::from_iso8601("2025-01-10T12:00:00.5Z").unwrap().to_iso8601(),
+            "2025-01-10T12:00:00.500Z"
+        );
+    }
+
+    #[test]
+    fn test_duration_parsing() {
+        let d = Duration::from_iso8601("P1DT2H3M4.5S").unwrap();
+        assert_eq!(d.total_nanos(), ((26 * 60 + 3) * 60 + 4) * 1_000_000_000 + 500_000_000);
+        assert_eq!(d.to_iso8601(), "P1DT2H3M4.5S");
+
+        // Negative durations are written with a leading `-` and read back
+        let negative = Duration::from_minutes(-90);
+        assert_eq!(negative.to_iso8601(), "-PT1H30M");
+        assert_eq!(Duration::from_iso8601("-PT1H30M").unwrap(), negative);
+        assert_eq!(Duration::from_iso8601("-PT0.5S").unwrap().total_nanos(), -500_000_000);
+
+        // Fractional seconds round to the nearest nanosecond
+        let d = Duration::from_iso8601("PT1.000000007S").unwrap();
+        assert_eq!(d.total_nanos(), 1_000_000_007);
+
+        // A designator needs at least one component
+        for empty in ["P", "PT", "-P", "-PT", "P1DT", "-"] {
+            assert!(Duration::from_iso8601(empty).is_err(), "{}", empty);
+        }
+    }
+
+    #[test]
+    fn test_instant_before_epoch() {
+        let instant = Instant::from_nanos(-1);
+        assert_eq!(instant.to_iso8601(), "1969-12-31T23:59:59.999999999Z");
+        assert_eq!(instant.to_datetime().timestamp_subsec_nanos(), 999_999_999);
+
+        // Whole seconds before the epoch have no fraction at all
+        let instant = Instant::from_nanos(-86_400 * 1_000_000_000);
+        assert_eq!(instant.to_iso8601(), "1969-12-31T00:00:00Z");
+
+        let text = "1950-06-15T08:30:00.25Z";
+        let instant = Instant::from_iso8601(text).unwrap();
+        assert!(instant.nanoseconds < 0);
+        assert_eq!(instant.to_iso8601(), text);
+        assert_eq!(instant.to_datetime().to_rfc3339(), "1950-06-15T08:30:00.250+00:00");
+    }
+
+    #[test]
+    fn test_serde_json() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Payment {
+            id: BigInt,
+            amount: Decimal128,
+            at: Instant,
+            window: Duration,
+        }
+
+        let payment = Payment {
+            id: BigInt::from_str("12345678901234567890").unwrap(),
+            amount: Decimal128::from_str("19.99").unwrap(),
+            at: Instant::from_iso8601("2025-01-10T12:00:00.5Z").unwrap(),
+            window: Duration::from_iso8601("PT30S").unwrap(),
+        };
+        let json = serde_json::to_string(&payment).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"id":"12345678901234567890n","amount":"19.99m","#,
+                r#""at":"2025-01-10T12:00:00.5Z","window":"PT30S"}"#
+            )
+        );
+        assert_eq!(serde_json::from_str::<Payment>(&json).unwrap(), payment);
+
+        // Plain JSON numbers are accepted where they fit
+        let payment: Payment = serde_json::from_str(
+            r#"{"id":42,"amount":1.5,"at":"2025-01-10T12:00:00Z","window":"PT1H"}"#,
+        )
+        .unwrap();
+        assert_eq!((payment.id.to_i64(), payment.amount.to_f64()), (Some(42), 1.5));
+        assert!(serde_json::from_str::<BigInt>("1.5").is_err());
+        assert!(serde_json::from_str::<Instant>(r#""yesterday""#).is_err());
     }
 
     #[test]
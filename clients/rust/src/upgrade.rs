@@ -0,0 +1,192 @@
+//! Heuristic type upgrading for documents imported from plain JSON
+//!
+//! JSON has no UUIDs, timestamps or big numbers, so datasets written as
+//! JSON carry them as strings. [`upgrade_types`] finds strings shaped like
+//! kJSON's extended literals and converts them to the matching variants:
+//!
+//! ```
+//! use kjson::upgrade::{upgrade_types, UpgradeOptions};
+//! use kjson::Value;
+//!
+//! let mut doc = kjson::parse(
+//!     r#"{
+//!         "id": "550e8400-e29b-41d4-a716-446655440000",
+//!         "created": "2025-01-10T12:00:00+02:00",
+//!         "balance": "12345678901234567890n",
+//!         "price": "19.99m",
+//!         "name": "shop"
+//!     }"#,
+//! )?;
+//! assert_eq!(upgrade_types(&mut doc, &UpgradeOptions::default()), 4);
+//! assert!(matches!(doc.pointer("/id"), Some(Value::Uuid(_))));
+//! assert_eq!(
+//!     kjson::to_string(doc.pointer("/created").unwrap())?,
+//!     "2025-01-10T10:00:00Z"
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! Only whole strings in the canonical shape are converted: hyphenated
+//! UUIDs, RFC 3339 timestamps with seconds and a zone, and integers or
+//! decimals with the `n` or `m` suffix. Object keys are never changed.
+//! [`from_json`](crate::from_json) can upgrade while converting a
+//! `serde_json::Value`.
+
+use crate::types::{BigInt, Decimal128, Instant};
+use crate::value::Value;
+use uuid::Uuid;
+
+/// Which string shapes [`upgrade_types`] converts; all are on by default
+#[derive(Debug, Clone)]
+pub struct UpgradeOptions {
+    /// Hyphenated UUIDs such as `"550e8400-e29b-41d4-a716-446655440000"`
+    pub uuids: bool,
+    /// Timestamps such as `"2025-01-10T12:00:00Z"`, as instants in UTC
+    pub instants: bool,
+    /// Integers with an `n` suffix such as `"12345678901234567890n"`
+    pub bigints: bool,
+    /// Decimals with an `m` suffix such as `"19.99m"`
+    pub decimals: bool,
+}
+
+impl Default for UpgradeOptions {
+    fn default() -> Self {
+        UpgradeOptions {
+            uuids: true,
+            instants: true,
+            bigints: true,
+            decimals: true,
+        }
+    }
+}
+
+/// Convert strings in `value` that look like extended literals, returning
+/// how many were converted
+pub fn upgrade_types(value: &mut Value, options: &UpgradeOptions) -> usize {
+    match value {
+        Value::String(s) => match upgrade(s, options) {
+            Some(upgraded) => {
+                *value = upgraded;
+                1
+            }
+            None => 0,
+        },
+        Value::Array(items) => items.iter_mut().map(|v| upgrade_types(v, options)).sum(),
+        Value::Object(map) => map.values_mut().map(|v| upgrade_types(v, options)).sum(),
+        _ => 0,
+    }
+}
+
+fn upgrade(s: &str, options: &UpgradeOptions) -> Option<Value> {
+    if options.uuids && is_uuid(s) {
+        return Uuid::parse_str(s).ok().map(Value::Uuid);
+    }
+    if options.instants && is_timestamp(s) {
+        return Instant::from_iso8601(s).ok().map(Value::Instant);
+    }
+    if let Some(digits) = s.strip_suffix('n') {
+        if options.bigints && is_integer(digits) {
+            return BigInt::from_str(digits).ok().map(Value::BigInt);
+        }
+    }
+    if let Some(number) = s.strip_suffix('m') {
+        if options.decimals && is_decimal(number) {
+            return Decimal128::from_str(number).ok().map(Value::Decimal128);
+        }
+    }
+    None
+}
+
+fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        })
+}
+
+/// `YYYY-MM-DDTHH:MM:SS`, optional fraction, then `Z` or `±HH:MM`
+fn is_timestamp(s: &str) -> bool {
+    let b = s.as_bytes();
+    let shape = |text: &[u8], pattern: &[u8]| {
+        text.len() == pattern.len()
+            && text.iter().zip(pattern).all(|(&c, &p)| match p {
+                b'd' => c.is_ascii_digit(),
+                b'+' => c == b'+' || c == b'-',
+                _ => c == p,
+            })
+    };
+    if b.len() < 20 || !shape(&b[..19], b"dddd-dd-ddTdd:dd:dd") {
+        return false;
+    }
+    let mut rest = &b[19..];
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        let digits = fraction.iter().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return false;
+        }
+        rest = &fraction[digits..];
+    }
+    rest == b"Z" || shape(rest, b"+dd:dd")
+}
+
+fn is_integer(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_decimal(s: &str) -> bool {
+    match s.split_once('.') {
+        Some((whole, fraction)) => {
+            let digits = !fraction.is_empty() && fraction.bytes().all(|b| b.is_ascii_digit());
+            is_integer(whole) && digits
+        }
+        None => is_integer(s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_upgrade_types() {
+        let mut doc = parse(
+            r#"{
+                "ids": ["550E8400-E29B-41D4-A716-446655440000", "550e8400e29b41d4a716446655440000"],
+                "times": ["2025-01-10T12:00:00.123456789Z", "2025-01-10T12:00:00", "2025-01-10"],
+                "numbers": ["-42n", "1e3n", "-0.5m", "5.m", "12"],
+                "550e8400-e29b-41d4-a716-446655440000": "plan"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(upgrade_types(&mut doc, &UpgradeOptions::default()), 4);
+        let expected = |text: &str| Some(parse(text).unwrap());
+        assert_eq!(
+            doc.pointer("/ids").cloned(),
+            expected("[550e8400-e29b-41d4-a716-446655440000, '550e8400e29b41d4a716446655440000']")
+        );
+        assert_eq!(
+            doc.pointer("/numbers").cloned(),
+            expected("[-42n, '1e3n', -0.5m, '5.m', '12']")
+        );
+        let instant = Instant::from_iso8601("2025-01-10T12:00:00.123456789Z").unwrap();
+        assert_eq!(doc.pointer("/times/0"), Some(&Value::Instant(instant)));
+        assert_eq!(doc.pointer("/times/1"), Some(&Value::string("2025-01-10T12:00:00")));
+        assert_eq!(
+            doc.pointer("/550e8400-e29b-41d4-a716-446655440000"),
+            Some(&Value::string("plan"))
+        );
+
+        let mut doc = parse(r#"["19.99m", "7n", "2025-01-10T12:00:00Z"]"#).unwrap();
+        let options = UpgradeOptions {
+            decimals: false,
+            instants: false,
+            ..Default::default()
+        };
+        assert_eq!(upgrade_types(&mut doc, &options), 1);
+        assert!(matches!(doc.pointer("/1"), Some(Value::BigInt(_))));
+        assert!(matches!(doc.pointer("/2"), Some(Value::String(_))));
+    }
+}
@@ -0,0 +1,261 @@
+//! utoipa OpenAPI support
+//!
+//! [`Value`] and the extended types implement [`ToSchema`], so handlers
+//! whose models contain them still get accurate OpenAPI documents. Schemas
+//! describe the JSON form, where extended types are strings; `Uuid`'s
+//! schema comes from utoipa's own `uuid` support.
+//!
+//! [`register_kjson_content`] offers every JSON request and response body of
+//! a document as `application/kjson` too, for services that accept both:
+//!
+//! ```
+//! use utoipa::openapi::{path::OperationBuilder, HttpMethod, OpenApiBuilder, PathItem, PathsBuilder};
+//! use utoipa::openapi::{ContentBuilder, ResponseBuilder};
+//!
+//! let response = ResponseBuilder::new()
+//!     .description("The order")
+//!     .content("application/json", ContentBuilder::new().build())
+//!     .build();
+//! let operation = OperationBuilder::new().response("200", response).build();
+//! let mut openapi = OpenApiBuilder::new()
+//!     .paths(PathsBuilder::new().path("/orders/{id}", PathItem::new(HttpMethod::Get, operation)))
+//!     .build();
+//!
+//! kjson::utoipa::register_kjson_content(&mut openapi);
+//! ```
+
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::Value;
+use std::borrow::Cow;
+use utoipa::openapi::path::Operation;
+use utoipa::openapi::schema::{KnownFormat, ObjectBuilder, SchemaFormat, SchemaType, Type};
+use utoipa::openapi::{Content, OpenApi, RefOr, Schema};
+use utoipa::{PartialSchema, ToSchema};
+
+pub use crate::media_type::CONTENT_TYPE;
+
+/// A string schema with the given details
+fn string(description: &str, format: Option<KnownFormat>, pattern: Option<&str>) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .format(format.map(SchemaFormat::KnownFormat))
+        .pattern(pattern)
+        .description(Some(description))
+        .into()
+}
+
+impl PartialSchema for BigInt {
+    fn schema() -> RefOr<Schema> {
+        string(
+            "Arbitrary-precision integer with an `n` suffix",
+            None,
+            Some("^-?[0-9]+n$"),
+        )
+    }
+}
+
+impl ToSchema for BigInt {
+    fn name() -> Cow<'static, str> {
+        "BigInt".into()
+    }
+}
+
+impl PartialSchema for Decimal128 {
+    fn schema() -> RefOr<Schema> {
+        string(
+            "Decimal number with an `m` suffix",
+            None,
+            Some("^-?[0-9]+(\\.[0-9]+)?m$"),
+        )
+    }
+}
+
+impl ToSchema for Decimal128 {
+    fn name() -> Cow<'static, str> {
+        "Decimal128".into()
+    }
+}
+
+impl PartialSchema for Instant {
+    fn schema() -> RefOr<Schema> {
+        string(
+            "UTC timestamp with nanosecond precision",
+            Some(KnownFormat::DateTime),
+            None,
+        )
+    }
+}
+
+impl ToSchema for Instant {
+    fn name() -> Cow<'static, str> {
+        "Instant".into()
+    }
+}
+
+impl PartialSchema for Duration {
+    fn schema() -> RefOr<Schema> {
+        string("ISO 8601 duration", Some(KnownFormat::Duration), None)
+    }
+}
+
+impl ToSchema for Duration {
+    fn name() -> Cow<'static, str> {
+        "Duration".into()
+    }
+}
+
+impl PartialSchema for Value {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(SchemaType::AnyValue)
+            .description(Some("Any kJSON value"))
+            .into()
+    }
+}
+
+impl ToSchema for Value {
+    fn name() -> Cow<'static, str> {
+        "Value".into()
+    }
+}
+
+/// Add an `application/kjson` entry, with the same schema, next to every
+/// `application/json` request and response body in `openapi`.
+///
+/// Bodies that already list `application/kjson` are left alone.
+pub fn register_kjson_content(openapi: &mut OpenApi) {
+    for item in openapi.paths.paths.values_mut() {
+        for operation in [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.options,
+            &mut item.head,
+            &mut item.patch,
+            &mut item.trace,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            register_operation(operation);
+        }
+    }
+    if let Some(components) = &mut openapi.components {
+        for response in components.responses.values_mut() {
+            if let RefOr::T(response) = response {
+                if let Some(content) = kjson_content(&response.content) {
+                    response.content.insert(CONTENT_TYPE.to_string(), content);
+                }
+            }
+        }
+    }
+}
+
+fn register_operation(operation: &mut Operation) {
+    if let Some(body) = &mut operation.request_body {
+        if let Some(content) = kjson_content(&body.content) {
+            body.content.insert(CONTENT_TYPE.to_string(), content);
+        }
+    }
+    for response in operation.responses.responses.values_mut() {
+        if let RefOr::T(response) = response {
+            if let Some(content) = kjson_content(&response.content) {
+                response.content.insert(CONTENT_TYPE.to_string(), content);
+            }
+        }
+    }
+}
+
+/// The content to add under `application/kjson`, if any
+fn kjson_content<'a>(
+    content: impl IntoIterator<Item = (&'a String, &'a Content)>,
+) -> Option<Content> {
+    let mut json = None;
+    for (content_type, entry) in content {
+        if content_type == CONTENT_TYPE {
+            return None;
+        }
+        if content_type == "application/json" {
+            json = Some(entry.clone());
+        }
+    }
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utoipa::openapi::path::{OperationBuilder, PathItem};
+    use utoipa::openapi::request_body::RequestBodyBuilder;
+    use utoipa::openapi::{
+        ContentBuilder, HttpMethod, OpenApiBuilder, PathsBuilder, ResponseBuilder,
+    };
+
+    #[allow(dead_code)]
+    #[derive(ToSchema)]
+    struct Order {
+        id: BigInt,
+        total: Decimal128,
+        placed: Instant,
+        ttl: Option<Duration>,
+        extra: Value,
+        key: crate::Uuid,
+    }
+
+    #[test]
+    fn test_schemas() {
+        let schema = serde_json::to_value(Order::schema()).unwrap();
+        let properties = &schema["properties"];
+        assert_eq!(properties["id"]["$ref"], "#/components/schemas/BigInt");
+        assert_eq!(properties["key"]["format"], "uuid");
+        // utoipa's derive writes any field named `Value` as an inline any-value schema
+        assert_eq!(properties["extra"], serde_json::json!({}));
+
+        let schema = |schema: RefOr<Schema>| serde_json::to_value(schema).unwrap();
+        assert_eq!(schema(BigInt::schema())["pattern"], "^-?[0-9]+n$");
+        assert_eq!(schema(Decimal128::schema())["type"], "string");
+        assert_eq!(schema(Instant::schema())["format"], "date-time");
+        assert_eq!(schema(Duration::schema())["format"], "duration");
+        assert_eq!(schema(Value::schema())["description"], "Any kJSON value");
+        assert_eq!(BigInt::name(), "BigInt");
+    }
+
+    #[test]
+    fn test_register_kjson_content() {
+        let json = || ContentBuilder::new().schema(Some(Order::schema())).build();
+        let operation = OperationBuilder::new()
+            .request_body(Some(
+                RequestBodyBuilder::new()
+                    .content("application/json", json())
+                    .build(),
+            ))
+            .response(
+                "200",
+                ResponseBuilder::new()
+                    .description("ok")
+                    .content("application/json", json())
+                    .content(CONTENT_TYPE, ContentBuilder::new().build())
+                    .build(),
+            )
+            .response("204", ResponseBuilder::new().description("empty").build())
+            .build();
+        let mut openapi = OpenApiBuilder::new()
+            .paths(PathsBuilder::new().path("/orders", PathItem::new(HttpMethod::Post, operation)))
+            .build();
+        register_kjson_content(&mut openapi);
+
+        let operation = openapi.paths.paths["/orders"].post.as_ref().unwrap();
+        let body = &operation.request_body.as_ref().unwrap().content;
+        assert!(body[CONTENT_TYPE] == body["application/json"]);
+        let RefOr::T(ok) = &operation.responses.responses["200"] else {
+            panic!("expected an inline response");
+        };
+        // An existing entry is kept as it was
+        assert!(ok.content[CONTENT_TYPE] == ContentBuilder::new().build());
+        let RefOr::T(empty) = &operation.responses.responses["204"] else {
+            panic!("expected an inline response");
+        };
+        assert!(empty.content.is_empty());
+    }
+}
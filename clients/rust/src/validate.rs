@@ -0,0 +1,623 @@
+//! Syntax checking without building values
+//!
+//! [`validate`] answers "would this parse?" without allocating a [`Value`]
+//! tree, for request validation at gateways and other hot paths where the
+//! document is only passed on:
+//!
+//! ```
+//! use kjson::validate;
+//!
+//! assert!(validate("{ id: 123n, tags: ['a', 'b',], at: 2025-01-01T00:00:00Z }").is_ok());
+//!
+//! let diagnostics = validate("{ a: [1, 2 }").unwrap_err();
+//! assert_eq!(diagnostics[0].message, "Expected ',' or ']'");
+//! assert_eq!(diagnostics[0].span.start, 11);
+//! ```
+//!
+//! By default unquoted dates, durations, `n`/`m` numbers and the base64 of
+//! binary literals are only checked for their shape, so `2025-13-45T00:00:00Z` passes. A [`Validator`] with
+//! [`extended_literals`](Validator::extended_literals) checks that they
+//! convert, and reports every malformed literal rather than the first:
+//!
+//! ```
+//! use kjson::validate::Validator;
+//!
+//! let validator = Validator::new().extended_literals(true);
+//! let diagnostics = validator.validate("[2025-13-45T00:00:00Z, 1.5n, PT1H]").unwrap_err();
+//! assert_eq!(diagnostics.len(), 2);
+//! ```
+//!
+//! Validation stops at the first syntax error, so there is at most one.
+//!
+//! [`Value`]: crate::Value
+
+use crate::parser::BASE64;
+use crate::search;
+use crate::span::Span;
+use crate::types::{BigInt, Date, Decimal128, Duration};
+use base64::Engine;
+use std::fmt;
+use uuid::Uuid;
+
+/// A problem found by [`validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Where it is in the input
+    pub span: Span,
+    /// What is wrong, worded as the parser's error
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}: {}", self.span, self.message)
+    }
+}
+
+/// Configured syntax check
+#[derive(Debug, Clone, Default)]
+pub struct Validator {
+    extended_literals: bool,
+}
+
+impl Validator {
+    /// A validator that checks syntax only
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also check that extended literals convert: dates are real dates,
+    /// durations are well-formed and `n` numbers are integers
+    pub fn extended_literals(mut self, check: bool) -> Self {
+        self.extended_literals = check;
+        self
+    }
+
+    /// Check `input`, returning everything wrong with it
+    pub fn validate(&self, input: &str) -> Result<(), Vec<Diagnostic>> {
+        let mut checker = Checker {
+            input,
+            bytes: input.as_bytes(),
+            position: 0,
+            extended_literals: self.extended_literals,
+            diagnostics: Vec::new(),
+        };
+        if let Err(diagnostic) = checker.document() {
+            checker.diagnostics.push(diagnostic);
+        }
+        if checker.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(checker.diagnostics)
+        }
+    }
+}
+
+/// Check that `input` is a syntactically valid kJSON document
+pub fn validate(input: &str) -> Result<(), Vec<Diagnostic>> {
+    Validator::new().validate(input)
+}
+
+/// An open array or object
+#[derive(Clone, Copy, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+struct Checker<'a> {
+    input: &'a str,
+    bytes: &'a [u8],
+    position: usize,
+    extended_literals: bool,
+    /// Malformed literals, which do not stop the check
+    diagnostics: Vec<Diagnostic>,
+}
+
+type Check = std::result::Result<(), Diagnostic>;
+
+impl Checker<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    /// An error covering the character at the current position
+    fn error(&self, message: &str) -> Diagnostic {
+        let len = self.input[self.position..]
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+        Diagnostic {
+            span: Span::new(self.position, self.position + len),
+            message: message.to_string(),
+        }
+    }
+
+    fn eof(&self) -> Diagnostic {
+        self.error("Unexpected end of input")
+    }
+
+    /// Skip whitespace and comments, as the parser does
+    fn skip_trivia(&mut self) {
+        while let Some(b) = self.peek() {
+            match b {
+                b' ' | b'\t' | b'\n' | b'\r' => {
+                    self.position += search::whitespace_len(&self.bytes[self.position..])
+                }
+                b'/' => match self.bytes.get(self.position + 1) {
+                    Some(b'/') => {
+                        self.position = match search::find(b'\n', &self.bytes[self.position..]) {
+                            Some(i) => self.position + i + 1,
+                            None => self.bytes.len(),
+                        };
+                    }
+                    Some(b'*') => {
+                        self.position =
+                            match search::find_comment_end(&self.bytes[self.position + 2..]) {
+                                Some(i) => self.position + 2 + i + 2,
+                                None => self.bytes.len(),
+                            };
+                    }
+                    _ => return,
+                },
+                _ => return,
+            }
+        }
+    }
+
+    /// The whole input: one value and nothing after it but trivia. Nesting
+    /// is tracked on a stack, so deep input cannot overflow the call stack.
+    fn document(&mut self) -> Check {
+        let mut stack = Vec::new();
+        'value: loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(b'[') => {
+                    self.position += 1;
+                    self.skip_trivia();
+                    if self.peek() == Some(b']') {
+                        self.position += 1;
+                    } else {
+                        stack.push(Container::Array);
+                        continue 'value;
+                    }
+                }
+                Some(b'{') => {
+                    self.position += 1;
+                    self.skip_trivia();
+                    if self.peek() == Some(b'}') {
+                        self.position += 1;
+                    } else {
+                        stack.push(Container::Object);
+                        self.member_key()?;
+                        continue 'value;
+                    }
+                }
+                Some(_) => self.scalar()?,
+                None => return Err(self.eof()),
+            }
+
+            // After a complete value: close containers until one continues
+            loop {
+                self.skip_trivia();
+                let Some(&container) = stack.last() else {
+                    if self.position < self.bytes.len() {
+                        return Err(self.error("Unexpected characters after value"));
+                    }
+                    return Ok(());
+                };
+                let close = match container {
+                    Container::Array => b']',
+                    Container::Object => b'}',
+                };
+                match self.peek() {
+                    Some(b',') => {
+                        self.position += 1;
+                        self.skip_trivia();
+                        // Allow trailing comma
+                        if self.peek() == Some(close) {
+                            self.position += 1;
+                            stack.pop();
+                            continue;
+                        }
+                        if container == Container::Object {
+                            self.member_key()?;
+                        }
+                        continue 'value;
+                    }
+                    Some(b) if b == close => {
+                        self.position += 1;
+                        stack.pop();
+                    }
+                    _ => {
+                        return Err(self.error(match container {
+                            Container::Array => "Expected ',' or ']'",
+                            Container::Object => "Expected ',' or '}'",
+                        }))
+                    }
+                }
+            }
+        }
+    }
+
+    /// A key and its colon
+    fn member_key(&mut self) -> Check {
+        self.skip_trivia();
+        match self.peek() {
+            Some(b'"' | b'\'' | b'`') => self.string()?,
+            _ => {
+                let rest = &self.input[self.position..];
+                match rest.chars().next() {
+                    Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+                        self.position += rest
+                            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                            .unwrap_or(rest.len());
+                    }
+                    Some(_) => return Err(self.error("Invalid unquoted key")),
+                    None => return Err(self.eof()),
+                }
+            }
+        }
+        self.skip_trivia();
+        if self.peek() != Some(b':') {
+            return Err(self.error("Expected ':' after key"));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    /// A quoted string, checking its escapes
+    fn string(&mut self) -> Check {
+        let quote = self.bytes[self.position];
+        self.position += 1;
+        loop {
+            let rest = &self.bytes[self.position..];
+            let Some(i) = search::find2(quote, b'\\', rest) else {
+                self.position = self.bytes.len();
+                return Err(self.error("Unterminated string"));
+            };
+            self.position += i + 1;
+            if rest[i] == quote {
+                return Ok(());
+            }
+            match self.peek() {
+                Some(b'"' | b'\'' | b'`' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                    self.position += 1;
+                }
+                Some(b'u') => {
+                    self.position += 1;
+                    let hex = self.bytes.get(self.position..self.position + 4);
+                    let code_point = hex
+                        .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                    match code_point {
+                        Some(code_point) if char::from_u32(code_point).is_some() => {
+                            self.position += 4;
+                        }
+                        Some(_) => return Err(self.error("Invalid unicode code point")),
+                        None => return Err(self.error("Invalid unicode escape")),
+                    }
+                }
+                Some(_) => {
+                    let c = self.input[self.position..]
+                        .chars()
+                        .next()
+                        .unwrap_or_default();
+                    return Err(self.error(&format!("Invalid escape sequence: \\{}", c)));
+                }
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    /// Any value other than an array or object
+    fn scalar(&mut self) -> Check {
+        let start = self.position;
+        match self.bytes[start] {
+            b'"' | b'\'' | b'`' => self.string(),
+            b'n' => {
+                if !self.bytes[start..].starts_with(b"null") {
+                    return Err(self.error("Invalid null value"));
+                }
+                self.position += 4;
+                Ok(())
+            }
+            b't' | b'f' => {
+                // A UUID may start with `f`
+                let end = self.literal_end();
+                if Uuid::parse_str(&self.input[start..end]).is_ok() {
+                    self.position = end;
+                } else if self.bytes[start..].starts_with(b"true") {
+                    self.position += 4;
+                } else if self.bytes[start..].starts_with(b"false") {
+                    self.position += 5;
+                } else {
+                    return Err(self.error("Invalid boolean value"));
+                }
+                Ok(())
+            }
+            b'-' | b'0'..=b'9' => {
+                if let Some(end) = self.number_end() {
+                    // The parser stops a number at the first character that
+                    // cannot continue it; anything but a delimiter there
+                    // means a date or UUID, or an error after the number
+                    if matches!(
+                        self.bytes.get(end),
+                        None | Some(b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}' | b'/')
+                    ) || !self.is_literal(start, self.literal_end())
+                    {
+                        self.check_number(start, end);
+                        self.position = end;
+                        return Ok(());
+                    }
+                }
+                self.literal()
+            }
+            b'b' if self.bytes[start..].starts_with(b"b64")
+                && matches!(self.bytes.get(start + 3), Some(b'\'' | b'"')) =>
+            {
+                self.binary()
+            }
+            _ => self.literal(),
+        }
+    }
+
+    /// A `b64'...'` binary literal
+    fn binary(&mut self) -> Check {
+        let start = self.position;
+        let body = start + 4;
+        let Some(len) = search::find(self.bytes[start + 3], &self.bytes[body..]) else {
+            self.position = self.bytes.len();
+            return Err(self.error("Unterminated binary literal"));
+        };
+        self.position = body + len + 1;
+        if self.extended_literals && BASE64.decode(&self.bytes[body..body + len]).is_err() {
+            self.diagnostics.push(Diagnostic {
+                span: Span::new(start, self.position),
+                message: "Invalid base64 in binary literal".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Where the unquoted literal at the current position ends
+    fn literal_end(&self) -> usize {
+        self.bytes[self.position..]
+            .iter()
+            .position(|b| matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b',' | b']' | b'}'))
+            .map_or(self.bytes.len(), |i| self.position + i)
+    }
+
+    /// Whether `start..end` is shaped like a UUID, date or duration
+    fn is_literal(&self, start: usize, end: usize) -> bool {
+        let literal = &self.input[start..end];
+        Uuid::parse_str(literal).is_ok() || is_date_shaped(literal) || is_duration_shaped(literal)
+    }
+
+    /// An unquoted UUID, date or duration
+    fn literal(&mut self) -> Check {
+        let start = self.position;
+        let end = self.literal_end();
+        if start == end {
+            return Err(self.error("Expected value"));
+        }
+        let literal = &self.input[start..end];
+        if !self.is_literal(start, end) {
+            return Err(Diagnostic {
+                span: Span::new(start, end),
+                message: format!("Invalid literal: {}", literal),
+            });
+        }
+        if self.extended_literals {
+            let converts = if is_date_shaped(literal) {
+                Date::from_iso8601(literal).is_ok()
+            } else if is_duration_shaped(literal) {
+                Duration::from_iso8601(literal).is_ok()
+            } else {
+                true
+            };
+            if !converts {
+                self.diagnostics.push(Diagnostic {
+                    span: Span::new(start, end),
+                    message: format!("Invalid literal: {}", literal),
+                });
+            }
+        }
+        self.position = end;
+        Ok(())
+    }
+
+    /// End of the number at the current position, with any `n` or `m`
+    /// suffix, or `None` if there is no number there
+    fn number_end(&self) -> Option<usize> {
+        let bytes = self.bytes;
+        let digits = |from: usize| from + search::digits_len(&bytes[from..]);
+        let mut end = self.position;
+        if bytes.get(end) == Some(&b'-') {
+            end += 1;
+        }
+        let int_start = end;
+        end = if bytes.get(end) == Some(&b'0') {
+            end + 1
+        } else {
+            digits(end)
+        };
+        let mut mantissa = end > int_start;
+        if bytes.get(end) == Some(&b'.') {
+            let frac_end = digits(end + 1);
+            if frac_end == end + 1 {
+                return None;
+            }
+            end = frac_end;
+            mantissa = true;
+        }
+        if !mantissa {
+            return None;
+        }
+        if matches!(bytes.get(end), Some(b'e' | b'E')) {
+            end += 1;
+            if matches!(bytes.get(end), Some(b'+' | b'-')) {
+                end += 1;
+            }
+            let exp_end = digits(end);
+            if exp_end == end {
+                return None;
+            }
+            end = exp_end;
+        }
+        if matches!(bytes.get(end), Some(b'n' | b'm')) {
+            end += 1;
+        }
+        Some(end)
+    }
+
+    /// Record a BigInt or Decimal128 that does not convert
+    fn check_number(&mut self, start: usize, end: usize) {
+        if !self.extended_literals {
+            return;
+        }
+        let number = &self.input[start..end];
+        let converts = match self.bytes[end - 1] {
+            b'n' => BigInt::from_str(number).is_ok(),
+            b'm' => Decimal128::from_str(number).is_ok(),
+            _ => true,
+        };
+        if !converts {
+            self.diagnostics.push(Diagnostic {
+                span: Span::new(start, end),
+                message: format!("Invalid number: {}", number),
+            });
+        }
+    }
+}
+
+/// `YYYY-MM-DDT` followed by time and offset characters
+fn is_date_shaped(s: &str) -> bool {
+    let b = s.as_bytes();
+    b.len() > 11
+        && b[..4].iter().all(u8::is_ascii_digit)
+        && b[4] == b'-'
+        && b[5..7].iter().all(u8::is_ascii_digit)
+        && b[7] == b'-'
+        && b[8..10].iter().all(u8::is_ascii_digit)
+        && matches!(b[10], b'T' | b't')
+        && b[11..]
+            .iter()
+            .all(|b| b.is_ascii_digit() || matches!(b, b':' | b'.' | b'+' | b'-' | b'Z' | b'z'))
+}
+
+/// `P`, optionally negated, followed by duration designators
+fn is_duration_shaped(s: &str) -> bool {
+    let s = s.strip_prefix('-').unwrap_or(s);
+    s.len() > 1
+        && s.starts_with('P')
+        && s[1..]
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'D' | b'T' | b'H' | b'M' | b'S' | b'.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_agrees_with_parse() {
+        let inputs = [
+            "null",
+            "  true // trailing\n",
+            "[1, -2.5e3, 0.1m, -7n, 'a\\'b', \"\\u00e9\", `x`, [], {},]",
+            "{ a: { 'b c': [null, false] }, $d_1: fade0000-0000-0000-0000-000000000000, }",
+            "/* lead */ { at: 2025-01-01T09:30:00+02:00, ttl: -PT1H30M, }",
+            "[2025-01-01T00:00:00.123Z/* c */, 1/* c */]",
+            "{ b64: b64'AQID', c: b64\"\" }",
+            "b64'AQID",
+            "b64",
+            "ééé",
+            "",
+            "[1,,]",
+            "[1 2]",
+            "{ a 1 }",
+            "{ 1: 2 }",
+            "{ a: 1 } x",
+            "'open",
+            "'\\q'",
+            "'\\u12'",
+            "'\\ud800'",
+            "nul",
+            "tru",
+            "0123",
+            "1.",
+            "1e",
+            "-",
+            "[1/2]",
+            "{ a: 1",
+            "[",
+            "/* open",
+        ];
+        for input in inputs {
+            assert_eq!(
+                validate(input).is_ok(),
+                parse(input).is_ok(),
+                "{:?}: {:?}",
+                input,
+                validate(input)
+            );
+        }
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let diagnostic = |input: &str| validate(input).unwrap_err().remove(0);
+        assert_eq!(
+            diagnostic("[1, 2 3]"),
+            Diagnostic {
+                span: Span::new(6, 7),
+                message: "Expected ',' or ']'".to_string()
+            }
+        );
+        assert_eq!(diagnostic("{ a: 'x").message, "Unterminated string");
+        assert_eq!(diagnostic("{ a: 'x").span, Span::new(7, 7));
+        assert_eq!(diagnostic("[hello]").span, Span::new(1, 6));
+        assert_eq!(diagnostic("[hello]").message, "Invalid literal: hello");
+        assert_eq!(diagnostic("{ a: é }").span, Span::new(5, 7));
+        assert_eq!(diagnostic("[1, ").message, "Unexpected end of input");
+        assert_eq!(
+            diagnostic("[1, ").to_string(),
+            "at 4..4: Unexpected end of input"
+        );
+    }
+
+    #[test]
+    fn test_extended_literals() {
+        let input = "[2025-02-30T00:00:00Z, 1.5n, P1X, 1.5m, PT1H, 12n]";
+        // P1X is not duration-shaped, so it is a syntax error either way
+        assert!(validate(input).is_err());
+
+        let input = "[2025-02-30T00:00:00Z, 1.5n, PTS, 1.5m, PT1H, 12n]";
+        assert!(validate(input).is_ok());
+        assert!(parse(input).is_err());
+        let diagnostics = Validator::new()
+            .extended_literals(true)
+            .validate(input)
+            .unwrap_err();
+        let spans: Vec<&str> = diagnostics.iter().map(|d| d.span.slice(input)).collect();
+        assert_eq!(spans, ["2025-02-30T00:00:00Z", "1.5n", "PTS"]);
+
+        let input = "[b64'AQID', b64'A!']";
+        assert!(validate(input).is_ok());
+        let diagnostics = Validator::new()
+            .extended_literals(true)
+            .validate(input)
+            .unwrap_err();
+        assert_eq!(diagnostics[0].span.slice(input), "b64'A!'");
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        let input = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(validate(&input).is_ok());
+        assert!(validate(&input[1..]).is_err());
+    }
+}
@@ -1,11 +1,45 @@
 use crate::error::{Error, Result};
-use crate::types::{BigInt, Date, Decimal128};
+use crate::types::{BigInt, Date, Decimal128, Duration, Instant};
+use crate::upgrade::{upgrade_types, UpgradeOptions};
+use crate::warnings::{self, Warning};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use uuid::Uuid;
 
+pub use crate::map::Map;
+pub use crate::string::Str;
+
+/// Entries of `map` in the order they are written: sorted by key, or in
+/// insertion order with the `preserve_order` feature
+pub(crate) fn ordered_entries(map: &Map) -> Vec<(&Str, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    if !cfg!(feature = "preserve_order") {
+        entries.sort_by_key(|(k, _)| k.as_str());
+    }
+    entries
+}
+
+/// `s` as a [`Str`], moving it where the types agree
+pub(crate) fn into_str(s: impl Into<Str>) -> Str {
+    s.into()
+}
+
+/// `s` as a `String`, moving it where the types agree
+pub(crate) fn into_string(s: impl Into<String>) -> String {
+    s.into()
+}
+
 /// kJSON Value enum representing all possible kJSON types
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Number literals without a fraction or exponent that fit in an `i64` are
+/// [`Value::Integer`], larger ones that fit in a `u64` [`Value::UInteger`],
+/// and other numbers [`Value::Number`]. They compare equal when they hold
+/// the same number, so `Integer(1) == Number(1.0)`.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "diesel",
+    derive(diesel::expression::AsExpression, diesel::deserialize::FromSqlRow),
+    diesel(sql_type = crate::diesel::Kjson)
+)]
 pub enum Value {
     /// Null value
     Null,
@@ -13,12 +47,17 @@ pub enum Value {
     Bool(bool),
     /// Number value (f64)
     Number(f64),
+    /// Integer number value, written without a fraction or exponent
+    Integer(i64),
+    /// Integer number value above `i64::MAX`, written without a fraction or
+    /// exponent
+    UInteger(u64),
     /// String value
-    String(String),
+    String(Str),
     /// Array of values
     Array(Vec<Value>),
     /// Object (key-value pairs)
-    Object(HashMap<String, Value>),
+    Object(Map),
     /// BigInt value
     BigInt(BigInt),
     /// Decimal128 value
@@ -27,9 +66,20 @@ pub enum Value {
     Uuid(Uuid),
     /// Date value
     Date(Date),
+    /// Instant value (nanosecond-precision UTC timestamp)
+    Instant(Instant),
+    /// Duration value
+    Duration(Duration),
+    /// Binary data, written as a `b64'...'` literal
+    Binary(Vec<u8>),
 }
 
 impl Value {
+    /// A string value
+    pub fn string(s: impl Into<Str>) -> Value {
+        Value::String(s.into())
+    }
+
     /// Check if value is null
     pub fn is_null(&self) -> bool {
         matches!(self, Value::Null)
@@ -43,10 +93,32 @@ impl Value {
         }
     }
 
-    /// Try to get as number
+    /// Try to get as number, which may round an integer above 2^53
     pub fn as_f64(&self) -> Option<f64> {
         match self {
             Value::Number(n) => Some(*n),
+            Value::Integer(n) => Some(*n as f64),
+            Value::UInteger(n) => Some(*n as f64),
+            _ => None,
+        }
+    }
+
+    /// Try to get as an integer, from an integer or a whole number
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(n) => Some(*n),
+            Value::UInteger(n) => i64::try_from(*n).ok(),
+            Value::Number(n) => f64_to_i64(*n),
+            _ => None,
+        }
+    }
+
+    /// Try to get as a non-negative integer
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Integer(n) => u64::try_from(*n).ok(),
+            Value::UInteger(n) => Some(*n),
+            Value::Number(n) => f64_to_u64(*n),
             _ => None,
         }
     }
@@ -59,6 +131,14 @@ impl Value {
         }
     }
 
+    /// Try to get as binary data
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Binary(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
     /// Try to get as array
     pub fn as_array(&self) -> Option<&Vec<Value>> {
         match self {
@@ -68,7 +148,7 @@ impl Value {
     }
 
     /// Try to get as object
-    pub fn as_object(&self) -> Option<&HashMap<String, Value>> {
+    pub fn as_object(&self) -> Option<&Map> {
         match self {
             Value::Object(o) => Some(o),
             _ => None,
@@ -107,12 +187,28 @@ impl Value {
         }
     }
 
+    /// Try to get as Instant
+    pub fn as_instant(&self) -> Option<&Instant> {
+        match self {
+            Value::Instant(i) => Some(i),
+            _ => None,
+        }
+    }
+
+    /// Try to get as Duration
+    pub fn as_duration(&self) -> Option<&Duration> {
+        match self {
+            Value::Duration(d) => Some(d),
+            _ => None,
+        }
+    }
+
     /// Get the type name of this value
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::Null => "null",
             Value::Bool(_) => "boolean",
-            Value::Number(_) => "number",
+            Value::Number(_) | Value::Integer(_) | Value::UInteger(_) => "number",
             Value::String(_) => "string",
             Value::Array(_) => "array",
             Value::Object(_) => "object",
@@ -120,10 +216,414 @@ impl Value {
             Value::Decimal128(_) => "decimal128",
             Value::Uuid(_) => "uuid",
             Value::Date(_) => "date",
+            Value::Instant(_) => "instant",
+            Value::Duration(_) => "duration",
+            Value::Binary(_) => "binary",
+        }
+    }
+
+    /// Look up a value by JSON Pointer (RFC 6901), e.g. `/items/0/id`
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        crate::pointer::lookup(self, pointer)
+    }
+}
+
+/// `n` as an `i64` if it is a whole number in range
+pub(crate) fn f64_to_i64(n: f64) -> Option<i64> {
+    // 2^63 is exact as an f64, unlike i64::MAX
+    (n.fract() == 0.0 && (-9.223_372_036_854_776e18..9.223_372_036_854_776e18).contains(&n))
+        .then_some(n as i64)
+}
+
+/// `n` as a `u64` if it is a whole number in range
+pub(crate) fn f64_to_u64(n: f64) -> Option<u64> {
+    // 2^64 is exact as an f64, unlike u64::MAX
+    (n.fract() == 0.0 && (0.0..1.844_674_407_370_955_2e19).contains(&n)).then_some(n as u64)
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Integer(a), Value::Number(b)) | (Value::Number(b), Value::Integer(a)) => {
+                f64_to_i64(*b) == Some(*a)
+            }
+            (Value::UInteger(a), Value::UInteger(b)) => a == b,
+            (Value::Integer(a), Value::UInteger(b)) | (Value::UInteger(b), Value::Integer(a)) => {
+                i128::from(*a) == i128::from(*b)
+            }
+            (Value::UInteger(a), Value::Number(b)) | (Value::Number(b), Value::UInteger(a)) => {
+                f64_to_u64(*b) == Some(*a)
+            }
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            (Value::BigInt(a), Value::BigInt(b)) => a == b,
+            (Value::Decimal128(a), Value::Decimal128(b)) => a == b,
+            (Value::Uuid(a), Value::Uuid(b)) => a == b,
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Instant(a), Value::Instant(b)) => a == b,
+            (Value::Duration(a), Value::Duration(b)) => a == b,
+            (Value::Binary(a), Value::Binary(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+static NULL: Value = Value::Null;
+
+/// `value["key"]` gives the member, or `Null` if it is missing or `value`
+/// is not an object
+impl std::ops::Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        match self {
+            Value::Object(map) => map.get(key).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+impl std::ops::Index<&String> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &String) -> &Value {
+        &self[key.as_str()]
+    }
+}
+
+/// `value[i]` gives the item, or `Null` if it is out of bounds or `value`
+/// is not an array
+impl std::ops::Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        match self {
+            Value::Array(items) => items.get(index).unwrap_or(&NULL),
+            _ => &NULL,
+        }
+    }
+}
+
+/// `value["key"] = ...` inserts a missing member, turning `Null` into an
+/// empty object first.
+///
+/// # Panics
+///
+/// If `value` is neither an object nor `Null`.
+impl std::ops::IndexMut<&str> for Value {
+    fn index_mut(&mut self, key: &str) -> &mut Value {
+        if let Value::Null = self {
+            *self = Value::Object(Map::new());
+        }
+        match self {
+            Value::Object(map) => map.entry(Str::from(key)).or_insert(Value::Null),
+            other => panic!("cannot index {} with key {:?}", other.type_name(), key),
+        }
+    }
+}
+
+impl std::ops::IndexMut<&String> for Value {
+    fn index_mut(&mut self, key: &String) -> &mut Value {
+        &mut self[key.as_str()]
+    }
+}
+
+/// `value[i] = ...` replaces an existing item.
+///
+/// # Panics
+///
+/// If `value` is not an array or `i` is out of bounds.
+impl std::ops::IndexMut<usize> for Value {
+    fn index_mut(&mut self, index: usize) -> &mut Value {
+        match self {
+            Value::Array(items) => {
+                let len = items.len();
+                items.get_mut(index).unwrap_or_else(|| {
+                    panic!("index {} out of bounds for array of length {}", index, len)
+                })
+            }
+            other => panic!("cannot index {} with {}", other.type_name(), index),
         }
     }
 }
 
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+/// Integers become `Integer`, `UInteger` above the range of `i64`, or
+/// `BigInt` beyond the range of `u64`
+macro_rules! from_integer {
+    ($($ty:ty),*) => {$(
+        impl From<$ty> for Value {
+            fn from(n: $ty) -> Value {
+                let n = n as i128;
+                match (i64::try_from(n), u64::try_from(n)) {
+                    (Ok(n), _) => Value::Integer(n),
+                    (_, Ok(n)) => Value::UInteger(n),
+                    _ => Value::BigInt(BigInt::from_i128(n)),
+                }
+            }
+        }
+    )*};
+}
+
+from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, i128);
+
+impl From<f32> for Value {
+    fn from(n: f32) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::string(s)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::string(s)
+    }
+}
+
+impl From<Str> for Value {
+    fn from(s: Str) -> Value {
+        Value::String(s)
+    }
+}
+
+impl From<std::borrow::Cow<'_, str>> for Value {
+    fn from(s: std::borrow::Cow<'_, str>) -> Value {
+        Value::string(s.as_ref())
+    }
+}
+
+macro_rules! from_variant {
+    ($($ty:ty => $variant:ident),*) => {$(
+        impl From<$ty> for Value {
+            fn from(v: $ty) -> Value {
+                Value::$variant(v)
+            }
+        }
+    )*};
+}
+
+from_variant!(
+    BigInt => BigInt,
+    Decimal128 => Decimal128,
+    Uuid => Uuid,
+    Date => Date,
+    Instant => Instant,
+    Duration => Duration
+);
+
+/// `None` becomes `Null`
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(v: Option<T>) -> Value {
+        v.map_or(Value::Null, Into::into)
+    }
+}
+
+/// An array of the converted items. Bytes become an array of numbers too;
+/// build `Value::Binary` for binary data.
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Value {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&[T]> for Value {
+    fn from(items: &[T]) -> Value {
+        items.iter().cloned().collect()
+    }
+}
+
+impl<K: Into<Str>, T: Into<Value>, S> From<std::collections::HashMap<K, T, S>> for Value {
+    fn from(map: std::collections::HashMap<K, T, S>) -> Value {
+        map.into_iter().collect()
+    }
+}
+
+impl<K: Into<Str>, T: Into<Value>> From<std::collections::BTreeMap<K, T>> for Value {
+    fn from(map: std::collections::BTreeMap<K, T>) -> Value {
+        map.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "preserve_order")]
+impl<K: Into<Str>, T: Into<Value>, S> From<indexmap::IndexMap<K, T, S>> for Value {
+    fn from(map: indexmap::IndexMap<K, T, S>) -> Value {
+        map.into_iter().collect()
+    }
+}
+
+/// Collect into an array
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(items: I) -> Value {
+        Value::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Collect key-value pairs into an object
+impl<K: Into<Str>, T: Into<Value>> FromIterator<(K, T)> for Value {
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(entries: I) -> Value {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+/// The error for converting `value` to a type it does not hold
+fn mismatch(expected: &str, value: &Value) -> Error {
+    Error::TypeMismatch {
+        expected: expected.to_string(),
+        actual: value.type_name().to_string(),
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<bool> {
+        value.as_bool().ok_or_else(|| mismatch("boolean", &value))
+    }
+}
+
+/// Integers convert from `Integer`, `UInteger`, whole `Number`s and
+/// `BigInt`; a value that does not fit is a [`Error::Conversion`]
+macro_rules! try_from_integer {
+    ($($ty:ty),*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = Error;
+
+            fn try_from(value: Value) -> Result<$ty> {
+                let n = match &value {
+                    Value::Integer(n) => Some(*n as i128),
+                    Value::UInteger(n) => Some(*n as i128),
+                    Value::Number(n) => f64_to_i64(*n)
+                        .map(i128::from)
+                        .or_else(|| f64_to_u64(*n).map(i128::from)),
+                    Value::BigInt(n) => n.to_i128(),
+                    _ => return Err(mismatch(stringify!($ty), &value)),
+                };
+                n.and_then(|n| <$ty>::try_from(n).ok()).ok_or_else(|| {
+                    let text = crate::to_string(&value).unwrap_or_default();
+                    Error::Conversion(format!("{} is not a valid {}", text, stringify!($ty)))
+                })
+            }
+        }
+    )*};
+}
+
+try_from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, i128);
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<f64> {
+        value.as_f64().ok_or_else(|| mismatch("number", &value))
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(mut value: Value) -> Result<String> {
+        match &mut value {
+            Value::String(s) => Ok(into_string(std::mem::take(s))),
+            _ => Err(mismatch("string", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(mut value: Value) -> Result<Vec<u8>> {
+        match &mut value {
+            Value::Binary(bytes) => Ok(std::mem::take(bytes)),
+            _ => Err(mismatch("binary", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = Error;
+
+    fn try_from(mut value: Value) -> Result<Vec<Value>> {
+        match &mut value {
+            Value::Array(items) => Ok(std::mem::take(items)),
+            _ => Err(mismatch("array", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Map {
+    type Error = Error;
+
+    fn try_from(mut value: Value) -> Result<Map> {
+        match &mut value {
+            Value::Object(map) => Ok(std::mem::take(map)),
+            _ => Err(mismatch("object", &value)),
+        }
+    }
+}
+
+/// `BigInt` also converts from `Integer` and `UInteger`
+impl TryFrom<Value> for BigInt {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<BigInt> {
+        match &value {
+            Value::BigInt(n) => Ok(n.clone()),
+            Value::Integer(n) => Ok(BigInt::from_i64(*n)),
+            Value::UInteger(n) => Ok(BigInt::from_i128(*n as i128)),
+            _ => Err(mismatch("bigint", &value)),
+        }
+    }
+}
+
+macro_rules! try_from_variant {
+    ($($ty:ty => $variant:ident, $expected:literal;)*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = Error;
+
+            fn try_from(value: Value) -> Result<$ty> {
+                match &value {
+                    Value::$variant(v) => Ok(v.clone()),
+                    _ => Err(mismatch($expected, &value)),
+                }
+            }
+        }
+    )*};
+}
+
+try_from_variant! {
+    Decimal128 => Decimal128, "decimal128";
+    Uuid => Uuid, "uuid";
+    Date => Date, "date";
+    Instant => Instant, "instant";
+    Duration => Duration, "duration";
+}
+
 /// Convert a serde-serializable value to a kJSON Value
 pub fn to_value<T>(value: T) -> Result<Value>
 where
@@ -136,31 +636,51 @@ where
     json_value_to_kjson_value(json_value)
 }
 
+/// Convert a `serde_json::Value` to a kJSON Value, converting strings that
+/// look like extended literals when `upgrade` is given (see
+/// [`upgrade_types`](crate::upgrade::upgrade_types))
+pub fn from_json(json: serde_json::Value, upgrade: Option<&UpgradeOptions>) -> Result<Value> {
+    let mut value = json_value_to_kjson_value(json)?;
+    if let Some(options) = upgrade {
+        upgrade_types(&mut value, options);
+    }
+    Ok(value)
+}
+
 /// Convert a kJSON Value to a serde-deserializable type
 pub fn from_value<T>(value: Value) -> Result<T>
 where
     T: for<'de> Deserialize<'de>,
 {
-    // This is a simplified implementation
-    // In a full implementation, we'd use a custom deserializer
-    let json_value = kjson_value_to_json_value(value)?;
-    serde_json::from_value(json_value)
-        .map_err(|e| Error::Custom(e.to_string()))
+    crate::de::from_value(value)
 }
 
+/// Largest integer an f64 holds exactly
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
 // Helper function to convert serde_json::Value to kJSON Value
 fn json_value_to_kjson_value(value: serde_json::Value) -> Result<Value> {
     match value {
         serde_json::Value::Null => Ok(Value::Null),
         serde_json::Value::Bool(b) => Ok(Value::Bool(b)),
         serde_json::Value::Number(n) => {
-            if let Some(f) = n.as_f64() {
+            if let Some(i) = n.as_i64() {
+                Ok(Value::Integer(i))
+            } else if let Some(u) = n.as_u64() {
+                Ok(Value::UInteger(u))
+            } else if let Some(f) = n.as_f64() {
+                if !n.is_f64() && format!("{:.0}", f) != n.to_string() {
+                    warnings::warn(|| Warning::PrecisionLoss {
+                        value: n.to_string(),
+                        target: "f64",
+                    });
+                }
                 Ok(Value::Number(f))
             } else {
                 Err(Error::InvalidNumber(n.to_string()))
             }
         }
-        serde_json::Value::String(s) => Ok(Value::String(s)),
+        serde_json::Value::String(s) => Ok(Value::string(s)),
         serde_json::Value::Array(arr) => {
             let mut result = Vec::new();
             for item in arr {
@@ -169,9 +689,9 @@ fn json_value_to_kjson_value(value: serde_json::Value) -> Result<Value> {
             Ok(Value::Array(result))
         }
         serde_json::Value::Object(obj) => {
-            let mut result = HashMap::new();
+            let mut result = Map::new();
             for (key, val) in obj {
-                result.insert(key, json_value_to_kjson_value(val)?);
+                result.insert(into_str(key), json_value_to_kjson_value(val)?);
             }
             Ok(Value::Object(result))
         }
@@ -179,23 +699,29 @@ fn json_value_to_kjson_value(value: serde_json::Value) -> Result<Value> {
 }
 
 // Helper function to convert kJSON Value to serde_json::Value
-fn kjson_value_to_json_value(value: Value) -> Result<serde_json::Value> {
-    match value {
+pub(crate) fn kjson_value_to_json_value(mut value: Value) -> Result<serde_json::Value> {
+    match &mut value {
         Value::Null => Ok(serde_json::Value::Null),
-        Value::Bool(b) => Ok(serde_json::Value::Bool(b)),
-        Value::Number(n) => Ok(serde_json::json!(n)),
-        Value::String(s) => Ok(serde_json::Value::String(s)),
+        Value::Bool(b) => Ok(serde_json::Value::Bool(*b)),
+        // Whole numbers become JSON integers so they deserialize into integer types
+        Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+            Ok(serde_json::json!(*n as i64))
+        }
+        Value::Number(n) => Ok(serde_json::json!(*n)),
+        Value::Integer(n) => Ok(serde_json::json!(*n)),
+        Value::UInteger(n) => Ok(serde_json::json!(*n)),
+        Value::String(s) => Ok(serde_json::Value::String(into_string(std::mem::take(s)))),
         Value::Array(arr) => {
             let mut result = Vec::new();
-            for item in arr {
+            for item in std::mem::take(arr) {
                 result.push(kjson_value_to_json_value(item)?);
             }
             Ok(serde_json::Value::Array(result))
         }
         Value::Object(obj) => {
             let mut result = serde_json::Map::new();
-            for (key, val) in obj {
-                result.insert(key, kjson_value_to_json_value(val)?);
+            for (key, val) in std::mem::take(obj) {
+                result.insert(into_string(key), kjson_value_to_json_value(val)?);
             }
             Ok(serde_json::Value::Object(result))
         }
@@ -204,6 +730,29 @@ fn kjson_value_to_json_value(value: Value) -> Result<serde_json::Value> {
         Value::Decimal128(d) => Ok(serde_json::Value::String(d.to_kjson_string())),
         Value::Uuid(u) => Ok(serde_json::Value::String(u.to_string())),
         Value::Date(d) => Ok(serde_json::Value::String(d.to_iso8601())),
+        Value::Instant(i) => Ok(serde_json::Value::String(i.to_iso8601())),
+        Value::Duration(d) => Ok(serde_json::Value::String(d.to_iso8601())),
+        // As serde_json writes bytes, so they deserialize into `Vec<u8>`
+        Value::Binary(bytes) => Ok(serde_json::Value::from(std::mem::take(bytes))),
+    }
+}
+
+// Nested values are freed from a work queue, not by recursion, so dropping
+// a pathologically deep document can't overflow the stack
+impl Drop for Value {
+    fn drop(&mut self) {
+        let mut queue = match self {
+            Value::Array(items) if !items.is_empty() => std::mem::take(items),
+            Value::Object(map) if !map.is_empty() => std::mem::take(map).into_values().collect(),
+            _ => return,
+        };
+        while let Some(mut value) = queue.pop() {
+            match &mut value {
+                Value::Array(items) => queue.append(items),
+                Value::Object(map) => queue.extend(std::mem::take(map).into_values()),
+                _ => {}
+            }
+        }
     }
 }
 
@@ -224,5 +773,164 @@ mod tests {
         let num_val = Value::Number(42.0);
         assert_eq!(num_val.as_f64(), Some(42.0));
         assert_eq!(num_val.type_name(), "number");
+
+        let int_val = Value::Integer(-42);
+        assert_eq!(int_val.as_i64(), Some(-42));
+        assert_eq!(int_val.as_u64(), None);
+        assert_eq!(int_val.type_name(), "number");
+        assert_eq!(int_val, Value::Number(-42.0));
+        assert_ne!(Value::Integer(i64::MAX), Value::Number(i64::MAX as f64));
+        assert_eq!(Value::Number(2.5).as_i64(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "small-strings")]
+    fn test_small_strings_inline() {
+        let value =
+            crate::parse("{ name: 'short', long: 'a string well over twenty-four bytes' }").unwrap();
+        let map = value.as_object().unwrap();
+        let (key, _) = map.get_key_value("name").unwrap();
+        assert!(!key.is_heap_allocated());
+        assert!(matches!(&map["name"], Value::String(s) if !s.is_heap_allocated()));
+        assert!(matches!(&map["long"], Value::String(s) if s.is_heap_allocated()));
+    }
+
+    #[test]
+    #[cfg(feature = "preserve_order")]
+    fn test_preserve_order() {
+        let text = "{zeta: 1, alpha: {c: 2, b: 3}, mid: [true]}";
+        let mut value = crate::parse(text).unwrap();
+        assert_eq!(crate::to_string(&value).unwrap(), text);
+        assert_eq!(value, crate::parse("{ mid: [true], alpha: { b: 3, c: 2 }, zeta: 1 }").unwrap());
+
+        let Value::Object(map) = &mut value else {
+            unreachable!()
+        };
+        map.remove("zeta");
+        map.insert("first", Value::Null);
+        assert_eq!(
+            crate::to_string(&value).unwrap(),
+            "{alpha: {c: 2, b: 3}, mid: [true], first: null}"
+        );
+        assert_eq!(
+            crate::to_canonical_string(&value).unwrap(),
+            "{\"alpha\":{\"b\":3,\"c\":2},\"first\":null,\"mid\":[true]}"
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_pointer() {
+        let value = crate::parse("{ items: [{ id: 7n }] }").unwrap();
+        assert_eq!(
+            value.pointer("/items/0/id"),
+            Some(&Value::BigInt(BigInt::from_i64(7)))
+        );
+        assert_eq!(value.pointer("/items/1"), None);
+    }
+
+    #[test]
+    fn test_index() {
+        let mut value = crate::parse("{ user: { tags: ['a', 'b'] }, n: 1 }").unwrap();
+        assert_eq!(value["user"]["tags"][1], Value::String("b".into()));
+        assert_eq!(value["user"]["missing"][0], Value::Null);
+        assert_eq!(value["n"]["x"], Value::Null);
+        assert_eq!(value["user"]["tags"][5], Value::Null);
+
+        value["user"]["tags"][0] = Value::Bool(true);
+        value["user"]["name"] = Value::String("kim".into());
+        value["extra"]["deep"] = Value::Integer(2);
+        assert_eq!(
+            value,
+            crate::parse("{ user: { tags: [true, 'b'], name: 'kim' }, n: 1, extra: { deep: 2 } }")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot index number with key \"x\"")]
+    fn test_index_mut_type_mismatch() {
+        let mut value = crate::parse("{ n: 1 }").unwrap();
+        value["n"]["x"] = Value::Null;
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 out of bounds for array of length 1")]
+    fn test_index_mut_out_of_bounds() {
+        let mut value = crate::parse("[1]").unwrap();
+        value[2] = Value::Null;
+    }
+
+    #[test]
+    fn test_from() {
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from(7u8), Value::Integer(7));
+        assert!(matches!(Value::from(u64::MAX), Value::UInteger(u64::MAX)));
+        assert_eq!(Value::from(i128::MAX), Value::BigInt(BigInt::from_i128(i128::MAX)));
+        assert_eq!(Value::from(1.5f32), Value::Number(1.5));
+        assert_eq!(Value::from("a"), Value::string("a"));
+        assert_eq!(Value::from(None::<i64>), Value::Null);
+        assert_eq!(Value::from(Uuid::nil()), Value::Uuid(Uuid::nil()));
+
+        let tags = Value::from(vec!["a", "b"]);
+        assert_eq!(tags, crate::parse("['a', 'b']").unwrap());
+        assert_eq!(Value::from(&[1, 2][..]), crate::parse("[1, 2]").unwrap());
+
+        let mut scores = std::collections::HashMap::new();
+        scores.insert("kim".to_string(), Some(BigInt::from_i64(3)));
+        scores.insert("lee".to_string(), None);
+        assert_eq!(
+            Value::from(scores),
+            crate::parse("{ kim: 3n, lee: null }").unwrap()
+        );
+
+        let squares: Value = (1..4).map(|n| n * n).collect();
+        assert_eq!(squares, crate::parse("[1, 4, 9]").unwrap());
+        let doc: Value = [("id", Value::from(1)), ("tags", tags)].into_iter().collect();
+        assert_eq!(doc, crate::parse("{ id: 1, tags: ['a', 'b'] }").unwrap());
+    }
+
+    #[test]
+    fn test_try_from() {
+        let doc = crate::parse(
+            "{ n: 42, big: 12345678901234567890n, f: 2.0, s: 'x', price: 9.99m,
+               id: a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11 }",
+        )
+        .unwrap();
+        let field = |key: &str| doc[key].clone();
+        assert_eq!(i64::try_from(field("n")).unwrap(), 42);
+        assert_eq!(u8::try_from(field("f")).unwrap(), 2);
+        assert_eq!(i128::try_from(field("big")).unwrap(), 12345678901234567890);
+        assert_eq!(BigInt::try_from(field("n")).unwrap(), BigInt::from_i64(42));
+        assert_eq!(String::try_from(field("s")).unwrap(), "x");
+        assert_eq!(f64::try_from(field("n")).unwrap(), 42.0);
+        assert_eq!(
+            Decimal128::try_from(field("price")).unwrap(),
+            Decimal128::from_str("9.99").unwrap()
+        );
+        assert!(Uuid::try_from(field("id")).is_ok());
+
+        let err = Uuid::try_from(field("s")).unwrap_err();
+        assert_eq!(err.to_string(), "Type conversion error: expected uuid, got string");
+        assert!(matches!(
+            Decimal128::try_from(field("n")),
+            Err(Error::TypeMismatch { expected, actual })
+                if expected == "decimal128" && actual == "number"
+        ));
+        assert!(matches!(i64::try_from(field("big")), Err(Error::Conversion(_))));
+        assert!(matches!(i64::try_from(Value::Number(1.5)), Err(Error::Conversion(_))));
+        assert!(matches!(bool::try_from(Value::Null), Err(Error::TypeMismatch { .. })));
+    }
+
+    #[test]
+    fn test_drop_deeply_nested() {
+        let mut value = Value::Null;
+        for i in 0..1_000_000 {
+            value = if i % 2 == 0 {
+                Value::Array(vec![value, Value::Number(i as f64)])
+            } else {
+                Value::Object(Map::from_iter([("next", value)]))
+            };
+        }
+        drop(value);
+    }
+}
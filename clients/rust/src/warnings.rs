@@ -0,0 +1,261 @@
+//! Non-fatal issues found while parsing and converting
+//!
+//! Some inputs are accepted with a loss that nothing reports: a repeated
+//! key silently replaces the earlier value, and a large integer becomes the
+//! nearest float. [`collect`] runs a closure and returns the [`Warning`]s
+//! raised inside it, so those losses can be logged or turned into errors:
+//!
+//! ```
+//! use kjson::warnings::{collect, Warning};
+//!
+//! let (value, warnings) = collect(|| kjson::parse("{ port: 80, port: 8080 }"));
+//! assert_eq!(value?, kjson::parse("{ port: 8080 }")?);
+//! assert_eq!(
+//!     warnings,
+//!     [Warning::DuplicateKey { key: "port".to_string(), position: 12 }]
+//! );
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! | Warning                       | Raised by                                                   |
+//! |-------------------------------|-------------------------------------------------------------|
+//! | [`Warning::DuplicateKey`]     | [`parse`](crate::parse) and [`Parser`](crate::Parser)       |
+//! | [`Warning::PrecisionLoss`]    | [`to_value`](crate::to_value) with integers beyond the i64 range, and protobuf conversion of Integer, BigInt or Decimal128 to a number |
+//! | [`Warning::UnknownField`]     | [`Schema::validate`](crate::schema::Schema::validate) on members a schema with properties does not list |
+//!
+//! Warnings are collected on the current thread, so work moved to another
+//! thread or task inside the closure is not covered. Outside [`collect`]
+//! they cost nothing and are dropped.
+
+#[cfg(any(feature = "protobuf", test))]
+use crate::types::{BigInt, Decimal128};
+use std::cell::RefCell;
+use std::fmt;
+
+/// Significant digits an f64 always round-trips
+#[cfg(any(feature = "protobuf", test))]
+const FLOAT_DIGITS: usize = 15;
+
+/// A non-fatal issue
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A key repeated in one object; the last value was kept
+    DuplicateKey {
+        /// The repeated key
+        key: String,
+        /// Byte offset of the later occurrence
+        position: usize,
+    },
+    /// A number converted to a type that cannot hold it exactly
+    PrecisionLoss {
+        /// The number before conversion, in kJSON form
+        value: String,
+        /// What it was converted to
+        target: &'static str,
+    },
+    /// An object member that a schema does not describe
+    UnknownField {
+        /// JSON Pointer to the member
+        path: String,
+    },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::DuplicateKey { key, position } => write!(
+                f,
+                "duplicate key `{}` at byte {}; the last value was kept",
+                key, position
+            ),
+            Warning::PrecisionLoss { value, target } => {
+                write!(f, "{} lost precision converting to {}", value, target)
+            }
+            Warning::UnknownField { path } => write!(f, "unknown field {}", path),
+        }
+    }
+}
+
+thread_local! {
+    /// Warnings of each active `collect`, innermost last
+    static SCOPES: RefCell<Vec<Vec<Warning>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run `f`, returning its result and the warnings raised while it ran.
+///
+/// Calls may nest; each warning goes to the innermost one.
+pub fn collect<R>(f: impl FnOnce() -> R) -> (R, Vec<Warning>) {
+    /// Ends the scope even if `f` panics
+    struct Scope;
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            SCOPES.with(|scopes| scopes.borrow_mut().pop());
+        }
+    }
+
+    SCOPES.with(|scopes| scopes.borrow_mut().push(Vec::new()));
+    let scope = Scope;
+    let result = f();
+    let warnings = SCOPES.with(|scopes| {
+        scopes
+            .borrow_mut()
+            .last_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    });
+    drop(scope);
+    (result, warnings)
+}
+
+/// Raise a warning, built only if something is collecting
+pub(crate) fn warn(warning: impl FnOnce() -> Warning) {
+    SCOPES.with(|scopes| {
+        if let Some(warnings) = scopes.borrow_mut().last_mut() {
+            warnings.push(warning());
+        }
+    });
+}
+
+/// `n` as the nearest f64, warning if that is not exact
+#[cfg(any(feature = "protobuf", test))]
+pub(crate) fn integer_to_f64(n: i64) -> f64 {
+    let f = n as f64;
+    if crate::value::f64_to_i64(f) != Some(n) {
+        warn(|| Warning::PrecisionLoss {
+            value: n.to_string(),
+            target: "f64",
+        });
+    }
+    f
+}
+
+/// `n` as the nearest f64, warning if that is not exact
+#[cfg(any(feature = "protobuf", test))]
+pub(crate) fn unsigned_to_f64(n: u64) -> f64 {
+    let f = n as f64;
+    if crate::value::f64_to_u64(f) != Some(n) {
+        warn(|| Warning::PrecisionLoss {
+            value: n.to_string(),
+            target: "f64",
+        });
+    }
+    f
+}
+
+/// `b` as the nearest f64, warning if that is not exact
+#[cfg(any(feature = "protobuf", test))]
+pub(crate) fn bigint_to_f64(b: &BigInt) -> f64 {
+    let f = b.to_f64();
+    if format!("{:.0}", f) != b.to_string() {
+        warn(|| Warning::PrecisionLoss {
+            value: b.to_kjson_string(),
+            target: "f64",
+        });
+    }
+    f
+}
+
+/// `d` as the nearest f64, warning if it has more significant digits than
+/// an f64 keeps
+#[cfg(any(feature = "protobuf", test))]
+pub(crate) fn decimal_to_f64(d: &Decimal128) -> f64 {
+    let text = d.to_kjson_string();
+    let significant = text
+        .trim_end_matches('m')
+        .replace(['-', '.'], "")
+        .trim_matches('0')
+        .len();
+    if significant > FLOAT_DIGITS {
+        warn(|| Warning::PrecisionLoss {
+            value: text,
+            target: "f64",
+        });
+    }
+    d.to_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, to_value};
+
+    #[test]
+    fn test_collect() {
+        let ((), outer) = collect(|| {
+            warn(|| Warning::UnknownField {
+                path: "/a".to_string(),
+            });
+            let ((), inner) = collect(|| {
+                warn(|| Warning::UnknownField {
+                    path: "/b".to_string(),
+                })
+            });
+            assert_eq!(inner.len(), 1);
+        });
+        assert_eq!(
+            outer,
+            [Warning::UnknownField {
+                path: "/a".to_string()
+            }]
+        );
+
+        // Nothing is kept outside a scope, or after one panics
+        warn(|| unreachable!());
+        let panicked = std::panic::catch_unwind(|| collect(|| panic!("boom")));
+        assert!(panicked.is_err());
+        warn(|| unreachable!());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let (value, warnings) =
+            collect(|| parse("[{ a: 1, b: 2, 'a': 3 }, { a: { a: 1, a: 2 } }]"));
+        assert_eq!(
+            value.unwrap(),
+            parse("[{ b: 2, a: 3 }, { a: { a: 2 } }]").unwrap()
+        );
+        let positions: Vec<_> = warnings
+            .iter()
+            .map(|w| match w {
+                Warning::DuplicateKey { position, .. } => *position,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(positions, [15, 38]);
+        assert_eq!(
+            warnings[0].to_string(),
+            "duplicate key `a` at byte 15; the last value was kept"
+        );
+    }
+
+    #[test]
+    fn test_precision_loss() {
+        let (value, warnings) = collect(|| to_value([u64::MAX, (1 << 60) + 1, 7]));
+        assert_eq!(value.unwrap()[0], crate::Value::UInteger(u64::MAX));
+        assert!(warnings.is_empty());
+
+        let (_, warnings) = collect(|| {
+            integer_to_f64(1 << 60);
+            integer_to_f64((1 << 60) + 1);
+            bigint_to_f64(&BigInt::from_i128(1 << 60));
+            decimal_to_f64(&Decimal128::from_str("-0.000123456789012345").unwrap());
+            decimal_to_f64(&Decimal128::from_str("1234567890.1234567").unwrap());
+            unsigned_to_f64(1 << 63);
+            unsigned_to_f64(u64::MAX);
+        });
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(
+            warnings[0].to_string(),
+            "1152921504606846977 lost precision converting to f64"
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            "1234567890.1234567m lost precision converting to f64"
+        );
+        assert_eq!(
+            warnings[2].to_string(),
+            "18446744073709551615 lost precision converting to f64"
+        );
+    }
+}
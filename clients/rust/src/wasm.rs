@@ -0,0 +1,283 @@
+//! WebAssembly bindings
+//!
+//! Exports `parse`, `stringify` and `stringifyPretty` to JavaScript through
+//! wasm-bindgen, converting values as follows:
+//!
+//! | kJSON | JavaScript |
+//! |-------|------------|
+//! | null | `null` (and `undefined` on input) |
+//! | Boolean, String | `boolean`, `string` |
+//! | Number, Integer, UInteger | `number` |
+//! | Array, Object | `Array`, plain object |
+//! | BigInt | `bigint` |
+//! | Decimal128, UUID, Duration | exported `Decimal128`, `Uuid`, `Duration` classes |
+//! | Instant, Date | `Date` (millisecond precision) |
+//! | Binary | `Uint8Array` |
+//!
+//! On input, whole numbers become Integers, and any object with a
+//! `toKjson()` method is converted by parsing the literal it returns, so
+//! JavaScript classes can opt in to extended types.
+//!
+//! The exports end up in any `cdylib` that depends on kjson with the `wasm`
+//! feature, so a one-line wrapper crate built with `wasm-pack` is enough.
+
+use crate::error::{Error, Result};
+use crate::parser::parse;
+use crate::serializer::{to_string, to_string_pretty};
+use crate::types::{BigInt, Decimal128, Duration, Instant};
+use crate::value::{f64_to_i64, into_str, Map, Value};
+use js_sys::{Array, Function, Object, Reflect, Uint8Array};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Method that extended-type objects use to expose their kJSON literal
+const TO_KJSON: &str = "toKjson";
+
+/// Parse kJSON text into a JavaScript value
+#[wasm_bindgen(js_name = parse)]
+pub fn parse_js(input: &str) -> std::result::Result<JsValue, JsError> {
+    Ok(to_js(&parse(input)?))
+}
+
+/// Serialize a JavaScript value as compact kJSON
+#[wasm_bindgen(js_name = stringify)]
+pub fn stringify_js(value: JsValue) -> std::result::Result<String, JsError> {
+    Ok(to_string(&from_js(&value)?)?)
+}
+
+/// Serialize a JavaScript value as indented kJSON
+#[wasm_bindgen(js_name = stringifyPretty)]
+pub fn stringify_pretty_js(value: JsValue) -> std::result::Result<String, JsError> {
+    Ok(to_string_pretty(&from_js(&value)?)?)
+}
+
+/// A decimal with up to 34 significant digits
+#[wasm_bindgen(js_name = Decimal128)]
+pub struct JsDecimal128(Decimal128);
+
+#[wasm_bindgen(js_class = Decimal128)]
+impl JsDecimal128 {
+    /// Parse a decimal such as `"19.99"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: &str) -> std::result::Result<JsDecimal128, JsError> {
+        Ok(JsDecimal128(Decimal128::from_str(value)?))
+    }
+
+    /// Decimal string without the `m` suffix
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// kJSON literal, e.g. `19.99m`
+    #[wasm_bindgen(js_name = toKjson)]
+    pub fn to_kjson(&self) -> String {
+        self.0.to_kjson_string()
+    }
+}
+
+/// A UUID
+#[wasm_bindgen(js_name = Uuid)]
+pub struct JsUuid(Uuid);
+
+#[wasm_bindgen(js_class = Uuid)]
+impl JsUuid {
+    /// Parse a hyphenated UUID
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: &str) -> std::result::Result<JsUuid, JsError> {
+        Uuid::parse_str(value)
+            .map(JsUuid)
+            .map_err(|e| Error::InvalidUuid(e.to_string()).into())
+    }
+
+    /// Random (version 4) UUID
+    pub fn v4() -> JsUuid {
+        JsUuid(crate::types::uuid_v4())
+    }
+
+    /// Time-ordered (version 7) UUID
+    pub fn v7() -> JsUuid {
+        JsUuid(crate::types::uuid_v7())
+    }
+
+    /// Hyphenated lowercase form
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// kJSON literal (the hyphenated form)
+    #[wasm_bindgen(js_name = toKjson)]
+    pub fn to_kjson(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// An ISO 8601 duration with nanosecond precision
+#[wasm_bindgen(js_name = Duration)]
+pub struct JsDuration(Duration);
+
+#[wasm_bindgen(js_class = Duration)]
+impl JsDuration {
+    /// Parse an ISO 8601 duration such as `"PT1H30M"`
+    #[wasm_bindgen(constructor)]
+    pub fn new(value: &str) -> std::result::Result<JsDuration, JsError> {
+        Ok(JsDuration(Duration::from_iso8601(value)?))
+    }
+
+    /// Total length in milliseconds
+    #[wasm_bindgen(js_name = totalMillis)]
+    pub fn total_millis(&self) -> f64 {
+        self.0.total_nanos() as f64 / 1e6
+    }
+
+    /// ISO 8601 form
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_iso8601()
+    }
+
+    /// kJSON literal (the ISO 8601 form)
+    #[wasm_bindgen(js_name = toKjson)]
+    pub fn to_kjson(&self) -> String {
+        self.0.to_iso8601()
+    }
+}
+
+/// Convert a value to its JavaScript representation
+pub fn to_js(value: &Value) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => JsValue::from_f64(*n),
+        Value::Integer(n) => JsValue::from_f64(*n as f64),
+        Value::UInteger(n) => JsValue::from_f64(*n as f64),
+        Value::String(s) => JsValue::from_str(s),
+        Value::Array(arr) => arr.iter().map(to_js).collect::<Array>().into(),
+        Value::Object(obj) => {
+            let object = Object::new();
+            for (k, v) in obj {
+                Reflect::set(&object, &JsValue::from_str(k), &to_js(v))
+                    .expect("setting a property on a plain object cannot fail");
+            }
+            object.into()
+        }
+        Value::BigInt(b) => js_sys::BigInt::new(&JsValue::from_str(&b.to_string()))
+            .expect("a BigInt's decimal form is a valid JS bigint")
+            .into(),
+        Value::Decimal128(d) => JsDecimal128(d.clone()).into(),
+        Value::Uuid(u) => JsUuid(*u).into(),
+        Value::Duration(d) => JsDuration(d.clone()).into(),
+        Value::Instant(i) => js_date(i.epoch_nanos() as f64 / 1e6),
+        Value::Date(d) => js_date(d.utc.timestamp_millis() as f64),
+        Value::Binary(bytes) => Uint8Array::from(bytes.as_slice()).into(),
+    }
+}
+
+/// Convert a JavaScript value to a value
+pub fn from_js(value: &JsValue) -> Result<Value> {
+    if value.is_null() || value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    if let Some(b) = value.as_bool() {
+        return Ok(Value::Bool(b));
+    }
+    if let Some(n) = value.as_f64() {
+        // Whole numbers become integers, as when parsing their text; -0 keeps its sign
+        return Ok(match f64_to_i64(n) {
+            Some(i) if n.is_sign_positive() || i != 0 => Value::Integer(i),
+            _ => Value::Number(n),
+        });
+    }
+    if let Some(s) = value.as_string() {
+        return Ok(Value::string(s));
+    }
+    if value.is_bigint() {
+        let digits = value
+            .dyn_ref::<js_sys::BigInt>()
+            .and_then(|b| b.to_string(10).ok())
+            .and_then(|s| s.as_string())
+            .ok_or_else(|| Error::Conversion("unreadable bigint".to_string()))?;
+        return Ok(Value::BigInt(BigInt::from_str(&digits)?));
+    }
+    if let Some(arr) = value.dyn_ref::<Array>() {
+        return arr
+            .iter()
+            .map(|v| from_js(&v))
+            .collect::<Result<_>>()
+            .map(Value::Array);
+    }
+    if let Some(bytes) = value.dyn_ref::<Uint8Array>() {
+        return Ok(Value::Binary(bytes.to_vec()));
+    }
+    if let Some(date) = value.dyn_ref::<js_sys::Date>() {
+        let millis = date.get_time();
+        if millis.is_nan() {
+            return Err(Error::InvalidInstant("Invalid Date".to_string()));
+        }
+        return Ok(Value::Instant(Instant::from_nanos((millis * 1e6) as i64)));
+    }
+    if let Some(literal) = kjson_literal(value)? {
+        return parse(&literal);
+    }
+    if let Some(object) = value.dyn_ref::<Object>() {
+        let mut map = Map::new();
+        for entry in Object::entries(object).iter() {
+            let entry: Array = entry.unchecked_into();
+            let key = entry.get(0).as_string().unwrap_or_default();
+            map.insert(into_str(key), from_js(&entry.get(1))?);
+        }
+        return Ok(Value::Object(map));
+    }
+    Err(Error::Conversion(format!(
+        "unsupported JavaScript value {:?}",
+        value
+    )))
+}
+
+/// Literal returned by the value's `toKjson()` method, if it has one
+fn kjson_literal(value: &JsValue) -> Result<Option<String>> {
+    let method = Reflect::get(value, &JsValue::from_str(TO_KJSON)).unwrap_or(JsValue::UNDEFINED);
+    let Some(method) = method.dyn_ref::<Function>() else {
+        return Ok(None);
+    };
+    method
+        .call0(value)
+        .ok()
+        .and_then(|literal| literal.as_string())
+        .map(Some)
+        .ok_or_else(|| Error::Conversion(format!("{}() must return a string", TO_KJSON)))
+}
+
+fn js_date(millis: f64) -> JsValue {
+    js_sys::Date::new(&JsValue::from_f64(millis)).into()
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn test_roundtrip() {
+        let value = parse(
+            "{ id: 550e8400-e29b-41d4-a716-446655440000, total: 123456789012345678901234567890n, price: 19.99m, ttl: PT1H, tags: ['a', null] }",
+        )
+        .unwrap();
+        let js = to_js(&value);
+        assert!(Reflect::get(&js, &"total".into()).unwrap().is_bigint());
+        assert_eq!(from_js(&js).unwrap(), value);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_dates() {
+        let value = parse("2025-01-10T12:00:00.123Z").unwrap();
+        let js = to_js(&value);
+        assert!(js.is_instance_of::<js_sys::Date>());
+        assert_eq!(
+            from_js(&js).unwrap(),
+            Value::Instant(Instant::from_nanos(1_736_510_400_123_000_000))
+        );
+    }
+}
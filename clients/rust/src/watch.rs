@@ -0,0 +1,213 @@
+//! Reload configuration files when they change
+//!
+//! A [`ConfigWatcher`] loads a [`ConfigLoader`] once, then watches its files
+//! and reloads on every change. Each reload is validated (see
+//! [`ConfigLoader::schema`]) and deserialized; if either fails the previous
+//! value stays current and the error is reported instead:
+//!
+//! ```no_run
+//! use kjson::config::ConfigLoader;
+//! use kjson::watch::{ConfigWatcher, Update};
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Settings {
+//!     workers: u16,
+//! }
+//!
+//! let loader = ConfigLoader::new().file("config/app.kjson");
+//! let (watcher, updates) = ConfigWatcher::<Settings>::channel(loader)?;
+//! println!("starting with {} workers", watcher.current().workers);
+//!
+//! for update in updates {
+//!     match update {
+//!         Update::Reloaded(settings) => println!("now {} workers", settings.workers),
+//!         Update::Rejected(e) => eprintln!("keeping the old configuration: {}", e),
+//!     }
+//! }
+//! # Ok::<(), kjson::Error>(())
+//! ```
+//!
+//! The directory of each file is watched rather than the file itself, so
+//! editors that save by replacing the file are picked up. Changes that leave
+//! the merged configuration as it was are not reported.
+
+use crate::config::{deserialize, ConfigLoader};
+use crate::error::{Error, Result};
+use crate::value::Value;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::DeserializeOwned;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+
+/// Outcome of a reload
+#[derive(Debug)]
+pub enum Update<T> {
+    /// The files changed and the new configuration is now current
+    Reloaded(Arc<T>),
+    /// The files changed but could not be loaded; the old value is kept
+    Rejected(Error),
+}
+
+/// Keeps a configuration up to date with its files
+///
+/// Watching stops when the watcher is dropped.
+pub struct ConfigWatcher<T> {
+    current: Arc<Mutex<Arc<T>>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl<T> ConfigWatcher<T>
+where
+    T: DeserializeOwned + Send + Sync + 'static,
+{
+    /// Load the configuration and call `on_update` after every change
+    pub fn new<F>(loader: ConfigLoader, mut on_update: F) -> Result<Self>
+    where
+        F: FnMut(Update<T>) + Send + 'static,
+    {
+        let value = loader.load_value()?;
+        let current = Arc::new(Mutex::new(Arc::new(deserialize::<T>(value.clone())?)));
+
+        let files = watched_files(&loader)?;
+        let dirs: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect();
+
+        let shared = Arc::clone(&current);
+        let mut last = value;
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => return on_update(Update::Rejected(Error::Config(e.to_string()))),
+                };
+                if matches!(event.kind, EventKind::Access(_))
+                    || !event.paths.iter().any(|path| files.contains(path))
+                {
+                    return;
+                }
+                match reload::<T>(&loader, &last) {
+                    Ok(None) => {}
+                    Ok(Some((value, config))) => {
+                        last = value;
+                        let config = Arc::new(config);
+                        *shared.lock().unwrap() = Arc::clone(&config);
+                        on_update(Update::Reloaded(config));
+                    }
+                    Err(e) => on_update(Update::Rejected(e)),
+                }
+            })
+            .map_err(|e| Error::Config(e.to_string()))?;
+        for dir in &dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .map_err(|e| Error::Config(format!("{}: {}", dir.display(), e)))?;
+        }
+
+        Ok(ConfigWatcher {
+            current,
+            _watcher: watcher,
+        })
+    }
+
+    /// Load the configuration and deliver updates on a channel
+    pub fn channel(loader: ConfigLoader) -> Result<(Self, Receiver<Update<T>>)> {
+        let (tx, rx) = mpsc::channel();
+        let watcher = Self::new(loader, move |update| {
+            let _ = tx.send(update);
+        })?;
+        Ok((watcher, rx))
+    }
+
+    /// The most recent configuration that loaded successfully
+    pub fn current(&self) -> Arc<T> {
+        Arc::clone(&self.current.lock().unwrap())
+    }
+}
+
+/// Absolute paths of the loader's files, as watch events report them
+fn watched_files(loader: &ConfigLoader) -> Result<HashSet<PathBuf>> {
+    loader
+        .files()
+        .map(|file| {
+            let dir = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let name = file
+                .file_name()
+                .ok_or_else(|| Error::Config(format!("{}: not a file", file.display())))?;
+            let dir = dir
+                .canonicalize()
+                .map_err(|e| Error::Config(format!("{}: {}", dir.display(), e)))?;
+            Ok(dir.join(name))
+        })
+        .collect()
+}
+
+/// Load again, or `None` if nothing changed
+fn reload<T: DeserializeOwned>(loader: &ConfigLoader, last: &Value) -> Result<Option<(Value, T)>> {
+    let value = loader.load_value()?;
+    if value == *last {
+        return Ok(None);
+    }
+    let config = deserialize(value.clone())?;
+    Ok(Some((value, config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Schema, Type};
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        workers: u16,
+    }
+
+    /// Replace the file atomically, the way editors save
+    fn save(file: &Path, text: &str) {
+        let tmp = file.with_extension("tmp");
+        std::fs::write(&tmp, text).unwrap();
+        std::fs::rename(tmp, file).unwrap();
+    }
+
+    fn next(updates: &Receiver<Update<Settings>>) -> Update<Settings> {
+        updates.recv_timeout(Duration::from_secs(10)).unwrap()
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("kjson-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("app.kjson");
+        std::fs::write(&file, "{ workers: 4 }").unwrap();
+
+        let loader = ConfigLoader::new()
+            .file(&file)
+            .schema(Schema::object().field("workers", Type::Number));
+        let (watcher, updates) = ConfigWatcher::<Settings>::channel(loader).unwrap();
+        assert_eq!(watcher.current().workers, 4);
+
+        save(&file, "{ workers: 8 }");
+        match next(&updates) {
+            Update::Reloaded(settings) => assert_eq!(settings.workers, 8),
+            Update::Rejected(e) => panic!("rejected: {}", e),
+        }
+
+        save(&file, "{ workers: 'many' }");
+        match next(&updates) {
+            Update::Rejected(Error::Config(m)) => assert!(m.contains("/workers"), "{}", m),
+            other => panic!("unexpected update {:?}", other),
+        }
+        assert_eq!(watcher.current().workers, 8);
+
+        drop(watcher);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+//! axum extractor and response type
+//!
+//! [`Kjson<T>`] mirrors `axum::Json`: as an extractor it deserializes an
+//! `application/kjson` request body, and as a response it serializes `T`
+//! with that content type.
+//!
+//! ```no_run
+//! use axum::{routing::post, Router};
+//! use kjson::web::Kjson;
+//!
+//! #[derive(serde::Deserialize, serde::Serialize)]
+//! struct Order {
+//!     id: String,
+//! }
+//!
+//! async fn create(Kjson(order): Kjson<Order>) -> Kjson<Order> {
+//!     Kjson(order)
+//! }
+//!
+//! let app: Router = Router::new().route("/orders", post(create));
+//! ```
+//!
+//! Rejected bodies get a kJSON error body. A syntax error (400) names the
+//! position, line and column where parsing failed:
+//!
+//! ```text
+//! {error: 'syntax', message: 'Unexpected character', position: 9, line: 1, column: 10}
+//! ```
+//!
+//! A body that parses but does not fit `T` is rejected with 422, and a
+//! request without the kJSON content type with 415.
+
+use crate::error::Error;
+use crate::media_type;
+use crate::parser::parse;
+use crate::serializer::to_string;
+use crate::span::SourceMap;
+use crate::value::{from_value, to_value, Map, Value};
+use axum::body::Bytes;
+use axum::extract::rejection::BytesRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::header::{self, HeaderMap, HeaderValue};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+pub use crate::media_type::CONTENT_TYPE;
+
+/// kJSON extractor and response
+#[derive(Debug, Clone, Copy, Default)]
+#[must_use]
+pub struct Kjson<T>(pub T);
+
+impl<T> Deref for Kjson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Kjson<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Kjson<T> {
+    fn from(inner: T) -> Self {
+        Kjson(inner)
+    }
+}
+
+impl<T: DeserializeOwned> Kjson<T> {
+    /// Deserialize a request body
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, KjsonRejection> {
+        let text = std::str::from_utf8(bytes).map_err(|e| {
+            KjsonRejection::syntax(bytes, e.valid_up_to(), "Invalid UTF-8".to_string())
+        })?;
+        let value = parse(text).map_err(|e| match e {
+            Error::ParseError { position, message } => {
+                KjsonRejection::syntax(bytes, position, message)
+            }
+            other => KjsonRejection::syntax(bytes, 0, other.to_string()),
+        })?;
+        from_value(value)
+            .map(Kjson)
+            .map_err(|e| KjsonRejection::Data(e.to_string()))
+    }
+}
+
+impl<T, S> FromRequest<S> for Kjson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = KjsonRejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !kjson_content_type(req.headers()) {
+            return Err(KjsonRejection::MissingContentType);
+        }
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(KjsonRejection::Body)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl<T: Serialize> IntoResponse for Kjson<T> {
+    fn into_response(self) -> Response {
+        match to_value(&self.0).and_then(|value| to_string(&value)) {
+            Ok(body) => kjson_response(StatusCode::OK, body),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                )],
+                e.to_string(),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Whether the request declares a kJSON body
+fn kjson_content_type(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(media_type::is_kjson)
+}
+
+fn kjson_response(status: StatusCode, body: String) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE))],
+        body,
+    )
+        .into_response()
+}
+
+/// Rejection used for [`Kjson`]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum KjsonRejection {
+    /// The `Content-Type` header is missing or not kJSON (415)
+    MissingContentType,
+    /// The body could not be read
+    Body(BytesRejection),
+    /// The body is not valid kJSON (400)
+    Syntax {
+        /// Error message
+        message: String,
+        /// Byte offset in the body
+        position: usize,
+        /// 1-based line
+        line: usize,
+        /// 1-based column, in characters
+        column: usize,
+    },
+    /// The body is valid kJSON but does not match the target type (422)
+    Data(String),
+}
+
+impl KjsonRejection {
+    /// Syntax error at a byte offset of the body
+    fn syntax(body: &[u8], position: usize, message: String) -> Self {
+        let location = SourceMap::new(&String::from_utf8_lossy(body)).location(position);
+        KjsonRejection::Syntax {
+            message,
+            position,
+            line: location.line,
+            column: location.column,
+        }
+    }
+
+    /// HTTP status of the rejection
+    pub fn status(&self) -> StatusCode {
+        match self {
+            KjsonRejection::MissingContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            KjsonRejection::Body(rejection) => rejection.status(),
+            KjsonRejection::Syntax { .. } => StatusCode::BAD_REQUEST,
+            KjsonRejection::Data(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// kJSON error body
+    fn body(&self) -> Value {
+        let mut body = Map::new();
+        let mut set = |key: &str, value: Value| {
+            body.insert(key, value);
+        };
+        match self {
+            KjsonRejection::Syntax {
+                message,
+                position,
+                line,
+                column,
+            } => {
+                set("error", Value::string("syntax"));
+                set("message", Value::string(message.clone()));
+                set("position", Value::Integer(*position as i64));
+                set("line", Value::Integer(*line as i64));
+                set("column", Value::Integer(*column as i64));
+            }
+            KjsonRejection::Data(message) => {
+                set("error", Value::string("data"));
+                set("message", Value::string(message.clone()));
+            }
+            _ => {
+                set("error", Value::string("request"));
+                set("message", Value::string(self.to_string()));
+            }
+        }
+        Value::Object(body)
+    }
+}
+
+impl fmt::Display for KjsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KjsonRejection::MissingContentType => {
+                write!(f, "Expected request with `Content-Type: {}`", CONTENT_TYPE)
+            }
+            KjsonRejection::Body(rejection) => write!(f, "{}", rejection.body_text()),
+            KjsonRejection::Syntax {
+                message,
+                line,
+                column,
+                ..
+            } => write!(
+                f,
+                "Failed to parse the request body as kJSON at line {}, column {}: {}",
+                line, column, message
+            ),
+            KjsonRejection::Data(message) => write!(
+                f,
+                "Failed to deserialize the kJSON body into the target type: {}",
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KjsonRejection {}
+
+impl IntoResponse for KjsonRejection {
+    fn into_response(self) -> Response {
+        match self {
+            KjsonRejection::Body(rejection) => rejection.into_response(),
+            KjsonRejection::MissingContentType => (self.status(), self.to_string()).into_response(),
+            _ => {
+                let body = to_string(&self.body()).unwrap_or_else(|_| self.to_string());
+                kjson_response(self.status(), body)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: String,
+        qty: f64,
+    }
+
+    fn request(content_type: Option<&str>, body: &'static str) -> Request {
+        let mut builder = Request::builder().method("POST").uri("/");
+        if let Some(content_type) = content_type {
+            builder = builder.header(header::CONTENT_TYPE, content_type);
+        }
+        builder.body(Body::from(body)).unwrap()
+    }
+
+    async fn extract(content_type: Option<&str>, body: &'static str) -> Result<Order, Response> {
+        Kjson::<Order>::from_request(request(content_type, body), &())
+            .await
+            .map(|Kjson(order)| order)
+            .map_err(IntoResponse::into_response)
+    }
+
+    async fn body_value(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        parse(std::str::from_utf8(&bytes).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_extract() {
+        let order = extract(
+            Some("application/kjson; charset=utf-8"),
+            "{ id: 'a', qty: 2 } // trailing comment",
+        )
+        .await
+        .unwrap();
+        assert_eq!(order.qty, 2.0);
+        assert!(
+            extract(Some("application/vnd.orders+kjson"), "{ id: 'a', qty: 1 }")
+                .await
+                .is_ok()
+        );
+
+        let missing = extract(None, "{}").await.unwrap_err();
+        assert_eq!(missing.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let json = extract(Some("application/json"), "{}").await.unwrap_err();
+        assert_eq!(json.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_rejection_bodies() {
+        let syntax = extract(Some(CONTENT_TYPE), "{\n  id: 'a',\n  qty: ]\n}")
+            .await
+            .unwrap_err();
+        assert_eq!(syntax.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(syntax.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = body_value(syntax).await;
+        let body = body.as_object().unwrap();
+        assert_eq!(body["error"], Value::String("syntax".into()));
+        assert_eq!(body["position"], Value::Number(20.0));
+        assert_eq!(body["line"], Value::Number(3.0));
+        assert_eq!(body["column"], Value::Number(8.0));
+
+        let data = extract(Some(CONTENT_TYPE), "{ id: 'a' }")
+            .await
+            .unwrap_err();
+        assert_eq!(data.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = body_value(data).await;
+        assert_eq!(
+            body.as_object().unwrap()["error"],
+            Value::String("data".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response() {
+        let response = Kjson(Order {
+            id: "a".to_string(),
+            qty: 2.0,
+        })
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        assert_eq!(
+            body_value(response).await,
+            parse("{ id: 'a', qty: 2 }").unwrap()
+        );
+    }
+}
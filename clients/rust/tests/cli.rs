@@ -0,0 +1,234 @@
+//! Tests of the `kjson` binary
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn kjson(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kjson"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+/// Fresh scratch directory for one test
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("kjson-cli-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_fmt_stdin() {
+    let output = kjson(&["fmt", "--compact"], "{ b: [1,2], a: 10n } // note");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        kjson::parse(stdout(&output)).unwrap(),
+        kjson::parse("{ a: 10n, b: [1, 2] }").unwrap()
+    );
+    assert_eq!(stdout(&output).lines().count(), 1);
+
+    let output = kjson(&["fmt"], "{ a: [1, 2] }");
+    assert!(stdout(&output).lines().count() > 1);
+}
+
+#[test]
+fn test_fmt_color() {
+    let output = kjson(&["fmt", "--compact", "--color"], "{ a: 'x' }");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\x1b[34ma\x1b[0m: \x1b[32m'x'\x1b[0m}\n"
+    );
+}
+
+#[test]
+fn test_fmt_write_and_check() {
+    let dir = scratch("fmt");
+    let file = dir.join("a.kjson");
+    std::fs::write(&file, "{a:1,b:[true]}").unwrap();
+    let path = file.to_str().unwrap();
+
+    let output = kjson(&["fmt", "--check", path], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output).trim(), path);
+
+    let output = kjson(&["fmt", "--write", path], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(kjson(&["fmt", "--check", path], "").status.code(), Some(0));
+
+    let output = kjson(&["fmt", "--write"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_validate() {
+    let dir = scratch("validate");
+    let schema = dir.join("schema.kjson");
+    std::fs::write(
+        &schema,
+        "{ type: 'object', required: ['id'], properties: { id: { type: 'uuid' } } }",
+    )
+    .unwrap();
+    let schema = schema.to_str().unwrap();
+
+    let output = kjson(&["validate"], "{\n  a: 1,\n  b: ]\n}");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr(&output).starts_with("<stdin>:3:6: "),
+        "{}",
+        stderr(&output)
+    );
+
+    let output = kjson(&["validate", "-s", schema], "{ id: 'x' }");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("/id"), "{}", stdout(&output));
+
+    let output = kjson(
+        &["validate", "-s", schema],
+        "{ id: 550e8400-e29b-41d4-a716-446655440000 }",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "<stdin>: ok\n");
+
+    let missing = dir.join("missing.kjson");
+    let output = kjson(&["validate", missing.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_get() {
+    let doc = "{ orders: [{ id: 1n, total: 9.5m }, { id: 2n }] }";
+    let output = kjson(&["get", "/orders/0/total"], doc);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "9.5m\n");
+
+    let output = kjson(&["get", "$.orders[*].id", "-"], doc);
+    assert_eq!(stdout(&output), "1n\n2n\n");
+
+    assert_eq!(kjson(&["get", "/missing"], doc).status.code(), Some(1));
+    assert_eq!(kjson(&["get", "$orders"], doc).status.code(), Some(2));
+}
+
+#[test]
+fn test_convert() {
+    let doc = "{ id: 10n, tags: ['a'] }";
+    let output = kjson(&["convert", "--to", "json"], doc);
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_str(stdout(&output)).unwrap();
+    assert_eq!(json, serde_json::json!({ "id": "10n", "tags": ["a"] }));
+
+    let output = kjson(&["convert", "--to", "yaml"], doc);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("- a\n"), "{}", stdout(&output));
+}
+
+#[test]
+fn test_convert_kjsonb() {
+    let doc = "{ id: 10n, price: 19.99m, ref: 550e8400-e29b-41d4-a716-446655440000 }";
+    let value = kjson::parse(doc).unwrap();
+    let output = kjson(&["convert", "--to", "kjsonb"], doc);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(output.stdout, kjson::binary::to_kjsonb(&value));
+
+    let dir = scratch("kjsonb");
+    let file = dir.join("doc.kjsonb");
+    let output = kjson(&["convert", "--to", "kjsonb", "-o", file.to_str().unwrap()], doc);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    let bytes = std::fs::read(&file).unwrap();
+    assert_eq!(kjson::binary::from_kjsonb(&bytes).unwrap(), value);
+
+    let output = kjson(&["convert", "--stream", "--to", "kjsonb"], doc);
+    assert_eq!(output.status.code(), Some(2));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_diff() {
+    let dir = scratch("diff");
+    let old = dir.join("old.kjson");
+    let new = dir.join("new.kjson");
+    std::fs::write(&old, "{ port: 80, tags: ['a'] }").unwrap();
+    std::fs::write(&new, "{ port: 8080, tags: ['a', 'b'] }").unwrap();
+    let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+
+    let output = kjson(&["diff", old, new], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "~ /port: 80 -> 8080\n+ /tags/1: 'b'\n");
+
+    let output = kjson(&["diff", "--patch", old, new], "");
+    assert_eq!(
+        kjson::parse(stdout(&output)).unwrap(),
+        kjson::parse(
+            "[{ op: 'replace', path: '/port', value: 8080 }, { op: 'add', path: '/tags/1', value: 'b' }]"
+        )
+        .unwrap()
+    );
+
+    let output = kjson(&["diff", "--style", "side-by-side", old, new], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "/port   | 80 | 8080\n/tags/1 |    | 'b'\n");
+
+    assert_eq!(kjson(&["diff", old, old], "").status.code(), Some(0));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_stream() {
+    let lines = "{ id: 1n, user: 'a' }\n{ id: 2n }\n{ id: }\n{ id: 3n, user: 'c' }\n";
+    let output = kjson(&["get", "--stream", "$.user"], lines);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "'a'\n'c'\n");
+    assert!(
+        stderr(&output).starts_with("<stdin>: byte "),
+        "{}",
+        stderr(&output)
+    );
+
+    let array = "[\n  { b: 1, a: [2] }, // first\n  'x',\n]";
+    let output = kjson(&["fmt", "--stream"], array);
+    assert_eq!(output.status.code(), Some(0));
+    let object = if cfg!(feature = "preserve_order") {
+        "{b: 1, a: [2]}"
+    } else {
+        "{a: [2], b: 1}"
+    };
+    assert_eq!(stdout(&output), format!("[\n  {},\n  'x'\n]\n", object));
+
+    let output = kjson(&["convert", "--stream", "--to", "json"], "1n\n[true]\n");
+    assert_eq!(stdout(&output), "\"1n\"\n[true]\n");
+    let output = kjson(
+        &["convert", "--stream", "--to", "yaml"],
+        "{ a: 1 }\n{ a: 2 }",
+    );
+    assert_eq!(stdout(&output), "---\na: 1\n---\na: 2\n");
+
+    assert_eq!(kjson(&["fmt", "--stream"], "").status.code(), Some(0));
+    assert_eq!(
+        kjson(&["fmt", "--stream", "--write"], "").status.code(),
+        Some(2)
+    );
+}
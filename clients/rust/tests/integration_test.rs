@@ -1,7 +1,7 @@
 use kjson::*;
-use std::collections::HashMap;
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_basic_types() {
     // Test null
     let null_str = "null";
@@ -113,8 +113,8 @@ fn test_complex_object() {
             match obj.get("tags") {
                 Some(Value::Array(arr)) => {
                     assert_eq!(arr.len(), 2);
-                    assert_eq!(arr[0], Value::String("new".to_string()));
-                    assert_eq!(arr[1], Value::String("sale".to_string()));
+                    assert_eq!(arr[0], Value::String("new".into()));
+                    assert_eq!(arr[1], Value::String("sale".into()));
                 }
                 _ => panic!("Expected tags array"),
             }
@@ -134,9 +134,9 @@ fn test_complex_object() {
 fn test_json5_features() {
     // Test unquoted keys
     let obj1 = parse("{name: \"test\", value: 42}").unwrap();
-    match obj1 {
+    match &obj1 {
         Value::Object(map) => {
-            assert_eq!(map.get("name"), Some(&Value::String("test".to_string())));
+            assert_eq!(map.get("name"), Some(&Value::String("test".into())));
             assert_eq!(map.get("value"), Some(&Value::Number(42.0)));
         }
         _ => panic!("Expected object"),
@@ -144,7 +144,7 @@ fn test_json5_features() {
 
     // Test trailing commas
     let arr = parse("[1, 2, 3,]").unwrap();
-    match arr {
+    match &arr {
         Value::Array(vec) => {
             assert_eq!(vec.len(), 3);
         }
@@ -160,9 +160,9 @@ fn test_json5_features() {
     }"#;
     
     let parsed = parse(with_comments).unwrap();
-    match parsed {
+    match &parsed {
         Value::Object(map) => {
-            assert_eq!(map.get("name"), Some(&Value::String("test".to_string())));
+            assert_eq!(map.get("name"), Some(&Value::String("test".into())));
             assert_eq!(map.get("value"), Some(&Value::Number(42.0)));
         }
         _ => panic!("Expected object"),
@@ -185,8 +185,8 @@ fn test_uuid_generation() {
 
 #[test]
 fn test_pretty_print() {
-    let mut obj = HashMap::new();
-    obj.insert("name".to_string(), Value::String("test".to_string()));
+    let mut obj = Map::new();
+    obj.insert("name".to_string(), Value::String("test".into()));
     obj.insert("values".to_string(), Value::Array(vec![
         Value::Number(1.0),
         Value::Number(2.0),
@@ -223,11 +223,11 @@ fn test_error_handling() {
 fn test_edge_cases() {
     // Empty array and object
     assert_eq!(parse("[]").unwrap(), Value::Array(vec![]));
-    assert_eq!(parse("{}").unwrap(), Value::Object(HashMap::new()));
+    assert_eq!(parse("{}").unwrap(), Value::Object(Map::new()));
     
     // Nested structures
     let nested = parse(r#"{"a": {"b": {"c": [1, 2, 3]}}}"#).unwrap();
-    match nested {
+    match &nested {
         Value::Object(obj) => {
             match obj.get("a") {
                 Some(Value::Object(inner)) => {
@@ -246,7 +246,7 @@ fn test_edge_cases() {
     
     // Large numbers
     let large = parse("999999999999999999999999999999999999999n").unwrap();
-    match large {
+    match &large {
         Value::BigInt(b) => {
             assert_eq!(b.to_string(), "999999999999999999999999999999999999999");
         }
@@ -259,7 +259,7 @@ fn test_unicode_handling() {
     // Unicode in strings
     let unicode_str = r#""Hello World""#;
     let parsed = parse(unicode_str).unwrap();
-    match parsed {
+    match &parsed {
         Value::String(s) => {
             assert_eq!(s, "Hello World");
         }
@@ -272,7 +272,7 @@ fn test_unicode_handling() {
     // Unicode escapes
     let escaped = r#""\u0048\u0065\u006c\u006c\u006f""#;
     let parsed = parse(escaped).unwrap();
-    match parsed {
+    match &parsed {
         Value::String(s) => {
             assert_eq!(s, "Hello");
         }
@@ -284,7 +284,7 @@ fn test_unicode_handling() {
 fn test_negative_numbers() {
     // Negative BigInt
     let neg_bigint = parse("-123456789012345678n").unwrap();
-    match neg_bigint {
+    match &neg_bigint {
         Value::BigInt(b) => {
             assert_eq!(b.to_string(), "-123456789012345678");
         }
@@ -293,7 +293,7 @@ fn test_negative_numbers() {
     
     // Negative Decimal128
     let neg_decimal = parse("-99.99m").unwrap();
-    match neg_decimal {
+    match &neg_decimal {
         Value::Decimal128(d) => {
             assert_eq!(d.to_string(), "-99.99");
         }
@@ -340,7 +340,7 @@ fn test_backtick_strings() {
     // Test simple backtick string
     let input = "`hello world`";
     let parsed = parse(input).unwrap();
-    match parsed {
+    match &parsed {
         Value::String(s) => assert_eq!(s, "hello world"),
         _ => panic!("Expected string"),
     }
@@ -348,7 +348,7 @@ fn test_backtick_strings() {
     // Test backtick string with quotes inside
     let input = r#"`He said "hello" and 'hi'`"#;
     let parsed = parse(input).unwrap();
-    match parsed {
+    match &parsed {
         Value::String(s) => assert_eq!(s, r#"He said "hello" and 'hi'"#),
         _ => panic!("Expected string"),
     }
@@ -356,7 +356,7 @@ fn test_backtick_strings() {
     // Test escaped backticks
     let input = r#"`This has a \` backtick`"#;
     let parsed = parse(input).unwrap();
-    match parsed {
+    match &parsed {
         Value::String(s) => assert_eq!(s, "This has a ` backtick"),
         _ => panic!("Expected string"),
     }
@@ -374,12 +374,12 @@ fn test_mixed_quote_types() {
     
     let parsed = parse(input).unwrap();
     
-    match parsed {
+    match &parsed {
         Value::Object(obj) => {
-            assert_eq!(obj.get("single").unwrap(), &Value::String("value1".to_string()));
-            assert_eq!(obj.get("double").unwrap(), &Value::String("value2".to_string()));
-            assert_eq!(obj.get("backtick").unwrap(), &Value::String("value3".to_string()));
-            assert_eq!(obj.get("mixed").unwrap(), &Value::String(r#"He said "hello" and 'hi'"#.to_string()));
+            assert_eq!(obj.get("single").unwrap(), &Value::String("value1".into()));
+            assert_eq!(obj.get("double").unwrap(), &Value::String("value2".into()));
+            assert_eq!(obj.get("backtick").unwrap(), &Value::String("value3".into()));
+            assert_eq!(obj.get("mixed").unwrap(), &Value::String(r#"He said "hello" and 'hi'"#.into()));
         }
         _ => panic!("Expected object"),
     }
@@ -387,11 +387,11 @@ fn test_mixed_quote_types() {
 
 #[test]
 fn test_smart_quote_serialization_roundtrip() {
-    let mut obj = HashMap::new();
-    obj.insert("simple".to_string(), Value::String("hello".to_string()));
-    obj.insert("with_single".to_string(), Value::String("it's nice".to_string()));
-    obj.insert("with_double".to_string(), Value::String(r#"He said "hi""#.to_string()));
-    obj.insert("with_both".to_string(), Value::String(r#"He said "hello" and 'hi'"#.to_string()));
+    let mut obj = Map::new();
+    obj.insert("simple".to_string(), Value::String("hello".into()));
+    obj.insert("with_single".to_string(), Value::String("it's nice".into()));
+    obj.insert("with_double".to_string(), Value::String(r#"He said "hi""#.into()));
+    obj.insert("with_both".to_string(), Value::String(r#"He said "hello" and 'hi'"#.into()));
     
     let value = Value::Object(obj);
     
@@ -403,4 +403,26 @@ fn test_smart_quote_serialization_roundtrip() {
     
     // Should be equal
     assert_eq!(value, parsed);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_kjsonb_vectors() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../testdata/binary/vectors.kjson");
+    let vectors = parse_file(path).unwrap();
+    for vector in vectors.as_array().unwrap() {
+        let vector = vector.as_object().unwrap();
+        let name = vector["name"].as_str().unwrap();
+        let value = parse(vector["kjson"].as_str().unwrap()).unwrap();
+        let bytes: Vec<u8> = vector["hex"]
+            .as_str()
+            .unwrap()
+            .split_whitespace()
+            .map(|b| u8::from_str_radix(b, 16).unwrap())
+            .collect();
+
+        assert_eq!(binary::to_kjsonb(&value), bytes, "encoding {}", name);
+        let decoded = binary::from_kjsonb(&bytes).unwrap();
+        assert_eq!(decoded, value, "decoding {}", name);
+        assert_eq!(to_string(&decoded).unwrap(), to_string(&value).unwrap(), "{}", name);
+    }
+}
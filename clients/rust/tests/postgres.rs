@@ -0,0 +1,123 @@
+//! Round trips against a PostgreSQL server with the kjson extension.
+//!
+//! These need a running server, so they are ignored by default:
+//!
+//! ```sh
+//! docker compose -f ../postgres/docker-compose.yml up -d postgres-kjson
+//! cargo test --features postgres --test postgres -- --ignored
+//! ```
+//!
+//! Set `KJSON_DATABASE_URL` to use a different server.
+#![cfg(feature = "postgres")]
+
+use bytes::BytesMut;
+use kjson::{parse, Value};
+use std::error::Error;
+use tokio_postgres::types::{to_sql_checked, IsNull, ToSql, Type};
+use tokio_postgres::{Client, NoTls};
+
+const DEFAULT_URL: &str = "host=localhost port=5433 user=postgres password=postgres";
+
+async fn connect() -> Client {
+    let url = std::env::var("KJSON_DATABASE_URL").unwrap_or_else(|_| DEFAULT_URL.to_string());
+    let (client, connection) = tokio_postgres::connect(&url, NoTls).await.unwrap();
+    tokio::spawn(connection);
+    client
+        .batch_execute(
+            "CREATE EXTENSION IF NOT EXISTS kjson;
+             CREATE TEMP TABLE docs (id serial PRIMARY KEY, doc kjson)",
+        )
+        .await
+        .unwrap();
+    client
+}
+
+fn sample() -> Value {
+    parse(
+        "{
+            id: 550e8400-e29b-41d4-a716-446655440000,
+            total: -123456789012345678901234567890n,
+            price: 19.99m,
+            scale: 0.000000000000000000015m,
+            ttl: -PT1H30M0.5S,
+            at: 2025-01-10T12:00:00Z,
+            local: 2025-01-10T12:00:00+05:30,
+            tags: ['a', null, true, 42.5],
+            nested: { empty: [], deep: { x: {} } }
+        }",
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+#[ignore = "needs the kjson PostgreSQL container"]
+async fn test_binary_roundtrip() {
+    let client = connect().await;
+    let value = sample();
+
+    client
+        .execute("INSERT INTO docs (doc) VALUES ($1)", &[&value])
+        .await
+        .unwrap();
+    let row = client.query_one("SELECT doc FROM docs", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Value>(0), value);
+}
+
+#[tokio::test]
+#[ignore = "needs the kjson PostgreSQL container"]
+async fn test_binary_matches_text() {
+    let client = connect().await;
+    let value = sample();
+
+    // Stored through the binary path, read back through the text output function
+    let row = client
+        .query_one("SELECT $1::kjson::text", &[&value])
+        .await
+        .unwrap();
+    assert_eq!(parse(row.get::<_, &str>(0)).unwrap(), value);
+
+    // Stored through the text input function, read back through the binary path
+    let text = kjson::to_string(&value).unwrap();
+    let row = client
+        .query_one("SELECT $1::text::kjson", &[&text])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Value>(0), value);
+}
+
+/// Raw `kjson` wire payload, version byte included
+#[derive(Debug)]
+struct Raw(Vec<u8>);
+
+impl ToSql for Raw {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.extend_from_slice(&self.0);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "kjson"
+    }
+
+    to_sql_checked!();
+}
+
+#[tokio::test]
+#[ignore = "needs the kjson PostgreSQL container"]
+async fn test_rejects_corrupt_binary() {
+    let client = connect().await;
+    let insert = "INSERT INTO docs (doc) VALUES ($1)";
+
+    // Version 2 array of two elements with only one present
+    let truncated = Raw(vec![kjson::postgres::WIRE_INTERNAL, 0x09, 2, 0x00]);
+    assert!(client.execute(insert, &[&truncated]).await.is_err());
+
+    let unknown = Raw(vec![9, b'1']);
+    assert!(client.execute(insert, &[&unknown]).await.is_err());
+
+    let mut text = vec![kjson::postgres::WIRE_TEXT];
+    text.extend_from_slice(b"[1, 2n]");
+    client.execute(insert, &[&Raw(text)]).await.unwrap();
+    let row = client.query_one("SELECT doc FROM docs", &[]).await.unwrap();
+    assert_eq!(row.get::<_, Value>(0), parse("[1, 2n]").unwrap());
+}